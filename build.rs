@@ -18,6 +18,22 @@ fn has_bytes() -> Result<(), Box<dyn Error>> {
     println!("Out {:}", out_dir);
     let mut prost_build = prost_build::Config::new();
     prost_build.protoc_arg("--experimental_allow_proto3_optional");
+    prost_build.type_attribute(
+        "za.co.agriio.job.StoreEvent.event",
+        "#[allow(clippy::large_enum_variant)]",
+    );
+    prost_build.type_attribute(
+        "za.co.agriio.job.JobState",
+        "#[cfg_attr(feature = \"graphql\", derive(async_graphql::Enum))]",
+    );
+    prost_build.type_attribute(
+        "za.co.agriio.job.JobType",
+        "#[cfg_attr(feature = \"graphql\", derive(async_graphql::Enum))]",
+    );
+    prost_build.type_attribute(
+        ".",
+        "#[cfg_attr(feature = \"serde\", derive(serde::Serialize, serde::Deserialize))]",
+    );
     prost_build.compile_protos(&["./proto/job.proto"], &["./proto/"])?;
 
     let src = Path::new(&out_dir).join("za.co.agriio.job.rs");
@@ -0,0 +1,35 @@
+//! Composes two arbitrary schedules. The union of their upcoming times, merged in order, and
+//! their intersection, the times of one the other includes, must agree with `includes`.
+#![no_main]
+
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use libfuzzer_sys::fuzz_target;
+use std::str::FromStr;
+
+/// The latest time the cron crate iterates up to, 2100-01-01
+const MAX_TIMESTAMP: i64 = 4_102_444_800;
+
+fuzz_target!(|input: (&str, &str, i64)| {
+    let (a, b, timestamp) = input;
+    let (a, b) = match (Schedule::from_str(a), Schedule::from_str(b)) {
+        (Ok(a), Ok(b)) => (a, b),
+        _ => return,
+    };
+    let after = match DateTime::<Utc>::from_timestamp(timestamp.rem_euclid(MAX_TIMESTAMP), 0) {
+        Some(after) => after,
+        None => return,
+    };
+
+    let mut union: Vec<_> = a.after(&after).take(8).chain(b.after(&after).take(8)).collect();
+    union.sort();
+    union.dedup();
+    for time in union.iter() {
+        assert!(*time > after);
+        assert!(a.includes(*time) || b.includes(*time));
+    }
+
+    for time in a.after(&after).take(8).filter(|time| b.includes(*time)) {
+        assert!(a.includes(time) && b.includes(time));
+    }
+});
@@ -0,0 +1,57 @@
+//! Iterates an arbitrary schedule forward and backward from an arbitrary time. The time before
+//! the next one is at or before the start, and the next tick of a job on the schedule, whatever
+//! its leap day policy, comes after the start, also from within a leap second.
+#![no_main]
+
+use chrono::{DateTime, Timelike, Utc};
+use cron::Schedule;
+use libfuzzer_sys::fuzz_target;
+use std::str::FromStr;
+use tokio_cron_scheduler::{Job, LeapDayPolicy};
+
+/// The latest time the cron crate iterates up to, 2100-01-01
+const MAX_TIMESTAMP: i64 = 4_102_444_800;
+
+fuzz_target!(|input: (&str, i64, bool)| {
+    let (expression, timestamp, leap_second) = input;
+    let schedule = match Schedule::from_str(expression) {
+        Ok(schedule) => schedule,
+        Err(_) => return,
+    };
+    let start = match DateTime::<Utc>::from_timestamp(timestamp.rem_euclid(MAX_TIMESTAMP), 0) {
+        Some(start) => start,
+        None => return,
+    };
+
+    let next = schedule.after(&start).next();
+    if let Some(next) = next {
+        assert!(next > start);
+        if let Some(previous) = schedule.after(&next).next_back() {
+            assert!(previous < next);
+            assert!(previous <= start);
+        }
+    }
+
+    let after = if leap_second {
+        start.with_nanosecond(1_000_000_000).unwrap_or(start)
+    } else {
+        start
+    };
+    let policies = [
+        LeapDayPolicy::LeapYearsOnly,
+        LeapDayPolicy::LastDayOfFebruary,
+        LeapDayPolicy::FirstOfMarch,
+    ];
+    for policy in policies {
+        let mut job = match Job::new(schedule.clone(), |_uuid, _l| {}) {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        job.set_leap_day_policy(policy).expect("set the leap day policy");
+        let data = job.job_data().expect("job data");
+        if let Some(tick) = data.next_cron_tick(&after) {
+            assert!(tick > start);
+            assert!(tick.nanosecond() < 1_000_000_000);
+        }
+    }
+});
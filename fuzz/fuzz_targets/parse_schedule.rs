@@ -0,0 +1,26 @@
+//! Parses arbitrary cron expressions, as given by users, in every day-of-week numbering. Parsing
+//! may fail but must never panic, and a parsed schedule must parse the same again.
+#![no_main]
+
+use chrono::Utc;
+use cron::Schedule;
+use libfuzzer_sys::fuzz_target;
+use std::str::FromStr;
+use tokio_cron_scheduler::DayOfWeekNumbering;
+
+fuzz_target!(|expression: &str| {
+    let numberings = [
+        DayOfWeekNumbering::Quartz,
+        DayOfWeekNumbering::Unix,
+        DayOfWeekNumbering::Iso,
+    ];
+    for numbering in numberings {
+        let schedule = match numbering.parse(expression) {
+            Ok(schedule) => schedule,
+            Err(_) => continue,
+        };
+        let again = Schedule::from_str(&schedule.to_string()).expect("a parsed schedule parses");
+        assert!(schedule.timeunitspec_eq(&again));
+        for _ in schedule.upcoming(Utc).take(4) {}
+    }
+});
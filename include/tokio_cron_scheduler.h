@@ -0,0 +1,89 @@
+#ifndef TOKIO_CRON_SCHEDULER_H
+#define TOKIO_CRON_SCHEDULER_H
+
+/* Generated with cbindgen, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The call succeeded
+ */
+#define TCS_OK 0
+
+/**
+ * A pointer argument was null or a string was not valid UTF-8
+ */
+#define TCS_INVALID_ARGUMENT -1
+
+/**
+ * The scheduler returned an error
+ */
+#define TCS_SCHEDULER_ERROR -2
+
+/**
+ *
+ * A scheduler for synchronous code. It owns a thread running its own Tokio runtime, and every
+ * method blocks the calling thread until the scheduler is done with it. These methods must not
+ * be called from within an async context.
+ */
+typedef struct TcsScheduler TcsScheduler;
+
+/**
+ * Called with the id of the job and the user data it was added with
+ */
+typedef void (*TcsJobCallback)(const uint8_t *job_id, void *user_data);
+
+/**
+ *
+ * Create a scheduler. Returns null when the scheduler could not be created.
+ * The scheduler must be released with `tcs_scheduler_shutdown`.
+ */
+struct TcsScheduler *tcs_scheduler_new(void);
+
+/**
+ *
+ * Start running the jobs of the scheduler
+ *
+ * # Safety
+ * `scheduler` must be a pointer returned by `tcs_scheduler_new` that was not shut down.
+ */
+int tcs_scheduler_start(struct TcsScheduler *scheduler);
+
+/**
+ *
+ * Add a cron job calling `callback` with `user_data` on every run. The 16 bytes of the job id
+ * are written to `job_id_out`. The callback is called from the scheduler thread.
+ *
+ * # Safety
+ * `scheduler` must be a live scheduler, `schedule` a NUL terminated string and `job_id_out`
+ * must point to 16 writable bytes. `user_data` must stay valid until the job is removed.
+ */
+int tcs_scheduler_add_cron_job(struct TcsScheduler *scheduler,
+                               const char *schedule,
+                               TcsJobCallback callback,
+                               void *user_data,
+                               uint8_t *job_id_out);
+
+/**
+ *
+ * Remove a job from the scheduler
+ *
+ * # Safety
+ * `scheduler` must be a live scheduler and `job_id` must point to the 16 bytes of a job id.
+ */
+int tcs_scheduler_remove(struct TcsScheduler *scheduler, const uint8_t *job_id);
+
+/**
+ *
+ * Shut the scheduler down and release it. The pointer must not be used afterwards.
+ *
+ * # Safety
+ * `scheduler` must be a pointer returned by `tcs_scheduler_new` that was not shut down.
+ */
+int tcs_scheduler_shutdown(struct TcsScheduler *scheduler);
+
+#endif  /* TOKIO_CRON_SCHEDULER_H */
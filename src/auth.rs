@@ -0,0 +1,288 @@
+use crate::JobSchedulerError;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use subtle::{ConditionallySelectable, ConstantTimeEq};
+
+/// The clients a rate limiter tracks before it forgets those with a full bucket, or else the
+/// one seen the longest ago
+const MAX_CLIENTS: usize = 10_000;
+
+///
+/// What a caller of the control APIs may do. Each role may do all a lower one may.
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    /// Lists the jobs, runs and dead letters, and follows their state changes
+    Viewer,
+    /// Triggers and cancels runs, approves or rejects them, and handles the dead letters
+    Operator,
+    /// Edits the schedule, e.g. removes jobs
+    Admin,
+}
+
+impl Role {
+    pub fn allows(&self, required: Role) -> bool {
+        *self >= required
+    }
+}
+
+///
+/// Tells the role of the caller of a control API from the credentials of its request, like a
+/// bearer token or an API key, `None` when they are unknown. Implement it to validate JWTs or to
+/// ask an identity provider.
+pub trait Authenticator: Send + Sync {
+    fn authenticate(&self, credentials: &str)
+        -> Pin<Box<dyn Future<Output = Option<Role>> + Send>>;
+}
+
+///
+/// An authenticator with a fixed set of tokens, each with its role. Only the hashes of the
+/// tokens are kept, and the credentials are compared to all of them in constant time, so the
+/// time taken tells nothing about the tokens.
+#[derive(Clone, Debug, Default)]
+pub struct StaticTokens {
+    tokens: Vec<([u8; 32], Role)>,
+}
+
+impl StaticTokens {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn token(mut self, token: impl AsRef<str>, role: Role) -> Self {
+        let hash = StaticTokens::hash(token.as_ref());
+        self.tokens.retain(|(known, _)| *known != hash);
+        self.tokens.push((hash, role));
+        self
+    }
+
+    fn hash(token: &str) -> [u8; 32] {
+        Sha256::digest(token.as_bytes()).into()
+    }
+}
+
+impl Authenticator for StaticTokens {
+    fn authenticate(
+        &self,
+        credentials: &str,
+    ) -> Pin<Box<dyn Future<Output = Option<Role>> + Send>> {
+        let hash = StaticTokens::hash(credentials);
+        // Every token is compared, the one matching is picked without branching on it
+        let mut found = 0u8;
+        let mut role = 0u8;
+        for (known, known_role) in self.tokens.iter() {
+            let matches = known.ct_eq(&hash);
+            found.conditional_assign(&1, matches);
+            role.conditional_assign(&(*known_role as u8), matches);
+        }
+        let role = match (found, role) {
+            (0, _) => None,
+            (_, 0) => Some(Role::Viewer),
+            (_, 1) => Some(Role::Operator),
+            _ => Some(Role::Admin),
+        };
+        Box::pin(async move { role })
+    }
+}
+
+///
+/// An authenticator letting any caller do anything, whatever its credentials, for the control
+/// APIs served to trusted callers only, e.g. behind a proxy checking them. Without an
+/// authenticator the control APIs deny every caller.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AllowAnonymousAdmin;
+
+impl Authenticator for AllowAnonymousAdmin {
+    fn authenticate(
+        &self,
+        _credentials: &str,
+    ) -> Pin<Box<dyn Future<Output = Option<Role>> + Send>> {
+        Box::pin(async { Some(Role::Admin) })
+    }
+}
+
+///
+/// The role of the caller with `credentials` when it has at least the `required` one, for the
+/// handlers of the control APIs to check before acting
+pub async fn authorize(
+    authenticator: &dyn Authenticator,
+    credentials: &str,
+    required: Role,
+) -> Result<Role, JobSchedulerError> {
+    match authenticator.authenticate(credentials).await {
+        Some(role) if role.allows(required) => Ok(role),
+        _ => Err(JobSchedulerError::Forbidden(required)),
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    at: Instant,
+}
+
+///
+/// Limits the requests of each client of the control APIs, told apart by e.g. their credentials
+/// or address, to `per_second` on average, allowing bursts of up to `burst` requests. The clients
+/// are kept by the hash of what tells them apart, and at most `MAX_CLIENTS` of them, the client
+/// seen the longest ago being forgotten for a new one.
+#[derive(Clone)]
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    buckets: Arc<Mutex<HashMap<[u8; 32], Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_second: u32, burst: u32) -> Self {
+        Self {
+            per_second: per_second as f64,
+            burst: burst.max(1) as f64,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    ///
+    /// Count a request of `client`, failing with `RateLimited` when it is over its limit
+    pub fn check(&self, client: &str) -> Result<(), JobSchedulerError> {
+        let now = Instant::now();
+        let key: [u8; 32] = Sha256::digest(client.as_bytes()).into();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(&key) {
+            buckets.retain(|_, bucket| self.tokens(bucket, now) < self.burst);
+            if buckets.len() >= MAX_CLIENTS {
+                let stalest = buckets
+                    .iter()
+                    .min_by_key(|(_, bucket)| bucket.at)
+                    .map(|(key, _)| *key);
+                if let Some(stalest) = stalest {
+                    buckets.remove(&stalest);
+                }
+            }
+        }
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: self.burst,
+            at: now,
+        });
+        if self.refill(bucket, now) < 1.0 {
+            return Err(JobSchedulerError::RateLimited);
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) -> f64 {
+        bucket.tokens = self.tokens(bucket, now);
+        bucket.at = now;
+        bucket.tokens
+    }
+
+    /// The tokens of `bucket` at `now`, leaving when it was last used as it was
+    fn tokens(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.at).as_secs_f64();
+        (bucket.tokens + elapsed * self.per_second).min(self.burst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        authorize, AllowAnonymousAdmin, Authenticator, RateLimiter, Role, StaticTokens, MAX_CLIENTS,
+    };
+    use crate::JobSchedulerError;
+
+    fn tokens() -> StaticTokens {
+        StaticTokens::new()
+            .token("viewer-token", Role::Viewer)
+            .token("operator-token", Role::Operator)
+            .token("admin-token", Role::Admin)
+    }
+
+    #[tokio::test]
+    async fn static_tokens_roles() {
+        let tokens = tokens();
+        assert_eq!(
+            tokens.authenticate("viewer-token").await,
+            Some(Role::Viewer)
+        );
+        assert_eq!(
+            tokens.authenticate("operator-token").await,
+            Some(Role::Operator)
+        );
+        assert_eq!(tokens.authenticate("admin-token").await, Some(Role::Admin));
+        assert_eq!(tokens.authenticate("admin-token ").await, None);
+        assert_eq!(tokens.authenticate("").await, None);
+
+        let tokens = tokens.token("viewer-token", Role::Admin);
+        assert_eq!(tokens.authenticate("viewer-token").await, Some(Role::Admin));
+    }
+
+    #[tokio::test]
+    async fn authorize_roles() {
+        let tokens = tokens();
+        assert!(matches!(
+            authorize(&tokens, "operator-token", Role::Viewer).await,
+            Ok(Role::Operator)
+        ));
+        assert!(matches!(
+            authorize(&tokens, "operator-token", Role::Operator).await,
+            Ok(Role::Operator)
+        ));
+        assert!(matches!(
+            authorize(&tokens, "operator-token", Role::Admin).await,
+            Err(JobSchedulerError::Forbidden(Role::Admin))
+        ));
+        assert!(matches!(
+            authorize(&tokens, "unknown", Role::Viewer).await,
+            Err(JobSchedulerError::Forbidden(Role::Viewer))
+        ));
+        assert!(matches!(
+            authorize(&AllowAnonymousAdmin, "", Role::Admin).await,
+            Ok(Role::Admin)
+        ));
+    }
+
+    #[test]
+    fn rate_limits_each_client() {
+        let limiter = RateLimiter::new(10, 2);
+        assert!(limiter.check("a").is_ok());
+        assert!(limiter.check("a").is_ok());
+        assert!(matches!(
+            limiter.check("a"),
+            Err(JobSchedulerError::RateLimited)
+        ));
+        // Each client has its own limit, shared by the clones of a limiter
+        assert!(limiter.check("b").is_ok());
+        let shared = limiter.clone();
+        assert!(matches!(
+            shared.check("a"),
+            Err(JobSchedulerError::RateLimited)
+        ));
+
+        std::thread::sleep(std::time::Duration::from_millis(150));
+        assert!(limiter.check("a").is_ok());
+        assert!(matches!(
+            limiter.check("a"),
+            Err(JobSchedulerError::RateLimited)
+        ));
+    }
+
+    /// Clients cycling through credentials faster than their buckets refill don't grow the
+    /// limiter past its bound, the stalest being forgotten first
+    #[test]
+    fn bounded_clients() {
+        let limiter = RateLimiter::new(1, 2);
+        assert!(limiter.check("first").is_ok());
+        for i in 0..MAX_CLIENTS {
+            assert!(limiter.check(&i.to_string()).is_ok());
+        }
+        assert_eq!(limiter.buckets.lock().unwrap().len(), MAX_CLIENTS);
+        // The first client was forgotten, its next request starting a full bucket
+        assert!(limiter.check("first").is_ok());
+        assert!(limiter.check("first").is_ok());
+        assert_eq!(limiter.buckets.lock().unwrap().len(), MAX_CLIENTS);
+    }
+}
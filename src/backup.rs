@@ -0,0 +1,140 @@
+use crate::context::Context;
+#[cfg(not(feature = "has_bytes"))]
+pub use crate::job::job_data::Snapshot;
+#[cfg(feature = "has_bytes")]
+pub use crate::job::job_data_prost::Snapshot;
+use crate::JobSchedulerError;
+use chrono::{DateTime, TimeZone, Utc};
+#[cfg(feature = "has_bytes")]
+use prost::Message;
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use uuid::Uuid;
+
+///
+/// Where the snapshots taken by a backup job go, e.g. written to a file or an object store
+pub type OnBackup = dyn FnMut(Snapshot) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync;
+
+///
+/// What a restore does with a job of the snapshot that is also in the store
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RestoreConflict {
+    /// Keep the job in the store
+    Skip,
+    /// Replace the job in the store, along with its notifications
+    Overwrite,
+    /// Fail the restore with `RestoreConflict`, before anything is written
+    Fail,
+}
+
+///
+/// What was done by a restore
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RestoreReport {
+    pub restored: usize,
+    pub skipped: usize,
+}
+
+impl Snapshot {
+    ///
+    /// When the snapshot was taken
+    pub fn taken_at_utc(&self) -> Option<DateTime<Utc>> {
+        Utc.timestamp_opt(self.taken_at as i64, 0).single()
+    }
+
+    ///
+    /// The snapshot encoded with Protobuf, to be stored wherever the backups are kept
+    #[cfg(feature = "has_bytes")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode_to_vec()
+    }
+
+    ///
+    /// Decode a snapshot encoded by `to_bytes`
+    #[cfg(feature = "has_bytes")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, JobSchedulerError> {
+        Snapshot::decode(bytes).map_err(|_| JobSchedulerError::CantDecodeSnapshot)
+    }
+}
+
+pub(crate) async fn take_snapshot(context: &Context) -> Result<Snapshot, JobSchedulerError> {
+    let mut metadata = context.metadata_storage.write().await;
+    let mut notification_storage = context.notification_storage.write().await;
+    let mut jobs = vec![];
+    let mut notifications = vec![];
+    for job_id in metadata.list_job_ids().await? {
+        let job = match metadata.get(job_id).await? {
+            Some(job) => job,
+            None => continue,
+        };
+        jobs.push(job);
+        let notification_ids = notification_storage
+            .list_notification_guids_for_job_id(job_id)
+            .await?;
+        for notification_id in notification_ids {
+            if let Some(notification) = notification_storage.get(notification_id).await? {
+                notifications.push(notification);
+            }
+        }
+    }
+    Ok(Snapshot {
+        taken_at: Utc::now().timestamp() as u64,
+        jobs,
+        notifications,
+    })
+}
+
+pub(crate) async fn restore(
+    context: &Context,
+    snapshot: Snapshot,
+    conflict: RestoreConflict,
+) -> Result<RestoreReport, JobSchedulerError> {
+    if context.read_only {
+        return Err(JobSchedulerError::ReadOnly);
+    }
+    let mut metadata = context.metadata_storage.write().await;
+    let mut notification_storage = context.notification_storage.write().await;
+    let mut report = RestoreReport::default();
+
+    let mut in_store = HashSet::new();
+    for job in snapshot.jobs.iter() {
+        let job_id: Uuid = match job.id.as_ref() {
+            Some(id) => id.into(),
+            None => continue,
+        };
+        if metadata.get(job_id).await?.is_some() {
+            if conflict == RestoreConflict::Fail {
+                return Err(JobSchedulerError::RestoreConflict(job_id));
+            }
+            in_store.insert(job_id);
+        }
+    }
+
+    let mut restored = HashSet::new();
+    for job in snapshot.jobs {
+        let job_id: Uuid = match job.id.as_ref() {
+            Some(id) => id.into(),
+            None => continue,
+        };
+        if in_store.contains(&job_id) {
+            if conflict == RestoreConflict::Skip {
+                report.skipped += 1;
+                continue;
+            }
+            notification_storage.delete_for_job(job_id).await?;
+        }
+        metadata.add_or_update(job).await?;
+        restored.insert(job_id);
+        report.restored += 1;
+    }
+    for notification in snapshot.notifications {
+        let restore = notification
+            .job_id_and_notification_id_from_data()
+            .is_some_and(|(job_id, _)| restored.contains(&job_id));
+        if restore {
+            notification_storage.add_or_update(notification).await?;
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,201 @@
+use crate::job::JobLocked;
+use crate::job_scheduler::{JobSchedulerBuilder, JobsSchedulerLocked};
+use crate::reconciliation::ReconciliationReport;
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::thread::JoinHandle;
+use tokio::runtime::Handle;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+///
+/// A scheduler for synchronous code. It owns a thread running its own Tokio runtime, and every
+/// method blocks the calling thread until the scheduler is done with it. These methods must not
+/// be called from within an async context.
+pub struct BlockingScheduler {
+    scheduler: JobsSchedulerLocked,
+    handle: Handle,
+    stop_tx: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BlockingScheduler {
+    ///
+    /// Create a new `BlockingScheduler` using the default storage and code implementations
+    pub fn new() -> Result<Self, JobSchedulerError> {
+        Self::with_builder(JobSchedulerBuilder::default())
+    }
+
+    ///
+    /// Create a new `BlockingScheduler` from a `JobSchedulerBuilder`
+    pub fn with_builder(builder: JobSchedulerBuilder) -> Result<Self, JobSchedulerError> {
+        let (handle_tx, handle_rx) = std::sync::mpsc::channel();
+        let (stop_tx, stop_rx) = oneshot::channel::<()>();
+        let thread = std::thread::Builder::new()
+            .name("tokio-cron-scheduler".to_string())
+            .spawn(move || {
+                let runtime = match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        error!("Error creating scheduler runtime {:?}", e);
+                        return;
+                    }
+                };
+                if handle_tx.send(runtime.handle().clone()).is_err() {
+                    return;
+                }
+                runtime.block_on(async move {
+                    let _ = stop_rx.await;
+                });
+            })
+            .map_err(|_| JobSchedulerError::BlockingRuntime)?;
+        let handle = handle_rx
+            .recv()
+            .map_err(|_| JobSchedulerError::BlockingRuntime)?;
+
+        let scheduler = handle
+            .block_on(handle.spawn(builder.build()))
+            .map_err(|_| JobSchedulerError::BlockingRuntime)??;
+
+        Ok(Self {
+            scheduler,
+            handle,
+            stop_tx: Some(stop_tx),
+            thread: Some(thread),
+        })
+    }
+
+    fn block_on<F, T>(&self, future: F) -> Result<T, JobSchedulerError>
+    where
+        F: Future<Output = Result<T, JobSchedulerError>> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.handle
+            .block_on(self.handle.spawn(future))
+            .map_err(|_| JobSchedulerError::BlockingRuntime)?
+    }
+
+    ///
+    /// Add a job, see `JobScheduler::add`
+    pub fn add(&self, job: JobLocked) -> Result<Uuid, JobSchedulerError> {
+        let scheduler = self.scheduler.clone();
+        self.block_on(async move { scheduler.add(job).await })
+    }
+
+    ///
+    /// Remove a job, see `JobScheduler::remove`
+    pub fn remove(&self, job_id: &Uuid) -> Result<(), JobSchedulerError> {
+        let scheduler = self.scheduler.clone();
+        let job_id = *job_id;
+        self.block_on(async move { scheduler.remove(&job_id).await })
+    }
+
+    ///
+    /// Run a job right away, see `JobScheduler::trigger`
+    pub fn trigger(&self, job_id: &Uuid) -> Result<(), JobSchedulerError> {
+        let scheduler = self.scheduler.clone();
+        let job_id = *job_id;
+        self.block_on(async move { scheduler.trigger(&job_id).await })
+    }
+
+    ///
+    /// Run a job right away as of `scheduled_time`, see `JobScheduler::trigger_as_of`
+    pub fn trigger_as_of(
+        &self,
+        job_id: &Uuid,
+        scheduled_time: DateTime<Utc>,
+    ) -> Result<(), JobSchedulerError> {
+        let scheduler = self.scheduler.clone();
+        let job_id = *job_id;
+        self.block_on(async move { scheduler.trigger_as_of(&job_id, scheduled_time).await })
+    }
+
+    ///
+    /// Start running the jobs, see `JobScheduler::start`
+    pub fn start(&self) -> Result<ReconciliationReport, JobSchedulerError> {
+        let scheduler = self.scheduler.clone();
+        self.block_on(async move { scheduler.start().await })
+    }
+
+    ///
+    /// Shut the scheduler down and stop its runtime thread
+    pub fn shutdown(mut self) -> Result<(), JobSchedulerError> {
+        self.stop()
+    }
+
+    ///
+    /// The async scheduler this wraps, for mixing with async code
+    pub fn scheduler(&self) -> JobsSchedulerLocked {
+        self.scheduler.clone()
+    }
+
+    fn stop(&mut self) -> Result<(), JobSchedulerError> {
+        let stop_tx = match self.stop_tx.take() {
+            Some(stop_tx) => stop_tx,
+            None => return Ok(()),
+        };
+        let mut scheduler = self.scheduler.clone();
+        let ret = self.block_on(async move { scheduler.shutdown().await });
+        let _ = stop_tx.send(());
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("Error joining scheduler runtime thread");
+            }
+        }
+        ret
+    }
+}
+
+impl Drop for BlockingScheduler {
+    fn drop(&mut self) {
+        if let Err(e) = self.stop() {
+            error!("Error shutting down blocking scheduler {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlockingScheduler;
+    use crate::Job;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// A synchronous job taking its time doesn't hold up the ticks of the other jobs
+    #[test]
+    fn slow_job_leaves_others_ticking() {
+        let sched = BlockingScheduler::new().expect("scheduler");
+        let slow = sched
+            .add(
+                Job::new("0 0 0 1 1 *", |_, _| {
+                    std::thread::sleep(Duration::from_secs(4))
+                })
+                .expect("job"),
+            )
+            .expect("add");
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let counted = ticks.clone();
+        sched
+            .add(
+                Job::new("* * * * * *", move |_, _| {
+                    counted.fetch_add(1, Ordering::SeqCst);
+                })
+                .expect("job"),
+            )
+            .expect("add");
+        sched.start().expect("start");
+        sched.trigger(&slow).expect("trigger");
+
+        std::thread::sleep(Duration::from_millis(3500));
+        assert!(
+            ticks.load(Ordering::SeqCst) >= 2,
+            "the other job was held up by the slow one"
+        );
+        sched.shutdown().expect("shutdown");
+    }
+}
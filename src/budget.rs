@@ -0,0 +1,73 @@
+use chrono::{DateTime, TimeZone, Utc};
+use std::time::Duration;
+use uuid::Uuid;
+
+///
+/// A cost budget per window of time, shared by all the jobs of a scheduler. The windows are
+/// aligned on the Unix epoch, so a budget per hour renews at the start of every hour in UTC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Budget {
+    pub amount: u64,
+    pub window: Duration,
+}
+
+impl Budget {
+    pub fn new(amount: u64, window: Duration) -> Self {
+        Self { amount, window }
+    }
+
+    ///
+    /// The index of the window the instant falls in
+    pub(crate) fn window_of(&self, instant: &DateTime<Utc>) -> i64 {
+        let window = (self.window.as_millis() as i64).max(1);
+        instant.timestamp_millis().div_euclid(window)
+    }
+
+    ///
+    /// The instant the window the instant falls in ends, and the budget renews
+    pub(crate) fn window_end(&self, instant: &DateTime<Utc>) -> DateTime<Utc> {
+        let window = (self.window.as_millis() as i64).max(1);
+        let end = (self.window_of(instant) + 1) * window;
+        Utc.timestamp_millis_opt(end).single().unwrap_or(*instant)
+    }
+}
+
+///
+/// What was spent of the budget in the current window
+#[derive(Default)]
+pub(crate) struct BudgetState {
+    window: i64,
+    spent: u64,
+}
+
+impl BudgetState {
+    ///
+    /// Charge the due runs, given with their cost and priority, to the budget, highest priority
+    /// first. Returns the runs to fire and the runs deferred to the next window. Runs that cost
+    /// nothing are always fired.
+    pub(crate) fn charge(
+        &mut self,
+        budget: &Budget,
+        now: &DateTime<Utc>,
+        mut runs: Vec<(Uuid, u32, i32)>,
+    ) -> (Vec<Uuid>, Vec<Uuid>) {
+        let window = budget.window_of(now);
+        if window != self.window {
+            self.window = window;
+            self.spent = 0;
+        }
+        runs.sort_by(|(_, _, a), (_, _, b)| b.cmp(a));
+        let mut fire = vec![];
+        let mut deferred = vec![];
+        for (job_id, cost, _) in runs {
+            let cost = cost as u64;
+            if cost == 0 || self.spent + cost <= budget.amount {
+                self.spent += cost;
+                fire.push(job_id);
+            } else {
+                deferred.push(job_id);
+            }
+        }
+        (fire, deferred)
+    }
+}
@@ -0,0 +1,314 @@
+use crate::job::{JobCreator, JobDeleter, JobLocked, JobToRunAsync};
+use crate::job_scheduler::JobsSchedulerLocked;
+use crate::{JobDiff, JobSchedulerError, JobStoredData};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+///
+/// The kind of change a `Changeset` makes to a job
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Add,
+    Update,
+    Remove,
+}
+
+///
+/// The effect a change will have on a job in the running scheduler
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangePreview {
+    pub job_id: Uuid,
+    pub kind: ChangeKind,
+    pub exists: bool,
+    pub current_schedule: Option<String>,
+    pub new_schedule: Option<String>,
+    pub current_next_tick: Option<DateTime<Utc>>,
+    pub new_next_tick: Option<DateTime<Utc>>,
+    /// What the change alters in a job that exists
+    pub diff: Option<JobDiff>,
+}
+
+enum Change {
+    Add(JobLocked),
+    Update(JobLocked),
+    Remove(Uuid),
+}
+
+type Snapshot = Option<(JobStoredData, Arc<RwLock<Box<JobToRunAsync>>>)>;
+
+///
+/// A set of job additions, updates and removals that are applied to a scheduler together.
+/// When applying fails halfway, the changes made so far are rolled back. An applied changeset
+/// can be rolled back later on as well. Notifications of removed jobs are not restored on rollback.
+#[derive(Default)]
+pub struct Changeset {
+    changes: Vec<Change>,
+    applied: Vec<(Uuid, Snapshot)>,
+}
+
+impl Changeset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Add a new job
+    pub fn add_job(mut self, job: JobLocked) -> Self {
+        self.changes.push(Change::Add(job));
+        self
+    }
+
+    ///
+    /// Replace the schedule and code of the job with the given id
+    pub fn update_job(
+        mut self,
+        job_id: Uuid,
+        mut job: JobLocked,
+    ) -> Result<Self, JobSchedulerError> {
+        let mut data = job.job_data()?;
+        data.id = Some(job_id.into());
+        job.set_job_data(data)?;
+        self.changes.push(Change::Update(job));
+        Ok(self)
+    }
+
+    ///
+    /// Remove the job with the given id
+    pub fn remove_job(mut self, job_id: Uuid) -> Self {
+        self.changes.push(Change::Remove(job_id));
+        self
+    }
+
+    ///
+    /// Whether the changeset was applied and not rolled back
+    pub fn applied(&self) -> bool {
+        !self.applied.is_empty()
+    }
+
+    ///
+    /// Describe what each change would do to the jobs in the scheduler, without changing anything
+    pub async fn preview(
+        &mut self,
+        scheduler: &JobsSchedulerLocked,
+    ) -> Result<Vec<ChangePreview>, JobSchedulerError> {
+        let storage = scheduler.context.metadata_storage.clone();
+        let mut storage = storage.write().await;
+        let mut ret = vec![];
+        for change in self.changes.iter_mut() {
+            let (kind, job_id, new_data) = match change {
+                Change::Add(job) => (ChangeKind::Add, job.guid(), Some(job.job_data()?)),
+                Change::Update(job) => (ChangeKind::Update, job.guid(), Some(job.job_data()?)),
+                Change::Remove(job_id) => (ChangeKind::Remove, *job_id, None),
+            };
+            let current = storage.get(job_id).await.ok().flatten();
+            let new_next_tick = match new_data.as_ref() {
+                Some(n) => {
+                    scheduler
+                        .context
+                        .reschedule_hooks
+                        .apply(n, n.next_tick_utc())
+                        .await
+                }
+                None => None,
+            };
+            ret.push(ChangePreview {
+                job_id,
+                kind,
+                exists: current.is_some(),
+                current_schedule: current
+                    .as_ref()
+                    .and_then(|c| c.schedule())
+                    .map(|s| s.to_string()),
+                new_schedule: new_data
+                    .as_ref()
+                    .and_then(|n| n.schedule())
+                    .map(|s| s.to_string()),
+                current_next_tick: current.as_ref().and_then(|c| c.next_tick_utc()),
+                new_next_tick,
+                diff: current
+                    .as_ref()
+                    .zip(new_data.as_ref())
+                    .map(|(current, new)| current.diff(new)),
+            });
+        }
+        Ok(ret)
+    }
+
+    ///
+    /// Apply all the changes. If one of them fails, the ones already made are rolled back.
+    pub async fn apply(
+        &mut self,
+        scheduler: &JobsSchedulerLocked,
+    ) -> Result<(), JobSchedulerError> {
+        if self.applied() {
+            return Err(JobSchedulerError::ChangesetAlreadyApplied);
+        }
+        let context = scheduler.context();
+        let changes = std::mem::take(&mut self.changes);
+        let mut result = Ok(());
+        for change in changes.iter() {
+            let job_id = match change {
+                Change::Add(job) | Change::Update(job) => job.guid(),
+                Change::Remove(job_id) => *job_id,
+            };
+            let snapshot = match Changeset::snapshot(scheduler, job_id).await {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            };
+            let done = match change {
+                Change::Add(job) | Change::Update(job) => {
+                    scheduler.add(job.clone()).await.map(|_| ())
+                }
+                Change::Remove(job_id) => JobDeleter::remove(&context, job_id).await,
+            };
+            if let Err(e) = done {
+                result = Err(e);
+                break;
+            }
+            if let (Change::Update(job), Some((current, _))) = (change, snapshot.as_ref()) {
+                if let Ok(new) = job.clone().job_data() {
+                    let diff = current.diff(&new);
+                    info!(
+                        job_id = job_id,
+                        changed = diff.changed,
+                        schedule = diff.schedule.as_ref().map(|s| &s.fields),
+                        current_next_tick = diff.current_next_tick,
+                        new_next_tick = diff.new_next_tick;
+                        "Updated job"
+                    );
+                }
+            }
+            self.applied.push((job_id, snapshot));
+        }
+        self.changes = changes;
+        if let Err(e) = result {
+            error!("Error applying changeset, rolling back {:?}", e);
+            self.rollback(scheduler).await?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    ///
+    /// Undo the applied changes, restoring the jobs as they were before applying
+    pub async fn rollback(
+        &mut self,
+        scheduler: &JobsSchedulerLocked,
+    ) -> Result<(), JobSchedulerError> {
+        let context = scheduler.context();
+        while let Some((job_id, snapshot)) = self.applied.pop() {
+            let undone = match snapshot {
+                Some((data, code)) => JobCreator::add_with_code(&context, data, code)
+                    .await
+                    .map(|_| ()),
+                None => JobDeleter::remove(&context, &job_id).await,
+            };
+            if let Err(e) = undone {
+                error!("Error rolling back change to {:?} {:?}", job_id, e);
+                return Err(JobSchedulerError::ChangesetRollback);
+            }
+        }
+        Ok(())
+    }
+
+    async fn snapshot(
+        scheduler: &JobsSchedulerLocked,
+        job_id: Uuid,
+    ) -> Result<Snapshot, JobSchedulerError> {
+        let data = {
+            let mut storage = scheduler.context.metadata_storage.write().await;
+            storage.get(job_id).await.ok().flatten()
+        };
+        let data = match data {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        let mut job_code = scheduler.context.job_code.write().await;
+        match job_code.get(job_id).await? {
+            Some(code) => Ok(Some((data, code))),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Changeset;
+    use crate::{Job, JobScheduler, JobSchedulerError};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// A job every second counting its runs with `runs`
+    fn counting(runs: &Arc<AtomicUsize>) -> Job {
+        let runs = runs.clone();
+        Job::new("* * * * * *", move |_, _| {
+            runs.fetch_add(1, Ordering::SeqCst);
+        })
+        .expect("job")
+    }
+
+    /// The runs counted by each counter over a couple of seconds
+    async fn runs_meanwhile(counters: &[&Arc<AtomicUsize>]) -> Vec<usize> {
+        let before: Vec<_> = counters.iter().map(|c| c.load(Ordering::SeqCst)).collect();
+        tokio::time::sleep(Duration::from_millis(2200)).await;
+        counters
+            .iter()
+            .zip(before)
+            .map(|(c, before)| c.load(Ordering::SeqCst) - before)
+            .collect()
+    }
+
+    /// The changes of an applied changeset take effect in the running scheduler together, and
+    /// rolling it back restores the jobs it changed, with their code
+    #[tokio::test]
+    async fn apply_and_rollback() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let (updated, updated_new, removed, added) = (
+            Arc::new(AtomicUsize::new(0)),
+            Arc::new(AtomicUsize::new(0)),
+            Arc::new(AtomicUsize::new(0)),
+            Arc::new(AtomicUsize::new(0)),
+        );
+        let updated_id = sched.add(counting(&updated)).await.expect("add");
+        let removed_id = sched.add(counting(&removed)).await.expect("add");
+        sched.start().await.expect("start");
+        let counters = [&updated, &updated_new, &removed, &added];
+        let runs = runs_meanwhile(&counters).await;
+        assert!(runs[0] > 0 && runs[2] > 0 && runs[1] == 0 && runs[3] == 0);
+
+        let mut changeset = Changeset::new()
+            .update_job(updated_id, counting(&updated_new))
+            .expect("update")
+            .remove_job(removed_id)
+            .add_job(counting(&added));
+        changeset.apply(&sched).await.expect("apply");
+        assert!(changeset.applied());
+        assert!(matches!(
+            changeset.apply(&sched).await,
+            Err(JobSchedulerError::ChangesetAlreadyApplied)
+        ));
+        // A run fired just before applying may still be ending
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let runs = runs_meanwhile(&counters).await;
+        assert_eq!(runs[0], 0, "the old code of the updated job still runs");
+        assert!(runs[1] > 0, "the new code of the updated job never ran");
+        assert_eq!(runs[2], 0, "the removed job still runs");
+        assert!(runs[3] > 0, "the added job never ran");
+
+        changeset.rollback(&sched).await.expect("rollback");
+        assert!(!changeset.applied());
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let runs = runs_meanwhile(&counters).await;
+        assert!(runs[0] > 0, "the old code of the updated job is not back");
+        assert_eq!(runs[1], 0);
+        assert!(runs[2] > 0, "the removed job is not back");
+        assert_eq!(runs[3], 0, "the added job is still there");
+        sched.shutdown().await.expect("shutdown");
+    }
+}
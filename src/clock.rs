@@ -0,0 +1,216 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::JobType;
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::JobType;
+use crate::store::MetaDataStorage;
+use crate::{JobAndNextTick, JobSchedulerError};
+use chrono::{DateTime, Utc};
+use std::time::Instant;
+use uuid::Uuid;
+
+/// How far the wall clock must move from the monotonic clock to count as stepped
+const STEP_THRESHOLD_MILLIS: i64 = 1_000;
+
+///
+/// The wall clock the scheduler goes by
+#[cfg(not(test))]
+pub(crate) fn now() -> DateTime<Utc> {
+    Utc::now()
+}
+
+///
+/// The wall clock the scheduler goes by, which the unit tests move along with the clock of the
+/// runtime, so a paused runtime stops it and advancing the runtime moves it as far
+#[cfg(test)]
+pub(crate) fn now() -> DateTime<Utc> {
+    use std::sync::OnceLock;
+
+    static STARTED: OnceLock<(DateTime<Utc>, Instant)> = OnceLock::new();
+    let (wall, started) = *STARTED.get_or_init(|| (Utc::now(), Instant::now()));
+    let at = instant();
+    match at.checked_duration_since(started) {
+        Some(elapsed) => wall + chrono::Duration::from_std(elapsed).unwrap_or_default(),
+        None => wall - chrono::Duration::from_std(started - at).unwrap_or_default(),
+    }
+}
+
+///
+/// The monotonic clock the scheduler goes by
+#[cfg(not(test))]
+pub(crate) fn instant() -> Instant {
+    Instant::now()
+}
+
+///
+/// The monotonic clock the scheduler goes by, the clock of the runtime in the unit tests
+#[cfg(test)]
+pub(crate) fn instant() -> Instant {
+    tokio::time::Instant::now().into_std()
+}
+
+///
+/// Watches the wall clock against the monotonic clock, to tell when the wall clock was stepped,
+/// e.g. by NTP or by hand, between two ticks of the scheduler
+pub(crate) struct ClockWatch {
+    wall: DateTime<Utc>,
+    instant: Instant,
+}
+
+impl ClockWatch {
+    pub(crate) fn new() -> Self {
+        Self::at(now(), instant())
+    }
+
+    pub(crate) fn at(wall: DateTime<Utc>, instant: Instant) -> Self {
+        Self { wall, instant }
+    }
+
+    ///
+    /// How many seconds the wall clock was stepped since the last look, forward when positive,
+    /// when it was stepped by more than a second
+    pub(crate) fn stepped(&mut self) -> Option<i64> {
+        self.stepped_at(now(), instant())
+    }
+
+    pub(crate) fn stepped_at(&mut self, wall: DateTime<Utc>, instant: Instant) -> Option<i64> {
+        let elapsed = instant.saturating_duration_since(self.instant).as_millis() as i64;
+        let step = (wall - self.wall).num_milliseconds() - elapsed;
+        self.wall = wall;
+        self.instant = instant;
+        if step.abs() < STEP_THRESHOLD_MILLIS {
+            return None;
+        }
+        Some((step as f64 / 1_000.0).round() as i64)
+    }
+}
+
+///
+/// Move the next and last ticks of the monotonic repeated jobs among `next_ticks` by `step`
+/// seconds, in the store and in `next_ticks`, so they come due after the same monotonic time
+/// as before the wall clock was stepped. Returns the ids of the jobs moved.
+pub(crate) async fn follow_step(
+    storage: &mut Box<dyn MetaDataStorage + Send + Sync>,
+    next_ticks: &mut [JobAndNextTick],
+    step: i64,
+) -> Result<Vec<Uuid>, JobSchedulerError> {
+    let moved = |tick: u64| JobAndNextTick::utc((tick as i64 + step).max(0) as u64);
+    let mut followed = vec![];
+    for n in next_ticks.iter_mut() {
+        if n.job_type != JobType::Repeated as i32 || n.next_tick == 0 {
+            continue;
+        }
+        let id: Uuid = match n.id.as_ref() {
+            Some(id) => id.into(),
+            None => continue,
+        };
+        match storage.get(id).await? {
+            Some(job) if job.monotonic() => {}
+            _ => continue,
+        }
+        let next_tick = moved(n.next_tick);
+        let last_tick = n.last_tick.map(moved);
+        storage
+            .set_next_and_last_tick(id, Some(next_tick), last_tick)
+            .await?;
+        n.next_tick = next_tick.timestamp() as u64;
+        n.last_tick = last_tick.map(|t| t.timestamp() as u64);
+        followed.push(id);
+    }
+    Ok(followed)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "has_bytes"))]
+    use crate::job::job_data::JobStoredData;
+    #[cfg(feature = "has_bytes")]
+    use crate::job::job_data_prost::JobStoredData;
+    use crate::store::MetaDataStorage;
+    use crate::SimpleMetadataStore;
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    /// The data of a job due at `next_tick` and last run a minute before, repeated every 30
+    /// seconds when `monotonic` is given and a cron job otherwise
+    fn repeated(monotonic: Option<bool>, next_tick: u64) -> JobStoredData {
+        let mut job = match monotonic {
+            Some(monotonic) => {
+                let mut job =
+                    crate::Job::new_repeated(std::time::Duration::from_secs(30), |_, _| {})
+                        .expect("job");
+                job.set_monotonic(monotonic).expect("monotonic");
+                job
+            }
+            None => crate::Job::new("0 * * * * *", |_, _| {}).expect("job"),
+        };
+        let mut data = job.job_data().expect("data");
+        data.next_tick = next_tick;
+        data.last_tick = Some(next_tick - 60);
+        data
+    }
+
+    /// Wall clock steps told apart from drift, and followed by the monotonic jobs only
+    #[tokio::test]
+    async fn monotonic_jobs() {
+        use super::{follow_step, ClockWatch};
+        use std::time::Instant;
+
+        let start = Instant::now();
+        let at = |millis: u64| start + std::time::Duration::from_millis(millis);
+        let wall = Utc::now();
+        let mut watch = ClockWatch::at(wall, start);
+        // Both clocks moving together, or apart by less than a second, is no step
+        assert_eq!(
+            watch.stepped_at(wall + Duration::milliseconds(500), at(500)),
+            None
+        );
+        assert_eq!(
+            watch.stepped_at(wall + Duration::milliseconds(1_200), at(1_000)),
+            None
+        );
+        // NTP stepping the wall clock an hour back, then two minutes forward
+        let stepped = |seconds: i64, millis: i64| {
+            wall + Duration::seconds(seconds) + Duration::milliseconds(millis)
+        };
+        assert_eq!(
+            watch.stepped_at(stepped(-3_600, 1_700), at(1_500)),
+            Some(-3_600)
+        );
+        assert_eq!(
+            watch.stepped_at(stepped(-3_480, 2_200), at(2_000)),
+            Some(120)
+        );
+
+        let mut store: Box<dyn MetaDataStorage + Send + Sync> =
+            Box::new(SimpleMetadataStore::default());
+        store.init().await.expect("init");
+        let next_tick = 1_600_000_120;
+        let jobs = [
+            repeated(Some(true), next_tick),
+            repeated(Some(false), next_tick),
+            repeated(None, next_tick),
+        ];
+        for job in jobs.iter() {
+            store.add_or_update(job.clone()).await.expect("add");
+        }
+        let mut next_ticks = store.list_next_ticks().await.expect("list");
+        let followed = follow_step(&mut store, &mut next_ticks, -3_600)
+            .await
+            .expect("follow step");
+        // Only the monotonic job moves, the others keep following the wall clock
+        let monotonic: Uuid = jobs[0].id.as_ref().unwrap().into();
+        assert_eq!(followed, vec![monotonic]);
+        for job in jobs.iter() {
+            let id: Uuid = job.id.as_ref().unwrap().into();
+            let moved = if id == monotonic { 3_600 } else { 0 };
+            let stored = store.get(id).await.expect("get").expect("job");
+            assert_eq!(stored.next_tick, next_tick - moved);
+            assert_eq!(stored.last_tick, job.last_tick.map(|t| t - moved));
+            let listed = next_ticks
+                .iter()
+                .find(|n| n.id.as_ref().map(Uuid::from) == Some(id))
+                .expect("listed");
+            assert_eq!(listed.next_tick, stored.next_tick);
+        }
+    }
+}
@@ -1,14 +1,34 @@
+use crate::day_of_week::DayOfWeekNumbering;
+use crate::dispatch::Dispatch;
+use crate::drift::DriftStats;
+use crate::internal_error::InternalErrors;
 #[cfg(not(feature = "has_bytes"))]
 use crate::job::job_data::{JobState, NotificationData};
 #[cfg(feature = "has_bytes")]
 use crate::job::job_data_prost::{JobState, NotificationData};
 use crate::job::to_code::{JobCode, NotificationCode};
-use crate::job::{JobToRunAsync, NotificationId};
-use crate::store::{MetaDataStorage, NotificationStore};
+use crate::job::{JobToRunAsync, NotificationId, RunErrors};
+use crate::job_scheduler::RunningExecution;
+use crate::leap;
+use crate::notification::EscalationPolicy;
+use crate::payload::PayloadGenerator;
+use crate::reconciliation::ReconciliationReport;
+use crate::redrive::DeadLetters;
+use crate::reschedule_hook::RescheduleHooks;
+use crate::retry::RetryAttempts;
+use crate::run_condition::RunConditions;
+use crate::run_scope::RunScopes;
+use crate::simple::SimpleRunHistoryStore;
+use crate::sla::CompletionTimes;
+use crate::store::{MetaDataStorage, NotificationStore, Outbox, RunHistoryStore};
 use crate::{JobSchedulerError, JobStoredData, OnJobNotification};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::broadcast::Sender;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock, Semaphore};
+use tokio::task::AbortHandle;
 use uuid::Uuid;
 
 pub type NotificationDeletedResult =
@@ -25,11 +45,67 @@ pub struct Context {
     pub notify_created_tx: Sender<Result<Uuid, (JobSchedulerError, Option<Uuid>)>>,
     pub notify_delete_tx: Sender<(Uuid, Option<Vec<JobState>>)>,
     pub notify_deleted_tx: Sender<NotificationDeletedResult>,
+    /// The due runs fired by the tick loop, in the order they were fired
+    pub dispatch_tx: Sender<Dispatch>,
+    /// The reports of what the scheduler found in its store when it started
+    pub reconciliation_tx: Sender<ReconciliationReport>,
     // TODO need to add when notification was deleted and there's no more references to it
     pub metadata_storage: Arc<RwLock<Box<dyn MetaDataStorage + Send + Sync>>>,
     pub notification_storage: Arc<RwLock<Box<dyn NotificationStore + Send + Sync>>>,
     pub job_code: Arc<RwLock<Box<dyn JobCode + Send + Sync>>>,
     pub notification_code: Arc<RwLock<Box<dyn NotificationCode + Send + Sync>>>,
+    pub pending_approvals: Arc<RwLock<HashMap<Uuid, DateTime<Utc>>>>,
+    pub reschedule_hooks: RescheduleHooks,
+    /// The predicates jobs have to meet to run
+    pub run_conditions: RunConditions,
+    /// The generators of the payloads of the runs of jobs
+    pub payload_generators: Arc<RwLock<HashMap<Uuid, Arc<PayloadGenerator>>>>,
+    /// The logical times of the last runs of jobs, the tick a scheduled run was due at or the
+    /// time a triggered run was run as of
+    pub scheduled_times: Arc<RwLock<HashMap<Uuid, DateTime<Utc>>>>,
+    /// The occurrences of jobs that missed their run or failed, to be re-driven
+    pub dead_letters: DeadLetters,
+    /// What the runs of jobs are wrapped in, like the task-local values they were added with
+    pub run_scopes: RunScopes,
+    /// Where the errors met while running jobs are reported, besides the log
+    pub internal_errors: InternalErrors,
+    /// Where the notifications of the outcomes of runs wait until they are delivered, if set
+    pub outbox: Arc<RwLock<Option<Box<dyn Outbox + Send + Sync>>>>,
+    /// Where a record of every run of the jobs is kept
+    pub run_history: Arc<RwLock<Box<dyn RunHistoryStore + Send + Sync>>>,
+    /// The group of jobs this scheduler loads and runs from the shared storage
+    pub group: String,
+    /// Whether this scheduler only observes the storage, never writing to it or running jobs
+    pub read_only: bool,
+    /// Whether the scheduler is shutting down gracefully, its runner not starting new runs
+    pub draining: Arc<RwLock<bool>>,
+    /// The runs in progress, by execution id
+    pub running_executions: Arc<RwLock<HashMap<Uuid, (RunningExecution, AbortHandle)>>>,
+    /// How late the runs of each job were started
+    pub drift: Arc<RwLock<HashMap<Uuid, DriftStats>>>,
+    /// How long after their scheduled time the runs of each job completed, and their SLA targets
+    pub completion_times: CompletionTimes,
+    /// The locks of the exclusive groups, of which only one job runs at a time
+    pub exclusive_groups: Arc<RwLock<HashMap<String, Arc<Mutex<()>>>>>,
+    /// The tags whose jobs run one after another, in the order they were due
+    pub sequential_tags: Vec<String>,
+    /// The timezones the next ticks of the listed jobs are also given in
+    pub display_timezones: Vec<Tz>,
+    /// The timezone the cron jobs added without one of their own are given
+    pub default_timezone: Option<Tz>,
+    /// How the day-of-week numbers of the cron expressions parsed by the scheduler are read
+    pub day_of_week_numbering: DayOfWeekNumbering,
+    /// How far ahead the next ticks of the jobs are looked for, a job whose next tick is further
+    /// away having no upcoming tick
+    pub search_horizon: chrono::Duration,
+    /// The slots of the runs that may be running at once, when their number is limited
+    pub execution_pool: Option<Arc<Semaphore>>,
+    /// The retries had by the occurrences of the jobs whose failed runs are being retried
+    pub retry_attempts: RetryAttempts,
+    /// The errors returned by the runs of fallible jobs, until the outcome of the run is recorded
+    pub run_errors: RunErrors,
+    /// Where the failed runs are routed to depending on how many times in a row a job failed
+    pub escalation_policy: Arc<RwLock<Option<EscalationPolicy>>>,
 }
 
 impl Context {
@@ -49,6 +125,8 @@ impl Context {
         let (notify_created_tx, _notify_created_rx) = tokio::sync::broadcast::channel(200);
         let (notify_delete_tx, _notify_delete_rx) = tokio::sync::broadcast::channel(200);
         let (notify_deleted_tx, _notify_deleted_rx) = tokio::sync::broadcast::channel(200);
+        let (dispatch_tx, _dispatch_rx) = tokio::sync::broadcast::channel(200);
+        let (reconciliation_tx, _reconciliation_rx) = tokio::sync::broadcast::channel(16);
 
         Self {
             job_activation_tx,
@@ -61,10 +139,38 @@ impl Context {
             notify_created_tx,
             notify_delete_tx,
             notify_deleted_tx,
+            dispatch_tx,
+            reconciliation_tx,
             metadata_storage,
             notification_storage,
             job_code,
             notification_code,
+            pending_approvals: Arc::new(RwLock::new(HashMap::new())),
+            reschedule_hooks: RescheduleHooks::default(),
+            run_conditions: RunConditions::default(),
+            payload_generators: Arc::new(RwLock::new(HashMap::new())),
+            scheduled_times: Arc::new(RwLock::new(HashMap::new())),
+            dead_letters: DeadLetters::default(),
+            run_scopes: RunScopes::default(),
+            internal_errors: InternalErrors::default(),
+            outbox: Arc::new(RwLock::new(None)),
+            run_history: Arc::new(RwLock::new(Box::new(SimpleRunHistoryStore::default()))),
+            group: String::new(),
+            read_only: false,
+            draining: Arc::new(RwLock::new(false)),
+            running_executions: Arc::new(RwLock::new(HashMap::new())),
+            drift: Arc::new(RwLock::new(HashMap::new())),
+            completion_times: CompletionTimes::default(),
+            exclusive_groups: Arc::new(RwLock::new(HashMap::new())),
+            sequential_tags: vec![],
+            display_timezones: vec![],
+            default_timezone: None,
+            day_of_week_numbering: DayOfWeekNumbering::default(),
+            search_horizon: chrono::Duration::days(leap::DEFAULT_SEARCH_HORIZON_DAYS),
+            execution_pool: None,
+            retry_attempts: RetryAttempts::default(),
+            run_errors: RunErrors::default(),
+            escalation_policy: Arc::new(RwLock::new(None)),
         }
     }
 }
@@ -82,10 +188,38 @@ impl Clone for Context {
             notify_created_tx: self.notify_created_tx.clone(),
             notify_delete_tx: self.notify_delete_tx.clone(),
             notify_deleted_tx: self.notify_deleted_tx.clone(),
+            dispatch_tx: self.dispatch_tx.clone(),
+            reconciliation_tx: self.reconciliation_tx.clone(),
             metadata_storage: self.metadata_storage.clone(),
             notification_storage: self.notification_storage.clone(),
             job_code: self.job_code.clone(),
             notification_code: self.notification_code.clone(),
+            pending_approvals: self.pending_approvals.clone(),
+            reschedule_hooks: self.reschedule_hooks.clone(),
+            run_conditions: self.run_conditions.clone(),
+            payload_generators: self.payload_generators.clone(),
+            scheduled_times: self.scheduled_times.clone(),
+            dead_letters: self.dead_letters.clone(),
+            run_scopes: self.run_scopes.clone(),
+            internal_errors: self.internal_errors.clone(),
+            outbox: self.outbox.clone(),
+            run_history: self.run_history.clone(),
+            group: self.group.clone(),
+            read_only: self.read_only,
+            draining: self.draining.clone(),
+            running_executions: self.running_executions.clone(),
+            drift: self.drift.clone(),
+            completion_times: self.completion_times.clone(),
+            exclusive_groups: self.exclusive_groups.clone(),
+            sequential_tags: self.sequential_tags.clone(),
+            display_timezones: self.display_timezones.clone(),
+            default_timezone: self.default_timezone,
+            day_of_week_numbering: self.day_of_week_numbering,
+            search_horizon: self.search_horizon,
+            execution_pool: self.execution_pool.clone(),
+            retry_attempts: self.retry_attempts.clone(),
+            run_errors: self.run_errors.clone(),
+            escalation_policy: self.escalation_policy.clone(),
         }
     }
 }
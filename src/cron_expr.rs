@@ -0,0 +1,265 @@
+use crate::JobSchedulerError;
+use chrono::Weekday;
+use cron::Schedule;
+use std::str::FromStr;
+
+///
+/// The values a field of a cron expression matches
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CronField<T = u32> {
+    /// Every value, `*`
+    Any,
+    /// A single value
+    At(T),
+    /// Every `n`th value from the lowest, `*/n`
+    Every(u32),
+    /// The values from the first to the last, both included
+    Range(T, T),
+    /// Every `n`th value from the first to the last, `first-last/n`
+    RangeEvery(T, T, u32),
+    /// The values listed
+    List(Vec<T>),
+}
+
+impl From<u32> for CronField<u32> {
+    fn from(value: u32) -> Self {
+        CronField::At(value)
+    }
+}
+
+impl From<Weekday> for CronField<Weekday> {
+    fn from(day: Weekday) -> Self {
+        CronField::At(day)
+    }
+}
+
+impl<T> CronField<T> {
+    ///
+    /// The field as written in a cron expression, checking its values are between `min` and
+    /// `max` and that its ranges and steps are not empty
+    fn expression(
+        &self,
+        number: impl Fn(&T) -> u32,
+        min: u32,
+        max: u32,
+    ) -> Result<String, JobSchedulerError> {
+        let value = |value: &T| {
+            let value = number(value);
+            if value < min || value > max {
+                return Err(JobSchedulerError::ParseSchedule);
+            }
+            Ok(value)
+        };
+        let range = |first: &T, last: &T| {
+            let (first, last) = (value(first)?, value(last)?);
+            if first > last {
+                return Err(JobSchedulerError::ParseSchedule);
+            }
+            Ok(format!("{}-{}", first, last))
+        };
+        let step = |n: u32| {
+            if n == 0 || n > max - min + 1 {
+                return Err(JobSchedulerError::ParseSchedule);
+            }
+            Ok(n)
+        };
+        match self {
+            CronField::Any => Ok("*".to_string()),
+            CronField::At(at) => value(at).map(|at| at.to_string()),
+            CronField::Every(n) => Ok(format!("*/{}", step(*n)?)),
+            CronField::Range(first, last) => range(first, last),
+            CronField::RangeEvery(first, last, n) => {
+                Ok(format!("{}/{}", range(first, last)?, step(*n)?))
+            }
+            CronField::List(values) if values.is_empty() => Err(JobSchedulerError::ParseSchedule),
+            CronField::List(values) => Ok(values
+                .iter()
+                .map(|v| value(v).map(|v| v.to_string()))
+                .collect::<Result<Vec<_>, _>>()?
+                .join(",")),
+        }
+    }
+}
+
+///
+/// Builds a cron schedule field by field, for schedules made by code instead of written by hand.
+/// Each field is checked against its range when the schedule is built, so the code making it
+/// doesn't format an expression of its own for the `cron` crate to reject.
+///
+/// ```rust,ignore
+/// use tokio_cron_scheduler::CronField::{Every, Range};
+///
+/// // Every 5 minutes from 9:00 to 17:55, on weekdays
+/// let schedule = CronExpr::builder()
+///     .minutes(Every(5))
+///     .hours(Range(9, 17))
+///     .days_of_week(Range(Weekday::Mon, Weekday::Fri))
+///     .build()?;
+/// let job = Job::new(schedule, |_uuid, _lock| {})?;
+/// ```
+pub struct CronExpr;
+
+impl CronExpr {
+    pub fn builder() -> CronExprBuilder {
+        CronExprBuilder::default()
+    }
+}
+
+///
+/// The fields of a cron schedule being built. The seconds are 0 unless set, so a schedule fires
+/// once in each minute it matches, and the other fields match any value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronExprBuilder {
+    seconds: CronField,
+    minutes: CronField,
+    hours: CronField,
+    days_of_month: CronField,
+    months: CronField,
+    days_of_week: CronField<Weekday>,
+    years: CronField,
+}
+
+impl Default for CronExprBuilder {
+    fn default() -> Self {
+        Self {
+            seconds: CronField::At(0),
+            minutes: CronField::Any,
+            hours: CronField::Any,
+            days_of_month: CronField::Any,
+            months: CronField::Any,
+            days_of_week: CronField::Any,
+            years: CronField::Any,
+        }
+    }
+}
+
+impl CronExprBuilder {
+    ///
+    /// The seconds, from 0 to 59
+    pub fn seconds(mut self, seconds: impl Into<CronField>) -> Self {
+        self.seconds = seconds.into();
+        self
+    }
+
+    ///
+    /// The minutes, from 0 to 59
+    pub fn minutes(mut self, minutes: impl Into<CronField>) -> Self {
+        self.minutes = minutes.into();
+        self
+    }
+
+    ///
+    /// The hours, from 0 to 23
+    pub fn hours(mut self, hours: impl Into<CronField>) -> Self {
+        self.hours = hours.into();
+        self
+    }
+
+    ///
+    /// The days of the month, from 1 to 31
+    pub fn days_of_month(mut self, days: impl Into<CronField>) -> Self {
+        self.days_of_month = days.into();
+        self
+    }
+
+    ///
+    /// The months, from 1 for January to 12
+    pub fn months(mut self, months: impl Into<CronField>) -> Self {
+        self.months = months.into();
+        self
+    }
+
+    ///
+    /// The days of the week. A range runs from Sunday to Saturday, so it can't wrap around
+    /// Saturday.
+    pub fn days_of_week(mut self, days: impl Into<CronField<Weekday>>) -> Self {
+        self.days_of_week = days.into();
+        self
+    }
+
+    ///
+    /// The years, from 1970 to 2100
+    pub fn years(mut self, years: impl Into<CronField>) -> Self {
+        self.years = years.into();
+        self
+    }
+
+    ///
+    /// The cron expression of the schedule, in the Quartz numbering of the days of the week
+    /// the schedules are stored in, or `ParseSchedule` when a field is out of its range
+    pub fn expression(&self) -> Result<String, JobSchedulerError> {
+        let number = |n: &u32| *n;
+        let mut fields = vec![
+            self.seconds.expression(number, 0, 59)?,
+            self.minutes.expression(number, 0, 59)?,
+            self.hours.expression(number, 0, 23)?,
+            self.days_of_month.expression(number, 1, 31)?,
+            self.months.expression(number, 1, 12)?,
+            self.days_of_week
+                .expression(|day| day.number_from_sunday(), 1, 7)?,
+        ];
+        if self.years != CronField::Any {
+            fields.push(self.years.expression(number, 1970, 2100)?);
+        }
+        Ok(fields.join(" "))
+    }
+
+    pub fn build(&self) -> Result<Schedule, JobSchedulerError> {
+        Schedule::from_str(&self.expression()?).map_err(|_| JobSchedulerError::ParseSchedule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    #[test]
+    fn cron_expr() {
+        use crate::CronField::{Any, Every, List, Range, RangeEvery};
+        use crate::{CronExpr, JobSchedulerError};
+        use chrono::Weekday;
+        use std::str::FromStr;
+
+        let builder = CronExpr::builder()
+            .seconds(0)
+            .minutes(Every(5))
+            .hours(Range(9, 17));
+        assert_eq!(builder.expression().unwrap(), "0 */5 9-17 * * *");
+        let schedule = builder.build().expect("schedule");
+        let after = Utc::now();
+        assert_eq!(
+            schedule.after(&after).take(5).collect::<Vec<_>>(),
+            cron::Schedule::from_str("0 */5 9-17 * * *")
+                .unwrap()
+                .after(&after)
+                .take(5)
+                .collect::<Vec<_>>()
+        );
+
+        let builder = CronExpr::builder()
+            .minutes(List(vec![0, 30]))
+            .hours(RangeEvery(8, 20, 4))
+            .days_of_week(Range(Weekday::Mon, Weekday::Fri))
+            .years(2030);
+        assert_eq!(builder.expression().unwrap(), "0 0,30 8-20/4 * * 2-6 2030");
+        assert!(builder.build().is_ok());
+        assert_eq!(
+            CronExpr::builder().seconds(Any).expression().unwrap(),
+            "* * * * * *"
+        );
+
+        for builder in [
+            CronExpr::builder().minutes(60),
+            CronExpr::builder().hours(Range(17, 9)),
+            CronExpr::builder().seconds(Every(0)),
+            CronExpr::builder().days_of_month(0),
+            CronExpr::builder().months(List(vec![])),
+            CronExpr::builder().days_of_week(Range(Weekday::Sat, Weekday::Sun)),
+        ] {
+            assert!(matches!(
+                builder.build(),
+                Err(JobSchedulerError::ParseSchedule)
+            ));
+        }
+    }
+}
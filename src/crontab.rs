@@ -0,0 +1,135 @@
+use crate::JobSchedulerError;
+
+/// The fields of a crontab line, without the seconds field of the `cron` crate
+const CRONTAB_FIELDS: usize = 5;
+
+///
+/// The cron expression, in the six or seven fields of the `cron` crate, of an expression written
+/// for crontab:
+/// - five fields, from the minutes to the day of the week, run at second 0
+/// - `@annually` and `@midnight`, the same as `@yearly` and `@daily`
+/// - `@every <duration>`, like `@every 30s`, `@every 5m` or `@every 2h`, for the durations that
+///   evenly divide the minute, hour or day they repeat in, up to a day
+///
+/// Other expressions, including `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly`, are
+/// returned as they are.
+pub(crate) fn expand(expression: &str) -> Result<String, JobSchedulerError> {
+    let expression = expression.trim();
+    if let Some(every) = expression.strip_prefix("@every") {
+        return every_expression(every.trim());
+    }
+    match expression {
+        "@annually" => return Ok("@yearly".to_string()),
+        "@midnight" => return Ok("@daily".to_string()),
+        _ => {}
+    }
+    let fields: Vec<_> = expression.split_whitespace().collect();
+    if fields.len() == CRONTAB_FIELDS && !expression.starts_with('@') {
+        return Ok(format!("0 {}", fields.join(" ")));
+    }
+    Ok(expression.to_string())
+}
+
+///
+/// The cron expression firing every `duration`, like `1h30m`
+fn every_expression(duration: &str) -> Result<String, JobSchedulerError> {
+    let seconds = duration_seconds(duration)?;
+    let divides =
+        |unit: u64, of: u64| seconds.is_multiple_of(unit) && of.is_multiple_of(seconds / unit);
+    if seconds < 60 && divides(1, 60) {
+        Ok(format!("*/{} * * * * *", seconds))
+    } else if seconds < 3_600 && divides(60, 60) {
+        Ok(format!("0 */{} * * * *", seconds / 60))
+    } else if seconds < 86_400 && divides(3_600, 24) {
+        Ok(format!("0 0 */{} * * *", seconds / 3_600))
+    } else if seconds == 86_400 {
+        Ok("0 0 0 * * *".to_string())
+    } else {
+        // Like 7m, which a schedule restarts at every hour. `Job::new_repeated` keeps such an
+        // interval.
+        Err(JobSchedulerError::ParseSchedule)
+    }
+}
+
+///
+/// The seconds of a duration written like `90s`, `5m`, `2h`, `1d` or `1h30m`
+fn duration_seconds(duration: &str) -> Result<u64, JobSchedulerError> {
+    let mut seconds = 0u64;
+    let mut number = String::new();
+    for c in duration.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3_600,
+            'd' => 86_400,
+            _ => return Err(JobSchedulerError::ParseSchedule),
+        };
+        let n: u64 = number
+            .parse()
+            .map_err(|_| JobSchedulerError::ParseSchedule)?;
+        seconds = n
+            .checked_mul(unit)
+            .and_then(|s| seconds.checked_add(s))
+            .ok_or(JobSchedulerError::ParseSchedule)?;
+        number.clear();
+    }
+    if !number.is_empty() || seconds == 0 {
+        return Err(JobSchedulerError::ParseSchedule);
+    }
+    Ok(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn crontab_expressions() {
+        use crate::DayOfWeekNumbering;
+
+        let normalize = |expression: &str| DayOfWeekNumbering::Quartz.normalize(expression);
+        assert_eq!(normalize("*/5 * * * *").unwrap(), "0 */5 * * * *");
+        assert_eq!(
+            DayOfWeekNumbering::Unix.normalize("0 9 * * 1-5").unwrap(),
+            "0 0 9 * * 2,3,4,5,6"
+        );
+        assert_eq!(normalize("0 0 9 * * *").unwrap(), "0 0 9 * * *");
+        assert_eq!(normalize("@hourly").unwrap(), "@hourly");
+        assert_eq!(normalize("@midnight").unwrap(), "@daily");
+        assert_eq!(normalize("@annually").unwrap(), "@yearly");
+        assert_eq!(normalize("@every 30s").unwrap(), "*/30 * * * * *");
+        assert_eq!(normalize("@every 5m").unwrap(), "0 */5 * * * *");
+        assert_eq!(normalize("@every 1h").unwrap(), "0 0 */1 * * *");
+        assert_eq!(normalize("@every 6h").unwrap(), "0 0 */6 * * *");
+        assert_eq!(normalize("@every 1d").unwrap(), "0 0 0 * * *");
+        for expression in [
+            "@hourly",
+            "@daily",
+            "@weekly",
+            "@monthly",
+            "@yearly",
+            "@every 2m",
+        ] {
+            assert!(DayOfWeekNumbering::Quartz.parse(expression).is_ok());
+        }
+
+        // Intervals a schedule can't keep across the hour or day, and durations that don't parse
+        for expression in [
+            "@every 7m",
+            "@every 1h30m",
+            "@every 2d",
+            "@every 0s",
+            "@every 5",
+            "@every 5w",
+            "@reboot",
+        ] {
+            assert!(
+                DayOfWeekNumbering::Quartz.parse(expression).is_err(),
+                "{}",
+                expression
+            );
+        }
+    }
+}
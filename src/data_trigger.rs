@@ -0,0 +1,49 @@
+use crate::run_condition::RunCondition;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+///
+/// Whether the data a job works on is available, e.g. whether the upstream file has landed
+pub type AvailabilityCheck =
+    dyn Fn(Uuid) -> Pin<Box<dyn Future<Output = bool> + Send>> + Send + Sync;
+
+///
+/// Runs a job at each of its scheduled times once its data is available, polling the check every
+/// `poll_every` until it is or `deadline` has passed since the scheduled time. A run whose data
+/// doesn't come in time is skipped. The deadline should be shorter than the time between runs,
+/// so that a run is not still waiting when the next one is due.
+#[derive(Clone)]
+pub struct DataTrigger {
+    check: Arc<AvailabilityCheck>,
+    poll_every: Duration,
+    deadline: Duration,
+}
+
+impl DataTrigger {
+    pub fn new(check: Box<AvailabilityCheck>, poll_every: Duration, deadline: Duration) -> Self {
+        Self {
+            check: Arc::from(check),
+            poll_every,
+            deadline,
+        }
+    }
+
+    pub(crate) fn into_condition(self) -> RunCondition {
+        let check = self.check;
+        let deadline = self.deadline;
+        RunCondition::new(Box::new(move |job_id| {
+            let check = check.clone();
+            Box::pin(async move {
+                if check(job_id).await {
+                    Ok(())
+                } else {
+                    Err(format!("Data not available within {:?}", deadline))
+                }
+            })
+        }))
+        .retry(self.poll_every, self.deadline)
+    }
+}
@@ -0,0 +1,198 @@
+use crate::crontab;
+use crate::JobSchedulerError;
+use chrono::Weekday;
+use cron::Schedule;
+use std::str::FromStr;
+
+/// The day-of-week field of a cron expression, after the seconds, minutes, hours, day-of-month
+/// and month fields
+const DAY_OF_WEEK_FIELD: usize = 5;
+
+const WEEK: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+///
+/// How the numbers of the day-of-week field of a cron expression are read. The names of the days,
+/// like `Mon-Fri`, mean the same in all of them. Expressions taken from other systems should be
+/// parsed with the numbering of that system, as the same number names a different day in each.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DayOfWeekNumbering {
+    /// 1 is Sunday and 7 is Saturday, like Quartz. The numbering of the `cron` crate, in which
+    /// the schedules are stored.
+    #[default]
+    Quartz,
+    /// 0 and 7 are Sunday, 1 is Monday and 6 is Saturday, like Unix cron
+    Unix,
+    /// 1 is Monday and 7 is Sunday, like ISO 8601, for weeks starting on Monday
+    Iso,
+}
+
+impl DayOfWeekNumbering {
+    ///
+    /// The numbering whose week starts on `week_start`, for expressions written by people of a
+    /// locale whose weeks start on Monday or on Sunday
+    pub fn week_starting_on(week_start: Weekday) -> Self {
+        match week_start {
+            Weekday::Mon => DayOfWeekNumbering::Iso,
+            _ => DayOfWeekNumbering::Quartz,
+        }
+    }
+
+    fn range(&self) -> (u32, u32) {
+        match self {
+            DayOfWeekNumbering::Quartz | DayOfWeekNumbering::Iso => (1, 7),
+            DayOfWeekNumbering::Unix => (0, 7),
+        }
+    }
+
+    fn weekday(&self, number: u32) -> Result<Weekday, JobSchedulerError> {
+        let (min, max) = self.range();
+        if number < min || number > max {
+            return Err(JobSchedulerError::ParseSchedule);
+        }
+        let days_from_monday = match self {
+            DayOfWeekNumbering::Quartz => number + 5,
+            DayOfWeekNumbering::Unix => number + 6,
+            DayOfWeekNumbering::Iso => number - 1,
+        };
+        Ok(WEEK[(days_from_monday % 7) as usize])
+    }
+
+    ///
+    /// The days of an item of the list of the day-of-week field, in the Quartz numbering.
+    /// `None` when the item is read the same in all numberings.
+    fn quartz_days(&self, item: &str) -> Result<Option<Vec<u32>>, JobSchedulerError> {
+        if !item.chars().any(|c| c.is_ascii_digit()) {
+            return Ok(None);
+        }
+        if item.chars().any(|c| c.is_ascii_alphabetic()) {
+            // Like 1-Fri, which can't be read in another numbering
+            return Err(JobSchedulerError::ParseSchedule);
+        }
+        let number = |s: &str| {
+            s.parse::<u32>()
+                .map_err(|_| JobSchedulerError::ParseSchedule)
+        };
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, number(step)?),
+            None => (item, 1),
+        };
+        let (min, max) = self.range();
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (number(start)?, number(end)?),
+            None if item.contains('/') => (number(range)?, max),
+            None => (number(range)?, number(range)?),
+        };
+        if step == 0 || start > end {
+            return Err(JobSchedulerError::ParseSchedule);
+        }
+        (start..=end)
+            .step_by(step as usize)
+            .map(|number| Ok(self.weekday(number)?.number_from_sunday()))
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+
+    ///
+    /// The cron expression with the numbers of its day-of-week field in the Quartz numbering of
+    /// the `cron` crate. Ranges and steps are written out as lists of days. Expressions written
+    /// for crontab, with five fields or like `@every 5m`, are first written out in the fields of
+    /// the `cron` crate.
+    pub fn normalize(&self, expression: &str) -> Result<String, JobSchedulerError> {
+        let expression = crontab::expand(expression)?;
+        let mut fields: Vec<String> = expression.split_whitespace().map(String::from).collect();
+        if *self == DayOfWeekNumbering::Quartz || fields.len() <= DAY_OF_WEEK_FIELD {
+            return Ok(expression);
+        }
+        let mut days = vec![];
+        let mut items = vec![];
+        for item in fields[DAY_OF_WEEK_FIELD].split(',') {
+            match self.quartz_days(item)? {
+                Some(item_days) => days.extend(item_days),
+                None => items.push(item.to_string()),
+            }
+        }
+        days.sort_unstable();
+        days.dedup();
+        let days = days.iter().map(u32::to_string);
+        fields[DAY_OF_WEEK_FIELD] = days.chain(items).collect::<Vec<_>>().join(",");
+        Ok(fields.join(" "))
+    }
+
+    ///
+    /// Parse a cron expression whose day-of-week numbers are in this numbering. Crontab lines
+    /// without the seconds, `@every 5m` and the other `@` macros of crontab are parsed too.
+    ///
+    /// ```rust,ignore
+    /// // Weekdays at 9, as written for Unix cron
+    /// let schedule = DayOfWeekNumbering::Unix.parse("0 0 9 * * 1-5")?;
+    /// // The same, as in a crontab file
+    /// let schedule = DayOfWeekNumbering::Unix.parse("0 9 * * 1-5")?;
+    /// let job = Job::new(schedule, |_uuid, _l| println!("Good morning"))?;
+    /// ```
+    pub fn parse(&self, expression: &str) -> Result<Schedule, JobSchedulerError> {
+        Schedule::from_str(&self.normalize(expression)?)
+            .map_err(|_| JobSchedulerError::ParseSchedule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    #[test]
+    fn day_of_week_numbering() {
+        use crate::DayOfWeekNumbering;
+        use chrono::{Datelike, TimeZone, Weekday};
+
+        let days = |numbering: DayOfWeekNumbering, expression: &str| -> Vec<Weekday> {
+            let after = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+            numbering
+                .parse(expression)
+                .unwrap()
+                .after(&after)
+                .take(7)
+                .map(|t| t.weekday())
+                .collect()
+        };
+        let weekdays = vec![
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Mon,
+            Weekday::Tue,
+        ];
+        assert_eq!(days(DayOfWeekNumbering::Quartz, "0 0 9 * * 2-6"), weekdays);
+        assert_eq!(days(DayOfWeekNumbering::Unix, "0 0 9 * * 1-5"), weekdays);
+        assert_eq!(days(DayOfWeekNumbering::Iso, "0 0 9 * * 1-5"), weekdays);
+        assert_eq!(
+            days(DayOfWeekNumbering::Unix, "0 0 9 * * Mon-Fri"),
+            weekdays
+        );
+
+        assert_eq!(
+            DayOfWeekNumbering::Unix.normalize("0 0 9 * * 0,7").unwrap(),
+            "0 0 9 * * 1"
+        );
+        assert_eq!(
+            DayOfWeekNumbering::Iso.normalize("0 0 9 * * 6-7").unwrap(),
+            "0 0 9 * * 1,7"
+        );
+        assert_eq!(
+            DayOfWeekNumbering::Unix.normalize("0 0 9 * * */2").unwrap(),
+            "0 0 9 * * 1,3,5,7"
+        );
+        assert!(DayOfWeekNumbering::Iso.parse("0 0 9 * * 0").is_err());
+        assert!(DayOfWeekNumbering::Unix.parse("0 0 9 * * 1-Fri").is_err());
+    }
+}
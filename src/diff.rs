@@ -0,0 +1,294 @@
+use crate::peek::PeekNext;
+use crate::JobStoredData;
+use chrono::{DateTime, Utc};
+use cron::{Schedule, TimeUnitSpec};
+use uuid::Uuid;
+
+///
+/// A field of a cron schedule
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScheduleField {
+    Seconds,
+    Minutes,
+    Hours,
+    DaysOfMonth,
+    Months,
+    /// In the Quartz numbering of the `cron` crate, 1 being Sunday
+    DaysOfWeek,
+    Years,
+}
+
+///
+/// The values a field of a schedule matches after a change and no longer matches
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: ScheduleField,
+    pub added: Vec<u32>,
+    pub removed: Vec<u32>,
+}
+
+///
+/// What changed between two schedules: the fields matching other values, and when each of them
+/// runs next. Two expressions matching the same instants, like `0 0 9 * * Mon-Fri` and
+/// `0 0 9 * * 2-6`, have no changed fields.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScheduleDiff {
+    pub fields: Vec<FieldChange>,
+    pub current_next_run: Option<DateTime<Utc>>,
+    pub new_next_run: Option<DateTime<Utc>>,
+}
+
+impl ScheduleDiff {
+    ///
+    /// Whether the two schedules match the same instants
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    ///
+    /// How far the next run moves, later when positive. `None` when either schedule never
+    /// runs again.
+    pub fn next_run_shift(&self) -> Option<chrono::Duration> {
+        Some(self.new_next_run? - self.current_next_run?)
+    }
+}
+
+///
+/// Compares a schedule to the one replacing it, e.g. to log what a reload of the schedules changed
+pub trait DiffSchedule {
+    ///
+    /// What changes when `other` replaces this schedule, with the next runs after now
+    fn diff(&self, other: &Schedule) -> ScheduleDiff {
+        self.diff_at(other, &Utc::now())
+    }
+
+    ///
+    /// What changes when `other` replaces this schedule, with the next runs after `after`
+    fn diff_at(&self, other: &Schedule, after: &DateTime<Utc>) -> ScheduleDiff;
+}
+
+impl DiffSchedule for Schedule {
+    fn diff_at(&self, other: &Schedule, after: &DateTime<Utc>) -> ScheduleDiff {
+        let fields = vec![
+            field_change(ScheduleField::Seconds, self.seconds(), other.seconds()),
+            field_change(ScheduleField::Minutes, self.minutes(), other.minutes()),
+            field_change(ScheduleField::Hours, self.hours(), other.hours()),
+            field_change(
+                ScheduleField::DaysOfMonth,
+                self.days_of_month(),
+                other.days_of_month(),
+            ),
+            field_change(ScheduleField::Months, self.months(), other.months()),
+            field_change(
+                ScheduleField::DaysOfWeek,
+                self.days_of_week(),
+                other.days_of_week(),
+            ),
+            field_change(ScheduleField::Years, self.years(), other.years()),
+        ];
+        ScheduleDiff {
+            fields: fields.into_iter().flatten().collect(),
+            current_next_run: self.peek_next(after),
+            new_next_run: other.peek_next(after),
+        }
+    }
+}
+
+fn field_change(
+    field: ScheduleField,
+    current: &impl TimeUnitSpec,
+    new: &impl TimeUnitSpec,
+) -> Option<FieldChange> {
+    let added: Vec<_> = new.iter().filter(|o| !current.includes(*o)).collect();
+    let removed: Vec<_> = current.iter().filter(|o| !new.includes(*o)).collect();
+    if added.is_empty() && removed.is_empty() {
+        return None;
+    }
+    Some(FieldChange {
+        field,
+        added,
+        removed,
+    })
+}
+
+///
+/// What changed between the stored data of a job and the data replacing it: the names of the
+/// settings that changed, how its cron schedule changed, and its next tick before and after.
+/// The state kept while the job runs, like its last tick or its count of runs, is left out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JobDiff {
+    pub job_id: Uuid,
+    /// The settings that changed, named like the fields of `JobStoredData`, `schedule`,
+    /// `timezone`, `exclusions` and `jitter_seconds` for the cron schedule, and
+    /// `repeated_every`, `fixed_delay`, `monotonic` and `instants` for the timing of the other
+    /// jobs
+    pub changed: Vec<&'static str>,
+    /// How the cron schedule changed, when both are cron jobs with other schedules
+    pub schedule: Option<ScheduleDiff>,
+    pub current_next_tick: Option<DateTime<Utc>>,
+    pub new_next_tick: Option<DateTime<Utc>>,
+}
+
+impl JobDiff {
+    ///
+    /// Whether none of the settings of the job changed
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty()
+    }
+
+    ///
+    /// Whether the next run of the job moves
+    pub fn moves_next_run(&self) -> bool {
+        self.current_next_tick != self.new_next_tick
+    }
+}
+
+pub(crate) fn job_diff(current: &JobStoredData, new: &JobStoredData) -> JobDiff {
+    let mut changed = vec![];
+    let mut check = |name: &'static str, differs: bool| {
+        if differs {
+            changed.push(name);
+        }
+    };
+    let schedule = match (current.schedule(), new.schedule()) {
+        (Some(current), Some(new)) => Some(current.diff(&new)).filter(|d| !d.is_empty()),
+        _ => None,
+    };
+    check("job_type", current.job_type != new.job_type);
+    check(
+        "schedule",
+        schedule.is_some() || current.schedule().is_some() != new.schedule().is_some(),
+    );
+    check("timezone", current.timezone() != new.timezone());
+    check("exclusions", current.exclusions() != new.exclusions());
+    check(
+        "jitter_seconds",
+        current.jitter_seconds() != new.jitter_seconds(),
+    );
+    check(
+        "repeated_every",
+        current.repeated_every() != new.repeated_every(),
+    );
+    check("fixed_delay", current.fixed_delay() != new.fixed_delay());
+    check("monotonic", current.monotonic() != new.monotonic());
+    check("instants", current.instants() != new.instants());
+    check(
+        "min_interval_seconds",
+        current.min_interval() != new.min_interval(),
+    );
+    check("after_job", current.after_job != new.after_job);
+    check("name", current.name != new.name);
+    check("group", current.group != new.group);
+    check("tags", current.tags != new.tags);
+    check("extra", current.extra != new.extra);
+    check("priority", current.priority != new.priority);
+    check("cost", current.cost != new.cost);
+    check(
+        "exclusive_group",
+        current.exclusive_group != new.exclusive_group,
+    );
+    check("retry_policy", current.retry_policy != new.retry_policy);
+    check(
+        "concurrency_policy",
+        current.concurrency_policy != new.concurrency_policy,
+    );
+    check(
+        "misfire_policy",
+        current.misfire_policy != new.misfire_policy,
+    );
+    check(
+        "leap_day_policy",
+        current.leap_day_policy != new.leap_day_policy,
+    );
+    check(
+        "maintenance_window_policy",
+        current.maintenance_window_policy != new.maintenance_window_policy,
+    );
+    check(
+        "starting_deadline_seconds",
+        current.starting_deadline_seconds != new.starting_deadline_seconds,
+    );
+    check(
+        "skip_if_last_failed",
+        current.skip_if_last_failed != new.skip_if_last_failed,
+    );
+    check(
+        "skip_if_upstream_failed",
+        current.skip_if_upstream_failed != new.skip_if_upstream_failed,
+    );
+    check(
+        "requires_approval",
+        current.requires_approval != new.requires_approval,
+    );
+    check("warm_start", current.warm_start != new.warm_start);
+    JobDiff {
+        job_id: new
+            .id
+            .as_ref()
+            .or(current.id.as_ref())
+            .map(Uuid::from)
+            .unwrap_or_default(),
+        changed,
+        schedule,
+        current_next_tick: current.next_tick_utc(),
+        new_next_tick: new.next_tick_utc(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiffSchedule, ScheduleField};
+    use crate::{Changeset, Job, JobScheduler};
+    use chrono::{Duration, TimeZone, Utc};
+    use std::str::FromStr;
+
+    /// The fields and the next run changed between two schedules, and a job changed by a
+    /// changeset
+    #[tokio::test]
+    async fn schedule_diff() {
+        let schedule = |expression: &str| cron::Schedule::from_str(expression).unwrap();
+        let after = Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap();
+
+        let diff = schedule("0 0 9 * * Mon-Fri").diff_at(&schedule("0 0 9 * * 2-6"), &after);
+        assert!(diff.is_empty());
+        assert_eq!(diff.next_run_shift(), Some(Duration::zero()));
+
+        let diff = schedule("0 0 9 * * *").diff_at(&schedule("0 30 8,9 * * *"), &after);
+        assert_eq!(
+            diff.fields
+                .iter()
+                .map(|f| (f.field, f.added.clone(), f.removed.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (ScheduleField::Minutes, vec![30], vec![0]),
+                (ScheduleField::Hours, vec![8], vec![]),
+            ]
+        );
+        assert_eq!(
+            diff.current_next_run,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap())
+        );
+        assert_eq!(diff.next_run_shift(), Some(Duration::minutes(-30)));
+
+        // A job whose schedule and name change
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let job_id = sched
+            .add(Job::new("0 0 9 * * *", |_, _| {}).expect("job"))
+            .await
+            .expect("add");
+        let mut job = Job::new("0 0 10 * * *", |_, _| {}).expect("job");
+        job.set_name("reports".to_string()).expect("name");
+        let mut changeset = Changeset::new().update_job(job_id, job).expect("update");
+        let preview = changeset.preview(&sched).await.expect("preview");
+        let diff = preview[0].diff.as_ref().expect("diff");
+        assert_eq!(diff.job_id, job_id);
+        assert_eq!(diff.changed, vec!["schedule", "name"]);
+        assert_eq!(
+            diff.schedule.as_ref().unwrap().fields[0].field,
+            ScheduleField::Hours
+        );
+        assert!(diff.moves_next_run());
+        changeset.apply(&sched).await.expect("apply");
+        sched.shutdown().await.expect("shutdown");
+    }
+}
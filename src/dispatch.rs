@@ -0,0 +1,92 @@
+use crate::JobStoredData;
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use uuid::Uuid;
+
+///
+/// A due run fired by the tick loop, at `position` among the `of` runs due at the same instant.
+/// Runs due at the same instant are fired by priority, the highest first, then by name and then
+/// by id, so dependent batch jobs start in the same order every time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dispatch {
+    pub job_id: Uuid,
+    pub due: DateTime<Utc>,
+    /// The place of the run among the runs due at the same instant, the first being 0
+    pub position: usize,
+    pub of: usize,
+}
+
+///
+/// The order the due runs of `jobs` are fired in, the earliest due first. Runs without a next
+/// tick are taken as due at `now`.
+pub(crate) fn order(jobs: Vec<JobStoredData>, now: &DateTime<Utc>) -> Vec<Dispatch> {
+    let mut due: Vec<_> = jobs
+        .into_iter()
+        .filter_map(|job| {
+            let job_id: Uuid = job.id.as_ref()?.into();
+            let due = job.next_tick_utc().unwrap_or(*now);
+            Some((due, Reverse(job.priority), job.name, job_id))
+        })
+        .collect();
+    due.sort();
+    due.chunk_by(|a, b| a.0 == b.0)
+        .flat_map(|runs| {
+            runs.iter()
+                .enumerate()
+                .map(move |(position, (due, _, _, job_id))| Dispatch {
+                    job_id: *job_id,
+                    due: *due,
+                    position,
+                    of: runs.len(),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    #[tokio::test]
+    async fn dispatch_order() {
+        let mut sched = crate::JobScheduler::new().await.expect("scheduler");
+        let instant = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        let mut job_ids = vec![];
+        for (name, priority) in [("b", 0), ("a", 0), ("c", 5), ("a", 0)] {
+            let mut job = crate::Job::new_one_shot_at_instant(instant, |_, _| {}).expect("job");
+            job.set_name(name).expect("name");
+            job.set_priority(priority).expect("priority");
+            let mut data = job.job_data().expect("data");
+            // At the same second whichever side of it the instant was converted on
+            data.next_tick = Utc::now().timestamp() as u64 + 2;
+            job.set_job_data(data).expect("job data");
+            job_ids.push(sched.add(job).await.expect("add"));
+        }
+        let mut dispatches = sched.subscribe_dispatches();
+        let mut events = sched.subscribe_events();
+        sched.start().await.expect("start");
+
+        let mut a = [job_ids[1], job_ids[3]];
+        a.sort();
+        let expected = [job_ids[2], a[0], a[1], job_ids[0]];
+        for (position, job_id) in expected.iter().enumerate() {
+            let dispatch =
+                tokio::time::timeout(std::time::Duration::from_secs(5), dispatches.recv())
+                    .await
+                    .expect("dispatched")
+                    .expect("dispatch");
+            assert_eq!(dispatch.job_id, *job_id);
+            assert_eq!(dispatch.position, position);
+            assert_eq!(dispatch.of, 4);
+        }
+        let mut scheduled = vec![];
+        while scheduled.len() < 4 {
+            let (job_id, state) = events.recv().await.expect("event");
+            if state == crate::JobNotification::Scheduled {
+                scheduled.push(job_id);
+            }
+        }
+        assert_eq!(scheduled, expected);
+        sched.shutdown().await.expect("shutdown");
+    }
+}
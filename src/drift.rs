@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+///
+/// How late the runs of a job were started after their scheduled instant
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DriftStats {
+    pub runs: u64,
+    pub last: Duration,
+    pub max: Duration,
+    pub total: Duration,
+}
+
+impl DriftStats {
+    pub(crate) fn record(&mut self, drift: Duration) {
+        self.runs += 1;
+        self.last = drift;
+        self.max = self.max.max(drift);
+        self.total += drift;
+    }
+
+    ///
+    /// The mean drift over all the recorded runs
+    pub fn mean(&self) -> Duration {
+        match self.runs {
+            0 => Duration::ZERO,
+            runs => self.total.div_f64(runs as f64),
+        }
+    }
+}
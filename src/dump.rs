@@ -0,0 +1,183 @@
+use crate::job_scheduler::{JobInfo, JobsSchedulerLocked, RunningExecution};
+use crate::{JobAndNextTick, JobSchedulerError};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+///
+/// The messages waiting in one of the internal channels of the scheduler
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChannelDepth {
+    pub channel: &'static str,
+    /// The messages not yet received by the slowest receiver
+    pub queued: usize,
+    pub receivers: usize,
+}
+
+///
+/// The internal state of a scheduler at one moment, as returned and logged by
+/// `JobScheduler::dump_state`, to find out why a scheduler got stuck
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateDump {
+    pub taken_at: DateTime<Utc>,
+    pub group: String,
+    pub paused: bool,
+    pub draining: bool,
+    pub read_only: bool,
+    /// All the jobs in the metadata storage, including those of other groups
+    pub jobs: Vec<JobInfo>,
+    /// The jobs of the group of the scheduler the tick loop fires, by their next tick
+    pub next_ticks: Vec<(Uuid, DateTime<Utc>)>,
+    /// The runs in progress
+    pub running: Vec<RunningExecution>,
+    /// The due runs waiting on approval, since when they are due
+    pub pending_approvals: Vec<(Uuid, DateTime<Utc>)>,
+    pub channels: Vec<ChannelDepth>,
+}
+
+pub(crate) async fn dump_state(
+    scheduler: &JobsSchedulerLocked,
+) -> Result<StateDump, JobSchedulerError> {
+    let context = scheduler.context.clone();
+    let taken_at = Utc::now();
+    let jobs = scheduler.list_jobs().await?;
+    let mut next_ticks: Vec<_> = {
+        let mut storage = context.metadata_storage.write().await;
+        storage
+            .list_next_ticks()
+            .await?
+            .into_iter()
+            .filter(|j| j.next_tick > 0)
+            .map(|j| {
+                (
+                    j.id.as_ref().map(Uuid::from).unwrap_or_default(),
+                    JobAndNextTick::utc(j.next_tick),
+                )
+            })
+            .collect()
+    };
+    next_ticks.sort_by_key(|(_, next_tick)| *next_tick);
+    let mut running = scheduler.running_executions().await;
+    running.sort_by_key(|r| r.started);
+    let mut pending_approvals: Vec<_> = {
+        let r = context.pending_approvals.read().await;
+        r.iter().map(|(job_id, due)| (*job_id, *due)).collect()
+    };
+    pending_approvals.sort_by_key(|(_, due)| *due);
+    let draining = {
+        let r = context.draining.read().await;
+        *r
+    };
+    let channels = vec![
+        channel("job_activation", &context.job_activation_tx),
+        channel("notify", &context.notify_tx),
+        channel("job_create", &context.job_create_tx),
+        channel("job_created", &context.job_created_tx),
+        channel("job_delete", &context.job_delete_tx),
+        channel("job_deleted", &context.job_deleted_tx),
+        channel("notify_create", &context.notify_create_tx),
+        channel("notify_created", &context.notify_created_tx),
+        channel("notify_delete", &context.notify_delete_tx),
+        channel("notify_deleted", &context.notify_deleted_tx),
+        channel("dispatch", &context.dispatch_tx),
+        channel("reconciliation", &context.reconciliation_tx),
+    ];
+    let dump = StateDump {
+        taken_at,
+        group: context.group.clone(),
+        paused: scheduler.is_paused().await,
+        draining,
+        read_only: context.read_only,
+        jobs,
+        next_ticks,
+        running,
+        pending_approvals,
+        channels,
+    };
+    log_dump(&dump);
+    Ok(dump)
+}
+
+fn channel<T>(name: &'static str, tx: &tokio::sync::broadcast::Sender<T>) -> ChannelDepth {
+    ChannelDepth {
+        channel: name,
+        queued: tx.len(),
+        receivers: tx.receiver_count(),
+    }
+}
+
+///
+/// Log the dump as one event for the scheduler and one for each job, tick, run and channel, so
+/// a subscriber can filter and index them by their fields
+fn log_dump(dump: &StateDump) {
+    info!(
+        taken_at = dump.taken_at,
+        group = dump.group,
+        paused = dump.paused,
+        draining = dump.draining,
+        read_only = dump.read_only,
+        jobs = dump.jobs.len(),
+        running = dump.running.len(),
+        pending_approvals = dump.pending_approvals.len();
+        "Scheduler state dump"
+    );
+    for job in dump.jobs.iter() {
+        info!(
+            job_id = job.job_id,
+            name = job.name,
+            group = job.group,
+            job_type = job.job_type,
+            schedule = job.schedule,
+            next_tick = job.next_tick,
+            last_tick = job.last_tick,
+            count = job.count,
+            stopped = job.stopped,
+            consecutive_failures = job.consecutive_failures;
+            "State dump: job"
+        );
+    }
+    for (position, (job_id, next_tick)) in dump.next_ticks.iter().enumerate() {
+        info!(position = position, job_id = job_id, next_tick = next_tick; "State dump: next tick");
+    }
+    for run in dump.running.iter() {
+        info!(
+            execution_id = run.execution_id,
+            job_id = run.job_id,
+            scheduled = run.scheduled,
+            started = run.started;
+            "State dump: running"
+        );
+    }
+    for (job_id, due) in dump.pending_approvals.iter() {
+        info!(job_id = job_id, due = due; "State dump: pending approval");
+    }
+    for channel in dump.channels.iter() {
+        info!(
+            channel = channel.channel,
+            queued = channel.queued,
+            receivers = channel.receivers;
+            "State dump: channel"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn state_dump() {
+        let mut sched = crate::JobScheduler::new().await.expect("scheduler");
+        let job =
+            crate::Job::new_repeated(std::time::Duration::from_secs(3600), |_, _| {}).expect("job");
+        let job_id = sched.add(job).await.expect("add");
+        sched.start().await.expect("start");
+        sched.pause_all().await.expect("pause");
+
+        let dump = sched.dump_state().await.expect("dump");
+        assert!(dump.paused);
+        assert!(!dump.draining);
+        assert!(dump.jobs.iter().any(|j| j.job_id == job_id));
+        assert_eq!(dump.next_ticks.first().map(|(id, _)| *id), Some(job_id));
+        assert!(dump.running.is_empty());
+        assert!(dump.channels.iter().any(|c| c.channel == "dispatch"));
+        sched.shutdown().await.expect("shutdown");
+    }
+}
@@ -1,5 +1,8 @@
+#[cfg(any(feature = "graphql", feature = "sse"))]
+use crate::auth::Role;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
+use uuid::Uuid;
 
 #[derive(Debug, Clone)]
 pub enum JobSchedulerError {
@@ -23,10 +26,32 @@ pub enum JobSchedulerError {
     JobTick,
     UpdateJobData,
     NoNextTick,
+    NoUpcoming,
     CantListGuids,
     CantListNextTicks,
     NotifyOnStateError,
     ParseSchedule,
+    NoPendingApproval,
+    ChangesetAlreadyApplied,
+    ChangesetRollback,
+    CantTrigger,
+    BlockingRuntime,
+    ReadOnly,
+    OutOfScope,
+    NoRunningExecution,
+    NoDeadLetter,
+    #[cfg(any(feature = "graphql", feature = "sse"))]
+    Forbidden(Role),
+    #[cfg(any(feature = "graphql", feature = "sse"))]
+    RateLimited,
+    MigrationVerification(Uuid),
+    RestoreConflict(Uuid),
+    CantDecodeSnapshot,
+    JobConstruction(String),
+    Codec(String),
+    Export(String),
+    LeaderElection,
+    RunLock,
     #[cfg(feature = "nats_storage")]
     BuilderNeedsField(String),
     #[cfg(feature = "nats_storage")]
@@ -0,0 +1,259 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{
+    ConcurrencyPolicy, JobType, LeapDayPolicy, MaintenanceWindowPolicy, MisfirePolicy,
+};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{
+    ConcurrencyPolicy, JobType, LeapDayPolicy, MaintenanceWindowPolicy, MisfirePolicy,
+};
+use crate::job_scheduler::JobsSchedulerLocked;
+use crate::maintenance_window::MaintenanceWindow;
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+///
+/// A decision of the scheduler that goes into when, or whether, a job runs next
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimingDecision {
+    /// The next tick is the next instant matching the cron schedule, in UTC
+    CronMatched { schedule: String },
+    /// In the years without February 29, the schedule fires on a day standing in for it
+    LeapDay { policy: LeapDayPolicy },
+    /// The instants matching one of these cron expressions are skipped
+    Excluded { exclusions: Vec<String> },
+    /// The next tick is put off by up to this many seconds, the same in every scheduler
+    Jitter { seconds: u64 },
+    /// The next tick is the previous one plus the interval of the job
+    Repeated { every_seconds: u64 },
+    /// The next tick is set to this many seconds after each run of the job ended
+    FixedDelay { delay_seconds: u64 },
+    /// The job runs once, at its next tick
+    OneShot,
+    /// The job runs once at each of its instants, with this many left after its next tick
+    Instants { remaining: usize },
+    /// The next tick is set to this many seconds after each successful run of the upstream job
+    AfterJob { upstream: Uuid, delay_seconds: u64 },
+    /// The ticks coming sooner than this many seconds after the previous one are skipped
+    MinInterval { seconds: u64 },
+    /// The job has no next tick, so it does not run again and gets removed
+    NoNextTick,
+    /// The next tick was adjusted by a reschedule hook of the job or the scheduler
+    Rescheduled { job_hook: bool, global_hook: bool },
+    /// The next tick falls in a maintenance window, where the policy of the job applies
+    InMaintenanceWindow {
+        window: MaintenanceWindow,
+        policy: MaintenanceWindowPolicy,
+    },
+    /// The job belongs to another group and is not run by this scheduler
+    OtherGroup { group: String },
+    /// This scheduler is read-only and never runs jobs
+    ReadOnly,
+    /// The last run failed and the job skips its runs until one succeeds
+    SkipsAfterFailure,
+    /// The last run of the upstream job failed, so the job skips its runs
+    UpstreamFailed { upstream: Uuid },
+    /// Due runs wait on approval
+    RequiresApproval,
+    /// A due run waits on approval since `due`
+    PendingApproval { due: DateTime<Utc> },
+    /// A run that cannot start within this many seconds of its tick is skipped
+    StartingDeadline { seconds: u64 },
+    /// A run waits while another job of the exclusive group runs
+    ExclusiveGroup { group: String },
+    /// A run due while the previous run of the job still runs is skipped or queued behind it
+    Concurrency { policy: ConcurrencyPolicy },
+    /// The runs that came due while the scheduler was down are skipped, or all run one by one
+    Misfire { policy: MisfirePolicy },
+    /// Runs are queued behind the earlier due runs of the jobs carrying the tag
+    Sequential { tag: String },
+    /// Runs go ahead only when the run condition of the job is met, `last_skip_reason` being why
+    /// the last run was skipped by it
+    RunCondition { last_skip_reason: Option<String> },
+}
+
+///
+/// Why a job runs next when it does, as returned by `JobScheduler::explain`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JobExplanation {
+    pub job_id: Uuid,
+    pub next_tick: Option<DateTime<Utc>>,
+    pub last_tick: Option<DateTime<Utc>>,
+    pub decisions: Vec<TimingDecision>,
+}
+
+pub(crate) async fn explain(
+    scheduler: &JobsSchedulerLocked,
+    job_id: Uuid,
+) -> Result<JobExplanation, JobSchedulerError> {
+    let context = scheduler.context.clone();
+    let mut storage = context.metadata_storage.write().await;
+    let job = storage
+        .get(job_id)
+        .await?
+        .ok_or(JobSchedulerError::FetchJob)?;
+    let next_tick = job.next_tick_utc();
+    let mut decisions = vec![];
+
+    match JobType::from_i32(job.job_type) {
+        Some(JobType::Cron) => {
+            if let Some(schedule) = job.schedule() {
+                decisions.push(TimingDecision::CronMatched {
+                    schedule: schedule.to_string(),
+                });
+            }
+            match LeapDayPolicy::from_i32(job.leap_day_policy) {
+                Some(LeapDayPolicy::LeapYearsOnly) | None => {}
+                Some(policy) => decisions.push(TimingDecision::LeapDay { policy }),
+            }
+            let exclusions = job.exclusions();
+            if !exclusions.is_empty() {
+                decisions.push(TimingDecision::Excluded {
+                    exclusions: exclusions.iter().map(|e| e.to_string()).collect(),
+                });
+            }
+            let seconds = job.jitter_seconds();
+            if seconds > 0 {
+                decisions.push(TimingDecision::Jitter { seconds });
+            }
+        }
+        Some(JobType::Repeated) => match job.repeated_every() {
+            Some(delay_seconds) if job.fixed_delay() => {
+                decisions.push(TimingDecision::FixedDelay { delay_seconds })
+            }
+            Some(every_seconds) => decisions.push(TimingDecision::Repeated { every_seconds }),
+            None => {}
+        },
+        Some(JobType::OneShot) => {
+            let instants = job.instants();
+            if instants.is_empty() {
+                decisions.push(TimingDecision::OneShot);
+            } else {
+                let remaining = instants
+                    .iter()
+                    .filter(|instant| next_tick.is_some_and(|next_tick| **instant > next_tick))
+                    .count();
+                decisions.push(TimingDecision::Instants { remaining });
+            }
+        }
+        Some(JobType::AfterJob) => {
+            if let (Some(upstream), Some(delay_seconds)) =
+                (job.after_job.as_ref(), job.repeated_every())
+            {
+                decisions.push(TimingDecision::AfterJob {
+                    upstream: upstream.into(),
+                    delay_seconds,
+                });
+            }
+        }
+        None => {}
+    }
+    if let Some(min_interval) = job.min_interval() {
+        decisions.push(TimingDecision::MinInterval {
+            seconds: min_interval.num_seconds() as u64,
+        });
+    }
+    if next_tick.is_none() && JobType::from_i32(job.job_type) != Some(JobType::AfterJob) {
+        decisions.push(TimingDecision::NoNextTick);
+    }
+
+    let job_hook = {
+        let r = context.reschedule_hooks.per_job.read().await;
+        r.contains_key(&job_id)
+    };
+    let global_hook = {
+        let r = context.reschedule_hooks.global.read().await;
+        r.is_some()
+    };
+    if job_hook || global_hook {
+        decisions.push(TimingDecision::Rescheduled {
+            job_hook,
+            global_hook,
+        });
+    }
+
+    if let Some(next_tick) = next_tick.as_ref() {
+        let windows = {
+            let r = scheduler.actors.scheduler.read().await;
+            r.maintenance_windows.clone()
+        };
+        if let Some(window) = windows.into_iter().find(|w| w.contains(next_tick)) {
+            let policy = MaintenanceWindowPolicy::from_i32(job.maintenance_window_policy)
+                .unwrap_or(MaintenanceWindowPolicy::Defer);
+            decisions.push(TimingDecision::InMaintenanceWindow { window, policy });
+        }
+    }
+
+    if job.group != context.group {
+        decisions.push(TimingDecision::OtherGroup {
+            group: job.group.clone(),
+        });
+    }
+    if context.read_only {
+        decisions.push(TimingDecision::ReadOnly);
+    }
+
+    if job.skip_if_last_failed && job.last_run_failed {
+        decisions.push(TimingDecision::SkipsAfterFailure);
+    }
+    if let Some(upstream) = job.skip_if_upstream_failed.as_ref() {
+        let upstream: Uuid = upstream.into();
+        if let Ok(Some(upstream_job)) = storage.get(upstream).await {
+            if upstream_job.last_run_failed {
+                decisions.push(TimingDecision::UpstreamFailed { upstream });
+            }
+        }
+    }
+
+    let pending = {
+        let r = context.pending_approvals.read().await;
+        r.get(&job_id).copied()
+    };
+    match pending {
+        Some(due) => decisions.push(TimingDecision::PendingApproval { due }),
+        None if job.requires_approval => decisions.push(TimingDecision::RequiresApproval),
+        None => {}
+    }
+
+    if let Some(seconds) = job.starting_deadline_seconds {
+        decisions.push(TimingDecision::StartingDeadline { seconds });
+    }
+
+    if let Some(group) = job.exclusive_group.clone() {
+        decisions.push(TimingDecision::ExclusiveGroup { group });
+    }
+
+    match ConcurrencyPolicy::from_i32(job.concurrency_policy) {
+        Some(ConcurrencyPolicy::Allow) | None => {}
+        Some(policy) => decisions.push(TimingDecision::Concurrency { policy }),
+    }
+
+    match MisfirePolicy::from_i32(job.misfire_policy) {
+        Some(MisfirePolicy::RunOnce) | None => {}
+        Some(policy) => decisions.push(TimingDecision::Misfire { policy }),
+    }
+
+    for tag in job.tags.iter() {
+        if context.sequential_tags.contains(tag) {
+            decisions.push(TimingDecision::Sequential { tag: tag.clone() });
+        }
+    }
+
+    let has_condition = {
+        let r = context.run_conditions.per_job.read().await;
+        r.contains_key(&job_id)
+    };
+    if has_condition {
+        let r = context.run_conditions.skip_reasons.read().await;
+        decisions.push(TimingDecision::RunCondition {
+            last_skip_reason: r.get(&job_id).cloned(),
+        });
+    }
+
+    Ok(JobExplanation {
+        job_id,
+        next_tick,
+        last_tick: job.last_tick_utc(),
+        decisions,
+    })
+}
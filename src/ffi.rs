@@ -0,0 +1,138 @@
+//! C ABI for embedding the scheduler in non-Rust hosts. The matching header is generated with
+//! cbindgen into `include/tokio_cron_scheduler.h`.
+
+use crate::blocking::BlockingScheduler;
+use crate::job::JobLocked;
+use std::ffi::{c_void, CStr};
+use std::os::raw::{c_char, c_int};
+use uuid::Uuid;
+
+/// The call succeeded
+pub const TCS_OK: c_int = 0;
+/// A pointer argument was null or a string was not valid UTF-8
+pub const TCS_INVALID_ARGUMENT: c_int = -1;
+/// The scheduler returned an error
+pub const TCS_SCHEDULER_ERROR: c_int = -2;
+
+/// Called with the id of the job and the user data it was added with
+pub type TcsJobCallback = extern "C" fn(job_id: *const u8, user_data: *mut c_void);
+
+struct UserData(*mut c_void);
+
+// The host is responsible for the user data being usable from the scheduler thread.
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+///
+/// Create a scheduler. Returns null when the scheduler could not be created.
+/// The scheduler must be released with `tcs_scheduler_shutdown`.
+#[no_mangle]
+pub extern "C" fn tcs_scheduler_new() -> *mut BlockingScheduler {
+    match BlockingScheduler::new() {
+        Ok(scheduler) => Box::into_raw(Box::new(scheduler)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+///
+/// Start running the jobs of the scheduler
+///
+/// # Safety
+/// `scheduler` must be a pointer returned by `tcs_scheduler_new` that was not shut down.
+#[no_mangle]
+pub unsafe extern "C" fn tcs_scheduler_start(scheduler: *mut BlockingScheduler) -> c_int {
+    let scheduler = match scheduler.as_ref() {
+        Some(scheduler) => scheduler,
+        None => return TCS_INVALID_ARGUMENT,
+    };
+    match scheduler.start() {
+        Ok(_) => TCS_OK,
+        Err(_) => TCS_SCHEDULER_ERROR,
+    }
+}
+
+///
+/// Add a cron job calling `callback` with `user_data` on every run. The 16 bytes of the job id
+/// are written to `job_id_out`. The callback is called from the scheduler thread.
+///
+/// # Safety
+/// `scheduler` must be a live scheduler, `schedule` a NUL terminated string and `job_id_out`
+/// must point to 16 writable bytes. `user_data` must stay valid until the job is removed.
+#[no_mangle]
+pub unsafe extern "C" fn tcs_scheduler_add_cron_job(
+    scheduler: *mut BlockingScheduler,
+    schedule: *const c_char,
+    callback: TcsJobCallback,
+    user_data: *mut c_void,
+    job_id_out: *mut u8,
+) -> c_int {
+    let scheduler = match scheduler.as_ref() {
+        Some(scheduler) => scheduler,
+        None => return TCS_INVALID_ARGUMENT,
+    };
+    if schedule.is_null() || job_id_out.is_null() {
+        return TCS_INVALID_ARGUMENT;
+    }
+    let schedule = match CStr::from_ptr(schedule).to_str() {
+        Ok(schedule) => schedule,
+        Err(_) => return TCS_INVALID_ARGUMENT,
+    };
+    let user_data = UserData(user_data);
+    let job = JobLocked::new(schedule, move |job_id, _| {
+        let user_data = &user_data;
+        callback(job_id.as_bytes().as_ptr(), user_data.0);
+    });
+    let job = match job {
+        Ok(job) => job,
+        Err(_) => return TCS_INVALID_ARGUMENT,
+    };
+    match scheduler.add(job) {
+        Ok(job_id) => {
+            std::ptr::copy_nonoverlapping(job_id.as_bytes().as_ptr(), job_id_out, 16);
+            TCS_OK
+        }
+        Err(_) => TCS_SCHEDULER_ERROR,
+    }
+}
+
+///
+/// Remove a job from the scheduler
+///
+/// # Safety
+/// `scheduler` must be a live scheduler and `job_id` must point to the 16 bytes of a job id.
+#[no_mangle]
+pub unsafe extern "C" fn tcs_scheduler_remove(
+    scheduler: *mut BlockingScheduler,
+    job_id: *const u8,
+) -> c_int {
+    let scheduler = match scheduler.as_ref() {
+        Some(scheduler) => scheduler,
+        None => return TCS_INVALID_ARGUMENT,
+    };
+    if job_id.is_null() {
+        return TCS_INVALID_ARGUMENT;
+    }
+    let mut bytes = [0u8; 16];
+    std::ptr::copy_nonoverlapping(job_id, bytes.as_mut_ptr(), 16);
+    match scheduler.remove(&Uuid::from_bytes(bytes)) {
+        Ok(()) => TCS_OK,
+        Err(_) => TCS_SCHEDULER_ERROR,
+    }
+}
+
+///
+/// Shut the scheduler down and release it. The pointer must not be used afterwards.
+///
+/// # Safety
+/// `scheduler` must be a pointer returned by `tcs_scheduler_new` that was not shut down.
+#[no_mangle]
+pub unsafe extern "C" fn tcs_scheduler_shutdown(scheduler: *mut BlockingScheduler) -> c_int {
+    if scheduler.is_null() {
+        return TCS_INVALID_ARGUMENT;
+    }
+    let scheduler = Box::from_raw(scheduler);
+    match scheduler.shutdown() {
+        Ok(()) => TCS_OK,
+        Err(_) => TCS_SCHEDULER_ERROR,
+    }
+}
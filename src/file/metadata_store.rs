@@ -0,0 +1,223 @@
+use crate::file::{blocking, default_dir, file_path, list, read, read_all, remove, write_atomic};
+use crate::store::{Codec, DataStore, InitStore, JsonCodec, MetaDataStorage};
+use crate::{JobAndNextTick, JobSchedulerError, JobStoredData};
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+///
+/// A metadata store keeping every job in a file of its own, in the `jobs` directory under `dir`,
+/// encoded by the codec. Files are replaced by renaming a new one over them, so a job is never
+/// read half written.
+#[derive(Clone)]
+pub struct FileMetadataStore {
+    pub dir: PathBuf,
+    /// How the jobs are encoded in the files, JSON unless set otherwise
+    pub codec: Arc<dyn Codec>,
+    /// Keeps the ticks of a job from being updated by two tasks at once
+    lock: Arc<Mutex<()>>,
+}
+
+impl Default for FileMetadataStore {
+    fn default() -> Self {
+        Self::new(default_dir())
+    }
+}
+
+impl FileMetadataStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            codec: Arc::new(JsonCodec),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    ///
+    /// Encode the jobs with `codec` instead of JSON, e.g. the `CborCodec`
+    pub fn codec(mut self, codec: impl Codec + 'static) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+
+    fn jobs_dir(&self) -> PathBuf {
+        self.dir.join("jobs")
+    }
+
+    fn all_jobs(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobStoredData>, JobSchedulerError>> + Send>> {
+        let dir = self.jobs_dir();
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let extension = codec.extension();
+            let files = blocking(
+                move || read_all(&dir, extension),
+                JobSchedulerError::CantListNextTicks,
+            )
+            .await?;
+            Ok(files
+                .iter()
+                .filter_map(|bytes| match codec.decode_job(bytes) {
+                    Ok(job) => Some(job),
+                    Err(e) => {
+                        error!("Error decoding job file {:?}", e);
+                        None
+                    }
+                })
+                .collect())
+        })
+    }
+}
+
+impl DataStore<JobStoredData> for FileMetadataStore {
+    fn get(
+        &mut self,
+        id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<JobStoredData>, JobSchedulerError>> + Send>>
+    {
+        let path = file_path(&self.jobs_dir(), id, self.codec.extension());
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let bytes = blocking(move || read(&path), JobSchedulerError::GetJobData).await?;
+            bytes.map(|bytes| codec.decode_job(&bytes)).transpose()
+        })
+    }
+
+    fn add_or_update(
+        &mut self,
+        data: JobStoredData,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let dir = self.jobs_dir();
+        let codec = self.codec.clone();
+        let lock = self.lock.clone();
+        Box::pin(async move {
+            let id: Uuid = match data.id.as_ref() {
+                Some(id) => id.into(),
+                None => return Err(JobSchedulerError::CantAdd),
+            };
+            let path = file_path(&dir, id, codec.extension());
+            let bytes = codec.encode_job(&data)?;
+            let _lock = lock.lock().await;
+            blocking(
+                move || write_atomic(&path, &bytes),
+                JobSchedulerError::CantAdd,
+            )
+            .await
+        })
+    }
+
+    fn delete(
+        &mut self,
+        guid: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let path = file_path(&self.jobs_dir(), guid, self.codec.extension());
+        let lock = self.lock.clone();
+        Box::pin(async move {
+            let _lock = lock.lock().await;
+            blocking(move || remove(&path), JobSchedulerError::CantRemove).await
+        })
+    }
+}
+
+impl InitStore for FileMetadataStore {
+    fn init(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let dir = self.jobs_dir();
+        Box::pin(blocking(
+            move || std::fs::create_dir_all(dir),
+            JobSchedulerError::CantInit,
+        ))
+    }
+
+    fn inited(&mut self) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let dir = self.jobs_dir();
+        Box::pin(async move { Ok(dir.is_dir()) })
+    }
+}
+
+impl MetaDataStorage for FileMetadataStore {
+    fn list_next_ticks(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobAndNextTick>, JobSchedulerError>> + Send>> {
+        let jobs = self.all_jobs();
+        Box::pin(async move {
+            let jobs = jobs.await?;
+            Ok(jobs
+                .into_iter()
+                .map(|jd| JobAndNextTick {
+                    id: jd.id,
+                    job_type: jd.job_type,
+                    next_tick: jd.next_tick,
+                    last_tick: jd.last_tick,
+                    group: jd.group,
+                })
+                .collect())
+        })
+    }
+
+    fn set_next_and_last_tick(
+        &mut self,
+        guid: Uuid,
+        next_tick: Option<DateTime<Utc>>,
+        last_tick: Option<DateTime<Utc>>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let path = file_path(&self.jobs_dir(), guid, self.codec.extension());
+        let codec = self.codec.clone();
+        let lock = self.lock.clone();
+        Box::pin(async move {
+            let _lock = lock.lock().await;
+            let read_path = path.clone();
+            let bytes =
+                blocking(move || read(&read_path), JobSchedulerError::UpdateJobData).await?;
+            let mut data = match bytes {
+                Some(bytes) => codec.decode_job(&bytes)?,
+                None => {
+                    error!("Could not find job {:?} to update", guid);
+                    return Err(JobSchedulerError::UpdateJobData);
+                }
+            };
+            data.next_tick = next_tick.map(|t| t.timestamp()).unwrap_or(0) as u64;
+            data.last_tick = last_tick.map(|t| t.timestamp() as u64);
+            let bytes = codec.encode_job(&data)?;
+            blocking(
+                move || write_atomic(&path, &bytes),
+                JobSchedulerError::UpdateJobData,
+            )
+            .await
+        })
+    }
+
+    fn time_till_next_job(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<Duration>, JobSchedulerError>> + Send>> {
+        let jobs = self.all_jobs();
+        Box::pin(async move {
+            let jobs = jobs
+                .await
+                .map_err(|_| JobSchedulerError::CouldNotGetTimeUntilNextTick)?;
+            let now = Utc::now().timestamp() as u64;
+            Ok(jobs
+                .iter()
+                .map(|jd| jd.next_tick)
+                .filter(|t| *t > now)
+                .min()
+                .map(|t| Duration::from_secs(t - now)))
+        })
+    }
+
+    fn list_job_ids(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Uuid>, JobSchedulerError>> + Send>> {
+        let dir = self.jobs_dir();
+        let extension = self.codec.extension();
+        Box::pin(blocking(
+            move || list(&dir, extension),
+            JobSchedulerError::CantListGuids,
+        ))
+    }
+}
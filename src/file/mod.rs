@@ -0,0 +1,100 @@
+mod metadata_store;
+mod notification_store;
+
+use crate::JobSchedulerError;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use uuid::Uuid;
+
+pub use metadata_store::FileMetadataStore;
+pub use notification_store::FileNotificationStore;
+
+///
+/// The directory the file stores keep their files in, `FILE_STORAGE_PATH` or
+/// `tokio_cron_scheduler` when it isn't set
+fn default_dir() -> PathBuf {
+    std::env::var("FILE_STORAGE_PATH")
+        .unwrap_or_else(|_| "tokio_cron_scheduler".to_string())
+        .into()
+}
+
+///
+/// Run a file operation on the blocking threads, failing with `error` when it fails
+async fn blocking<T, F>(f: F, error: JobSchedulerError) -> Result<T, JobSchedulerError>
+where
+    F: FnOnce() -> std::io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(Ok(val)) => Ok(val),
+        Ok(Err(e)) => {
+            error!("Error accessing storage file {:?}", e);
+            Err(error)
+        }
+        Err(e) => {
+            error!("Error waiting on storage file {:?}", e);
+            Err(error)
+        }
+    }
+}
+
+fn file_path(dir: &Path, id: Uuid, extension: &str) -> PathBuf {
+    dir.join(id.to_string()).with_extension(extension)
+}
+
+///
+/// Write `bytes` to a file of their own next to `path` first, and rename it over `path`, so the
+/// file is never seen half written, even when the process dies while writing
+fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension(format!("{}.tmp", Uuid::new_v4()));
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+fn read(path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn remove(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        removed => removed,
+    }
+}
+
+///
+/// The ids of the files in `dir` with the extension, leaving out those still being written
+fn list(dir: &Path, extension: &str) -> std::io::Result<Vec<Uuid>> {
+    let mut ids = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(extension) {
+            continue;
+        }
+        if let Some(id) = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| Uuid::from_str(s).ok())
+        {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+///
+/// The contents of the files in `dir` with the extension
+fn read_all(dir: &Path, extension: &str) -> std::io::Result<Vec<Vec<u8>>> {
+    let mut files = vec![];
+    for id in list(dir, extension)? {
+        // A file deleted since it was listed is left out
+        files.extend(read(&file_path(dir, id, extension))?);
+    }
+    Ok(files)
+}
@@ -0,0 +1,227 @@
+use crate::file::{blocking, default_dir, file_path, read, read_all, remove, write_atomic};
+use crate::job::job_data_prost::{JobState, NotificationData};
+use crate::job::{JobId, NotificationId};
+use crate::store::{Codec, DataStore, InitStore, JsonCodec, NotificationStore};
+use crate::JobSchedulerError;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+///
+/// A notification store keeping every notification in a file of its own, in the
+/// `notifications` directory under `dir`, encoded by the codec
+#[derive(Clone)]
+pub struct FileNotificationStore {
+    pub dir: PathBuf,
+    /// How the notifications are encoded in the files, JSON unless set otherwise
+    pub codec: Arc<dyn Codec>,
+    /// Keeps a notification from being changed by two tasks at once
+    lock: Arc<Mutex<()>>,
+}
+
+impl Default for FileNotificationStore {
+    fn default() -> Self {
+        Self::new(default_dir())
+    }
+}
+
+impl FileNotificationStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            codec: Arc::new(JsonCodec),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    ///
+    /// Encode the notifications with `codec` instead of JSON, e.g. the `CborCodec`
+    pub fn codec(mut self, codec: impl Codec + 'static) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+
+    fn notifications_dir(&self) -> PathBuf {
+        self.dir.join("notifications")
+    }
+}
+
+///
+/// The notifications of the job in `dir`, with their ids
+async fn for_job(
+    dir: PathBuf,
+    codec: Arc<dyn Codec>,
+    job_id: Uuid,
+) -> Result<Vec<(Uuid, NotificationData)>, JobSchedulerError> {
+    let extension = codec.extension();
+    let files = blocking(
+        move || read_all(&dir, extension),
+        JobSchedulerError::CantListGuids,
+    )
+    .await?;
+    Ok(files
+        .iter()
+        .filter_map(|bytes| codec.decode_notification(bytes).ok())
+        .filter_map(|data| match data.job_id_and_notification_id_from_data() {
+            Some((job, notification_id)) if job == job_id => Some((notification_id, data)),
+            _ => None,
+        })
+        .collect())
+}
+
+impl DataStore<NotificationData> for FileNotificationStore {
+    fn get(
+        &mut self,
+        id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<NotificationData>, JobSchedulerError>> + Send>>
+    {
+        let path = file_path(&self.notifications_dir(), id, self.codec.extension());
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let bytes = blocking(move || read(&path), JobSchedulerError::GetJobData).await?;
+            bytes
+                .map(|bytes| codec.decode_notification(&bytes))
+                .transpose()
+        })
+    }
+
+    fn add_or_update(
+        &mut self,
+        data: NotificationData,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let dir = self.notifications_dir();
+        let codec = self.codec.clone();
+        let lock = self.lock.clone();
+        Box::pin(async move {
+            let id = match data.job_id_and_notification_id_from_data() {
+                Some((_, notification_id)) => notification_id,
+                None => return Err(JobSchedulerError::UpdateJobData),
+            };
+            let path = file_path(&dir, id, codec.extension());
+            let bytes = codec.encode_notification(&data)?;
+            let _lock = lock.lock().await;
+            blocking(
+                move || write_atomic(&path, &bytes),
+                JobSchedulerError::UpdateJobData,
+            )
+            .await
+        })
+    }
+
+    fn delete(
+        &mut self,
+        guid: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let path = file_path(&self.notifications_dir(), guid, self.codec.extension());
+        let lock = self.lock.clone();
+        Box::pin(async move {
+            let _lock = lock.lock().await;
+            blocking(move || remove(&path), JobSchedulerError::CantRemove).await
+        })
+    }
+}
+
+impl InitStore for FileNotificationStore {
+    fn init(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let dir = self.notifications_dir();
+        Box::pin(blocking(
+            move || std::fs::create_dir_all(dir),
+            JobSchedulerError::CantInit,
+        ))
+    }
+
+    fn inited(&mut self) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let dir = self.notifications_dir();
+        Box::pin(async move { Ok(dir.is_dir()) })
+    }
+}
+
+impl NotificationStore for FileNotificationStore {
+    fn list_notification_guids_for_job_and_state(
+        &mut self,
+        job: JobId,
+        state: JobState,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<NotificationId>, JobSchedulerError>> + Send>> {
+        let notifications = for_job(self.notifications_dir(), self.codec.clone(), job);
+        Box::pin(async move {
+            Ok(notifications
+                .await?
+                .into_iter()
+                .filter(|(_, data)| data.job_states.contains(&(state as i32)))
+                .map(|(id, _)| id)
+                .collect())
+        })
+    }
+
+    fn list_notification_guids_for_job_id(
+        &mut self,
+        job_id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Uuid>, JobSchedulerError>> + Send>> {
+        let notifications = for_job(self.notifications_dir(), self.codec.clone(), job_id);
+        Box::pin(async move { Ok(notifications.await?.into_iter().map(|(id, _)| id).collect()) })
+    }
+
+    fn delete_notification_for_state(
+        &mut self,
+        notification_id: Uuid,
+        state: JobState,
+    ) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let path = file_path(
+            &self.notifications_dir(),
+            notification_id,
+            self.codec.extension(),
+        );
+        let codec = self.codec.clone();
+        let lock = self.lock.clone();
+        Box::pin(async move {
+            let _lock = lock.lock().await;
+            let read_path = path.clone();
+            let bytes = blocking(move || read(&read_path), JobSchedulerError::CantRemove).await?;
+            let mut data = match bytes {
+                Some(bytes) => codec.decode_notification(&bytes)?,
+                None => return Ok(false),
+            };
+            let state = state as i32;
+            if !data.job_states.contains(&state) {
+                return Ok(false);
+            }
+            data.job_states.retain(|s| *s != state);
+            let bytes = codec.encode_notification(&data)?;
+            blocking(
+                move || write_atomic(&path, &bytes),
+                JobSchedulerError::CantRemove,
+            )
+            .await?;
+            Ok(true)
+        })
+    }
+
+    fn delete_for_job(
+        &mut self,
+        job_id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let notifications = for_job(self.notifications_dir(), self.codec.clone(), job_id);
+        let dir = self.notifications_dir();
+        let extension = self.codec.extension();
+        let lock = self.lock.clone();
+        Box::pin(async move {
+            let notifications = notifications
+                .await
+                .map_err(|_| JobSchedulerError::CantRemove)?;
+            let _lock = lock.lock().await;
+            blocking(
+                move || {
+                    for (id, _) in notifications {
+                        remove(&file_path(&dir, id, extension))?;
+                    }
+                    Ok(())
+                },
+                JobSchedulerError::CantRemove,
+            )
+            .await
+        })
+    }
+}
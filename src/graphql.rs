@@ -0,0 +1,412 @@
+//! A GraphQL schema over a scheduler, for dashboards to list the jobs, runs and dead letters,
+//! act on them, and follow the state changes of the jobs live. Serve it with the async-graphql
+//! integration of the web framework at hand, e.g. `async-graphql-axum`.
+//!
+//! The schema checks the role of the caller with an authenticator from the `Credentials` the web
+//! framework adds to the data of each request: viewers query and subscribe, operators also act
+//! on the runs and dead letters, and admins also edit the schedule. Without an authenticator
+//! every caller is denied; `AllowAnonymousAdmin` lets any caller do anything instead. The
+//! `RateLimit` extension limits the requests of each client, told apart by their credentials.
+
+use crate::auth::{authorize, Authenticator, RateLimiter, Role};
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::JobState;
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::JobState;
+use crate::job_scheduler::{JobInfo, JobsSchedulerLocked, PendingApproval, RunningExecution};
+use crate::redrive::{DeadLetter, DeadLetterQuery, DeadLetterReason};
+use crate::store::{JobListState, JobQuery, JobSort};
+use crate::JobSchedulerError;
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest,
+};
+use async_graphql::futures_util::stream::{self, Stream};
+use async_graphql::{
+    Context, Guard, Object, Request, Result, Schema, SchemaBuilder, ServerError, ServerResult,
+    SimpleObject, Subscription,
+};
+use chrono::{DateTime, Utc};
+use std::any::TypeId;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+pub type SchedulerSchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
+
+///
+/// The schema over `scheduler`, denying every caller until an authenticator is added, e.g. with
+/// `schema_with_authenticator`. Built on a read-only scheduler the mutations all fail.
+pub fn schema(scheduler: JobsSchedulerLocked) -> SchedulerSchema {
+    schema_builder(scheduler).finish()
+}
+
+///
+/// The schema over `scheduler`, checking the role of each caller with `authenticator`
+pub fn schema_with_authenticator(
+    scheduler: JobsSchedulerLocked,
+    authenticator: Arc<dyn Authenticator>,
+) -> SchedulerSchema {
+    schema_builder(scheduler).data(authenticator).finish()
+}
+
+///
+/// The builder of the schema over `scheduler`, to add an authenticator as data, as
+/// `Arc<dyn Authenticator>`, extensions like `RateLimit`, or limits on the queries
+pub fn schema_builder(
+    scheduler: JobsSchedulerLocked,
+) -> SchemaBuilder<QueryRoot, MutationRoot, SubscriptionRoot> {
+    Schema::build(
+        QueryRoot {
+            scheduler: scheduler.clone(),
+        },
+        MutationRoot {
+            scheduler: scheduler.clone(),
+        },
+        SubscriptionRoot { scheduler },
+    )
+}
+
+///
+/// The credentials of a request, like its bearer token, for the web framework to add to the
+/// request data
+pub struct Credentials(pub String);
+
+///
+/// The role of the caller, when it has at least the `required` one. No caller has any role when
+/// the schema has no authenticator.
+async fn role(ctx: &Context<'_>, required: Role) -> Result<Role> {
+    let authenticator = match ctx.data_opt::<Arc<dyn Authenticator>>() {
+        Some(authenticator) => authenticator,
+        None => return Err(JobSchedulerError::Forbidden(required).into()),
+    };
+    let credentials = ctx
+        .data_opt::<Credentials>()
+        .map(|c| c.0.as_str())
+        .unwrap_or_default();
+    Ok(authorize(authenticator.as_ref(), credentials, required).await?)
+}
+
+struct RoleGuard(Role);
+
+///
+/// An extension limiting the requests and subscriptions of each client, told apart by their
+/// `Credentials`, with a `RateLimiter`. The requests without credentials share one limit.
+pub struct RateLimit(pub RateLimiter);
+
+impl ExtensionFactory for RateLimit {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(RateLimit(self.0.clone()))
+    }
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for RateLimit {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        let client = request
+            .data
+            .get(&TypeId::of::<Credentials>())
+            .and_then(|credentials| credentials.downcast_ref::<Credentials>())
+            .map(|credentials| credentials.0.as_str())
+            .unwrap_or_default();
+        self.0
+            .check(client)
+            .map_err(|e| ServerError::new(e.to_string(), None))?;
+        next.run(ctx, request).await
+    }
+}
+
+impl Guard for RoleGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        role(ctx, self.0).await.map(|_| ())
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct JobPage {
+    pub items: Vec<JobInfo>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(SimpleObject)]
+pub struct DeadLetterPage {
+    pub items: Vec<DeadLetter>,
+    pub next_cursor: Option<String>,
+}
+
+///
+/// A job changing state, e.g. a run starting or being done
+#[derive(Clone, Copy, SimpleObject)]
+pub struct JobStateChange {
+    pub job_id: Uuid,
+    pub state: JobState,
+}
+
+pub struct QueryRoot {
+    scheduler: JobsSchedulerLocked,
+}
+
+#[Object]
+impl QueryRoot {
+    /// The role of the caller, for a dashboard to only offer what it may do
+    async fn role(&self, ctx: &Context<'_>) -> Result<Role> {
+        role(ctx, Role::Viewer).await
+    }
+
+    /// A page of the stored jobs, of all groups
+    #[graphql(guard = "RoleGuard(Role::Viewer)")]
+    #[allow(clippy::too_many_arguments)]
+    async fn jobs(
+        &self,
+        #[graphql(default)] tags: Vec<String>,
+        group: Option<String>,
+        state: Option<JobListState>,
+        #[graphql(default)] sort: JobSort,
+        #[graphql(default)] descending: bool,
+        after: Option<String>,
+        #[graphql(default = 100)] first: usize,
+    ) -> Result<JobPage> {
+        let query = JobQuery {
+            tags,
+            group,
+            state,
+            sort,
+            descending,
+            cursor: after,
+            ..JobQuery::new()
+        }
+        .limit(first);
+        let page = self.scheduler.query_jobs(query).await?;
+        Ok(JobPage {
+            items: page.items,
+            next_cursor: page.next_cursor,
+        })
+    }
+
+    #[graphql(guard = "RoleGuard(Role::Viewer)")]
+    async fn job(&self, id: Uuid) -> Result<Option<JobInfo>> {
+        let mut storage = self.scheduler.context.metadata_storage.write().await;
+        let job = storage.get(id).await?;
+        Ok(job.map(|job| JobInfo::new(id, job, &self.scheduler.context.display_timezones)))
+    }
+
+    /// The runs in progress in this scheduler process
+    #[graphql(guard = "RoleGuard(Role::Viewer)")]
+    async fn runs(&self) -> Vec<RunningExecution> {
+        self.scheduler.running_executions().await
+    }
+
+    #[graphql(guard = "RoleGuard(Role::Viewer)")]
+    async fn pending_approvals(&self) -> Vec<PendingApproval> {
+        self.scheduler.pending_approvals().await
+    }
+
+    /// A page of the dead letters, oldest first
+    #[graphql(guard = "RoleGuard(Role::Viewer)")]
+    async fn dead_letters(
+        &self,
+        job_id: Option<Uuid>,
+        reason: Option<DeadLetterReason>,
+        resolved: Option<bool>,
+        after: Option<String>,
+        #[graphql(default = 100)] first: usize,
+    ) -> DeadLetterPage {
+        let mut query = DeadLetterQuery::new().limit(first);
+        if let Some(job_id) = job_id {
+            query = query.job(job_id);
+        }
+        if let Some(reason) = reason {
+            query = query.reason(reason);
+        }
+        if let Some(resolved) = resolved {
+            query = query.resolved(resolved);
+        }
+        if let Some(after) = after {
+            query = query.after(after);
+        }
+        let page = self.scheduler.query_dead_letters(query).await;
+        DeadLetterPage {
+            items: page.items,
+            next_cursor: page.next_cursor,
+        }
+    }
+}
+
+pub struct MutationRoot {
+    scheduler: JobsSchedulerLocked,
+}
+
+#[Object]
+impl MutationRoot {
+    /// Run a job right away, outside of its schedule
+    #[graphql(guard = "RoleGuard(Role::Operator)")]
+    async fn trigger(&self, job_id: Uuid) -> Result<bool> {
+        self.scheduler.trigger(&job_id).await?;
+        Ok(true)
+    }
+
+    /// Run a job right away as of an earlier time
+    #[graphql(guard = "RoleGuard(Role::Operator)")]
+    async fn trigger_as_of(&self, job_id: Uuid, scheduled_time: DateTime<Utc>) -> Result<bool> {
+        self.scheduler
+            .trigger_as_of(&job_id, scheduled_time)
+            .await?;
+        Ok(true)
+    }
+
+    #[graphql(guard = "RoleGuard(Role::Operator)")]
+    async fn cancel_run(&self, execution_id: Uuid) -> Result<bool> {
+        self.scheduler.cancel_run(&execution_id).await?;
+        Ok(true)
+    }
+
+    #[graphql(guard = "RoleGuard(Role::Operator)")]
+    async fn approve(&self, job_id: Uuid) -> Result<bool> {
+        self.scheduler.approve(&job_id).await?;
+        Ok(true)
+    }
+
+    #[graphql(guard = "RoleGuard(Role::Operator)")]
+    async fn reject(&self, job_id: Uuid) -> Result<bool> {
+        self.scheduler.reject(&job_id).await?;
+        Ok(true)
+    }
+
+    #[graphql(guard = "RoleGuard(Role::Operator)")]
+    async fn annotate_dead_letter(&self, id: Uuid, annotation: String) -> Result<DeadLetter> {
+        Ok(self.scheduler.annotate_dead_letter(&id, annotation).await?)
+    }
+
+    #[graphql(guard = "RoleGuard(Role::Operator)")]
+    async fn resolve_dead_letter(&self, id: Uuid, annotation: String) -> Result<DeadLetter> {
+        Ok(self.scheduler.resolve_dead_letter(&id, annotation).await?)
+    }
+
+    /// Remove a job from the schedule
+    #[graphql(guard = "RoleGuard(Role::Admin)")]
+    async fn remove_job(&self, job_id: Uuid) -> Result<bool> {
+        self.scheduler.remove(&job_id).await?;
+        Ok(true)
+    }
+}
+
+pub struct SubscriptionRoot {
+    scheduler: JobsSchedulerLocked,
+}
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// The state changes of the jobs run by this scheduler process, of one job when given
+    #[graphql(guard = "RoleGuard(Role::Viewer)")]
+    async fn job_states(&self, job_id: Option<Uuid>) -> impl Stream<Item = JobStateChange> {
+        let rx = self.scheduler.subscribe_events();
+        stream::unfold(rx, move |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok((id, state)) if job_id.is_none_or(|job_id| job_id == id) => {
+                        return Some((JobStateChange { job_id: id, state }, rx));
+                    }
+                    // A slow subscriber skips the changes it missed
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        schema, schema_builder, schema_with_authenticator, Credentials, RateLimit, SchedulerSchema,
+    };
+    use crate::auth::{Authenticator, RateLimiter, Role, StaticTokens};
+    use crate::{Job, JobScheduler};
+    use async_graphql::Request;
+    use std::sync::Arc;
+
+    /// The errors of `query` asked with `token`, empty when it was allowed
+    async fn errors(schema: &SchedulerSchema, token: &str, query: &str) -> Vec<String> {
+        let request = Request::new(query).data(Credentials(token.to_string()));
+        let response = schema.execute(request).await;
+        response.errors.into_iter().map(|e| e.message).collect()
+    }
+
+    #[tokio::test]
+    async fn denied_without_authenticator() {
+        let sched = JobScheduler::new().await.expect("scheduler");
+        let schema = schema(sched);
+        assert_eq!(
+            errors(&schema, "", "{ runs { __typename } }").await,
+            vec!["Forbidden(Viewer)"]
+        );
+        assert_eq!(
+            errors(&schema, "", "{ role }").await,
+            vec!["Forbidden(Viewer)"]
+        );
+    }
+
+    #[tokio::test]
+    async fn role_checks() {
+        let sched = JobScheduler::new().await.expect("scheduler");
+        let job_id = sched
+            .add(Job::new("0 0 0 1 1 *", |_, _| {}).expect("job"))
+            .await
+            .expect("add");
+        let tokens = StaticTokens::new()
+            .token("viewer-token", Role::Viewer)
+            .token("operator-token", Role::Operator)
+            .token("admin-token", Role::Admin);
+        let schema = schema_with_authenticator(sched, Arc::new(tokens));
+        let reject = format!("mutation {{ reject(jobId: \"{}\") }}", job_id);
+        let remove = format!("mutation {{ removeJob(jobId: \"{}\") }}", job_id);
+
+        assert!(errors(&schema, "viewer-token", "{ runs { __typename } }")
+            .await
+            .is_empty());
+        assert_eq!(
+            errors(&schema, "unknown", "{ runs { __typename } }").await,
+            vec!["Forbidden(Viewer)"]
+        );
+        assert_eq!(
+            errors(&schema, "viewer-token", &reject).await,
+            vec!["Forbidden(Operator)"]
+        );
+        assert_ne!(
+            errors(&schema, "operator-token", &reject).await,
+            vec!["Forbidden(Operator)"]
+        );
+        assert_eq!(
+            errors(&schema, "operator-token", &remove).await,
+            vec!["Forbidden(Admin)"]
+        );
+        assert!(errors(&schema, "admin-token", &remove).await.is_empty());
+    }
+
+    /// Each client is limited on its own once over the burst of the limiter
+    #[tokio::test]
+    async fn rate_limited_clients() {
+        let sched = JobScheduler::new().await.expect("scheduler");
+        let tokens: Arc<dyn Authenticator> = Arc::new(
+            StaticTokens::new()
+                .token("viewer-token", Role::Viewer)
+                .token("other-token", Role::Viewer),
+        );
+        let schema = schema_builder(sched)
+            .data(tokens)
+            .extension(RateLimit(RateLimiter::new(1, 2)))
+            .finish();
+
+        assert!(errors(&schema, "viewer-token", "{ role }").await.is_empty());
+        assert!(errors(&schema, "viewer-token", "{ role }").await.is_empty());
+        assert_eq!(
+            errors(&schema, "viewer-token", "{ role }").await,
+            vec!["RateLimited"]
+        );
+        assert!(errors(&schema, "other-token", "{ role }").await.is_empty());
+    }
+}
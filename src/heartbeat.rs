@@ -0,0 +1,97 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::JobState;
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::JobState;
+use crate::job::{JobId, JobLocked};
+use crate::job_scheduler::JobsSchedulerLocked;
+use crate::notification::NotificationCreator;
+use crate::JobSchedulerError;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+///
+/// What a heartbeat reports to its sink
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Beat {
+    /// The scheduler is alive, sent on the schedule of the heartbeat
+    Alive,
+    /// A watched job completed a run
+    Completed { job_id: JobId },
+    /// A run of a watched job failed
+    Failed { job_id: JobId },
+}
+
+///
+/// The sink of a heartbeat, e.g. pinging the check URL of an external dead man's switch monitor
+pub type OnHeartbeat = dyn FnMut(Beat) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync;
+
+///
+/// A job reporting that the scheduler is alive to an external monitor on a schedule, along with
+/// the completed and failed runs of the jobs it watches, so that the monitor alerts when the
+/// reports stop coming.
+pub struct Heartbeat {
+    schedule: String,
+    watched: Vec<JobId>,
+    sink: Arc<Mutex<Box<OnHeartbeat>>>,
+}
+
+impl Heartbeat {
+    pub fn new(schedule: impl Into<String>, sink: Box<OnHeartbeat>) -> Self {
+        Self {
+            schedule: schedule.into(),
+            watched: vec![],
+            sink: Arc::new(Mutex::new(sink)),
+        }
+    }
+
+    ///
+    /// Also report when a run of the job completes or fails
+    pub fn watch(mut self, job_id: JobId) -> Self {
+        self.watched.push(job_id);
+        self
+    }
+
+    ///
+    /// Add the heartbeat job and the notifications on the watched jobs to the scheduler. Returns
+    /// the id of the heartbeat job.
+    pub async fn register(
+        self,
+        scheduler: &JobsSchedulerLocked,
+    ) -> Result<Uuid, JobSchedulerError> {
+        let sink = self.sink.clone();
+        let job = JobLocked::new_async(self.schedule.as_str(), move |_, _| {
+            let sink = sink.clone();
+            Box::pin(async move {
+                let mut sink = sink.lock().await;
+                (sink)(Beat::Alive).await;
+            })
+        })?;
+        let heartbeat_id = scheduler.add(job).await?;
+
+        let context = scheduler.context();
+        for job_id in self.watched {
+            let sink = self.sink.clone();
+            NotificationCreator::add(
+                &context,
+                Box::new(move |job_id, _, state| {
+                    let sink = sink.clone();
+                    Box::pin(async move {
+                        let beat = match state {
+                            JobState::Failed => Beat::Failed { job_id },
+                            _ => Beat::Completed { job_id },
+                        };
+                        let mut sink = sink.lock().await;
+                        (sink)(beat).await;
+                    })
+                }),
+                vec![JobState::Done, JobState::Failed],
+                &job_id,
+            )
+            .await?;
+        }
+        Ok(heartbeat_id)
+    }
+}
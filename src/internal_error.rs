@@ -0,0 +1,133 @@
+use crate::JobSchedulerError;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+///
+/// Where an internal error of the scheduler happened
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InternalErrorKind {
+    /// The tick loop could not list, move on or fire the due jobs
+    Tick,
+    /// A store failed to read or write the data of a job
+    Storage,
+    /// A message could not be sent between the parts of the scheduler
+    Channel,
+    /// A part of the scheduler fell behind on a channel and missed this many messages
+    ChannelOverflow { missed: u64 },
+    /// The outbox could not be read or written, or an entry of it could not be delivered
+    Outbox,
+    /// The leader election could not be reached, so the scheduler doesn't fire jobs until it is
+    LeaderElection,
+    /// The run lock of a due job could not be taken, so the scheduler left the run out
+    RunLock,
+    /// The record of a run could not be written to the run history
+    RunHistory,
+}
+
+///
+/// An error the scheduler ran into while running jobs, away from any call made by the user. The
+/// scheduler logs it and carries on.
+#[derive(Clone, Debug)]
+pub struct InternalError {
+    pub kind: InternalErrorKind,
+    pub job_id: Option<Uuid>,
+    pub message: String,
+    pub source: Option<JobSchedulerError>,
+}
+
+///
+/// Called for every internal error of the scheduler, e.g. to count them in metrics or alert on
+/// them
+pub type OnInternalError = dyn Fn(InternalError) + Send + Sync;
+
+///
+/// Reports the internal errors of a scheduler to the log and to its handler
+#[derive(Clone, Default)]
+pub struct InternalErrors {
+    pub handler: Arc<RwLock<Option<Arc<OnInternalError>>>>,
+}
+
+impl InternalErrors {
+    pub async fn report(
+        &self,
+        kind: InternalErrorKind,
+        job_id: Option<Uuid>,
+        message: impl Into<String>,
+        source: Option<JobSchedulerError>,
+    ) {
+        let error = InternalError {
+            kind,
+            job_id,
+            message: message.into(),
+            source,
+        };
+        error!(kind = error.kind, job_id = error.job_id, source = error.source; "{}", error.message);
+        let handler = {
+            let r = self.handler.read().await;
+            r.clone()
+        };
+        if let Some(handler) = handler {
+            handler(error);
+        }
+    }
+
+    ///
+    /// Receive the next message of a channel, reporting the messages missed when the receiver
+    /// fell behind rather than giving up on the channel. `None` once the channel is closed.
+    pub async fn recv<T: Clone>(&self, rx: &mut Receiver<T>) -> Option<T> {
+        loop {
+            match rx.recv().await {
+                Ok(val) => return Some(val),
+                Err(RecvError::Lagged(missed)) => {
+                    self.report(
+                        InternalErrorKind::ChannelOverflow { missed },
+                        None,
+                        "Fell behind on a channel",
+                        None,
+                    )
+                    .await;
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InternalErrorKind, InternalErrors};
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::broadcast;
+
+    /// A receiver that fell behind skips to the oldest message kept and reports how many it
+    /// missed, rather than giving up on the channel
+    #[tokio::test]
+    async fn recv_past_lag() {
+        let errors = InternalErrors::default();
+        let reported = Arc::new(Mutex::new(vec![]));
+        {
+            let reported = reported.clone();
+            let mut w = errors.handler.write().await;
+            *w = Some(Arc::new(move |e: super::InternalError| {
+                reported.lock().unwrap().push(e.kind)
+            }));
+        }
+
+        let (tx, mut rx) = broadcast::channel(2);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(errors.recv(&mut rx).await, Some(3));
+        assert_eq!(errors.recv(&mut rx).await, Some(4));
+        assert_eq!(
+            *reported.lock().unwrap(),
+            vec![InternalErrorKind::ChannelOverflow { missed: 3 }]
+        );
+
+        drop(tx);
+        assert_eq!(errors.recv(&mut rx).await, None);
+    }
+}
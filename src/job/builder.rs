@@ -0,0 +1,344 @@
+use crate::job::{fallible, fallible_async, nop, nop_async, JobLocked, JobToRun, JobToRunAsync};
+use crate::job_scheduler::JobsSchedulerLocked;
+use crate::{ConcurrencyPolicy, JobSchedulerError, LeapDayPolicy, MisfirePolicy, RetryPolicy};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use std::convert::TryInto;
+use std::fmt::Display;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+enum Timing {
+    Cron(Box<Result<Schedule, JobSchedulerError>>),
+    OneShot(Duration),
+    OneShotAtInstant(Instant),
+    AtInstants(Vec<DateTime<Utc>>),
+    Repeated(Duration),
+    FixedDelay(Duration),
+    AfterJob(Uuid, Duration),
+}
+
+enum Run {
+    Sync(Box<JobToRun>),
+    Async(Box<JobToRunAsync>),
+}
+
+///
+/// Builds a job from its timing, its code and its settings, as an alternative to the `Job::new_*`
+/// constructors. The errors, like a schedule that doesn't parse, are returned by `build`.
+///
+/// ```rust,ignore
+/// let job = JobBuilder::new()
+///     .with_schedule("0 0 3 * * *")
+///     .with_timezone(chrono_tz::Europe::Amsterdam)
+///     .with_name("cleanup")
+///     .with_run_async(|_uuid, _lock| Box::pin(async move { cleanup().await }))
+///     .build()?;
+/// ```
+#[derive(Default)]
+pub struct JobBuilder {
+    timing: Option<Timing>,
+    timezone: Option<Tz>,
+    exclusions: Vec<Result<Schedule, JobSchedulerError>>,
+    jitter: Option<Duration>,
+    min_interval: Option<Duration>,
+    name: Option<String>,
+    tags: Vec<String>,
+    retry_policy: Option<RetryPolicy>,
+    concurrency_policy: Option<ConcurrencyPolicy>,
+    leap_day_policy: Option<LeapDayPolicy>,
+    misfire_policy: Option<MisfirePolicy>,
+    monotonic: bool,
+    run: Option<Run>,
+}
+
+impl JobBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Run the job on a cron schedule, evaluated in UTC unless `with_timezone` is given
+    pub fn with_schedule<S, E>(mut self, schedule: S) -> Self
+    where
+        S: TryInto<Schedule, Error = E>,
+        E: std::error::Error + 'static,
+    {
+        let schedule = schedule
+            .try_into()
+            .map_err(|_| JobSchedulerError::ParseSchedule);
+        self.timing = Some(Timing::Cron(Box::new(schedule)));
+        self
+    }
+
+    ///
+    /// The timezone the cron schedule is evaluated in
+    pub fn with_timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = Some(timezone);
+        self
+    }
+
+    ///
+    /// Don't fire the cron schedule at the times matching `exclusion`, like `0 * 2 * * *` for
+    /// none between 2:00 and 3:00. May be given more than once.
+    pub fn with_exclusion<S, E>(mut self, exclusion: S) -> Self
+    where
+        S: TryInto<Schedule, Error = E>,
+        E: std::error::Error + 'static,
+    {
+        let exclusion = exclusion
+            .try_into()
+            .map_err(|_| JobSchedulerError::ParseSchedule);
+        self.exclusions.push(exclusion);
+        self
+    }
+
+    ///
+    /// Put each tick of the cron schedule off by up to `jitter`, spreading the jobs sharing a
+    /// schedule
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    ///
+    /// Keep the runs at least `min_interval` apart, skipping the ticks coming sooner
+    pub fn with_min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = Some(min_interval);
+        self
+    }
+
+    ///
+    /// Run the job once, `duration` from now
+    pub fn with_one_shot(mut self, duration: Duration) -> Self {
+        self.timing = Some(Timing::OneShot(duration));
+        self
+    }
+
+    ///
+    /// Run the job once, at `instant`
+    pub fn with_one_shot_at_instant(mut self, instant: Instant) -> Self {
+        self.timing = Some(Timing::OneShotAtInstant(instant));
+        self
+    }
+
+    ///
+    /// Run the job once at each of `instants`
+    pub fn with_instants(mut self, instants: Vec<DateTime<Utc>>) -> Self {
+        self.timing = Some(Timing::AtInstants(instants));
+        self
+    }
+
+    ///
+    /// Run the job every `duration`
+    pub fn with_repeated(mut self, duration: Duration) -> Self {
+        self.timing = Some(Timing::Repeated(duration));
+        self
+    }
+
+    ///
+    /// Run the job every `duration` from the start of its previous run, like `with_repeated`
+    pub fn with_fixed_rate(self, duration: Duration) -> Self {
+        self.with_repeated(duration)
+    }
+
+    ///
+    /// Run the job `delay` after its previous run ended
+    pub fn with_fixed_delay(mut self, delay: Duration) -> Self {
+        self.timing = Some(Timing::FixedDelay(delay));
+        self
+    }
+
+    ///
+    /// Run the job `delay` after each successful run of the `upstream` job
+    pub fn with_after_job(mut self, upstream: Uuid, delay: Duration) -> Self {
+        self.timing = Some(Timing::AfterJob(upstream, delay));
+        self
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    pub fn with_concurrency_policy(mut self, concurrency_policy: ConcurrencyPolicy) -> Self {
+        self.concurrency_policy = Some(concurrency_policy);
+        self
+    }
+
+    ///
+    /// When the cron schedule, if it fires on February 29, fires in the years without one
+    pub fn with_leap_day_policy(mut self, leap_day_policy: LeapDayPolicy) -> Self {
+        self.leap_day_policy = Some(leap_day_policy);
+        self
+    }
+
+    ///
+    /// What happens to the runs that came due while the scheduler was down
+    pub fn with_misfire_policy(mut self, misfire_policy: MisfirePolicy) -> Self {
+        self.misfire_policy = Some(misfire_policy);
+        self
+    }
+
+    ///
+    /// Keep the timing of the repeated job on the monotonic clock, unaffected by steps of the
+    /// wall clock
+    pub fn with_monotonic(mut self, monotonic: bool) -> Self {
+        self.monotonic = monotonic;
+        self
+    }
+
+    pub fn with_run_sync<T>(mut self, run: T) -> Self
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) + Send + Sync,
+    {
+        self.run = Some(Run::Sync(Box::new(run)));
+        self
+    }
+
+    pub fn with_run_async<T>(mut self, run: T) -> Self
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = ()> + Send>>
+            + Send
+            + Sync,
+    {
+        self.run = Some(Run::Async(Box::new(run)));
+        self
+    }
+
+    ///
+    /// Run code that can fail, a returned error counting as a failed run like with
+    /// `Job::new_fallible`
+    pub fn with_run_fallible<T, E>(mut self, run: T) -> Self
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) -> Result<(), E> + Send + Sync,
+        E: Display + 'static,
+    {
+        self.run = Some(Run::Sync(Box::new(fallible(run))));
+        self
+    }
+
+    pub fn with_run_fallible_async<T, E>(mut self, run: T) -> Self
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = Result<(), E>> + Send>>
+            + Send
+            + Sync,
+        E: Display + 'static,
+    {
+        self.run = Some(Run::Async(Box::new(fallible_async(run))));
+        self
+    }
+
+    pub fn build(self) -> Result<JobLocked, JobSchedulerError> {
+        let timing = self.timing.ok_or_else(|| {
+            JobSchedulerError::JobConstruction("the job has no schedule".to_string())
+        })?;
+        let (run, run_async, async_job): (Box<JobToRun>, Box<JobToRunAsync>, bool) = match self.run
+        {
+            Some(Run::Sync(run)) => (run, Box::new(nop_async), false),
+            Some(Run::Async(run_async)) => (Box::new(nop), run_async, true),
+            None => {
+                return Err(JobSchedulerError::JobConstruction(
+                    "the job has no code to run".to_string(),
+                ))
+            }
+        };
+        if self.timezone.is_some() && !matches!(timing, Timing::Cron(_)) {
+            return Err(JobSchedulerError::JobConstruction(
+                "a timezone only applies to a cron schedule".to_string(),
+            ));
+        }
+        if self.leap_day_policy.is_some() && !matches!(timing, Timing::Cron(_)) {
+            return Err(JobSchedulerError::JobConstruction(
+                "a leap day policy only applies to a cron schedule".to_string(),
+            ));
+        }
+        if !self.exclusions.is_empty() && !matches!(timing, Timing::Cron(_)) {
+            return Err(JobSchedulerError::JobConstruction(
+                "exclusions only apply to a cron schedule".to_string(),
+            ));
+        }
+        if self.jitter.is_some() && !matches!(timing, Timing::Cron(_)) {
+            return Err(JobSchedulerError::JobConstruction(
+                "a jitter only applies to a cron schedule".to_string(),
+            ));
+        }
+        let exclusions = self.exclusions.into_iter().collect::<Result<Vec<_>, _>>()?;
+        if self.monotonic && !matches!(timing, Timing::Repeated(_) | Timing::FixedDelay(_)) {
+            return Err(JobSchedulerError::JobConstruction(
+                "monotonic timing only applies to a repeated job".to_string(),
+            ));
+        }
+        let mut job = match timing {
+            Timing::Cron(schedule) => {
+                JobLocked::make_cron_job((*schedule)?, self.timezone, run, run_async, async_job)
+            }
+            Timing::OneShot(duration) => {
+                JobLocked::make_one_shot_job(duration, run, run_async, async_job)
+            }
+            Timing::OneShotAtInstant(instant) => {
+                JobLocked::make_new_one_shot_at_an_instant(instant, run, run_async, async_job)
+            }
+            Timing::AtInstants(instants) => {
+                JobLocked::make_at_instants(instants, run, run_async, async_job)
+            }
+            Timing::Repeated(duration) => {
+                JobLocked::make_new_repeated(duration, run, run_async, async_job)
+            }
+            Timing::FixedDelay(delay) => {
+                JobLocked::make_fixed_delay(delay, run, run_async, async_job)
+            }
+            Timing::AfterJob(upstream, delay) => {
+                JobLocked::make_after_job(upstream, delay, run, run_async, async_job)
+            }
+        }?;
+        if let Some(name) = self.name {
+            job.set_name(name)?;
+        }
+        if !self.tags.is_empty() {
+            job.set_tags(self.tags)?;
+        }
+        if self.retry_policy.is_some() {
+            job.set_retry_policy(self.retry_policy)?;
+        }
+        if let Some(concurrency_policy) = self.concurrency_policy {
+            job.set_concurrency_policy(concurrency_policy)?;
+        }
+        if let Some(leap_day_policy) = self.leap_day_policy {
+            job.set_leap_day_policy(leap_day_policy)?;
+        }
+        if !exclusions.is_empty() {
+            job.set_exclusions(exclusions)?;
+        }
+        if let Some(jitter) = self.jitter {
+            job.set_jitter(jitter)?;
+        }
+        if self.min_interval.is_some() {
+            job.set_min_interval(self.min_interval)?;
+        }
+        if let Some(misfire_policy) = self.misfire_policy {
+            job.set_misfire_policy(misfire_policy)?;
+        }
+        if self.monotonic {
+            job.set_monotonic(true)?;
+        }
+        Ok(job)
+    }
+}
@@ -1,13 +1,23 @@
+use crate::clock;
 use crate::context::Context;
-use crate::job::{JobLocked, JobToRunAsync};
-use crate::store::MetaDataStorage;
+use crate::internal_error::{InternalErrorKind, InternalErrors};
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{JobRun, JobState, JobType};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{JobRun, JobState, JobType};
+use crate::job::{runner, spawn_async_job, JobLocked, JobToRunAsync, RunErrors};
+use crate::job_scheduler::JobsSchedulerLocked;
+use crate::redrive::DeadLetterReason;
+use crate::retry;
+use crate::store::{MetaDataStorage, OutboxEntry};
 use crate::{JobSchedulerError, JobStoredData};
+use chrono::{DateTime, Utc};
 use std::future::Future;
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::broadcast::{Receiver, Sender};
 use tokio::sync::RwLock;
-use tracing::error;
 use uuid::Uuid;
 
 #[derive(Default)]
@@ -18,14 +28,9 @@ impl JobCreator {
         storage: Arc<RwLock<Box<dyn MetaDataStorage + Send + Sync>>>,
         mut rx: Receiver<(JobStoredData, Arc<RwLock<Box<JobToRunAsync>>>)>,
         tx_created: Sender<Result<Uuid, (JobSchedulerError, Option<Uuid>)>>,
+        internal_errors: InternalErrors,
     ) {
-        loop {
-            let val = rx.recv().await;
-            if let Err(e) = val {
-                error!("Error receiving {:?}", e);
-                break;
-            }
-            let (data, _) = val.unwrap();
+        while let Some((data, _)) = internal_errors.recv(&mut rx).await {
             let uuid: Uuid = match data.id.as_ref().map(|b| b.into()) {
                 Some(uuid) => uuid,
                 None => {
@@ -39,7 +44,14 @@ impl JobCreator {
                 let mut storage = storage.write().await;
                 let saved = storage.add_or_update(data).await;
                 if let Err(e) = saved {
-                    error!("Error saving job metadata {:?}", e);
+                    internal_errors
+                        .report(
+                            InternalErrorKind::Storage,
+                            Some(uuid),
+                            "Error saving job metadata",
+                            Some(e.clone()),
+                        )
+                        .await;
                     if let Err(e) = tx_created.send(Err((e, Some(uuid)))) {
                         error!("Could not send failure {:?}", e);
                     }
@@ -59,19 +71,289 @@ impl JobCreator {
         let rx = context.job_create_tx.subscribe();
         let tx_created = context.job_created_tx.clone();
         let storage = context.metadata_storage.clone();
+        let internal_errors = context.internal_errors.clone();
 
         Box::pin(async move {
-            tokio::spawn(JobCreator::listen_to_additions(storage, rx, tx_created));
+            tokio::spawn(JobCreator::listen_to_additions(
+                storage,
+                rx,
+                tx_created,
+                internal_errors,
+            ));
             Ok(())
         })
     }
 
-    pub async fn add(context: &Context, mut job: JobLocked) -> Result<Uuid, JobSchedulerError> {
-        let tx = context.job_create_tx.clone();
-        let mut rx = context.job_created_tx.subscribe();
+    ///
+    /// Write the notifications of the outcome of a run to the outbox, if there is one, before the
+    /// outcome is stored
+    async fn enqueue_outcome(context: &Context, job_id: Uuid, states: &[JobState]) {
+        let mut w = context.outbox.write().await;
+        let outbox = match w.as_mut() {
+            Some(outbox) => outbox,
+            None => return,
+        };
+        for state in states.iter().copied().chain([JobState::Done]) {
+            if let Err(e) = outbox.enqueue(OutboxEntry::new(job_id, state)).await {
+                context
+                    .internal_errors
+                    .report(
+                        InternalErrorKind::Outbox,
+                        Some(job_id),
+                        "Error writing the outcome of a run to the outbox",
+                        Some(e),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    ///
+    /// The tick the current run of a job is for
+    async fn scheduled_time(context: &Context, job_id: Uuid) -> Option<DateTime<Utc>> {
+        if let Some(run) = runner::current_run() {
+            return Some(run.scheduled);
+        }
+        let r = context.scheduled_times.read().await;
+        r.get(&job_id).copied()
+    }
+
+    ///
+    /// Write the record of a run that ended now with `outcome` to the run history
+    pub(crate) async fn record_run(
+        context: &Context,
+        job_id: Uuid,
+        scheduled_time: DateTime<Utc>,
+        started: DateTime<Utc>,
+        outcome: JobState,
+        error: &Option<String>,
+    ) {
+        let ended = clock::now();
+        debug!(job_id = job_id, outcome = outcome, error = error; "Run ended");
+        let run = JobRun::new(
+            job_id,
+            scheduled_time,
+            started,
+            ended,
+            outcome,
+            error.clone(),
+        );
+        let mut w = context.run_history.write().await;
+        if let Err(e) = w.record(run).await {
+            context
+                .internal_errors
+                .report(
+                    InternalErrorKind::RunHistory,
+                    Some(job_id),
+                    "Error writing the run to the run history",
+                    Some(e),
+                )
+                .await;
+        }
+    }
+
+    ///
+    /// The notifications of the outcome of a run, before the `Done` one
+    fn outcome_states(failed: bool, error: &Option<String>) -> Vec<JobState> {
+        match (failed, error) {
+            (true, Some(_)) => vec![JobState::Failed, JobState::FailedWithError],
+            (true, None) => vec![JobState::Failed],
+            (false, _) => vec![JobState::Succeeded],
+        }
+    }
+
+    ///
+    /// The jobs run after the job, for a store that can't look them up itself
+    async fn find_followers(
+        storage: &mut Box<dyn MetaDataStorage + Send + Sync>,
+        job_id: Uuid,
+    ) -> Result<Vec<JobStoredData>, JobSchedulerError> {
+        let ids = storage.list_job_ids().await?;
+        let jobs = storage.get_many(&ids).await?;
+        Ok(jobs
+            .into_iter()
+            .filter(|data| {
+                data.job_type == JobType::AfterJob as i32
+                    && data.after_job.as_ref().map(|id| id.into()) == Some(job_id)
+            })
+            .collect())
+    }
+
+    ///
+    /// Set the next tick of the jobs run after the job, to their delay after its successful run
+    /// completed now
+    async fn schedule_followers(
+        context: &Context,
+        storage: &mut Box<dyn MetaDataStorage + Send + Sync>,
+        job_id: Uuid,
+    ) {
+        let now = clock::now();
+        let followers = match storage.list_followers(job_id) {
+            Some(followers) => followers.await,
+            None => JobCreator::find_followers(storage, job_id).await,
+        };
+        let followers = match followers {
+            Ok(followers) => followers,
+            Err(e) => {
+                context
+                    .internal_errors
+                    .report(
+                        InternalErrorKind::Storage,
+                        Some(job_id),
+                        "Error listing the jobs run after the job",
+                        Some(e),
+                    )
+                    .await;
+                return;
+            }
+        };
+        for follower in followers {
+            let follower_id: Uuid = match follower.id.as_ref() {
+                Some(id) => id.into(),
+                None => continue,
+            };
+            let delay = follower.repeated_every().unwrap_or_default();
+            let next_tick = now.checked_add_signed(chrono::Duration::seconds(delay as i64));
+            let next_tick = context.reschedule_hooks.apply(&follower, next_tick).await;
+            if let Err(e) = storage
+                .set_next_and_last_tick(follower_id, next_tick, follower.last_tick_utc())
+                .await
+            {
+                context
+                    .internal_errors
+                    .report(
+                        InternalErrorKind::Storage,
+                        Some(follower_id),
+                        "Error scheduling the job run after another",
+                        Some(e),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    async fn record_outcome(
+        context: Arc<Context>,
+        job_id: Uuid,
+        started: DateTime<Utc>,
+        failed: bool,
+    ) {
+        // A fallible job failed when its run returned an error
+        let error = context.run_errors.take(&RunErrors::key(job_id));
+        let failed = failed || error.is_some();
+        let states = JobCreator::outcome_states(failed, &error);
+        let scheduled_time = JobCreator::scheduled_time(&context, job_id).await;
+        #[cfg(feature = "metrics")]
+        crate::metrics::run_ended(
+            job_id,
+            failed,
+            (clock::now() - started).to_std().unwrap_or_default(),
+        );
+        let outcome = if failed {
+            JobState::Failed
+        } else {
+            JobState::Succeeded
+        };
+        JobCreator::record_run(
+            &context,
+            job_id,
+            scheduled_time.unwrap_or(started),
+            started,
+            outcome,
+            &error,
+        )
+        .await;
+        JobCreator::enqueue_outcome(&context, job_id, &states).await;
+        let mut storage = context.metadata_storage.write().await;
+        let data = storage.get(job_id).await;
+        match data {
+            Ok(Some(mut data)) => {
+                let retry_policy = data.retry_policy.clone();
+                if data.fixed_delay() {
+                    // The delay counts from the end of the run, the tick set when it was fired
+                    // only keeping the job due should it never end
+                    let delay = data.repeated_every().unwrap_or_default();
+                    let next_tick =
+                        clock::now().checked_add_signed(chrono::Duration::seconds(delay as i64));
+                    let next_tick = context.reschedule_hooks.apply(&data, next_tick).await;
+                    data.set_next_tick(next_tick);
+                }
+                let next_tick = data.next_tick_utc();
+                let consecutive_failures = if failed {
+                    data.consecutive_failures.saturating_add(1)
+                } else {
+                    0
+                };
+                data.count = data.count.saturating_add(1);
+                data.last_run_failed = failed;
+                data.consecutive_failures = consecutive_failures;
+                data.last_error = error;
+                if let Err(e) = storage.add_or_update(data).await {
+                    context
+                        .internal_errors
+                        .report(
+                            InternalErrorKind::Storage,
+                            Some(job_id),
+                            "Error saving run outcome",
+                            Some(e),
+                        )
+                        .await;
+                }
+                if failed {
+                    let scheduled_time = scheduled_time.unwrap_or_else(Utc::now);
+                    let retrying = match retry_policy {
+                        Some(policy) => {
+                            retry::retry(
+                                context.clone(),
+                                job_id,
+                                &policy,
+                                next_tick,
+                                scheduled_time,
+                            )
+                            .await
+                        }
+                        None => false,
+                    };
+                    // A run that is retried is only a dead letter once its retries ran out
+                    if !retrying {
+                        context
+                            .dead_letters
+                            .record(job_id, scheduled_time, DeadLetterReason::Failed)
+                            .await;
+                    }
+                } else {
+                    context.retry_attempts.reset(&job_id).await;
+                    if let Some(scheduled_time) = scheduled_time {
+                        context
+                            .completion_times
+                            .record(job_id, scheduled_time)
+                            .await;
+                    }
+                    JobCreator::schedule_followers(&context, &mut storage, job_id).await;
+                }
+                for state in states {
+                    if let Err(e) = context.notify_tx.send((job_id, state)) {
+                        error!("Error sending notification {:?} {:?}", state, e);
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                context
+                    .internal_errors
+                    .report(
+                        InternalErrorKind::Storage,
+                        Some(job_id),
+                        "Error getting job data",
+                        Some(e),
+                    )
+                    .await;
+            }
+        }
+    }
 
+    pub async fn add(context: &Context, mut job: JobLocked) -> Result<Uuid, JobSchedulerError> {
         let data = job.job_data();
-        let uuid = job.guid();
 
         if let Err(e) = data {
             error!("Error getting job data {e:?}");
@@ -81,46 +363,140 @@ impl JobCreator {
         let job: Box<JobToRunAsync> = Box::new(move |job_id, job_scheduler| {
             let job = job.clone();
             Box::pin(async move {
-                let job_done = {
-                    let w = job.0.write();
-                    if let Err(e) = w {
+                let context = job_scheduler.context.clone();
+                let started = clock::now();
+                // The code of a synchronous job is called right away by `run`
+                let job_done = runner::run_blocking(move || match job.0.write() {
+                    Ok(mut w) => Some(w.run(job_scheduler)),
+                    Err(e) => {
                         error!("Error getting job {:?}", e);
-                        return;
+                        None
+                    }
+                })
+                .await;
+                let job_done = match job_done {
+                    Ok(Some(job_done)) => job_done.await,
+                    Ok(None) => return,
+                    Err(e) => {
+                        error!(job_id = job_id; "Error running job {:?}", e);
+                        return JobCreator::record_outcome(context, job_id, started, true).await;
                     }
-                    let mut w = w.unwrap();
-                    w.run(job_scheduler)
                 };
-                let job_done = job_done.await;
-                match job_done {
+                let failed = match job_done {
                     Err(e) => {
-                        error!("Error running job {:?} {:?}", job_id, e);
+                        error!(job_id = job_id; "Error running job {:?}", e);
+                        true
                     }
                     Ok(val) => {
                         if !val {
-                            error!("Error running job {:?}", job_id);
+                            error!(job_id = job_id; "Error running job");
                         }
+                        !val
                     }
-                }
+                };
+                JobCreator::record_outcome(context, job_id, started, failed).await;
             })
         });
 
         let job = Arc::new(RwLock::new(job));
+        JobCreator::add_with_code(context, data, job).await
+    }
+
+    ///
+    /// Job code running `run`, recording whether it panicked as the outcome of the run
+    pub(crate) fn code<T>(run: T) -> Box<JobToRunAsync>
+    where
+        T: FnMut(Uuid, JobsSchedulerLocked) + Send + Sync + 'static,
+    {
+        let run = Arc::new(std::sync::Mutex::new(run));
+        Box::new(move |job_id, job_scheduler| {
+            let run = run.clone();
+            Box::pin(async move {
+                let context = job_scheduler.context.clone();
+                let started = clock::now();
+                let ran = runner::run_blocking(move || match run.lock() {
+                    Ok(mut run) => Some(
+                        std::panic::catch_unwind(AssertUnwindSafe(|| (run)(job_id, job_scheduler)))
+                            .is_err(),
+                    ),
+                    Err(e) => {
+                        error!("Error getting job code {:?}", e);
+                        None
+                    }
+                })
+                .await;
+                let failed = match ran {
+                    Ok(Some(failed)) => failed,
+                    Ok(None) => return,
+                    Err(_) => true,
+                };
+                JobCreator::record_outcome(context, job_id, started, failed).await;
+            })
+        })
+    }
+
+    ///
+    /// Job code running the future returned by `run`, recording whether it panicked as the
+    /// outcome of the run
+    pub(crate) fn code_async<T>(mut run: T) -> Box<JobToRunAsync>
+    where
+        T: FnMut(Uuid, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = ()> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Box::new(move |job_id, job_scheduler| {
+            let context = job_scheduler.context.clone();
+            let future = (run)(job_id, job_scheduler);
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            Box::pin(async move {
+                let started = clock::now();
+                spawn_async_job(future, tx);
+                let failed = rx.await.is_err();
+                JobCreator::record_outcome(context, job_id, started, failed).await;
+            })
+        })
+    }
+
+    ///
+    /// Add job metadata along with the code to run for it, overwriting a job with the same id
+    pub async fn add_with_code(
+        context: &Context,
+        mut data: JobStoredData,
+        job: Arc<RwLock<Box<JobToRunAsync>>>,
+    ) -> Result<Uuid, JobSchedulerError> {
+        if context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        if let Some(timezone) = context.default_timezone {
+            data.set_default_timezone(timezone);
+        }
+        let next_tick = context
+            .reschedule_hooks
+            .apply(&data, data.next_tick_utc())
+            .await;
+        data.set_next_tick(next_tick);
+        data.group = context.group.clone();
+        let tx = context.job_create_tx.clone();
+        let mut rx = context.job_created_tx.subscribe();
+        let uuid: Uuid = data
+            .id
+            .as_ref()
+            .map(|id| id.into())
+            .ok_or(JobSchedulerError::CantAdd)?;
+
         if let Err(_e) = tx.send((data, job)) {
             error!("Error sending new job");
             return Err(JobSchedulerError::CantAdd);
         }
 
-        while let Ok(val) = rx.recv().await {
+        while let Some(val) = context.internal_errors.recv(&mut rx).await {
             match val {
-                Ok(ret_uuid) => {
-                    if ret_uuid == uuid {
-                        return Ok(uuid);
-                    }
+                Ok(ret_uuid) if ret_uuid == uuid => {
+                    return Ok(uuid);
                 }
-                Err((e, Some(ret_uuid))) => {
-                    if ret_uuid == uuid {
-                        return Err(e);
-                    }
+                Err((e, Some(ret_uuid))) if ret_uuid == uuid => {
+                    return Err(e);
                 }
                 _ => {}
             }
@@ -129,3 +505,62 @@ impl JobCreator {
         Err(JobSchedulerError::CantAdd)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Job, JobNotification, JobScheduler};
+    use chrono::{Duration, Utc};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Two runs of a job at the same time, the first failing after the second succeeded, each
+    /// recorded with its own tick and outcome
+    #[tokio::test]
+    async fn overlapping_runs() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let runs = Arc::new(AtomicUsize::new(0));
+        let job = Job::new_fallible_async("0 0 0 1 1 *", move |_, _| {
+            let first = runs.fetch_add(1, Ordering::SeqCst) == 0;
+            Box::pin(async move {
+                if first {
+                    tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+                    return Err("slow");
+                }
+                Ok(())
+            })
+        })
+        .expect("job");
+        let job_id = sched.add(job).await.expect("add");
+        sched.start().await.expect("start");
+
+        let first = Utc::now() - Duration::hours(2);
+        let second = Utc::now() - Duration::hours(1);
+        sched
+            .trigger_as_of(&job_id, first)
+            .await
+            .expect("trigger the first");
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        sched
+            .trigger_as_of(&job_id, second)
+            .await
+            .expect("trigger the second");
+        tokio::time::sleep(std::time::Duration::from_millis(800)).await;
+
+        let history = sched.run_history(&job_id, 10).await.expect("history");
+        let run_at = |scheduled: chrono::DateTime<Utc>| {
+            history
+                .iter()
+                .find(|run| {
+                    run.scheduled_at_utc().map(|t| t.timestamp_millis())
+                        == Some(scheduled.timestamp_millis())
+                })
+                .expect("run")
+        };
+        assert_eq!(history.len(), 2);
+        assert_eq!(run_at(first).outcome, JobNotification::Failed as i32);
+        assert_eq!(run_at(first).error.as_deref(), Some("slow"));
+        assert_eq!(run_at(second).outcome, JobNotification::Succeeded as i32);
+        assert_eq!(run_at(second).error, None);
+        sched.shutdown().await.expect("shutdown");
+    }
+}
@@ -2,12 +2,11 @@
 use crate::job::job_data::{JobStoredData, JobType};
 #[cfg(feature = "has_bytes")]
 use crate::job::job_data_prost::{JobStoredData, JobType};
-use crate::job::{Job, JobToRunAsync};
+use crate::job::{spawn_async_job, Job, JobToRunAsync};
 use crate::{JobScheduler, JobSchedulerError, JobToRun};
 use chrono::{DateTime, Utc};
-use cron::Schedule;
+use std::panic::AssertUnwindSafe;
 use tokio::sync::oneshot::Receiver;
-use tracing::error;
 use uuid::Uuid;
 
 pub struct CronJob {
@@ -18,14 +17,6 @@ pub struct CronJob {
 }
 
 impl Job for CronJob {
-    fn is_cron_job(&self) -> bool {
-        true
-    }
-
-    fn schedule(&self) -> Option<Schedule> {
-        self.data.schedule()
-    }
-
     fn repeated_every(&self) -> Option<u64> {
         None
     }
@@ -54,14 +45,6 @@ impl Job for CronJob {
         self.data.count
     }
 
-    fn increment_count(&mut self) {
-        self.data.count = if self.data.count + 1 < u32::MAX {
-            self.data.count + 1
-        } else {
-            0
-        }; // Overflow check
-    }
-
     fn job_id(&self) -> Uuid {
         self.data.id.as_ref().cloned().map(|e| e.into()).unwrap()
     }
@@ -78,10 +61,6 @@ impl Job for CronJob {
         self.data.ran = ran;
     }
 
-    fn stop(&self) -> bool {
-        self.data.stopped
-    }
-
     fn set_stopped(&mut self) {
         self.data.stopped = true;
     }
@@ -104,18 +83,15 @@ impl Job for CronJob {
         let job_id = self.job_id();
 
         if !self.async_job {
-            (self.run)(job_id, jobs);
-            if let Err(e) = tx.send(true) {
+            let run = &mut self.run;
+            let succeeded =
+                std::panic::catch_unwind(AssertUnwindSafe(|| (run)(job_id, jobs))).is_ok();
+            if let Err(e) = tx.send(succeeded) {
                 error!("Error notifying done {:?}", e);
             }
         } else {
             let future = (self.run_async)(job_id, jobs);
-            tokio::task::spawn(async move {
-                future.await;
-                if let Err(e) = tx.send(true) {
-                    error!("Error notifying done {:?}", e);
-                }
-            });
+            spawn_async_job(future, tx);
         }
         rx
     }
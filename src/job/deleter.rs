@@ -1,41 +1,70 @@
 use crate::context::Context;
-use crate::store::MetaDataStorage;
+use crate::internal_error::InternalErrorKind;
 use crate::JobSchedulerError;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
 use tokio::sync::broadcast::{Receiver, Sender};
-use tokio::sync::RwLock;
-use tracing::error;
 use uuid::Uuid;
 
 #[derive(Default)]
 pub struct JobDeleter {}
 
 impl JobDeleter {
+    ///
+    /// Drop what the scheduler keeps in memory for a job that was deleted
+    async fn forget(context: &Context, uuid: Uuid) {
+        {
+            let mut w = context.pending_approvals.write().await;
+            w.remove(&uuid);
+        }
+        {
+            let mut w = context.reschedule_hooks.per_job.write().await;
+            w.remove(&uuid);
+        }
+        context.run_conditions.remove(&uuid).await;
+        context.run_scopes.remove(&uuid).await;
+        {
+            let mut w = context.payload_generators.write().await;
+            w.remove(&uuid);
+        }
+        {
+            let mut w = context.scheduled_times.write().await;
+            w.remove(&uuid);
+        }
+        context.dead_letters.remove_job(&uuid).await;
+        {
+            let mut w = context.drift.write().await;
+            w.remove(&uuid);
+        }
+        context.completion_times.remove_job(&uuid).await;
+    }
+
     async fn listen_to_removals(
-        storage: Arc<RwLock<Box<dyn MetaDataStorage + Send + Sync>>>,
+        context: Context,
         mut rx: Receiver<Uuid>,
         tx_deleted: Sender<Result<Uuid, (JobSchedulerError, Option<Uuid>)>>,
     ) {
-        loop {
-            let val = rx.recv().await;
-            if let Err(e) = val {
-                error!("Error receiving value {:?}", e);
-                break;
-            }
-            let uuid = val.unwrap();
+        while let Some(uuid) = context.internal_errors.recv(&mut rx).await {
             {
-                let mut storage = storage.write().await;
+                let mut storage = context.metadata_storage.write().await;
                 let delete = storage.delete(uuid).await;
                 if let Err(e) = delete {
-                    error!("Error deleting {:?}", e);
+                    context
+                        .internal_errors
+                        .report(
+                            InternalErrorKind::Storage,
+                            Some(uuid),
+                            "Error deleting",
+                            Some(e.clone()),
+                        )
+                        .await;
                     if let Err(e) = tx_deleted.send(Err((e, Some(uuid)))) {
                         error!("Error sending delete error {:?}", e);
                     }
                     continue;
                 }
             }
+            JobDeleter::forget(&context, uuid).await;
             if let Err(e) = tx_deleted.send(Ok(uuid)) {
                 error!("Error sending error {:?}", e);
             }
@@ -48,15 +77,18 @@ impl JobDeleter {
     ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send + Sync>> {
         let rx = context.job_delete_tx.subscribe();
         let tx_deleted = context.job_deleted_tx.clone();
-        let storage = context.metadata_storage.clone();
+        let context = context.clone();
 
         Box::pin(async move {
-            tokio::spawn(JobDeleter::listen_to_removals(storage, rx, tx_deleted));
+            tokio::spawn(JobDeleter::listen_to_removals(context, rx, tx_deleted));
             Ok(())
         })
     }
 
     pub async fn remove(context: &Context, job_id: &Uuid) -> Result<(), JobSchedulerError> {
+        if context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
         let delete = context.job_delete_tx.clone();
         let mut deleted = context.job_deleted_tx.subscribe();
 
@@ -66,7 +98,7 @@ impl JobDeleter {
                 error!("Error sending delete id {:?}", e);
             }
         });
-        while let Ok(deleted) = deleted.recv().await {
+        while let Some(deleted) = context.internal_errors.recv(&mut deleted).await {
             match deleted {
                 Ok(uuid) => {
                     if uuid == job_id {
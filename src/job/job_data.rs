@@ -1,11 +1,25 @@
 #[derive(Clone, PartialEq, Debug)]
 pub struct CronJob {
     pub schedule: String,
+    pub timezone: String,
+    pub exclusions: Vec<String>,
+    pub jitter_seconds: u64,
 }
 #[derive(Clone, PartialEq, Debug)]
 pub struct NonCronJob {
     pub repeating: bool,
     pub repeated_every: u64,
+    pub monotonic: bool,
+    pub fixed_delay: bool,
+    pub instants: Vec<u64>,
+}
+#[derive(Clone, PartialEq, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub delay_millis: u64,
+    pub exponential: bool,
+    pub max_delay_millis: u64,
+    pub jitter: bool,
 }
 #[derive(Clone, PartialEq, Debug)]
 pub struct Uuid {
@@ -23,6 +37,29 @@ pub struct JobStoredData {
     pub extra: Vec<u8>,
     pub ran: bool,
     pub stopped: bool,
+    pub last_run_failed: bool,
+    pub skip_if_last_failed: bool,
+    pub skip_if_upstream_failed: ::core::option::Option<Uuid>,
+    pub requires_approval: bool,
+    pub maintenance_window_policy: i32,
+    pub group: String,
+    pub starting_deadline_seconds: ::core::option::Option<u64>,
+    pub missed_count: u32,
+    pub tags: Vec<String>,
+    pub warm_start: bool,
+    pub cost: u32,
+    pub priority: i32,
+    pub exclusive_group: ::core::option::Option<String>,
+    pub consecutive_failures: u32,
+    pub last_payload: Vec<u8>,
+    pub name: String,
+    pub retry_policy: ::core::option::Option<RetryPolicy>,
+    pub last_error: ::core::option::Option<String>,
+    pub concurrency_policy: i32,
+    pub leap_day_policy: i32,
+    pub misfire_policy: i32,
+    pub after_job: ::core::option::Option<Uuid>,
+    pub min_interval_seconds: ::core::option::Option<u64>,
     pub job: ::core::option::Option<job_stored_data::Job>,
 }
 
@@ -47,30 +84,61 @@ pub struct NotificationData {
     pub extra: Vec<u8>,
 }
 #[derive(Clone, PartialEq, Debug)]
-pub struct NotificationIdAndState {
-    pub notification_id: ::core::option::Option<Uuid>,
-    pub job_state: i32,
-}
-#[derive(Clone, PartialEq, Debug)]
 pub struct JobAndNextTick {
     pub id: ::core::option::Option<Uuid>,
     pub job_type: i32,
     pub next_tick: u64,
     pub last_tick: ::core::option::Option<u64>,
+    pub group: String,
 }
 #[derive(Clone, PartialEq, Debug)]
 pub struct ListOfUuids {
     pub uuids: Vec<Uuid>,
 }
 #[derive(Clone, PartialEq, Debug)]
-pub struct JobAndNotifications {
+pub struct Snapshot {
+    pub taken_at: u64,
+    pub jobs: Vec<JobStoredData>,
+    pub notifications: Vec<NotificationData>,
+}
+#[derive(Clone, PartialEq, Debug)]
+pub struct TicksSet {
     pub job_id: ::core::option::Option<Uuid>,
-    pub notification_ids: Vec<Uuid>,
+    pub next_tick: ::core::option::Option<u64>,
+    pub last_tick: ::core::option::Option<u64>,
 }
 #[derive(Clone, PartialEq, Debug)]
-pub struct ListOfJobsAndNotifications {
-    pub job_and_notifications: Vec<JobAndNotifications>,
+pub struct StoreEvent {
+    pub sequence: u64,
+    pub at: u64,
+    pub event: ::core::option::Option<store_event::Event>,
 }
+/// Nested message and enum types in `StoreEvent`.
+pub mod store_event {
+    #[allow(clippy::large_enum_variant)]
+    #[derive(Clone, PartialEq, Debug)]
+    pub enum Event {
+        JobSaved(super::JobStoredData),
+        JobDeleted(super::Uuid),
+        TicksSet(super::TicksSet),
+    }
+}
+#[derive(Clone, PartialEq, Debug)]
+pub struct LoggedSnapshot {
+    pub sequence: u64,
+    pub snapshot: ::core::option::Option<Snapshot>,
+}
+#[derive(Clone, PartialEq, Debug)]
+pub struct JobRun {
+    pub id: ::core::option::Option<Uuid>,
+    pub job_id: ::core::option::Option<Uuid>,
+    pub scheduled_at: u64,
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub outcome: i32,
+    pub error: ::core::option::Option<String>,
+}
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, FromPrimitive, ToPrimitive)]
 #[repr(i32)]
 pub enum JobState {
@@ -79,13 +147,49 @@ pub enum JobState {
     Started = 2,
     Done = 3,
     Removed = 4,
+    Skipped = 5,
+    Pending = 6,
+    Deferred = 7,
+    Cancelled = 8,
+    Failed = 9,
+    Succeeded = 10,
+    FailedWithError = 11,
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, FromPrimitive, ToPrimitive)]
 #[repr(i32)]
+pub enum MaintenanceWindowPolicy {
+    Defer = 0,
+    Cancel = 1,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, FromPrimitive, ToPrimitive)]
+#[repr(i32)]
+pub enum LeapDayPolicy {
+    LeapYearsOnly = 0,
+    LastDayOfFebruary = 1,
+    FirstOfMarch = 2,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, FromPrimitive, ToPrimitive)]
+#[repr(i32)]
+pub enum MisfirePolicy {
+    RunOnce = 0,
+    Skip = 1,
+    RunAll = 2,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, FromPrimitive, ToPrimitive)]
+#[repr(i32)]
+pub enum ConcurrencyPolicy {
+    Allow = 0,
+    Skip = 1,
+    Queue = 2,
+}
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, FromPrimitive, ToPrimitive)]
+#[repr(i32)]
 pub enum JobType {
     Cron = 0,
     Repeated = 1,
     OneShot = 2,
+    AfterJob = 3,
 }
 
 impl JobState {
@@ -96,6 +200,56 @@ impl JobState {
             2 => Some(Self::Started),
             3 => Some(Self::Done),
             4 => Some(Self::Removed),
+            5 => Some(Self::Skipped),
+            6 => Some(Self::Pending),
+            7 => Some(Self::Deferred),
+            8 => Some(Self::Cancelled),
+            9 => Some(Self::Failed),
+            10 => Some(Self::Succeeded),
+            11 => Some(Self::FailedWithError),
+            _ => None,
+        }
+    }
+}
+
+impl MaintenanceWindowPolicy {
+    pub fn from_i32(x: i32) -> Option<Self> {
+        match x {
+            0 => Some(Self::Defer),
+            1 => Some(Self::Cancel),
+            _ => None,
+        }
+    }
+}
+
+impl LeapDayPolicy {
+    pub fn from_i32(x: i32) -> Option<Self> {
+        match x {
+            0 => Some(Self::LeapYearsOnly),
+            1 => Some(Self::LastDayOfFebruary),
+            2 => Some(Self::FirstOfMarch),
+            _ => None,
+        }
+    }
+}
+
+impl MisfirePolicy {
+    pub fn from_i32(x: i32) -> Option<Self> {
+        match x {
+            0 => Some(Self::RunOnce),
+            1 => Some(Self::Skip),
+            2 => Some(Self::RunAll),
+            _ => None,
+        }
+    }
+}
+
+impl ConcurrencyPolicy {
+    pub fn from_i32(x: i32) -> Option<Self> {
+        match x {
+            0 => Some(Self::Allow),
+            1 => Some(Self::Skip),
+            2 => Some(Self::Queue),
             _ => None,
         }
     }
@@ -107,6 +261,7 @@ impl JobType {
             0 => Some(Self::Cron),
             1 => Some(Self::Repeated),
             2 => Some(Self::OneShot),
+            3 => Some(Self::AfterJob),
             _ => None,
         }
     }
@@ -118,6 +273,30 @@ impl From<JobState> for i32 {
     }
 }
 
+impl From<MaintenanceWindowPolicy> for i32 {
+    fn from(val: MaintenanceWindowPolicy) -> Self {
+        val as i32
+    }
+}
+
+impl From<LeapDayPolicy> for i32 {
+    fn from(val: LeapDayPolicy) -> Self {
+        val as i32
+    }
+}
+
+impl From<MisfirePolicy> for i32 {
+    fn from(val: MisfirePolicy) -> Self {
+        val as i32
+    }
+}
+
+impl From<ConcurrencyPolicy> for i32 {
+    fn from(val: ConcurrencyPolicy) -> Self {
+        val as i32
+    }
+}
+
 impl From<JobType> for i32 {
     fn from(val: JobType) -> Self {
         val as i32
@@ -1,9 +1,20 @@
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CronJob {
     #[prost(string, tag = "1")]
     pub schedule: ::prost::alloc::string::String,
+    /// The IANA timezone the schedule is evaluated in, UTC when empty
+    #[prost(string, tag = "2")]
+    pub timezone: ::prost::alloc::string::String,
+    /// Cron expressions of the times the schedule doesn't fire in
+    #[prost(string, repeated, tag = "3")]
+    pub exclusions: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// The most seconds each tick is put off by, spreading the jobs firing at the same time
+    #[prost(uint64, tag = "4")]
+    pub jitter_seconds: u64,
 }
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct NonCronJob {
@@ -11,7 +22,35 @@ pub struct NonCronJob {
     pub repeating: bool,
     #[prost(uint64, tag = "2")]
     pub repeated_every: u64,
+    #[prost(bool, tag = "3")]
+    pub monotonic: bool,
+    #[prost(bool, tag = "4")]
+    pub fixed_delay: bool,
+    #[prost(uint64, repeated, tag = "5")]
+    pub instants: ::prost::alloc::vec::Vec<u64>,
+}
+/// How the failed runs of a job are retried before its next tick
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RetryPolicy {
+    /// The runs of an occurrence in all, the first one included
+    #[prost(uint32, tag = "1")]
+    pub max_attempts: u32,
+    /// The wait before the first retry
+    #[prost(uint64, tag = "2")]
+    pub delay_millis: u64,
+    /// Whether the wait doubles on every next retry
+    #[prost(bool, tag = "3")]
+    pub exponential: bool,
+    /// The longest wait, when exponential
+    #[prost(uint64, tag = "4")]
+    pub max_delay_millis: u64,
+    /// Whether a random part of up to half the wait is taken off
+    #[prost(bool, tag = "5")]
+    pub jitter: bool,
 }
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Uuid {
@@ -20,6 +59,7 @@ pub struct Uuid {
     #[prost(uint64, tag = "2")]
     pub id2: u64,
 }
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct JobStoredData {
@@ -41,11 +81,62 @@ pub struct JobStoredData {
     pub ran: bool,
     #[prost(bool, tag = "11")]
     pub stopped: bool,
+    #[prost(bool, tag = "12")]
+    pub last_run_failed: bool,
+    #[prost(bool, tag = "13")]
+    pub skip_if_last_failed: bool,
+    #[prost(message, optional, tag = "14")]
+    pub skip_if_upstream_failed: ::core::option::Option<Uuid>,
+    #[prost(bool, tag = "15")]
+    pub requires_approval: bool,
+    #[prost(enumeration = "MaintenanceWindowPolicy", tag = "16")]
+    pub maintenance_window_policy: i32,
+    #[prost(string, tag = "17")]
+    pub group: ::prost::alloc::string::String,
+    #[prost(uint64, optional, tag = "18")]
+    pub starting_deadline_seconds: ::core::option::Option<u64>,
+    #[prost(uint32, tag = "19")]
+    pub missed_count: u32,
+    #[prost(string, repeated, tag = "20")]
+    pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(bool, tag = "21")]
+    pub warm_start: bool,
+    #[prost(uint32, tag = "22")]
+    pub cost: u32,
+    #[prost(int32, tag = "23")]
+    pub priority: i32,
+    #[prost(string, optional, tag = "24")]
+    pub exclusive_group: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(uint32, tag = "25")]
+    pub consecutive_failures: u32,
+    #[prost(bytes = "vec", tag = "26")]
+    pub last_payload: ::prost::alloc::vec::Vec<u8>,
+    /// A name for humans to tell the job by, not necessarily unique
+    #[prost(string, tag = "27")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "28")]
+    pub retry_policy: ::core::option::Option<RetryPolicy>,
+    /// The error returned by the last run of a fallible job, if it failed
+    #[prost(string, optional, tag = "29")]
+    pub last_error: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(enumeration = "ConcurrencyPolicy", tag = "30")]
+    pub concurrency_policy: i32,
+    #[prost(enumeration = "LeapDayPolicy", tag = "31")]
+    pub leap_day_policy: i32,
+    #[prost(enumeration = "MisfirePolicy", tag = "32")]
+    pub misfire_policy: i32,
+    /// The job an AfterJob runs after, the delay being the repeated_every of its NonCronJob
+    #[prost(message, optional, tag = "33")]
+    pub after_job: ::core::option::Option<Uuid>,
+    /// The least seconds between two ticks, the ticks coming sooner being skipped
+    #[prost(uint64, optional, tag = "34")]
+    pub min_interval_seconds: ::core::option::Option<u64>,
     #[prost(oneof = "job_stored_data::Job", tags = "6, 7")]
     pub job: ::core::option::Option<job_stored_data::Job>,
 }
 /// Nested message and enum types in `JobStoredData`.
 pub mod job_stored_data {
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Job {
@@ -55,6 +146,7 @@ pub mod job_stored_data {
         NonCronJob(super::NonCronJob),
     }
 }
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct JobIdAndNotification {
@@ -63,6 +155,7 @@ pub struct JobIdAndNotification {
     #[prost(message, optional, tag = "2")]
     pub notification_id: ::core::option::Option<Uuid>,
 }
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct NotificationData {
@@ -73,6 +166,7 @@ pub struct NotificationData {
     #[prost(bytes = "vec", tag = "3")]
     pub extra: ::prost::alloc::vec::Vec<u8>,
 }
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct NotificationIdAndState {
@@ -81,6 +175,7 @@ pub struct NotificationIdAndState {
     #[prost(enumeration = "JobState", tag = "2")]
     pub job_state: i32,
 }
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct JobAndNextTick {
@@ -92,13 +187,17 @@ pub struct JobAndNextTick {
     pub next_tick: u64,
     #[prost(uint64, optional, tag = "4")]
     pub last_tick: ::core::option::Option<u64>,
+    #[prost(string, tag = "5")]
+    pub group: ::prost::alloc::string::String,
 }
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ListOfUuids {
     #[prost(message, repeated, tag = "1")]
     pub uuids: ::prost::alloc::vec::Vec<Uuid>,
 }
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct JobAndNotifications {
@@ -107,12 +206,93 @@ pub struct JobAndNotifications {
     #[prost(message, repeated, tag = "2")]
     pub notification_ids: ::prost::alloc::vec::Vec<Uuid>,
 }
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ListOfJobsAndNotifications {
     #[prost(message, repeated, tag = "1")]
     pub job_and_notifications: ::prost::alloc::vec::Vec<JobAndNotifications>,
 }
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Snapshot {
+    #[prost(uint64, tag = "1")]
+    pub taken_at: u64,
+    #[prost(message, repeated, tag = "2")]
+    pub jobs: ::prost::alloc::vec::Vec<JobStoredData>,
+    #[prost(message, repeated, tag = "3")]
+    pub notifications: ::prost::alloc::vec::Vec<NotificationData>,
+}
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TicksSet {
+    #[prost(message, optional, tag = "1")]
+    pub job_id: ::core::option::Option<Uuid>,
+    #[prost(uint64, optional, tag = "2")]
+    pub next_tick: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "3")]
+    pub last_tick: ::core::option::Option<u64>,
+}
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StoreEvent {
+    #[prost(uint64, tag = "1")]
+    pub sequence: u64,
+    #[prost(uint64, tag = "2")]
+    pub at: u64,
+    #[prost(oneof = "store_event::Event", tags = "3, 4, 5")]
+    pub event: ::core::option::Option<store_event::Event>,
+}
+/// Nested message and enum types in `StoreEvent`.
+pub mod store_event {
+    #[allow(clippy::large_enum_variant)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "3")]
+        JobSaved(super::JobStoredData),
+        #[prost(message, tag = "4")]
+        JobDeleted(super::Uuid),
+        #[prost(message, tag = "5")]
+        TicksSet(super::TicksSet),
+    }
+}
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LoggedSnapshot {
+    #[prost(uint64, tag = "1")]
+    pub sequence: u64,
+    #[prost(message, optional, tag = "2")]
+    pub snapshot: ::core::option::Option<Snapshot>,
+}
+/// A run of a job as kept in the run history, the times in milliseconds since the epoch
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobRun {
+    #[prost(message, optional, tag = "1")]
+    pub id: ::core::option::Option<Uuid>,
+    #[prost(message, optional, tag = "2")]
+    pub job_id: ::core::option::Option<Uuid>,
+    #[prost(uint64, tag = "3")]
+    pub scheduled_at: u64,
+    #[prost(uint64, tag = "4")]
+    pub started_at: u64,
+    #[prost(uint64, tag = "5")]
+    pub ended_at: u64,
+    #[prost(enumeration = "JobState", tag = "6")]
+    pub outcome: i32,
+    /// The error returned by the run of a fallible job, if it failed
+    #[prost(string, optional, tag = "7")]
+    pub error: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum JobState {
@@ -121,6 +301,15 @@ pub enum JobState {
     Started = 2,
     Done = 3,
     Removed = 4,
+    Skipped = 5,
+    Pending = 6,
+    Deferred = 7,
+    Cancelled = 8,
+    Failed = 9,
+    /// The run finished without error
+    Succeeded = 10,
+    /// The run of a fallible job returned an error, sent along with Failed
+    FailedWithError = 11,
 }
 impl JobState {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -134,6 +323,13 @@ impl JobState {
             JobState::Started => "Started",
             JobState::Done => "Done",
             JobState::Removed => "Removed",
+            JobState::Skipped => "Skipped",
+            JobState::Pending => "Pending",
+            JobState::Deferred => "Deferred",
+            JobState::Cancelled => "Cancelled",
+            JobState::Failed => "Failed",
+            JobState::Succeeded => "Succeeded",
+            JobState::FailedWithError => "FailedWithError",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -144,16 +340,148 @@ impl JobState {
             "Started" => Some(Self::Started),
             "Done" => Some(Self::Done),
             "Removed" => Some(Self::Removed),
+            "Skipped" => Some(Self::Skipped),
+            "Pending" => Some(Self::Pending),
+            "Deferred" => Some(Self::Deferred),
+            "Cancelled" => Some(Self::Cancelled),
+            "Failed" => Some(Self::Failed),
+            "Succeeded" => Some(Self::Succeeded),
+            "FailedWithError" => Some(Self::FailedWithError),
+            _ => None,
+        }
+    }
+}
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum MaintenanceWindowPolicy {
+    Defer = 0,
+    Cancel = 1,
+}
+impl MaintenanceWindowPolicy {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            MaintenanceWindowPolicy::Defer => "Defer",
+            MaintenanceWindowPolicy::Cancel => "Cancel",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "Defer" => Some(Self::Defer),
+            "Cancel" => Some(Self::Cancel),
+            _ => None,
+        }
+    }
+}
+/// When a cron schedule firing on February 29 fires in the years without one
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum LeapDayPolicy {
+    LeapYearsOnly = 0,
+    LastDayOfFebruary = 1,
+    FirstOfMarch = 2,
+}
+impl LeapDayPolicy {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            LeapDayPolicy::LeapYearsOnly => "LeapYearsOnly",
+            LeapDayPolicy::LastDayOfFebruary => "LastDayOfFebruary",
+            LeapDayPolicy::FirstOfMarch => "FirstOfMarch",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "LeapYearsOnly" => Some(Self::LeapYearsOnly),
+            "LastDayOfFebruary" => Some(Self::LastDayOfFebruary),
+            "FirstOfMarch" => Some(Self::FirstOfMarch),
+            _ => None,
+        }
+    }
+}
+/// What happens to the runs of a job that came due while the scheduler was down or suspended
+/// The values are prefixed as Skip is already a value of ConcurrencyPolicy
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum MisfirePolicy {
+    RunOnce = 0,
+    Skip = 1,
+    RunAll = 2,
+}
+impl MisfirePolicy {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            MisfirePolicy::RunOnce => "MisfirePolicyRunOnce",
+            MisfirePolicy::Skip => "MisfirePolicySkip",
+            MisfirePolicy::RunAll => "MisfirePolicyRunAll",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "MisfirePolicyRunOnce" => Some(Self::RunOnce),
+            "MisfirePolicySkip" => Some(Self::Skip),
+            "MisfirePolicyRunAll" => Some(Self::RunAll),
+            _ => None,
+        }
+    }
+}
+/// What happens to a due run of a job while its previous run is still running
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ConcurrencyPolicy {
+    Allow = 0,
+    Skip = 1,
+    Queue = 2,
+}
+impl ConcurrencyPolicy {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ConcurrencyPolicy::Allow => "Allow",
+            ConcurrencyPolicy::Skip => "Skip",
+            ConcurrencyPolicy::Queue => "Queue",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "Allow" => Some(Self::Allow),
+            "Skip" => Some(Self::Skip),
+            "Queue" => Some(Self::Queue),
             _ => None,
         }
     }
 }
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum JobType {
     Cron = 0,
     Repeated = 1,
     OneShot = 2,
+    /// Runs a delay after each successful run of another job
+    AfterJob = 3,
 }
 impl JobType {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -165,6 +493,7 @@ impl JobType {
             JobType::Cron => "Cron",
             JobType::Repeated => "Repeated",
             JobType::OneShot => "OneShot",
+            JobType::AfterJob => "AfterJob",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -173,6 +502,7 @@ impl JobType {
             "Cron" => Some(Self::Cron),
             "Repeated" => Some(Self::Repeated),
             "OneShot" => Some(Self::OneShot),
+            "AfterJob" => Some(Self::AfterJob),
             _ => None,
         }
     }
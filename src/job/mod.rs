@@ -1,22 +1,34 @@
+use crate::clock;
 #[cfg(not(feature = "has_bytes"))]
-use crate::job::job_data::{JobState, JobType};
+use crate::job::job_data::{
+    ConcurrencyPolicy, JobState, JobType, LeapDayPolicy, MaintenanceWindowPolicy, MisfirePolicy,
+    RetryPolicy,
+};
 #[cfg(feature = "has_bytes")]
-use crate::job::job_data_prost::{JobState, JobType};
+use crate::job::job_data_prost::{
+    ConcurrencyPolicy, JobState, JobType, LeapDayPolicy, MaintenanceWindowPolicy, MisfirePolicy,
+    RetryPolicy,
+};
 use crate::job_scheduler::JobsSchedulerLocked;
+use crate::leap;
 use crate::{JobScheduler, JobSchedulerError, JobStoredData};
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use cron::Schedule;
 use cron_job::CronJob;
 use non_cron_job::NonCronJob;
+use std::collections::HashMap;
 use std::convert::TryInto;
+use std::fmt::Display;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::Poll;
 use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::oneshot::Receiver;
-use tracing::error;
 use uuid::Uuid;
 
+mod builder;
 mod creator;
 mod cron_job;
 mod deleter;
@@ -28,13 +40,51 @@ mod non_cron_job;
 mod runner;
 pub mod to_code;
 use crate::notification::{NotificationCreator, NotificationDeleter};
+pub use builder::JobBuilder;
 pub use creator::JobCreator;
 pub use deleter::JobDeleter;
 pub use runner::JobRunner;
+pub(crate) use runner::{current_run, RunningExecutions};
+
+/// How many upcoming ticks of a schedule a jitter is checked against
+const JITTER_CHECKED_TICKS: usize = 64;
 
 pub type JobId = Uuid;
 pub type NotificationId = Uuid;
 
+///
+/// Run the future of an async job, dropping it when the receiver of its outcome is dropped, e.g.
+/// because the run was cancelled
+async fn run_async_job(
+    mut future: Pin<Box<dyn Future<Output = ()> + Send>>,
+    mut tx: tokio::sync::oneshot::Sender<bool>,
+) {
+    let done = std::future::poll_fn(|cx| {
+        if future.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(true);
+        }
+        tx.poll_closed(cx).map(|_| false)
+    })
+    .await;
+    if done {
+        if let Err(e) = tx.send(true) {
+            error!("Error notifying done {:?}", e);
+        }
+    }
+}
+
+///
+/// Run the future of an async job in a task of its own, within the span of the run
+fn spawn_async_job(
+    future: Pin<Box<dyn Future<Output = ()> + Send>>,
+    tx: tokio::sync::oneshot::Sender<bool>,
+) {
+    let run = runner::in_current_run(run_async_job(future, tx));
+    #[cfg(feature = "tracing")]
+    let run = tracing::Instrument::in_current_span(run);
+    tokio::spawn(run);
+}
+
 pub type JobToRun = dyn FnMut(JobId, JobsSchedulerLocked) + Send + Sync;
 pub type JobToRunAsync =
     dyn FnMut(JobId, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync;
@@ -43,6 +93,83 @@ pub type OnJobNotification = dyn FnMut(JobId, NotificationId, JobState) -> Pin<B
     + Send
     + Sync;
 
+///
+/// The name a timezone is stored under, empty when the job has none of its own, so that it takes
+/// the default timezone of the scheduler it is added to, or UTC like the jobs stored before
+/// timezones were supported
+fn timezone_name(timezone: Option<Tz>) -> String {
+    timezone.map(|tz| tz.name().to_string()).unwrap_or_default()
+}
+
+///
+/// The errors returned by the runs of fallible jobs, by run, or by job for code called outside
+/// of a run
+#[derive(Clone, Default)]
+pub struct RunErrors(Arc<Mutex<HashMap<Uuid, String>>>);
+
+impl RunErrors {
+    ///
+    /// The key of the current run of a job
+    pub(crate) fn key(job_id: Uuid) -> Uuid {
+        runner::current_run()
+            .map(|run| run.execution_id)
+            .unwrap_or(job_id)
+    }
+
+    fn set(&self, key: Uuid, error: String) {
+        if let Ok(mut w) = self.0.lock() {
+            w.insert(key, error);
+        }
+    }
+
+    ///
+    /// The error returned by the run that just finished, if it returned one
+    pub(crate) fn take(&self, key: &Uuid) -> Option<String> {
+        self.0.lock().ok().and_then(|mut w| w.remove(key))
+    }
+}
+
+///
+/// Job code running a fallible `run`, keeping the error it returns for the outcome of the run
+fn fallible<T, E>(mut run: T) -> impl FnMut(Uuid, JobsSchedulerLocked) + Send + Sync
+where
+    T: FnMut(Uuid, JobsSchedulerLocked) -> Result<(), E> + Send + Sync + 'static,
+    E: Display + 'static,
+{
+    move |job_id, job_scheduler| {
+        let run_errors = job_scheduler.context.run_errors.clone();
+        if let Err(e) = (run)(job_id, job_scheduler) {
+            run_errors.set(RunErrors::key(job_id), e.to_string());
+        }
+    }
+}
+
+///
+/// Async job code running the future of a fallible `run`, keeping the error it returns for the
+/// outcome of the run
+pub(crate) fn fallible_async<T, E>(
+    mut run: T,
+) -> impl FnMut(Uuid, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync
+where
+    T: FnMut(Uuid, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = Result<(), E>> + Send>>
+        + Send
+        + Sync
+        + 'static,
+    E: Display + 'static,
+{
+    move |job_id, job_scheduler| {
+        let run_errors = job_scheduler.context.run_errors.clone();
+        // Taken now, as the future runs in a task of its own
+        let key = RunErrors::key(job_id);
+        let future = (run)(job_id, job_scheduler);
+        Box::pin(async move {
+            if let Err(e) = future.await {
+                run_errors.set(key, e.to_string());
+            }
+        })
+    }
+}
+
 fn nop(_uuid: Uuid, _jobs: JobsSchedulerLocked) {
     // Do nothing
 }
@@ -57,8 +184,6 @@ fn nop_async(_uuid: Uuid, _jobs: JobsSchedulerLocked) -> Pin<Box<dyn Future<Outp
 pub struct JobLocked(pub(crate) Arc<RwLock<Box<dyn Job + Send + Sync>>>);
 
 pub trait Job {
-    fn is_cron_job(&self) -> bool;
-    fn schedule(&self) -> Option<Schedule>;
     fn repeated_every(&self) -> Option<u64>;
     fn last_tick(&self) -> Option<DateTime<Utc>>;
     fn set_last_tick(&mut self, tick: Option<DateTime<Utc>>);
@@ -66,12 +191,10 @@ pub trait Job {
     fn set_next_tick(&mut self, tick: Option<DateTime<Utc>>);
     fn set_count(&mut self, count: u32);
     fn count(&self) -> u32;
-    fn increment_count(&mut self);
     fn job_id(&self) -> Uuid;
     fn job_type(&self) -> JobType;
     fn ran(&self) -> bool;
     fn set_ran(&mut self, ran: bool);
-    fn stop(&self) -> bool;
     fn set_stopped(&mut self);
     fn set_started(&mut self);
     fn job_data_from_job(&mut self) -> Result<Option<JobStoredData>, JobSchedulerError>;
@@ -102,37 +225,35 @@ impl JobLocked {
         let schedule: Schedule = schedule
             .try_into()
             .map_err(|_| JobSchedulerError::ParseSchedule)?;
-        let job_id = Uuid::new_v4();
-        Ok(Self(Arc::new(RwLock::new(Box::new(CronJob {
-            data: JobStoredData {
-                id: Some(job_id.into()),
-                last_updated: None,
-                last_tick: None,
-                next_tick: schedule
-                    .upcoming(Utc)
-                    .next()
-                    .map(|t| t.timestamp() as u64)
-                    .unwrap_or(0),
-                job_type: JobType::Cron.into(),
-                count: 0,
-                extra: vec![],
-                ran: false,
-                stopped: false,
-                #[cfg(feature = "has_bytes")]
-                job: Some(job_data_prost::job_stored_data::Job::CronJob(
-                    job_data_prost::CronJob {
-                        schedule: schedule.to_string(),
-                    },
-                )),
-                #[cfg(not(feature = "has_bytes"))]
-                job: Some(job_data::job_stored_data::Job::CronJob(job_data::CronJob {
-                    schedule: schedule.to_string(),
-                })),
-            },
-            run: Box::new(run),
-            run_async: Box::new(nop_async),
-            async_job: false,
-        })))))
+        JobLocked::make_cron_job(schedule, None, Box::new(run), Box::new(nop_async), false)
+    }
+
+    /// Create a new cron job whose schedule is evaluated in `timezone` instead of UTC, so it
+    /// keeps running at the same local time across daylight saving time changes.
+    ///
+    /// ```rust,ignore
+    /// // Run at 9am in Johannesburg every weekday
+    /// let job = Job::new_tz("0 0 9 * * Mon-Fri", chrono_tz::Africa::Johannesburg, |_uuid, _lock| {
+    ///             println!("{:?} Good morning", chrono::Utc::now());
+    ///         });
+    /// ```
+    pub fn new_tz<S, T, E>(schedule: S, timezone: Tz, run: T) -> Result<Self, JobSchedulerError>
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) + Send + Sync,
+        S: TryInto<Schedule, Error = E>,
+        E: std::error::Error + 'static,
+    {
+        let schedule: Schedule = schedule
+            .try_into()
+            .map_err(|_| JobSchedulerError::ParseSchedule)?;
+        JobLocked::make_cron_job(
+            schedule,
+            Some(timezone),
+            Box::new(run),
+            Box::new(nop_async),
+            false,
+        )
     }
 
     /// Create a new async cron job.
@@ -159,37 +280,27 @@ impl JobLocked {
         let schedule: Schedule = schedule
             .try_into()
             .map_err(|_| JobSchedulerError::ParseSchedule)?;
-        let job_id = Uuid::new_v4();
-        Ok(Self(Arc::new(RwLock::new(Box::new(CronJob {
-            data: JobStoredData {
-                id: Some(job_id.into()),
-                last_updated: None,
-                last_tick: None,
-                next_tick: schedule
-                    .upcoming(Utc)
-                    .next()
-                    .map(|t| t.timestamp() as u64)
-                    .unwrap_or(0),
-                job_type: JobType::Cron.into(),
-                count: 0,
-                extra: vec![],
-                ran: false,
-                stopped: false,
-                #[cfg(feature = "has_bytes")]
-                job: Some(job_data_prost::job_stored_data::Job::CronJob(
-                    job_data_prost::CronJob {
-                        schedule: schedule.to_string(),
-                    },
-                )),
-                #[cfg(not(feature = "has_bytes"))]
-                job: Some(job_data::job_stored_data::Job::CronJob(job_data::CronJob {
-                    schedule: schedule.to_string(),
-                })),
-            },
-            run: Box::new(nop),
-            run_async: Box::new(run),
-            async_job: true,
-        })))))
+        JobLocked::make_cron_job(schedule, None, Box::new(nop), Box::new(run), true)
+    }
+
+    /// Create a new async cron job whose schedule is evaluated in `timezone`, like `new_tz`.
+    pub fn new_async_tz<S, T, E>(
+        schedule: S,
+        timezone: Tz,
+        run: T,
+    ) -> Result<Self, JobSchedulerError>
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = ()> + Send>>
+            + Send
+            + Sync,
+        S: TryInto<Schedule, Error = E>,
+        E: std::error::Error + 'static,
+    {
+        let schedule: Schedule = schedule
+            .try_into()
+            .map_err(|_| JobSchedulerError::ParseSchedule)?;
+        JobLocked::make_cron_job(schedule, Some(timezone), Box::new(nop), Box::new(run), true)
     }
 
     /// Create a new cron job.
@@ -238,6 +349,165 @@ impl JobLocked {
         JobLocked::new_async(schedule, run)
     }
 
+    /// Create a new cron job whose code is built by a factory that can fail, e.g. because the
+    /// client the job uses can't be configured. The error of the factory is returned as
+    /// `JobConstruction`. The schedule is checked before the factory is called.
+    ///
+    /// ```rust,ignore
+    /// let job = Job::try_new("0 0 2 * * *", || {
+    ///     let client = ExportClient::from_env()?;
+    ///     Ok::<_, ExportError>(move |_uuid, _lock| client.export())
+    /// })?;
+    /// ```
+    pub fn try_new<S, F, T, E, FE>(schedule: S, factory: F) -> Result<Self, JobSchedulerError>
+    where
+        F: FnOnce() -> Result<T, FE>,
+        FE: Display,
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) + Send + Sync,
+        S: TryInto<Schedule, Error = E>,
+        E: std::error::Error + 'static,
+    {
+        let schedule: Schedule = schedule
+            .try_into()
+            .map_err(|_| JobSchedulerError::ParseSchedule)?;
+        let run = factory().map_err(|e| JobSchedulerError::JobConstruction(e.to_string()))?;
+        JobLocked::new(schedule, run)
+    }
+
+    /// Create a new async cron job whose code is built by a factory that can fail, like
+    /// `try_new`.
+    pub fn try_new_async<S, F, T, E, FE>(schedule: S, factory: F) -> Result<Self, JobSchedulerError>
+    where
+        F: FnOnce() -> Result<T, FE>,
+        FE: Display,
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = ()> + Send>>
+            + Send
+            + Sync,
+        S: TryInto<Schedule, Error = E>,
+        E: std::error::Error + 'static,
+    {
+        let schedule: Schedule = schedule
+            .try_into()
+            .map_err(|_| JobSchedulerError::ParseSchedule)?;
+        let run = factory().map_err(|e| JobSchedulerError::JobConstruction(e.to_string()))?;
+        JobLocked::new_async(schedule, run)
+    }
+
+    /// Create a new cron job whose code can fail. A run returning an error counts as a failed run,
+    /// like a run that panics, and the error is kept as the last error of the job. A
+    /// `FailedWithError` notification is sent along with the `Failed` one.
+    ///
+    /// ```rust,ignore
+    /// let job = Job::new_fallible("0 0 2 * * *", |_uuid, _lock| {
+    ///     export()?;
+    ///     Ok::<_, ExportError>(())
+    /// })?;
+    /// ```
+    pub fn new_fallible<S, T, E, RE>(schedule: S, run: T) -> Result<Self, JobSchedulerError>
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) -> Result<(), RE> + Send + Sync,
+        RE: Display + 'static,
+        S: TryInto<Schedule, Error = E>,
+        E: std::error::Error + 'static,
+    {
+        JobLocked::new(schedule, fallible(run))
+    }
+
+    /// Create a new async cron job whose code can fail, like `new_fallible`.
+    ///
+    /// ```rust,ignore
+    /// let job = Job::new_fallible_async("0 0 2 * * *", |_uuid, _lock| {
+    ///     Box::pin(async move { export().await })
+    /// })?;
+    /// ```
+    pub fn new_fallible_async<S, T, E, RE>(schedule: S, run: T) -> Result<Self, JobSchedulerError>
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = Result<(), RE>> + Send>>
+            + Send
+            + Sync,
+        RE: Display + 'static,
+        S: TryInto<Schedule, Error = E>,
+        E: std::error::Error + 'static,
+    {
+        JobLocked::new_async(schedule, fallible_async(run))
+    }
+
+    fn make_cron_job(
+        schedule: Schedule,
+        timezone: Option<Tz>,
+        run: Box<JobToRun>,
+        run_async: Box<JobToRunAsync>,
+        async_job: bool,
+    ) -> Result<Self, JobSchedulerError> {
+        let job_id = Uuid::new_v4();
+        Ok(Self(Arc::new(RwLock::new(Box::new(CronJob {
+            data: JobStoredData {
+                id: Some(job_id.into()),
+                last_updated: None,
+                last_tick: None,
+                next_tick: leap::next_cron_tick(
+                    &schedule,
+                    timezone.unwrap_or(Tz::UTC),
+                    LeapDayPolicy::LeapYearsOnly,
+                    &clock::now(),
+                )
+                .map(|t| t.timestamp() as u64)
+                .unwrap_or(0),
+                job_type: JobType::Cron.into(),
+                count: 0,
+                extra: vec![],
+                ran: false,
+                stopped: false,
+                last_run_failed: false,
+                skip_if_last_failed: false,
+                skip_if_upstream_failed: None,
+                requires_approval: false,
+                maintenance_window_policy: MaintenanceWindowPolicy::Defer.into(),
+                group: String::new(),
+                starting_deadline_seconds: None,
+                missed_count: 0,
+                tags: vec![],
+                warm_start: false,
+                cost: 0,
+                priority: 0,
+                exclusive_group: None,
+                consecutive_failures: 0,
+                last_payload: vec![],
+                name: String::new(),
+                retry_policy: None,
+                last_error: None,
+                concurrency_policy: ConcurrencyPolicy::Allow.into(),
+                leap_day_policy: LeapDayPolicy::LeapYearsOnly.into(),
+                misfire_policy: MisfirePolicy::RunOnce.into(),
+                after_job: None,
+                min_interval_seconds: None,
+                #[cfg(feature = "has_bytes")]
+                job: Some(job_data_prost::job_stored_data::Job::CronJob(
+                    job_data_prost::CronJob {
+                        schedule: schedule.to_string(),
+                        timezone: timezone_name(timezone),
+                        exclusions: vec![],
+                        jitter_seconds: 0,
+                    },
+                )),
+                #[cfg(not(feature = "has_bytes"))]
+                job: Some(job_data::job_stored_data::Job::CronJob(job_data::CronJob {
+                    schedule: schedule.to_string(),
+                    timezone: timezone_name(timezone),
+                    exclusions: vec![],
+                    jitter_seconds: 0,
+                })),
+            },
+            run,
+            run_async,
+            async_job,
+        })))))
+    }
+
     fn make_one_shot_job(
         duration: Duration,
         run: Box<JobToRun>,
@@ -263,11 +533,37 @@ impl JobLocked {
                 extra: vec![],
                 ran: false,
                 stopped: false,
+                last_run_failed: false,
+                skip_if_last_failed: false,
+                skip_if_upstream_failed: None,
+                requires_approval: false,
+                maintenance_window_policy: MaintenanceWindowPolicy::Defer.into(),
+                group: String::new(),
+                starting_deadline_seconds: None,
+                missed_count: 0,
+                tags: vec![],
+                warm_start: false,
+                cost: 0,
+                priority: 0,
+                exclusive_group: None,
+                consecutive_failures: 0,
+                last_payload: vec![],
+                name: String::new(),
+                retry_policy: None,
+                last_error: None,
+                concurrency_policy: ConcurrencyPolicy::Allow.into(),
+                leap_day_policy: LeapDayPolicy::LeapYearsOnly.into(),
+                misfire_policy: MisfirePolicy::RunOnce.into(),
+                after_job: None,
+                min_interval_seconds: None,
                 #[cfg(feature = "has_bytes")]
                 job: Some(job_data_prost::job_stored_data::Job::NonCronJob(
                     job_data_prost::NonCronJob {
                         repeating: false,
                         repeated_every: duration.as_secs(),
+                        monotonic: false,
+                        fixed_delay: false,
+                        instants: vec![],
                     },
                 )),
                 #[cfg(not(feature = "has_bytes"))]
@@ -275,6 +571,9 @@ impl JobLocked {
                     job_data::NonCronJob {
                         repeating: false,
                         repeated_every: duration.as_secs(),
+                        monotonic: false,
+                        fixed_delay: false,
+                        instants: vec![],
                     },
                 )),
             },
@@ -342,9 +641,9 @@ impl JobLocked {
                 id: Some(id.into()),
                 last_updated: None,
                 last_tick: None,
-                next_tick: chrono::Utc::now()
+                next_tick: clock::now()
                     .checked_add_signed(chrono::Duration::seconds(
-                        instant.duration_since(Instant::now()).as_secs() as i64,
+                        instant.duration_since(clock::instant()).as_secs() as i64,
                     ))
                     .map(|t| t.timestamp() as u64)
                     .unwrap_or(0),
@@ -353,18 +652,47 @@ impl JobLocked {
                 extra: vec![],
                 ran: false,
                 stopped: false,
+                last_run_failed: false,
+                skip_if_last_failed: false,
+                skip_if_upstream_failed: None,
+                requires_approval: false,
+                maintenance_window_policy: MaintenanceWindowPolicy::Defer.into(),
+                group: String::new(),
+                starting_deadline_seconds: None,
+                missed_count: 0,
+                tags: vec![],
+                warm_start: false,
+                cost: 0,
+                priority: 0,
+                exclusive_group: None,
+                consecutive_failures: 0,
+                last_payload: vec![],
+                name: String::new(),
+                retry_policy: None,
+                last_error: None,
+                concurrency_policy: ConcurrencyPolicy::Allow.into(),
+                leap_day_policy: LeapDayPolicy::LeapYearsOnly.into(),
+                misfire_policy: MisfirePolicy::RunOnce.into(),
+                after_job: None,
+                min_interval_seconds: None,
                 #[cfg(feature = "has_bytes")]
                 job: Some(job_data_prost::job_stored_data::Job::NonCronJob(
                     job_data_prost::NonCronJob {
                         repeating: false,
-                        repeated_every: instant.duration_since(Instant::now()).as_secs(),
+                        repeated_every: instant.duration_since(clock::instant()).as_secs(),
+                        monotonic: false,
+                        fixed_delay: false,
+                        instants: vec![],
                     },
                 )),
                 #[cfg(not(feature = "has_bytes"))]
                 job: Some(job_data::job_stored_data::Job::NonCronJob(
                     job_data::NonCronJob {
                         repeating: false,
-                        repeated_every: instant.duration_since(Instant::now()).as_secs(),
+                        repeated_every: instant.duration_since(clock::instant()).as_secs(),
+                        monotonic: false,
+                        fixed_delay: false,
+                        instants: vec![],
                     },
                 )),
             },
@@ -425,6 +753,83 @@ impl JobLocked {
         JobLocked::make_new_one_shot_at_an_instant(instant, Box::new(nop), Box::new(run), true)
     }
 
+    fn make_at_instants(
+        instants: Vec<DateTime<Utc>>,
+        run: Box<JobToRun>,
+        run_async: Box<JobToRunAsync>,
+        async_job: bool,
+    ) -> Result<Self, JobSchedulerError> {
+        let mut instants: Vec<u64> = instants
+            .iter()
+            .map(|instant| instant.timestamp().max(0) as u64)
+            .collect();
+        instants.sort_unstable();
+        instants.dedup();
+        let first = match instants.first() {
+            Some(first) => *first,
+            None => {
+                return Err(JobSchedulerError::JobConstruction(
+                    "A job needs at least one instant to run at".to_string(),
+                ))
+            }
+        };
+        let mut job = JobLocked::make_one_shot_job(Duration::ZERO, run, run_async, async_job)?;
+        let mut data = job.job_data()?;
+        data.next_tick = first;
+        match data.job.as_mut() {
+            #[cfg(feature = "has_bytes")]
+            Some(job_data_prost::job_stored_data::Job::NonCronJob(ncj)) => ncj.instants = instants,
+            #[cfg(not(feature = "has_bytes"))]
+            Some(job_data::job_stored_data::Job::NonCronJob(ncj)) => ncj.instants = instants,
+            _ => {}
+        }
+        job.set_job_data(data)?;
+        Ok(job)
+    }
+
+    /// Create a new job running once at each of `instants`, in whatever order they are given,
+    /// and removed after the last one. An instant given twice runs once.
+    ///
+    /// ```rust,ignore
+    /// let release = Utc.with_ymd_and_hms(2026, 11, 2, 9, 0, 0).unwrap();
+    /// let job = Job::new_at_instants(
+    ///     vec![release - chrono::Duration::days(1), release, release + chrono::Duration::days(7)],
+    ///     |_uuid, _lock| println!("{:?} Reminding about the release", chrono::Utc::now()),
+    /// )?;
+    /// sched.add(job).await?;
+    /// ```
+    pub fn new_at_instants<T>(
+        instants: Vec<DateTime<Utc>>,
+        run: T,
+    ) -> Result<Self, JobSchedulerError>
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) + Send + Sync,
+    {
+        JobLocked::make_at_instants(instants, Box::new(run), Box::new(nop_async), false)
+    }
+
+    /// Create a new async job running once at each of `instants`, and removed after the last one.
+    ///
+    /// ```rust,ignore
+    /// let job = Job::new_at_instants_async(instants, |_uuid, _lock| Box::pin(async move {
+    ///     println!("{:?} Reminding about the release", chrono::Utc::now());
+    /// }))?;
+    /// sched.add(job).await?;
+    /// ```
+    pub fn new_at_instants_async<T>(
+        instants: Vec<DateTime<Utc>>,
+        run: T,
+    ) -> Result<Self, JobSchedulerError>
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = ()> + Send>>
+            + Send
+            + Sync,
+    {
+        JobLocked::make_at_instants(instants, Box::new(nop), Box::new(run), true)
+    }
+
     fn make_new_repeated(
         duration: Duration,
         run: Box<JobToRun>,
@@ -440,7 +845,7 @@ impl JobLocked {
                 id: Some(id.into()),
                 last_updated: None,
                 last_tick: None,
-                next_tick: chrono::Utc::now()
+                next_tick: clock::now()
                     .checked_add_signed(chrono::Duration::seconds(duration.as_secs() as i64))
                     .map(|t| t.timestamp() as u64)
                     .unwrap_or(0),
@@ -449,12 +854,38 @@ impl JobLocked {
                 extra: vec![],
                 ran: false,
                 stopped: false,
+                last_run_failed: false,
+                skip_if_last_failed: false,
+                skip_if_upstream_failed: None,
+                requires_approval: false,
+                maintenance_window_policy: MaintenanceWindowPolicy::Defer.into(),
+                group: String::new(),
+                starting_deadline_seconds: None,
+                missed_count: 0,
+                tags: vec![],
+                warm_start: false,
+                cost: 0,
+                priority: 0,
+                exclusive_group: None,
+                consecutive_failures: 0,
+                last_payload: vec![],
+                name: String::new(),
+                retry_policy: None,
+                last_error: None,
+                concurrency_policy: ConcurrencyPolicy::Allow.into(),
+                leap_day_policy: LeapDayPolicy::LeapYearsOnly.into(),
+                misfire_policy: MisfirePolicy::RunOnce.into(),
+                after_job: None,
+                min_interval_seconds: None,
 
                 #[cfg(feature = "has_bytes")]
                 job: Some(job_data_prost::job_stored_data::Job::NonCronJob(
                     job_data_prost::NonCronJob {
                         repeating: true,
                         repeated_every: duration.as_secs(),
+                        monotonic: false,
+                        fixed_delay: false,
+                        instants: vec![],
                     },
                 )),
                 #[cfg(not(feature = "has_bytes"))]
@@ -462,6 +893,9 @@ impl JobLocked {
                     job_data::NonCronJob {
                         repeating: true,
                         repeated_every: duration.as_secs(),
+                        monotonic: false,
+                        fixed_delay: false,
+                        instants: vec![],
                     },
                 )),
             },
@@ -513,18 +947,220 @@ impl JobLocked {
         JobLocked::make_new_repeated(duration, Box::new(nop), Box::new(run), true)
     }
 
+    fn make_fixed_delay(
+        delay: Duration,
+        run: Box<JobToRun>,
+        run_async: Box<JobToRunAsync>,
+        async_job: bool,
+    ) -> Result<Self, JobSchedulerError> {
+        let mut job = JobLocked::make_new_repeated(delay, run, run_async, async_job)?;
+        let mut data = job.job_data()?;
+        match data.job.as_mut() {
+            #[cfg(feature = "has_bytes")]
+            Some(job_data_prost::job_stored_data::Job::NonCronJob(ncj)) => ncj.fixed_delay = true,
+            #[cfg(not(feature = "has_bytes"))]
+            Some(job_data::job_stored_data::Job::NonCronJob(ncj)) => ncj.fixed_delay = true,
+            _ => {}
+        }
+        job.set_job_data(data)?;
+        Ok(job)
+    }
+
+    /// Create a new job running at a fixed rate, every `duration` from the start of its previous
+    /// run whether that run is done or not, like `new_repeated`.
+    ///
+    /// ```rust,ignore
+    /// let job = Job::new_fixed_rate(Duration::from_secs(30), |_uuid, _lock| {
+    ///     println!("{:?} I start every 30 seconds", chrono::Utc::now());
+    /// })?;
+    /// ```
+    pub fn new_fixed_rate<T>(duration: Duration, run: T) -> Result<Self, JobSchedulerError>
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) + Send + Sync,
+    {
+        JobLocked::make_new_repeated(duration, Box::new(run), Box::new(nop_async), false)
+    }
+
+    /// Create a new async job running at a fixed rate, every `duration` from the start of its
+    /// previous run whether that run is done or not, like `new_repeated_async`.
+    pub fn new_fixed_rate_async<T>(duration: Duration, run: T) -> Result<Self, JobSchedulerError>
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = ()> + Send>>
+            + Send
+            + Sync,
+    {
+        JobLocked::make_new_repeated(duration, Box::new(nop), Box::new(run), true)
+    }
+
+    /// Create a new job running with a fixed delay, `delay` after its previous run ended, so a
+    /// slow run pushes the next one back instead of overlapping with it. The first run is
+    /// `delay` after the job is created.
+    ///
+    /// ```rust,ignore
+    /// let job = Job::new_fixed_delay(Duration::from_secs(30), |_uuid, _lock| {
+    ///     println!("{:?} I start 30 seconds after my last run ended", chrono::Utc::now());
+    /// })?;
+    /// ```
+    pub fn new_fixed_delay<T>(delay: Duration, run: T) -> Result<Self, JobSchedulerError>
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) + Send + Sync,
+    {
+        JobLocked::make_fixed_delay(delay, Box::new(run), Box::new(nop_async), false)
+    }
+
+    /// Create a new async job running with a fixed delay, `delay` after its previous run ended.
+    ///
+    /// ```rust,ignore
+    /// let job = Job::new_fixed_delay_async(Duration::from_secs(30), |_uuid, _lock| Box::pin(async move {
+    ///     sync_mailbox().await;
+    /// }))?;
+    /// ```
+    pub fn new_fixed_delay_async<T>(delay: Duration, run: T) -> Result<Self, JobSchedulerError>
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = ()> + Send>>
+            + Send
+            + Sync,
+    {
+        JobLocked::make_fixed_delay(delay, Box::new(nop), Box::new(run), true)
+    }
+
+    fn make_after_job(
+        upstream: Uuid,
+        delay: Duration,
+        run: Box<JobToRun>,
+        run_async: Box<JobToRunAsync>,
+        async_job: bool,
+    ) -> Result<Self, JobSchedulerError> {
+        let id = Uuid::new_v4();
+        let job = NonCronJob {
+            run,
+            run_async,
+            async_job,
+            data: JobStoredData {
+                id: Some(id.into()),
+                last_updated: None,
+                last_tick: None,
+                next_tick: 0,
+                job_type: JobType::AfterJob.into(),
+                count: 0,
+                extra: vec![],
+                ran: false,
+                stopped: false,
+                last_run_failed: false,
+                skip_if_last_failed: false,
+                skip_if_upstream_failed: None,
+                requires_approval: false,
+                maintenance_window_policy: MaintenanceWindowPolicy::Defer.into(),
+                group: String::new(),
+                starting_deadline_seconds: None,
+                missed_count: 0,
+                tags: vec![],
+                warm_start: false,
+                cost: 0,
+                priority: 0,
+                exclusive_group: None,
+                consecutive_failures: 0,
+                last_payload: vec![],
+                name: String::new(),
+                retry_policy: None,
+                last_error: None,
+                concurrency_policy: ConcurrencyPolicy::Allow.into(),
+                leap_day_policy: LeapDayPolicy::LeapYearsOnly.into(),
+                misfire_policy: MisfirePolicy::RunOnce.into(),
+                after_job: Some(upstream.into()),
+                min_interval_seconds: None,
+                #[cfg(feature = "has_bytes")]
+                job: Some(job_data_prost::job_stored_data::Job::NonCronJob(
+                    job_data_prost::NonCronJob {
+                        repeating: true,
+                        repeated_every: delay.as_secs(),
+                        monotonic: false,
+                        fixed_delay: false,
+                        instants: vec![],
+                    },
+                )),
+                #[cfg(not(feature = "has_bytes"))]
+                job: Some(job_data::job_stored_data::Job::NonCronJob(
+                    job_data::NonCronJob {
+                        repeating: true,
+                        repeated_every: delay.as_secs(),
+                        monotonic: false,
+                        fixed_delay: false,
+                        instants: vec![],
+                    },
+                )),
+            },
+        };
+
+        let job: Arc<RwLock<Box<dyn Job + Send + Sync + 'static>>> =
+            Arc::new(RwLock::new(Box::new(job)));
+
+        Ok(Self(job))
+    }
+
+    /// Create a new job run `delay` after each successful run of the `upstream` job, rather than
+    /// on a clock, e.g. for a step of a pipeline whose upstream step finishes at varying times.
+    /// The job has no next tick until the upstream job succeeds.
+    ///
+    /// ```rust,ignore
+    /// let mut sched = JobScheduler::new();
+    /// let report = Job::new_after_job(import_id, Duration::from_secs(15 * 60), |_uuid, _lock| {
+    ///     println!("{:?} Reporting 15 minutes after the import", chrono::Utc::now());
+    /// })?;
+    /// sched.add(report)
+    /// tokio::spawn(sched.start());
+    /// ```
+    pub fn new_after_job<T>(
+        upstream: Uuid,
+        delay: Duration,
+        run: T,
+    ) -> Result<Self, JobSchedulerError>
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) + Send + Sync,
+    {
+        JobLocked::make_after_job(upstream, delay, Box::new(run), Box::new(nop_async), false)
+    }
+
+    /// Create a new async job run `delay` after each successful run of the `upstream` job.
+    ///
+    /// ```rust,ignore
+    /// let mut sched = JobScheduler::new();
+    /// let report = Job::new_after_job_async(import_id, Duration::from_secs(15 * 60), |_uuid, _lock| Box::pin(async move {
+    ///     println!("{:?} Reporting 15 minutes after the import", chrono::Utc::now());
+    /// }))?;
+    /// sched.add(report)
+    /// tokio::spawn(sched.start());
+    /// ```
+    pub fn new_after_job_async<T>(
+        upstream: Uuid,
+        delay: Duration,
+        run: T,
+    ) -> Result<Self, JobSchedulerError>
+    where
+        T: 'static,
+        T: FnMut(Uuid, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = ()> + Send>>
+            + Send
+            + Sync,
+    {
+        JobLocked::make_after_job(upstream, delay, Box::new(nop), Box::new(run), true)
+    }
+
     ///
     /// The `tick` method returns a true if there was an invocation needed after it was last called
     /// This method will also change the last tick on itself
     pub fn tick(&mut self) -> Result<bool, JobSchedulerError> {
-        let now = Utc::now();
-        let (job_type, last_tick, next_tick, schedule, repeated_every, ran, count) = {
+        let now = clock::now();
+        let (job_type, last_tick, next_tick, repeated_every, ran, count) = {
             let r = self.0.read().map_err(|_| JobSchedulerError::TickError)?;
             (
                 r.job_type(),
                 r.last_tick(),
                 r.next_tick(),
-                r.schedule(),
                 r.repeated_every(),
                 r.ran(),
                 r.count(),
@@ -547,6 +1183,11 @@ impl JobLocked {
                 matches!(now_to_next, std::cmp::Ordering::Greater)
                     || matches!(now_to_next, std::cmp::Ordering::Equal)
             }
+            (None, Some(next_tick), JobType::AfterJob) => {
+                let now_to_next = now.cmp(next_tick);
+                matches!(now_to_next, std::cmp::Ordering::Greater)
+                    || matches!(now_to_next, std::cmp::Ordering::Equal)
+            }
             (Some(last_tick), Some(next_tick), _) => {
                 let now_to_next = now.cmp(next_tick);
                 let last_to_next = last_tick.cmp(next_tick);
@@ -560,9 +1201,16 @@ impl JobLocked {
         };
 
         let next_tick = if must_run {
+            let data = self.job_data()?;
+            // At least the min interval of the job after the tick that ran
+            let after = next_tick
+                .map(|next_tick| data.spaced_after(&next_tick))
+                .filter(|after| *after > now)
+                .unwrap_or(now);
             match job_type {
-                JobType::Cron => schedule.and_then(|s| s.after(&now).next()),
-                JobType::OneShot => None,
+                JobType::Cron => data.next_cron_tick(&after),
+                JobType::OneShot => data.instant_after(&after),
+                JobType::AfterJob => None,
                 JobType::Repeated => repeated_every.and_then(|r| {
                     next_tick
                         .and_then(|nt| nt.checked_add_signed(chrono::Duration::seconds(r as i64)))
@@ -649,7 +1297,7 @@ impl JobLocked {
             job_scheduler.init().await?;
         }
         let context = job_scheduler.context();
-        NotificationDeleter::remove(&context, notification_id, states)
+        NotificationDeleter::remove(&context, notification_id, states).await
     }
 
     ///
@@ -773,6 +1421,252 @@ impl JobLocked {
         Ok(())
     }
 
+    ///
+    /// Skip the runs of this job, with a `Skipped` notification, while its previous run failed
+    pub fn set_skip_if_last_failed(&mut self, skip: bool) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.skip_if_last_failed = skip;
+        self.set_job_data(data)
+    }
+
+    ///
+    /// Skip the runs of this job, with a `Skipped` notification, while the last run of the
+    /// upstream job failed
+    pub fn set_skip_if_upstream_failed(
+        &mut self,
+        upstream: Option<Uuid>,
+    ) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.skip_if_upstream_failed = upstream.map(|u| u.into());
+        self.set_job_data(data)
+    }
+
+    ///
+    /// Require due runs of this job to be approved before they are run. Until approved, a due run
+    /// is pending and a `Pending` notification is sent.
+    pub fn set_requires_approval(
+        &mut self,
+        requires_approval: bool,
+    ) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.requires_approval = requires_approval;
+        self.set_job_data(data)
+    }
+
+    ///
+    /// What happens to a run of this job that falls in a maintenance window. It is either deferred
+    /// until the window is over, sending a `Deferred` notification, or cancelled, sending a
+    /// `Skipped` notification.
+    pub fn set_maintenance_window_policy(
+        &mut self,
+        policy: MaintenanceWindowPolicy,
+    ) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.maintenance_window_policy = policy.into();
+        self.set_job_data(data)
+    }
+
+    ///
+    /// What happens to a due run of this job while its previous run is still running. By default
+    /// the runs overlap. A skipped run sends a `Skipped` notification, and a queued run starts
+    /// once the runs before it are done.
+    pub fn set_concurrency_policy(
+        &mut self,
+        policy: ConcurrencyPolicy,
+    ) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.concurrency_policy = policy.into();
+        self.set_job_data(data)
+    }
+
+    ///
+    /// What happens to the runs of this job that came due while the scheduler was down or its
+    /// host suspended, found over a minute late. By default such a job runs once and then moves
+    /// on to its next tick from now. A skipped run sends a `Skipped` notification.
+    pub fn set_misfire_policy(&mut self, policy: MisfirePolicy) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.misfire_policy = policy.into();
+        self.set_job_data(data)
+    }
+
+    ///
+    /// Keep the timing of this repeated job on the monotonic clock, like `tokio::time::interval`,
+    /// so a heartbeat every 30 seconds still runs every 30 seconds when the wall clock is stepped,
+    /// e.g. by NTP. The ticks of the job move along with a step of the wall clock, instead of
+    /// running the job at once when the clock jumps forward or not for a while when it jumps back.
+    /// Has no effect on cron jobs, which follow the wall clock.
+    pub fn set_monotonic(&mut self, monotonic: bool) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        match data.job.as_mut() {
+            #[cfg(feature = "has_bytes")]
+            Some(job_data_prost::job_stored_data::Job::NonCronJob(ncj)) => {
+                ncj.monotonic = monotonic
+            }
+            #[cfg(not(feature = "has_bytes"))]
+            Some(job_data::job_stored_data::Job::NonCronJob(ncj)) => ncj.monotonic = monotonic,
+            _ => return Ok(()),
+        }
+        self.set_job_data(data)
+    }
+
+    ///
+    /// When the cron schedule of this job, if it fires on February 29, fires in the years
+    /// without one. By default it only fires in leap years.
+    pub fn set_leap_day_policy(&mut self, policy: LeapDayPolicy) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.leap_day_policy = policy.into();
+        if let Some(next_tick) = data.next_cron_tick(&clock::now()) {
+            data.set_next_tick(Some(next_tick));
+        }
+        self.set_job_data(data)
+    }
+
+    ///
+    /// Don't fire the cron schedule of this job at the times matching one of `exclusions`, e.g.
+    /// `0 * 2 * * *` for no runs between 2:00 and 3:00, in the timezone of the job. Replaces the
+    /// exclusions set before. Has no effect on other jobs.
+    pub fn set_exclusions(&mut self, exclusions: Vec<Schedule>) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        match data.job.as_mut() {
+            #[cfg(feature = "has_bytes")]
+            Some(job_data_prost::job_stored_data::Job::CronJob(cj)) => {
+                cj.exclusions = exclusions.iter().map(|e| e.to_string()).collect()
+            }
+            #[cfg(not(feature = "has_bytes"))]
+            Some(job_data::job_stored_data::Job::CronJob(cj)) => {
+                cj.exclusions = exclusions.iter().map(|e| e.to_string()).collect()
+            }
+            _ => return Ok(()),
+        }
+        data.set_next_tick(data.next_cron_tick(&clock::now()));
+        self.set_job_data(data)
+    }
+
+    ///
+    /// Put each tick of the cron schedule of this job off by up to `jitter`, in whole seconds,
+    /// so the many jobs sharing a schedule like `0 0 * * * *` don't all fire at once. The delay
+    /// of a tick is the same in every scheduler sharing the store. A jitter as long as the time
+    /// between two ticks of the schedule is refused, as it would put a tick past the next one.
+    /// Has no effect on other jobs.
+    pub fn set_jitter(&mut self, jitter: Duration) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        if let Some(schedule) = data.schedule() {
+            let ticks = schedule
+                .upcoming(data.timezone())
+                .take(JITTER_CHECKED_TICKS)
+                .collect::<Vec<_>>();
+            let period = ticks.windows(2).map(|w| w[1] - w[0]).min();
+            if period.is_some_and(|period| period.to_std().is_ok_and(|p| jitter >= p)) {
+                return Err(JobSchedulerError::JobConstruction(
+                    "a jitter must be shorter than the time between two ticks".to_string(),
+                ));
+            }
+        }
+        match data.job.as_mut() {
+            #[cfg(feature = "has_bytes")]
+            Some(job_data_prost::job_stored_data::Job::CronJob(cj)) => {
+                cj.jitter_seconds = jitter.as_secs()
+            }
+            #[cfg(not(feature = "has_bytes"))]
+            Some(job_data::job_stored_data::Job::CronJob(cj)) => {
+                cj.jitter_seconds = jitter.as_secs()
+            }
+            _ => return Ok(()),
+        }
+        data.set_next_tick(data.next_cron_tick(&clock::now()));
+        self.set_job_data(data)
+    }
+
+    ///
+    /// Keep the runs of this job at least `min_interval`, in whole seconds, apart: a tick coming
+    /// sooner after the previous one is skipped, like the close matches of a cron schedule
+    /// `0 0,5 * * * *` with a min interval of 30 minutes. `None` lets the ticks come as they are.
+    pub fn set_min_interval(
+        &mut self,
+        min_interval: Option<Duration>,
+    ) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.min_interval_seconds = min_interval.map(|m| m.as_secs());
+        self.set_job_data(data)
+    }
+
+    ///
+    /// Skip a run of this job that cannot start within `deadline` of its scheduled time, e.g.
+    /// because the scheduler is overloaded, instead of running it late. A skipped run sends a
+    /// `Skipped` notification and is counted in the job's `missed_count`.
+    pub fn set_starting_deadline(
+        &mut self,
+        deadline: Option<Duration>,
+    ) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.starting_deadline_seconds = deadline.map(|d| d.as_secs());
+        self.set_job_data(data)
+    }
+
+    ///
+    /// Prepare the runs of this job ahead of the scheduler's pass and fire them from a task
+    /// waiting for their exact instant, keeping the start delay of sub-second jobs low. The delay
+    /// is reported by `JobScheduler::drift`.
+    pub fn set_warm_start(&mut self, warm_start: bool) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.warm_start = warm_start;
+        self.set_job_data(data)
+    }
+
+    ///
+    /// The cost of a run of this job, charged to the budget of the scheduler. A run that does not
+    /// fit in what is left of the budget is deferred to the next budget window.
+    pub fn set_cost(&mut self, cost: u32) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.cost = cost;
+        self.set_job_data(data)
+    }
+
+    ///
+    /// The priority of this job, runs of higher priority are charged to the budget first
+    pub fn set_priority(&mut self, priority: i32) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.priority = priority;
+        self.set_job_data(data)
+    }
+
+    ///
+    /// Put this job in an exclusive group. Only one job of the group runs at a time in the
+    /// scheduler, a run of another job of the group waits until it is done.
+    pub fn set_exclusive_group(&mut self, group: Option<String>) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.exclusive_group = group;
+        self.set_job_data(data)
+    }
+
+    ///
+    /// Set the tags of this job, used to scope scheduler handles with `JobScheduler::scoped`
+    pub fn set_tags(&mut self, tags: Vec<String>) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.tags = tags;
+        self.set_job_data(data)
+    }
+
+    ///
+    /// Retry the failed runs of this job according to `policy`, as long as the retry comes before
+    /// the next tick. A run is only a dead letter once its retries ran out.
+    pub fn set_retry_policy(
+        &mut self,
+        policy: Option<RetryPolicy>,
+    ) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.retry_policy = policy;
+        self.set_job_data(data)
+    }
+
+    ///
+    /// Set the name of this job, for humans to tell it by. Names don't need to be unique.
+    pub fn set_name(&mut self, name: impl Into<String>) -> Result<(), JobSchedulerError> {
+        let mut data = self.job_data()?;
+        data.name = name.into();
+        self.set_job_data(data)
+    }
+
     ///
     /// Get the job data
     pub fn job_data(&mut self) -> Result<JobStoredData, JobSchedulerError> {
@@ -783,3 +1677,177 @@ impl JobLocked {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::JobLocked;
+    use crate::clock;
+    use crate::{JobNotification, JobScheduler, TimingDecision};
+    use chrono::Duration as ChronoDuration;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    /// The next run of a fixed-delay job starts its delay after the previous one ended
+    #[tokio::test(start_paused = true)]
+    async fn fixed_delay() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let starts: Arc<Mutex<Vec<Instant>>> = Arc::default();
+        let ends: Arc<Mutex<Vec<Instant>>> = Arc::default();
+        let (started, ended) = (starts.clone(), ends.clone());
+        let mut job = JobLocked::new_fixed_delay_async(Duration::from_secs(1), move |_, _| {
+            let (started, ended) = (started.clone(), ended.clone());
+            Box::pin(async move {
+                started.lock().unwrap().push(Instant::now());
+                // Longer than the delay, a fixed rate would start the next run meanwhile
+                tokio::time::sleep(Duration::from_secs(2)).await;
+                ended.lock().unwrap().push(Instant::now());
+            })
+        })
+        .expect("job");
+        let data = job.job_data().expect("data");
+        assert!(data.fixed_delay());
+        sched.add(job).await.expect("add");
+        sched.start().await.expect("start");
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while starts.lock().unwrap().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("second run");
+        let second_start = starts.lock().unwrap()[1];
+        let first_end = *ends
+            .lock()
+            .unwrap()
+            .first()
+            .expect("first run ended before the second started");
+        assert!(second_start >= first_end);
+        sched.shutdown().await.expect("shutdown");
+    }
+
+    /// A job at a list of instants runs at each of them once, in order, and is removed after
+    #[tokio::test(start_paused = true)]
+    async fn at_instants() {
+        assert!(matches!(
+            JobLocked::new_at_instants(vec![], |_, _| {}),
+            Err(crate::JobSchedulerError::JobConstruction(_))
+        ));
+
+        let now = clock::now();
+        let (first, second) = (
+            now + ChronoDuration::seconds(1),
+            now + ChronoDuration::seconds(3),
+        );
+        let runs = Arc::new(AtomicUsize::new(0));
+        let ran = runs.clone();
+        let mut job = JobLocked::new_at_instants(vec![second, first, second], move |_, _| {
+            ran.fetch_add(1, Ordering::SeqCst);
+        })
+        .expect("job");
+        let data = job.job_data().expect("data");
+        let (first, second) = (
+            crate::JobAndNextTick::utc(first.timestamp() as u64),
+            crate::JobAndNextTick::utc(second.timestamp() as u64),
+        );
+        assert_eq!(data.instants(), vec![first, second]);
+        assert_eq!(data.next_tick_utc(), Some(first));
+        assert_eq!(
+            crate::scheduler::Scheduler::tick_after(
+                &data,
+                &first,
+                crate::leap::default_search_horizon()
+            ),
+            Some(second)
+        );
+        assert_eq!(
+            crate::scheduler::Scheduler::tick_after(
+                &data,
+                &second,
+                crate::leap::default_search_horizon()
+            ),
+            None
+        );
+        assert_eq!(data.upcoming_ticks(5), vec![first, second]);
+
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let job_id = sched.add(job).await.expect("add");
+        sched.start().await.expect("start");
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while sched.time_next_scheduled_for(&job_id).await.is_ok() {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("job removed");
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        sched.shutdown().await.expect("shutdown");
+    }
+
+    /// Wait for `job_id` to change to `state`, failing after a few seconds
+    async fn wait_for(
+        events: &mut tokio::sync::broadcast::Receiver<(uuid::Uuid, JobNotification)>,
+        job_id: uuid::Uuid,
+        state: JobNotification,
+    ) {
+        tokio::time::timeout(Duration::from_secs(3), async {
+            loop {
+                match events.recv().await {
+                    Ok((id, changed)) if id == job_id && changed == state => return,
+                    Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(e) => panic!("{:?}", e),
+                }
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("never {:?}", state))
+    }
+
+    /// A job run a delay after each run of its upstream job, and only then
+    #[tokio::test(start_paused = true)]
+    async fn after_job() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let upstream =
+            JobLocked::new_repeated(Duration::from_secs(3600), |_, _| {}).expect("upstream job");
+        let upstream_id = sched.add(upstream).await.expect("add upstream");
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counted = runs.clone();
+        let follower =
+            JobLocked::new_after_job(upstream_id, Duration::from_secs(1), move |_, _| {
+                counted.fetch_add(1, Ordering::SeqCst);
+            })
+            .expect("follower job");
+        let follower_id = sched.add(follower).await.expect("add follower");
+        let mut events = sched.subscribe_events();
+        sched.start().await.expect("start");
+
+        // Waiting on the upstream job without a next tick, and without being removed for it
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+        let explanation = sched.explain(&follower_id).await.expect("follower kept");
+        assert_eq!(explanation.next_tick, None);
+        assert!(explanation.decisions.contains(&TimingDecision::AfterJob {
+            upstream: upstream_id,
+            delay_seconds: 1,
+        }));
+
+        let triggered = clock::now();
+        sched.trigger(&upstream_id).await.expect("trigger upstream");
+        wait_for(&mut events, upstream_id, JobNotification::Done).await;
+        let next_tick = sched
+            .next_tick_for_job(follower_id)
+            .await
+            .expect("follower")
+            .expect("next tick after the upstream run");
+        assert!(next_tick > triggered);
+        assert!(next_tick <= triggered + ChronoDuration::seconds(2));
+
+        wait_for(&mut events, follower_id, JobNotification::Done).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        let explanation = sched.explain(&follower_id).await.expect("follower kept");
+        assert_eq!(explanation.next_tick, None);
+        sched.shutdown().await.expect("shutdown");
+    }
+}
@@ -1,60 +1,404 @@
+use crate::clock;
 use crate::context::Context;
+use crate::internal_error::InternalErrorKind;
 #[cfg(not(feature = "has_bytes"))]
-use crate::job::job_data::JobState;
+use crate::job::job_data::{ConcurrencyPolicy, JobState};
 #[cfg(feature = "has_bytes")]
-use crate::job::job_data_prost::JobState;
+use crate::job::job_data_prost::{ConcurrencyPolicy, JobState};
 use crate::job::to_code::JobCode;
-use crate::job_scheduler::JobsSchedulerLocked;
+use crate::job::JobToRunAsync;
+use crate::job_scheduler::{JobsSchedulerLocked, RunningExecution};
+use crate::run_scope::{RunFuture, RunScope};
 use crate::JobSchedulerError;
+use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::broadcast::{Receiver, Sender};
-use tokio::sync::RwLock;
-use tracing::error;
+use tokio::sync::{oneshot, OwnedMutexGuard, OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio::task::AbortHandle;
 use uuid::Uuid;
 
+pub(crate) type RunningExecutions = Arc<RwLock<HashMap<Uuid, (RunningExecution, AbortHandle)>>>;
+
+tokio::task_local! {
+    static CURRENT_RUN: RunningExecution;
+}
+
+///
+/// The run the code of a job is called for or running in, so the outcome of a run is kept apart
+/// from that of another run of the same job running at the same time
+pub(crate) fn current_run() -> Option<RunningExecution> {
+    CURRENT_RUN.try_with(|run| *run).ok()
+}
+
+///
+/// `future` running in the current run, if there is one, for a task spawned by the code of a job
+pub(crate) fn in_current_run<F>(future: F) -> Pin<Box<dyn Future<Output = F::Output> + Send>>
+where
+    F: Future + Send + 'static,
+{
+    match current_run() {
+        Some(run) => Box::pin(CURRENT_RUN.scope(run, future)),
+        None => Box::pin(future),
+    }
+}
+
+///
+/// Call the synchronous code of a job on a thread of the blocking pool, in the current run, so a
+/// long run, or one waiting on a lock like the GIL, doesn't hold up the runtime the scheduler
+/// ticks on
+pub(crate) async fn run_blocking<F, T>(f: F) -> Result<T, tokio::task::JoinError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let run = current_run();
+    let f = move || match run {
+        Some(run) => CURRENT_RUN.sync_scope(run, f),
+        None => f(),
+    };
+    // The code logs to the span of the run, and the tasks it spawns are instrumented with it
+    #[cfg(feature = "tracing")]
+    let f = {
+        let span = tracing::Span::current();
+        let dispatch = tracing::dispatcher::get_default(|dispatch| dispatch.clone());
+        move || tracing::dispatcher::with_default(&dispatch, || span.in_scope(f))
+    };
+    tokio::task::spawn_blocking(f).await
+}
+
 #[derive(Default)]
 pub struct JobRunner {}
 
+///
+/// The place of a run in the queues of its sequential tags. The runs ahead are done once their
+/// place is dropped.
+#[derive(Default)]
+struct QueuePlace {
+    ahead: Vec<oneshot::Receiver<()>>,
+    done: Vec<oneshot::Sender<()>>,
+}
+
+impl QueuePlace {
+    ///
+    /// Whether the runs ahead are all done already
+    fn is_ready(&mut self) -> bool {
+        self.ahead
+            .iter_mut()
+            .all(|ahead| !matches!(ahead.try_recv(), Err(oneshot::error::TryRecvError::Empty)))
+    }
+
+    async fn wait(&mut self) {
+        for ahead in self.ahead.drain(..) {
+            let _ = ahead.await;
+        }
+    }
+}
+
+///
+/// The turn of a run to take a slot of the execution pool, after the runs activated before it
+struct PoolTurn {
+    pool: Arc<Semaphore>,
+    ahead: Option<oneshot::Receiver<()>>,
+    taken: Option<oneshot::Sender<()>>,
+}
+
+impl PoolTurn {
+    ///
+    /// Give up the turn, for a run that has to wait for other runs before it takes a slot. The
+    /// runs activated after it take their turn once the runs before it took theirs.
+    fn leave(&mut self) {
+        let ahead = self.ahead.take();
+        let taken = self.taken.take();
+        if taken.is_none() {
+            return;
+        }
+        tokio::spawn(async move {
+            if let Some(ahead) = ahead {
+                let _ = ahead.await;
+            }
+            drop(taken);
+        });
+    }
+
+    async fn take(self) -> Option<OwnedSemaphorePermit> {
+        if let Some(ahead) = self.ahead {
+            let _ = ahead.await;
+        }
+        let permit = self.pool.acquire_owned().await.ok();
+        drop(self.taken);
+        permit
+    }
+}
+
 impl JobRunner {
+    ///
+    /// Wait until no other job of the exclusive group of the job runs, giving up the turn of the
+    /// run in the execution pool while another one does
+    async fn lock_exclusive_group(
+        context: &Context,
+        job_id: Uuid,
+        turn: &mut Option<PoolTurn>,
+    ) -> Option<OwnedMutexGuard<()>> {
+        let group = {
+            let mut r = context.metadata_storage.write().await;
+            match r.get(job_id).await {
+                Ok(Some(job)) => job.exclusive_group?,
+                _ => return None,
+            }
+        };
+        let lock = {
+            let mut w = context.exclusive_groups.write().await;
+            w.entry(group).or_default().clone()
+        };
+        match lock.clone().try_lock_owned() {
+            Ok(guard) => Some(guard),
+            Err(_) => {
+                if let Some(turn) = turn.as_mut() {
+                    turn.leave();
+                }
+                Some(lock.lock_owned().await)
+            }
+        }
+    }
+
+    ///
+    /// What happens to a run of the job while its previous run is still running
+    async fn concurrency_policy(context: &Context, job_id: Uuid) -> ConcurrencyPolicy {
+        let mut r = context.metadata_storage.write().await;
+        match r.get(job_id).await {
+            Ok(Some(job)) => ConcurrencyPolicy::from_i32(job.concurrency_policy)
+                .unwrap_or(ConcurrencyPolicy::Allow),
+            _ => ConcurrencyPolicy::Allow,
+        }
+    }
+
+    async fn is_running(running_executions: &RunningExecutions, job_id: Uuid) -> bool {
+        let r = running_executions.read().await;
+        r.values().any(|(execution, _)| execution.job_id == job_id)
+    }
+
+    ///
+    /// The span of a run of a job, which its code and the events of the run are recorded in
+    #[cfg(feature = "tracing")]
+    async fn run_span(context: &Context, job_id: Uuid, execution_id: Uuid) -> tracing::Span {
+        let name = {
+            let mut r = context.metadata_storage.write().await;
+            match r.get(job_id).await {
+                Ok(Some(job)) => job.name,
+                _ => String::new(),
+            }
+        };
+        let scheduled_at = {
+            let r = context.scheduled_times.read().await;
+            r.get(&job_id).copied()
+        };
+        let attempt = match scheduled_at {
+            Some(scheduled_at) => context.retry_attempts.attempt(&job_id, scheduled_at).await,
+            None => 1,
+        };
+        tracing::info_span!(
+            "job.run",
+            job_id = %job_id,
+            execution_id = %execution_id,
+            name = %name,
+            scheduled_at = ?scheduled_at,
+            attempt = attempt,
+        )
+    }
+
+    ///
+    /// Call the code of a job within its run scopes, as the code of sync jobs runs when called
+    fn scoped(
+        code: Arc<RwLock<Box<JobToRunAsync>>>,
+        job_id: Uuid,
+        job_scheduler: JobsSchedulerLocked,
+        scopes: Vec<Arc<RunScope>>,
+    ) -> RunFuture {
+        let run: RunFuture = Box::pin(async move {
+            let v = {
+                let mut code = code.write().await;
+                (code)(job_id, job_scheduler)
+            };
+            v.await;
+        });
+        scopes.iter().fold(run, |run, scope| scope(run))
+    }
+
+    ///
+    /// Take the place of a run at the end of the queues of the sequential tags of the job
+    async fn join_queues(
+        context: &Context,
+        tails: &mut HashMap<String, oneshot::Receiver<()>>,
+        job_id: Uuid,
+    ) -> QueuePlace {
+        let mut place = QueuePlace::default();
+        if context.sequential_tags.is_empty() {
+            return place;
+        }
+        let tags = {
+            let mut r = context.metadata_storage.write().await;
+            match r.get(job_id).await {
+                Ok(Some(job)) => job.tags,
+                _ => return place,
+            }
+        };
+        for tag in tags {
+            if !context.sequential_tags.contains(&tag) {
+                continue;
+            }
+            let (done, tail) = oneshot::channel();
+            if let Some(ahead) = tails.insert(tag, tail) {
+                place.ahead.push(ahead);
+            }
+            place.done.push(done);
+        }
+        place
+    }
+
     async fn listen_for_activations(
         job_code: Arc<RwLock<Box<dyn JobCode + Send + Sync>>>,
         mut rx: Receiver<Uuid>,
         tx_notify: Sender<(Uuid, JobState)>,
         job_scheduler: JobsSchedulerLocked,
+        running_executions: RunningExecutions,
     ) {
-        loop {
-            let val = rx.recv().await;
-            if let Err(e) = val {
-                error!("Error receiving {:?}", e);
-                break;
+        // The last run queued for each sequential tag, done when its sender is dropped
+        let mut tails = HashMap::new();
+        // The last run of each job queued behind its previous runs
+        let mut job_tails: HashMap<Uuid, oneshot::Receiver<()>> = HashMap::new();
+        // Taken once the last activated run has a slot of the execution pool
+        let mut last_turn: Option<oneshot::Receiver<()>> = None;
+        let internal_errors = job_scheduler.context.internal_errors.clone();
+        while let Some(uuid) = internal_errors.recv(&mut rx).await {
+            let draining = {
+                let r = job_scheduler.context.draining.read().await;
+                *r
+            };
+            if draining {
+                if let Err(e) = tx_notify.send((uuid, JobState::Skipped)) {
+                    error!(job_id = uuid; "Error sending skipped run {:?}", e);
+                }
+                continue;
             }
-            let uuid = val.unwrap();
+            let policy = JobRunner::concurrency_policy(&job_scheduler.context, uuid).await;
+            if policy == ConcurrencyPolicy::Skip
+                && JobRunner::is_running(&running_executions, uuid).await
+            {
+                if let Err(e) = tx_notify.send((uuid, JobState::Skipped)) {
+                    error!(job_id = uuid; "Error sending skipped run {:?}", e);
+                }
+                continue;
+            }
+            #[cfg(feature = "metrics")]
+            crate::metrics::run_started(uuid);
             {
                 let tx = tx_notify.clone();
                 tokio::spawn(async move {
                     if let Err(e) = tx.send((uuid, JobState::Started)) {
-                        error!("Error sending error listening for activation {:?}", e);
+                        error!(job_id = uuid; "Error sending error listening for activation {:?}", e);
                     }
                 });
             }
+            let mut place = JobRunner::join_queues(&job_scheduler.context, &mut tails, uuid).await;
+            if policy == ConcurrencyPolicy::Queue {
+                let (done, tail) = oneshot::channel();
+                if let Some(ahead) = job_tails.insert(uuid, tail) {
+                    place.ahead.push(ahead);
+                }
+                place.done.push(done);
+            } else {
+                job_tails.remove(&uuid);
+            }
             let mut w = job_code.write().await;
             let code = w.get(uuid).await;
             match code {
                 Ok(Some(job)) => {
-                    let mut job = job.write().await;
-                    let v = (job)(uuid, job_scheduler.clone());
+                    let scheduled = {
+                        let r = job_scheduler.context.scheduled_times.read().await;
+                        r.get(&uuid).copied()
+                    };
+                    let started = clock::now();
+                    let execution = RunningExecution {
+                        execution_id: Uuid::new_v4(),
+                        job_id: uuid,
+                        scheduled: scheduled.unwrap_or(started),
+                        started,
+                    };
+                    let scopes = job_scheduler.context.run_scopes.for_run(uuid).await;
+                    let v = if scopes.is_empty() {
+                        let mut job = job.write().await;
+                        CURRENT_RUN.sync_scope(execution, || (job)(uuid, job_scheduler.clone()))
+                    } else {
+                        JobRunner::scoped(job, uuid, job_scheduler.clone(), scopes)
+                    };
+                    let v = CURRENT_RUN.scope(execution, v);
                     let tx = tx_notify.clone();
-                    tokio::spawn(async move {
+                    #[cfg(feature = "tracing")]
+                    let span =
+                        JobRunner::run_span(&job_scheduler.context, uuid, execution.execution_id)
+                            .await;
+                    // Locked until the run is listed, so it cannot finish and unlist itself first
+                    let mut running = running_executions.write().await;
+                    let for_run = running_executions.clone();
+                    let context = job_scheduler.context.clone();
+                    let turn = context.execution_pool.clone().map(|pool| {
+                        let (taken, next) = oneshot::channel();
+                        PoolTurn {
+                            pool,
+                            ahead: last_turn.replace(next),
+                            taken: Some(taken),
+                        }
+                    });
+                    let run = async move {
+                        // A run waiting for others takes its slot once done waiting, so it
+                        // doesn't keep a slot from the runs that can run now
+                        let mut turn = turn;
+                        if !place.is_ready() {
+                            if let Some(turn) = turn.as_mut() {
+                                turn.leave();
+                            }
+                        }
+                        place.wait().await;
+                        let exclusive =
+                            JobRunner::lock_exclusive_group(&context, uuid, &mut turn).await;
+                        let slot = match turn {
+                            Some(turn) => turn.take().await,
+                            None => None,
+                        };
+                        debug!(job_id = uuid, execution_id = execution.execution_id; "Run started");
                         v.await;
+                        drop(exclusive);
+                        drop(place);
+                        drop(slot);
+                        {
+                            let mut w = for_run.write().await;
+                            w.remove(&execution.execution_id);
+                        }
                         if let Err(e) = tx.send((uuid, JobState::Done)) {
-                            error!("Error sending spawned task {:?}", e);
+                            error!(
+                                job_id = uuid,
+                                execution_id = execution.execution_id;
+                                "Error sending spawned task {:?}",
+                                e
+                            );
                         }
-                    });
+                    };
+                    #[cfg(feature = "tracing")]
+                    let run = tracing::Instrument::instrument(run, span);
+                    let handle = tokio::spawn(run);
+                    running.insert(execution.execution_id, (execution, handle.abort_handle()));
                 }
-                _ => {
-                    error!("Error getting {:?} from job code", uuid);
+                other => {
+                    internal_errors
+                        .report(
+                            InternalErrorKind::Storage,
+                            Some(uuid),
+                            "Error getting the job code",
+                            other.err(),
+                        )
+                        .await;
                     continue;
                 }
             }
@@ -69,6 +413,7 @@ impl JobRunner {
         let job_code = context.job_code.clone();
         let notify_tx = context.notify_tx.clone();
         let job_activation_rx = context.job_activation_tx.subscribe();
+        let running_executions = context.running_executions.clone();
 
         Box::pin(async move {
             tokio::spawn(JobRunner::listen_for_activations(
@@ -76,8 +421,196 @@ impl JobRunner {
                 job_activation_rx,
                 notify_tx,
                 job_scheduler,
+                running_executions,
             ));
             Ok(())
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{JobBuilder, JobSchedulerBuilder};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// A run queued behind another run of its sequential tag leaves the last slot of the pool to
+    /// a job that can run now
+    #[tokio::test]
+    async fn queued_run_leaves_its_slot() {
+        let mut sched = JobSchedulerBuilder::default()
+            .max_concurrency(2)
+            .sequential_tags(vec!["import".to_string()])
+            .build()
+            .await
+            .expect("scheduler");
+        let sequential = || {
+            JobBuilder::new()
+                .with_schedule("0 0 0 1 1 *")
+                .with_tags(vec!["import".to_string()])
+                .with_run_async(|_, _| {
+                    Box::pin(async move {
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    })
+                })
+                .build()
+                .expect("job")
+        };
+        let first = sched.add(sequential()).await.expect("add the first");
+        let second = sched.add(sequential()).await.expect("add the second");
+        let ran = Arc::new(AtomicBool::new(false));
+        let flag = ran.clone();
+        let other = JobBuilder::new()
+            .with_schedule("0 0 0 1 1 *")
+            .with_run_sync(move |_, _| flag.store(true, Ordering::SeqCst))
+            .build()
+            .expect("job");
+        let other = sched.add(other).await.expect("add the other");
+        sched.start().await.expect("start");
+
+        sched.trigger(&first).await.expect("trigger the first");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        sched.trigger(&second).await.expect("trigger the second");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        sched.trigger(&other).await.expect("trigger the other");
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(
+            ran.load(Ordering::SeqCst),
+            "the other job waited for a slot"
+        );
+        sched.shutdown().await.expect("shutdown");
+    }
+
+    /// The code of a job and the outcome of its run are in the `job.run` span of the run
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn run_spans() {
+        use std::collections::HashMap;
+        use std::sync::atomic::AtomicU64;
+        use std::sync::Mutex;
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+        use tracing_core::span::Current;
+
+        /// The fields recorded, by name
+        #[derive(Default)]
+        struct Fields(HashMap<String, String>);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .insert(field.name().to_string(), format!("{:?}", value));
+            }
+        }
+
+        type Spans = HashMap<u64, (&'static Metadata<'static>, Fields)>;
+        /// The events with the span they were in
+        type Events = Vec<(Fields, Option<u64>)>;
+
+        /// The spans by id with their metadata and fields, the spans entered and the events in the
+        /// innermost span entered
+        #[derive(Clone, Default)]
+        struct Recorded {
+            next_id: Arc<AtomicU64>,
+            spans: Arc<Mutex<Spans>>,
+            entered: Arc<Mutex<Vec<u64>>>,
+            events: Arc<Mutex<Events>>,
+        }
+
+        impl Subscriber for Recorded {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+                let mut fields = Fields::default();
+                span.record(&mut fields);
+                let metadata = span.metadata();
+                self.spans.lock().unwrap().insert(id, (metadata, fields));
+                Id::from_u64(id)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                let mut fields = Fields::default();
+                event.record(&mut fields);
+                let span = self.entered.lock().unwrap().last().copied();
+                self.events.lock().unwrap().push((fields, span));
+            }
+
+            fn enter(&self, span: &Id) {
+                self.entered.lock().unwrap().push(span.into_u64());
+            }
+
+            fn current_span(&self) -> Current {
+                let entered = self.entered.lock().unwrap();
+                let spans = self.spans.lock().unwrap();
+                match entered.last().and_then(|id| Some((*id, spans.get(id)?.0))) {
+                    Some((id, metadata)) => Current::new(Id::from_u64(id), metadata),
+                    None => Current::none(),
+                }
+            }
+
+            fn exit(&self, span: &Id) {
+                let mut entered = self.entered.lock().unwrap();
+                if let Some(i) = entered.iter().rposition(|id| *id == span.into_u64()) {
+                    entered.remove(i);
+                }
+            }
+        }
+
+        let recorded = Recorded::default();
+        let _subscriber = tracing::subscriber::set_default(recorded.clone());
+        let mut sched = crate::JobScheduler::new().await.expect("scheduler");
+        let mut job = crate::Job::new_async("0 0 0 1 1 *", |_, _| {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                tracing::info!("Inside the job");
+            })
+        })
+        .expect("job");
+        job.set_name("traced").expect("name");
+        let job_id = sched.add(job).await.expect("add");
+        let mut events = sched.subscribe_events();
+        sched.start().await.expect("start");
+        sched.trigger(&job_id).await.expect("trigger");
+        tokio::time::timeout(Duration::from_secs(3), async {
+            loop {
+                if let Ok((id, crate::JobNotification::Done)) = events.recv().await {
+                    if id == job_id {
+                        return;
+                    }
+                }
+            }
+        })
+        .await
+        .expect("run done");
+
+        {
+            let spans = recorded.spans.lock().unwrap();
+            let events = recorded.events.lock().unwrap();
+            let span_of = |message: &str| {
+                events
+                    .iter()
+                    .find(|(fields, _)| {
+                        fields.0.get("message").map(|m| m.as_str()) == Some(message)
+                    })
+                    .and_then(|(_, span)| spans.get(span.as_ref()?))
+            };
+            let (metadata, fields) = span_of("Inside the job").expect("job event in a span");
+            assert_eq!(metadata.name(), "job.run");
+            assert_eq!(fields.0.get("job_id"), Some(&job_id.to_string()));
+            assert_eq!(fields.0.get("name").map(|n| n.as_str()), Some("traced"));
+            assert_eq!(fields.0.get("attempt").map(|n| n.as_str()), Some("1"));
+            let (metadata, _) = span_of("Run ended").expect("outcome in a span");
+            assert_eq!(metadata.name(), "job.run");
+        }
+        sched.shutdown().await.expect("shutdown");
+    }
+}
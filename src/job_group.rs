@@ -0,0 +1,90 @@
+use crate::job::JobLocked;
+use crate::job_scheduler::JobsSchedulerLocked;
+use crate::{JobSchedulerError, MaintenanceWindowPolicy};
+use std::time::Duration;
+use uuid::Uuid;
+
+///
+/// Defaults shared by similar jobs. A job added through the group gets the tags of the group
+/// added to its own, and takes each option of the group that it left at its default.
+#[derive(Clone, Debug, Default)]
+pub struct JobGroup {
+    pub tags: Vec<String>,
+    pub skip_if_last_failed: bool,
+    pub requires_approval: bool,
+    pub maintenance_window_policy: Option<MaintenanceWindowPolicy>,
+    pub starting_deadline: Option<Duration>,
+    pub exclusive_group: Option<String>,
+}
+
+impl JobGroup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn skip_if_last_failed(mut self, skip: bool) -> Self {
+        self.skip_if_last_failed = skip;
+        self
+    }
+
+    pub fn requires_approval(mut self, requires_approval: bool) -> Self {
+        self.requires_approval = requires_approval;
+        self
+    }
+
+    pub fn maintenance_window_policy(mut self, policy: MaintenanceWindowPolicy) -> Self {
+        self.maintenance_window_policy = Some(policy);
+        self
+    }
+
+    pub fn starting_deadline(mut self, deadline: Duration) -> Self {
+        self.starting_deadline = Some(deadline);
+        self
+    }
+
+    pub fn exclusive_group(mut self, exclusive_group: impl Into<String>) -> Self {
+        self.exclusive_group = Some(exclusive_group.into());
+        self
+    }
+
+    ///
+    /// Apply the defaults of the group to a job
+    pub fn apply(&self, job: &mut JobLocked) -> Result<(), JobSchedulerError> {
+        let mut data = job.job_data()?;
+        for tag in self.tags.iter() {
+            if !data.tags.contains(tag) {
+                data.tags.push(tag.clone());
+            }
+        }
+        data.skip_if_last_failed |= self.skip_if_last_failed;
+        data.requires_approval |= self.requires_approval;
+        if let Some(policy) = self.maintenance_window_policy {
+            if data.maintenance_window_policy == i32::from(MaintenanceWindowPolicy::Defer) {
+                data.maintenance_window_policy = policy.into();
+            }
+        }
+        if data.starting_deadline_seconds.is_none() {
+            data.starting_deadline_seconds = self.starting_deadline.map(|d| d.as_secs());
+        }
+        if data.exclusive_group.is_none() {
+            data.exclusive_group = self.exclusive_group.clone();
+        }
+        job.set_job_data(data)
+    }
+
+    ///
+    /// Add a job to the scheduler with the defaults of the group
+    pub async fn add(
+        &self,
+        scheduler: &JobsSchedulerLocked,
+        mut job: JobLocked,
+    ) -> Result<Uuid, JobSchedulerError> {
+        self.apply(&mut job)?;
+        scheduler.add(job).await
+    }
+}
@@ -1,63 +1,238 @@
+use crate::backup::{self, OnBackup, RestoreConflict, RestoreReport, Snapshot};
+use crate::budget::Budget;
+use crate::clock;
 use crate::context::Context;
+use crate::data_trigger::DataTrigger;
+use crate::day_of_week::DayOfWeekNumbering;
+use crate::dispatch::Dispatch;
+use crate::drift::DriftStats;
+use crate::dump::{self, StateDump};
 use crate::error::JobSchedulerError;
+use crate::explain::{self, JobExplanation};
+use crate::internal_error::OnInternalError;
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{JobRun, JobState, JobType};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{JobRun, JobState, JobType};
 use crate::job::to_code::{JobCode, NotificationCode};
-use crate::job::{JobCreator, JobDeleter, JobLocked, JobRunner};
-use crate::notification::{NotificationCreator, NotificationDeleter, NotificationRunner};
+use crate::job::{JobCreator, JobDeleter, JobLocked, JobRunner, JobToRunAsync};
+use crate::leader::LeaderElection;
+use crate::leap;
+use crate::maintenance_window::MaintenanceWindow;
+use crate::notification::{
+    EscalationPolicy, NotificationCreator, NotificationDeleter, NotificationRunner,
+    OutboxDispatcher,
+};
+use crate::payload::PayloadGenerator;
+use crate::reconciliation::{self, ReconciliationReport};
+use crate::redrive::{
+    self, DeadLetter, DeadLetterQuery, DeadLetterReason, RedriveFilter, RedriveProgress,
+};
+use crate::reschedule_hook::RescheduleHook;
+use crate::run_condition::RunCondition;
+use crate::run_lock::RunLock;
+use crate::run_scope::RunScope;
 use crate::scheduler::Scheduler;
+use crate::scoped::ScopedScheduler;
 use crate::simple::{
     SimpleJobCode, SimpleMetadataStore, SimpleNotificationCode, SimpleNotificationStore,
 };
-use crate::store::{MetaDataStorage, NotificationStore};
-use chrono::{DateTime, NaiveDateTime, Utc};
+use crate::sla::{CompletionStats, OnSlaBreach, SlaTarget};
+use crate::store::{
+    ExportFormat, JobQuery, MetaDataStorage, NotificationStore, Outbox, Page, RunHistoryStore,
+};
+use crate::{JobStoredData, LeapDayPolicy};
+use chrono::{DateTime, FixedOffset, Offset, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::future::Future;
+use std::io::Write;
+use std::ops::Range;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 #[cfg(feature = "signal")]
 use tokio::signal::unix::SignalKind;
-use tokio::sync::RwLock;
-use tracing::{error, info};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::{RwLock, Semaphore};
 use uuid::Uuid;
 
+/// How often a graceful shutdown checks whether the runs in progress are done
+const DRAIN_POLL: std::time::Duration = std::time::Duration::from_millis(100);
+
+///
+/// A due run of a job that waits on approval
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PendingApproval {
+    pub job_id: Uuid,
+    pub due: DateTime<Utc>,
+}
+
+///
+/// A run of a job that is in progress
+#[cfg_attr(
+    feature = "graphql",
+    derive(async_graphql::SimpleObject),
+    graphql(name = "Run")
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunningExecution {
+    pub execution_id: Uuid,
+    pub job_id: Uuid,
+    /// The tick the run is for
+    pub scheduled: DateTime<Utc>,
+    pub started: DateTime<Utc>,
+}
+
+///
+/// A time in a named timezone
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalTime {
+    /// The IANA name of the timezone, like `Europe/Amsterdam`
+    pub timezone: String,
+    pub time: DateTime<FixedOffset>,
+}
+
+impl From<DateTime<Tz>> for LocalTime {
+    fn from(time: DateTime<Tz>) -> Self {
+        Self {
+            timezone: time.timezone().name().to_string(),
+            time: time.with_timezone(&time.offset().fix()),
+        }
+    }
+}
+
+impl Display for LocalTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            self.time.format("%Y-%m-%d %H:%M:%S %:z"),
+            self.timezone
+        )
+    }
+}
+
+///
+/// A job as found in the metadata storage
+#[cfg_attr(
+    feature = "graphql",
+    derive(async_graphql::SimpleObject),
+    graphql(name = "Job")
+)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JobInfo {
+    pub job_id: Uuid,
+    pub name: String,
+    pub group: String,
+    /// The cron schedule of cron jobs
+    pub schedule: Option<String>,
+    /// The interval of repeated jobs, in seconds
+    pub repeated_every: Option<u64>,
+    pub job_type: JobType,
+    pub next_tick: Option<DateTime<Utc>>,
+    /// The next tick in each of the display timezones of the scheduler
+    pub next_tick_local: Vec<LocalTime>,
+    pub last_tick: Option<DateTime<Utc>>,
+    /// The runs of the job so far
+    pub count: u32,
+    /// Whether the job is paused, its runs not fired until it is started again
+    pub stopped: bool,
+    pub last_run_failed: bool,
+    /// The runs in a row that failed, up to the last one
+    pub consecutive_failures: u32,
+    /// The error returned by the last run of a fallible job, if it failed
+    pub last_error: Option<String>,
+    /// Runs skipped because they could not start within the job's starting deadline
+    pub missed_count: u32,
+    pub tags: Vec<String>,
+}
+
+impl JobInfo {
+    pub(crate) fn new(job_id: Uuid, job: JobStoredData, timezones: &[Tz]) -> Self {
+        Self {
+            next_tick_local: job
+                .next_ticks_in(timezones)
+                .into_iter()
+                .map(LocalTime::from)
+                .collect(),
+            job_id,
+            name: job.name.clone(),
+            schedule: job.schedule().map(|s| s.to_string()),
+            repeated_every: job.repeated_every(),
+            job_type: JobType::from_i32(job.job_type).unwrap_or(JobType::Cron),
+            next_tick: job.next_tick_utc(),
+            last_tick: job.last_tick_utc(),
+            count: job.count,
+            stopped: job.stopped,
+            last_run_failed: job.last_run_failed,
+            consecutive_failures: job.consecutive_failures,
+            last_error: job.last_error,
+            missed_count: job.missed_count,
+            group: job.group,
+            tags: job.tags,
+        }
+    }
+}
+
+///
+/// A job as listed by `list_jobs`, with its schedule, ticks, state and run counts
+pub type JobDescriptor = JobInfo;
+
 pub type ShutdownNotification =
     dyn FnMut() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync;
 
 /// The JobScheduler contains and executes the scheduled jobs.
+///
+/// The scheduler is a handle onto channels shared with the actors doing the actual work. Cloning
+/// it only clones `Arc`s, and the handle is `Send + Sync`, so it can be moved into jobs and
+/// notifications freely. Adding, removing and approving jobs go through those channels and never
+/// block the executor, so they are safe to call from inside a running job.
+#[derive(Clone)]
 pub struct JobsSchedulerLocked {
     pub context: Arc<Context>,
-    pub inited: Arc<RwLock<bool>>,
-    pub job_creator: Arc<RwLock<JobCreator>>,
-    pub job_deleter: Arc<RwLock<JobDeleter>>,
-    pub job_runner: Arc<RwLock<JobRunner>>,
-    pub notification_creator: Arc<RwLock<NotificationCreator>>,
-    pub notification_deleter: Arc<RwLock<NotificationDeleter>>,
-    pub notification_runner: Arc<RwLock<NotificationRunner>>,
-    pub scheduler: Arc<RwLock<Scheduler>>,
+    pub(crate) actors: Arc<Actors>,
     pub shutdown_notifier: Option<Arc<RwLock<Box<ShutdownNotification>>>>,
 }
 
-impl Clone for JobsSchedulerLocked {
-    fn clone(&self) -> Self {
-        JobsSchedulerLocked {
-            context: self.context.clone(),
-            inited: self.inited.clone(),
-            job_creator: self.job_creator.clone(),
-            job_deleter: self.job_deleter.clone(),
-            job_runner: self.job_runner.clone(),
-            notification_creator: self.notification_creator.clone(),
-            notification_deleter: self.notification_deleter.clone(),
-            notification_runner: self.notification_runner.clone(),
-            scheduler: self.scheduler.clone(),
-            shutdown_notifier: self.shutdown_notifier.clone(),
-        }
-    }
+///
+/// The actors behind a scheduler, shared by all the clones of its handle
+pub(crate) struct Actors {
+    inited: AtomicBool,
+    job_creator: RwLock<JobCreator>,
+    job_deleter: RwLock<JobDeleter>,
+    job_runner: RwLock<JobRunner>,
+    notification_creator: RwLock<NotificationCreator>,
+    notification_deleter: RwLock<NotificationDeleter>,
+    notification_runner: RwLock<NotificationRunner>,
+    pub(crate) scheduler: RwLock<Scheduler>,
 }
 
+const _: fn() = || {
+    fn assert_handle<T: Clone + Send + Sync + 'static>() {}
+    assert_handle::<JobsSchedulerLocked>();
+};
+
 impl JobsSchedulerLocked {
+    #[allow(clippy::too_many_arguments)]
     async fn init_context(
         metadata_storage: Arc<RwLock<Box<dyn MetaDataStorage + Send + Sync>>>,
         notification_storage: Arc<RwLock<Box<dyn NotificationStore + Send + Sync>>>,
         job_code: Arc<RwLock<Box<dyn JobCode + Send + Sync>>>,
         notify_code: Arc<RwLock<Box<dyn NotificationCode + Send + Sync>>>,
+        group: String,
+        read_only: bool,
+        sequential_tags: Vec<String>,
+        display_timezones: Vec<Tz>,
+        default_timezone: Option<Tz>,
+        day_of_week_numbering: DayOfWeekNumbering,
+        max_concurrency: Option<usize>,
+        completion_window: Option<std::time::Duration>,
+        search_horizon: Option<std::time::Duration>,
     ) -> Result<Arc<Context>, JobSchedulerError> {
         {
             let mut metadata_storage = metadata_storage.write().await;
@@ -67,12 +242,27 @@ impl JobsSchedulerLocked {
             let mut notification_storage = notification_storage.write().await;
             notification_storage.init().await?;
         }
-        let context = Context::new(
+        let mut context = Context::new(
             metadata_storage,
             notification_storage,
             job_code.clone(),
             notify_code.clone(),
         );
+        context.group = group;
+        context.read_only = read_only;
+        context.sequential_tags = sequential_tags;
+        context.display_timezones = display_timezones;
+        context.default_timezone = default_timezone;
+        context.day_of_week_numbering = day_of_week_numbering;
+        context.execution_pool = max_concurrency
+            .map(|max| Arc::new(Semaphore::new(max.clamp(1, Semaphore::MAX_PERMITS))));
+        if let Some(completion_window) = completion_window {
+            context.completion_times.window = completion_window;
+        }
+        if let Some(search_horizon) = search_horizon {
+            context.search_horizon =
+                chrono::Duration::from_std(search_horizon).unwrap_or(chrono::Duration::MAX);
+        }
         {
             let mut job_code = job_code.write().await;
             job_code.init(&context).await?;
@@ -86,8 +276,8 @@ impl JobsSchedulerLocked {
 
     async fn init_actors(self) -> Result<(), JobSchedulerError> {
         let for_job_runner = self.clone();
-        let Self {
-            context,
+        let context = &self.context;
+        let Actors {
             job_creator,
             job_deleter,
             job_runner,
@@ -96,41 +286,41 @@ impl JobsSchedulerLocked {
             notification_runner,
             scheduler,
             ..
-        } = self;
+        } = &*self.actors;
 
         {
             let job_creator = job_creator.write().await;
-            job_creator.init(&context).await?;
+            job_creator.init(context).await?;
         }
 
         {
             let mut job_deleter = job_deleter.write().await;
-            job_deleter.init(&context).await?;
+            job_deleter.init(context).await?;
         }
 
         {
             let mut notification_creator = notification_creator.write().await;
-            notification_creator.init(&context).await?;
+            notification_creator.init(context).await?;
         }
 
         {
             let mut notification_deleter = notification_deleter.write().await;
-            notification_deleter.init(&context).await?;
+            notification_deleter.init(context).await?;
         }
 
         {
             let mut notification_runner = notification_runner.write().await;
-            notification_runner.init(&context).await?;
+            notification_runner.init(context).await?;
         }
 
         {
             let mut runner = job_runner.write().await;
-            runner.init(&context, for_job_runner).await?;
+            runner.init(context, for_job_runner).await?;
         }
 
         {
             let mut scheduler = scheduler.write().await;
-            scheduler.init(&context).await;
+            scheduler.init(context).await;
         }
 
         Ok(())
@@ -139,20 +329,15 @@ impl JobsSchedulerLocked {
     ///
     /// Get whether the scheduler is initialized
     pub async fn inited(&self) -> bool {
-        let r = self.inited.read().await;
-        *r
+        self.actors.inited.load(Ordering::Acquire)
     }
 
     ///
     /// Initialize the actors
     pub async fn init(&mut self) -> Result<(), JobSchedulerError> {
-        if self.inited().await {
+        if self.actors.inited.swap(true, Ordering::AcqRel) {
             return Ok(());
         }
-        {
-            let mut w = self.inited.write().await;
-            *w = true;
-        }
         self.clone()
             .init_actors()
             .await
@@ -163,45 +348,20 @@ impl JobsSchedulerLocked {
     /// Create a new `MetaDataStorage` and `NotificationStore` using the `SimpleMetadataStore`, `SimpleNotificationStore`,
     /// `SimpleJobCode` and `SimpleNotificationCode` implementation
     pub async fn new() -> Result<Self, JobSchedulerError> {
-        let metadata_storage = SimpleMetadataStore::default();
-        let metadata_storage: Arc<RwLock<Box<dyn MetaDataStorage + Send + Sync>>> =
-            Arc::new(RwLock::new(Box::new(metadata_storage)));
-
-        let notification_storage = SimpleNotificationStore::default();
-        let notification_storage: Arc<RwLock<Box<dyn NotificationStore + Send + Sync>>> =
-            Arc::new(RwLock::new(Box::new(notification_storage)));
-
-        let job_code = SimpleJobCode::default();
-        let job_code: Arc<RwLock<Box<dyn JobCode + Send + Sync>>> =
-            Arc::new(RwLock::new(Box::new(job_code)));
-
-        let notify_code = SimpleNotificationCode::default();
-        let notify_code: Arc<RwLock<Box<dyn NotificationCode + Send + Sync>>> =
-            Arc::new(RwLock::new(Box::new(notify_code)));
-
-        let context = JobsSchedulerLocked::init_context(
-            metadata_storage,
-            notification_storage,
-            job_code,
-            notify_code,
-        )
-        .await
-        .map_err(|_| JobSchedulerError::CantInit)?;
-
-        let val = JobsSchedulerLocked {
-            context,
-            inited: Arc::new(RwLock::new(false)),
-            job_creator: Arc::new(Default::default()),
-            job_deleter: Arc::new(Default::default()),
-            job_runner: Arc::new(Default::default()),
-            notification_creator: Arc::new(Default::default()),
-            notification_deleter: Arc::new(Default::default()),
-            notification_runner: Arc::new(Default::default()),
-            scheduler: Arc::new(Default::default()),
-            shutdown_notifier: None,
-        };
+        JobSchedulerBuilder::default()
+            .build()
+            .await
+            .map_err(|_| JobSchedulerError::CantInit)
+    }
 
-        Ok(val)
+    ///
+    /// Create a new scheduler with the default stores and code providers, running at most
+    /// `max_concurrency` jobs at once
+    pub async fn new_with_concurrency(max_concurrency: usize) -> Result<Self, JobSchedulerError> {
+        JobSchedulerBuilder::default()
+            .max_concurrency(max_concurrency)
+            .build()
+            .await
     }
 
     ///
@@ -213,33 +373,19 @@ impl JobsSchedulerLocked {
         job_code: Box<dyn JobCode + Send + Sync>,
         notification_code: Box<dyn NotificationCode + Send + Sync>,
     ) -> Result<Self, JobSchedulerError> {
-        let metadata_storage = Arc::new(RwLock::new(metadata_storage));
-        let notification_storage = Arc::new(RwLock::new(notification_storage));
-        let job_code = Arc::new(RwLock::new(job_code));
-        let notification_code = Arc::new(RwLock::new(notification_code));
-
-        let context = JobsSchedulerLocked::init_context(
-            metadata_storage,
-            notification_storage,
-            job_code,
-            notification_code,
-        )
-        .await?;
-
-        let val = JobsSchedulerLocked {
-            context,
-            inited: Arc::new(RwLock::new(false)),
-            job_creator: Arc::new(Default::default()),
-            job_deleter: Arc::new(Default::default()),
-            job_runner: Arc::new(Default::default()),
-            notification_creator: Arc::new(Default::default()),
-            notification_deleter: Arc::new(Default::default()),
-            notification_runner: Arc::new(Default::default()),
-            scheduler: Arc::new(Default::default()),
-            shutdown_notifier: None,
-        };
+        JobSchedulerBuilder::default()
+            .metadata_storage(metadata_storage)
+            .notification_storage(notification_storage)
+            .job_code(job_code)
+            .notification_code(notification_code)
+            .build()
+            .await
+    }
 
-        Ok(val)
+    ///
+    /// Create a new builder
+    pub fn new_builder() -> JobSchedulerBuilder {
+        JobSchedulerBuilder::default()
     }
 
     /// Add a job to the `JobScheduler`
@@ -266,6 +412,29 @@ impl JobsSchedulerLocked {
         Ok(guid)
     }
 
+    ///
+    /// Add a job run at each of its scheduled times once the data checked by the trigger is
+    /// available, rather than right away
+    pub async fn add_when_available(
+        &self,
+        job: JobLocked,
+        trigger: DataTrigger,
+    ) -> Result<Uuid, JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        let guid = job.guid();
+        {
+            let mut w = self.context.run_conditions.per_job.write().await;
+            w.insert(guid, trigger.into_condition());
+        }
+        if let Err(e) = self.add(job).await {
+            self.context.run_conditions.remove(&guid).await;
+            return Err(e);
+        }
+        Ok(guid)
+    }
+
     /// Remove a job from the `JobScheduler`
     ///
     /// ```rust,ignore
@@ -293,21 +462,46 @@ impl JobsSchedulerLocked {
     /// runs the tick method to increment any
     /// any pending jobs.
     ///
+    /// Before it starts ticking, the scheduler goes through the jobs in its store and returns a
+    /// `ReconciliationReport` of what it found: the jobs loaded, those without code to run, the
+    /// runs missed while it was down and the stale run locks released. The report is also sent
+    /// to the `subscribe_reconciliations` subscribers and logged.
+    ///
     /// ```rust,ignore
-    /// if let Err(e) = sched.start().await {
-    ///         eprintln!("Error on scheduler {:?}", e);
-    ///     }
+    /// match sched.start().await {
+    ///     Ok(report) => println!("Loaded {} jobs, {} missed runs", report.jobs_loaded, report.missed.len()),
+    ///     Err(e) => eprintln!("Error on scheduler {:?}", e),
+    /// }
     /// ```
-    pub async fn start(&self) -> Result<(), JobSchedulerError> {
+    pub async fn start(&self) -> Result<ReconciliationReport, JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
         if !self.inited().await {
             let mut s = self.clone();
             s.init().await?;
         }
-        let mut scheduler = self.scheduler.write().await;
+        let mut scheduler = self.actors.scheduler.write().await;
+        let run_lock = scheduler
+            .run_lock
+            .as_ref()
+            .map(|(run_lock, _)| run_lock.clone());
+        let report = reconciliation::reconcile(&self.context, run_lock).await?;
         let ret = scheduler.start().await;
 
         match ret {
-            Ok(ret) => Ok(ret),
+            Ok(()) => {
+                info!(
+                    jobs_loaded = report.jobs_loaded,
+                    missing_code = report.missing_code.len(),
+                    missed = report.missed.len(),
+                    locks_recovered = report.locks_recovered.len();
+                    "Reconciled the jobs in the store on start"
+                );
+                // Nobody may be listening
+                let _ = self.context.reconciliation_tx.send(report.clone());
+                Ok(report)
+            }
             Err(e) => {
                 error!("Error receiving start result {:?}", e);
                 Err(JobSchedulerError::StartScheduler)
@@ -315,6 +509,59 @@ impl JobsSchedulerLocked {
         }
     }
 
+    ///
+    /// Hold off firing the runs of all jobs, e.g. for a maintenance, leaving the jobs in their
+    /// stores. Runs can still be triggered by hand.
+    pub async fn pause_all(&self) -> Result<(), JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        let scheduler = self.actors.scheduler.read().await;
+        let mut w = scheduler.paused.write().await;
+        *w = true;
+        Ok(())
+    }
+
+    ///
+    /// Fire the runs of the jobs again after `pause_all`. The jobs that fell due while paused
+    /// are moved on to their next tick from now instead of all firing at once; a one-shot job
+    /// that fell due still runs.
+    pub async fn resume_all(&self) -> Result<(), JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        let scheduler = self.actors.scheduler.read().await;
+        let mut paused = scheduler.paused.write().await;
+        if !*paused {
+            return Ok(());
+        }
+        let now = clock::now();
+        let mut storage = self.context.metadata_storage.write().await;
+        for job_id in storage.list_job_ids().await? {
+            let job = match storage.get(job_id).await? {
+                Some(job) if job.group == self.context.group => job,
+                _ => continue,
+            };
+            let next_tick = match Scheduler::resumed_tick(&job, &now) {
+                Some(next_tick) => self.context.reschedule_hooks.apply(&job, next_tick).await,
+                None => continue,
+            };
+            storage
+                .set_next_and_last_tick(job_id, next_tick, job.last_tick_utc())
+                .await?;
+        }
+        *paused = false;
+        Ok(())
+    }
+
+    ///
+    /// Whether the scheduler was paused with `pause_all`
+    pub async fn is_paused(&self) -> bool {
+        let scheduler = self.actors.scheduler.read().await;
+        let r = scheduler.paused.read().await;
+        *r
+    }
+
     /// The `time_till_next_job` method returns the duration till the next job
     /// is supposed to run. This can be used to sleep until then without waking
     /// up at a fixed interval.AsMut
@@ -354,74 +601,1613 @@ impl JobsSchedulerLocked {
         r.get(job_id).await.map(|v| {
             v.map(|vv| vv.next_tick)
                 .filter(|t| *t != 0)
-                .map(|ts| NaiveDateTime::from_timestamp(ts as i64, 0))
-                .map(|ts| DateTime::from_utc(ts, Utc))
+                .and_then(|ts| DateTime::from_timestamp(ts as i64, 0))
         })
     }
 
     ///
-    /// Shut the scheduler down
-    pub async fn shutdown(&mut self) -> Result<(), JobSchedulerError> {
-        let mut notify = None;
-        std::mem::swap(&mut self.shutdown_notifier, &mut notify);
+    /// When the next run of a job is scheduled for, e.g. to show "next run at ..." next to the
+    /// job, whether it runs on a cron schedule, once or repeatedly. `None` when the job doesn't
+    /// run again, like a one-shot job that ran. A run that is due but not fired yet is returned
+    /// as scheduled.
+    pub async fn time_next_scheduled_for(
+        &self,
+        job_id: &Uuid,
+    ) -> Result<Option<DateTime<Utc>>, JobSchedulerError> {
+        let job = {
+            let mut storage = self.context.metadata_storage.write().await;
+            storage.get(*job_id).await?
+        };
+        let job = job.ok_or(JobSchedulerError::FetchJob)?;
+        Ok(job.next_tick_utc())
+    }
 
-        let mut scheduler = self.scheduler.write().await;
-        scheduler.shutdown().await;
+    ///
+    /// How long until the next run of a job, zero when it is due. The per-job counterpart of
+    /// `time_till_next_job`, `None` when the job doesn't run again.
+    pub async fn time_until_next(
+        &self,
+        job_id: &Uuid,
+    ) -> Result<Option<std::time::Duration>, JobSchedulerError> {
+        let next = self.time_next_scheduled_for(job_id).await?;
+        Ok(next.map(|next| (next - clock::now()).to_std().unwrap_or_default()))
+    }
 
-        if let Some(notify) = notify {
-            let mut notify = notify.write().await;
-            notify().await;
+    ///
+    /// The next instant a job fires at, without changing any state, so it can be asked while the
+    /// scheduler runs. A tick that is already due is taken as fired, giving the tick the job is
+    /// moved on to, so the answer is the same before and after the scheduler gets to it.
+    pub async fn peek_next_tick(
+        &self,
+        job_id: &Uuid,
+    ) -> Result<Option<DateTime<Utc>>, JobSchedulerError> {
+        let job = {
+            let mut storage = self.context.metadata_storage.write().await;
+            storage.get(*job_id).await?
+        };
+        let job = job.ok_or(JobSchedulerError::FetchJob)?;
+        let now = clock::now();
+        match job.next_tick_utc() {
+            Some(next_tick) if next_tick > now => Ok(Some(next_tick)),
+            Some(_) => {
+                let (next_tick, _, _) =
+                    Scheduler::after_misfire(&job, &now, self.context.search_horizon);
+                Ok(self.context.reschedule_hooks.apply(&job, next_tick).await)
+            }
+            None => Ok(None),
+        }
+    }
+
+    ///
+    /// The next `count` ticks of each of the jobs, read from the store at once, e.g. for a
+    /// dashboard showing many jobs. The jobs that aren't stored are left out, and a job asked
+    /// for twice is read once.
+    pub async fn next_ticks_bulk(
+        &self,
+        job_ids: &[Uuid],
+        count: usize,
+    ) -> Result<HashMap<Uuid, Vec<DateTime<Utc>>>, JobSchedulerError> {
+        let mut asked = HashSet::new();
+        let job_ids = job_ids
+            .iter()
+            .copied()
+            .filter(|id| asked.insert(*id))
+            .collect::<Vec<_>>();
+        let jobs = {
+            let mut storage = self.context.metadata_storage.write().await;
+            storage.get_many(&job_ids)
+        };
+        Ok(jobs
+            .await?
+            .iter()
+            .filter_map(|job| Some((job.id.as_ref()?.into(), job.upcoming_ticks(count))))
+            .collect())
+    }
+
+    ///
+    /// Wrap the runs of a job in a scope, e.g. one made by `propagate` to carry a task-local value
+    /// where the job is added into its runs. Scopes added later wrap the earlier ones.
+    pub async fn add_run_scope(
+        &self,
+        job_id: &Uuid,
+        scope: Box<RunScope>,
+    ) -> Result<(), JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        {
+            let mut storage = self.context.metadata_storage.write().await;
+            storage
+                .get(*job_id)
+                .await?
+                .ok_or(JobSchedulerError::FetchJob)?;
         }
+        let mut w = self.context.run_scopes.per_job.write().await;
+        w.entry(*job_id).or_default().push(Arc::from(scope));
         Ok(())
     }
 
     ///
-    /// Wait for a signal to shut the runtime down with
-    #[cfg(feature = "signal")]
-    pub fn shutdown_on_signal(&self, signal: SignalKind) {
-        let mut l = self.clone();
-        tokio::spawn(async move {
-            if let Some(_k) = tokio::signal::unix::signal(signal)
-                .expect("Can't wait for signal")
-                .recv()
-                .await
-            {
-                l.shutdown().await.expect("Problem shutting down");
-            }
-        });
+    /// Set a generator computing the payload of each scheduled run of a job when it fires
+    pub async fn set_payload_generator(
+        &self,
+        job_id: &Uuid,
+        generator: Box<PayloadGenerator>,
+    ) -> Result<(), JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        {
+            let mut storage = self.context.metadata_storage.write().await;
+            storage
+                .get(*job_id)
+                .await?
+                .ok_or(JobSchedulerError::FetchJob)?;
+        }
+        let mut w = self.context.payload_generators.write().await;
+        w.insert(*job_id, Arc::from(generator));
+        Ok(())
     }
 
     ///
-    /// Wait for a signal to shut the runtime down with
-    #[cfg(feature = "signal")]
-    pub fn shutdown_on_ctrl_c(&self) {
-        let mut l = self.clone();
-        tokio::spawn(async move {
-            tokio::signal::ctrl_c()
-                .await
-                .expect("Could not await ctrl-c");
+    /// The payload generated for the last scheduled run of a job, empty when it has none. It is
+    /// stored with the job, so a run triggered by hand replays the last one with the same payload.
+    pub async fn payload(&self, job_id: &Uuid) -> Result<Vec<u8>, JobSchedulerError> {
+        let mut storage = self.context.metadata_storage.write().await;
+        storage
+            .get(*job_id)
+            .await?
+            .map(|job| job.last_payload)
+            .ok_or(JobSchedulerError::FetchJob)
+    }
 
-            if let Err(err) = l.shutdown().await {
-                error!("{:?}", err);
+    ///
+    /// Parse a cron expression with the day-of-week numbering of the scheduler, for the
+    /// constructors of the cron jobs. Crontab lines without the seconds and the `@` macros of
+    /// crontab, like `@every 5m`, are parsed too. An expression not firing within the search
+    /// horizon of the scheduler fails with `NoUpcoming`.
+    ///
+    /// ```rust,ignore
+    /// let schedule = sched.parse_schedule("0 0 9 * * 1-5")?;
+    /// let schedule = sched.parse_schedule("@every 15m")?;
+    /// sched.add(Job::new(schedule, |_uuid, _l| println!("Good morning"))?).await?;
+    /// ```
+    pub fn parse_schedule(&self, expression: &str) -> Result<Schedule, JobSchedulerError> {
+        let schedule = self.context.day_of_week_numbering.parse(expression)?;
+        let now = clock::now();
+        let until = now.checked_add_signed(self.context.search_horizon);
+        leap::next_cron_tick_until(
+            &schedule,
+            Tz::UTC,
+            LeapDayPolicy::LeapYearsOnly,
+            &now,
+            until.as_ref(),
+        )
+        .ok_or(JobSchedulerError::NoUpcoming)?;
+        Ok(schedule)
+    }
+
+    ///
+    /// Parse a cron expression followed by the expressions of the times it doesn't fire in, each
+    /// after a `!`, like `0 */5 * * * * ! 0 * 2 * * *` for every 5 minutes but between 2:00 and
+    /// 3:00. The expressions are parsed like `parse_schedule`, the exclusions going to
+    /// `JobLocked::set_exclusions`.
+    ///
+    /// ```rust,ignore
+    /// let (schedule, exclusions) =
+    ///     sched.parse_schedule_with_exclusions("0 */5 * * * * ! 0 * 2 * * *")?;
+    /// let mut job = Job::new(schedule, |_uuid, _l| println!("Not at night"))?;
+    /// job.set_exclusions(exclusions)?;
+    /// ```
+    pub fn parse_schedule_with_exclusions(
+        &self,
+        expression: &str,
+    ) -> Result<(Schedule, Vec<Schedule>), JobSchedulerError> {
+        let mut expressions = expression.split('!');
+        let schedule = self.parse_schedule(expressions.next().unwrap_or_default())?;
+        let exclusions = expressions
+            .map(|e| self.context.day_of_week_numbering.parse(e))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((schedule, exclusions))
+    }
+
+    ///
+    /// The error returned by the last run of a fallible job, `None` when it didn't fail or
+    /// failed by panicking. Listeners of `FailedWithError` notifications read it from here.
+    pub async fn last_error(&self, job_id: &Uuid) -> Result<Option<String>, JobSchedulerError> {
+        let mut storage = self.context.metadata_storage.write().await;
+        storage
+            .get(*job_id)
+            .await?
+            .map(|job| job.last_error)
+            .ok_or(JobSchedulerError::FetchJob)
+    }
+
+    ///
+    /// List all the jobs in the metadata storage, including those of other groups
+    pub async fn list_jobs(&self) -> Result<Vec<JobInfo>, JobSchedulerError> {
+        let mut storage = self.context.metadata_storage.write().await;
+        let job_ids = storage.list_job_ids().await?;
+        let mut ret = Vec::with_capacity(job_ids.len());
+        for job_id in job_ids {
+            // The job may have been removed since it was listed
+            if let Some(job) = storage.get(job_id).await? {
+                ret.push(JobInfo::new(job_id, job, &self.context.display_timezones));
             }
-        });
+        }
+        Ok(ret)
     }
 
     ///
-    /// Code that is run after the shutdown was run
-    pub fn set_shutdown_handler(&mut self, job: Box<ShutdownNotification>) {
-        self.shutdown_notifier = Some(Arc::new(RwLock::new(job)));
+    /// A page of the jobs in the metadata storage, including those of other groups, selected,
+    /// sorted and paged by `query`. The query is run by the storage itself when it can.
+    pub async fn query_jobs(&self, query: JobQuery) -> Result<Page<JobInfo>, JobSchedulerError> {
+        let mut storage = self.context.metadata_storage.write().await;
+        let page = query.run(&mut storage).await?;
+        Ok(page.map(|job| {
+            let job_id = job.id.as_ref().map(Uuid::from).unwrap_or_default();
+            JobInfo::new(job_id, job, &self.context.display_timezones)
+        }))
     }
 
     ///
-    /// Remove the shutdown handler
-    pub fn remove_shutdown_handler(&mut self) {
-        self.shutdown_notifier = None;
+    /// Explain why a job runs next when it does: how its next tick was computed and what may
+    /// hold back or skip its run
+    pub async fn explain(&self, job_id: &Uuid) -> Result<JobExplanation, JobSchedulerError> {
+        explain::explain(self, *job_id).await
     }
 
     ///
-    /// Get the context
-    pub fn context(&self) -> Arc<Context> {
-        self.context.clone()
+    /// Take the internal state of the scheduler, its jobs, the next ticks it fires, the runs in
+    /// progress and the messages waiting in its channels, and log it as structured events, to
+    /// debug a scheduler that got stuck
+    pub async fn dump_state(&self) -> Result<StateDump, JobSchedulerError> {
+        dump::dump_state(self).await
+    }
+
+    ///
+    /// A snapshot of the stored jobs and their notifications, to be restored with `restore`
+    pub async fn snapshot(&self) -> Result<Snapshot, JobSchedulerError> {
+        backup::take_snapshot(&self.context).await
+    }
+
+    ///
+    /// Write the jobs and notifications of a snapshot back to the storage. The code of the jobs
+    /// is not in the snapshot: a restored job runs the code registered for it in this process.
+    pub async fn restore(
+        &self,
+        snapshot: Snapshot,
+        conflict: RestoreConflict,
+    ) -> Result<RestoreReport, JobSchedulerError> {
+        backup::restore(&self.context, snapshot, conflict).await
+    }
+
+    ///
+    /// Add a job taking a snapshot on a cron schedule and handing it to `on_backup`
+    pub async fn add_backup(
+        &self,
+        schedule: &str,
+        on_backup: Box<OnBackup>,
+    ) -> Result<Uuid, JobSchedulerError> {
+        let on_backup = Arc::new(RwLock::new(on_backup));
+        let job = JobLocked::new_async(schedule, move |_, scheduler| {
+            let on_backup = on_backup.clone();
+            Box::pin(async move {
+                match scheduler.snapshot().await {
+                    Ok(snapshot) => {
+                        let mut on_backup = on_backup.write().await;
+                        (on_backup)(snapshot).await;
+                    }
+                    Err(e) => error!("Could not take a snapshot {:?}", e),
+                }
+            })
+        })?;
+        self.add(job).await
+    }
+
+    ///
+    /// A handle on this scheduler that only sees and changes the jobs tagged with `tag`
+    pub fn scoped(&self, tag: impl Into<String>) -> ScopedScheduler {
+        ScopedScheduler::new(self.clone(), tag.into())
+    }
+
+    ///
+    /// Subscribe to the state changes of the jobs run by this scheduler process. Runs done by
+    /// other schedulers sharing the storage are not seen.
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<(Uuid, JobState)> {
+        self.context.notify_tx.subscribe()
+    }
+
+    ///
+    /// Subscribe to the due runs fired by this scheduler process, in the order they were fired.
+    /// Their `Scheduled` state changes come in the same order.
+    pub fn subscribe_dispatches(&self) -> tokio::sync::broadcast::Receiver<Dispatch> {
+        self.context.dispatch_tx.subscribe()
+    }
+
+    ///
+    /// Subscribe to the reports of what this scheduler found in its store when it started, as
+    /// returned by `start`
+    pub fn subscribe_reconciliations(
+        &self,
+    ) -> tokio::sync::broadcast::Receiver<ReconciliationReport> {
+        self.context.reconciliation_tx.subscribe()
+    }
+
+    ///
+    /// Run a job right away, outside of its schedule
+    pub async fn trigger(&self, job_id: &Uuid) -> Result<(), JobSchedulerError> {
+        self.activate(job_id, clock::now()).await
+    }
+
+    ///
+    /// Run a job right away as the run it had, or would have had, at `scheduled_time`, e.g. to
+    /// redo the run of a day that failed. The run reads that time with `scheduled_time` instead
+    /// of now, and a job with a payload generator gets the payload generated for that time.
+    pub async fn trigger_as_of(
+        &self,
+        job_id: &Uuid,
+        scheduled_time: DateTime<Utc>,
+    ) -> Result<(), JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        let generator = {
+            let r = self.context.payload_generators.read().await;
+            r.get(job_id).cloned()
+        };
+        if let Some(generator) = generator {
+            let payload = generator(*job_id, scheduled_time).await;
+            let mut storage = self.context.metadata_storage.write().await;
+            let mut job = storage
+                .get(*job_id)
+                .await?
+                .ok_or(JobSchedulerError::FetchJob)?;
+            job.last_payload = payload;
+            storage.add_or_update(job).await?;
+        }
+        self.activate(job_id, scheduled_time).await
+    }
+
+    ///
+    /// The logical time of the last run of a job, for its code to know which occurrence it
+    /// processes: the tick a scheduled run was due at, or the time a triggered run is run as of.
+    /// `None` until the job ran in this process. Called from a run of the job, it is the time of
+    /// that run, even while another run of the job started since.
+    pub async fn scheduled_time(&self, job_id: &Uuid) -> Option<DateTime<Utc>> {
+        if let Some(run) = crate::job::current_run().filter(|run| run.job_id == *job_id) {
+            return Some(run.scheduled);
+        }
+        let r = self.context.scheduled_times.read().await;
+        r.get(job_id).copied()
+    }
+
+    ///
+    /// The occurrences of jobs that missed their run or failed in this process, oldest first
+    pub async fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.context.dead_letters.list().await
+    }
+
+    ///
+    /// A page of the dead letters selected by `query`
+    pub async fn query_dead_letters(&self, query: DeadLetterQuery) -> Page<DeadLetter> {
+        self.context.dead_letters.query(&query).await
+    }
+
+    ///
+    /// Leave a note on a dead-lettered occurrence, like a link to the incident it is part of
+    pub async fn annotate_dead_letter(
+        &self,
+        letter_id: &Uuid,
+        annotation: impl Into<String>,
+    ) -> Result<DeadLetter, JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        self.context
+            .dead_letters
+            .annotate(letter_id, annotation.into(), false)
+            .await
+            .ok_or(JobSchedulerError::NoDeadLetter)
+    }
+
+    ///
+    /// Mark a dead-lettered occurrence resolved with a note, like "known issue, ignored". It is
+    /// no longer re-driven, and a failed run stops counting toward the failures in a row of its
+    /// job, which skip and escalate on, when it is one of them.
+    pub async fn resolve_dead_letter(
+        &self,
+        letter_id: &Uuid,
+        annotation: impl Into<String>,
+    ) -> Result<DeadLetter, JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        let letters = self.context.dead_letters.list().await;
+        let letter = letters
+            .iter()
+            .find(|letter| letter.id == *letter_id)
+            .ok_or(JobSchedulerError::NoDeadLetter)?;
+        // The unresolved failures of the job, the last ones being those it has in a row
+        let failures: Vec<Uuid> = letters
+            .iter()
+            .filter(|l| {
+                l.job_id == letter.job_id && l.reason == DeadLetterReason::Failed && !l.resolved
+            })
+            .map(|l| l.id)
+            .collect();
+        let resolved = self
+            .context
+            .dead_letters
+            .annotate(letter_id, annotation.into(), true)
+            .await
+            .ok_or(JobSchedulerError::NoDeadLetter)?;
+        if letter.reason != DeadLetterReason::Failed || letter.resolved {
+            return Ok(resolved);
+        }
+        let mut storage = self.context.metadata_storage.write().await;
+        if let Some(mut job) = storage.get(letter.job_id).await? {
+            let in_a_row = job.consecutive_failures as usize;
+            if failures
+                .iter()
+                .rev()
+                .take(in_a_row)
+                .any(|id| id == letter_id)
+            {
+                job.consecutive_failures -= 1;
+                job.last_run_failed = job.consecutive_failures > 0;
+                storage.add_or_update(job).await?;
+            }
+        }
+        Ok(resolved)
+    }
+
+    ///
+    /// Run the dead-lettered occurrences selected by `filter` again, each as of its scheduled
+    /// time, at the pace of the filter. The occurrences are taken out of the dead letters and
+    /// the progress is sent on the returned receiver. A run failing again is dead-lettered anew.
+    pub async fn redrive(
+        &self,
+        filter: RedriveFilter,
+    ) -> Result<UnboundedReceiver<RedriveProgress>, JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        let letters = self.context.dead_letters.take(&filter).await;
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(redrive::redrive(self.clone(), letters, filter.every, tx));
+        Ok(rx)
+    }
+
+    async fn activate(
+        &self,
+        job_id: &Uuid,
+        scheduled_time: DateTime<Utc>,
+    ) -> Result<(), JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        if !self.inited().await {
+            let mut s = self.clone();
+            s.init().await?;
+        }
+        {
+            let mut r = self.context.metadata_storage.write().await;
+            r.get(*job_id).await?.ok_or(JobSchedulerError::FetchJob)?;
+        }
+        {
+            let mut w = self.context.scheduled_times.write().await;
+            w.insert(*job_id, scheduled_time);
+        }
+        if let Err(e) = self.context.notify_tx.send((*job_id, JobState::Scheduled)) {
+            error!(job_id = job_id; "Error sending notification activation {:?}", e);
+        }
+        if let Err(e) = self.context.job_activation_tx.send(*job_id) {
+            error!(job_id = job_id; "Error sending job activation tx {:?}", e);
+            return Err(JobSchedulerError::CantTrigger);
+        }
+        Ok(())
+    }
+
+    ///
+    /// Run a job right away within `scopes`, e.g. to carry the task-local values of the request
+    /// triggering it into the run, along with the scopes of the job
+    pub async fn trigger_scoped(
+        &self,
+        job_id: &Uuid,
+        scopes: Vec<Box<RunScope>>,
+    ) -> Result<(), JobSchedulerError> {
+        {
+            let mut w = self.context.run_scopes.triggered.write().await;
+            w.insert(*job_id, scopes.into_iter().map(Arc::from).collect());
+        }
+        let triggered = self.trigger(job_id).await;
+        if triggered.is_err() {
+            let mut w = self.context.run_scopes.triggered.write().await;
+            w.remove(job_id);
+        }
+        triggered
+    }
+
+    ///
+    /// Swap the code run by a job for `run`, keeping the schedule and state of the job. A run
+    /// already started finishes with the old code, the next ones use the new code. This swaps
+    /// the code held by the job code provider, so needs a provider that hands out the code it
+    /// holds, like the `SimpleJobCode`.
+    pub async fn replace_code<T>(&self, job_id: &Uuid, run: T) -> Result<(), JobSchedulerError>
+    where
+        T: FnMut(Uuid, JobsSchedulerLocked) + Send + Sync + 'static,
+    {
+        self.swap_code(job_id, JobCreator::code(run)).await
+    }
+
+    ///
+    /// Swap the code run by a job for the async `run`, like `replace_code`
+    pub async fn replace_code_async<T>(
+        &self,
+        job_id: &Uuid,
+        run: T,
+    ) -> Result<(), JobSchedulerError>
+    where
+        T: FnMut(Uuid, JobsSchedulerLocked) -> Pin<Box<dyn Future<Output = ()> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.swap_code(job_id, JobCreator::code_async(run)).await
+    }
+
+    async fn swap_code(
+        &self,
+        job_id: &Uuid,
+        code: Box<JobToRunAsync>,
+    ) -> Result<(), JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        let current = {
+            let mut w = self.context.job_code.write().await;
+            w.get(*job_id).await?
+        };
+        let current = current.ok_or(JobSchedulerError::FetchJob)?;
+        let mut w = current.write().await;
+        *w = code;
+        Ok(())
+    }
+
+    ///
+    /// Set a condition the job has to meet to run, checked just before each scheduled run. A
+    /// run whose condition isn't met is skipped, sending the `Skipped` notification.
+    pub async fn set_run_condition(
+        &self,
+        job_id: &Uuid,
+        condition: RunCondition,
+    ) -> Result<(), JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        {
+            let mut storage = self.context.metadata_storage.write().await;
+            storage
+                .get(*job_id)
+                .await?
+                .ok_or(JobSchedulerError::FetchJob)?;
+        }
+        let mut w = self.context.run_conditions.per_job.write().await;
+        w.insert(*job_id, condition);
+        Ok(())
+    }
+
+    ///
+    /// Why the last run of the job was skipped by its run condition, if it was
+    pub async fn skip_reason(&self, job_id: &Uuid) -> Option<String> {
+        let r = self.context.run_conditions.skip_reasons.read().await;
+        r.get(job_id).cloned()
+    }
+
+    ///
+    /// Set a hook adjusting the next tick computed for a job, on top of the global hook. The
+    /// next tick the job already has is adjusted right away.
+    pub async fn set_reschedule_hook(
+        &self,
+        job_id: &Uuid,
+        hook: Box<RescheduleHook>,
+    ) -> Result<(), JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        let mut storage = self.context.metadata_storage.write().await;
+        let job = storage
+            .get(*job_id)
+            .await?
+            .ok_or(JobSchedulerError::FetchJob)?;
+        {
+            let mut w = self.context.reschedule_hooks.per_job.write().await;
+            w.insert(*job_id, Arc::from(hook));
+        }
+        let next_tick = self
+            .context
+            .reschedule_hooks
+            .apply(&job, job.next_tick_utc())
+            .await;
+        if next_tick != job.next_tick_utc() {
+            storage
+                .set_next_and_last_tick(*job_id, next_tick, job.last_tick_utc())
+                .await?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Remove the reschedule hook of a job
+    pub async fn remove_reschedule_hook(&self, job_id: &Uuid) {
+        let mut w = self.context.reschedule_hooks.per_job.write().await;
+        w.remove(job_id);
+    }
+
+    ///
+    /// How late the runs of a job were started after their scheduled instant
+    pub async fn drift(&self, job_id: &Uuid) -> Option<DriftStats> {
+        let r = self.context.drift.read().await;
+        r.get(job_id).copied()
+    }
+
+    ///
+    /// Check the completion times of the runs of a job, how long after their scheduled time
+    /// they succeeded, against `target`. The breaches are reported to the handler set with
+    /// `JobSchedulerBuilder::on_sla_breach`.
+    pub async fn set_sla_target(&self, job_id: &Uuid, target: SlaTarget) {
+        let mut w = self.context.completion_times.targets.write().await;
+        w.insert(*job_id, target);
+    }
+
+    ///
+    /// Stop checking the completion times of a job against its SLA target
+    pub async fn remove_sla_target(&self, job_id: &Uuid) {
+        self.context
+            .completion_times
+            .targets
+            .write()
+            .await
+            .remove(job_id);
+        self.context
+            .completion_times
+            .breached
+            .write()
+            .await
+            .remove(job_id);
+    }
+
+    ///
+    /// How long after their scheduled time the successful runs of a job in the completion window
+    /// completed, to read percentiles of
+    pub async fn completion_times(&self, job_id: &Uuid) -> Option<CompletionStats> {
+        let r = self.context.completion_times.stats.read().await;
+        r.get(job_id).cloned()
+    }
+
+    ///
+    /// The last `limit` runs of a job in the run history, the latest first, with when they were
+    /// scheduled, started and ended, and how they ended
+    pub async fn run_history(
+        &self,
+        job_id: &Uuid,
+        limit: usize,
+    ) -> Result<Vec<JobRun>, JobSchedulerError> {
+        let mut w = self.context.run_history.write().await;
+        w.history(*job_id, limit).await
+    }
+
+    ///
+    /// Write the runs of all the jobs in the run history that ended in `range` to `writer` in
+    /// `format`, the earliest first. Returns the number of runs written.
+    pub async fn export_run_history(
+        &self,
+        range: Range<DateTime<Utc>>,
+        format: ExportFormat,
+        writer: Box<dyn Write + Send>,
+    ) -> Result<usize, JobSchedulerError> {
+        let mut w = self.context.run_history.write().await;
+        w.export(range, format, writer).await
+    }
+
+    ///
+    /// List the runs of jobs that are in progress
+    pub async fn running_executions(&self) -> Vec<RunningExecution> {
+        let r = self.context.running_executions.read().await;
+        r.values().map(|(execution, _)| *execution).collect()
+    }
+
+    ///
+    /// Cancel a run that is in progress, without stopping or removing its job. The run is
+    /// aborted at its next await point, so a run of a synchronous job can not be cancelled once
+    /// its closure is called. The run is written to the run history as `Cancelled`, and a
+    /// `Cancelled` notification is sent before the `Done` one.
+    pub async fn cancel_run(&self, execution_id: &Uuid) -> Result<(), JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        let (execution, abort) = {
+            let mut w = self.context.running_executions.write().await;
+            w.remove(execution_id)
+                .ok_or(JobSchedulerError::NoRunningExecution)?
+        };
+        abort.abort();
+        JobCreator::record_run(
+            &self.context,
+            execution.job_id,
+            execution.scheduled,
+            execution.started,
+            JobState::Cancelled,
+            &None,
+        )
+        .await;
+        // The aborted run never gets to send its own `Done`
+        for state in [JobState::Cancelled, JobState::Done] {
+            if let Err(e) = self.context.notify_tx.send((execution.job_id, state)) {
+                error!(
+                    job_id = execution.job_id,
+                    execution_id = execution.execution_id;
+                    "Error sending notification {:?} {:?}",
+                    state,
+                    e
+                );
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// List the due runs of jobs that are waiting on approval
+    pub async fn pending_approvals(&self) -> Vec<PendingApproval> {
+        let r = self.context.pending_approvals.read().await;
+        r.iter()
+            .map(|(job_id, due)| PendingApproval {
+                job_id: *job_id,
+                due: *due,
+            })
+            .collect()
+    }
+
+    ///
+    /// Approve the pending run of a job, which then gets run
+    pub async fn approve(&self, job_id: &Uuid) -> Result<(), JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        self.take_pending_approval(job_id).await?;
+        if let Err(e) = self.context.notify_tx.send((*job_id, JobState::Scheduled)) {
+            error!(job_id = job_id; "Error sending notification activation {:?}", e);
+        }
+        if let Err(e) = self.context.job_activation_tx.send(*job_id) {
+            error!(job_id = job_id; "Error sending job activation tx {:?}", e);
+        }
+        Ok(())
+    }
+
+    ///
+    /// Reject the pending run of a job, which then gets skipped
+    pub async fn reject(&self, job_id: &Uuid) -> Result<(), JobSchedulerError> {
+        if self.context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
+        self.take_pending_approval(job_id).await?;
+        if let Err(e) = self.context.notify_tx.send((*job_id, JobState::Skipped)) {
+            error!(job_id = job_id; "Error sending notification skipped {:?}", e);
+        }
+        Ok(())
+    }
+
+    async fn take_pending_approval(&self, job_id: &Uuid) -> Result<(), JobSchedulerError> {
+        let mut w = self.context.pending_approvals.write().await;
+        w.remove(job_id)
+            .map(|_| ())
+            .ok_or(JobSchedulerError::NoPendingApproval)
+    }
+
+    ///
+    /// Shut the scheduler down
+    pub async fn shutdown(&mut self) -> Result<(), JobSchedulerError> {
+        let mut notify = None;
+        std::mem::swap(&mut self.shutdown_notifier, &mut notify);
+
+        let mut scheduler = self.actors.scheduler.write().await;
+        scheduler.shutdown().await;
+
+        if let Some(notify) = notify {
+            let mut notify = notify.write().await;
+            notify().await;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Shut the scheduler down once the runs in progress are done. No new runs are started, the
+    /// due ones being skipped, and the runs in progress are waited on for up to `timeout` before
+    /// the shutdown handler is run. Returns the runs that were still in progress at the timeout,
+    /// which are left running.
+    pub async fn shutdown_graceful(
+        &mut self,
+        timeout: std::time::Duration,
+    ) -> Result<Vec<RunningExecution>, JobSchedulerError> {
+        {
+            let mut w = self.context.draining.write().await;
+            *w = true;
+        }
+        {
+            let mut scheduler = self.actors.scheduler.write().await;
+            scheduler.shutdown().await;
+        }
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut running = self.running_executions().await;
+        while !running.is_empty() && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep_until(deadline.min(tokio::time::Instant::now() + DRAIN_POLL)).await;
+            running = self.running_executions().await;
+        }
+        if !running.is_empty() {
+            warn!(runs = running.len(); "Shutting down with runs still in progress");
+        }
+        self.shutdown().await?;
+        Ok(running)
+    }
+
+    ///
+    /// Wait for a signal to shut the runtime down with
+    #[cfg(feature = "signal")]
+    pub fn shutdown_on_signal(&self, signal: SignalKind) {
+        let mut l = self.clone();
+        tokio::spawn(async move {
+            if let Some(_k) = tokio::signal::unix::signal(signal)
+                .expect("Can't wait for signal")
+                .recv()
+                .await
+            {
+                l.shutdown().await.expect("Problem shutting down");
+            }
+        });
+    }
+
+    ///
+    /// Dump the state of the scheduler, like `dump_state`, each time `signal` comes in, e.g.
+    /// `SignalKind::user_defined1()` for `kill -USR1`
+    #[cfg(feature = "signal")]
+    pub fn dump_state_on_signal(&self, signal: SignalKind) {
+        let l = self.clone();
+        tokio::spawn(async move {
+            let mut signals = tokio::signal::unix::signal(signal).expect("Can't wait for signal");
+            while signals.recv().await.is_some() {
+                if let Err(e) = l.dump_state().await {
+                    error!("Error dumping the scheduler state {:?}", e);
+                }
+            }
+        });
+    }
+
+    ///
+    /// Wait for a signal to shut the runtime down with
+    #[cfg(feature = "signal")]
+    pub fn shutdown_on_ctrl_c(&self) {
+        let mut l = self.clone();
+        tokio::spawn(async move {
+            tokio::signal::ctrl_c()
+                .await
+                .expect("Could not await ctrl-c");
+
+            if let Err(err) = l.shutdown().await {
+                error!("{:?}", err);
+            }
+        });
+    }
+
+    ///
+    /// Code that is run after the shutdown was run
+    pub fn set_shutdown_handler(&mut self, job: Box<ShutdownNotification>) {
+        self.shutdown_notifier = Some(Arc::new(RwLock::new(job)));
+    }
+
+    ///
+    /// Remove the shutdown handler
+    pub fn remove_shutdown_handler(&mut self) {
+        self.shutdown_notifier = None;
+    }
+
+    ///
+    /// Get the context
+    pub fn context(&self) -> Arc<Context> {
+        self.context.clone()
+    }
+}
+
+///
+/// Builds a `JobsSchedulerLocked`. Storage and code providers that are not set default to
+/// the `SimpleMetadataStore`, `SimpleNotificationStore`, `SimpleJobCode` and `SimpleNotificationCode`
+/// implementations.
+#[derive(Default)]
+pub struct JobSchedulerBuilder {
+    pub metadata_storage: Option<Box<dyn MetaDataStorage + Send + Sync>>,
+    pub notification_storage: Option<Box<dyn NotificationStore + Send + Sync>>,
+    pub job_code: Option<Box<dyn JobCode + Send + Sync>>,
+    pub notification_code: Option<Box<dyn NotificationCode + Send + Sync>>,
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    pub reschedule_hook: Option<Box<RescheduleHook>>,
+    pub group: String,
+    pub read_only: bool,
+    pub budget: Option<Budget>,
+    pub dispatch_budget: Option<usize>,
+    pub sequential_tags: Vec<String>,
+    pub display_timezones: Vec<Tz>,
+    pub default_timezone: Option<Tz>,
+    pub day_of_week_numbering: DayOfWeekNumbering,
+    pub max_concurrency: Option<usize>,
+    pub escalation_policy: Option<EscalationPolicy>,
+    pub on_internal_error: Option<Box<OnInternalError>>,
+    pub outbox: Option<Box<dyn Outbox + Send + Sync>>,
+    pub leader_election: Option<Box<dyn LeaderElection + Send + Sync>>,
+    pub run_lock: Option<(Box<dyn RunLock + Send + Sync>, std::time::Duration)>,
+    pub completion_window: Option<std::time::Duration>,
+    pub search_horizon: Option<std::time::Duration>,
+    pub on_sla_breach: Option<Box<OnSlaBreach>>,
+    pub run_history: Option<Box<dyn RunHistoryStore + Send + Sync>>,
+}
+
+impl JobSchedulerBuilder {
+    pub fn metadata_storage(
+        mut self,
+        metadata_storage: Box<dyn MetaDataStorage + Send + Sync>,
+    ) -> Self {
+        self.metadata_storage = Some(metadata_storage);
+        self
+    }
+
+    pub fn notification_storage(
+        mut self,
+        notification_storage: Box<dyn NotificationStore + Send + Sync>,
+    ) -> Self {
+        self.notification_storage = Some(notification_storage);
+        self
+    }
+
+    pub fn job_code(mut self, job_code: Box<dyn JobCode + Send + Sync>) -> Self {
+        self.job_code = Some(job_code);
+        self
+    }
+
+    pub fn notification_code(
+        mut self,
+        notification_code: Box<dyn NotificationCode + Send + Sync>,
+    ) -> Self {
+        self.notification_code = Some(notification_code);
+        self
+    }
+
+    ///
+    /// Weekly recurring windows during which no jobs fire. What happens to runs that become due
+    /// during a window is set per job with `set_maintenance_window_policy`.
+    pub fn maintenance_windows(mut self, maintenance_windows: Vec<MaintenanceWindow>) -> Self {
+        self.maintenance_windows = maintenance_windows;
+        self
+    }
+
+    ///
+    /// A hook adjusting the next tick computed for every job
+    pub fn reschedule_hook(mut self, reschedule_hook: Box<RescheduleHook>) -> Self {
+        self.reschedule_hook = Some(reschedule_hook);
+        self
+    }
+
+    ///
+    /// The group of the scheduler. Schedulers sharing a storage only load and run the jobs that
+    /// were added to a scheduler of their own group. Defaults to the empty group.
+    pub fn group(mut self, group: impl Into<String>) -> Self {
+        self.group = group.into();
+        self
+    }
+
+    ///
+    /// A budget per window of time the costs of the runs are charged to. When a due run does not
+    /// fit in what is left, its next tick moves to the start of the next window with a `Deferred`
+    /// notification, runs of jobs with a higher priority being charged first.
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    ///
+    /// The most runs fired on one tick, e.g. when thousands of runs come due at once after an
+    /// outage. The other due runs stay due and are fired on the next ticks, the earliest and those
+    /// of the jobs with a higher priority first. Unlimited by default.
+    pub fn dispatch_budget(mut self, dispatch_budget: usize) -> Self {
+        self.dispatch_budget = Some(dispatch_budget.max(1));
+        self
+    }
+
+    ///
+    /// Tags whose jobs run strictly one after another, for jobs sharing a resource that cannot
+    /// take parallel writers. Due runs of the jobs carrying such a tag are queued in the order of
+    /// their ticks, and each waits until the run before it in the queue is done.
+    pub fn sequential_tags(mut self, sequential_tags: Vec<String>) -> Self {
+        self.sequential_tags = sequential_tags;
+        self
+    }
+
+    ///
+    /// Timezones the next tick of each job is also given in by `list_jobs` and `query_jobs`, for
+    /// teams that don't think in UTC
+    pub fn display_timezones(mut self, display_timezones: Vec<Tz>) -> Self {
+        self.display_timezones = display_timezones;
+        self
+    }
+
+    ///
+    /// The timezone the schedules of the cron jobs are evaluated in when they weren't given one
+    /// of their own with `new_tz` or `with_timezone`, for teams working in a single business
+    /// timezone. It is stored with each job as it is added, so the job keeps its timezone when
+    /// the store is shared with or reloaded by a scheduler with another default.
+    pub fn default_timezone(mut self, default_timezone: Tz) -> Self {
+        self.default_timezone = Some(default_timezone);
+        self
+    }
+
+    ///
+    /// How the day-of-week numbers of the cron expressions parsed by `parse_schedule` are read,
+    /// e.g. `Unix` for a schedule imported from a crontab
+    pub fn day_of_week_numbering(mut self, day_of_week_numbering: DayOfWeekNumbering) -> Self {
+        self.day_of_week_numbering = day_of_week_numbering;
+        self
+    }
+
+    ///
+    /// Run at most `max_concurrency` jobs at once. Further due runs wait for a free slot, first
+    /// come first served. A run waiting its turn in a sequential tag, an exclusive group or the
+    /// queue of its job holds its slot while it waits.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = Some(max_concurrency);
+        self
+    }
+
+    ///
+    /// Route the failed runs of all jobs to the sinks of the policy, depending on how many times
+    /// in a row a job failed
+    pub fn escalation_policy(mut self, escalation_policy: EscalationPolicy) -> Self {
+        self.escalation_policy = Some(escalation_policy);
+        self
+    }
+
+    ///
+    /// A handler called with the errors the scheduler runs into while running jobs, like a
+    /// failing tick, a store that can't be written or a channel that overflowed. These are logged
+    /// and the scheduler carries on either way.
+    pub fn on_internal_error(mut self, on_internal_error: Box<OnInternalError>) -> Self {
+        self.on_internal_error = Some(on_internal_error);
+        self
+    }
+
+    ///
+    /// Write the notifications of the outcomes of runs, like `Done` and `Failed`, to an outbox
+    /// before the outcome is stored, and deliver them from there with retries. With a persistent
+    /// outbox next to a persistent store, the notifications of a run are delivered even when the
+    /// process dies right after it, once the scheduler is started again and its notifications
+    /// added back.
+    pub fn outbox(mut self, outbox: Box<dyn Outbox + Send + Sync>) -> Self {
+        self.outbox = Some(outbox);
+        self
+    }
+
+    ///
+    /// Only fire the due jobs while this scheduler is the leader of the election, for replicas
+    /// of an application each running a scheduler against the same store, like the
+    /// `PostgresLeaderElection` or the `NatsLeaderElection`. The schedulers that aren't the
+    /// leader add, remove and list jobs as usual, and one of them takes over when the leader
+    /// shuts down, is paused or dies.
+    pub fn leader_election(
+        mut self,
+        leader_election: Box<dyn LeaderElection + Send + Sync>,
+    ) -> Self {
+        self.leader_election = Some(leader_election);
+        self
+    }
+
+    ///
+    /// Take the lock on every due run from `run_lock` before firing it, held for `ttl`, so
+    /// schedulers sharing a store, like with the `PostgresRunLock`, the `NatsRunLock` or the
+    /// `RedisRunLock`, all fire due jobs and each run still happens once among them. A run whose
+    /// lock another scheduler holds is left to that one. The `ttl` should outlast the differences
+    /// between the clocks of the schedulers, and the same for all of them.
+    pub fn run_lock(
+        mut self,
+        run_lock: Box<dyn RunLock + Send + Sync>,
+        ttl: std::time::Duration,
+    ) -> Self {
+        self.run_lock = Some((run_lock, ttl));
+        self
+    }
+
+    ///
+    /// Keep the record of every run of the jobs in `run_history`, read back with
+    /// `JobScheduler::run_history`. Defaults to a `SimpleRunHistoryStore` keeping the last 100 runs
+    /// of each job in memory.
+    pub fn run_history(mut self, run_history: Box<dyn RunHistoryStore + Send + Sync>) -> Self {
+        self.run_history = Some(run_history);
+        self
+    }
+
+    ///
+    /// How far back the completion times of the runs of jobs are kept to check their SLA
+    /// targets against. Defaults to a day.
+    pub fn completion_window(mut self, completion_window: std::time::Duration) -> Self {
+        self.completion_window = Some(completion_window);
+        self
+    }
+
+    ///
+    /// How far ahead the next tick of a job is looked for, about 30 years by default. A job whose
+    /// next tick is further away, like a sparse schedule firing on February 29 of a year that
+    /// never comes, has no upcoming tick: `parse_schedule` fails with `NoUpcoming` for it, and
+    /// the tick loop removes the job like a job that runs no more.
+    pub fn search_horizon(mut self, search_horizon: std::time::Duration) -> Self {
+        self.search_horizon = Some(search_horizon);
+        self
+    }
+
+    ///
+    /// A handler called when the completion times of a job go over its SLA target, set with
+    /// `JobScheduler::set_sla_target`. It is called once per breach, and again only after the
+    /// job met its target in between.
+    pub fn on_sla_breach(mut self, on_sla_breach: Box<OnSlaBreach>) -> Self {
+        self.on_sla_breach = Some(on_sla_breach);
+        self
+    }
+
+    ///
+    /// Only observe the storage, e.g. for a dashboard next to the schedulers running the jobs.
+    /// A read-only scheduler can list jobs and their ticks, but adding, removing or triggering
+    /// jobs, approving or rejecting runs, adding notifications and starting the scheduler fail
+    /// with `ReadOnly`.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Build a JobsSchedulerLocked
+    pub async fn build(self) -> Result<JobsSchedulerLocked, JobSchedulerError> {
+        let JobSchedulerBuilder {
+            metadata_storage,
+            notification_storage,
+            job_code,
+            notification_code,
+            maintenance_windows,
+            reschedule_hook,
+            group,
+            read_only,
+            budget,
+            dispatch_budget,
+            sequential_tags,
+            display_timezones,
+            default_timezone,
+            day_of_week_numbering,
+            max_concurrency,
+            escalation_policy,
+            on_internal_error,
+            outbox,
+            leader_election,
+            run_lock,
+            completion_window,
+            search_horizon,
+            on_sla_breach,
+            run_history,
+        } = self;
+        #[cfg(feature = "metrics")]
+        crate::metrics::describe();
+        let metadata_storage =
+            metadata_storage.unwrap_or_else(|| Box::new(SimpleMetadataStore::default()));
+        let notification_storage =
+            notification_storage.unwrap_or_else(|| Box::new(SimpleNotificationStore::default()));
+        let job_code = job_code.unwrap_or_else(|| Box::new(SimpleJobCode::default()));
+        let notification_code =
+            notification_code.unwrap_or_else(|| Box::new(SimpleNotificationCode::default()));
+
+        let context = JobsSchedulerLocked::init_context(
+            Arc::new(RwLock::new(metadata_storage)),
+            Arc::new(RwLock::new(notification_storage)),
+            Arc::new(RwLock::new(job_code)),
+            Arc::new(RwLock::new(notification_code)),
+            group,
+            read_only,
+            sequential_tags,
+            display_timezones,
+            default_timezone,
+            day_of_week_numbering,
+            max_concurrency,
+            completion_window,
+            search_horizon,
+        )
+        .await?;
+        if let Some(reschedule_hook) = reschedule_hook {
+            let mut w = context.reschedule_hooks.global.write().await;
+            *w = Some(Arc::from(reschedule_hook));
+        }
+        if let Some(escalation_policy) = escalation_policy {
+            let mut w = context.escalation_policy.write().await;
+            *w = Some(escalation_policy);
+        }
+        if let Some(on_internal_error) = on_internal_error {
+            let mut w = context.internal_errors.handler.write().await;
+            *w = Some(Arc::from(on_internal_error));
+        }
+        if let Some(on_sla_breach) = on_sla_breach {
+            let mut w = context.completion_times.handler.write().await;
+            *w = Some(Arc::from(on_sla_breach));
+        }
+        {
+            let mut w = context.run_history.write().await;
+            if let Some(run_history) = run_history {
+                *w = run_history;
+            }
+            w.init().await?;
+        }
+        if let Some(mut outbox) = outbox {
+            outbox.init().await?;
+            {
+                let mut w = context.outbox.write().await;
+                *w = Some(outbox);
+            }
+            if !read_only {
+                OutboxDispatcher::spawn(context.clone());
+            }
+        }
+
+        let scheduler = Scheduler {
+            maintenance_windows,
+            budget,
+            dispatch_budget,
+            leader_election: leader_election.map(|l| Arc::new(tokio::sync::Mutex::new(l))),
+            run_lock: run_lock.map(|(l, ttl)| (Arc::new(tokio::sync::Mutex::new(l)), ttl)),
+            ..Default::default()
+        };
+
+        Ok(JobsSchedulerLocked {
+            context,
+            actors: Arc::new(Actors {
+                inited: AtomicBool::new(false),
+                job_creator: Default::default(),
+                job_deleter: Default::default(),
+                job_runner: Default::default(),
+                notification_creator: Default::default(),
+                notification_deleter: Default::default(),
+                notification_runner: Default::default(),
+                scheduler: RwLock::new(scheduler),
+            }),
+            shutdown_notifier: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Job, JobNotification, JobScheduler, JobSchedulerError};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Sets its flag when the code of a run is dropped
+    struct Dropped(Arc<AtomicBool>);
+
+    impl Drop for Dropped {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// A run cancelled while it runs, its code dropped and the run kept in the run history as
+    /// cancelled
+    #[tokio::test]
+    async fn cancel_run() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let dropped = Arc::new(AtomicBool::new(false));
+        let flag = dropped.clone();
+        let job = Job::new_async("0 0 0 1 1 *", move |_, _| {
+            let guard = Dropped(flag.clone());
+            Box::pin(async move {
+                let _guard = guard;
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            })
+        })
+        .expect("job");
+        let job_id = sched.add(job).await.expect("add");
+        let mut notifications = sched.context.notify_tx.subscribe();
+        sched.start().await.expect("start");
+        sched.trigger(&job_id).await.expect("trigger");
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let running = sched.running_executions().await;
+        assert_eq!(running.len(), 1);
+        sched
+            .cancel_run(&running[0].execution_id)
+            .await
+            .expect("cancel");
+        assert!(sched.running_executions().await.is_empty());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(
+            dropped.load(Ordering::SeqCst),
+            "the code of the run kept running"
+        );
+        assert!(matches!(
+            sched.cancel_run(&running[0].execution_id).await,
+            Err(crate::JobSchedulerError::NoRunningExecution)
+        ));
+
+        let history = sched.run_history(&job_id, 10).await.expect("history");
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].outcome, JobNotification::Cancelled as i32);
+        assert_eq!(
+            history[0].scheduled_at_utc().map(|t| t.timestamp_millis()),
+            Some(running[0].scheduled.timestamp_millis())
+        );
+
+        let mut states = vec![];
+        while let Ok((id, state)) = notifications.try_recv() {
+            if id == job_id {
+                states.push(state);
+            }
+        }
+        assert!(states.ends_with(&[JobNotification::Cancelled, JobNotification::Done]));
+        sched.shutdown().await.expect("shutdown");
+    }
+
+    /// Wait for `job_id` to change to `state`, failing after a few seconds
+    async fn wait_for(
+        events: &mut tokio::sync::broadcast::Receiver<(uuid::Uuid, JobNotification)>,
+        job_id: uuid::Uuid,
+        state: JobNotification,
+    ) {
+        tokio::time::timeout(Duration::from_secs(3), async {
+            loop {
+                match events.recv().await {
+                    Ok((id, changed)) if id == job_id && changed == state => return,
+                    Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(e) => panic!("{:?}", e),
+                }
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("never {:?}", state))
+    }
+
+    /// A due run of a job requiring approval waits until it is approved, and is skipped when it
+    /// is rejected
+    #[tokio::test]
+    async fn approval_gate() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counted = runs.clone();
+        let mut job = Job::new("* * * * * *", move |_, _| {
+            counted.fetch_add(1, Ordering::SeqCst);
+        })
+        .expect("job");
+        job.set_requires_approval(true).expect("approval");
+        let job_id = sched.add(job).await.expect("add");
+        let mut events = sched.subscribe_events();
+        sched.start().await.expect("start");
+
+        wait_for(&mut events, job_id, JobNotification::Pending).await;
+        let pending = sched.pending_approvals().await;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].job_id, job_id);
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 0, "ran without approval");
+        // Later due runs wait on the same approval
+        assert_eq!(sched.pending_approvals().await.len(), 1);
+
+        sched.approve(&job_id).await.expect("approve");
+        wait_for(&mut events, job_id, JobNotification::Done).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert!(matches!(
+            sched.approve(&job_id).await,
+            Err(JobSchedulerError::NoPendingApproval)
+        ));
+
+        wait_for(&mut events, job_id, JobNotification::Pending).await;
+        sched.reject(&job_id).await.expect("reject");
+        wait_for(&mut events, job_id, JobNotification::Skipped).await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1, "ran after being rejected");
+        sched.shutdown().await.expect("shutdown");
+    }
+
+    /// A slow job counting its started and finished runs
+    fn slow(started: &Arc<AtomicUsize>, finished: &Arc<AtomicBool>, secs: u64) -> Job {
+        let started = started.clone();
+        let finished = finished.clone();
+        Job::new_async("0 0 0 1 1 *", move |_, _| {
+            started.fetch_add(1, Ordering::SeqCst);
+            let finished = finished.clone();
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_secs(secs)).await;
+                finished.store(true, Ordering::SeqCst);
+            })
+        })
+        .expect("job")
+    }
+
+    /// A graceful shutdown waits for the run in progress and skips the runs activated meanwhile
+    #[tokio::test]
+    async fn graceful_shutdown_drains() {
+        let sched = JobScheduler::new().await.expect("scheduler");
+        let started = Arc::new(AtomicUsize::new(0));
+        let finished = Arc::new(AtomicBool::new(false));
+        let job_id = sched.add(slow(&started, &finished, 1)).await.expect("add");
+        let mut events = sched.subscribe_events();
+        sched.start().await.expect("start");
+        sched.trigger(&job_id).await.expect("trigger");
+        wait_for(&mut events, job_id, JobNotification::Started).await;
+
+        let mut draining = sched.clone();
+        let shutdown = tokio::spawn(async move {
+            draining
+                .shutdown_graceful(Duration::from_secs(5))
+                .await
+                .expect("shutdown")
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        sched.trigger(&job_id).await.expect("trigger");
+        wait_for(&mut events, job_id, JobNotification::Skipped).await;
+        assert!(
+            !finished.load(Ordering::SeqCst),
+            "shut down before draining"
+        );
+
+        let left = shutdown.await.expect("join");
+        assert!(left.is_empty());
+        assert!(
+            finished.load(Ordering::SeqCst),
+            "the run in progress was not waited on"
+        );
+        assert_eq!(
+            started.load(Ordering::SeqCst),
+            1,
+            "a run started while draining"
+        );
+    }
+
+    /// The runs still in progress when a graceful shutdown times out are returned
+    #[tokio::test]
+    async fn graceful_shutdown_timeout() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let started = Arc::new(AtomicUsize::new(0));
+        let finished = Arc::new(AtomicBool::new(false));
+        let job_id = sched.add(slow(&started, &finished, 60)).await.expect("add");
+        let mut events = sched.subscribe_events();
+        sched.start().await.expect("start");
+        sched.trigger(&job_id).await.expect("trigger");
+        wait_for(&mut events, job_id, JobNotification::Started).await;
+
+        let begun = tokio::time::Instant::now();
+        let left = sched
+            .shutdown_graceful(Duration::from_millis(300))
+            .await
+            .expect("shutdown");
+        assert!(begun.elapsed() < Duration::from_secs(5));
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].job_id, job_id);
+        assert!(!finished.load(Ordering::SeqCst));
+    }
+
+    /// A read-only scheduler neither approves nor rejects a pending run, which is left pending
+    #[tokio::test]
+    async fn read_only_approvals() {
+        let sched = JobScheduler::new_builder()
+            .read_only(true)
+            .build()
+            .await
+            .expect("scheduler");
+        let job_id = uuid::Uuid::new_v4();
+        let mut activations = sched.context.job_activation_tx.subscribe();
+        {
+            let mut w = sched.context.pending_approvals.write().await;
+            w.insert(job_id, chrono::Utc::now());
+        }
+
+        assert!(matches!(
+            sched.approve(&job_id).await,
+            Err(JobSchedulerError::ReadOnly)
+        ));
+        assert!(matches!(
+            sched.reject(&job_id).await,
+            Err(JobSchedulerError::ReadOnly)
+        ));
+        assert_eq!(sched.pending_approvals().await.len(), 1);
+        assert!(activations.try_recv().is_err(), "activated a run");
+    }
+
+    /// The runs of a job kept in the run history with their outcome, the latest first
+    #[tokio::test]
+    async fn run_history() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let runs = Arc::new(AtomicUsize::new(0));
+        let job = Job::new_fallible("0 0 0 1 1 *", move |_, _| {
+            match runs.fetch_add(1, Ordering::SeqCst) {
+                0 => Ok(()),
+                _ => Err("boom"),
+            }
+        })
+        .expect("job");
+        let job_id = sched.add(job).await.expect("add");
+        let mut events = sched.subscribe_events();
+        sched.start().await.expect("start");
+
+        let triggered = chrono::Utc::now() - chrono::Duration::hours(1);
+        sched
+            .trigger_as_of(&job_id, triggered)
+            .await
+            .expect("trigger as of");
+        wait_for(&mut events, job_id, JobNotification::Done).await;
+        sched.trigger(&job_id).await.expect("trigger");
+        wait_for(&mut events, job_id, JobNotification::Done).await;
+
+        let history = sched.run_history(&job_id, 10).await.expect("history");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].job_id(), Some(job_id));
+        assert_eq!(history[0].outcome, JobNotification::Failed as i32);
+        assert_eq!(history[0].error.as_deref(), Some("boom"));
+        assert_eq!(history[1].outcome, JobNotification::Succeeded as i32);
+        assert_eq!(history[1].error, None);
+        assert_eq!(
+            history[1].scheduled_at_utc().map(|t| t.timestamp_millis()),
+            Some(triggered.timestamp_millis())
+        );
+        assert!(history[1].ended_at >= history[1].started_at);
+        assert_eq!(
+            sched.run_history(&job_id, 1).await.expect("latest").len(),
+            1
+        );
+        sched.shutdown().await.expect("shutdown");
+    }
+
+    /// The time until the next run of repeated, one-shot and cron jobs, and of no job at all
+    #[tokio::test]
+    async fn time_until_next() {
+        let sched = JobScheduler::new().await.expect("scheduler");
+        let repeated = Job::new_repeated(Duration::from_secs(3600), |_, _| {}).expect("job");
+        let repeated = sched.add(repeated).await.expect("add");
+        let one_shot = Job::new_one_shot(Duration::from_secs(60), |_, _| {}).expect("job");
+        let one_shot = sched.add(one_shot).await.expect("add");
+        let cron = Job::new("0 0 0 1 1 *", |_, _| {}).expect("job");
+        let cron = sched.add(cron).await.expect("add");
+
+        let within = |until: Option<Duration>, secs: u64| {
+            let until = until.expect("next run");
+            assert!(until <= Duration::from_secs(secs), "{:?}", until);
+            assert!(until + Duration::from_secs(2) >= Duration::from_secs(secs));
+        };
+        within(sched.time_until_next(&repeated).await.expect("until"), 3600);
+        within(sched.time_until_next(&one_shot).await.expect("until"), 60);
+        let next = sched
+            .time_next_scheduled_for(&cron)
+            .await
+            .expect("next")
+            .expect("next run");
+        assert_eq!(next.format("%m-%d %H:%M:%S").to_string(), "01-01 00:00:00");
+        assert!(matches!(
+            sched.time_until_next(&uuid::Uuid::new_v4()).await,
+            Err(JobSchedulerError::FetchJob)
+        ));
+    }
+
+    /// The jobs listed with their type, schedule, runs and state
+    #[tokio::test]
+    async fn list_jobs() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let mut job = Job::new_fallible("0 0 0 1 1 *", |_, _| Err("boom")).expect("job");
+        job.set_name("yearly").expect("name");
+        let cron = sched.add(job).await.expect("add");
+        let mut job = Job::new_repeated(Duration::from_secs(3600), |_, _| {}).expect("job");
+        job.set_stop(true).expect("stop");
+        let repeated = sched.add(job).await.expect("add");
+        let mut events = sched.subscribe_events();
+        sched.start().await.expect("start");
+        sched.trigger(&cron).await.expect("trigger");
+        wait_for(&mut events, cron, JobNotification::Done).await;
+
+        let jobs: Vec<crate::JobDescriptor> = sched.list_jobs().await.expect("list");
+        assert_eq!(jobs.len(), 2);
+        let cron = jobs.iter().find(|j| j.job_id == cron).expect("cron job");
+        assert_eq!(cron.name, "yearly");
+        assert_eq!(cron.job_type, crate::JobType::Cron);
+        assert_eq!(cron.schedule.as_deref(), Some("0 0 0 1 1 *"));
+        assert!(cron.next_tick.is_some());
+        assert_eq!(cron.count, 1);
+        assert!(cron.last_run_failed);
+        assert_eq!(cron.consecutive_failures, 1);
+        assert!(!cron.stopped);
+        let repeated = jobs
+            .iter()
+            .find(|j| j.job_id == repeated)
+            .expect("repeated job");
+        assert_eq!(repeated.job_type, crate::JobType::Repeated);
+        assert_eq!(repeated.repeated_every, Some(3600));
+        assert_eq!(repeated.schedule, None);
+        assert!(repeated.stopped);
+        assert_eq!(repeated.count, 0);
+        sched.shutdown().await.expect("shutdown");
     }
 }
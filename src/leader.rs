@@ -0,0 +1,73 @@
+use crate::JobSchedulerError;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+type ElectionFuture<T> = Pin<Box<dyn Future<Output = Result<T, JobSchedulerError>> + Send>>;
+
+///
+/// Decides which of the schedulers sharing a store fires the due jobs, so that the replicas of an
+/// application each running a scheduler against the same Postgres or Nats store run every job
+/// once instead of once per replica. The other schedulers still add, remove and list jobs.
+pub trait LeaderElection {
+    ///
+    /// Become the leader when no other scheduler is, or stay the leader. Called on every tick of
+    /// the scheduler, which only fires the due jobs when this returns true.
+    fn acquire(&mut self) -> ElectionFuture<bool>;
+
+    ///
+    /// Stop being the leader, so that another scheduler takes over without waiting for the lease
+    /// to run out. Called when the scheduler shuts down.
+    fn release(&mut self) -> ElectionFuture<()>;
+}
+
+///
+/// A leader election between the schedulers of one process, for tests and simulations. The
+/// clones of an election take part in the same one, each as a candidate of its own.
+pub struct MemoryLeaderElection {
+    id: Uuid,
+    leader: Arc<Mutex<Option<Uuid>>>,
+}
+
+impl Default for MemoryLeaderElection {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            leader: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl Clone for MemoryLeaderElection {
+    fn clone(&self) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            leader: self.leader.clone(),
+        }
+    }
+}
+
+impl LeaderElection for MemoryLeaderElection {
+    fn acquire(&mut self) -> ElectionFuture<bool> {
+        let id = self.id;
+        let leader = self.leader.clone();
+        Box::pin(async move {
+            let mut leader = leader.lock().await;
+            Ok(*leader.get_or_insert(id) == id)
+        })
+    }
+
+    fn release(&mut self) -> ElectionFuture<()> {
+        let id = self.id;
+        let leader = self.leader.clone();
+        Box::pin(async move {
+            let mut leader = leader.lock().await;
+            if *leader == Some(id) {
+                *leader = None;
+            }
+            Ok(())
+        })
+    }
+}
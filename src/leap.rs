@@ -0,0 +1,435 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::LeapDayPolicy;
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::LeapDayPolicy;
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use cron::{Schedule, TimeUnitSpec};
+
+/// How far ahead the next tick of a schedule is looked for by default, about 30 years, enough
+/// for February 29 on a given day of the week
+pub(crate) const DEFAULT_SEARCH_HORIZON_DAYS: i64 = 30 * 366;
+
+///
+/// The default search horizon, for the ticks looked for without a scheduler
+pub(crate) fn default_search_horizon() -> chrono::Duration {
+    chrono::Duration::days(DEFAULT_SEARCH_HORIZON_DAYS)
+}
+
+/// A leap second is stored as a nanosecond count past a full second
+const NANOS_PER_SECOND: u32 = 1_000_000_000;
+
+/// The most ticks in a row skipped for matching an exclusion, a day of ticks every second
+const MAX_EXCLUDED_TICKS: usize = 86_400;
+
+///
+/// `after` with a leap second read as the last second of its minute. The system clock repeats
+/// or smears leap seconds, so a schedule never fires in one, and fires once around one.
+fn without_leap_second(after: &DateTime<Utc>) -> DateTime<Utc> {
+    let nanosecond = after.nanosecond();
+    if nanosecond < NANOS_PER_SECOND {
+        return *after;
+    }
+    after
+        .with_nanosecond(nanosecond - NANOS_PER_SECOND)
+        .unwrap_or(*after)
+}
+
+fn is_leap_year(year: i32) -> bool {
+    NaiveDate::from_ymd_opt(year, 2, 29).is_some()
+}
+
+///
+/// The times the schedule fires on the day standing in for February 29 in `year`, in order,
+/// when it is not a leap year
+fn on_stand_in_day(
+    schedule: &Schedule,
+    timezone: Tz,
+    policy: LeapDayPolicy,
+    year: i32,
+) -> impl DoubleEndedIterator<Item = DateTime<Utc>> + '_ {
+    let day = match policy {
+        LeapDayPolicy::LeapYearsOnly => None,
+        LeapDayPolicy::LastDayOfFebruary => NaiveDate::from_ymd_opt(year, 2, 28),
+        LeapDayPolicy::FirstOfMarch => NaiveDate::from_ymd_opt(year, 3, 1),
+    };
+    let day = day.filter(|day| {
+        !is_leap_year(year)
+            && schedule.years().includes(year as u32)
+            && schedule
+                .days_of_week()
+                .includes(day.weekday().number_from_sunday())
+    });
+    day.into_iter().flat_map(move |day| {
+        schedule.hours().iter().flat_map(move |hour| {
+            schedule.minutes().iter().flat_map(move |minute| {
+                schedule.seconds().iter().filter_map(move |second| {
+                    // A time skipped by a daylight saving change doesn't fire
+                    let time = day.and_hms_opt(hour, minute, second)?;
+                    let time = timezone.from_local_datetime(&time).earliest()?;
+                    Some(time.with_timezone(&Utc))
+                })
+            })
+        })
+    })
+}
+
+///
+/// The first time after `after` matching `schedule` in `timezone`, with February 29 handled
+/// according to `policy`
+pub(crate) fn next_cron_tick(
+    schedule: &Schedule,
+    timezone: Tz,
+    policy: LeapDayPolicy,
+    after: &DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    next_cron_tick_until(schedule, timezone, policy, after, None)
+}
+
+///
+/// The first time after `after` matching `schedule`, like `next_cron_tick_until`, that none of
+/// `exclusions` match in `timezone`. `None` when there is none up to `until`, or when more than
+/// `MAX_EXCLUDED_TICKS` ticks in a row are excluded, like for a schedule excluded for good.
+pub(crate) fn next_cron_tick_excluding(
+    schedule: &Schedule,
+    exclusions: &[Schedule],
+    timezone: Tz,
+    policy: LeapDayPolicy,
+    after: &DateTime<Utc>,
+    until: Option<&DateTime<Utc>>,
+) -> Option<DateTime<Utc>> {
+    let mut after = *after;
+    for _ in 0..=MAX_EXCLUDED_TICKS {
+        let next = next_cron_tick_until(schedule, timezone, policy, &after, until)?;
+        let local = next.with_timezone(&timezone);
+        if !exclusions.iter().any(|e| e.includes(local)) {
+            return Some(next);
+        }
+        after = next;
+    }
+    None
+}
+
+///
+/// The first time after `after` matching `schedule`, like `next_cron_tick`, but no later than
+/// `until`. The schedule isn't searched when none of its years come before `until`, and the days
+/// standing in for February 29 are looked for up to `until` only.
+pub(crate) fn next_cron_tick_until(
+    schedule: &Schedule,
+    timezone: Tz,
+    policy: LeapDayPolicy,
+    after: &DateTime<Utc>,
+    until: Option<&DateTime<Utc>>,
+) -> Option<DateTime<Utc>> {
+    let after = without_leap_second(after);
+    let from = after.with_timezone(&timezone).year();
+    let until_year = until.map(|until| until.with_timezone(&timezone).year());
+    if let Some(until_year) = until_year {
+        if !(from..=until_year).any(|year| schedule.years().includes(year as u32)) {
+            return None;
+        }
+    }
+    let within = |t: &DateTime<Utc>| until.is_none_or(|until| t <= until);
+    let next = schedule
+        .after(&after.with_timezone(&timezone))
+        .map(|t| t.with_timezone(&Utc))
+        .take_while(within)
+        .next();
+    if policy == LeapDayPolicy::LeapYearsOnly
+        || !schedule.months().includes(2)
+        || !schedule.days_of_month().includes(29)
+    {
+        return next;
+    }
+    // Stand-in days after the next regular tick come too late
+    let to = next
+        .map(|t| t.with_timezone(&timezone).year())
+        .unwrap_or(from + 8);
+    let to = until_year.map_or(to, |until_year| to.min(until_year));
+    (from..=to)
+        .find_map(|year| on_stand_in_day(schedule, timezone, policy, year).find(|t| *t > after))
+        .into_iter()
+        .chain(next)
+        .min()
+        .filter(within)
+}
+
+///
+/// The last time before `before` matching `schedule`, like `prev_cron_tick_until`, that none of
+/// `exclusions` match in `timezone`. `None` when there is none back to `since`, or when more
+/// than `MAX_EXCLUDED_TICKS` ticks in a row are excluded.
+pub(crate) fn prev_cron_tick_excluding(
+    schedule: &Schedule,
+    exclusions: &[Schedule],
+    timezone: Tz,
+    policy: LeapDayPolicy,
+    before: &DateTime<Utc>,
+    since: Option<&DateTime<Utc>>,
+) -> Option<DateTime<Utc>> {
+    let mut before = *before;
+    for _ in 0..=MAX_EXCLUDED_TICKS {
+        let prev = prev_cron_tick_until(schedule, timezone, policy, &before, since)?;
+        let local = prev.with_timezone(&timezone);
+        if !exclusions.iter().any(|e| e.includes(local)) {
+            return Some(prev);
+        }
+        before = prev;
+    }
+    None
+}
+
+///
+/// The last time strictly before `before` matching `schedule` in `timezone`, with February 29
+/// handled according to `policy`, but no earlier than `since`. The times `next_cron_tick_until`
+/// gives, looked up the other way.
+pub(crate) fn prev_cron_tick_until(
+    schedule: &Schedule,
+    timezone: Tz,
+    policy: LeapDayPolicy,
+    before: &DateTime<Utc>,
+    since: Option<&DateTime<Utc>>,
+) -> Option<DateTime<Utc>> {
+    let before = without_leap_second(before);
+    let from = before.with_timezone(&timezone).year();
+    let since_year = since.map(|since| since.with_timezone(&timezone).year());
+    if let Some(since_year) = since_year {
+        if !(since_year..=from).any(|year| schedule.years().includes(year as u32)) {
+            return None;
+        }
+    }
+    let within = |t: &DateTime<Utc>| since.is_none_or(|since| t >= since);
+    // The schedule is looked up from the whole second before the one given, which would leave
+    // out the times earlier in the second of a `before` within one
+    let whole_second = match before.nanosecond() {
+        0 => before,
+        _ => before.with_nanosecond(0).unwrap_or(before) + chrono::Duration::seconds(1),
+    };
+    let prev = schedule
+        .after(&whole_second.with_timezone(&timezone))
+        .next_back()
+        .map(|t| t.with_timezone(&Utc))
+        .filter(within);
+    if policy == LeapDayPolicy::LeapYearsOnly
+        || !schedule.months().includes(2)
+        || !schedule.days_of_month().includes(29)
+    {
+        return prev;
+    }
+    // Stand-in days before the previous regular tick come too early
+    let to = prev
+        .map(|t| t.with_timezone(&timezone).year())
+        .unwrap_or(from - 8);
+    let to = since_year.map_or(to, |since_year| to.max(since_year));
+    (to..=from)
+        .rev()
+        .find_map(|year| {
+            on_stand_in_day(schedule, timezone, policy, year)
+                .rev()
+                .find(|t| *t < before)
+        })
+        .into_iter()
+        .chain(prev)
+        .max()
+        .filter(within)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "has_bytes"))]
+    use crate::job::job_data::{job_stored_data::Job, CronJob, JobStoredData, LeapDayPolicy};
+    #[cfg(feature = "has_bytes")]
+    use crate::job::job_data_prost::{job_stored_data::Job, CronJob, JobStoredData, LeapDayPolicy};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    /// The data of a cron job with `id`, due at `next_tick`
+    fn job_data(id: Uuid, next_tick: u64) -> JobStoredData {
+        let mut job = crate::Job::new("0 0 * * * *", |_, _| {}).expect("job");
+        let mut data = job.job_data().expect("data");
+        data.id = Some(id.into());
+        data.next_tick = next_tick;
+        data
+    }
+
+    #[test]
+    fn leap_days() {
+        use chrono::{TimeZone, Timelike};
+
+        let next = |schedule: &str, timezone: &str, policy: LeapDayPolicy, after: &str| {
+            let mut data = job_data(Uuid::new_v4(), 0);
+            data.leap_day_policy = policy.into();
+            data.job = Some(Job::CronJob(CronJob {
+                schedule: schedule.to_string(),
+                timezone: timezone.to_string(),
+                exclusions: vec![],
+                jitter_seconds: 0,
+            }));
+            let after = after.parse::<chrono::DateTime<Utc>>().unwrap();
+            data.next_cron_tick(&after).map(|t| t.to_rfc3339())
+        };
+        let feb_29 = "0 0 9 29 Feb *";
+        // (policy, after, next tick)
+        let matrix = [
+            (
+                LeapDayPolicy::LeapYearsOnly,
+                "2025-01-01T00:00:00Z",
+                "2028-02-29T09:00:00+00:00",
+            ),
+            (
+                LeapDayPolicy::LastDayOfFebruary,
+                "2025-01-01T00:00:00Z",
+                "2025-02-28T09:00:00+00:00",
+            ),
+            (
+                LeapDayPolicy::FirstOfMarch,
+                "2025-01-01T00:00:00Z",
+                "2025-03-01T09:00:00+00:00",
+            ),
+            // Leap years have their own February 29
+            (
+                LeapDayPolicy::LastDayOfFebruary,
+                "2024-01-01T00:00:00Z",
+                "2024-02-29T09:00:00+00:00",
+            ),
+            (
+                LeapDayPolicy::FirstOfMarch,
+                "2024-01-01T00:00:00Z",
+                "2024-02-29T09:00:00+00:00",
+            ),
+            // Past the stand-in day, the next one is a year later
+            (
+                LeapDayPolicy::LastDayOfFebruary,
+                "2025-02-28T09:00:00Z",
+                "2026-02-28T09:00:00+00:00",
+            ),
+            (
+                LeapDayPolicy::FirstOfMarch,
+                "2027-03-01T09:00:00Z",
+                "2028-02-29T09:00:00+00:00",
+            ),
+        ];
+        for (policy, after, expected) in matrix {
+            assert_eq!(
+                next(feb_29, "", policy, after).as_deref(),
+                Some(expected),
+                "{:?} after {}",
+                policy,
+                after
+            );
+        }
+
+        // A schedule also firing on February 28 fires there once
+        assert_eq!(
+            next(
+                "0 0 9 28,29 Feb *",
+                "",
+                LeapDayPolicy::LastDayOfFebruary,
+                "2025-02-28T09:00:00Z"
+            )
+            .as_deref(),
+            Some("2026-02-28T09:00:00+00:00")
+        );
+        // Schedules not firing on February 29 are left alone
+        assert_eq!(
+            next(
+                "0 0 9 1 * *",
+                "",
+                LeapDayPolicy::FirstOfMarch,
+                "2025-02-15T00:00:00Z"
+            )
+            .as_deref(),
+            Some("2025-03-01T09:00:00+00:00")
+        );
+        // The stand-in day is in the timezone of the job
+        assert_eq!(
+            next(
+                feb_29,
+                "Asia/Tokyo",
+                LeapDayPolicy::LastDayOfFebruary,
+                "2025-01-01T00:00:00Z"
+            )
+            .as_deref(),
+            Some("2025-02-28T00:00:00+00:00")
+        );
+
+        // A leap second reads as the last second of its minute, so no run fires in it or twice
+        let mut data = job_data(Uuid::new_v4(), 0);
+        data.job = Some(Job::CronJob(CronJob {
+            schedule: "* * * * * *".to_string(),
+            timezone: String::new(),
+            exclusions: vec![],
+            jitter_seconds: 0,
+        }));
+        let leap_second = Utc
+            .with_ymd_and_hms(2016, 12, 31, 23, 59, 59)
+            .unwrap()
+            .with_nanosecond(1_500_000_000)
+            .unwrap();
+        assert_eq!(
+            data.next_cron_tick(&leap_second),
+            Some(Utc.with_ymd_and_hms(2017, 1, 1, 0, 0, 0).unwrap())
+        );
+    }
+
+    /// The next ticks looked for up to the search horizon, by the tick loop and when parsing a
+    /// schedule
+    #[tokio::test]
+    async fn search_horizon() {
+        use crate::scheduler::Scheduler;
+        use chrono::{Duration, TimeZone};
+        use chrono_tz::Tz;
+        use std::str::FromStr;
+
+        // February 29 on a Monday, 28 years after 2016
+        let schedule = cron::Schedule::from_str("0 0 0 29 2 Mon").unwrap();
+        let after = Utc.with_ymd_and_hms(2016, 3, 1, 0, 0, 0).unwrap();
+        let next = |years: i64| {
+            super::next_cron_tick_until(
+                &schedule,
+                Tz::UTC,
+                LeapDayPolicy::LeapYearsOnly,
+                &after,
+                Some(&(after + Duration::days(years * 366))),
+            )
+        };
+        assert_eq!(next(10), None);
+        assert_eq!(
+            next(30),
+            Some(Utc.with_ymd_and_hms(2044, 2, 29, 0, 0, 0).unwrap())
+        );
+
+        let now = Utc::now();
+        let job_id = Uuid::new_v4();
+        assert_eq!(
+            Scheduler::within_horizon(
+                job_id,
+                Some(now + Duration::days(2)),
+                &now,
+                Duration::days(1)
+            ),
+            None
+        );
+        assert_eq!(
+            Scheduler::within_horizon(
+                job_id,
+                Some(now + Duration::hours(2)),
+                &now,
+                Duration::days(1)
+            ),
+            Some(now + Duration::hours(2))
+        );
+
+        let sched = crate::JobSchedulerBuilder::default()
+            .search_horizon(std::time::Duration::from_secs(86_400))
+            .build()
+            .await
+            .expect("scheduler");
+        assert!(sched.parse_schedule("0 0 9 * * *").is_ok());
+        assert!(matches!(
+            sched.parse_schedule("0 0 0 1 1 * 2099"),
+            Err(crate::JobSchedulerError::NoUpcoming)
+        ));
+        let sched = crate::JobScheduler::new().await.expect("scheduler");
+        assert!(sched.parse_schedule("0 0 0 29 2 Mon").is_ok());
+    }
+}
@@ -3,18 +3,74 @@
 extern crate num_derive;
 extern crate core;
 
+#[macro_use]
+mod logging;
+
+#[cfg(any(feature = "graphql", feature = "sse"))]
+mod auth;
+mod backup;
+pub mod blocking;
+mod budget;
+mod changeset;
+mod clock;
 mod context;
+mod cron_expr;
+mod crontab;
+mod data_trigger;
+mod day_of_week;
+mod diff;
+mod dispatch;
+mod drift;
+mod dump;
 mod error;
+mod explain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "file_storage")]
+mod file;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+mod heartbeat;
+mod internal_error;
 mod job;
+mod job_group;
 mod job_scheduler;
+mod leader;
+mod leap;
+mod maintenance_window;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 #[cfg(feature = "nats_storage")]
 mod nats;
 mod notification;
+mod payload;
+mod peek;
 #[cfg(feature = "postgres_storage")]
 mod postgres;
+#[cfg(feature = "python")]
+mod python;
+mod reconciliation;
+#[cfg(feature = "redis_storage")]
+mod redis;
+mod redrive;
+mod reschedule_hook;
+mod retry;
+mod run_condition;
+mod run_lock;
+mod run_scope;
 mod scheduler;
+mod scoped;
 mod simple;
+mod simulation;
+mod sla;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sse")]
+pub mod sse;
 mod store;
+mod synthetic;
+#[cfg(any(test, feature = "test_harness"))]
+pub mod test_harness;
 
 use std::ops::Add;
 use std::str::FromStr;
@@ -25,34 +81,122 @@ use crate::job::job_data::ListOfUuids;
 #[cfg(feature = "has_bytes")]
 use crate::job::job_data_prost::ListOfUuids;
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use cron::Schedule;
 #[cfg(not(feature = "has_bytes"))]
-use job::job_data::{JobAndNextTick, JobStoredData, Uuid as JobUuid};
+use job::job_data::{JobAndNextTick, JobStoredData, JobType, Uuid as JobUuid};
 #[cfg(feature = "has_bytes")]
-use job::job_data_prost::{JobAndNextTick, JobStoredData, Uuid as JobUuid};
+use job::job_data_prost::{JobAndNextTick, JobStoredData, JobType, Uuid as JobUuid};
 use uuid::Uuid;
 
+#[cfg(feature = "file_storage")]
+pub use crate::file::{FileMetadataStore, FileNotificationStore};
+
 #[cfg(feature = "nats_storage")]
-pub use crate::nats::{NatsMetadataStore, NatsNotificationStore, NatsStore, NatsStoreBuilder};
+pub use crate::nats::{
+    NatsLeaderElection, NatsMetadataStore, NatsNotificationStore, NatsPartitions,
+    NatsRunHistoryStore, NatsRunLock, NatsStore, NatsStoreBuilder,
+};
 
 #[cfg(feature = "postgres_storage")]
-pub use crate::postgres::{PostgresMetadataStore, PostgresNotificationStore, PostgresStore};
+pub use crate::postgres::{
+    PostgresEventLog, PostgresLeaderElection, PostgresMetadataStore, PostgresNotificationStore,
+    PostgresOutbox, PostgresPartitions, PostgresRunHistoryStore, PostgresRunLock, PostgresStore,
+};
 
+#[cfg(feature = "redis_storage")]
+pub use crate::redis::{RedisMetadataStore, RedisNotificationStore, RedisRunLock, RedisStore};
+
+#[cfg(feature = "sqlite")]
+pub use crate::sqlite::{SqliteMetadataStore, SqliteNotificationStore, SqliteStore};
+
+#[cfg(any(feature = "graphql", feature = "sse"))]
+pub use auth::{authorize, AllowAnonymousAdmin, Authenticator, RateLimiter, Role, StaticTokens};
+pub use backup::{OnBackup, RestoreConflict, RestoreReport, Snapshot};
+pub use budget::Budget;
+pub use changeset::{ChangeKind, ChangePreview, Changeset};
 pub use context::Context;
+pub use cron_expr::{CronExpr, CronExprBuilder, CronField};
+pub use data_trigger::{AvailabilityCheck, DataTrigger};
+pub use day_of_week::DayOfWeekNumbering;
+pub use diff::{DiffSchedule, FieldChange, JobDiff, ScheduleDiff, ScheduleField};
+pub use dispatch::Dispatch;
+pub use drift::DriftStats;
+pub use dump::{ChannelDepth, StateDump};
 pub use error::JobSchedulerError;
+pub use explain::{JobExplanation, TimingDecision};
+pub use heartbeat::{Beat, Heartbeat, OnHeartbeat};
+pub use internal_error::{InternalError, InternalErrorKind, OnInternalError};
 #[cfg(not(feature = "has_bytes"))]
 pub use job::job_data::JobState as JobNotification;
+#[cfg(not(feature = "has_bytes"))]
+pub use job::job_data::RetryPolicy;
+#[cfg(not(feature = "has_bytes"))]
+pub use job::job_data::{store_event, JobRun, LoggedSnapshot, StoreEvent, TicksSet};
+#[cfg(not(feature = "has_bytes"))]
+pub use job::job_data::{ConcurrencyPolicy, LeapDayPolicy, MaintenanceWindowPolicy, MisfirePolicy};
 #[cfg(feature = "has_bytes")]
 pub use job::job_data_prost::JobState as JobNotification;
+#[cfg(feature = "has_bytes")]
+pub use job::job_data_prost::RetryPolicy;
+#[cfg(feature = "has_bytes")]
+pub use job::job_data_prost::{store_event, JobRun, LoggedSnapshot, StoreEvent, TicksSet};
+#[cfg(feature = "has_bytes")]
+pub use job::job_data_prost::{
+    ConcurrencyPolicy, LeapDayPolicy, MaintenanceWindowPolicy, MisfirePolicy,
+};
 pub use job::to_code::{JobCode, NotificationCode, PinnedGetFuture, ToCode};
+pub use job::JobBuilder;
 pub use job::JobLocked as Job;
 pub use job::OnJobNotification;
 pub use job::{JobToRun, JobToRunAsync};
+pub use job_group::JobGroup;
+pub use job_scheduler::JobSchedulerBuilder;
 pub use job_scheduler::JobsSchedulerLocked as JobScheduler;
-pub use store::{MetaDataStorage, NotificationStore};
+pub use job_scheduler::{JobDescriptor, JobInfo, LocalTime, PendingApproval, RunningExecution};
+pub use leader::{LeaderElection, MemoryLeaderElection};
+pub use maintenance_window::MaintenanceWindow;
+pub use notification::{
+    DeliveryError, DeliveryMetrics, DeliveryStats, EscalationPolicy, NotificationThrottle,
+    OnDelivery, OnEscalation, OnThrottledNotification,
+};
+pub use payload::PayloadGenerator;
+pub use peek::PeekNext;
+pub use reconciliation::{MissedRuns, ReconciliationReport};
+pub use redrive::{DeadLetter, DeadLetterQuery, DeadLetterReason, RedriveFilter, RedriveProgress};
+pub use reschedule_hook::RescheduleHook;
+pub use run_condition::{RunCondition, RunPredicate};
+pub use run_lock::{run_id, MemoryRunLock, RunLock};
+#[cfg(feature = "tracing")]
+pub use run_scope::propagate_span;
+pub use run_scope::{propagate, RunFuture, RunScope};
+pub use scoped::ScopedScheduler;
+#[cfg(feature = "cbor_codec")]
+pub use store::CborCodec;
+#[cfg(feature = "json_codec")]
+pub use store::JsonCodec;
+#[cfg(feature = "msgpack_codec")]
+pub use store::MessagePackCodec;
+pub use store::{
+    migrate_store, ChaosStore, CodeGet, DataLoss, EventLog, EventQuery, EventSourcedStore,
+    ExportFormat, FaultConfig, JobCodeGet, JobListState, JobQuery, JobSort, MemoryEventLog,
+    MemoryOutbox, MetaDataStorage, MigrationReport, NotificationRunnableCodeGet, NotificationStore,
+    OfflineQueueConfig, OfflineQueueEvent, OfflineQueueStore, OnOfflineQueue, OnStoreRetry, Outbox,
+    OutboxEntry, Page, PartitionProvider, PartitionedStore, RetryingStore, RunHistoryStore,
+    StoreRetryConfig, StoreRetryEvent,
+};
+#[cfg(any(feature = "zstd_compression", feature = "lz4_compression"))]
+pub use store::{BlobCompression, CompressedStore, CompressionAlgorithm};
+#[cfg(feature = "has_bytes")]
+pub use store::{Codec, FileEventLog, ProstCodec};
+
+pub use simulation::{simulate, DstAnomaly, Hotspot, SimulationReport};
+pub use sla::{CompletionStats, OnSlaBreach, SlaBreach, SlaTarget};
+pub use synthetic::{SyntheticSchedule, SyntheticWorkload};
 
 pub use simple::{
     SimpleJobCode, SimpleMetadataStore, SimpleNotificationCode, SimpleNotificationStore,
+    SimpleRunHistoryStore,
 };
 
 impl JobUuid {
@@ -124,6 +268,209 @@ impl JobStoredData {
             .and_then(|s| Schedule::from_str(s).ok())
     }
 
+    ///
+    /// The timezone the schedule of a cron job is evaluated in, UTC unless one was given
+    pub fn timezone(&self) -> Tz {
+        self.job
+            .as_ref()
+            .and_then(|j| match j {
+                #[cfg(feature = "has_bytes")]
+                job::job_data_prost::job_stored_data::Job::CronJob(cj) => Some(&*cj.timezone),
+                #[cfg(not(feature = "has_bytes"))]
+                job::job_data::job_stored_data::Job::CronJob(cj) => Some(&*cj.timezone),
+                _ => None,
+            })
+            .filter(|tz| !tz.is_empty())
+            .and_then(|tz| Tz::from_str(tz).ok())
+            .unwrap_or(Tz::UTC)
+    }
+
+    ///
+    /// Give a cron job without a timezone of its own `timezone`, moving its next tick to the
+    /// first one in that timezone
+    pub(crate) fn set_default_timezone(&mut self, timezone: Tz) {
+        let cron_job = match self.job.as_mut() {
+            #[cfg(feature = "has_bytes")]
+            Some(job::job_data_prost::job_stored_data::Job::CronJob(cj)) => cj,
+            #[cfg(not(feature = "has_bytes"))]
+            Some(job::job_data::job_stored_data::Job::CronJob(cj)) => cj,
+            _ => return,
+        };
+        if !cron_job.timezone.is_empty() {
+            return;
+        }
+        cron_job.timezone = timezone.name().to_string();
+        let next_tick = self.next_cron_tick(&clock::now());
+        self.set_next_tick(next_tick);
+    }
+
+    ///
+    /// The first time after `after` matching the schedule of a cron job, in its timezone, with
+    /// February 29 handled according to its leap day policy, the times matching one of its
+    /// exclusions skipped, and put off by up to its jitter. The search stops at the default
+    /// search horizon.
+    pub fn next_cron_tick(&self, after: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.next_cron_tick_within(after, leap::default_search_horizon())
+    }
+
+    ///
+    /// The next tick of a cron job like `next_cron_tick`, looked for no further than `horizon`
+    /// after `after`. The tick is strictly after `after`, so slots of the schedule put off onto
+    /// the same instant by the jitter, or a stand-in day falling on a day of the schedule, fire
+    /// once.
+    pub(crate) fn next_cron_tick_within(
+        &self,
+        after: &DateTime<Utc>,
+        horizon: chrono::Duration,
+    ) -> Option<DateTime<Utc>> {
+        let policy =
+            LeapDayPolicy::from_i32(self.leap_day_policy).unwrap_or(LeapDayPolicy::LeapYearsOnly);
+        let schedule = self.schedule()?;
+        let exclusions = self.exclusions();
+        let until = after.checked_add_signed(horizon);
+        // The ticks follow the slots of the schedule, each put off by its own delay, so the slot
+        // that last ran, put off up to `after`, is looked past rather than the tick it ran at
+        let jitter = chrono::Duration::seconds(self.jitter_seconds() as i64);
+        let mut slot = after.checked_sub_signed(jitter).unwrap_or(*after);
+        loop {
+            slot = leap::next_cron_tick_excluding(
+                &schedule,
+                &exclusions,
+                self.timezone(),
+                policy,
+                &slot,
+                until.as_ref(),
+            )?;
+            let tick = self.jittered(slot);
+            if tick > *after {
+                return Some(tick);
+            }
+        }
+    }
+
+    ///
+    /// The last time before `before` a cron job ticks at, the tick `next_cron_tick` gives when
+    /// looking from it, with the same timezone, leap day policy, exclusions and jitter. The
+    /// search stops at the default search horizon.
+    pub fn prev_cron_tick(&self, before: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.prev_cron_tick_within(before, leap::default_search_horizon())
+    }
+
+    ///
+    /// The previous tick of a cron job like `prev_cron_tick`, looked for no further than
+    /// `horizon` before `before`
+    pub(crate) fn prev_cron_tick_within(
+        &self,
+        before: &DateTime<Utc>,
+        horizon: chrono::Duration,
+    ) -> Option<DateTime<Utc>> {
+        let policy =
+            LeapDayPolicy::from_i32(self.leap_day_policy).unwrap_or(LeapDayPolicy::LeapYearsOnly);
+        let schedule = self.schedule()?;
+        let exclusions = self.exclusions();
+        let since = before.checked_sub_signed(horizon);
+        // A slot is put off by up to the jitter, so the slots are looked back from `before` until
+        // none could be put off past the latest tick before it
+        let jitter = chrono::Duration::seconds(self.jitter_seconds() as i64);
+        let mut slot = *before;
+        let mut latest: Option<DateTime<Utc>> = None;
+        loop {
+            slot = match leap::prev_cron_tick_excluding(
+                &schedule,
+                &exclusions,
+                self.timezone(),
+                policy,
+                &slot,
+                since.as_ref(),
+            ) {
+                Some(slot) => slot,
+                None => return latest,
+            };
+            if latest.is_some_and(|latest| slot + jitter <= latest) {
+                return latest;
+            }
+            let tick = self.jittered(slot);
+            if tick < *before {
+                latest = latest.max(Some(tick));
+            }
+        }
+    }
+
+    ///
+    /// The most seconds each tick of a cron job is put off by
+    pub fn jitter_seconds(&self) -> u64 {
+        match self.job.as_ref() {
+            #[cfg(feature = "has_bytes")]
+            Some(job::job_data_prost::job_stored_data::Job::CronJob(cj)) => cj.jitter_seconds,
+            #[cfg(not(feature = "has_bytes"))]
+            Some(job::job_data::job_stored_data::Job::CronJob(cj)) => cj.jitter_seconds,
+            _ => 0,
+        }
+    }
+
+    ///
+    /// `tick` put off by up to the jitter of the job. The delay is taken from the id of the job
+    /// and the tick rather than drawn at random, so every scheduler sharing a store, and one
+    /// restarted, puts the tick off by the same delay.
+    pub(crate) fn jittered(&self, tick: DateTime<Utc>) -> DateTime<Utc> {
+        let jitter = self.jitter_seconds();
+        if jitter == 0 {
+            return tick;
+        }
+        let id = self
+            .id
+            .as_ref()
+            .map(Uuid::from)
+            .unwrap_or_default()
+            .as_u128();
+        // The finalizer of SplitMix64, spreading close ticks and ids over the jitter
+        let mut x = (id as u64) ^ ((id >> 64) as u64) ^ (tick.timestamp() as u64);
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        x ^= x >> 31;
+        let delay = (x % (jitter + 1)) as i64;
+        tick.checked_add_signed(chrono::Duration::seconds(delay))
+            .unwrap_or(tick)
+    }
+
+    ///
+    /// The schedules of the times a cron job doesn't fire in, matched in its timezone
+    pub fn exclusions(&self) -> Vec<Schedule> {
+        let exclusions = match self.job.as_ref() {
+            #[cfg(feature = "has_bytes")]
+            Some(job::job_data_prost::job_stored_data::Job::CronJob(cj)) => &cj.exclusions,
+            #[cfg(not(feature = "has_bytes"))]
+            Some(job::job_data::job_stored_data::Job::CronJob(cj)) => &cj.exclusions,
+            _ => return vec![],
+        };
+        exclusions
+            .iter()
+            .filter_map(|e| Schedule::from_str(e).ok())
+            .collect()
+    }
+
+    ///
+    /// The next `count` ticks of the job, from its next tick on, as its schedule or interval
+    /// gives them, each instant once and in order. Reschedule hooks may still move the ticks
+    /// after the next one.
+    pub fn upcoming_ticks(&self, count: usize) -> Vec<DateTime<Utc>> {
+        let job_type = JobType::from_i32(self.job_type);
+        std::iter::successors(self.next_tick_utc(), |tick| {
+            let next = match job_type {
+                Some(JobType::Cron) => self.next_cron_tick(&self.spaced_after(tick)),
+                Some(JobType::Repeated) => self.repeated_every().and_then(|every| {
+                    tick.checked_add_signed(chrono::Duration::seconds(every as i64))
+                }),
+                Some(JobType::OneShot) => self.instant_after(&self.spaced_after(tick)),
+                _ => None,
+            };
+            // An instant given again would be given over and over
+            next.filter(|next| next > tick)
+        })
+        .take(count)
+        .collect()
+    }
+
     pub fn next_tick_utc(&self) -> Option<DateTime<Utc>> {
         match self.next_tick {
             0 => None,
@@ -131,6 +478,20 @@ impl JobStoredData {
         }
     }
 
+    ///
+    /// The next tick in each of `timezones`, e.g. to show teams spread over the world when a job
+    /// runs in their own time. Empty when the job has no next tick.
+    pub fn next_ticks_in(&self, timezones: &[Tz]) -> Vec<DateTime<Tz>> {
+        self.next_tick_utc()
+            .map(|next_tick| {
+                timezones
+                    .iter()
+                    .map(|timezone| next_tick.with_timezone(timezone))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     pub fn last_tick_utc(&self) -> Option<DateTime<Utc>> {
         self.last_tick.map(JobAndNextTick::utc)
     }
@@ -148,6 +509,72 @@ impl JobStoredData {
         })
     }
 
+    ///
+    /// Whether the job is a repeated job waiting its interval from the end of its previous run
+    pub fn fixed_delay(&self) -> bool {
+        match self.job.as_ref() {
+            #[cfg(feature = "has_bytes")]
+            Some(job::job_data_prost::job_stored_data::Job::NonCronJob(ncj)) => ncj.fixed_delay,
+            #[cfg(not(feature = "has_bytes"))]
+            Some(job::job_data::job_stored_data::Job::NonCronJob(ncj)) => ncj.fixed_delay,
+            _ => false,
+        }
+    }
+
+    ///
+    /// Whether the job is a repeated job keeping monotonic timing
+    pub fn monotonic(&self) -> bool {
+        match self.job.as_ref() {
+            #[cfg(feature = "has_bytes")]
+            Some(job::job_data_prost::job_stored_data::Job::NonCronJob(ncj)) => ncj.monotonic,
+            #[cfg(not(feature = "has_bytes"))]
+            Some(job::job_data::job_stored_data::Job::NonCronJob(ncj)) => ncj.monotonic,
+            _ => false,
+        }
+    }
+
+    ///
+    /// The instants a one-shot job runs at, earliest first. Empty for a job running only once.
+    pub fn instants(&self) -> Vec<DateTime<Utc>> {
+        let instants = match self.job.as_ref() {
+            #[cfg(feature = "has_bytes")]
+            Some(job::job_data_prost::job_stored_data::Job::NonCronJob(ncj)) => &ncj.instants,
+            #[cfg(not(feature = "has_bytes"))]
+            Some(job::job_data::job_stored_data::Job::NonCronJob(ncj)) => &ncj.instants,
+            _ => return vec![],
+        };
+        instants.iter().map(|i| JobAndNextTick::utc(*i)).collect()
+    }
+
+    ///
+    /// What changes when `new` replaces this data of the job, e.g. on an update by a changeset
+    pub fn diff(&self, new: &JobStoredData) -> JobDiff {
+        diff::job_diff(self, new)
+    }
+
+    ///
+    /// The least time between two ticks of the job, if it has one
+    pub fn min_interval(&self) -> Option<chrono::Duration> {
+        self.min_interval_seconds
+            .filter(|seconds| *seconds > 0)
+            .map(|seconds| chrono::Duration::seconds(seconds as i64))
+    }
+
+    ///
+    /// The instant the tick following `tick` is looked for after, for the ticks to be at least
+    /// the min interval of the job apart
+    pub(crate) fn spaced_after(&self, tick: &DateTime<Utc>) -> DateTime<Utc> {
+        self.min_interval()
+            .and_then(|min| tick.checked_add_signed(min - chrono::Duration::seconds(1)))
+            .unwrap_or(*tick)
+    }
+
+    ///
+    /// The first of the instants of the job after `tick`, if any is left
+    pub fn instant_after(&self, tick: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.instants().into_iter().find(|instant| instant > tick)
+    }
+
     pub fn set_next_tick(&mut self, tick: Option<DateTime<Utc>>) {
         self.next_tick = match tick {
             Some(t) => t.timestamp() as u64,
@@ -173,3 +600,418 @@ impl ListOfUuids {
             .any(|val| val == uuid)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "has_bytes"))]
+    use crate::job::job_data::{job_stored_data::Job, CronJob, JobStoredData};
+    #[cfg(feature = "has_bytes")]
+    use crate::job::job_data_prost::{job_stored_data::Job, CronJob, JobStoredData};
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    /// The data of a cron job with `id`, due at `next_tick`
+    fn job_data(id: Uuid, next_tick: u64) -> JobStoredData {
+        let mut job = crate::Job::new("0 0 * * * *", |_, _| {}).expect("job");
+        let mut data = job.job_data().expect("data");
+        data.id = Some(id.into());
+        data.next_tick = next_tick;
+        data
+    }
+
+    #[test]
+    fn schedule_jitter() {
+        use chrono::TimeZone;
+        use std::collections::HashSet;
+
+        let hourly = |id: u128| {
+            let mut data = job_data(Uuid::from_u128(id), 0);
+            data.job = Some(Job::CronJob(CronJob {
+                schedule: "0 0 * * * *".to_string(),
+                timezone: String::new(),
+                exclusions: vec![],
+                jitter_seconds: 600,
+            }));
+            data
+        };
+        let after = Utc.with_ymd_and_hms(2024, 1, 1, 0, 30, 0).unwrap();
+        let hour = Utc.with_ymd_and_hms(2024, 1, 1, 1, 0, 0).unwrap();
+        let ticks: Vec<_> = (1..=50)
+            .map(|id| hourly(id).next_cron_tick(&after).expect("tick"))
+            .collect();
+        assert!(ticks
+            .iter()
+            .all(|t| *t >= hour && *t <= hour + Duration::minutes(10)));
+        assert!(ticks.iter().collect::<HashSet<_>>().len() > 10);
+
+        // The same in every scheduler, and the next one is after the next hour
+        let data = hourly(7);
+        let tick = data.next_cron_tick(&after).expect("tick");
+        assert_eq!(data.next_cron_tick(&after), Some(tick));
+        let next = data.next_cron_tick(&tick).expect("next tick");
+        assert!(next >= hour + Duration::hours(1) && next <= hour + Duration::minutes(70));
+
+        // The tick of a slot put off past `after` is still ahead, rather than the next slot's
+        assert!(tick > hour);
+        assert_eq!(data.next_cron_tick(&hour), Some(tick));
+        assert_eq!(
+            data.next_cron_tick(&(tick - Duration::seconds(1))),
+            Some(tick)
+        );
+
+        let mut job = crate::JobBuilder::new()
+            .with_schedule("0 0 * * * *")
+            .with_jitter(std::time::Duration::from_secs(600))
+            .with_run_sync(|_, _| {})
+            .build()
+            .expect("job");
+        assert_eq!(job.job_data().expect("data").jitter_seconds(), 600);
+        assert!(crate::JobBuilder::new()
+            .with_repeated(std::time::Duration::from_secs(60))
+            .with_jitter(std::time::Duration::from_secs(10))
+            .with_run_sync(|_, _| {})
+            .build()
+            .is_err());
+
+        // A jitter as long as the time between two ticks is refused
+        let every_minute = |jitter| {
+            crate::JobBuilder::new()
+                .with_schedule("0 * * * * *")
+                .with_jitter(std::time::Duration::from_secs(jitter))
+                .with_run_sync(|_, _| {})
+                .build()
+        };
+        assert!(every_minute(59).is_ok());
+        assert!(matches!(
+            every_minute(60),
+            Err(crate::JobSchedulerError::JobConstruction(_))
+        ));
+    }
+
+    /// The ticks of a cron job in its timezone, shown in others, and in UTC for an unknown one
+    #[test]
+    fn cron_timezone() {
+        use chrono::TimeZone;
+
+        let mut data = job_data(Uuid::new_v4(), 0);
+        data.job = Some(Job::CronJob(CronJob {
+            schedule: "0 0 9 * * *".to_string(),
+            timezone: "Africa/Johannesburg".to_string(),
+            exclusions: vec![],
+            jitter_seconds: 0,
+        }));
+        let after = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            data.next_cron_tick(&after),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 7, 0, 0).unwrap())
+        );
+
+        // 2024-01-01 07:00 UTC
+        data.next_tick = 1_704_092_400;
+        let local: Vec<_> = data
+            .next_ticks_in(&[chrono_tz::Asia::Tokyo, chrono_tz::America::New_York])
+            .iter()
+            .map(|t| t.to_rfc3339())
+            .collect();
+        assert_eq!(
+            local,
+            vec!["2024-01-01T16:00:00+09:00", "2024-01-01T02:00:00-05:00"]
+        );
+
+        // An unknown timezone falls back to UTC
+        data.job = Some(Job::CronJob(CronJob {
+            schedule: "0 0 9 * * *".to_string(),
+            timezone: "Nowhere/Special".to_string(),
+            exclusions: vec![],
+            jitter_seconds: 0,
+        }));
+        assert_eq!(
+            data.next_cron_tick(&after),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap())
+        );
+    }
+
+    /// The default timezone of the scheduler given to the cron jobs without one of their own
+    #[test]
+    fn default_timezone() {
+        use chrono::Timelike;
+
+        let mut data = job_data(Uuid::new_v4(), 0);
+        data.set_default_timezone(chrono_tz::Asia::Kolkata);
+        assert_eq!(data.timezone(), chrono_tz::Asia::Kolkata);
+        // On the hour in India is half past in UTC
+        let next_tick = data.next_tick_utc().expect("next tick");
+        assert!(next_tick > Utc::now());
+        assert_eq!(next_tick.minute(), 30);
+
+        // A job with a timezone of its own keeps it, UTC included
+        let mut data = job_data(Uuid::new_v4(), 0);
+        data.job = Some(Job::CronJob(CronJob {
+            schedule: "0 0 * * * *".to_string(),
+            timezone: "UTC".to_string(),
+            exclusions: vec![],
+            jitter_seconds: 0,
+        }));
+        data.set_default_timezone(chrono_tz::Asia::Kolkata);
+        assert_eq!(data.timezone(), chrono_tz::UTC);
+        assert_eq!(data.next_tick, 0);
+    }
+
+    /// Cron ticks skipped when an exclusion matches them, and exclusions parsed after a `!`
+    #[tokio::test]
+    async fn schedule_exclusions() {
+        use chrono::TimeZone;
+
+        // Every half hour, but not from 2:00 to 3:00 in Johannesburg, 0:00 to 1:00 UTC
+        let mut data = job_data(Uuid::new_v4(), 0);
+        data.job = Some(Job::CronJob(CronJob {
+            schedule: "0 */30 * * * *".to_string(),
+            timezone: "Africa/Johannesburg".to_string(),
+            exclusions: vec!["0 * 2 * * *".to_string()],
+            jitter_seconds: 0,
+        }));
+        let after = Utc.with_ymd_and_hms(2023, 12, 31, 23, 50, 0).unwrap();
+        assert_eq!(
+            data.next_cron_tick(&after),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 1, 0, 0).unwrap())
+        );
+        assert_eq!(data.exclusions().len(), 1);
+
+        // Excluded for good
+        data.job = Some(Job::CronJob(CronJob {
+            schedule: "0 */30 * * * *".to_string(),
+            timezone: String::new(),
+            exclusions: vec!["* * * * * *".to_string()],
+            jitter_seconds: 0,
+        }));
+        assert_eq!(data.next_cron_tick(&after), None);
+
+        // Excluded for two days, looked for no further than the horizon
+        data.job = Some(Job::CronJob(CronJob {
+            schedule: "0 */30 * * * *".to_string(),
+            timezone: String::new(),
+            exclusions: vec!["* * * 1-2 1 *".to_string()],
+            jitter_seconds: 0,
+        }));
+        assert_eq!(data.next_cron_tick_within(&after, Duration::days(1)), None);
+        assert_eq!(
+            data.next_cron_tick_within(&after, Duration::days(3)),
+            Some(Utc.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).unwrap())
+        );
+
+        let sched = crate::JobScheduler::new().await.expect("scheduler");
+        let (schedule, exclusions) = sched
+            .parse_schedule_with_exclusions("0 */5 * * * * ! 0 * 2 * * * ! 0 0 9 * * 1-5")
+            .expect("parse");
+        assert_eq!(schedule.to_string(), "0 */5 * * * *");
+        assert_eq!(exclusions.len(), 2);
+        assert!(sched
+            .parse_schedule_with_exclusions("0 */5 * * * * ! nonsense")
+            .is_err());
+
+        let mut job = crate::Job::new("0 */30 * * * *", |_, _| {}).expect("job");
+        job.set_exclusions(exclusions).expect("exclusions");
+        assert_eq!(job.job_data().expect("data").exclusions().len(), 2);
+
+        let job = crate::JobBuilder::new()
+            .with_schedule("0 */30 * * * *")
+            .with_exclusion("0 * 2 * * *")
+            .with_run_sync(|_, _| {})
+            .build()
+            .expect("job");
+        assert_eq!(job.clone().job_data().expect("data").exclusions().len(), 1);
+        assert!(crate::JobBuilder::new()
+            .with_repeated(std::time::Duration::from_secs(60))
+            .with_exclusion("0 * 2 * * *")
+            .with_run_sync(|_, _| {})
+            .build()
+            .is_err());
+    }
+
+    /// Overlapping parts of a schedule, stand-in days and slots jittered onto the same instant
+    /// give each tick once, in order
+    #[test]
+    fn overlapping_ticks() {
+        #[cfg(not(feature = "has_bytes"))]
+        use crate::job::job_data::LeapDayPolicy;
+        #[cfg(feature = "has_bytes")]
+        use crate::job::job_data_prost::LeapDayPolicy;
+        use chrono::{DateTime, TimeZone};
+        use std::collections::HashSet;
+
+        let cron = |schedule: &str, exclusions: &[&str], jitter_seconds: u64| {
+            let mut data = job_data(Uuid::from_u128(1), 0);
+            data.job = Some(Job::CronJob(CronJob {
+                schedule: schedule.to_string(),
+                timezone: String::new(),
+                exclusions: exclusions.iter().map(|e| e.to_string()).collect(),
+                jitter_seconds,
+            }));
+            data
+        };
+        let at = |day: u32, hour: u32, minute: u32| {
+            Utc.with_ymd_and_hms(2024, 1, day, hour, minute, 0).unwrap()
+        };
+        let upcoming = |mut data: JobStoredData, from: DateTime<Utc>, count: usize| {
+            let next_tick = data.next_cron_tick(&(from - Duration::seconds(1)));
+            data.set_next_tick(next_tick);
+            data.upcoming_ticks(count)
+        };
+
+        // Parts of the schedule matching the same minutes
+        assert_eq!(
+            upcoming(cron("0 0,30,*/15 9 * * *", &[], 0), at(1, 9, 0), 5),
+            vec![
+                at(1, 9, 0),
+                at(1, 9, 15),
+                at(1, 9, 30),
+                at(1, 9, 45),
+                at(2, 9, 0)
+            ]
+        );
+        // Exclusions matching the same ticks as each other
+        assert_eq!(
+            upcoming(
+                cron("0 */30 * * * *", &["0 0 * * * *", "0 0,30 1 * * *"], 0),
+                at(1, 0, 0),
+                3
+            ),
+            vec![at(1, 0, 30), at(1, 2, 30), at(1, 3, 30)]
+        );
+
+        // A stand-in day falling on a day of the schedule
+        let mut data = cron("0 0 9 28,29 Feb *", &[], 0);
+        data.leap_day_policy = LeapDayPolicy::LastDayOfFebruary.into();
+        let feb_28 = |year: i32| Utc.with_ymd_and_hms(year, 2, 28, 9, 0, 0).unwrap();
+        assert_eq!(
+            upcoming(data, feb_28(2025), 3),
+            vec![feb_28(2025), feb_28(2026), feb_28(2027)]
+        );
+
+        // Slots of a jitter longer than the time between them put off onto the same instant
+        let data = cron("* * * * * *", &[], 5);
+        let slots = (0..60).map(|second| at(1, 0, 0) + Duration::seconds(second));
+        let jittered = slots.map(|slot| data.jittered(slot)).collect::<Vec<_>>();
+        assert!(jittered.iter().collect::<HashSet<_>>().len() < jittered.len());
+        let ticks = upcoming(data, at(1, 0, 0), 60);
+        assert_eq!(ticks.len(), 60);
+        assert!(ticks.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    /// The previous ticks of cron jobs, looked up back from each of their next ticks
+    #[test]
+    fn prev_ticks() {
+        #[cfg(not(feature = "has_bytes"))]
+        use crate::job::job_data::LeapDayPolicy;
+        #[cfg(feature = "has_bytes")]
+        use crate::job::job_data_prost::LeapDayPolicy;
+        use chrono::TimeZone;
+
+        // (schedule, timezone, exclusions, jitter, leap day policy)
+        let matrix = [
+            (
+                "0 */15 9-17 * * Mon-Fri",
+                "",
+                vec![],
+                0,
+                LeapDayPolicy::LeapYearsOnly,
+            ),
+            (
+                "0 */30 * * * *",
+                "Africa/Johannesburg",
+                vec!["0 * 2 * * *"],
+                0,
+                LeapDayPolicy::LeapYearsOnly,
+            ),
+            (
+                "0 0 * * * *",
+                "",
+                vec!["0 0 12 * * *"],
+                600,
+                LeapDayPolicy::LeapYearsOnly,
+            ),
+            (
+                "0 30 2 * * *",
+                "Europe/Berlin",
+                vec![],
+                0,
+                LeapDayPolicy::LeapYearsOnly,
+            ),
+            (
+                "0 0 9 29 Feb *",
+                "",
+                vec![],
+                0,
+                LeapDayPolicy::LastDayOfFebruary,
+            ),
+            (
+                "0 0 9 28,29 Feb *",
+                "Asia/Tokyo",
+                vec![],
+                0,
+                LeapDayPolicy::FirstOfMarch,
+            ),
+        ];
+        let after = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        for (schedule, timezone, exclusions, jitter_seconds, policy) in matrix {
+            let mut data = job_data(Uuid::from_u128(3), 0);
+            data.leap_day_policy = policy.into();
+            data.job = Some(Job::CronJob(CronJob {
+                schedule: schedule.to_string(),
+                timezone: timezone.to_string(),
+                exclusions: exclusions.iter().map(|e| e.to_string()).collect(),
+                jitter_seconds,
+            }));
+            let ticks = std::iter::successors(data.next_cron_tick(&after), |tick| {
+                data.next_cron_tick(tick)
+            })
+            .take(300)
+            .collect::<Vec<_>>();
+            assert!(ticks.len() > 20, "{}", schedule);
+            assert!(data.prev_cron_tick(&ticks[0]).is_none_or(|t| t <= after));
+            for pair in ticks.windows(2) {
+                let (prev, tick) = (pair[0], pair[1]);
+                // Strictly before, from the tick itself or from within the second after it
+                assert_eq!(data.prev_cron_tick(&tick), Some(prev), "{}", schedule);
+                let within = tick + Duration::milliseconds(500);
+                assert_eq!(data.prev_cron_tick(&within), Some(tick), "{}", schedule);
+            }
+        }
+
+        // With a jitter longer than the time between two slots, a slot put off less than the one
+        // after it still gives the latest tick before
+        let mut data = job_data(Uuid::from_u128(1), 0);
+        data.job = Some(Job::CronJob(CronJob {
+            schedule: "* * * * * *".to_string(),
+            timezone: String::new(),
+            exclusions: vec![],
+            jitter_seconds: 5,
+        }));
+        let ticks = (0..120)
+            .map(|second| data.jittered(after + Duration::seconds(second)))
+            .collect::<Vec<_>>();
+        for second in 10..110 {
+            let before = after + Duration::seconds(second);
+            let latest = ticks.iter().filter(|tick| **tick < before).max().copied();
+            assert_eq!(data.prev_cron_tick(&before), latest);
+        }
+
+        // Looked for no further back than the horizon
+        let mut data = job_data(Uuid::new_v4(), 0);
+        data.job = Some(Job::CronJob(CronJob {
+            schedule: "0 0 0 1 1 *".to_string(),
+            timezone: String::new(),
+            exclusions: vec![],
+            jitter_seconds: 0,
+        }));
+        let before = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            data.prev_cron_tick_within(&before, Duration::days(100)),
+            None
+        );
+        assert_eq!(
+            data.prev_cron_tick_within(&before, Duration::days(200)),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap())
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! The logging of the scheduler, going to `tracing` and `log` when their features are enabled,
+//! and nowhere when neither is. The messages are written like those of `log` and `tracing`,
+//! optionally preceded by fields, like the id of a job, that both get as structured fields:
+//!
+//! ```rust,ignore
+//! error!(job_id = uuid; "Error sending notification {:?}", e);
+//! ```
+//!
+//! The fields are taken with their `Debug` implementation.
+
+macro_rules! emit {
+    // Each value is bound once, by reference, before both backends take it, so an expression
+    // with a side effect or a move runs once. The binding of every level of the recursion is its
+    // own `value`, as the identifiers a macro introduces do not clash between its expansions.
+    // With both backends the message is formatted once too, and both are given the formatted
+    // arguments.
+    (@bind $tracing_level:ident, $log_level:ident, [$($bound:tt)*] $key:ident = $value:expr ; $($arg:tt)+) => {
+        match &$value {
+            value => emit!(@bound $tracing_level, $log_level, [$($bound)* $key = value,] ; $($arg)+),
+        }
+    };
+    (@bind $tracing_level:ident, $log_level:ident, [$($bound:tt)*] $key:ident = $value:expr, $($rest:tt)+) => {
+        match &$value {
+            value => emit!(@bind $tracing_level, $log_level, [$($bound)* $key = value,] $($rest)+),
+        }
+    };
+    (@bound $tracing_level:ident, $log_level:ident, [$($key:ident = $value:ident,)+] ; $($arg:tt)+) => {{
+        #[cfg(all(feature = "tracing", feature = "log"))]
+        match format_args!($($arg)+) {
+            args => {
+                tracing::event!(tracing::Level::$tracing_level, $($key = ?$value,)+ "{}", args);
+                log::log!(log::Level::$log_level, $($key:? = $value),+ ; "{}", args);
+            }
+        }
+        #[cfg(all(feature = "tracing", not(feature = "log")))]
+        tracing::event!(tracing::Level::$tracing_level, $($key = ?$value,)+ $($arg)+);
+        #[cfg(all(feature = "log", not(feature = "tracing")))]
+        log::log!(log::Level::$log_level, $($key:? = $value),+ ; $($arg)+);
+        #[cfg(not(any(feature = "tracing", feature = "log")))]
+        {
+            $(let _ = $value;)+
+            let _ = format_args!($($arg)+);
+        }
+    }};
+    ($tracing_level:ident, $log_level:ident, $($key:ident = $value:expr),+ ; $($arg:tt)+) => {
+        emit!(@bind $tracing_level, $log_level, [] $($key = $value),+ ; $($arg)+)
+    };
+    ($tracing_level:ident, $log_level:ident, ; $($arg:tt)+) => {{
+        #[cfg(all(feature = "tracing", feature = "log"))]
+        match format_args!($($arg)+) {
+            args => {
+                tracing::event!(tracing::Level::$tracing_level, "{}", args);
+                log::log!(log::Level::$log_level, "{}", args);
+            }
+        }
+        #[cfg(all(feature = "tracing", not(feature = "log")))]
+        tracing::event!(tracing::Level::$tracing_level, $($arg)+);
+        #[cfg(all(feature = "log", not(feature = "tracing")))]
+        log::log!(log::Level::$log_level, $($arg)+);
+        #[cfg(not(any(feature = "tracing", feature = "log")))]
+        {
+            let _ = format_args!($($arg)+);
+        }
+    }};
+}
+
+macro_rules! error {
+    ($($key:ident = $value:expr),+ ; $($arg:tt)+) => {
+        emit!(ERROR, Error, $($key = $value),+ ; $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        emit!(ERROR, Error, ; $($arg)+)
+    };
+}
+
+macro_rules! warn {
+    ($($key:ident = $value:expr),+ ; $($arg:tt)+) => {
+        emit!(WARN, Warn, $($key = $value),+ ; $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        emit!(WARN, Warn, ; $($arg)+)
+    };
+}
+
+macro_rules! info {
+    ($($key:ident = $value:expr),+ ; $($arg:tt)+) => {
+        emit!(INFO, Info, $($key = $value),+ ; $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        emit!(INFO, Info, ; $($arg)+)
+    };
+}
+
+macro_rules! debug {
+    ($($key:ident = $value:expr),+ ; $($arg:tt)+) => {
+        emit!(DEBUG, Debug, $($key = $value),+ ; $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        emit!(DEBUG, Debug, ; $($arg)+)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    /// Enables every event, so that `tracing` evaluates the arguments of each message
+    #[cfg(feature = "tracing")]
+    struct Enabled;
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for Enabled {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            tracing::span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, _: &tracing::Event<'_>) {}
+
+        fn enter(&self, _: &tracing::span::Id) {}
+
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    /// The fields and the arguments of a message are evaluated once, whichever backends log it
+    #[test]
+    fn evaluated_once() {
+        #[cfg(feature = "tracing")]
+        let _subscriber = tracing::subscriber::set_default(Enabled);
+        #[cfg(feature = "log")]
+        log::set_max_level(log::LevelFilter::Trace);
+        let fields = Cell::new(0);
+        let args = Cell::new(0);
+        let count = |counter: &Cell<usize>| {
+            counter.set(counter.get() + 1);
+            counter.get()
+        };
+        error!(job_id = count(&fields); "Run {}", count(&args));
+        assert_eq!(fields.get(), 1);
+        assert_eq!(args.get(), 1);
+        warn!("Run {}", count(&args));
+        assert_eq!(args.get(), 2);
+    }
+}
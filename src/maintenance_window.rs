@@ -0,0 +1,66 @@
+use chrono::{DateTime, Datelike, NaiveTime, Timelike, Utc, Weekday};
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+///
+/// A weekly recurring range of time, in UTC, during which the scheduler does not fire any jobs.
+/// The range may wrap around the end of the week, e.g. Sunday 22:00 until Monday 02:00.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    pub start_day: Weekday,
+    pub start_time: NaiveTime,
+    pub end_day: Weekday,
+    pub end_time: NaiveTime,
+}
+
+impl MaintenanceWindow {
+    ///
+    /// Create a window starting on `start_day` at `start_time` and ending on `end_day` at `end_time`
+    pub fn new(
+        start_day: Weekday,
+        start_time: NaiveTime,
+        end_day: Weekday,
+        end_time: NaiveTime,
+    ) -> Self {
+        Self {
+            start_day,
+            start_time,
+            end_day,
+            end_time,
+        }
+    }
+
+    ///
+    /// Create a window that recurs every day of the week between `start_time` and `end_time`
+    pub fn daily(start_time: NaiveTime, end_time: NaiveTime) -> Vec<Self> {
+        let mut day = Weekday::Mon;
+        let mut ret = vec![];
+        for _ in 0..7 {
+            let end_day = if end_time <= start_time {
+                day.succ()
+            } else {
+                day
+            };
+            ret.push(Self::new(day, start_time, end_day, end_time));
+            day = day.succ();
+        }
+        ret
+    }
+
+    ///
+    /// Whether the instant falls inside this window. The start is inclusive, the end exclusive.
+    pub fn contains(&self, instant: &DateTime<Utc>) -> bool {
+        let start = second_of_week(self.start_day, &self.start_time);
+        let end = second_of_week(self.end_day, &self.end_time);
+        let now = second_of_week(instant.weekday(), &instant.time());
+        if start <= end {
+            start <= now && now < end
+        } else {
+            now >= start || now < end
+        }
+    }
+}
+
+fn second_of_week(day: Weekday, time: &NaiveTime) -> u32 {
+    day.num_days_from_monday() * SECONDS_PER_DAY + time.num_seconds_from_midnight()
+}
@@ -0,0 +1,183 @@
+//! The metrics of the scheduler, recorded through the [metrics](https://docs.rs/metrics) facade
+//! for the exporter installed by the application, e.g. `metrics-exporter-prometheus` for
+//! Prometheus. The runs are labelled with the `job_id` of their job, and the registered jobs
+//! with the `group` of the scheduler.
+
+use ::metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// The runs started, a counter
+pub const RUNS_STARTED: &str = "tokio_cron_scheduler_runs_started_total";
+/// The runs that succeeded, a counter
+pub const RUNS_SUCCEEDED: &str = "tokio_cron_scheduler_runs_succeeded_total";
+/// The runs that failed, a counter
+pub const RUNS_FAILED: &str = "tokio_cron_scheduler_runs_failed_total";
+/// How long the runs took, a histogram in seconds
+pub const RUN_DURATION: &str = "tokio_cron_scheduler_run_duration_seconds";
+/// How late the runs were fired after they were due, a histogram in seconds
+pub const TICK_LAG: &str = "tokio_cron_scheduler_tick_lag_seconds";
+/// The jobs the scheduler ticks, a gauge
+pub const JOBS_REGISTERED: &str = "tokio_cron_scheduler_jobs_registered";
+
+///
+/// Describe the metrics to the installed recorder, for exporters showing the descriptions
+pub(crate) fn describe() {
+    describe_counter!(RUNS_STARTED, "The runs of jobs started");
+    describe_counter!(RUNS_SUCCEEDED, "The runs of jobs that succeeded");
+    describe_counter!(RUNS_FAILED, "The runs of jobs that failed");
+    describe_histogram!(
+        RUN_DURATION,
+        ::metrics::Unit::Seconds,
+        "How long the runs of jobs took"
+    );
+    describe_histogram!(
+        TICK_LAG,
+        ::metrics::Unit::Seconds,
+        "How late the runs of jobs were fired after they were due"
+    );
+    describe_gauge!(JOBS_REGISTERED, "The jobs the scheduler ticks");
+}
+
+pub(crate) fn run_started(job_id: Uuid) {
+    counter!(RUNS_STARTED, "job_id" => job_id.to_string()).increment(1);
+}
+
+pub(crate) fn run_ended(job_id: Uuid, failed: bool, duration: Duration) {
+    let outcome = if failed { RUNS_FAILED } else { RUNS_SUCCEEDED };
+    counter!(outcome, "job_id" => job_id.to_string()).increment(1);
+    histogram!(RUN_DURATION, "job_id" => job_id.to_string()).record(duration.as_secs_f64());
+}
+
+pub(crate) fn tick_lag(job_id: Uuid, lag: Duration) {
+    histogram!(TICK_LAG, "job_id" => job_id.to_string()).record(lag.as_secs_f64());
+}
+
+pub(crate) fn jobs_registered(group: &str, jobs: usize) {
+    gauge!(JOBS_REGISTERED, "group" => group.to_string()).set(jobs as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Job, JobNotification, JobScheduler};
+    use ::metrics::{
+        Counter, CounterFn, Gauge, GaugeFn, Histogram, HistogramFn, Key, KeyName, Metadata,
+        Recorder, SharedString, Unit,
+    };
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// The last value each metric was set to, or its count, by name and labels
+    #[derive(Clone, Default)]
+    struct Recorded(Arc<Mutex<HashMap<String, f64>>>);
+
+    struct Metric(String, Recorded);
+
+    impl Metric {
+        fn update(&self, f: impl FnOnce(f64) -> f64) {
+            let mut values = self.1 .0.lock().unwrap();
+            let value = values.entry(self.0.clone()).or_default();
+            *value = f(*value);
+        }
+    }
+
+    impl CounterFn for Metric {
+        fn increment(&self, value: u64) {
+            self.update(|v| v + value as f64);
+        }
+
+        fn absolute(&self, value: u64) {
+            self.update(|_| value as f64);
+        }
+    }
+
+    impl GaugeFn for Metric {
+        fn increment(&self, value: f64) {
+            self.update(|v| v + value);
+        }
+
+        fn decrement(&self, value: f64) {
+            self.update(|v| v - value);
+        }
+
+        fn set(&self, value: f64) {
+            self.update(|_| value);
+        }
+    }
+
+    impl HistogramFn for Metric {
+        fn record(&self, _value: f64) {
+            // The number of values recorded
+            self.update(|v| v + 1.0);
+        }
+    }
+
+    impl Recorded {
+        fn metric(&self, key: &Key) -> Arc<Metric> {
+            let labels: Vec<_> = key.labels().map(|l| l.value().to_string()).collect();
+            let name = format!("{}{:?}", key.name(), labels);
+            Arc::new(Metric(name, self.clone()))
+        }
+
+        fn get(&self, name: &str, label: &str) -> f64 {
+            let values = self.0.lock().unwrap();
+            let key = format!("{}{:?}", name, [label]);
+            values.get(&key).copied().unwrap_or_default()
+        }
+    }
+
+    impl Recorder for Recorded {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.metric(key))
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::from_arc(self.metric(key))
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::from_arc(self.metric(key))
+        }
+    }
+
+    /// The runs of a failing job and of a one-shot job counted, timed and labelled with their job
+    #[tokio::test]
+    async fn recorded_runs() {
+        let recorded = Recorded::default();
+        let _recorder = ::metrics::set_default_local_recorder(&recorded);
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let job = Job::new_fallible("0 0 0 1 1 *", |_, _| Err("boom")).expect("job");
+        let failing = sched.add(job).await.expect("add");
+        let job = Job::new_one_shot(Duration::from_millis(500), |_, _| {}).expect("one shot");
+        let one_shot = sched.add(job).await.expect("add");
+        let mut events = sched.subscribe_events();
+        sched.start().await.expect("start");
+        sched.trigger(&failing).await.expect("trigger");
+        let mut done = vec![];
+        tokio::time::timeout(Duration::from_secs(3), async {
+            while done.len() < 2 {
+                if let Ok((job_id, JobNotification::Done)) = events.recv().await {
+                    done.push(job_id);
+                }
+            }
+        })
+        .await
+        .expect("both runs done");
+
+        let failing = failing.to_string();
+        assert_eq!(recorded.get(super::RUNS_STARTED, &failing), 1.0);
+        assert_eq!(recorded.get(super::RUNS_FAILED, &failing), 1.0);
+        assert_eq!(recorded.get(super::RUNS_SUCCEEDED, &failing), 0.0);
+        assert_eq!(recorded.get(super::RUN_DURATION, &failing), 1.0);
+        let one_shot = one_shot.to_string();
+        assert_eq!(recorded.get(super::RUNS_SUCCEEDED, &one_shot), 1.0);
+        assert_eq!(recorded.get(super::TICK_LAG, &one_shot), 1.0);
+        assert!(recorded.get(super::JOBS_REGISTERED, "") >= 1.0);
+        sched.shutdown().await.expect("shutdown");
+    }
+}
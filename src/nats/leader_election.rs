@@ -0,0 +1,113 @@
+use crate::nats::{sanitize_nats_bucket, NatsStore};
+use crate::{JobSchedulerError, LeaderElection};
+use nats::kv::{Config, Operation, Store};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+const LEADER_KEY: &str = "TCS_LEADER";
+
+/// How long a leader stays the leader without renewing its lease
+const LEASE: Duration = Duration::from_secs(10);
+
+///
+/// Elects the leader with a lease in a Nats KV bucket named after the bucket of `store`, like
+/// `tokiocron-leader`. The leader renews the lease on every tick, and when it stops, because its
+/// process died or lost the connection, the lease runs out after `lease` and the scheduler
+/// ticking next takes over.
+#[derive(Clone)]
+pub struct NatsLeaderElection {
+    pub store: NatsStore,
+    pub lease: Duration,
+    id: Uuid,
+    bucket: Arc<RwLock<Option<Store>>>,
+}
+
+impl Default for NatsLeaderElection {
+    fn default() -> Self {
+        Self::new(NatsStore::default(), LEASE)
+    }
+}
+
+impl NatsLeaderElection {
+    pub fn new(store: NatsStore, lease: Duration) -> Self {
+        Self {
+            store,
+            lease,
+            id: Uuid::new_v4(),
+            bucket: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    fn bucket_name(&self) -> String {
+        sanitize_nats_bucket(&(self.store.bucket_name.clone() + "-leader"))
+    }
+}
+
+impl LeaderElection for NatsLeaderElection {
+    fn acquire(&mut self) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let context = self.store.context.clone();
+        let bucket_name = self.bucket_name();
+        let lease = self.lease;
+        let bucket = self.bucket.clone();
+        let id = self.id.to_string();
+        Box::pin(async move {
+            let mut bucket = bucket.write().await;
+            if bucket.is_none() {
+                let context = context.read().await;
+                let created = context
+                    .create_key_value(&Config {
+                        bucket: bucket_name,
+                        history: 1,
+                        max_age: lease,
+                        ..Default::default()
+                    })
+                    .map_err(|e| JobSchedulerError::NatsCouldNotCreateKvStore(e.to_string()))?;
+                *bucket = Some(created);
+            }
+            let bucket = bucket.as_ref().unwrap();
+            let entry = bucket.entry(LEADER_KEY).map_err(|e| {
+                error!("Error getting the leader {:?}", e);
+                JobSchedulerError::LeaderElection
+            })?;
+            // Renewing the lease, or taking it when nobody holds it. Losing the race for it to
+            // another scheduler fails the write.
+            let written = match entry {
+                Some(entry) if entry.operation == Operation::Put => {
+                    if entry.value != id.as_bytes() {
+                        return Ok(false);
+                    }
+                    bucket.update(LEADER_KEY, &id, entry.revision)
+                }
+                _ => bucket.create(LEADER_KEY, &id),
+            };
+            Ok(written.is_ok())
+        })
+    }
+
+    fn release(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let bucket = self.bucket.clone();
+        let id = self.id.to_string();
+        Box::pin(async move {
+            let bucket = bucket.read().await;
+            let bucket = match bucket.as_ref() {
+                Some(bucket) => bucket,
+                None => return Ok(()),
+            };
+            let leader = bucket.get(LEADER_KEY).map_err(|e| {
+                error!("Error getting the leader {:?}", e);
+                JobSchedulerError::LeaderElection
+            })?;
+            if leader.as_deref() == Some(id.as_bytes()) {
+                bucket.delete(LEADER_KEY).map_err(|e| {
+                    error!("Error releasing the leader lease {:?}", e);
+                    JobSchedulerError::LeaderElection
+                })?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -9,7 +9,6 @@ use std::future::Future;
 use std::pin::Pin;
 use std::time::Duration;
 use tokio::sync::RwLockReadGuard;
-use tracing::error;
 use uuid::Uuid;
 
 const LIST_NAME: &str = "TCS_JOB_LIST";
@@ -34,6 +33,7 @@ impl DataStore<JobStoredData> for NatsMetadataStore {
     ) -> Pin<Box<dyn Future<Output = Result<Option<JobStoredData>, JobSchedulerError>> + Send>>
     {
         let bucket = self.store.bucket.clone();
+        let codec = self.store.codec.clone();
         Box::pin(async move {
             let r = bucket.read().await;
             let id = uuid_to_nats_id(id);
@@ -42,7 +42,7 @@ impl DataStore<JobStoredData> for NatsMetadataStore {
                     error!("Error getting data {:?}", e);
                     JobSchedulerError::GetJobData
                 })
-                .map(|v| v.and_then(|v| JobStoredData::decode(v.as_slice()).ok()))
+                .map(|v| v.and_then(|v| codec.decode_job(&v).ok()))
         })
     }
 
@@ -51,12 +51,13 @@ impl DataStore<JobStoredData> for NatsMetadataStore {
         data: JobStoredData,
     ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
         let bucket = self.store.bucket.clone();
+        let codec = self.store.codec.clone();
         let uuid: Uuid = data.id.as_ref().unwrap().into();
         let get = self.get(uuid);
         let add_to_list = self.add_to_list_of_guids(uuid);
         Box::pin(async move {
             let bucket = bucket.read().await;
-            let bytes = data.encode_to_vec();
+            let bytes = codec.encode_job(&data)?;
             let prev = get.await;
             let uuid = uuid_to_nats_id(uuid);
             let done = match prev {
@@ -117,6 +118,7 @@ impl MetaDataStorage for NatsMetadataStore {
     ) -> Pin<Box<dyn Future<Output = Result<Vec<JobAndNextTick>, JobSchedulerError>> + Send>> {
         let list_guids = self.list_guids();
         let bucket = self.store.bucket.clone();
+        let codec = self.store.codec.clone();
         Box::pin(async move {
             let list = list_guids.await;
             if let Err(e) = list {
@@ -134,12 +136,13 @@ impl MetaDataStorage for NatsMetadataStore {
                 })
                 .flat_map(|uuid| bucket.get(&*uuid_to_nats_id(uuid)))
                 .flatten()
-                .flat_map(|buf| JobStoredData::decode(buf.as_slice()))
+                .flat_map(|buf| codec.decode_job(&buf))
                 .map(|jd| JobAndNextTick {
                     id: jd.id,
                     job_type: jd.job_type,
                     next_tick: jd.next_tick,
                     last_tick: jd.last_tick,
+                    group: jd.group,
                 })
                 .collect::<Vec<_>>();
             Ok(list)
@@ -154,6 +157,7 @@ impl MetaDataStorage for NatsMetadataStore {
     ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
         let get = self.get(guid);
         let bucket = self.store.bucket.clone();
+        let codec = self.store.codec.clone();
         Box::pin(async move {
             let get = get.await;
             match get {
@@ -163,7 +167,7 @@ impl MetaDataStorage for NatsMetadataStore {
                         None => 0,
                     } as u64;
                     val.last_tick = last_tick.map(|lt| lt.timestamp() as u64);
-                    let bytes = val.encode_to_vec();
+                    let bytes = codec.encode_job(&val)?;
                     let bucket = bucket.read().await;
                     bucket
                         .put(&*uuid_to_nats_id(guid), bytes)
@@ -190,6 +194,7 @@ impl MetaDataStorage for NatsMetadataStore {
     ) -> Pin<Box<dyn Future<Output = Result<Option<Duration>, JobSchedulerError>> + Send>> {
         let list = self.list_guids();
         let bucket = self.store.bucket.clone();
+        let codec = self.store.codec.clone();
         Box::pin(async move {
             let list = list.await;
             if let Err(e) = list {
@@ -209,7 +214,7 @@ impl MetaDataStorage for NatsMetadataStore {
                 })
                 .flat_map(|uuid| bucket.get(&*uuid_to_nats_id(uuid)))
                 .flatten()
-                .flat_map(|b| JobStoredData::decode(b.as_slice()))
+                .flat_map(|b| codec.decode_job(&b))
                 .filter_map(|jd| match jd.next_tick {
                     0 => None,
                     i => {
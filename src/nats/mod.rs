@@ -1,5 +1,9 @@
+mod leader_election;
 mod metadata_store;
 mod notification_store;
+mod partitions;
+mod run_history_store;
+mod run_lock;
 
 use nats::jetstream::JetStream;
 use nats::kv::{Config, Store};
@@ -7,9 +11,14 @@ use nats::JetStreamOptions;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::store::{Codec, ProstCodec};
 use crate::JobSchedulerError;
+pub use leader_election::NatsLeaderElection;
 pub use metadata_store::NatsMetadataStore;
 pub use notification_store::NatsNotificationStore;
+pub use partitions::NatsPartitions;
+pub use run_history_store::NatsRunHistoryStore;
+pub use run_lock::NatsRunLock;
 
 pub fn sanitize_nats_key(key: &str) -> String {
     key.replace('#', ".")
@@ -28,6 +37,8 @@ pub struct NatsStore {
     pub inited: bool,
     pub bucket_name: String,
     pub bucket: Arc<RwLock<Store>>,
+    /// How the jobs and notifications are encoded in the bucket
+    pub codec: Arc<dyn Codec>,
 }
 
 impl Default for NatsStore {
@@ -74,6 +85,7 @@ impl Default for NatsStore {
             inited: true,
             bucket_name,
             bucket,
+            codec: Arc::new(ProstCodec),
         }
     }
 }
@@ -94,6 +106,7 @@ pub struct NatsStoreBuilder {
     pub bucket: Option<String>,
     pub bucket_description: Option<String>,
     pub api_prefix: Option<String>,
+    pub codec: Option<Arc<dyn Codec>>,
 }
 
 impl NatsStoreBuilder {
@@ -127,6 +140,14 @@ impl NatsStoreBuilder {
         self
     }
 
+    ///
+    /// Encode the jobs and notifications with `codec` instead of protobuf. All the schedulers
+    /// sharing the bucket have to use the same codec.
+    pub fn codec(mut self, codec: impl Codec + 'static) -> Self {
+        self.codec = Some(Arc::new(codec));
+        self
+    }
+
     /// Build a NatsStore
     pub fn build(self) -> Result<NatsStore, JobSchedulerError> {
         let NatsStoreBuilder {
@@ -137,6 +158,7 @@ impl NatsStoreBuilder {
             bucket,
             bucket_description,
             api_prefix,
+            codec,
         } = self;
         let host = host.ok_or_else(|| JobSchedulerError::BuilderNeedsField("host".to_string()))?;
         let bucket =
@@ -191,6 +213,7 @@ impl NatsStoreBuilder {
             inited: true,
             bucket_name,
             bucket,
+            codec: codec.unwrap_or_else(|| Arc::new(ProstCodec)),
         })
     }
 }
@@ -10,7 +10,6 @@ use prost::Message;
 use std::future::Future;
 use std::pin::Pin;
 use tokio::sync::RwLockReadGuard;
-use tracing::error;
 use uuid::Uuid;
 
 const LIST_NAME: &str = "TCS_NOTIFICATION_LIST";
@@ -33,6 +32,7 @@ impl DataStore<NotificationData> for NatsNotificationStore {
     ) -> Pin<Box<dyn Future<Output = Result<Option<NotificationData>, JobSchedulerError>> + Send>>
     {
         let bucket = self.store.bucket.clone();
+        let codec = self.store.codec.clone();
         Box::pin(async move {
             let r = bucket.read().await;
             let id = uuid_to_nats_id(id);
@@ -41,7 +41,7 @@ impl DataStore<NotificationData> for NatsNotificationStore {
                     error!("Error getting data {:?}", e);
                     JobSchedulerError::GetJobData
                 })
-                .map(|v| v.and_then(|v| NotificationData::decode(v.as_slice()).ok()))
+                .map(|v| v.and_then(|v| codec.decode_notification(&v).ok()))
         })
     }
 
@@ -50,6 +50,7 @@ impl DataStore<NotificationData> for NatsNotificationStore {
         data: NotificationData,
     ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
         let bucket = self.store.bucket.clone();
+        let codec = self.store.codec.clone();
         let notification_id: Uuid = data
             .job_id
             .as_ref()
@@ -68,7 +69,7 @@ impl DataStore<NotificationData> for NatsNotificationStore {
         let add_to_list = self.add_to_list_of_guids(job_id, notification_id);
         Box::pin(async move {
             let bucket = bucket.read().await;
-            let bytes = data.encode_to_vec();
+            let bytes = codec.encode_notification(&data)?;
             let prev = get.await;
             let uuid = uuid_to_nats_id(notification_id);
             let done = match prev {
@@ -131,6 +132,7 @@ impl NotificationStore for NatsNotificationStore {
     ) -> Pin<Box<dyn Future<Output = Result<Vec<NotificationId>, JobSchedulerError>> + Send>> {
         let list_of_notification_guids = self.list_notification_guids_for_job_id(job);
         let bucket = self.store.bucket.clone();
+        let codec = self.store.codec.clone();
         let state = state as i32;
         Box::pin(async move {
             let list_of_notification_guids = list_of_notification_guids.await;
@@ -148,7 +150,7 @@ impl NotificationStore for NatsNotificationStore {
                         .get(&*uuid_to_nats_id(notification_id))
                         .ok()
                         .flatten()
-                        .and_then(|b| NotificationData::decode(b.as_slice()).ok())
+                        .and_then(|b| codec.decode_notification(&b).ok())
                         .filter(|nd| nd.job_states.contains(&state))
                         .map(|_| notification_id)
                 })
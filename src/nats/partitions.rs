@@ -0,0 +1,89 @@
+use crate::nats::{sanitize_nats_bucket, NatsMetadataStore, NatsStore};
+use crate::store::{MetaDataStorage, PartitionProvider};
+use crate::JobSchedulerError;
+use nats::kv::Config;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, JobSchedulerError>> + Send>>;
+
+/// The prefix of the streams backing the KV buckets
+const KV_STREAM_PRE: &str = "KV_";
+
+///
+/// Keeps the partitions of a `PartitionedStore` in Nats, as KV buckets named after the bucket of
+/// `store`, like `tokiocron-p1700000000`, using its connection and codec
+#[derive(Clone, Default)]
+pub struct NatsPartitions {
+    pub store: NatsStore,
+}
+
+impl NatsPartitions {
+    pub fn new(store: NatsStore) -> Self {
+        Self { store }
+    }
+
+    fn bucket_of(&self, name: &str) -> String {
+        sanitize_nats_bucket(&(self.store.bucket_name.clone() + "-" + name))
+    }
+}
+
+impl PartitionProvider for NatsPartitions {
+    fn open(&self, name: &str) -> StoreFuture<Box<dyn MetaDataStorage + Send + Sync>> {
+        let store = self.store.clone();
+        let bucket_name = self.bucket_of(name);
+        Box::pin(async move {
+            let bucket = {
+                let context = store.context.read().await;
+                context.create_key_value(&Config {
+                    bucket: bucket_name.clone(),
+                    history: 1,
+                    ..Default::default()
+                })
+            }
+            .map_err(|e| JobSchedulerError::NatsCouldNotCreateKvStore(e.to_string()))?;
+            let partition = NatsMetadataStore {
+                store: NatsStore {
+                    bucket_name,
+                    bucket: Arc::new(RwLock::new(bucket)),
+                    ..store
+                },
+            };
+            Ok(Box::new(partition) as Box<dyn MetaDataStorage + Send + Sync>)
+        })
+    }
+
+    fn list(&self) -> StoreFuture<Vec<String>> {
+        let context = self.store.context.clone();
+        let prefix = KV_STREAM_PRE.to_string() + &*self.bucket_of("");
+        Box::pin(async move {
+            let context = context.read().await;
+            let mut names = vec![];
+            for stream in context.stream_names() {
+                let stream = stream.map_err(|e| {
+                    error!("Error listing partitions {:?}", e);
+                    JobSchedulerError::CantListNextTicks
+                })?;
+                if let Some(name) = stream.strip_prefix(&*prefix) {
+                    names.push(name.to_string());
+                }
+            }
+            Ok(names)
+        })
+    }
+
+    fn drop_partition(&self, name: &str) -> StoreFuture<()> {
+        let context = self.store.context.clone();
+        let bucket_name = self.bucket_of(name);
+        Box::pin(async move {
+            let context = context.read().await;
+            if let Err(e) = context.delete_key_value(&bucket_name) {
+                error!("Error dropping partition {} {:?}", bucket_name, e);
+                return Err(JobSchedulerError::CantRemove);
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,159 @@
+use crate::job::job_data_prost::JobRun;
+use crate::nats::{sanitize_nats_bucket, NatsStore};
+use crate::store::{InitStore, RunHistoryStore};
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use nats::kv::{Config, Operation, Store};
+use prost::Message;
+use std::future::Future;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// The most revisions of a key a Nats KV bucket keeps
+const MAX_HISTORY: i64 = 64;
+
+///
+/// A run history in a Nats KV bucket named after the bucket of `store`, like
+/// `tokiocron-history`. Every run of a job is a revision of the key of the job, so the bucket
+/// keeps the last 64 runs of each job.
+#[derive(Clone)]
+pub struct NatsRunHistoryStore {
+    pub store: NatsStore,
+    bucket: Arc<RwLock<Option<Store>>>,
+}
+
+impl Default for NatsRunHistoryStore {
+    fn default() -> Self {
+        Self::new(NatsStore::default())
+    }
+}
+
+impl NatsRunHistoryStore {
+    pub fn new(store: NatsStore) -> Self {
+        Self {
+            store,
+            bucket: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    fn bucket_name(&self) -> String {
+        sanitize_nats_bucket(&(self.store.bucket_name.clone() + "-history"))
+    }
+}
+
+impl InitStore for NatsRunHistoryStore {
+    fn init(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let context = self.store.context.clone();
+        let bucket_name = self.bucket_name();
+        let bucket = self.bucket.clone();
+        Box::pin(async move {
+            let mut bucket = bucket.write().await;
+            if bucket.is_none() {
+                let context = context.read().await;
+                let created = context
+                    .create_key_value(&Config {
+                        bucket: bucket_name,
+                        history: MAX_HISTORY,
+                        ..Default::default()
+                    })
+                    .map_err(|e| JobSchedulerError::NatsCouldNotCreateKvStore(e.to_string()))?;
+                *bucket = Some(created);
+            }
+            Ok(())
+        })
+    }
+
+    fn inited(&mut self) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let bucket = self.bucket.clone();
+        Box::pin(async move {
+            let bucket = bucket.read().await;
+            Ok(bucket.is_some())
+        })
+    }
+}
+
+impl RunHistoryStore for NatsRunHistoryStore {
+    fn record(
+        &mut self,
+        run: JobRun,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let bucket = self.bucket.clone();
+        Box::pin(async move {
+            let job_id = run.job_id().ok_or(JobSchedulerError::SaveJob)?;
+            let bucket = bucket.read().await;
+            let bucket = bucket.as_ref().ok_or(JobSchedulerError::SaveJob)?;
+            if let Err(e) = bucket.put(&job_id.to_string(), run.encode_to_vec()) {
+                error!("Error writing to the run history {:?}", e);
+                return Err(JobSchedulerError::SaveJob);
+            }
+            Ok(())
+        })
+    }
+
+    fn history(
+        &mut self,
+        job_id: Uuid,
+        limit: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobRun>, JobSchedulerError>> + Send>> {
+        let bucket = self.bucket.clone();
+        Box::pin(async move {
+            let bucket = bucket.read().await;
+            let bucket = bucket.as_ref().ok_or(JobSchedulerError::ErrorLoadingJob)?;
+            let key = job_id.to_string();
+            // Reading the history of a key without revisions waits for the first one, so a job
+            // that never ran is told apart first
+            match bucket.entry(&key) {
+                Ok(Some(_)) => {}
+                Ok(None) => return Ok(vec![]),
+                Err(e) => {
+                    error!("Error reading the run history {:?}", e);
+                    return Err(JobSchedulerError::ErrorLoadingJob);
+                }
+            }
+            let history = bucket.history(&key).map_err(|e| {
+                error!("Error reading the run history {:?}", e);
+                JobSchedulerError::ErrorLoadingJob
+            })?;
+            let mut runs: Vec<JobRun> = history
+                .filter(|entry| entry.operation == Operation::Put)
+                .flat_map(|entry| JobRun::decode(&*entry.value))
+                .collect();
+            runs.reverse();
+            runs.truncate(limit);
+            Ok(runs)
+        })
+    }
+
+    fn runs_between(
+        &mut self,
+        range: Range<DateTime<Utc>>,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobRun>, JobSchedulerError>> + Send>> {
+        let bucket = self.bucket.clone();
+        Box::pin(async move {
+            let bucket = bucket.read().await;
+            let bucket = bucket.as_ref().ok_or(JobSchedulerError::ErrorLoadingJob)?;
+            let keys = bucket.keys().map_err(|e| {
+                error!("Error listing the run history {:?}", e);
+                JobSchedulerError::ErrorLoadingJob
+            })?;
+            let mut runs = vec![];
+            for key in keys {
+                let history = bucket.history(&key).map_err(|e| {
+                    error!("Error reading the run history {:?}", e);
+                    JobSchedulerError::ErrorLoadingJob
+                })?;
+                runs.extend(
+                    history
+                        .filter(|entry| entry.operation == Operation::Put)
+                        .flat_map(|entry| JobRun::decode(&*entry.value))
+                        .filter(|run| run.ended_in(&range)),
+                );
+            }
+            runs.sort_by_key(|run| run.ended_at);
+            Ok(runs)
+        })
+    }
+}
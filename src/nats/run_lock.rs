@@ -0,0 +1,82 @@
+use crate::nats::{sanitize_nats_bucket, NatsStore};
+use crate::{JobSchedulerError, RunLock};
+use nats::kv::{Config, Store};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+///
+/// Run locks kept as keys of a Nats KV bucket named after the bucket of `store`, like
+/// `tokiocron-runs`. A run is locked by the scheduler creating its key first. The bucket is
+/// created with the ttl of the first lock taken as the age its keys are removed at, so all the
+/// schedulers should use the same ttl.
+#[derive(Clone)]
+pub struct NatsRunLock {
+    pub store: NatsStore,
+    bucket: Arc<RwLock<Option<Store>>>,
+}
+
+impl Default for NatsRunLock {
+    fn default() -> Self {
+        Self::new(NatsStore::default())
+    }
+}
+
+impl NatsRunLock {
+    pub fn new(store: NatsStore) -> Self {
+        Self {
+            store,
+            bucket: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    fn bucket_name(&self) -> String {
+        sanitize_nats_bucket(&(self.store.bucket_name.clone() + "-runs"))
+    }
+}
+
+impl RunLock for NatsRunLock {
+    fn try_acquire_run_lock(
+        &mut self,
+        job_id: Uuid,
+        run_id: Uuid,
+        ttl: Duration,
+    ) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let context = self.store.context.clone();
+        let bucket_name = self.bucket_name();
+        let bucket = self.bucket.clone();
+        Box::pin(async move {
+            let mut bucket = bucket.write().await;
+            if bucket.is_none() {
+                let context = context.read().await;
+                let created = context
+                    .create_key_value(&Config {
+                        bucket: bucket_name,
+                        history: 1,
+                        max_age: ttl,
+                        ..Default::default()
+                    })
+                    .map_err(|e| JobSchedulerError::NatsCouldNotCreateKvStore(e.to_string()))?;
+                *bucket = Some(created);
+            }
+            let bucket = bucket.as_ref().unwrap();
+            let key = job_id.to_string() + "." + &run_id.to_string();
+            if bucket.create(&key, run_id.as_bytes()).is_ok() {
+                return Ok(true);
+            }
+            // Creating the key fails when another scheduler created it first, or when the bucket
+            // can't be reached, which reading the key tells apart
+            match bucket.get(&key) {
+                Ok(Some(_)) => Ok(false),
+                Ok(None) => Err(JobSchedulerError::RunLock),
+                Err(e) => {
+                    error!("Error acquiring the run lock {:?}", e);
+                    Err(JobSchedulerError::RunLock)
+                }
+            }
+        })
+    }
+}
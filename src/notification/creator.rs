@@ -10,7 +10,6 @@ use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::broadcast::{Receiver, Sender};
 use tokio::sync::RwLock;
-use tracing::{error, warn};
 use uuid::Uuid;
 
 #[derive(Default)]
@@ -94,6 +93,9 @@ impl NotificationCreator {
         job_states: Vec<JobState>,
         job_id: &Uuid,
     ) -> Result<Uuid, JobSchedulerError> {
+        if context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
         let notification_id = Uuid::new_v4();
         let data = NotificationData {
             #[cfg(feature = "has_bytes")]
@@ -111,52 +113,20 @@ impl NotificationCreator {
         };
         let create_tx = context.notify_create_tx.clone();
         let mut created_rx = context.notify_created_tx.subscribe();
-        let (tx, rx) = std::sync::mpsc::channel();
-
-        tokio::spawn(async move {
-            tokio::spawn(async move {
-                // TODO can maybe not use RwLock
-                if let Err(_e) = create_tx.send((data, Arc::new(RwLock::new(run)))) {
-                    error!("Error sending notification data");
-                }
-            });
-
-            'receiving_additions: loop {
-                let created = created_rx.recv().await;
-                match created {
-                    Ok(e) => match e {
-                        Ok(uuid) => {
-                            if uuid == notification_id {
-                                if let Err(e) = tx.send(Ok(uuid)) {
-                                    error!("Error sending notification addition success {:?}", e);
-                                }
-                                break 'receiving_additions;
-                            }
-                        }
-                        Err((e, Some(uuid))) => {
-                            if uuid == notification_id {
-                                if let Err(e) = tx.send(Err(e)) {
-                                    error!("Error sending notification addition failure {:?}", e);
-                                }
-                                break 'receiving_additions;
-                            }
-                        }
-                        _ => {}
-                    },
-                    Err(e) => {
-                        error!("Error receiving from created {:?}", e);
-                    }
-                }
-            }
-        });
+        // TODO can maybe not use RwLock
+        if let Err(_e) = create_tx.send((data, Arc::new(RwLock::new(run)))) {
+            error!("Error sending notification data");
+            return Err(JobSchedulerError::CantAdd);
+        }
 
-        let rx = rx.recv();
-        match rx {
-            Ok(ret) => ret,
-            Err(e) => {
-                error!("Error receiving status from notification addition {:?}", e);
-                Err(JobSchedulerError::CantAdd)
+        while let Some(created) = context.internal_errors.recv(&mut created_rx).await {
+            match created {
+                Ok(uuid) if uuid == notification_id => return Ok(uuid),
+                Err((e, Some(uuid))) if uuid == notification_id => return Err(e),
+                _ => {}
             }
         }
+        error!("Error receiving status from notification addition");
+        Err(JobSchedulerError::CantAdd)
     }
 }
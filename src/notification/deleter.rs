@@ -11,7 +11,6 @@ use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::broadcast::{Receiver, Sender};
 use tokio::sync::RwLock;
-use tracing::error;
 
 #[derive(Default)]
 pub struct NotificationDeleter {}
@@ -114,51 +113,30 @@ impl NotificationDeleter {
         })
     }
 
-    pub fn remove(
+    pub async fn remove(
         context: &Context,
         notification_id: &NotificationId,
         states: Option<Vec<JobState>>,
     ) -> Result<(NotificationId, bool), JobSchedulerError> {
+        if context.read_only {
+            return Err(JobSchedulerError::ReadOnly);
+        }
         let notification_id = *notification_id;
         let delete_tx = context.notify_delete_tx.clone();
         let mut deleted_rx = context.notify_deleted_tx.subscribe();
-        let (tx, rx) = std::sync::mpsc::channel();
+        if let Err(e) = delete_tx.send((notification_id, states)) {
+            error!("Error sending notification removal {:?}", e);
+            return Err(JobSchedulerError::CantRemove);
+        }
 
-        tokio::spawn(async move {
-            tokio::spawn(async move {
-                if let Err(e) = delete_tx.send((notification_id, states)) {
-                    error!("Error sending notification removal {:?}", e);
-                }
-            });
-            while let Ok(val) = deleted_rx.recv().await {
-                match val {
-                    Ok((uuid, deleted, _)) => {
-                        if uuid == notification_id {
-                            if let Err(e) = tx.send(Ok((uuid, deleted))) {
-                                error!("Error sending notification removal success {:?}", e);
-                            }
-                            break;
-                        }
-                    }
-                    Err((e, Some(uuid))) => {
-                        if uuid == notification_id {
-                            if let Err(e) = tx.send(Err(e)) {
-                                error!("Error sending removal error {:?}", e);
-                            }
-                            break;
-                        }
-                    }
-                    _ => {}
-                }
-            }
-        });
-        let ret = rx.recv();
-        match ret {
-            Ok(ret) => ret,
-            Err(e) => {
-                error!("Error getting result from notification removal {:?}", e);
-                Err(JobSchedulerError::CantRemove)
+        while let Some(val) = context.internal_errors.recv(&mut deleted_rx).await {
+            match val {
+                Ok((uuid, deleted, _)) if uuid == notification_id => return Ok((uuid, deleted)),
+                Err((e, Some(uuid))) if uuid == notification_id => return Err(e),
+                _ => {}
             }
         }
+        error!("Error getting result from notification removal");
+        Err(JobSchedulerError::CantRemove)
     }
 }
@@ -0,0 +1,179 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::JobState;
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::JobState;
+use crate::job::{JobId, NotificationId, OnJobNotification};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+///
+/// Why a sink could not deliver a notification
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeliveryError {
+    /// A failure that may go away, like a timeout or a 503 from a webhook, which is retried
+    Transient(String),
+    /// A failure retrying won't fix, like a rejected address
+    Permanent(String),
+}
+
+///
+/// A notification sink that reports whether it delivered, e.g. posting to a webhook or sending a
+/// mail
+pub type OnDelivery = dyn Fn(
+        JobId,
+        NotificationId,
+        JobState,
+    ) -> Pin<Box<dyn Future<Output = Result<(), DeliveryError>> + Send>>
+    + Send
+    + Sync;
+
+///
+/// The delivery outcomes of a sink
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeliveryStats {
+    /// Notifications delivered, on the first attempt or a retry
+    pub delivered: u64,
+    /// Notifications lost, after a permanent failure or running out of retries
+    pub failed: u64,
+    /// Attempts made again after a transient failure
+    pub retried: u64,
+    /// Notifications waiting for a retry right now
+    pub retrying: u64,
+    /// The time from the first attempt to the outcome, summed over all the notifications
+    pub total_latency: Duration,
+    pub max_latency: Duration,
+}
+
+impl DeliveryStats {
+    ///
+    /// The mean time from the first attempt to the outcome
+    pub fn mean_latency(&self) -> Option<Duration> {
+        let outcomes = self.delivered + self.failed;
+        if outcomes == 0 {
+            return None;
+        }
+        Some(self.total_latency / outcomes as u32)
+    }
+
+    fn record(&mut self, delivered: bool, latency: Duration) {
+        if delivered {
+            self.delivered += 1;
+        } else {
+            self.failed += 1;
+        }
+        self.total_latency += latency;
+        self.max_latency = self.max_latency.max(latency);
+    }
+}
+
+///
+/// Tracks the delivery outcomes of notification sinks by name, and retries the deliveries that
+/// failed transiently with an exponential backoff, so that lost alerts show up in the stats
+/// rather than going unnoticed.
+#[derive(Clone)]
+pub struct DeliveryMetrics {
+    attempts: u32,
+    backoff: Duration,
+    stats: Arc<Mutex<HashMap<String, DeliveryStats>>>,
+}
+
+impl Default for DeliveryMetrics {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_secs(1),
+            stats: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl DeliveryMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Attempt each delivery up to `attempts` times, waiting `backoff` before the first retry and
+    /// twice as long before each next one. Defaults to 3 attempts and 1 second.
+    pub fn retry(mut self, attempts: u32, backoff: Duration) -> Self {
+        self.attempts = attempts.max(1);
+        self.backoff = backoff;
+        self
+    }
+
+    ///
+    /// Wrap a sink into a notification to add to jobs, tracked under `sink`. Wrapped
+    /// notifications with the same name share their stats.
+    pub fn wrap(&self, sink: impl Into<String>, run: Box<OnDelivery>) -> Box<OnJobNotification> {
+        let metrics = self.clone();
+        let sink = sink.into();
+        let run: Arc<OnDelivery> = Arc::from(run);
+        Box::new(move |job_id, notification_id, state| {
+            let metrics = metrics.clone();
+            let sink = sink.clone();
+            let run = run.clone();
+            Box::pin(async move {
+                metrics
+                    .deliver(&sink, run, job_id, notification_id, state)
+                    .await;
+            })
+        })
+    }
+
+    ///
+    /// The stats of a sink
+    pub fn stats(&self, sink: &str) -> DeliveryStats {
+        let stats = self.stats.lock().unwrap();
+        stats.get(sink).copied().unwrap_or_default()
+    }
+
+    ///
+    /// The stats of all the sinks, by name
+    pub fn all_stats(&self) -> HashMap<String, DeliveryStats> {
+        let stats = self.stats.lock().unwrap();
+        stats.clone()
+    }
+
+    async fn deliver(
+        &self,
+        sink: &str,
+        run: Arc<OnDelivery>,
+        job_id: JobId,
+        notification_id: NotificationId,
+        state: JobState,
+    ) {
+        let started = Instant::now();
+        let mut backoff = self.backoff;
+        let mut attempt = 1;
+        let delivered = loop {
+            match run(job_id, notification_id, state).await {
+                Ok(()) => break true,
+                Err(DeliveryError::Transient(e)) if attempt < self.attempts => {
+                    warn!(sink = sink, job_id = job_id, attempt = attempt; "Delivery failed, retrying: {}", e);
+                    self.update(sink, |stats| stats.retrying += 1);
+                    tokio::time::sleep(backoff).await;
+                    self.update(sink, |stats| {
+                        stats.retrying -= 1;
+                        stats.retried += 1;
+                    });
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                Err(DeliveryError::Transient(e)) | Err(DeliveryError::Permanent(e)) => {
+                    warn!(sink = sink, job_id = job_id, attempt = attempt; "Delivery failed: {}", e);
+                    break false;
+                }
+            }
+        };
+        let latency = started.elapsed();
+        self.update(sink, |stats| stats.record(delivered, latency));
+    }
+
+    fn update(&self, sink: &str, f: impl FnOnce(&mut DeliveryStats)) {
+        let mut stats = self.stats.lock().unwrap();
+        f(stats.entry(sink.to_string()).or_default());
+    }
+}
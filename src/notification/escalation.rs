@@ -0,0 +1,105 @@
+use crate::job::JobId;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+///
+/// A sink an escalation step routes failures to, given the job and its number of consecutive
+/// failed runs
+pub type OnEscalation =
+    dyn FnMut(JobId, u32) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync;
+
+///
+/// Routes the failed runs of all jobs to sinks depending on how many times in a row a job failed,
+/// e.g. logging the first failure, alerting a chat on the third and paging on the fifth. Every
+/// failed run goes to the sink of the highest step its consecutive failures reached.
+#[derive(Clone, Default)]
+pub struct EscalationPolicy {
+    steps: Vec<(u32, Arc<RwLock<Box<OnEscalation>>>)>,
+}
+
+impl EscalationPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Route failures to `sink` from the `after`th consecutive failure of a job on
+    pub fn step(mut self, after: u32, sink: Box<OnEscalation>) -> Self {
+        self.steps.push((after, Arc::new(RwLock::new(sink))));
+        self.steps.sort_by_key(|(after, _)| *after);
+        self
+    }
+
+    ///
+    /// The sink of the highest step reached by a number of consecutive failures
+    pub(crate) fn sink_for(
+        &self,
+        consecutive_failures: u32,
+    ) -> Option<Arc<RwLock<Box<OnEscalation>>>> {
+        self.steps
+            .iter()
+            .rev()
+            .find(|(after, _)| *after <= consecutive_failures)
+            .map(|(_, sink)| sink.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EscalationPolicy;
+    use crate::{Job, JobScheduler};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// The failed runs of a running job go to the sink of the step their consecutive failures
+    /// reached, starting over after a run that succeeded
+    #[tokio::test]
+    async fn escalated_failures() {
+        let logged = Arc::new(Mutex::new(vec![]));
+        let alerted = Arc::new(Mutex::new(vec![]));
+        let sink = |failures: &Arc<Mutex<Vec<u32>>>| {
+            let failures = failures.clone();
+            Box::new(move |_, consecutive| {
+                failures.lock().unwrap().push(consecutive);
+                Box::pin(async {}) as _
+            })
+        };
+        let policy = EscalationPolicy::new()
+            .step(3, sink(&alerted))
+            .step(1, sink(&logged));
+        let mut sched = JobScheduler::new_builder()
+            .escalation_policy(policy)
+            .build()
+            .await
+            .expect("scheduler");
+        // The fifth run succeeds, the others fail
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counted = runs.clone();
+        let job = Job::new_fallible("* * * * * *", move |_, _| {
+            match counted.fetch_add(1, Ordering::SeqCst) + 1 {
+                5 => Ok(()),
+                _ => Err("failed"),
+            }
+        })
+        .expect("job");
+        let job_id = sched.add(job).await.expect("add");
+        sched.start().await.expect("start");
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while runs.load(Ordering::SeqCst) < 6 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("the job stopped running");
+        sched.remove(&job_id).await.expect("remove");
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        assert_eq!(*logged.lock().unwrap(), vec![1, 2, 1]);
+        assert_eq!(*alerted.lock().unwrap(), vec![3, 4]);
+        sched.shutdown().await.expect("shutdown");
+    }
+}
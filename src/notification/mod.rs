@@ -1,6 +1,10 @@
 mod creator;
 mod deleter;
+mod delivery;
+mod escalation;
+mod outbox;
 mod runner;
+mod throttle;
 
 #[cfg(not(feature = "has_bytes"))]
 use crate::job::job_data::NotificationData;
@@ -9,7 +13,11 @@ use crate::job::job_data_prost::NotificationData;
 use crate::job::{JobId, NotificationId};
 pub use creator::NotificationCreator;
 pub use deleter::NotificationDeleter;
+pub use delivery::{DeliveryError, DeliveryMetrics, DeliveryStats, OnDelivery};
+pub use escalation::{EscalationPolicy, OnEscalation};
+pub use outbox::OutboxDispatcher;
 pub use runner::NotificationRunner;
+pub use throttle::{NotificationThrottle, OnThrottledNotification};
 use uuid::Uuid;
 
 impl NotificationData {
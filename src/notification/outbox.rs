@@ -0,0 +1,244 @@
+use crate::context::Context;
+use crate::internal_error::InternalErrorKind;
+use crate::store::OutboxEntry;
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the outbox is checked for entries to deliver
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// The wait before the first retry of an entry, doubled on every next one
+const BACKOFF: Duration = Duration::from_secs(1);
+/// The attempts at delivering an entry before it is given up on
+const MAX_ATTEMPTS: u32 = 10;
+
+///
+/// Delivers the entries of the outbox of a scheduler to the notifications of their jobs
+pub struct OutboxDispatcher {}
+
+impl OutboxDispatcher {
+    pub fn spawn(context: Arc<Context>) {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(POLL_INTERVAL).await;
+                OutboxDispatcher::dispatch_pending(&context).await;
+            }
+        });
+    }
+
+    async fn dispatch_pending(context: &Context) {
+        let pending = {
+            let mut w = context.outbox.write().await;
+            match w.as_mut() {
+                Some(outbox) => outbox.pending().await,
+                None => return,
+            }
+        };
+        let pending = match pending {
+            Ok(pending) => pending,
+            Err(e) => {
+                context
+                    .internal_errors
+                    .report(
+                        InternalErrorKind::Outbox,
+                        None,
+                        "Could not list the pending entries of the outbox",
+                        Some(e),
+                    )
+                    .await;
+                return;
+            }
+        };
+        for entry in pending {
+            if !OutboxDispatcher::due(&entry) {
+                continue;
+            }
+            let delivered = OutboxDispatcher::deliver(context, &entry).await;
+            let given_up = !delivered && entry.attempts + 1 >= MAX_ATTEMPTS;
+            let done = {
+                let mut w = context.outbox.write().await;
+                let outbox = match w.as_mut() {
+                    Some(outbox) => outbox,
+                    None => return,
+                };
+                if delivered || given_up {
+                    outbox.remove(entry.id).await
+                } else {
+                    outbox.failed_attempt(entry.id).await
+                }
+            };
+            if let Err(e) = done {
+                context
+                    .internal_errors
+                    .report(
+                        InternalErrorKind::Outbox,
+                        Some(entry.job_id),
+                        "Could not update the outbox",
+                        Some(e),
+                    )
+                    .await;
+            }
+            if given_up {
+                context
+                    .internal_errors
+                    .report(
+                        InternalErrorKind::Outbox,
+                        Some(entry.job_id),
+                        format!(
+                            "Gave up delivering the {:?} notification after {} attempts",
+                            entry.state, MAX_ATTEMPTS
+                        ),
+                        None,
+                    )
+                    .await;
+            }
+        }
+    }
+
+    ///
+    /// Whether the backoff of an entry since its last failed attempt is over
+    fn due(entry: &OutboxEntry) -> bool {
+        let waits = 2u32.saturating_pow(entry.attempts.min(16)) - 1;
+        let wait = chrono::Duration::from_std(BACKOFF * waits).unwrap_or_default();
+        Utc::now() >= entry.created_at + wait
+    }
+
+    ///
+    /// Run the notifications of the job for the state of the entry. Fails when the job has such
+    /// notifications but none of their code is registered, as after a restart until the
+    /// notifications are added again.
+    async fn deliver(context: &Context, entry: &OutboxEntry) -> bool {
+        let notification_ids = {
+            let mut storage = context.notification_storage.write().await;
+            storage
+                .list_notification_guids_for_job_and_state(entry.job_id, entry.state)
+                .await
+        };
+        let notification_ids = match notification_ids {
+            Ok(notification_ids) => notification_ids,
+            Err(e) => {
+                context
+                    .internal_errors
+                    .report(
+                        InternalErrorKind::Outbox,
+                        Some(entry.job_id),
+                        "Could not list the notifications to deliver",
+                        Some(e),
+                    )
+                    .await;
+                return false;
+            }
+        };
+        if notification_ids.is_empty() {
+            return true;
+        }
+        let mut codes = vec![];
+        {
+            let mut code = context.notification_code.write().await;
+            for notification_id in notification_ids {
+                if let Ok(Some(code)) = code.get(notification_id).await {
+                    codes.push((notification_id, code));
+                }
+            }
+        }
+        if codes.is_empty() {
+            return false;
+        }
+        for (notification_id, code) in codes {
+            let mut code = code.write().await;
+            (code)(entry.job_id, notification_id, entry.state).await;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "has_bytes"))]
+    use crate::job::job_data::{JobIdAndNotification, NotificationData};
+    #[cfg(feature = "has_bytes")]
+    use crate::job::job_data_prost::{JobIdAndNotification, NotificationData};
+    use crate::store::{MemoryOutbox, Outbox, OutboxEntry};
+    use crate::{Job, JobNotification, JobScheduler};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    /// The done notification of a run goes through the outbox, delivered once and then removed
+    #[tokio::test]
+    async fn outbox_delivery() {
+        let mut outbox = MemoryOutbox::default();
+        let mut sched = JobScheduler::new_builder()
+            .outbox(Box::new(outbox.clone()))
+            .build()
+            .await
+            .expect("scheduler");
+        let delivered = Arc::new(AtomicUsize::new(0));
+        let counted = delivered.clone();
+        let mut job = Job::new("0 0 0 1 1 *", |_, _| {}).expect("job");
+        job.on_done_notification_add(
+            &sched,
+            Box::new(move |_, _, _| {
+                counted.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async {})
+            }),
+        )
+        .await
+        .expect("notification");
+        let job_id = sched.add(job).await.expect("add");
+        sched.start().await.expect("start");
+        sched.trigger(&job_id).await.expect("trigger");
+
+        tokio::time::timeout(Duration::from_secs(3), async {
+            while delivered.load(Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("never delivered");
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert_eq!(delivered.load(Ordering::SeqCst), 1, "delivered twice");
+        assert!(outbox.pending().await.expect("pending").is_empty());
+        sched.shutdown().await.expect("shutdown");
+    }
+
+    /// An entry whose notification has no code in this process, as after a restart, stays in
+    /// the outbox with its failed attempts counted
+    #[tokio::test]
+    async fn outbox_retry() {
+        let mut outbox = MemoryOutbox::default();
+        let mut sched = JobScheduler::new_builder()
+            .outbox(Box::new(outbox.clone()))
+            .build()
+            .await
+            .expect("scheduler");
+        let job_id = Uuid::new_v4();
+        {
+            let mut storage = sched.context.notification_storage.write().await;
+            storage
+                .add_or_update(NotificationData {
+                    job_id: Some(JobIdAndNotification {
+                        job_id: Some(job_id.into()),
+                        notification_id: Some(Uuid::new_v4().into()),
+                    }),
+                    job_states: vec![JobNotification::Done as i32],
+                    extra: vec![],
+                })
+                .await
+                .expect("notification");
+        }
+        outbox
+            .enqueue(OutboxEntry::new(job_id, JobNotification::Done))
+            .await
+            .expect("enqueue");
+        sched.start().await.expect("start");
+
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        let pending = outbox.pending().await.expect("pending");
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].job_id, job_id);
+        assert!(pending[0].attempts >= 1);
+        sched.shutdown().await.expect("shutdown");
+    }
+}
@@ -1,35 +1,81 @@
 use crate::context::Context;
+use crate::internal_error::InternalErrors;
 #[cfg(not(feature = "has_bytes"))]
 use crate::job::job_data::JobState;
 #[cfg(feature = "has_bytes")]
 use crate::job::job_data_prost::JobState;
 use crate::job::to_code::NotificationCode;
-use crate::store::NotificationStore;
+use crate::notification::EscalationPolicy;
+use crate::store::{MetaDataStorage, NotificationStore, Outbox};
 use crate::JobSchedulerError;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::broadcast::Receiver;
 use tokio::sync::RwLock;
-use tracing::error;
 use uuid::Uuid;
 
 #[derive(Default)]
 pub struct NotificationRunner {}
 
 impl NotificationRunner {
+    ///
+    /// Route a failed run to the sink of the step of the escalation policy the consecutive
+    /// failures of the job reached
+    async fn escalate(
+        escalation_policy: &Arc<RwLock<Option<EscalationPolicy>>>,
+        metadata_storage: &Arc<RwLock<Box<dyn MetaDataStorage + Send + Sync>>>,
+        job_id: Uuid,
+    ) {
+        let policy = {
+            let r = escalation_policy.read().await;
+            match r.as_ref() {
+                Some(policy) => policy.clone(),
+                None => return,
+            }
+        };
+        let consecutive_failures = {
+            let mut r = metadata_storage.write().await;
+            match r.get(job_id).await {
+                Ok(Some(job)) => job.consecutive_failures,
+                _ => {
+                    error!(job_id = job_id; "Could not get job metadata to escalate");
+                    return;
+                }
+            }
+        };
+        if let Some(sink) = policy.sink_for(consecutive_failures) {
+            tokio::spawn(async move {
+                let mut sink = sink.write().await;
+                (sink)(job_id, consecutive_failures).await;
+            });
+        }
+    }
+
     async fn listen_for_activations(
         code: Arc<RwLock<Box<dyn NotificationCode + Send + Sync>>>,
         mut rx: Receiver<(Uuid, JobState)>,
         storage: Arc<RwLock<Box<dyn NotificationStore + Send + Sync>>>,
+        metadata_storage: Arc<RwLock<Box<dyn MetaDataStorage + Send + Sync>>>,
+        escalation_policy: Arc<RwLock<Option<EscalationPolicy>>>,
+        internal_errors: InternalErrors,
+        outbox: Arc<RwLock<Option<Box<dyn Outbox + Send + Sync>>>>,
     ) {
-        loop {
-            let val = rx.recv().await;
-            if let Err(e) = val {
-                error!("Error receiving value {:?}", e);
-                break;
+        while let Some((job_id, state)) = internal_errors.recv(&mut rx).await {
+            debug!(job_id = job_id, state = state; "Job state changed");
+            if state == JobState::Failed {
+                NotificationRunner::escalate(&escalation_policy, &metadata_storage, job_id).await;
+            }
+            if matches!(
+                state,
+                JobState::Done | JobState::Failed | JobState::Succeeded | JobState::FailedWithError
+            ) {
+                // Delivered from the outbox instead
+                let r = outbox.read().await;
+                if r.is_some() {
+                    continue;
+                }
             }
-            let (job_id, state) = val.unwrap();
             let mut storage = storage.write().await;
             let notifications = storage
                 .list_notification_guids_for_job_and_state(job_id, state)
@@ -72,10 +118,20 @@ impl NotificationRunner {
         let code = context.notification_code.clone();
         let rx = context.notify_tx.subscribe();
         let storage = context.notification_storage.clone();
+        let metadata_storage = context.metadata_storage.clone();
+        let escalation_policy = context.escalation_policy.clone();
+        let internal_errors = context.internal_errors.clone();
+        let outbox = context.outbox.clone();
 
         Box::pin(async move {
             tokio::spawn(NotificationRunner::listen_for_activations(
-                code, rx, storage,
+                code,
+                rx,
+                storage,
+                metadata_storage,
+                escalation_policy,
+                internal_errors,
+                outbox,
             ));
             Ok(())
         })
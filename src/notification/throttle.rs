@@ -0,0 +1,154 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::JobState;
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::JobState;
+use crate::job::{JobId, NotificationId, OnJobNotification};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+///
+/// A notification sink behind a throttle. Besides the job, notification and state, it is given
+/// the number of identical events collapsed into this one since the last it was called with.
+pub type OnThrottledNotification = dyn FnMut(JobId, NotificationId, JobState, u64) -> Pin<Box<dyn Future<Output = ()> + Send>>
+    + Send
+    + Sync;
+
+#[derive(Default)]
+struct ThrottleState {
+    /// When the sink was last called, by job and state
+    last_sent: HashMap<(JobId, JobState), Instant>,
+    /// Events suppressed since the sink was last called, by job and state
+    collapsed: HashMap<(JobId, JobState), u64>,
+    /// All the events suppressed, by job
+    suppressed: HashMap<JobId, u64>,
+}
+
+///
+/// Throttles a notification sink, e.g. one posting to a chat or sending mails, so a flapping job
+/// does not flood it. The sink is called at most once per job and state per interval; the
+/// identical events in between are suppressed, counted, and reported with the next call.
+#[derive(Clone)]
+pub struct NotificationThrottle {
+    interval: Duration,
+    state: Arc<Mutex<ThrottleState>>,
+}
+
+impl NotificationThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            state: Arc::new(Mutex::new(ThrottleState::default())),
+        }
+    }
+
+    ///
+    /// Wrap a sink into a notification to add to jobs. The wrapped notifications of one throttle
+    /// share its counters, so the same throttle can guard one sink added to several jobs.
+    pub fn wrap(&self, mut run: Box<OnThrottledNotification>) -> Box<OnJobNotification> {
+        let throttle = self.clone();
+        Box::new(
+            move |job_id, notification_id, state| match throttle.admit(job_id, state) {
+                Some(collapsed) => (run)(job_id, notification_id, state, collapsed),
+                None => Box::pin(async {}),
+            },
+        )
+    }
+
+    ///
+    /// The number of events suppressed so far, for all jobs
+    pub fn suppressed(&self) -> u64 {
+        let state = self.state.lock().unwrap();
+        state.suppressed.values().sum()
+    }
+
+    ///
+    /// The number of events of a job suppressed so far
+    pub fn suppressed_for(&self, job_id: &JobId) -> u64 {
+        let state = self.state.lock().unwrap();
+        state.suppressed.get(job_id).copied().unwrap_or_default()
+    }
+
+    ///
+    /// Whether the sink is to be called for an event, with the number of events collapsed into it
+    fn admit(&self, job_id: JobId, job_state: JobState) -> Option<u64> {
+        let mut state = self.state.lock().unwrap();
+        let key = (job_id, job_state);
+        let now = Instant::now();
+        match state.last_sent.get(&key) {
+            Some(last_sent) if now.duration_since(*last_sent) < self.interval => {
+                *state.collapsed.entry(key).or_default() += 1;
+                *state.suppressed.entry(job_id).or_default() += 1;
+                None
+            }
+            _ => {
+                state.last_sent.insert(key, now);
+                Some(state.collapsed.remove(&key).unwrap_or_default())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NotificationThrottle;
+    use crate::{Job, JobNotification, JobScheduler};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// The done notifications of a job running every second reach a sink throttled to one call
+    /// every two seconds, the ones in between counted and reported with the next call
+    #[tokio::test]
+    async fn throttled_sink() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let throttle = NotificationThrottle::new(Duration::from_secs(2));
+        let calls = Arc::new(Mutex::new(vec![]));
+        let called = calls.clone();
+        let mut job = Job::new("* * * * * *", |_, _| {}).expect("job");
+        job.on_done_notification_add(
+            &sched,
+            throttle.wrap(Box::new(move |_, _, _, collapsed| {
+                called.lock().unwrap().push(collapsed);
+                Box::pin(async {})
+            })),
+        )
+        .await
+        .expect("notification");
+        let job_id = sched.add(job).await.expect("add");
+        let mut events = sched.subscribe_events();
+        sched.start().await.expect("start");
+
+        let mut done = 0;
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(4500);
+        while let Ok(Ok((id, state))) = tokio::time::timeout_at(deadline, events.recv()).await {
+            if id == job_id && state == JobNotification::Done {
+                done += 1;
+            }
+        }
+        sched.remove(&job_id).await.expect("remove");
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        while let Ok((id, state)) = events.try_recv() {
+            if id == job_id && state == JobNotification::Done {
+                done += 1;
+            }
+        }
+
+        let calls = calls.lock().unwrap().clone();
+        assert!(calls.len() >= 2, "{:?}", calls);
+        assert!(
+            calls.len() < done,
+            "{} calls for {} runs",
+            calls.len(),
+            done
+        );
+        assert_eq!(calls[0], 0);
+        assert!(calls[1] > 0, "nothing collapsed into {:?}", calls);
+        assert_eq!(calls.len() as u64 + throttle.suppressed(), done as u64);
+        assert_eq!(throttle.suppressed_for(&job_id), throttle.suppressed());
+        // What was reported collapsed was suppressed, the rest still waits for the next call
+        assert!(calls.iter().sum::<u64>() <= throttle.suppressed());
+        sched.shutdown().await.expect("shutdown");
+    }
+}
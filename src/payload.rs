@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::pin::Pin;
+use uuid::Uuid;
+
+///
+/// Computes the payload of a run of a job when it fires, e.g. the list of shards to process that
+/// day, from the id of the job and the time of the run. The payload is stored with the job as its
+/// last payload, where the run reads it with `JobScheduler::payload`.
+pub type PayloadGenerator =
+    dyn Fn(Uuid, DateTime<Utc>) -> Pin<Box<dyn Future<Output = Vec<u8>> + Send>> + Send + Sync;
@@ -0,0 +1,43 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::LeapDayPolicy;
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::LeapDayPolicy;
+use crate::leap;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+
+///
+/// Looks up the next instant of a schedule without holding an iterator over it, so it can be
+/// asked from anywhere, as often as needed, and always gives the same answer for the same instant
+pub trait PeekNext {
+    ///
+    /// The first instant strictly after `after`, in UTC. A leap second in `after` is read as the
+    /// last second of its minute.
+    fn peek_next(&self, after: &DateTime<Utc>) -> Option<DateTime<Utc>>;
+}
+
+impl PeekNext for Schedule {
+    fn peek_next(&self, after: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        leap::next_cron_tick(self, Tz::UTC, LeapDayPolicy::LeapYearsOnly, after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PeekNext;
+    use chrono::{TimeZone, Utc};
+    use std::str::FromStr;
+
+    /// The next instant of a schedule, asked again and again
+    #[test]
+    fn peek_next() {
+        let schedule = cron::Schedule::from_str("0 */15 * * * *").unwrap();
+        let at = Utc.with_ymd_and_hms(2025, 6, 1, 10, 15, 0).unwrap();
+        // Strictly after, and the same answer however often it is asked
+        let expected = Some(Utc.with_ymd_and_hms(2025, 6, 1, 10, 30, 0).unwrap());
+        assert_eq!(schedule.peek_next(&at), expected);
+        assert_eq!(schedule.peek_next(&at), expected);
+        assert_eq!(schedule.peek_next(&at), schedule.after(&at).next());
+    }
+}
@@ -0,0 +1,268 @@
+use crate::job::job_data_prost::{LoggedSnapshot, StoreEvent};
+use crate::postgres::PostgresStore;
+use crate::store::{EventLog, EventQuery, InitStore, Page};
+use crate::JobSchedulerError;
+use prost::Message;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+const TABLE: &str = "job_event";
+
+///
+/// An event log in a Postgres table, with the snapshots in a table of the same name suffixed
+/// with `_snapshot`
+#[derive(Clone)]
+pub struct PostgresEventLog {
+    pub store: Arc<RwLock<PostgresStore>>,
+    pub init_tables: bool,
+    pub table: String,
+}
+
+impl Default for PostgresEventLog {
+    fn default() -> Self {
+        let init_tables = std::env::var("POSTGRES_INIT_EVENT_LOG")
+            .map(|s| s.to_lowercase() == "true")
+            .unwrap_or_default();
+        let table =
+            std::env::var("POSTGRES_EVENT_LOG_TABLE").unwrap_or_else(|_| TABLE.to_lowercase());
+        let store = Arc::new(RwLock::new(PostgresStore::default()));
+        Self {
+            init_tables,
+            table,
+            store,
+        }
+    }
+}
+
+impl PostgresEventLog {
+    fn snapshot_table(&self) -> String {
+        self.table.clone() + "_snapshot"
+    }
+}
+
+impl EventLog for PostgresEventLog {
+    fn append(
+        &mut self,
+        event: StoreEvent,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::SaveJob),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let sql = "INSERT INTO ".to_string()
+                        + &*table
+                        + " (sequence, at, event) VALUES ($1, $2, $3)";
+                    let sequence = event.sequence as i64;
+                    let at = event.at as i64;
+                    let bytes = event.encode_to_vec();
+                    let result = store.execute(&*sql, &[&sequence, &at, &bytes]).await;
+                    if let Err(e) = result {
+                        error!("Error appending to the event log {:?}", e);
+                        return Err(JobSchedulerError::SaveJob);
+                    }
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    fn events_after(
+        &mut self,
+        sequence: u64,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<StoreEvent>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::ErrorLoadingJob),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let sql = "SELECT event FROM ".to_string()
+                        + &*table
+                        + " WHERE sequence > $1 ORDER BY sequence";
+                    let sequence = sequence as i64;
+                    let rows = store.query(&*sql, &[&sequence]).await;
+                    match rows {
+                        Ok(rows) => Ok(rows
+                            .iter()
+                            .flat_map(|row| {
+                                let bytes: Vec<u8> = row.try_get(0).ok()?;
+                                StoreEvent::decode(bytes.as_slice()).ok()
+                            })
+                            .collect()),
+                        Err(e) => {
+                            error!("Error reading the event log {:?}", e);
+                            Err(JobSchedulerError::ErrorLoadingJob)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn save_snapshot(
+        &mut self,
+        snapshot: LoggedSnapshot,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.snapshot_table();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::SaveJob),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let sql = "INSERT INTO ".to_string()
+                        + &*table
+                        + " (sequence, snapshot) VALUES ($1, $2) \
+                        ON CONFLICT (sequence) DO UPDATE SET snapshot = $2";
+                    let sequence = snapshot.sequence as i64;
+                    let bytes = snapshot.encode_to_vec();
+                    let result = store.execute(&*sql, &[&sequence, &bytes]).await;
+                    if let Err(e) = result {
+                        error!("Error saving the event log snapshot {:?}", e);
+                        return Err(JobSchedulerError::SaveJob);
+                    }
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    fn latest_snapshot(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<LoggedSnapshot>, JobSchedulerError>> + Send>>
+    {
+        let store = self.store.clone();
+        let table = self.snapshot_table();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::ErrorLoadingJob),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let sql = "SELECT snapshot FROM ".to_string()
+                        + &*table
+                        + " ORDER BY sequence DESC LIMIT 1";
+                    let row = store.query_opt(&*sql, &[]).await;
+                    match row {
+                        Ok(Some(row)) => {
+                            let bytes: Vec<u8> = row
+                                .try_get(0)
+                                .map_err(|_| JobSchedulerError::CantDecodeSnapshot)?;
+                            LoggedSnapshot::decode(bytes.as_slice())
+                                .map(Some)
+                                .map_err(|_| JobSchedulerError::CantDecodeSnapshot)
+                        }
+                        Ok(None) => Ok(None),
+                        Err(e) => {
+                            error!("Error reading the event log snapshot {:?}", e);
+                            Err(JobSchedulerError::ErrorLoadingJob)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn query_events(
+        &mut self,
+        query: &EventQuery,
+    ) -> Option<Pin<Box<dyn Future<Output = Result<Page<StoreEvent>, JobSchedulerError>> + Send>>>
+    {
+        // The job of an event is only in its encoded body, so those queries are left to the
+        // caller to filter
+        if query.job_id.is_some() {
+            return None;
+        }
+        let store = self.store.clone();
+        let table = self.table.clone();
+        let query = query.clone();
+        Some(Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::ErrorLoadingJob),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let sql = "SELECT event FROM ".to_string()
+                        + &*table
+                        + " WHERE sequence > $1 AND at >= $2 AND at < $3 \
+                        ORDER BY sequence LIMIT $4";
+                    let sequence = query.after_sequence() as i64;
+                    let from = query.from.map(|t| t.timestamp()).unwrap_or(i64::MIN);
+                    let to = query.to.map(|t| t.timestamp()).unwrap_or(i64::MAX);
+                    let limit = query.limit as i64 + 1;
+                    let rows = store.query(&*sql, &[&sequence, &from, &to, &limit]).await;
+                    match rows {
+                        Ok(rows) => Ok(query.page(
+                            rows.iter()
+                                .flat_map(|row| {
+                                    let bytes: Vec<u8> = row.try_get(0).ok()?;
+                                    StoreEvent::decode(bytes.as_slice()).ok()
+                                })
+                                .collect(),
+                        )),
+                        Err(e) => {
+                            error!("Error querying the event log {:?}", e);
+                            Err(JobSchedulerError::ErrorLoadingJob)
+                        }
+                    }
+                }
+            }
+        }))
+    }
+}
+
+impl InitStore for PostgresEventLog {
+    fn init(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let init_tables = self.init_tables;
+        let table = self.table.clone();
+        let snapshot_table = self.snapshot_table();
+        Box::pin(async move {
+            let mut w = store.write().await;
+            let val = w.clone().init().await?;
+            if init_tables {
+                if let PostgresStore::Inited(client) = &val {
+                    let client = client.read().await;
+                    let events = "CREATE TABLE IF NOT EXISTS ".to_string()
+                        + &*table
+                        + " (\
+                            sequence BIGINT PRIMARY KEY,\
+                            at BIGINT,\
+                            event BYTEA\
+                        )";
+                    let snapshots = "CREATE TABLE IF NOT EXISTS ".to_string()
+                        + &*snapshot_table
+                        + " (\
+                            sequence BIGINT PRIMARY KEY,\
+                            snapshot BYTEA\
+                        )";
+                    for sql in [events, snapshots] {
+                        if let Err(e) = client.execute(&*sql, &[]).await {
+                            error!("Error on init Postgres event log {:?}", e);
+                            return Err(JobSchedulerError::CantInit);
+                        }
+                    }
+                }
+            }
+            *w = val;
+            Ok(())
+        })
+    }
+
+    fn inited(&mut self) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            Ok(store.inited())
+        })
+    }
+}
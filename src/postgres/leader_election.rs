@@ -0,0 +1,113 @@
+use crate::postgres::PostgresStore;
+use crate::{JobSchedulerError, LeaderElection};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// The key of the advisory lock unless `POSTGRES_LEADER_LOCK_KEY` is set, "tcs_lead" in ASCII
+const LOCK_KEY: i64 = 0x7463_735f_6c65_6164;
+
+///
+/// Elects the leader with a Postgres advisory lock, held by the connection of the leader for as
+/// long as it lives. When the leader's process dies its connection closes, and the scheduler
+/// ticking next takes the lock over. The schedulers sharing a store have to use the same `key`.
+#[derive(Clone)]
+pub struct PostgresLeaderElection {
+    pub store: Arc<RwLock<PostgresStore>>,
+    pub key: i64,
+    /// Whether this election holds the lock
+    leader: Arc<AtomicBool>,
+}
+
+impl Default for PostgresLeaderElection {
+    fn default() -> Self {
+        let key = std::env::var("POSTGRES_LEADER_LOCK_KEY")
+            .ok()
+            .and_then(|key| key.parse().ok())
+            .unwrap_or(LOCK_KEY);
+        Self {
+            store: Arc::new(RwLock::new(PostgresStore::default())),
+            key,
+            leader: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl PostgresLeaderElection {
+    /// An election holding the lock on a connection of its own to `store`, which shouldn't be
+    /// shared with another election
+    pub fn new(store: PostgresStore) -> Self {
+        Self {
+            store: Arc::new(RwLock::new(store)),
+            ..Default::default()
+        }
+    }
+}
+
+impl LeaderElection for PostgresLeaderElection {
+    fn acquire(&mut self) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let key = self.key;
+        let leader = self.leader.clone();
+        Box::pin(async move {
+            let mut w = store.write().await;
+            if !w.inited() {
+                *w = w.clone().init().await?;
+            }
+            match &*w {
+                PostgresStore::Created(_) => Err(JobSchedulerError::LeaderElection),
+                PostgresStore::Inited(client) => {
+                    let client = client.read().await;
+                    // The lock is held by the session, so the leader only checks that its
+                    // connection still lives
+                    let result = if leader.load(Ordering::SeqCst) {
+                        client.query_one("SELECT true", &[]).await
+                    } else {
+                        client
+                            .query_one("SELECT pg_try_advisory_lock($1)", &[&key])
+                            .await
+                    };
+                    match result {
+                        Ok(row) => {
+                            let acquired = row.get::<_, bool>(0);
+                            leader.store(acquired, Ordering::SeqCst);
+                            Ok(acquired)
+                        }
+                        Err(e) => {
+                            error!("Error acquiring the leader lock {:?}", e);
+                            leader.store(false, Ordering::SeqCst);
+                            Err(JobSchedulerError::LeaderElection)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn release(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let leader = self.leader.clone();
+        Box::pin(async move {
+            if !leader.swap(false, Ordering::SeqCst) {
+                return Ok(());
+            }
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Ok(()),
+                PostgresStore::Inited(client) => {
+                    let client = client.read().await;
+                    // Also releasing the lock taken again after an error that left it held
+                    match client.execute("SELECT pg_advisory_unlock_all()", &[]).await {
+                        Ok(_) => Ok(()),
+                        Err(e) => {
+                            error!("Error releasing the leader lock {:?}", e);
+                            Err(JobSchedulerError::LeaderElection)
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
@@ -1,6 +1,6 @@
-use crate::job::job_data_prost::{CronJob, JobType, NonCronJob};
+use crate::job::job_data_prost::{CronJob, JobType, NonCronJob, RetryPolicy};
 use crate::postgres::PostgresStore;
-use crate::store::{DataStore, InitStore, MetaDataStorage};
+use crate::store::{DataStore, InitStore, JobListState, JobQuery, JobSort, MetaDataStorage, Page};
 use crate::{JobAndNextTick, JobSchedulerError, JobStoredData, JobUuid};
 use chrono::{DateTime, Utc};
 use std::future::Future;
@@ -8,11 +8,22 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
-use tokio_postgres::Row;
-use tracing::error;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, Row};
 use uuid::Uuid;
 
 const TABLE: &str = "job";
+/// The columns of a job, in the order `JobStoredData::from` reads them
+const COLUMNS: &str = "\
+    id, last_updated, next_tick, last_tick, job_type, count, \
+    ran, stopped, schedule, repeating, repeated_every, \
+    extra, last_run_failed, skip_if_last_failed, skip_if_upstream_failed, \
+    requires_approval, maintenance_window_policy, job_group, \
+    starting_deadline_seconds, missed_count, tags, warm_start, cost, priority, \
+    exclusive_group, consecutive_failures, last_payload, timezone, name, \
+    retry_max_attempts, retry_delay_millis, retry_exponential, retry_max_delay_millis, retry_jitter, \
+    last_error, concurrency_policy, leap_day_policy, misfire_policy, after_job, monotonic, fixed_delay, instants, \
+    exclusions, jitter_seconds, min_interval_seconds";
 
 #[derive(Clone)]
 pub struct PostgresMetadataStore {
@@ -51,21 +62,19 @@ impl DataStore<JobStoredData> for PostgresMetadataStore {
                 PostgresStore::Created(_) => Err(JobSchedulerError::GetJobData),
                 PostgresStore::Inited(store) => {
                     let store = store.read().await;
-                    let sql = "select \
-                        id, last_updated, next_tick, last_tick, job_type, count, \
-                        ran, stopped, schedule, repeating, repeated_every, \
-                        extra \
-                     from "
-                        .to_string()
+                    let sql = "select ".to_string()
+                        + COLUMNS
+                        + " from "
                         + &*table
                         + " where id = $1 limit 1";
-                    let row = store.query_one(&*sql, &[&id]).await;
-                    if let Err(e) = row {
-                        error!("Error getting value {:?}", e);
-                        return Err(JobSchedulerError::GetJobData);
+                    let row = store.query_opt(&*sql, &[&id]).await;
+                    match row {
+                        Ok(row) => Ok(row.map(|row| row.into())),
+                        Err(e) => {
+                            error!("Error getting value {:?}", e);
+                            Err(JobSchedulerError::GetJobData)
+                        }
                     }
-                    let row = row.unwrap();
-                    Ok(Some(row.into()))
                 }
             }
         })
@@ -92,12 +101,21 @@ impl DataStore<JobStoredData> for PostgresMetadataStore {
                         + " (\
                         id, last_updated, next_tick, job_type, count, \
                         ran, stopped, schedule, repeating, repeated_every, \
-                        extra, last_tick \
+                        extra, last_tick, last_run_failed, skip_if_last_failed, \
+                        skip_if_upstream_failed, requires_approval, maintenance_window_policy, \
+                        job_group, starting_deadline_seconds, missed_count, tags, warm_start, \
+                        cost, priority, exclusive_group, consecutive_failures, last_payload, \
+                        timezone, name, retry_max_attempts, retry_delay_millis, retry_exponential, \
+                        retry_max_delay_millis, retry_jitter, last_error, concurrency_policy, \
+                        leap_day_policy, misfire_policy, after_job, monotonic, fixed_delay, instants, \
+                        exclusions, jitter_seconds, min_interval_seconds \
                     )\
                     VALUES (\
                         $1, $2, $3, $4, $5, \
                         $6, $7, $8, $9, $10,\
-                        $11, $12 \
+                        $11, $12, $13, $14, \
+                        $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, \
+                        $30, $31, $32, $33, $34, $35, $36, $37, $38, $39, $40, $41, $42, $43, $44, $45 \
                     )\
                     ON CONFLICT (id) \
                     DO \
@@ -105,7 +123,18 @@ impl DataStore<JobStoredData> for PostgresMetadataStore {
                         SET \
                             last_updated=$2, next_tick=$3, job_type=$4, count=$5, \
                             ran=$6, stopped=$7, schedule=$8, repeating=$9, repeated_every=$10, \
-                            extra=$11, last_tick=$12
+                            extra=$11, last_tick=$12, last_run_failed=$13, skip_if_last_failed=$14, \
+                            skip_if_upstream_failed=$15, requires_approval=$16, \
+                            maintenance_window_policy=$17, job_group=$18, \
+                            starting_deadline_seconds=$19, missed_count=$20, tags=$21, \
+                            warm_start=$22, cost=$23, priority=$24, exclusive_group=$25,
+                            consecutive_failures=$26, last_payload=$27, timezone=$28, name=$29,
+                            retry_max_attempts=$30, retry_delay_millis=$31, retry_exponential=$32,
+                            retry_max_delay_millis=$33, retry_jitter=$34, last_error=$35,
+                            concurrency_policy=$36, leap_day_policy=$37, misfire_policy=$38,
+                            after_job=$39, monotonic=$40, fixed_delay=$41, instants=$42,
+                            exclusions=$43, jitter_seconds=$44,
+                            min_interval_seconds=$45
                     ";
                     let last_updated = data.last_updated.as_ref().map(|i| *i as i64);
                     let next_tick = data.next_tick as i64;
@@ -117,6 +146,18 @@ impl DataStore<JobStoredData> for PostgresMetadataStore {
                         Some(CronJobType(ct)) => Some(ct.schedule.clone()),
                         _ => None,
                     };
+                    let timezone = match data.job.as_ref() {
+                        Some(CronJobType(ct)) => Some(ct.timezone.clone()),
+                        _ => None,
+                    };
+                    let exclusions = match data.job.as_ref() {
+                        Some(CronJobType(ct)) => Some(ct.exclusions.clone()),
+                        _ => None,
+                    };
+                    let jitter_seconds = match data.job.as_ref() {
+                        Some(CronJobType(ct)) => Some(ct.jitter_seconds as i64),
+                        _ => None,
+                    };
                     let repeating = match data.job.as_ref() {
                         Some(NonCronJobType(ct)) => Some(ct.repeating),
                         _ => None,
@@ -125,8 +166,52 @@ impl DataStore<JobStoredData> for PostgresMetadataStore {
                         Some(NonCronJobType(ct)) => Some(ct.repeated_every as i64),
                         _ => None,
                     };
+                    let monotonic = match data.job.as_ref() {
+                        Some(NonCronJobType(ct)) => Some(ct.monotonic),
+                        _ => None,
+                    };
+                    let fixed_delay = match data.job.as_ref() {
+                        Some(NonCronJobType(ct)) => Some(ct.fixed_delay),
+                        _ => None,
+                    };
+                    let instants: Option<Vec<i64>> = match data.job.as_ref() {
+                        Some(NonCronJobType(ct)) => {
+                            Some(ct.instants.iter().map(|i| *i as i64).collect())
+                        }
+                        _ => None,
+                    };
                     let extra = data.extra;
                     let last_tick = data.last_tick.as_ref().map(|i| *i as i64);
+                    let last_run_failed = data.last_run_failed;
+                    let skip_if_last_failed = data.skip_if_last_failed;
+                    let skip_if_upstream_failed: Option<Uuid> =
+                        data.skip_if_upstream_failed.as_ref().map(|u| u.into());
+                    let requires_approval = data.requires_approval;
+                    let maintenance_window_policy = data.maintenance_window_policy;
+                    let group = data.group;
+                    let starting_deadline_seconds =
+                        data.starting_deadline_seconds.map(|s| s as i64);
+                    let missed_count = data.missed_count as i32;
+                    let tags = data.tags;
+                    let warm_start = data.warm_start;
+                    let cost = data.cost as i32;
+                    let priority = data.priority;
+                    let exclusive_group = data.exclusive_group;
+                    let consecutive_failures = data.consecutive_failures as i32;
+                    let last_payload = data.last_payload;
+                    let name = data.name;
+                    let retry = data.retry_policy.as_ref();
+                    let retry_max_attempts = retry.map(|r| r.max_attempts as i32);
+                    let retry_delay_millis = retry.map(|r| r.delay_millis as i64);
+                    let retry_exponential = retry.map(|r| r.exponential);
+                    let retry_max_delay_millis = retry.map(|r| r.max_delay_millis as i64);
+                    let retry_jitter = retry.map(|r| r.jitter);
+                    let last_error = data.last_error;
+                    let concurrency_policy = data.concurrency_policy;
+                    let leap_day_policy = data.leap_day_policy;
+                    let misfire_policy = data.misfire_policy;
+                    let after_job: Option<Uuid> = data.after_job.as_ref().map(|u| u.into());
+                    let min_interval_seconds = data.min_interval_seconds.map(|s| s as i64);
 
                     let val = store
                         .query(
@@ -144,6 +229,39 @@ impl DataStore<JobStoredData> for PostgresMetadataStore {
                                 &repeated_every,
                                 &extra,
                                 &last_tick,
+                                &last_run_failed,
+                                &skip_if_last_failed,
+                                &skip_if_upstream_failed,
+                                &requires_approval,
+                                &maintenance_window_policy,
+                                &group,
+                                &starting_deadline_seconds,
+                                &missed_count,
+                                &tags,
+                                &warm_start,
+                                &cost,
+                                &priority,
+                                &exclusive_group,
+                                &consecutive_failures,
+                                &last_payload,
+                                &timezone,
+                                &name,
+                                &retry_max_attempts,
+                                &retry_delay_millis,
+                                &retry_exponential,
+                                &retry_max_delay_millis,
+                                &retry_jitter,
+                                &last_error,
+                                &concurrency_policy,
+                                &leap_day_policy,
+                                &misfire_policy,
+                                &after_job,
+                                &monotonic,
+                                &fixed_delay,
+                                &instants,
+                                &exclusions,
+                                &jitter_seconds,
+                                &min_interval_seconds,
                             ],
                         )
                         .await;
@@ -191,7 +309,14 @@ impl From<Row> for JobStoredData {
         /*
         id, last_updated, next_tick, last_tick, job_type, count, \
                         ran, stopped, schedule, repeating, repeated_every, \
-                        extra
+                        extra, last_run_failed, skip_if_last_failed, skip_if_upstream_failed,
+                        requires_approval, maintenance_window_policy, job_group,
+                        starting_deadline_seconds, missed_count, tags, warm_start, cost, priority,
+                        exclusive_group, consecutive_failures, last_payload, timezone, name,
+                        retry_max_attempts, retry_delay_millis, retry_exponential,
+                        retry_max_delay_millis, retry_jitter, last_error, concurrency_policy,
+                        leap_day_policy, misfire_policy, after_job, monotonic, fixed_delay,
+                        instants, exclusions, jitter_seconds, min_interval_seconds
          */
         let id: Uuid = row.get(0);
         let last_updated = row.try_get(1).ok().map(|i: i64| i as u64);
@@ -203,7 +328,11 @@ impl From<Row> for JobStoredData {
         let last_tick = row.try_get(3).ok().map(|i: i64| i as u64);
 
         let job_type: i32 = row.try_get(4).unwrap_or_default();
-        let count = row.try_get(5).unwrap_or_default();
+        let count = row
+            .try_get(5)
+            .ok()
+            .map(|i: i32| i as u32)
+            .unwrap_or_default();
         let ran = row.try_get(6).unwrap_or_default();
         let stopped = row.try_get(7).unwrap_or_default();
         let job = {
@@ -213,7 +342,17 @@ impl From<Row> for JobStoredData {
             let job_type = JobType::from_i32(job_type);
             match job_type {
                 Some(JobType::Cron) => match row.try_get(8) {
-                    Ok(schedule) => Some(CronJobType(CronJob { schedule })),
+                    Ok(schedule) => Some(CronJobType(CronJob {
+                        schedule,
+                        timezone: row.try_get(27).ok().flatten().unwrap_or_default(),
+                        exclusions: row.try_get(42).ok().flatten().unwrap_or_default(),
+                        jitter_seconds: row
+                            .try_get(43)
+                            .ok()
+                            .flatten()
+                            .map(|i: i64| i as u64)
+                            .unwrap_or_default(),
+                    })),
                     _ => None,
                 },
                 Some(_) => {
@@ -223,15 +362,81 @@ impl From<Row> for JobStoredData {
                         .ok()
                         .map(|i: i64| i as u64)
                         .unwrap_or_default();
+                    let monotonic = row.try_get(39).unwrap_or_default();
+                    let fixed_delay = row.try_get(40).unwrap_or_default();
+                    let instants = row
+                        .try_get(41)
+                        .ok()
+                        .flatten()
+                        .map(|i: Vec<i64>| i.into_iter().map(|i| i as u64).collect())
+                        .unwrap_or_default();
                     Some(NonCronJobType(NonCronJob {
                         repeating,
                         repeated_every,
+                        monotonic,
+                        fixed_delay,
+                        instants,
                     }))
                 }
                 None => None,
             }
         };
         let extra = row.try_get(11).unwrap_or_default();
+        let last_run_failed = row.try_get(12).unwrap_or_default();
+        let skip_if_last_failed = row.try_get(13).unwrap_or_default();
+        let skip_if_upstream_failed = row.try_get(14).ok().flatten().map(|u: Uuid| u.into());
+        let requires_approval = row.try_get(15).unwrap_or_default();
+        let maintenance_window_policy = row.try_get(16).unwrap_or_default();
+        let group = row.try_get(17).unwrap_or_default();
+        let starting_deadline_seconds = row.try_get(18).ok().flatten().map(|s: i64| s as u64);
+        let missed_count = row
+            .try_get(19)
+            .ok()
+            .map(|i: i32| i as u32)
+            .unwrap_or_default();
+        let tags = row.try_get(20).unwrap_or_default();
+        let warm_start = row.try_get(21).unwrap_or_default();
+        let cost = row
+            .try_get(22)
+            .ok()
+            .map(|i: i32| i as u32)
+            .unwrap_or_default();
+        let priority = row.try_get(23).unwrap_or_default();
+        let exclusive_group = row.try_get(24).ok().flatten();
+        let consecutive_failures = row
+            .try_get(25)
+            .ok()
+            .map(|i: i32| i as u32)
+            .unwrap_or_default();
+        let last_payload = row.try_get(26).unwrap_or_default();
+        let name = row.try_get(28).ok().flatten().unwrap_or_default();
+        let retry_policy = row
+            .try_get(29)
+            .ok()
+            .flatten()
+            .map(|max_attempts: i32| RetryPolicy {
+                max_attempts: max_attempts as u32,
+                delay_millis: row
+                    .try_get(30)
+                    .ok()
+                    .flatten()
+                    .map(|d: i64| d as u64)
+                    .unwrap_or_default(),
+                exponential: row.try_get(31).ok().flatten().unwrap_or_default(),
+                max_delay_millis: row
+                    .try_get(32)
+                    .ok()
+                    .flatten()
+                    .map(|d: i64| d as u64)
+                    .unwrap_or_default(),
+                jitter: row.try_get(33).ok().flatten().unwrap_or_default(),
+            });
+        let last_error = row.try_get(34).ok().flatten();
+        let concurrency_policy = row.try_get(35).ok().flatten().unwrap_or_default();
+        let leap_day_policy = row.try_get(36).ok().flatten().unwrap_or_default();
+        let misfire_policy = row.try_get(37).ok().flatten().unwrap_or_default();
+        let after_job = row.try_get(38).ok().flatten().map(|u: Uuid| u.into());
+        let min_interval_seconds = row.try_get(44).ok().flatten().map(|s: i64| s as u64);
 
         Self {
             id: Some(id.into()),
@@ -243,11 +448,125 @@ impl From<Row> for JobStoredData {
             extra,
             ran,
             stopped,
+            last_run_failed,
+            skip_if_last_failed,
+            skip_if_upstream_failed,
+            requires_approval,
+            maintenance_window_policy,
+            group,
+            starting_deadline_seconds,
+            missed_count,
+            tags,
+            warm_start,
+            cost,
+            priority,
+            exclusive_group,
+            consecutive_failures,
+            last_payload,
+            name,
+            retry_policy,
+            last_error,
+            concurrency_policy,
+            leap_day_policy,
+            misfire_policy,
+            after_job,
+            min_interval_seconds,
             job,
         }
     }
 }
 
+/// The columns added to the table of the jobs since its first release, with their types, in the
+/// order they were added. `migrate_table` adds those missing from a table made by an older version.
+const ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("last_run_failed", "BOOL"),
+    ("skip_if_last_failed", "BOOL"),
+    ("skip_if_upstream_failed", "UUID"),
+    ("requires_approval", "BOOL"),
+    ("maintenance_window_policy", "INTEGER"),
+    ("job_group", "TEXT"),
+    ("starting_deadline_seconds", "BIGINT"),
+    ("missed_count", "INTEGER"),
+    ("tags", "TEXT[]"),
+    ("warm_start", "BOOL"),
+    ("cost", "INTEGER"),
+    ("priority", "INTEGER"),
+    ("exclusive_group", "TEXT"),
+    ("consecutive_failures", "INTEGER"),
+    ("last_payload", "BYTEA"),
+    ("timezone", "TEXT"),
+    ("name", "TEXT"),
+    ("retry_max_attempts", "INTEGER"),
+    ("retry_delay_millis", "BIGINT"),
+    ("retry_exponential", "BOOL"),
+    ("retry_max_delay_millis", "BIGINT"),
+    ("retry_jitter", "BOOL"),
+    ("last_error", "TEXT"),
+    ("concurrency_policy", "INTEGER"),
+    ("leap_day_policy", "INTEGER"),
+    ("misfire_policy", "INTEGER"),
+    ("after_job", "UUID"),
+    ("monotonic", "BOOL"),
+    ("fixed_delay", "BOOL"),
+    ("instants", "BIGINT[]"),
+    ("exclusions", "TEXT[]"),
+    ("jitter_seconds", "BIGINT"),
+    ("min_interval_seconds", "BIGINT"),
+];
+
+///
+/// Create the table of the jobs, named `table` with a primary key named `primary_key`, unless
+/// it exists already, then add the columns missing from a table made by an older version
+pub(crate) async fn create_table(
+    client: &Client,
+    table: &str,
+    primary_key: &str,
+) -> Result<u64, tokio_postgres::Error> {
+    let added: String = ADDED_COLUMNS
+        .iter()
+        .map(|(column, column_type)| column.to_string() + " " + column_type + ",\n")
+        .collect();
+    let sql = "CREATE TABLE IF NOT EXISTS ".to_string()
+        + table
+        + " (\
+            id UUID,\
+            last_updated BIGINT,\
+            next_tick BIGINT,\
+            last_tick BIGINT,\
+            job_type INTEGER NOT NULL,\
+            count INTEGER,\
+            ran BOOL,\
+            stopped BOOL,\
+            schedule TEXT,\
+            repeating BOOL,\
+            repeated_every BIGINT,\
+            extra BYTEA,\n"
+        + &added
+        + "CONSTRAINT "
+        + primary_key
+        + " PRIMARY KEY (id)
+        )";
+    client.execute(&*sql, &[]).await?;
+    migrate_table(client, table).await
+}
+
+///
+/// Add the columns of `ADDED_COLUMNS` that `table` does not have yet, leaving them empty in the
+/// rows already there, which read back as the defaults of their fields
+pub(crate) async fn migrate_table(
+    client: &Client,
+    table: &str,
+) -> Result<u64, tokio_postgres::Error> {
+    let actions: Vec<String> = ADDED_COLUMNS
+        .iter()
+        .map(|(column, column_type)| {
+            "ADD COLUMN IF NOT EXISTS ".to_string() + column + " " + column_type
+        })
+        .collect();
+    let sql = "ALTER TABLE ".to_string() + table + " " + &actions.join(", ");
+    client.execute(&*sql, &[]).await
+}
+
 impl InitStore for PostgresMetadataStore {
     fn init(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
         let inited = self.inited();
@@ -265,25 +584,7 @@ impl InitStore for PostgresMetadataStore {
                         if init_tables {
                             if let PostgresStore::Inited(client) = &v {
                                 let v = client.read().await;
-                                let sql = "CREATE TABLE IF NOT EXISTS ".to_string()
-                                    + &*table
-                                    + " (\
-                                            id UUID,\
-                                            last_updated BIGINT,\
-                                            next_tick BIGINT,\
-                                            last_tick BIGINT,\
-                                            job_type INTEGER NOT NULL,\
-                                            count INTEGER,\
-                                            ran BOOL,\
-                                            stopped BOOL,\
-                                            schedule TEXT,\
-                                            repeating BOOL,\
-                                            repeated_every BIGINT,\
-                                            extra BYTEA,
-                                            CONSTRAINT pk_metadata PRIMARY KEY (id)
-                                        )";
-                                let create = v.execute(&*sql, &[]).await;
-                                if let Err(e) = create {
+                                if let Err(e) = create_table(&v, &table, "pk_metadata").await {
                                     error!("Error on init Postgres Metadata store {:?}", e);
                                     return Err(JobSchedulerError::CantInit);
                                 }
@@ -325,9 +626,10 @@ impl MetaDataStorage for PostgresMetadataStore {
                 PostgresStore::Created(_) => Err(JobSchedulerError::CantListNextTicks),
                 PostgresStore::Inited(store) => {
                     let store = store.read().await;
-                    let now = Utc::now().timestamp();
+                    // Include the jobs due in the next second, so warm jobs can be prepared
+                    let now = Utc::now().timestamp() + 1;
                     let sql = "SELECT \
-                            id, job_type, next_tick, last_tick \
+                            id, job_type, next_tick, last_tick, job_group \
                         FROM "
                         .to_string()
                         + &*table
@@ -349,12 +651,14 @@ impl MetaDataStorage for PostgresMetadataStore {
                                     .map(|i: i64| i as u64)
                                     .unwrap_or_default();
                                 let last_tick = row.try_get(3).ok().map(|i: i64| i as u64);
+                                let group = row.try_get(4).unwrap_or_default();
 
                                 JobAndNextTick {
                                     id: Some(id),
                                     job_type,
                                     next_tick,
                                     last_tick,
+                                    group,
                                 }
                             })
                             .collect::<Vec<_>>()),
@@ -389,15 +693,20 @@ impl MetaDataStorage for PostgresMetadataStore {
                         + &*table
                         + " \
                         SET \
-                         next_tick=$1, last_tick=$2\
+                         next_tick=$1, last_tick=$2 \
                         WHERE \
                             id = $3";
-                    let resp = store.query(&sql, &[&next_tick, &last_tick, &guid]).await;
-                    if let Err(e) = resp {
-                        error!("Error updating next and last tick {:?}", e);
-                        Err(JobSchedulerError::UpdateJobData)
-                    } else {
-                        Ok(())
+                    let resp = store.execute(&sql, &[&next_tick, &last_tick, &guid]).await;
+                    match resp {
+                        Ok(0) => {
+                            error!("Could not find job {:?} to update", guid);
+                            Err(JobSchedulerError::UpdateJobData)
+                        }
+                        Ok(_) => Ok(()),
+                        Err(e) => {
+                            error!("Error updating next and last tick {:?}", e);
+                            Err(JobSchedulerError::UpdateJobData)
+                        }
                     }
                 }
             }
@@ -423,8 +732,8 @@ impl MetaDataStorage for PostgresMetadataStore {
                         + &*table
                         + " \
                         WHERE \
-                              next_tick > 0\
-                          AND next_tick > $2 \
+                              next_tick > 0 \
+                          AND next_tick > $1 \
                         ORDER BY next_tick ASC \
                         LIMIT 1";
                     let row = store.query(&*sql, &[&now]).await;
@@ -444,4 +753,178 @@ impl MetaDataStorage for PostgresMetadataStore {
             }
         })
     }
+
+    fn list_job_ids(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Uuid>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::CantListGuids),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let sql = "SELECT id FROM ".to_string() + &*table;
+                    match store.query(&*sql, &[]).await {
+                        Ok(rows) => Ok(rows.iter().map(|row| row.get(0)).collect()),
+                        Err(e) => {
+                            error!("Error listing job ids {:?}", e);
+                            Err(JobSchedulerError::CantListGuids)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn get_many(
+        &mut self,
+        ids: &[Uuid],
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobStoredData>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        let ids = ids.to_vec();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::GetJobData),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let sql = format!("SELECT {} FROM {} WHERE id = ANY($1)", COLUMNS, table);
+                    match store.query(&*sql, &[&ids]).await {
+                        Ok(rows) => Ok(rows.into_iter().map(JobStoredData::from).collect()),
+                        Err(e) => {
+                            error!("Error getting values {:?}", e);
+                            Err(JobSchedulerError::GetJobData)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn list_followers(
+        &mut self,
+        job_id: Uuid,
+    ) -> Option<Pin<Box<dyn Future<Output = Result<Vec<JobStoredData>, JobSchedulerError>> + Send>>>
+    {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Some(Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::GetJobData),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let sql = format!(
+                        "SELECT {} FROM {} WHERE job_type = $1 AND after_job = $2",
+                        COLUMNS, table
+                    );
+                    match store
+                        .query(&*sql, &[&(JobType::AfterJob as i32), &job_id])
+                        .await
+                    {
+                        Ok(rows) => Ok(rows.into_iter().map(JobStoredData::from).collect()),
+                        Err(e) => {
+                            error!("Error getting the jobs run after a job {:?}", e);
+                            Err(JobSchedulerError::GetJobData)
+                        }
+                    }
+                }
+            }
+        }))
+    }
+
+    fn query_jobs(
+        &mut self,
+        query: &JobQuery,
+    ) -> Option<Pin<Box<dyn Future<Output = Result<Page<JobStoredData>, JobSchedulerError>> + Send>>>
+    {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        let query = query.clone();
+        Some(Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::CantListGuids),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let mut params: Vec<Box<dyn ToSql + Sync + Send>> = vec![];
+                    let mut conditions = vec!["TRUE".to_string()];
+                    let mut param = |value: Box<dyn ToSql + Sync + Send>| {
+                        params.push(value);
+                        format!("${}", params.len())
+                    };
+                    if !query.tags.is_empty() {
+                        conditions
+                            .push("tags @> ".to_string() + &param(Box::new(query.tags.clone())));
+                    }
+                    if let Some(group) = &query.group {
+                        conditions
+                            .push("job_group = ".to_string() + &param(Box::new(group.clone())));
+                    }
+                    match query.state {
+                        None => {}
+                        Some(JobListState::Active) => {
+                            conditions.push("NOT COALESCE(stopped, FALSE)".to_string())
+                        }
+                        Some(JobListState::Stopped) => {
+                            conditions.push("COALESCE(stopped, FALSE)".to_string())
+                        }
+                        Some(JobListState::Failed) => {
+                            conditions.push("COALESCE(last_run_failed, FALSE)".to_string())
+                        }
+                    }
+                    if let Some(from) = query.next_tick_from {
+                        conditions
+                            .push("next_tick >= ".to_string() + &param(Box::new(from.timestamp())));
+                    }
+                    if let Some(to) = query.next_tick_to {
+                        conditions
+                            .push("next_tick < ".to_string() + &param(Box::new(to.timestamp())));
+                    }
+                    let key = match query.sort {
+                        JobSort::Id => "0::BIGINT",
+                        JobSort::NextTick => "COALESCE(next_tick, 0)",
+                        JobSort::LastTick => "COALESCE(last_tick, 0)",
+                    };
+                    let (direction, comparison) = if query.descending {
+                        ("DESC", "<")
+                    } else {
+                        ("ASC", ">")
+                    };
+                    if let Some((after, id)) = query.cursor_key() {
+                        let after = param(Box::new(after as i64));
+                        let id = param(Box::new(id));
+                        conditions
+                            .push(format!("({}, id) {} ({}, {})", key, comparison, after, id));
+                    }
+                    let sql = format!(
+                        "SELECT {} FROM {} WHERE {} ORDER BY {} {}, id {} LIMIT {}",
+                        COLUMNS,
+                        table,
+                        conditions.join(" AND "),
+                        key,
+                        direction,
+                        direction,
+                        query.limit + 1
+                    );
+                    let params = params
+                        .iter()
+                        .map(|p| p.as_ref() as &(dyn ToSql + Sync))
+                        .collect::<Vec<_>>();
+                    match store.query(&*sql, &params).await {
+                        Ok(rows) => {
+                            Ok(query.page(rows.into_iter().map(JobStoredData::from).collect()))
+                        }
+                        Err(e) => {
+                            error!("Error querying jobs {:?}", e);
+                            Err(JobSchedulerError::CantListGuids)
+                        }
+                    }
+                }
+            }
+        }))
+    }
 }
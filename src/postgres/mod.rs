@@ -1,5 +1,11 @@
+mod event_log;
+mod leader_election;
 mod metadata_store;
 mod notification_store;
+mod outbox;
+mod partitions;
+mod run_history_store;
+mod run_lock;
 
 use crate::JobSchedulerError;
 use std::future::Future;
@@ -7,10 +13,15 @@ use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio_postgres::{Client, NoTls};
-use tracing::error;
 
+pub use event_log::PostgresEventLog;
+pub use leader_election::PostgresLeaderElection;
 pub use metadata_store::PostgresMetadataStore;
 pub use notification_store::PostgresNotificationStore;
+pub use outbox::PostgresOutbox;
+pub use partitions::PostgresPartitions;
+pub use run_history_store::PostgresRunHistoryStore;
+pub use run_lock::PostgresRunLock;
 
 #[derive(Clone)]
 pub enum PostgresStore {
@@ -6,7 +6,6 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::error;
 use uuid::Uuid;
 
 const MAIN_TABLE: &str = "notification";
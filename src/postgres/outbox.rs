@@ -0,0 +1,193 @@
+use crate::job::job_data_prost::JobState;
+use crate::postgres::PostgresStore;
+use crate::store::{InitStore, Outbox, OutboxEntry};
+use crate::JobSchedulerError;
+use chrono::{TimeZone, Utc};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+const TABLE: &str = "job_outbox";
+
+///
+/// An outbox in a Postgres table, usually in the database of the `PostgresMetadataStore`
+#[derive(Clone)]
+pub struct PostgresOutbox {
+    pub store: Arc<RwLock<PostgresStore>>,
+    pub init_tables: bool,
+    pub table: String,
+}
+
+impl Default for PostgresOutbox {
+    fn default() -> Self {
+        let init_tables = std::env::var("POSTGRES_INIT_OUTBOX")
+            .map(|s| s.to_lowercase() == "true")
+            .unwrap_or_default();
+        let table = std::env::var("POSTGRES_OUTBOX_TABLE").unwrap_or_else(|_| TABLE.to_lowercase());
+        let store = Arc::new(RwLock::new(PostgresStore::default()));
+        Self {
+            init_tables,
+            table,
+            store,
+        }
+    }
+}
+
+impl PostgresOutbox {
+    fn entry(row: Row) -> Option<OutboxEntry> {
+        let state: i32 = row.try_get(2).ok()?;
+        let created_at: i64 = row.try_get(3).ok()?;
+        let attempts: i32 = row.try_get(4).unwrap_or_default();
+        Some(OutboxEntry {
+            id: row.try_get(0).ok()?,
+            job_id: row.try_get(1).ok()?,
+            state: JobState::from_i32(state)?,
+            created_at: Utc.timestamp_millis_opt(created_at).single()?,
+            attempts: attempts as u32,
+        })
+    }
+
+    fn execute(
+        &self,
+        sql: String,
+        id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::UpdateJobData),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    if let Err(e) = store.execute(&*sql, &[&id]).await {
+                        error!("Error updating the outbox {:?}", e);
+                        return Err(JobSchedulerError::UpdateJobData);
+                    }
+                    Ok(())
+                }
+            }
+        })
+    }
+}
+
+impl Outbox for PostgresOutbox {
+    fn enqueue(
+        &mut self,
+        entry: OutboxEntry,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::SaveJob),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let sql = "INSERT INTO ".to_string()
+                        + &*table
+                        + " (id, job_id, state, created_at, attempts) VALUES ($1, $2, $3, $4, $5)";
+                    let state = entry.state as i32;
+                    let created_at = entry.created_at.timestamp_millis();
+                    let attempts = entry.attempts as i32;
+                    let result = store
+                        .execute(
+                            &*sql,
+                            &[&entry.id, &entry.job_id, &state, &created_at, &attempts],
+                        )
+                        .await;
+                    if let Err(e) = result {
+                        error!("Error writing to the outbox {:?}", e);
+                        return Err(JobSchedulerError::SaveJob);
+                    }
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    fn pending(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<OutboxEntry>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::ErrorLoadingJob),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let sql = "SELECT id, job_id, state, created_at, attempts FROM ".to_string()
+                        + &*table
+                        + " ORDER BY created_at";
+                    match store.query(&*sql, &[]).await {
+                        Ok(rows) => Ok(rows.into_iter().flat_map(PostgresOutbox::entry).collect()),
+                        Err(e) => {
+                            error!("Error reading the outbox {:?}", e);
+                            Err(JobSchedulerError::ErrorLoadingJob)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn failed_attempt(
+        &mut self,
+        id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let sql =
+            "UPDATE ".to_string() + &*self.table + " SET attempts = attempts + 1 WHERE id = $1";
+        self.execute(sql, id)
+    }
+
+    fn remove(
+        &mut self,
+        id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let sql = "DELETE FROM ".to_string() + &*self.table + " WHERE id = $1";
+        self.execute(sql, id)
+    }
+}
+
+impl InitStore for PostgresOutbox {
+    fn init(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let init_tables = self.init_tables;
+        let table = self.table.clone();
+        Box::pin(async move {
+            let mut w = store.write().await;
+            let val = w.clone().init().await?;
+            if init_tables {
+                if let PostgresStore::Inited(client) = &val {
+                    let client = client.read().await;
+                    let sql = "CREATE TABLE IF NOT EXISTS ".to_string()
+                        + &*table
+                        + " (\
+                            id UUID PRIMARY KEY,\
+                            job_id UUID,\
+                            state INTEGER,\
+                            created_at BIGINT,\
+                            attempts INTEGER\
+                        )";
+                    if let Err(e) = client.execute(&*sql, &[]).await {
+                        error!("Error on init Postgres outbox {:?}", e);
+                        return Err(JobSchedulerError::CantInit);
+                    }
+                }
+            }
+            *w = val;
+            Ok(())
+        })
+    }
+
+    fn inited(&mut self) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            Ok(store.inited())
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use crate::postgres::metadata_store::create_table;
+use crate::postgres::{PostgresMetadataStore, PostgresStore};
+use crate::store::{InitStore, MetaDataStorage, PartitionProvider};
+use crate::JobSchedulerError;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, JobSchedulerError>> + Send>>;
+
+///
+/// Keeps the partitions of a `PartitionedStore` in Postgres, as tables named after `table`,
+/// like `job_p1700000000`, all sharing one connection
+#[derive(Clone)]
+pub struct PostgresPartitions {
+    pub store: Arc<RwLock<PostgresStore>>,
+    pub table: String,
+}
+
+impl Default for PostgresPartitions {
+    fn default() -> Self {
+        let PostgresMetadataStore { store, table, .. } = PostgresMetadataStore::default();
+        Self { store, table }
+    }
+}
+
+impl PostgresPartitions {
+    fn table_of(&self, name: &str) -> String {
+        self.table.clone() + "_" + name
+    }
+
+    async fn client(
+        store: &Arc<RwLock<PostgresStore>>,
+    ) -> Result<Arc<RwLock<tokio_postgres::Client>>, JobSchedulerError> {
+        let mut w = store.write().await;
+        if let PostgresStore::Created(_) = &*w {
+            *w = w.clone().init().await?;
+        }
+        match &*w {
+            PostgresStore::Inited(client) => Ok(client.clone()),
+            PostgresStore::Created(_) => Err(JobSchedulerError::CantInit),
+        }
+    }
+}
+
+impl PartitionProvider for PostgresPartitions {
+    fn open(&self, name: &str) -> StoreFuture<Box<dyn MetaDataStorage + Send + Sync>> {
+        let store = self.store.clone();
+        let table = self.table_of(name);
+        Box::pin(async move {
+            let client = PostgresPartitions::client(&store).await?;
+            {
+                let client = client.read().await;
+                if let Err(e) = create_table(&client, &table, &("pk_".to_string() + &table)).await {
+                    error!("Error creating partition {} {:?}", table, e);
+                    return Err(JobSchedulerError::CantInit);
+                }
+            }
+            let mut partition = PostgresMetadataStore {
+                store,
+                init_tables: false,
+                table,
+            };
+            partition.init().await?;
+            Ok(Box::new(partition) as Box<dyn MetaDataStorage + Send + Sync>)
+        })
+    }
+
+    fn list(&self) -> StoreFuture<Vec<String>> {
+        let store = self.store.clone();
+        let prefix = self.table_of("");
+        Box::pin(async move {
+            let client = PostgresPartitions::client(&store).await?;
+            let client = client.read().await;
+            let rows = client
+                .query(
+                    "SELECT table_name FROM information_schema.tables \
+                     WHERE table_schema = current_schema()",
+                    &[],
+                )
+                .await
+                .map_err(|e| {
+                    error!("Error listing partitions {:?}", e);
+                    JobSchedulerError::CantListNextTicks
+                })?;
+            Ok(rows
+                .iter()
+                .filter_map(|row| row.try_get::<_, String>(0).ok())
+                .filter_map(|table| table.strip_prefix(&*prefix).map(|name| name.to_string()))
+                .collect())
+        })
+    }
+
+    fn drop_partition(&self, name: &str) -> StoreFuture<()> {
+        let store = self.store.clone();
+        let table = self.table_of(name);
+        Box::pin(async move {
+            let client = PostgresPartitions::client(&store).await?;
+            let client = client.read().await;
+            let sql = "DROP TABLE IF EXISTS ".to_string() + &*table;
+            if let Err(e) = client.execute(&*sql, &[]).await {
+                error!("Error dropping partition {} {:?}", table, e);
+                return Err(JobSchedulerError::CantRemove);
+            }
+            Ok(())
+        })
+    }
+}
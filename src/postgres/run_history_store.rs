@@ -0,0 +1,233 @@
+use crate::job::job_data_prost::JobRun;
+use crate::postgres::PostgresStore;
+use crate::store::{InitStore, RunHistoryStore};
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+const TABLE: &str = "job_run_history";
+
+///
+/// A run history in a Postgres table, usually in the database of the `PostgresMetadataStore`.
+/// Every run is kept, removing the old ones is left to the database.
+#[derive(Clone)]
+pub struct PostgresRunHistoryStore {
+    pub store: Arc<RwLock<PostgresStore>>,
+    pub init_tables: bool,
+    pub table: String,
+}
+
+impl Default for PostgresRunHistoryStore {
+    fn default() -> Self {
+        let init_tables = std::env::var("POSTGRES_INIT_RUN_HISTORY")
+            .map(|s| s.to_lowercase() == "true")
+            .unwrap_or_default();
+        let table =
+            std::env::var("POSTGRES_RUN_HISTORY_TABLE").unwrap_or_else(|_| TABLE.to_lowercase());
+        let store = Arc::new(RwLock::new(PostgresStore::default()));
+        Self {
+            init_tables,
+            table,
+            store,
+        }
+    }
+}
+
+impl PostgresRunHistoryStore {
+    pub fn new(store: PostgresStore) -> Self {
+        Self {
+            store: Arc::new(RwLock::new(store)),
+            ..Default::default()
+        }
+    }
+
+    fn run(row: Row) -> Option<JobRun> {
+        let id: Uuid = row.try_get(0).ok()?;
+        let job_id: Uuid = row.try_get(1).ok()?;
+        let scheduled_at: i64 = row.try_get(2).ok()?;
+        let started_at: i64 = row.try_get(3).ok()?;
+        let ended_at: i64 = row.try_get(4).ok()?;
+        let outcome: i32 = row.try_get(5).ok()?;
+        Some(JobRun {
+            id: Some(id.into()),
+            job_id: Some(job_id.into()),
+            scheduled_at: scheduled_at as u64,
+            started_at: started_at as u64,
+            ended_at: ended_at as u64,
+            outcome,
+            error: row.try_get(6).ok()?,
+        })
+    }
+}
+
+impl RunHistoryStore for PostgresRunHistoryStore {
+    fn record(
+        &mut self,
+        run: JobRun,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::SaveJob),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let id: Uuid = run.id.as_ref().map(|id| id.into()).unwrap_or_default();
+                    let job_id = run.job_id().ok_or(JobSchedulerError::SaveJob)?;
+                    let scheduled_at = run.scheduled_at as i64;
+                    let started_at = run.started_at as i64;
+                    let ended_at = run.ended_at as i64;
+                    let sql = "INSERT INTO ".to_string()
+                        + &*table
+                        + " (id, job_id, scheduled_at, started_at, ended_at, outcome, error) \
+                        VALUES ($1, $2, $3, $4, $5, $6, $7)";
+                    let result = store
+                        .execute(
+                            &*sql,
+                            &[
+                                &id,
+                                &job_id,
+                                &scheduled_at,
+                                &started_at,
+                                &ended_at,
+                                &run.outcome,
+                                &run.error,
+                            ],
+                        )
+                        .await;
+                    if let Err(e) = result {
+                        error!("Error writing to the run history {:?}", e);
+                        return Err(JobSchedulerError::SaveJob);
+                    }
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    fn history(
+        &mut self,
+        job_id: Uuid,
+        limit: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobRun>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::ErrorLoadingJob),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let sql = "SELECT id, job_id, scheduled_at, started_at, ended_at, outcome, \
+                        error FROM "
+                        .to_string()
+                        + &*table
+                        + " WHERE job_id = $1 ORDER BY ended_at DESC LIMIT $2";
+                    let limit = limit.min(i64::MAX as usize) as i64;
+                    match store.query(&*sql, &[&job_id, &limit]).await {
+                        Ok(rows) => Ok(rows
+                            .into_iter()
+                            .flat_map(PostgresRunHistoryStore::run)
+                            .collect()),
+                        Err(e) => {
+                            error!("Error reading the run history {:?}", e);
+                            Err(JobSchedulerError::ErrorLoadingJob)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn runs_between(
+        &mut self,
+        range: Range<DateTime<Utc>>,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobRun>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                PostgresStore::Created(_) => Err(JobSchedulerError::ErrorLoadingJob),
+                PostgresStore::Inited(store) => {
+                    let store = store.read().await;
+                    let sql = "SELECT id, job_id, scheduled_at, started_at, ended_at, outcome, \
+                        error FROM "
+                        .to_string()
+                        + &*table
+                        + " WHERE ended_at >= $1 AND ended_at < $2 ORDER BY ended_at";
+                    let start = range.start.timestamp_millis();
+                    let end = range.end.timestamp_millis();
+                    match store.query(&*sql, &[&start, &end]).await {
+                        Ok(rows) => Ok(rows
+                            .into_iter()
+                            .flat_map(PostgresRunHistoryStore::run)
+                            .collect()),
+                        Err(e) => {
+                            error!("Error reading the run history {:?}", e);
+                            Err(JobSchedulerError::ErrorLoadingJob)
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl InitStore for PostgresRunHistoryStore {
+    fn init(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let init_tables = self.init_tables;
+        let table = self.table.clone();
+        Box::pin(async move {
+            let mut w = store.write().await;
+            let val = w.clone().init().await?;
+            if init_tables {
+                if let PostgresStore::Inited(client) = &val {
+                    let client = client.read().await;
+                    let sql = "CREATE TABLE IF NOT EXISTS ".to_string()
+                        + &*table
+                        + " (\
+                            id UUID PRIMARY KEY,\
+                            job_id UUID,\
+                            scheduled_at BIGINT,\
+                            started_at BIGINT,\
+                            ended_at BIGINT,\
+                            outcome INTEGER,\
+                            error TEXT\
+                        )";
+                    if let Err(e) = client.execute(&*sql, &[]).await {
+                        error!("Error on init Postgres run history {:?}", e);
+                        return Err(JobSchedulerError::CantInit);
+                    }
+                    let sql = "CREATE INDEX IF NOT EXISTS ".to_string()
+                        + &*table
+                        + "_job_id_ended_at ON "
+                        + &*table
+                        + " (job_id, ended_at DESC)";
+                    if let Err(e) = client.execute(&*sql, &[]).await {
+                        error!("Error on init Postgres run history {:?}", e);
+                        return Err(JobSchedulerError::CantInit);
+                    }
+                }
+            }
+            *w = val;
+            Ok(())
+        })
+    }
+
+    fn inited(&mut self) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            Ok(store.inited())
+        })
+    }
+}
@@ -0,0 +1,153 @@
+use crate::postgres::PostgresStore;
+use crate::{JobSchedulerError, RunLock};
+use chrono::Utc;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+const TABLE: &str = "run_lock";
+
+///
+/// Run locks kept as rows of a Postgres table, usually in the database of the
+/// `PostgresMetadataStore`. A run is locked by the scheduler inserting its row first, and the
+/// rows past their ttl are removed by the next lock taken.
+#[derive(Clone)]
+pub struct PostgresRunLock {
+    pub store: Arc<RwLock<PostgresStore>>,
+    pub init_tables: bool,
+    pub table: String,
+}
+
+impl Default for PostgresRunLock {
+    fn default() -> Self {
+        let init_tables = std::env::var("POSTGRES_INIT_RUN_LOCKS")
+            .map(|s| s.to_lowercase() == "true")
+            .unwrap_or_default();
+        let table =
+            std::env::var("POSTGRES_RUN_LOCK_TABLE").unwrap_or_else(|_| TABLE.to_lowercase());
+        Self {
+            store: Arc::new(RwLock::new(PostgresStore::default())),
+            init_tables,
+            table,
+        }
+    }
+}
+
+impl PostgresRunLock {
+    pub fn new(store: PostgresStore) -> Self {
+        Self {
+            store: Arc::new(RwLock::new(store)),
+            ..Default::default()
+        }
+    }
+}
+
+impl PostgresRunLock {
+    ///
+    /// Connect to the database, creating the table of the locks if asked to
+    async fn inited(
+        store: &Arc<RwLock<PostgresStore>>,
+        init_tables: bool,
+        table: &str,
+    ) -> Result<(), JobSchedulerError> {
+        let mut w = store.write().await;
+        if !w.inited() {
+            let val = w.clone().init().await?;
+            if init_tables {
+                if let PostgresStore::Inited(client) = &val {
+                    let client = client.read().await;
+                    let sql = "CREATE TABLE IF NOT EXISTS ".to_string()
+                        + table
+                        + " (\
+                            job_id UUID,\
+                            run_id UUID,\
+                            expires_at BIGINT,\
+                            PRIMARY KEY (job_id, run_id)\
+                        )";
+                    if let Err(e) = client.execute(&*sql, &[]).await {
+                        error!("Error on init Postgres run locks {:?}", e);
+                        return Err(JobSchedulerError::CantInit);
+                    }
+                }
+            }
+            *w = val;
+        }
+        Ok(())
+    }
+}
+
+impl RunLock for PostgresRunLock {
+    fn try_acquire_run_lock(
+        &mut self,
+        job_id: Uuid,
+        run_id: Uuid,
+        ttl: Duration,
+    ) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let init_tables = self.init_tables;
+        let table = self.table.clone();
+        Box::pin(async move {
+            PostgresRunLock::inited(&store, init_tables, &table).await?;
+            let w = store.read().await;
+            match &*w {
+                PostgresStore::Created(_) => Err(JobSchedulerError::RunLock),
+                PostgresStore::Inited(client) => {
+                    let client = client.read().await;
+                    let now = Utc::now().timestamp_millis();
+                    let expires_at = now + ttl.as_millis() as i64;
+                    let sql = "DELETE FROM ".to_string() + &*table + " WHERE expires_at < $1";
+                    if let Err(e) = client.execute(&*sql, &[&now]).await {
+                        error!("Error removing expired run locks {:?}", e);
+                        return Err(JobSchedulerError::RunLock);
+                    }
+                    let sql = "INSERT INTO ".to_string()
+                        + &*table
+                        + " (job_id, run_id, expires_at) VALUES ($1, $2, $3) \
+                        ON CONFLICT DO NOTHING";
+                    match client
+                        .execute(&*sql, &[&job_id, &run_id, &expires_at])
+                        .await
+                    {
+                        Ok(inserted) => Ok(inserted == 1),
+                        Err(e) => {
+                            error!("Error acquiring the run lock {:?}", e);
+                            Err(JobSchedulerError::RunLock)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn recover(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Uuid>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let init_tables = self.init_tables;
+        let table = self.table.clone();
+        Box::pin(async move {
+            PostgresRunLock::inited(&store, init_tables, &table).await?;
+            let w = store.read().await;
+            match &*w {
+                PostgresStore::Created(_) => Err(JobSchedulerError::RunLock),
+                PostgresStore::Inited(client) => {
+                    let client = client.read().await;
+                    let now = Utc::now().timestamp_millis();
+                    let sql = "DELETE FROM ".to_string()
+                        + &*table
+                        + " WHERE expires_at < $1 RETURNING job_id";
+                    match client.query(&*sql, &[&now]).await {
+                        Ok(rows) => Ok(rows.iter().map(|row| row.get(0)).collect()),
+                        Err(e) => {
+                            error!("Error removing expired run locks {:?}", e);
+                            Err(JobSchedulerError::RunLock)
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
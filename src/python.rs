@@ -0,0 +1,176 @@
+//! Python bindings. Build the extension module with
+//! `cargo rustc --lib --release --features python --crate-type cdylib`.
+
+// The code generated by `#[pymethods]` converts `PyErr` into itself
+#![allow(clippy::useless_conversion)]
+
+use crate::blocking::BlockingScheduler;
+use crate::job::JobLocked;
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use std::str::FromStr;
+use uuid::Uuid;
+
+fn to_py_err(e: JobSchedulerError) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+fn parse_job_id(job_id: &str) -> PyResult<Uuid> {
+    Uuid::parse_str(job_id).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+///
+/// A cron job calling a Python callable with the job id on every run
+#[pyclass(name = "Job")]
+pub struct PyJob {
+    schedule: String,
+    callback: PyObject,
+}
+
+#[pymethods]
+impl PyJob {
+    #[new]
+    fn new(schedule: String, callback: PyObject) -> PyResult<Self> {
+        Schedule::from_str(&schedule).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(Self { schedule, callback })
+    }
+}
+
+///
+/// The scheduler, running its jobs on a thread of its own
+#[pyclass(name = "JobScheduler")]
+pub struct PyJobScheduler {
+    scheduler: Option<BlockingScheduler>,
+}
+
+impl PyJobScheduler {
+    fn scheduler(&self) -> PyResult<&BlockingScheduler> {
+        self.scheduler
+            .as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("The scheduler was shut down"))
+    }
+}
+
+#[pymethods]
+impl PyJobScheduler {
+    #[new]
+    fn new(py: Python<'_>) -> PyResult<Self> {
+        let scheduler = py
+            .allow_threads(BlockingScheduler::new)
+            .map_err(to_py_err)?;
+        Ok(Self {
+            scheduler: Some(scheduler),
+        })
+    }
+
+    /// Add a job, returning its id
+    fn add(&self, py: Python<'_>, job: &PyJob) -> PyResult<String> {
+        let callback = job.callback.clone_ref(py);
+        let job = JobLocked::new(job.schedule.as_str(), move |job_id, _| {
+            Python::with_gil(|py| {
+                if let Err(e) = callback.call1(py, (job_id.to_string(),)) {
+                    e.print(py);
+                }
+            });
+        })
+        .map_err(to_py_err)?;
+        let scheduler = self.scheduler()?;
+        py.allow_threads(|| scheduler.add(job))
+            .map(|job_id| job_id.to_string())
+            .map_err(to_py_err)
+    }
+
+    /// Remove a job by its id
+    fn remove(&self, py: Python<'_>, job_id: &str) -> PyResult<()> {
+        let job_id = parse_job_id(job_id)?;
+        let scheduler = self.scheduler()?;
+        py.allow_threads(|| scheduler.remove(&job_id))
+            .map_err(to_py_err)
+    }
+
+    /// Run a job right away
+    fn trigger(&self, py: Python<'_>, job_id: &str) -> PyResult<()> {
+        let job_id = parse_job_id(job_id)?;
+        let scheduler = self.scheduler()?;
+        py.allow_threads(|| scheduler.trigger(&job_id))
+            .map_err(to_py_err)
+    }
+
+    /// Start running the jobs
+    fn start(&self, py: Python<'_>) -> PyResult<()> {
+        let scheduler = self.scheduler()?;
+        py.allow_threads(|| scheduler.start())
+            .map(|_| ())
+            .map_err(to_py_err)
+    }
+
+    /// Shut the scheduler down
+    fn shutdown(&mut self, py: Python<'_>) -> PyResult<()> {
+        match self.scheduler.take() {
+            Some(scheduler) => py.allow_threads(|| scheduler.shutdown()).map_err(to_py_err),
+            None => Ok(()),
+        }
+    }
+}
+
+///
+/// A cron schedule, iterating over its upcoming times
+#[pyclass(name = "Schedule")]
+pub struct PySchedule {
+    schedule: Schedule,
+}
+
+#[pymethods]
+impl PySchedule {
+    #[new]
+    fn new(schedule: &str) -> PyResult<Self> {
+        let schedule =
+            Schedule::from_str(schedule).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(Self { schedule })
+    }
+
+    /// The next `count` times of the schedule
+    fn upcoming(&self, count: usize) -> Vec<DateTime<Utc>> {
+        self.schedule.upcoming(Utc).take(count).collect()
+    }
+
+    fn __iter__(&self) -> PyScheduleIter {
+        PyScheduleIter {
+            schedule: self.schedule.clone(),
+            after: Utc::now(),
+        }
+    }
+}
+
+///
+/// Iterator over the upcoming times of a `Schedule`
+#[pyclass(name = "ScheduleIter")]
+pub struct PyScheduleIter {
+    schedule: Schedule,
+    after: DateTime<Utc>,
+}
+
+#[pymethods]
+impl PyScheduleIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> Option<DateTime<Utc>> {
+        let next = self.schedule.after(&self.after).next()?;
+        self.after = next;
+        Some(next)
+    }
+}
+
+#[pymodule]
+fn tokio_cron_scheduler(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyJob>()?;
+    m.add_class::<PyJobScheduler>()?;
+    m.add_class::<PySchedule>()?;
+    m.add_class::<PyScheduleIter>()?;
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use crate::context::Context;
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::MisfirePolicy;
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::MisfirePolicy;
+use crate::scheduler::Scheduler;
+use crate::{JobSchedulerError, JobStoredData, RunLock};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// The most missed runs of a job counted, the ones beyond are not told apart
+const MAX_COUNTED_MISSED_RUNS: u32 = 10_000;
+
+///
+/// What the scheduler found in its store when it started, so operators can verify how it
+/// recovered from its downtime instead of trusting silence
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReconciliationReport {
+    /// The jobs of the group of the scheduler found in the store
+    pub jobs_loaded: usize,
+    /// The stored jobs without code to run in this process, e.g. added by an earlier deployment,
+    /// whose runs won't do anything until they are added again
+    pub missing_code: Vec<Uuid>,
+    /// The jobs that missed runs while the scheduler was down
+    pub missed: Vec<MissedRuns>,
+    /// The jobs whose run locks, left by runs that never ended, were released
+    pub locks_recovered: Vec<Uuid>,
+}
+
+///
+/// The runs of a job that came due while the scheduler was down, which its misfire policy
+/// handles on the first tick
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MissedRuns {
+    pub job_id: Uuid,
+    /// When the first missed run was due
+    pub first_due: DateTime<Utc>,
+    /// How many runs were missed, counting up to 10 000
+    pub runs: u32,
+    /// Whether the missed runs run once, are skipped or all run one by one
+    pub policy: MisfirePolicy,
+}
+
+impl MissedRuns {
+    fn of(job: &JobStoredData, now: &DateTime<Utc>, horizon: chrono::Duration) -> Option<Self> {
+        if !Scheduler::misfired(job, now) {
+            return None;
+        }
+        let first_due = job.next_tick_utc()?;
+        let mut runs = 1;
+        let mut tick = first_due;
+        while runs < MAX_COUNTED_MISSED_RUNS {
+            match Scheduler::tick_after(job, &tick, horizon) {
+                Some(next) if next <= *now => {
+                    runs += 1;
+                    tick = next;
+                }
+                _ => break,
+            }
+        }
+        Some(Self {
+            job_id: job.id.as_ref()?.into(),
+            first_due,
+            runs,
+            policy: MisfirePolicy::from_i32(job.misfire_policy).unwrap_or(MisfirePolicy::RunOnce),
+        })
+    }
+}
+
+///
+/// Go through the jobs in the store of the scheduler before it starts ticking, releasing the
+/// stale run locks
+pub(crate) async fn reconcile(
+    context: &Context,
+    run_lock: Option<Arc<Mutex<Box<dyn RunLock + Send + Sync>>>>,
+) -> Result<ReconciliationReport, JobSchedulerError> {
+    let now = Utc::now();
+    let jobs = {
+        let mut storage = context.metadata_storage.write().await;
+        let job_ids = storage.list_job_ids().await?;
+        storage.get_many(&job_ids).await?
+    };
+    let jobs: Vec<_> = jobs
+        .into_iter()
+        .filter(|job| job.group == context.group)
+        .collect();
+    let mut report = ReconciliationReport {
+        jobs_loaded: jobs.len(),
+        ..Default::default()
+    };
+    {
+        let mut job_code = context.job_code.write().await;
+        for job in jobs.iter() {
+            let job_id: Uuid = match job.id.as_ref() {
+                Some(id) => id.into(),
+                None => continue,
+            };
+            if matches!(job_code.get(job_id).await, Ok(None)) {
+                report.missing_code.push(job_id);
+            }
+        }
+    }
+    report.missed = jobs
+        .iter()
+        .filter_map(|job| MissedRuns::of(job, &now, context.search_horizon))
+        .collect();
+    if let Some(run_lock) = run_lock {
+        let mut w = run_lock.lock().await;
+        report.locks_recovered = w.recover().await?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MisfirePolicy;
+    use crate::{
+        run_id, Job, JobScheduler, MemoryRunLock, MetaDataStorage, RunLock, SimpleMetadataStore,
+    };
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    /// A job left in the store without its code and missing its runs, and a run lock left by a
+    /// run that never ended, reported when the scheduler starts
+    #[tokio::test]
+    async fn reconciliation_report() {
+        // A job left in the store by an earlier deployment, due every minute and down for ten
+        let mut store: Box<dyn MetaDataStorage + Send + Sync> =
+            Box::new(SimpleMetadataStore::default());
+        store.init().await.expect("init");
+        let mut job = Job::new("0 * * * * *", |_, _| {}).expect("job");
+        job.set_misfire_policy(MisfirePolicy::Skip)
+            .expect("misfire policy");
+        let mut data = job.job_data().expect("data");
+        data.next_tick = (Utc::now() - Duration::minutes(10)).timestamp() as u64;
+        let orphan = job.guid();
+        store.add_or_update(data).await.expect("add");
+        // A run lock of a run that never ended
+        let mut run_lock = MemoryRunLock::default();
+        let stale = Uuid::new_v4();
+        run_lock
+            .try_acquire_run_lock(stale, run_id(stale, Utc::now()), std::time::Duration::ZERO)
+            .await
+            .expect("lock");
+
+        let mut sched = JobScheduler::new_builder()
+            .metadata_storage(store)
+            .run_lock(Box::new(run_lock), std::time::Duration::from_secs(60))
+            .build()
+            .await
+            .expect("scheduler");
+        let job = Job::new("0 0 0 1 1 *", |_, _| {}).expect("job");
+        sched.add(job).await.expect("add");
+        let mut reconciliations = sched.subscribe_reconciliations();
+        let report = sched.start().await.expect("start");
+
+        assert_eq!(report.jobs_loaded, 2);
+        assert_eq!(report.missing_code, vec![orphan]);
+        assert_eq!(report.missed.len(), 1);
+        let missed = &report.missed[0];
+        assert_eq!(missed.job_id, orphan);
+        assert_eq!(missed.policy, MisfirePolicy::Skip);
+        assert!((9..=11).contains(&missed.runs), "{}", missed.runs);
+        assert_eq!(report.locks_recovered, vec![stale]);
+        assert_eq!(reconciliations.recv().await.expect("report"), report);
+        sched.shutdown().await.expect("shutdown");
+    }
+}
@@ -0,0 +1,415 @@
+use crate::redis::{prefix, RedisStore};
+use crate::store::{Codec, DataStore, InitStore, MetaDataStorage, ProstCodec};
+use crate::{JobAndNextTick, JobSchedulerError, JobStoredData};
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+///
+/// A Redis backed metadata store. The jobs are kept encoded by the codec in a hash, and the jobs
+/// with a next tick in a sorted set scored by it, so the due ones are found without going
+/// through all of them.
+#[derive(Clone)]
+pub struct RedisMetadataStore {
+    pub store: Arc<RwLock<RedisStore>>,
+    /// The prefix of the keys, `tcs` unless `REDIS_PREFIX` is set
+    pub prefix: String,
+    /// How the jobs are encoded in the hash
+    pub codec: Arc<dyn Codec>,
+}
+
+impl Default for RedisMetadataStore {
+    fn default() -> Self {
+        Self {
+            store: Arc::new(RwLock::new(RedisStore::default())),
+            prefix: prefix(),
+            codec: Arc::new(ProstCodec),
+        }
+    }
+}
+
+impl RedisMetadataStore {
+    /// A store keeping its jobs in `store`, which may be shared with a `RedisNotificationStore`
+    pub fn new(store: RedisStore) -> Self {
+        Self {
+            store: Arc::new(RwLock::new(store)),
+            ..Default::default()
+        }
+    }
+
+    fn jobs_key(&self) -> String {
+        self.prefix.clone() + ":jobs"
+    }
+
+    fn next_ticks_key(&self) -> String {
+        self.prefix.clone() + ":next_ticks"
+    }
+}
+
+/// Write a job along with its place in the sorted set of next ticks, which only holds the jobs
+/// that have a next tick
+fn write_job(
+    jobs_key: &str,
+    next_ticks_key: &str,
+    id: &str,
+    next_tick: u64,
+    bytes: Vec<u8>,
+) -> redis::Pipeline {
+    let mut pipe = redis::pipe();
+    pipe.atomic().hset(jobs_key, id, bytes).ignore();
+    if next_tick > 0 {
+        pipe.zadd(next_ticks_key, id, next_tick).ignore();
+    } else {
+        pipe.zrem(next_ticks_key, id).ignore();
+    }
+    pipe
+}
+
+impl DataStore<JobStoredData> for RedisMetadataStore {
+    fn get(
+        &mut self,
+        id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<JobStoredData>, JobSchedulerError>> + Send>>
+    {
+        let store = self.store.clone();
+        let jobs_key = self.jobs_key();
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::GetJobData),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    let data: Result<Option<Vec<u8>>, _> =
+                        connection.hget(jobs_key, id.to_string()).await;
+                    match data {
+                        Ok(data) => data.map(|data| codec.decode_job(&data)).transpose(),
+                        Err(e) => {
+                            error!("Error getting value {:?}", e);
+                            Err(JobSchedulerError::GetJobData)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn add_or_update(
+        &mut self,
+        data: JobStoredData,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let jobs_key = self.jobs_key();
+        let next_ticks_key = self.next_ticks_key();
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::UpdateJobData),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    let uuid: Uuid = match data.id.as_ref() {
+                        Some(id) => id.into(),
+                        None => return Err(JobSchedulerError::CantAdd),
+                    };
+                    let bytes = codec.encode_job(&data)?;
+                    let pipe = write_job(
+                        &jobs_key,
+                        &next_ticks_key,
+                        &uuid.to_string(),
+                        data.next_tick,
+                        bytes,
+                    );
+                    let result: Result<(), _> = pipe.query_async(&mut connection).await;
+                    match result {
+                        Ok(()) => Ok(()),
+                        Err(e) => {
+                            error!("Error {:?}", e);
+                            Err(JobSchedulerError::CantAdd)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn delete(
+        &mut self,
+        guid: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let jobs_key = self.jobs_key();
+        let next_ticks_key = self.next_ticks_key();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::CantRemove),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    let id = guid.to_string();
+                    let result: Result<(), _> = redis::pipe()
+                        .atomic()
+                        .hdel(jobs_key, &id)
+                        .ignore()
+                        .zrem(next_ticks_key, &id)
+                        .ignore()
+                        .query_async(&mut connection)
+                        .await;
+                    match result {
+                        Ok(()) => Ok(()),
+                        Err(e) => {
+                            error!("Error deleting job data {:?}", e);
+                            Err(JobSchedulerError::CantRemove)
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl InitStore for RedisMetadataStore {
+    fn init(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let inited = self.inited();
+        let store = self.store.clone();
+        Box::pin(async move {
+            let inited = inited.await;
+            if !matches!(inited, Ok(true)) {
+                let mut w = store.write().await;
+                let val = w.clone();
+                match val.init().await {
+                    Ok(v) => {
+                        *w = v;
+                        Ok(())
+                    }
+                    Err(e) => {
+                        error!("Error initialising {:?}", e);
+                        Err(e)
+                    }
+                }
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    fn inited(&mut self) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            Ok(store.inited())
+        })
+    }
+}
+
+impl MetaDataStorage for RedisMetadataStore {
+    fn list_next_ticks(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobAndNextTick>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let jobs_key = self.jobs_key();
+        let next_ticks_key = self.next_ticks_key();
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::CantListNextTicks),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    // Include the jobs due in the next second, so warm jobs can be prepared
+                    let now = Utc::now().timestamp() + 1;
+                    let ids: Result<Vec<String>, _> = connection
+                        .zrangebyscore(&next_ticks_key, "(0", format!("({}", now))
+                        .await;
+                    let ids = match ids {
+                        Ok(ids) if ids.is_empty() => return Ok(vec![]),
+                        Ok(ids) => ids,
+                        Err(e) => {
+                            error!("Error getting next ticks {:?}", e);
+                            return Err(JobSchedulerError::CantListNextTicks);
+                        }
+                    };
+                    let jobs: Result<Vec<Option<Vec<u8>>>, _> = redis::cmd("HMGET")
+                        .arg(&jobs_key)
+                        .arg(&ids)
+                        .query_async(&mut connection)
+                        .await;
+                    match jobs {
+                        Ok(jobs) => Ok(jobs
+                            .iter()
+                            .flatten()
+                            .flat_map(|data| codec.decode_job(data))
+                            .map(|jd| JobAndNextTick {
+                                id: jd.id,
+                                job_type: jd.job_type,
+                                next_tick: jd.next_tick,
+                                last_tick: jd.last_tick,
+                                group: jd.group,
+                            })
+                            .collect()),
+                        Err(e) => {
+                            error!("Error getting next ticks {:?}", e);
+                            Err(JobSchedulerError::CantListNextTicks)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn set_next_and_last_tick(
+        &mut self,
+        guid: Uuid,
+        next_tick: Option<DateTime<Utc>>,
+        last_tick: Option<DateTime<Utc>>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let get = self.get(guid);
+        let store = self.store.clone();
+        let jobs_key = self.jobs_key();
+        let next_ticks_key = self.next_ticks_key();
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let mut data = match get.await {
+                Ok(Some(data)) => data,
+                Ok(None) => {
+                    error!("Could not find job {:?} to update", guid);
+                    return Err(JobSchedulerError::UpdateJobData);
+                }
+                Err(e) => {
+                    error!("Error getting job {:?} to update {:?}", guid, e);
+                    return Err(JobSchedulerError::UpdateJobData);
+                }
+            };
+            data.next_tick = next_tick.map(|t| t.timestamp()).unwrap_or(0) as u64;
+            data.last_tick = last_tick.map(|t| t.timestamp() as u64);
+            let bytes = codec.encode_job(&data)?;
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::UpdateJobData),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    let pipe = write_job(
+                        &jobs_key,
+                        &next_ticks_key,
+                        &guid.to_string(),
+                        data.next_tick,
+                        bytes,
+                    );
+                    let result: Result<(), _> = pipe.query_async(&mut connection).await;
+                    result.map_err(|e| {
+                        error!("Error updating next and last tick {:?}", e);
+                        JobSchedulerError::UpdateJobData
+                    })
+                }
+            }
+        })
+    }
+
+    fn time_till_next_job(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<Duration>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let next_ticks_key = self.next_ticks_key();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::CouldNotGetTimeUntilNextTick),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    let now = Utc::now().timestamp();
+                    let next: Result<Vec<(String, i64)>, _> = connection
+                        .zrangebyscore_limit_withscores(
+                            next_ticks_key,
+                            format!("({}", now),
+                            "+inf",
+                            0,
+                            1,
+                        )
+                        .await;
+                    match next {
+                        Ok(next) => Ok(next
+                            .first()
+                            .map(|(_, ts)| ts - now)
+                            .filter(|ts| *ts > 0)
+                            .map(|ts| Duration::from_secs(ts as u64))),
+                        Err(e) => {
+                            error!("Error getting time until next job {:?}", e);
+                            Err(JobSchedulerError::CouldNotGetTimeUntilNextTick)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn list_job_ids(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Uuid>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let jobs_key = self.jobs_key();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::CantListGuids),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    let ids: Result<Vec<String>, _> = connection.hkeys(jobs_key).await;
+                    match ids {
+                        Ok(ids) => Ok(ids
+                            .iter()
+                            .filter_map(|id| Uuid::from_str(id).ok())
+                            .collect()),
+                        Err(e) => {
+                            error!("Error listing job ids {:?}", e);
+                            Err(JobSchedulerError::CantListGuids)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn get_many(
+        &mut self,
+        ids: &[Uuid],
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobStoredData>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let jobs_key = self.jobs_key();
+        let codec = self.codec.clone();
+        let ids = ids.iter().map(Uuid::to_string).collect::<Vec<_>>();
+        Box::pin(async move {
+            if ids.is_empty() {
+                return Ok(vec![]);
+            }
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::GetJobData),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    let jobs: Result<Vec<Option<Vec<u8>>>, _> = redis::cmd("HMGET")
+                        .arg(jobs_key)
+                        .arg(ids)
+                        .query_async(&mut connection)
+                        .await;
+                    match jobs {
+                        Ok(jobs) => jobs
+                            .iter()
+                            .flatten()
+                            .map(|data| codec.decode_job(data))
+                            .collect(),
+                        Err(e) => {
+                            error!("Error getting values {:?}", e);
+                            Err(JobSchedulerError::GetJobData)
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
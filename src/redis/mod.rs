@@ -0,0 +1,68 @@
+mod metadata_store;
+mod notification_store;
+mod run_lock;
+
+use crate::JobSchedulerError;
+use redis::aio::MultiplexedConnection;
+use std::future::Future;
+use std::pin::Pin;
+
+pub use metadata_store::RedisMetadataStore;
+pub use notification_store::RedisNotificationStore;
+pub use run_lock::RedisRunLock;
+
+///
+/// A Redis server, connected to on `init` with the url it was created with
+#[derive(Clone)]
+pub enum RedisStore {
+    Created(String),
+    Inited(MultiplexedConnection),
+}
+
+impl RedisStore {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self::Created(url.into())
+    }
+
+    pub fn inited(&self) -> bool {
+        matches!(self, RedisStore::Inited(_))
+    }
+}
+
+impl Default for RedisStore {
+    fn default() -> Self {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        Self::Created(url)
+    }
+}
+
+impl RedisStore {
+    pub fn init(
+        self,
+    ) -> Pin<Box<dyn Future<Output = Result<RedisStore, JobSchedulerError>> + Send>> {
+        Box::pin(async move {
+            match self {
+                RedisStore::Created(url) => {
+                    let client = redis::Client::open(url).map_err(|e| {
+                        error!("Error with the Redis url {:?}", e);
+                        JobSchedulerError::CantInit
+                    })?;
+                    match client.get_multiplexed_tokio_connection().await {
+                        Ok(connection) => Ok(RedisStore::Inited(connection)),
+                        Err(e) => {
+                            error!("Error connecting to Redis {:?}", e);
+                            Err(JobSchedulerError::CantInit)
+                        }
+                    }
+                }
+                RedisStore::Inited(connection) => Ok(RedisStore::Inited(connection)),
+            }
+        })
+    }
+}
+
+///
+/// The prefix of the keys of the stores, so schedulers can share a Redis server
+fn prefix() -> String {
+    std::env::var("REDIS_PREFIX").unwrap_or_else(|_| "tcs".to_string())
+}
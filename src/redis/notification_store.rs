@@ -0,0 +1,375 @@
+use crate::job::job_data_prost::{JobState, NotificationData};
+use crate::job::{JobId, NotificationId};
+use crate::redis::{prefix, RedisStore};
+use crate::store::{Codec, DataStore, InitStore, NotificationStore, ProstCodec};
+use crate::JobSchedulerError;
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+///
+/// A Redis backed notification store. The notifications are kept encoded by the codec in a hash,
+/// and the ids of the notifications of each job in a set of its own.
+#[derive(Clone)]
+pub struct RedisNotificationStore {
+    pub store: Arc<RwLock<RedisStore>>,
+    /// The prefix of the keys, `tcs` unless `REDIS_PREFIX` is set
+    pub prefix: String,
+    /// How the notifications are encoded in the hash
+    pub codec: Arc<dyn Codec>,
+}
+
+impl Default for RedisNotificationStore {
+    fn default() -> Self {
+        Self {
+            store: Arc::new(RwLock::new(RedisStore::default())),
+            prefix: prefix(),
+            codec: Arc::new(ProstCodec),
+        }
+    }
+}
+
+impl RedisNotificationStore {
+    /// A store keeping its notifications in `store`, which may be shared with a
+    /// `RedisMetadataStore`
+    pub fn new(store: RedisStore) -> Self {
+        Self {
+            store: Arc::new(RwLock::new(store)),
+            ..Default::default()
+        }
+    }
+
+    fn notifications_key(&self) -> String {
+        self.prefix.clone() + ":notifications"
+    }
+
+    fn job_key_prefix(&self) -> String {
+        self.prefix.clone() + ":job_notifications:"
+    }
+}
+
+async fn get_notification(
+    connection: &mut MultiplexedConnection,
+    codec: &dyn Codec,
+    notifications_key: &str,
+    id: Uuid,
+) -> Result<Option<NotificationData>, JobSchedulerError> {
+    let data: Result<Option<Vec<u8>>, _> = connection.hget(notifications_key, id.to_string()).await;
+    match data {
+        Ok(data) => data
+            .map(|data| codec.decode_notification(&data))
+            .transpose(),
+        Err(e) => {
+            error!("Error fetching notification data {:?}", e);
+            Err(JobSchedulerError::GetJobData)
+        }
+    }
+}
+
+async fn job_notification_ids(
+    connection: &mut MultiplexedConnection,
+    job_key: String,
+) -> Result<Vec<Uuid>, JobSchedulerError> {
+    let ids: Result<Vec<String>, _> = connection.smembers(job_key).await;
+    match ids {
+        Ok(ids) => Ok(ids
+            .iter()
+            .filter_map(|id| Uuid::from_str(id).ok())
+            .collect()),
+        Err(e) => {
+            error!(
+                "Error getting list of notifications guids for job id {:?}",
+                e
+            );
+            Err(JobSchedulerError::CantListGuids)
+        }
+    }
+}
+
+impl DataStore<NotificationData> for RedisNotificationStore {
+    fn get(
+        &mut self,
+        id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<NotificationData>, JobSchedulerError>> + Send>>
+    {
+        let store = self.store.clone();
+        let notifications_key = self.notifications_key();
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::GetJobData),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    get_notification(&mut connection, &*codec, &notifications_key, id).await
+                }
+            }
+        })
+    }
+
+    fn add_or_update(
+        &mut self,
+        data: NotificationData,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let notifications_key = self.notifications_key();
+        let job_key_prefix = self.job_key_prefix();
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::UpdateJobData),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    let (job_id, notification_id) =
+                        match data.job_id_and_notification_id_from_data() {
+                            Some((job_id, notification_id)) => (job_id, notification_id),
+                            None => return Err(JobSchedulerError::UpdateJobData),
+                        };
+                    let bytes = codec.encode_notification(&data)?;
+                    let notification_id = notification_id.to_string();
+                    let result: Result<(), _> = redis::pipe()
+                        .atomic()
+                        .hset(notifications_key, &notification_id, bytes)
+                        .ignore()
+                        .sadd(job_key_prefix + &job_id.to_string(), &notification_id)
+                        .ignore()
+                        .query_async(&mut connection)
+                        .await;
+                    match result {
+                        Ok(()) => Ok(()),
+                        Err(e) => {
+                            error!("Error doing the upsert {:?}", e);
+                            Err(JobSchedulerError::UpdateJobData)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn delete(
+        &mut self,
+        guid: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let notifications_key = self.notifications_key();
+        let job_key_prefix = self.job_key_prefix();
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::CantRemove),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    let data = get_notification(&mut connection, &*codec, &notifications_key, guid)
+                        .await
+                        .map_err(|_| JobSchedulerError::CantRemove)?;
+                    let id = guid.to_string();
+                    let mut pipe = redis::pipe();
+                    pipe.atomic().hdel(notifications_key, &id).ignore();
+                    if let Some((job_id, _)) = data
+                        .as_ref()
+                        .and_then(|data| data.job_id_and_notification_id_from_data())
+                    {
+                        pipe.srem(job_key_prefix + &job_id.to_string(), &id)
+                            .ignore();
+                    }
+                    let result: Result<(), _> = pipe.query_async(&mut connection).await;
+                    result.map_err(|e| {
+                        error!("Error deleting notification {:?}", e);
+                        JobSchedulerError::CantRemove
+                    })
+                }
+            }
+        })
+    }
+}
+
+impl InitStore for RedisNotificationStore {
+    fn init(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let inited = self.inited();
+        let store = self.store.clone();
+        Box::pin(async move {
+            let inited = inited.await;
+            if !matches!(inited, Ok(true)) {
+                let mut w = store.write().await;
+                let val = w.clone();
+                match val.init().await {
+                    Ok(v) => {
+                        *w = v;
+                        Ok(())
+                    }
+                    Err(e) => {
+                        error!("Error initialising {:?}", e);
+                        Err(e)
+                    }
+                }
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    fn inited(&mut self) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            Ok(store.inited())
+        })
+    }
+}
+
+impl NotificationStore for RedisNotificationStore {
+    fn list_notification_guids_for_job_and_state(
+        &mut self,
+        job: JobId,
+        state: JobState,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<NotificationId>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let notifications_key = self.notifications_key();
+        let job_key = self.job_key_prefix() + &job.to_string();
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::CantListGuids),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    let ids = job_notification_ids(&mut connection, job_key).await?;
+                    if ids.is_empty() {
+                        return Ok(vec![]);
+                    }
+                    let notifications: Result<Vec<Option<Vec<u8>>>, _> = redis::cmd("HMGET")
+                        .arg(notifications_key)
+                        .arg(ids.iter().map(Uuid::to_string).collect::<Vec<_>>())
+                        .query_async(&mut connection)
+                        .await;
+                    match notifications {
+                        Ok(notifications) => Ok(ids
+                            .into_iter()
+                            .zip(notifications)
+                            .filter(|(_, data)| {
+                                data.as_ref()
+                                    .and_then(|data| codec.decode_notification(data).ok())
+                                    .map(|data| data.job_states.contains(&(state as i32)))
+                                    .unwrap_or_default()
+                            })
+                            .map(|(id, _)| id)
+                            .collect()),
+                        Err(e) => {
+                            error!("Error listing notification guids for job and state {:?}", e);
+                            Err(JobSchedulerError::CantListGuids)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn list_notification_guids_for_job_id(
+        &mut self,
+        job_id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Uuid>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let job_key = self.job_key_prefix() + &job_id.to_string();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::CantListGuids),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    job_notification_ids(&mut connection, job_key).await
+                }
+            }
+        })
+    }
+
+    fn delete_notification_for_state(
+        &mut self,
+        notification_id: Uuid,
+        state: JobState,
+    ) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let notifications_key = self.notifications_key();
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::CantRemove),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    let data = get_notification(
+                        &mut connection,
+                        &*codec,
+                        &notifications_key,
+                        notification_id,
+                    )
+                    .await
+                    .map_err(|_| JobSchedulerError::CantRemove)?;
+                    let mut data = match data {
+                        Some(data) => data,
+                        None => return Ok(false),
+                    };
+                    let state = state as i32;
+                    if !data.job_states.contains(&state) {
+                        return Ok(false);
+                    }
+                    data.job_states.retain(|s| *s != state);
+                    let bytes = codec.encode_notification(&data)?;
+                    let result: Result<(), _> = connection
+                        .hset(notifications_key, notification_id.to_string(), bytes)
+                        .await;
+                    match result {
+                        Ok(()) => Ok(true),
+                        Err(e) => {
+                            error!("Error deleting notification for state {:?}", e);
+                            Err(JobSchedulerError::CantRemove)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn delete_for_job(
+        &mut self,
+        job_id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let notifications_key = self.notifications_key();
+        let job_key = self.job_key_prefix() + &job_id.to_string();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                RedisStore::Created(_) => Err(JobSchedulerError::CantRemove),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    let ids = job_notification_ids(&mut connection, job_key.clone())
+                        .await
+                        .map_err(|_| JobSchedulerError::CantRemove)?;
+                    let mut pipe = redis::pipe();
+                    pipe.atomic();
+                    if !ids.is_empty() {
+                        pipe.hdel(
+                            notifications_key,
+                            ids.iter().map(Uuid::to_string).collect::<Vec<_>>(),
+                        )
+                        .ignore();
+                    }
+                    pipe.del(job_key).ignore();
+                    let result: Result<(), _> = pipe.query_async(&mut connection).await;
+                    result.map_err(|e| {
+                        error!("Error deleting for job {:?}", e);
+                        JobSchedulerError::CantRemove
+                    })
+                }
+            }
+        })
+    }
+}
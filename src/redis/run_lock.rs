@@ -0,0 +1,76 @@
+use crate::redis::{prefix, RedisStore};
+use crate::{JobSchedulerError, RunLock};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+///
+/// Run locks kept as Redis keys expiring after their ttl. A run is locked by the scheduler
+/// setting its key first.
+#[derive(Clone)]
+pub struct RedisRunLock {
+    pub store: Arc<RwLock<RedisStore>>,
+    /// The prefix of the keys, `tcs` unless `REDIS_PREFIX` is set
+    pub prefix: String,
+}
+
+impl Default for RedisRunLock {
+    fn default() -> Self {
+        Self {
+            store: Arc::new(RwLock::new(RedisStore::default())),
+            prefix: prefix(),
+        }
+    }
+}
+
+impl RedisRunLock {
+    pub fn new(store: RedisStore) -> Self {
+        Self {
+            store: Arc::new(RwLock::new(store)),
+            ..Default::default()
+        }
+    }
+}
+
+impl RunLock for RedisRunLock {
+    fn try_acquire_run_lock(
+        &mut self,
+        job_id: Uuid,
+        run_id: Uuid,
+        ttl: Duration,
+    ) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let key =
+            self.prefix.clone() + ":run_lock:" + &job_id.to_string() + ":" + &run_id.to_string();
+        Box::pin(async move {
+            let mut w = store.write().await;
+            if !w.inited() {
+                *w = w.clone().init().await?;
+            }
+            match &*w {
+                RedisStore::Created(_) => Err(JobSchedulerError::RunLock),
+                RedisStore::Inited(connection) => {
+                    let mut connection = connection.clone();
+                    let set: Result<Option<String>, _> = redis::cmd("SET")
+                        .arg(&key)
+                        .arg(run_id.to_string())
+                        .arg("NX")
+                        .arg("PX")
+                        .arg(ttl.as_millis().max(1) as u64)
+                        .query_async(&mut connection)
+                        .await;
+                    match set {
+                        Ok(set) => Ok(set.is_some()),
+                        Err(e) => {
+                            error!("Error acquiring the run lock {:?}", e);
+                            Err(JobSchedulerError::RunLock)
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
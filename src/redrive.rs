@@ -0,0 +1,483 @@
+use crate::job_scheduler::JobsSchedulerLocked;
+use crate::store::Page;
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// The dead letters kept, the oldest being dropped past it
+const MAX_DEAD_LETTERS: usize = 1000;
+
+///
+/// Why an occurrence of a job did not get a successful run
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeadLetterReason {
+    /// The run could not start within the starting deadline of the job
+    Missed,
+    /// The run panicked or returned an error
+    Failed,
+}
+
+///
+/// An occurrence of a job that did not get a successful run, to be re-driven
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeadLetter {
+    pub id: Uuid,
+    pub job_id: Uuid,
+    /// The logical time of the occurrence, which a re-driven run is run as of
+    pub scheduled_time: DateTime<Utc>,
+    pub reason: DeadLetterReason,
+    pub recorded_at: DateTime<Utc>,
+    /// A note left by an operator, like "known issue, ignored"
+    pub annotation: Option<String>,
+    /// Whether an operator resolved the occurrence, which is then not re-driven and, for a
+    /// failed run, no longer counted as a failure of the job
+    pub resolved: bool,
+}
+
+///
+/// The occurrences that missed their run or failed, kept in memory for the life of the scheduler
+#[derive(Clone, Default)]
+pub struct DeadLetters {
+    entries: Arc<RwLock<VecDeque<DeadLetter>>>,
+}
+
+impl DeadLetters {
+    pub async fn record(
+        &self,
+        job_id: Uuid,
+        scheduled_time: DateTime<Utc>,
+        reason: DeadLetterReason,
+    ) {
+        let mut w = self.entries.write().await;
+        if w.len() >= MAX_DEAD_LETTERS {
+            w.pop_front();
+        }
+        w.push_back(DeadLetter {
+            id: Uuid::new_v4(),
+            job_id,
+            scheduled_time,
+            reason,
+            recorded_at: Utc::now(),
+            annotation: None,
+            resolved: false,
+        });
+    }
+
+    pub async fn list(&self) -> Vec<DeadLetter> {
+        let r = self.entries.read().await;
+        r.iter().cloned().collect()
+    }
+
+    ///
+    /// Take the dead letters matching `filter` out, oldest first
+    pub async fn take(&self, filter: &RedriveFilter) -> Vec<DeadLetter> {
+        let mut w = self.entries.write().await;
+        let taken = w
+            .iter()
+            .filter(|letter| filter.matches(letter))
+            .cloned()
+            .collect();
+        w.retain(|letter| !filter.matches(letter));
+        taken
+    }
+
+    ///
+    /// Put back a dead letter that could not be re-driven
+    pub async fn put_back(&self, letter: DeadLetter) {
+        let mut w = self.entries.write().await;
+        w.push_back(letter);
+    }
+
+    ///
+    /// Set the annotation of a dead letter, resolving it when `resolve` is set
+    pub async fn annotate(
+        &self,
+        id: &Uuid,
+        annotation: String,
+        resolve: bool,
+    ) -> Option<DeadLetter> {
+        let mut w = self.entries.write().await;
+        let letter = w.iter_mut().find(|letter| letter.id == *id)?;
+        letter.annotation = Some(annotation);
+        letter.resolved |= resolve;
+        Some(letter.clone())
+    }
+
+    ///
+    /// The page of the dead letters selected by `query`
+    pub async fn query(&self, query: &DeadLetterQuery) -> Page<DeadLetter> {
+        let r = self.entries.read().await;
+        let after = query.cursor_key();
+        let mut letters: Vec<_> = r
+            .iter()
+            .filter(|letter| query.matches(letter))
+            .filter(|letter| match (after, query.newest_first) {
+                (None, _) => true,
+                (Some(after), false) => sort_key(letter) > after,
+                (Some(after), true) => sort_key(letter) < after,
+            })
+            .cloned()
+            .collect();
+        letters.sort_by_key(sort_key);
+        if query.newest_first {
+            letters.reverse();
+        }
+        let more = letters.len() > query.limit;
+        letters.truncate(query.limit);
+        let next_cursor = letters.last().filter(|_| more).map(|letter| {
+            let (at, id) = sort_key(letter);
+            format!("{}:{}", at, id)
+        });
+        Page {
+            items: letters,
+            next_cursor,
+        }
+    }
+
+    pub async fn remove_job(&self, job_id: &Uuid) {
+        let mut w = self.entries.write().await;
+        w.retain(|letter| letter.job_id != *job_id);
+    }
+}
+
+///
+/// Selects the dead letters to re-drive, and how fast. An empty filter selects all of them.
+#[derive(Clone, Debug)]
+pub struct RedriveFilter {
+    job_ids: Vec<Uuid>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    reasons: Vec<DeadLetterReason>,
+    pub(crate) every: Duration,
+}
+
+impl Default for RedriveFilter {
+    fn default() -> Self {
+        Self {
+            job_ids: vec![],
+            from: None,
+            to: None,
+            reasons: vec![],
+            every: Duration::from_millis(100),
+        }
+    }
+}
+
+impl RedriveFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Only re-drive the occurrences of this job. Can be given more than once.
+    pub fn job(mut self, job_id: Uuid) -> Self {
+        self.job_ids.push(job_id);
+        self
+    }
+
+    ///
+    /// Only re-drive the occurrences scheduled from `from`, included, to `to`, excluded
+    pub fn between(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self.to = Some(to);
+        self
+    }
+
+    ///
+    /// Only re-drive the occurrences dead-lettered for this reason. Can be given more than once.
+    pub fn reason(mut self, reason: DeadLetterReason) -> Self {
+        self.reasons.push(reason);
+        self
+    }
+
+    ///
+    /// Wait `every` between two re-driven occurrences, so a backlog doesn't start all at once.
+    /// Defaults to 100 milliseconds.
+    pub fn every(mut self, every: Duration) -> Self {
+        self.every = every;
+        self
+    }
+
+    fn matches(&self, letter: &DeadLetter) -> bool {
+        !letter.resolved
+            && (self.job_ids.is_empty() || self.job_ids.contains(&letter.job_id))
+            && self.from.is_none_or(|from| letter.scheduled_time >= from)
+            && self.to.is_none_or(|to| letter.scheduled_time < to)
+            && (self.reasons.is_empty() || self.reasons.contains(&letter.reason))
+    }
+}
+
+///
+/// Dead letters are listed by when they were recorded, their id breaking ties
+fn sort_key(letter: &DeadLetter) -> (i64, Uuid) {
+    (letter.recorded_at.timestamp_micros(), letter.id)
+}
+
+///
+/// Selects and pages the dead letters, in the order they were recorded. An empty query lists
+/// all of them, resolved or not, a hundred at a time.
+#[derive(Clone, Debug)]
+pub struct DeadLetterQuery {
+    job_ids: Vec<Uuid>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    reasons: Vec<DeadLetterReason>,
+    resolved: Option<bool>,
+    newest_first: bool,
+    cursor: Option<String>,
+    limit: usize,
+}
+
+impl Default for DeadLetterQuery {
+    fn default() -> Self {
+        Self {
+            job_ids: vec![],
+            from: None,
+            to: None,
+            reasons: vec![],
+            resolved: None,
+            newest_first: false,
+            cursor: None,
+            limit: 100,
+        }
+    }
+}
+
+impl DeadLetterQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Only list the occurrences of this job. Can be given more than once.
+    pub fn job(mut self, job_id: Uuid) -> Self {
+        self.job_ids.push(job_id);
+        self
+    }
+
+    ///
+    /// Only list the occurrences scheduled from `from`, included, to `to`, excluded
+    pub fn between(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self.to = Some(to);
+        self
+    }
+
+    ///
+    /// Only list the occurrences dead-lettered for this reason. Can be given more than once.
+    pub fn reason(mut self, reason: DeadLetterReason) -> Self {
+        self.reasons.push(reason);
+        self
+    }
+
+    ///
+    /// Only list the resolved occurrences, or only the unresolved ones
+    pub fn resolved(mut self, resolved: bool) -> Self {
+        self.resolved = Some(resolved);
+        self
+    }
+
+    pub fn newest_first(mut self) -> Self {
+        self.newest_first = true;
+        self
+    }
+
+    ///
+    /// Continue from the page that returned `cursor`
+    pub fn after(mut self, cursor: String) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit.max(1);
+        self
+    }
+
+    fn cursor_key(&self) -> Option<(i64, Uuid)> {
+        let (at, id) = self.cursor.as_ref()?.split_once(':')?;
+        Some((at.parse().ok()?, Uuid::parse_str(id).ok()?))
+    }
+
+    fn matches(&self, letter: &DeadLetter) -> bool {
+        self.resolved
+            .is_none_or(|resolved| letter.resolved == resolved)
+            && (self.job_ids.is_empty() || self.job_ids.contains(&letter.job_id))
+            && self.from.is_none_or(|from| letter.scheduled_time >= from)
+            && self.to.is_none_or(|to| letter.scheduled_time < to)
+            && (self.reasons.is_empty() || self.reasons.contains(&letter.reason))
+    }
+}
+
+///
+/// The progress of a re-drive
+#[derive(Clone, Debug)]
+pub enum RedriveProgress {
+    /// An occurrence was run again, being the `done`th of `total`
+    Requeued {
+        letter: DeadLetter,
+        done: usize,
+        total: usize,
+    },
+    /// An occurrence could not be run again, e.g. because its job was removed. It is kept as a
+    /// dead letter unless its job is gone.
+    Failed {
+        letter: DeadLetter,
+        error: JobSchedulerError,
+    },
+    /// All the selected occurrences were handled
+    Finished { requeued: usize, failed: usize },
+}
+
+///
+/// Run the dead letters again as of their scheduled time, one every `every`
+pub(crate) async fn redrive(
+    scheduler: JobsSchedulerLocked,
+    letters: Vec<DeadLetter>,
+    every: Duration,
+    progress: UnboundedSender<RedriveProgress>,
+) {
+    let total = letters.len();
+    let mut requeued = 0;
+    let mut failed = 0;
+    for (i, letter) in letters.into_iter().enumerate() {
+        if i > 0 {
+            tokio::time::sleep(every).await;
+        }
+        match scheduler
+            .trigger_as_of(&letter.job_id, letter.scheduled_time)
+            .await
+        {
+            Ok(()) => {
+                requeued += 1;
+                let _ = progress.send(RedriveProgress::Requeued {
+                    letter,
+                    done: requeued + failed,
+                    total,
+                });
+            }
+            Err(error) => {
+                failed += 1;
+                if !matches!(error, JobSchedulerError::FetchJob) {
+                    scheduler
+                        .context
+                        .dead_letters
+                        .put_back(letter.clone())
+                        .await;
+                }
+                let _ = progress.send(RedriveProgress::Failed { letter, error });
+            }
+        }
+    }
+    let _ = progress.send(RedriveProgress::Finished { requeued, failed });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeadLetterReason, RedriveFilter, RedriveProgress};
+    use crate::{Job, JobNotification, JobScheduler};
+    use chrono::{Duration as ChronoDuration, TimeZone, Utc};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// The failed runs of a job are dead-lettered, and re-driven as of their scheduled times once
+    /// the job works again, leaving the dead letters of the other jobs
+    #[tokio::test]
+    async fn redrive_failed_runs() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let failing = Arc::new(AtomicBool::new(true));
+        let runs = Arc::new(AtomicUsize::new(0));
+        let (fails, counted) = (failing.clone(), runs.clone());
+        let job = Job::new_fallible("0 0 0 1 1 *", move |_, _| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            if fails.load(Ordering::SeqCst) {
+                Err("failed")
+            } else {
+                Ok(())
+            }
+        })
+        .expect("job");
+        let job_id = sched.add(job).await.expect("add");
+        let other_id = sched
+            .add(Job::new_fallible("0 0 0 1 1 *", |_, _| Err("failed")).expect("job"))
+            .await
+            .expect("add");
+        sched.start().await.expect("start");
+
+        let first = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let scheduled: Vec<_> = (0..3).map(|i| first + ChronoDuration::hours(i)).collect();
+        for scheduled_time in scheduled.iter() {
+            sched
+                .trigger_as_of(&job_id, *scheduled_time)
+                .await
+                .expect("trigger");
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        sched.trigger(&other_id).await.expect("trigger");
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let letters = sched.dead_letters().await;
+        assert_eq!(letters.len(), 4);
+        assert!(letters
+            .iter()
+            .all(|letter| letter.reason == DeadLetterReason::Failed));
+
+        failing.store(false, Ordering::SeqCst);
+        let mut progress = sched
+            .redrive(
+                RedriveFilter::new()
+                    .job(job_id)
+                    .every(Duration::from_millis(100)),
+            )
+            .await
+            .expect("redrive");
+        let mut requeued = vec![];
+        loop {
+            let step = tokio::time::timeout(Duration::from_secs(3), progress.recv())
+                .await
+                .expect("redrive stuck")
+                .expect("progress");
+            match step {
+                RedriveProgress::Requeued {
+                    letter,
+                    done,
+                    total,
+                } => {
+                    assert_eq!((done, total), (requeued.len() + 1, 3));
+                    requeued.push(letter.scheduled_time);
+                }
+                RedriveProgress::Failed { letter, error } => {
+                    panic!("{:?} failed {:?}", letter, error)
+                }
+                RedriveProgress::Finished { requeued, failed } => {
+                    assert_eq!((requeued, failed), (3, 0));
+                    break;
+                }
+            }
+        }
+        requeued.sort();
+        assert_eq!(requeued, scheduled);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        assert_eq!(runs.load(Ordering::SeqCst), 6);
+        let letters = sched.dead_letters().await;
+        assert_eq!(letters.len(), 1, "{:?}", letters);
+        assert_eq!(letters[0].job_id, other_id);
+        let history = sched.run_history(&job_id, 3).await.expect("history");
+        let mut redriven: Vec<_> = history
+            .iter()
+            .filter(|run| run.outcome == JobNotification::Succeeded as i32)
+            .filter_map(|run| run.scheduled_at_utc())
+            .collect();
+        redriven.sort();
+        assert_eq!(redriven, scheduled);
+        sched.shutdown().await.expect("shutdown");
+    }
+}
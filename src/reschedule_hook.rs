@@ -0,0 +1,50 @@
+use crate::JobStoredData;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+///
+/// Adjusts the next tick computed for a job, e.g. to align it on a 15 minute boundary. Hooks may
+/// be applied more than once to the same tick, so they should return a tick unchanged when it
+/// already fits.
+pub type RescheduleHook = dyn Fn(&JobStoredData, DateTime<Utc>) -> DateTime<Utc> + Send + Sync;
+
+///
+/// The global reschedule hook of a scheduler and the hooks of its single jobs
+#[derive(Clone, Default)]
+pub struct RescheduleHooks {
+    pub global: Arc<RwLock<Option<Arc<RescheduleHook>>>>,
+    pub per_job: Arc<RwLock<HashMap<Uuid, Arc<RescheduleHook>>>>,
+}
+
+impl RescheduleHooks {
+    ///
+    /// Run the hook of the job and then the global hook over a computed next tick
+    pub async fn apply(
+        &self,
+        job: &JobStoredData,
+        next_tick: Option<DateTime<Utc>>,
+    ) -> Option<DateTime<Utc>> {
+        let mut next_tick = next_tick?;
+        let job_hook = match job.id.as_ref() {
+            Some(id) => {
+                let r = self.per_job.read().await;
+                r.get(&id.into()).cloned()
+            }
+            None => None,
+        };
+        if let Some(hook) = job_hook {
+            next_tick = hook(job, next_tick);
+        }
+        let global = {
+            let r = self.global.read().await;
+            r.clone()
+        };
+        if let Some(hook) = global {
+            next_tick = hook(job, next_tick);
+        }
+        Some(next_tick)
+    }
+}
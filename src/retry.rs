@@ -0,0 +1,194 @@
+use crate::context::Context;
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{JobState, RetryPolicy};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{JobState, RetryPolicy};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// The time of an occurrence being retried and the retries it had so far
+type Attempts = (DateTime<Utc>, u32);
+
+impl RetryPolicy {
+    ///
+    /// Retry a failed run after `delay`, until an occurrence was run `max_attempts` times in all
+    pub fn fixed(delay: Duration, max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            delay_millis: delay.as_millis() as u64,
+            exponential: false,
+            max_delay_millis: 0,
+            jitter: false,
+        }
+    }
+
+    ///
+    /// Retry a failed run after `initial`, doubling the wait on every next retry up to
+    /// `max_delay`, until an occurrence was run `max_attempts` times in all
+    pub fn exponential(initial: Duration, max_delay: Duration, max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            delay_millis: initial.as_millis() as u64,
+            exponential: true,
+            max_delay_millis: max_delay.as_millis() as u64,
+            jitter: false,
+        }
+    }
+
+    ///
+    /// Take a random part of up to half the wait off, so jobs failing together don't all retry
+    /// at the same instant
+    pub fn with_jitter(mut self) -> Self {
+        self.jitter = true;
+        self
+    }
+
+    ///
+    /// The wait before the `retry`th retry, the first being 1, without the jitter. `None` when
+    /// the occurrence has had all its attempts.
+    pub fn delay(&self, retry: u32) -> Option<Duration> {
+        if retry == 0 || retry >= self.max_attempts {
+            return None;
+        }
+        let delay = if self.exponential {
+            let factor = 1u64.checked_shl(retry - 1).unwrap_or(u64::MAX);
+            self.delay_millis
+                .saturating_mul(factor)
+                .min(self.max_delay_millis.max(self.delay_millis))
+        } else {
+            self.delay_millis
+        };
+        Some(Duration::from_millis(delay))
+    }
+
+    pub(crate) fn jittered(&self, delay: Duration) -> Duration {
+        if !self.jitter {
+            return delay;
+        }
+        // A v4 uuid is random enough to spread retries
+        let random = (Uuid::new_v4().as_u128() % 1_000) as u32;
+        delay - delay / 2 * random / 1_000
+    }
+}
+
+///
+/// The retries had so far by the occurrence of each job that is being retried
+#[derive(Clone, Default)]
+pub struct RetryAttempts {
+    attempts: Arc<RwLock<HashMap<Uuid, Attempts>>>,
+}
+
+impl RetryAttempts {
+    ///
+    /// Count a retry of the occurrence of a job at `scheduled_time`, returning the number of
+    /// retries it had with this one
+    async fn next(&self, job_id: Uuid, scheduled_time: DateTime<Utc>) -> u32 {
+        let mut w = self.attempts.write().await;
+        let entry = w.entry(job_id).or_insert((scheduled_time, 0));
+        if entry.0 != scheduled_time {
+            *entry = (scheduled_time, 0);
+        }
+        entry.1 += 1;
+        entry.1
+    }
+
+    ///
+    /// The attempt the next run of the occurrence of a job at `scheduled_time` is, the first run
+    /// being 1
+    #[cfg(feature = "tracing")]
+    pub(crate) async fn attempt(&self, job_id: &Uuid, scheduled_time: DateTime<Utc>) -> u32 {
+        let r = self.attempts.read().await;
+        match r.get(job_id) {
+            Some((time, retries)) if *time == scheduled_time => retries + 1,
+            _ => 1,
+        }
+    }
+
+    ///
+    /// Forget the retries of a job, once a run succeeded or the retries ran out
+    pub(crate) async fn reset(&self, job_id: &Uuid) {
+        let mut w = self.attempts.write().await;
+        w.remove(job_id);
+    }
+}
+
+///
+/// Retry a failed run of a job according to its policy, when the retry comes before its next
+/// tick. Returns whether a retry was scheduled.
+pub(crate) async fn retry(
+    context: Arc<Context>,
+    job_id: Uuid,
+    policy: &RetryPolicy,
+    next_tick: Option<DateTime<Utc>>,
+    scheduled_time: DateTime<Utc>,
+) -> bool {
+    let retry = context.retry_attempts.next(job_id, scheduled_time).await;
+    let delay = match policy.delay(retry) {
+        Some(delay) => policy.jittered(delay),
+        None => {
+            context.retry_attempts.reset(&job_id).await;
+            return false;
+        }
+    };
+    let at = Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default();
+    if next_tick.is_some_and(|next_tick| at >= next_tick) {
+        context.retry_attempts.reset(&job_id).await;
+        return false;
+    }
+    info!(job_id = job_id, retry = retry, delay = delay; "Retrying failed run");
+    tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        {
+            let mut r = context.metadata_storage.write().await;
+            match r.get(job_id).await {
+                Ok(Some(job)) if !job.stopped => {}
+                // Removed or stopped while waiting
+                _ => return,
+            }
+        }
+        {
+            let mut w = context.scheduled_times.write().await;
+            w.insert(job_id, scheduled_time);
+        }
+        if let Err(e) = context.notify_tx.send((job_id, JobState::Scheduled)) {
+            error!(job_id = job_id; "Error sending notification retry {:?}", e);
+        }
+        if let Err(e) = context.job_activation_tx.send(job_id) {
+            error!(job_id = job_id; "Error sending job activation for retry {:?}", e);
+        }
+    });
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RetryPolicy;
+
+    #[test]
+    fn retry_policy_delays() {
+        use std::time::Duration;
+
+        let fixed = RetryPolicy::fixed(Duration::from_secs(5), 3);
+        assert_eq!(fixed.delay(1), Some(Duration::from_secs(5)));
+        assert_eq!(fixed.delay(2), Some(Duration::from_secs(5)));
+        // The third attempt was the last
+        assert_eq!(fixed.delay(3), None);
+
+        let exponential =
+            RetryPolicy::exponential(Duration::from_secs(1), Duration::from_secs(5), 100);
+        let delays: Vec<_> = (1..=5)
+            .filter_map(|retry| exponential.delay(retry))
+            .map(|delay| delay.as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+        assert_eq!(
+            exponential.delay(99),
+            Some(Duration::from_secs(5)),
+            "the wait doesn't overflow"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+///
+/// Checked just before a job is run, e.g. whether a feature flag is on or the upstream data is
+/// available. Returns why the run can't go ahead when it can't.
+pub type RunPredicate =
+    dyn Fn(Uuid) -> Pin<Box<dyn Future<Output = Result<(), String>> + Send>> + Send + Sync;
+
+///
+/// A predicate a job has to meet to run, and how long to keep checking it before the run is
+/// skipped
+#[derive(Clone)]
+pub struct RunCondition {
+    predicate: Arc<RunPredicate>,
+    retry: Option<(Duration, Duration)>,
+}
+
+impl RunCondition {
+    pub fn new(predicate: Box<RunPredicate>) -> Self {
+        Self {
+            predicate: Arc::from(predicate),
+            retry: None,
+        }
+    }
+
+    ///
+    /// Check the predicate again every `every` when it isn't met, until `within` has passed since
+    /// the first check, checking it a last time then
+    pub fn retry(mut self, every: Duration, within: Duration) -> Self {
+        self.retry = Some((every, within));
+        self
+    }
+
+    ///
+    /// Check the predicate, retrying it when set to. Returns the reason of the last failed check
+    /// when the run can't go ahead.
+    pub async fn check(&self, job_id: Uuid) -> Result<(), String> {
+        let started = Instant::now();
+        loop {
+            let reason = match (self.predicate)(job_id).await {
+                Ok(()) => return Ok(()),
+                Err(reason) => reason,
+            };
+            match self.retry {
+                Some((every, within)) if started.elapsed() < within => {
+                    tokio::time::sleep(every.min(within - started.elapsed())).await;
+                }
+                _ => return Err(reason),
+            }
+        }
+    }
+}
+
+///
+/// The run conditions of the jobs of a scheduler, and why the last run of each job was skipped
+/// by its condition
+#[derive(Clone, Default)]
+pub struct RunConditions {
+    pub per_job: Arc<RwLock<HashMap<Uuid, RunCondition>>>,
+    pub skip_reasons: Arc<RwLock<HashMap<Uuid, String>>>,
+    /// The jobs with a run waiting on its condition, which are not removed when they have no
+    /// next tick until the condition is checked
+    pub waiting: Arc<RwLock<HashSet<Uuid>>>,
+}
+
+impl RunConditions {
+    ///
+    /// Keep a job with a condition from being removed until `allows` has checked its run, as its
+    /// next tick may be gone by then
+    pub async fn hold(&self, job_id: Uuid) {
+        let has_condition = {
+            let r = self.per_job.read().await;
+            r.contains_key(&job_id)
+        };
+        if has_condition {
+            let mut w = self.waiting.write().await;
+            w.insert(job_id);
+        }
+    }
+
+    ///
+    /// Whether the job may run now. Jobs without a condition always may.
+    pub async fn allows(&self, job_id: Uuid) -> bool {
+        let condition = {
+            let r = self.per_job.read().await;
+            r.get(&job_id).cloned()
+        };
+        let condition = match condition {
+            Some(condition) => condition,
+            None => return true,
+        };
+        let checked = condition.check(job_id).await;
+        {
+            let mut w = self.waiting.write().await;
+            w.remove(&job_id);
+        }
+        let mut w = self.skip_reasons.write().await;
+        match checked {
+            Ok(()) => {
+                w.remove(&job_id);
+                true
+            }
+            Err(reason) => {
+                w.insert(job_id, reason);
+                false
+            }
+        }
+    }
+
+    pub async fn remove(&self, job_id: &Uuid) {
+        {
+            let mut w = self.per_job.write().await;
+            w.remove(job_id);
+        }
+        {
+            let mut w = self.waiting.write().await;
+            w.remove(job_id);
+        }
+        let mut w = self.skip_reasons.write().await;
+        w.remove(job_id);
+    }
+}
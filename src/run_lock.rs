@@ -0,0 +1,86 @@
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+type LockFuture<T> = Pin<Box<dyn Future<Output = Result<T, JobSchedulerError>> + Send>>;
+
+///
+/// A lock on every run of a job, taken from a store shared by several schedulers before the run
+/// fires. All the schedulers fire due jobs, sharing the load, and the one taking the lock on a
+/// run is the only one running it, so every run happens once in the whole cluster.
+pub trait RunLock {
+    ///
+    /// Take the lock on the run `run_id` of the job, held for `ttl`. Returns false when another
+    /// scheduler holds it, which then runs it.
+    fn try_acquire_run_lock(
+        &mut self,
+        job_id: Uuid,
+        run_id: Uuid,
+        ttl: Duration,
+    ) -> LockFuture<bool>;
+
+    ///
+    /// Release the locks past their ttl, held by runs that never ended like those of a scheduler
+    /// that crashed, returning the jobs whose locks were released. Done when the scheduler
+    /// starts; nothing is released by default, for stores expiring the locks by themselves.
+    fn recover(&mut self) -> LockFuture<Vec<Uuid>> {
+        Box::pin(async { Ok(vec![]) })
+    }
+}
+
+///
+/// The id of the run of a job due at `due`, the same in every scheduler
+pub fn run_id(job_id: Uuid, due: DateTime<Utc>) -> Uuid {
+    Uuid::new_v5(&job_id, &due.timestamp_millis().to_be_bytes())
+}
+
+///
+/// Run locks between the schedulers of one process, for tests and simulations. The clones of a
+/// lock share the runs locked.
+#[derive(Clone, Default)]
+pub struct MemoryRunLock {
+    locked: Arc<Mutex<HashMap<(Uuid, Uuid), Instant>>>,
+}
+
+impl RunLock for MemoryRunLock {
+    fn try_acquire_run_lock(
+        &mut self,
+        job_id: Uuid,
+        run_id: Uuid,
+        ttl: Duration,
+    ) -> LockFuture<bool> {
+        let locked = self.locked.clone();
+        Box::pin(async move {
+            let mut locked = locked.lock().await;
+            let now = Instant::now();
+            locked.retain(|_, expires| *expires > now);
+            if locked.contains_key(&(job_id, run_id)) {
+                return Ok(false);
+            }
+            locked.insert((job_id, run_id), now + ttl);
+            Ok(true)
+        })
+    }
+
+    fn recover(&mut self) -> LockFuture<Vec<Uuid>> {
+        let locked = self.locked.clone();
+        Box::pin(async move {
+            let mut locked = locked.lock().await;
+            let now = Instant::now();
+            let mut recovered = vec![];
+            locked.retain(|(job_id, _), expires| {
+                if *expires <= now {
+                    recovered.push(*job_id);
+                }
+                *expires > now
+            });
+            Ok(recovered)
+        })
+    }
+}
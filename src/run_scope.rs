@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::task::LocalKey;
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
+use uuid::Uuid;
+
+///
+/// A run of a job, as wrapped by run scopes
+pub type RunFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+///
+/// Wraps the runs of a job, e.g. to set the task-local values captured when the job was
+/// registered or triggered, like a request id or a tenant id, so that logging and database
+/// row-level security contexts flow into the run
+pub type RunScope = dyn Fn(RunFuture) -> RunFuture + Send + Sync;
+
+///
+/// A scope setting the task-local `key` to the value it has where this is called, e.g. when the
+/// job is added. Runs aren't scoped when the key isn't set there.
+pub fn propagate<T: Clone + Send + Sync + 'static>(key: &'static LocalKey<T>) -> Box<RunScope> {
+    let value = key.try_with(|v| v.clone()).ok();
+    Box::new(move |run| match value.clone() {
+        Some(value) => Box::pin(key.scope(value, run)),
+        None => run,
+    })
+}
+
+///
+/// A scope running in the tracing span current where this is called
+#[cfg(feature = "tracing")]
+pub fn propagate_span() -> Box<RunScope> {
+    let span = tracing::Span::current();
+    Box::new(move |run| Box::pin(run.instrument(span.clone())))
+}
+
+///
+/// The run scopes of the jobs of a scheduler, and those given to the next run of jobs when they
+/// were triggered
+#[derive(Clone, Default)]
+pub struct RunScopes {
+    pub per_job: Arc<RwLock<HashMap<Uuid, Vec<Arc<RunScope>>>>>,
+    pub triggered: Arc<RwLock<HashMap<Uuid, Vec<Arc<RunScope>>>>>,
+}
+
+impl RunScopes {
+    ///
+    /// The scopes of the next run of a job, those it was triggered with coming first so that they
+    /// are the innermost
+    pub async fn for_run(&self, job_id: Uuid) -> Vec<Arc<RunScope>> {
+        let mut scopes = {
+            let mut w = self.triggered.write().await;
+            w.remove(&job_id).unwrap_or_default()
+        };
+        let r = self.per_job.read().await;
+        if let Some(per_job) = r.get(&job_id) {
+            scopes.extend(per_job.iter().cloned());
+        }
+        scopes
+    }
+
+    pub async fn remove(&self, job_id: &Uuid) {
+        {
+            let mut w = self.per_job.write().await;
+            w.remove(job_id);
+        }
+        let mut w = self.triggered.write().await;
+        w.remove(job_id);
+    }
+}
@@ -1,23 +1,64 @@
+use crate::budget::{Budget, BudgetState};
+use crate::clock::{self, ClockWatch};
 use crate::context::Context;
+use crate::dispatch::{self, Dispatch};
+use crate::drift::DriftStats;
+use crate::internal_error::{InternalErrorKind, InternalErrors};
 #[cfg(not(feature = "has_bytes"))]
-use crate::job::job_data::{JobState, JobType};
+use crate::job::job_data::{JobState, JobType, MaintenanceWindowPolicy, MisfirePolicy};
 #[cfg(feature = "has_bytes")]
-use crate::job::job_data_prost::{JobState, JobType};
-use crate::JobSchedulerError;
-use chrono::Utc;
+use crate::job::job_data_prost::{JobState, JobType, MaintenanceWindowPolicy, MisfirePolicy};
+use crate::job::RunningExecutions;
+use crate::leader::LeaderElection;
+use crate::maintenance_window::MaintenanceWindow;
+use crate::payload::PayloadGenerator;
+use crate::redrive::{DeadLetterReason, DeadLetters};
+use crate::reschedule_hook::RescheduleHooks;
+use crate::run_condition::RunConditions;
+use crate::run_lock::{run_id, RunLock};
+use crate::store::MetaDataStorage;
+use crate::{JobAndNextTick, JobSchedulerError, JobStoredData};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::oneshot::{Receiver, Sender};
-use tokio::sync::RwLock;
-use tracing::error;
+use tokio::sync::{broadcast, RwLock};
 use uuid::Uuid;
 
+/// How often the scheduler checks for due jobs
+const TICK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How many runs are fired one after another before the task firing them yields to the runtime
+const DISPATCH_CHUNK: usize = 64;
+
+/// How late a due run may be, beyond the lag of the tick loop, before it counts as misfired,
+/// like after the scheduler was down or its host suspended
+const MISFIRE_THRESHOLD_SECONDS: i64 = 60;
+
+/// A run lock shared by the tick loop and the runs it fires, with the ttl of its locks
+type SharedRunLock = (
+    Arc<tokio::sync::Mutex<Box<dyn RunLock + Send + Sync>>>,
+    Duration,
+);
+
 pub struct Scheduler {
     pub shutdown: Arc<RwLock<bool>>,
+    /// Whether the tick loop holds off firing runs, until the scheduler is resumed
+    pub paused: Arc<RwLock<bool>>,
     pub start_tx: Arc<RwLock<Option<Sender<bool>>>>,
     pub start_rx: Arc<RwLock<Option<Receiver<bool>>>>,
     pub ticking: Arc<RwLock<bool>>,
     pub inited: bool,
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    pub budget: Option<Budget>,
+    /// The most runs fired on one tick, the other due runs being fired on the next ticks
+    pub dispatch_budget: Option<usize>,
+    /// Decides whether this scheduler fires the due jobs, when it shares its store with others
+    pub leader_election: Option<Arc<tokio::sync::Mutex<Box<dyn LeaderElection + Send + Sync>>>>,
+    /// Locks every due run before firing it, held for the duration, when other schedulers
+    /// sharing the store fire the same jobs
+    pub run_lock: Option<SharedRunLock>,
 }
 
 impl Default for Scheduler {
@@ -25,10 +66,16 @@ impl Default for Scheduler {
         let (ticker_tx, ticker_rx) = tokio::sync::oneshot::channel();
         Self {
             shutdown: Arc::new(RwLock::new(false)),
+            paused: Arc::new(RwLock::new(false)),
             inited: false,
             start_tx: Arc::new(RwLock::new(Some(ticker_tx))),
             start_rx: Arc::new(RwLock::new(Some(ticker_rx))),
             ticking: Arc::new(RwLock::new(false)),
+            maintenance_windows: vec![],
+            budget: None,
+            dispatch_budget: None,
+            leader_election: None,
+            run_lock: None,
         }
     }
 }
@@ -43,7 +90,37 @@ impl Scheduler {
         let notify_tx = context.notify_tx.clone();
         let job_delete_tx = context.job_delete_tx.clone();
         let shutdown = self.shutdown.clone();
+        let paused = self.paused.clone();
         let metadata_storage = context.metadata_storage.clone();
+        let pending_approvals = context.pending_approvals.clone();
+        let reschedule_hooks = context.reschedule_hooks.clone();
+        let group = context.group.clone();
+        let maintenance_windows = self.maintenance_windows.clone();
+        let budget = self.budget;
+        let dispatch_budget = self.dispatch_budget;
+        let leader_election = self.leader_election.clone();
+        let sequential_tags = Arc::new(context.sequential_tags.clone());
+        let internal_errors = context.internal_errors.clone();
+        let search_horizon = context.search_horizon;
+        let firing = Firing {
+            storage: metadata_storage.clone(),
+            notify_tx: notify_tx.clone(),
+            job_activation_tx,
+            dispatch_tx: context.dispatch_tx.clone(),
+            pending_approvals,
+            reschedule_hooks: reschedule_hooks.clone(),
+            run_conditions: context.run_conditions.clone(),
+            payload_generators: context.payload_generators.clone(),
+            scheduled_times: context.scheduled_times.clone(),
+            dead_letters: context.dead_letters.clone(),
+            drift: context.drift.clone(),
+            budgeted: budget.is_some(),
+            sequential_tags: sequential_tags.clone(),
+            internal_errors: internal_errors.clone(),
+            run_lock: self.run_lock.clone(),
+            running_executions: context.running_executions.clone(),
+            search_horizon: context.search_horizon,
+        };
 
         self.inited = true;
 
@@ -56,7 +133,9 @@ impl Scheduler {
         };
 
         let ticking = self.ticking.clone();
-        tokio::spawn(async move {
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!("scheduler.tick_loop", group = %group);
+        let tick_loop = async move {
             let is_ticking = {
                 let ticking = ticking.read().await;
                 *ticking
@@ -64,7 +143,7 @@ impl Scheduler {
             if !is_ticking {
                 if let Some(start_rx) = start_rx {
                     if let Err(e) = start_rx.await {
-                        error!(?e, "Could not subscribe to ticker starter");
+                        error!(error = e; "Could not subscribe to ticker starter");
                         return;
                     }
                 }
@@ -85,46 +164,155 @@ impl Scheduler {
                     }
                 }
             }
+            // Runs deferred until the maintenance window they fell in is over
+            let mut deferred = HashSet::new();
+            // Runs of warm jobs waiting for their instant, by job and tick
+            let mut warming = HashSet::new();
+            let mut budget_state = BudgetState::default();
+            // Whether this scheduler was the leader on the last tick
+            let mut leading = false;
+            // The task firing the due runs of an earlier tick, until they are all fired
+            let mut dispatching: Option<tokio::task::JoinHandle<()>> = None;
+            let mut clock = ClockWatch::new();
+            // How far the wall clock was stepped, in seconds, not yet followed by the monotonic jobs
+            let mut clock_step = 0;
             'next_tick: loop {
                 let shutdown = {
                     let r = shutdown.read().await;
                     *r
                 };
                 if shutdown {
+                    Scheduler::step_down(&leader_election, &mut leading, &internal_errors).await;
                     break 'next_tick;
                 }
-                tokio::time::sleep(Duration::from_millis(500)).await;
-                let now = Utc::now();
+                tokio::time::sleep(TICK_INTERVAL).await;
+                clock_step += clock.stepped().unwrap_or(0);
+                let paused = {
+                    let r = paused.read().await;
+                    *r
+                };
+                if paused {
+                    // Resuming moves the due jobs on from now anyway
+                    clock_step = 0;
+                    // Another scheduler fires the jobs meanwhile
+                    Scheduler::step_down(&leader_election, &mut leading, &internal_errors).await;
+                    continue 'next_tick;
+                }
+                if let Some(leader_election) = leader_election.as_ref() {
+                    let acquired = {
+                        let mut w = leader_election.lock().await;
+                        w.acquire().await
+                    };
+                    let leader = match acquired {
+                        Ok(leader) => leader,
+                        Err(e) => {
+                            internal_errors
+                                .report(
+                                    InternalErrorKind::LeaderElection,
+                                    None,
+                                    "Could not acquire the leadership",
+                                    Some(e),
+                                )
+                                .await;
+                            false
+                        }
+                    };
+                    if leader != leading {
+                        if leader {
+                            info!("Became the leader, firing the due jobs");
+                        } else {
+                            info!("Lost the leadership, another scheduler fires the due jobs");
+                        }
+                        leading = leader;
+                    }
+                    if !leader {
+                        clock_step = 0;
+                        continue 'next_tick;
+                    }
+                }
+                let now = clock::now();
+                let in_maintenance_window = maintenance_windows.iter().any(|w| w.contains(&now));
+                if !in_maintenance_window {
+                    deferred.clear();
+                }
                 let next_ticks = {
                     let mut w = metadata_storage.write().await;
                     w.list_next_ticks().await
                 };
                 if let Err(e) = next_ticks {
-                    error!("Error with listing next ticks {:?}", e);
+                    internal_errors
+                        .report(
+                            InternalErrorKind::Tick,
+                            None,
+                            "Error with listing next ticks",
+                            Some(e),
+                        )
+                        .await;
                     continue 'next_tick;
                 }
                 let mut next_ticks = next_ticks.unwrap();
+                next_ticks.retain(|n| n.group == group);
+                Scheduler::listed_once(&mut next_ticks);
+                if clock_step != 0 {
+                    let followed = {
+                        let mut w = metadata_storage.write().await;
+                        clock::follow_step(&mut w, &mut next_ticks, clock_step).await
+                    };
+                    // Trying again could move the jobs moved before an error twice
+                    let step = std::mem::take(&mut clock_step);
+                    match followed {
+                        Ok(followed) if followed.is_empty() => {}
+                        Ok(followed) => {
+                            info!(step = step, jobs = followed.len(); "Wall clock stepped, moved the ticks of the monotonic jobs");
+                        }
+                        Err(e) => {
+                            internal_errors
+                                .report(
+                                    InternalErrorKind::Tick,
+                                    None,
+                                    "Could not move the ticks of the monotonic jobs",
+                                    Some(e),
+                                )
+                                .await;
+                        }
+                    }
+                }
+                #[cfg(feature = "metrics")]
+                crate::metrics::jobs_registered(&group, next_ticks.len());
+                let waiting = {
+                    let r = firing.run_conditions.waiting.read().await;
+                    r.clone()
+                };
+                // Jobs run after another job wait without a next tick for it to succeed
                 let to_be_deleted = next_ticks.iter().filter_map(|v| {
                     v.id.as_ref()?;
-                    if v.next_tick == 0 {
+                    if v.next_tick == 0 && v.job_type != JobType::AfterJob as i32 {
                         let id: Uuid = v.id.as_ref().unwrap().into();
-                        Some(id)
+                        Some(id).filter(|id| !waiting.contains(id))
                     } else {
                         None
                     }
                 });
                 for uuid in to_be_deleted {
                     let tx = job_delete_tx.clone();
+                    let internal_errors = internal_errors.clone();
                     tokio::spawn(async move {
                         if let Err(e) = tx.send(uuid) {
-                            error!("Error sending deletion {:?}", e);
+                            internal_errors
+                                .report(
+                                    InternalErrorKind::Channel,
+                                    Some(uuid),
+                                    format!("Error sending deletion {:?}", e),
+                                    None,
+                                )
+                                .await;
                         }
                     });
                 }
 
                 next_ticks.retain(|n| n.next_tick != 0);
 
-                let must_runs = next_ticks.iter().filter_map(|n| {
+                let mut must_runs = next_ticks.iter().filter_map(|n| {
                     let next_tick = n.next_tick_utc();
                     let last_tick = n.last_tick_utc();
                     let job_type: JobType = JobType::from_i32(n.job_type).unwrap();
@@ -145,6 +333,11 @@ impl Scheduler {
                             matches!(now_to_next, std::cmp::Ordering::Greater)
                                 || matches!(now_to_next, std::cmp::Ordering::Equal)
                         }
+                        (None, Some(next_tick), JobType::AfterJob) => {
+                            let now_to_next = now.cmp(next_tick);
+                            matches!(now_to_next, std::cmp::Ordering::Greater)
+                                || matches!(now_to_next, std::cmp::Ordering::Equal)
+                        }
                         (Some(last_tick), Some(next_tick), _) => {
                             let now_to_next = now.cmp(next_tick);
                             let last_to_next = last_tick.cmp(next_tick);
@@ -164,66 +357,390 @@ impl Scheduler {
                     }
                 });
 
-                for uuid in must_runs {
-                    {
-                        let tx = notify_tx.clone();
-                        tokio::spawn(async move {
-                            if let Err(e) = tx.send((uuid, JobState::Scheduled)) {
-                                error!("Error sending notification activation {:?}", e);
-                            }
-                        });
-                    }
-                    {
-                        let tx = job_activation_tx.clone();
-                        tokio::spawn(async move {
-                            if let Err(e) = tx.send(uuid) {
-                                error!("Error sending job activation tx {:?}", e);
+                if in_maintenance_window {
+                    let mut w = metadata_storage.write().await;
+                    for uuid in must_runs.by_ref() {
+                        if deferred.contains(&uuid) {
+                            continue;
+                        }
+                        let job = match w.get(uuid).await {
+                            Ok(Some(job)) => job,
+                            other => {
+                                internal_errors
+                                    .report(
+                                        InternalErrorKind::Storage,
+                                        Some(uuid),
+                                        "Could not get job metadata",
+                                        other.err(),
+                                    )
+                                    .await;
+                                continue;
                             }
-                        });
-                    }
-
-                    let storage = metadata_storage.clone();
-                    tokio::spawn(async move {
-                        let mut w = storage.write().await;
-                        let job = w.get(uuid).await;
-
-                        let next_and_last_tick = match job {
-                            Ok(Some(job)) => {
-                                let job_type: JobType = JobType::from_i32(job.job_type).unwrap();
-                                let schedule = job.schedule();
-                                let repeated_every = job.repeated_every();
-                                let next_tick = job.next_tick_utc();
-                                let next_tick = match job_type {
-                                    JobType::Cron => schedule.and_then(|s| s.after(&now).next()),
-                                    JobType::OneShot => None,
-                                    JobType::Repeated => repeated_every.and_then(|r| {
-                                        next_tick.and_then(|nt| {
-                                            nt.checked_add_signed(chrono::Duration::seconds(
-                                                r as i64,
-                                            ))
-                                        })
-                                    }),
-                                };
-                                let last_tick = Some(now);
-                                Some((next_tick, last_tick))
+                        };
+                        let policy =
+                            MaintenanceWindowPolicy::from_i32(job.maintenance_window_policy)
+                                .unwrap_or(MaintenanceWindowPolicy::Defer);
+                        let state = match policy {
+                            MaintenanceWindowPolicy::Defer => {
+                                deferred.insert(uuid);
+                                JobState::Deferred
                             }
-                            _ => {
-                                error!("Could not get job metadata");
-                                None
+                            MaintenanceWindowPolicy::Cancel => {
+                                let next_tick = Scheduler::next_tick(&job, &now, search_horizon);
+                                let next_tick = Scheduler::within_horizon(
+                                    uuid,
+                                    next_tick,
+                                    &now,
+                                    search_horizon,
+                                );
+                                let next_tick = reschedule_hooks.apply(&job, next_tick).await;
+                                if let Err(e) =
+                                    w.set_next_and_last_tick(uuid, next_tick, Some(now)).await
+                                {
+                                    internal_errors
+                                        .report(
+                                            InternalErrorKind::Storage,
+                                            Some(uuid),
+                                            "Could not set next and last tick",
+                                            Some(e),
+                                        )
+                                        .await;
+                                }
+                                JobState::Skipped
                             }
                         };
+                        firing.notify(uuid, state).await;
+                    }
+                    continue 'next_tick;
+                }
 
-                        if let Some((next_tick, last_tick)) = next_and_last_tick {
-                            if let Err(e) =
-                                w.set_next_and_last_tick(uuid, next_tick, last_tick).await
-                            {
-                                error!("Could not set next and last tick {:?}", e);
+                let mut must_runs = must_runs.collect::<Vec<_>>();
+                if let Some(budget) = budget.as_ref() {
+                    let mut runs = vec![];
+                    let mut last_ticks = HashMap::new();
+                    {
+                        let mut w = metadata_storage.write().await;
+                        for uuid in must_runs {
+                            let (cost, priority) = match w.get(uuid).await {
+                                Ok(Some(job)) => {
+                                    last_ticks.insert(uuid, job.last_tick_utc());
+                                    (job.cost, job.priority)
+                                }
+                                _ => (0, 0),
+                            };
+                            runs.push((uuid, cost, priority));
+                        }
+                    }
+                    let (fire, over_budget) = budget_state.charge(budget, &now, runs);
+                    // The deferred runs are due again once the budget renews, and not on every
+                    // tick until then
+                    let renewal = Some(budget.window_end(&now));
+                    for uuid in over_budget {
+                        let last_tick = last_ticks.get(&uuid).copied().flatten();
+                        let deferred = {
+                            let mut w = metadata_storage.write().await;
+                            w.set_next_and_last_tick(uuid, renewal, last_tick).await
+                        };
+                        if let Err(e) = deferred {
+                            internal_errors
+                                .report(
+                                    InternalErrorKind::Storage,
+                                    Some(uuid),
+                                    "Could not defer the run to the next budget window",
+                                    Some(e),
+                                )
+                                .await;
+                            continue;
+                        }
+                        firing.notify(uuid, JobState::Deferred).await;
+                    }
+                    must_runs = fire;
+                }
+                // Runs still being fired from an earlier tick would be fired twice otherwise, the
+                // runs due meanwhile are fired once they are done
+                let dispatched = dispatching.as_ref().is_none_or(|d| d.is_finished());
+                if !must_runs.is_empty() && dispatched {
+                    let jobs = {
+                        let mut w = metadata_storage.write().await;
+                        w.get_many(&must_runs).await
+                    };
+                    let mut dispatches = match jobs {
+                        Ok(jobs) => {
+                            let mut due = Vec::with_capacity(jobs.len());
+                            for job in jobs {
+                                if !firing.waits_for_run(&job).await {
+                                    due.push(job);
+                                }
                             }
+                            dispatch::order(due, &now)
                         }
-                    });
+                        Err(e) => {
+                            internal_errors
+                                .report(
+                                    InternalErrorKind::Storage,
+                                    None,
+                                    "Could not get the metadata of the due jobs",
+                                    Some(e),
+                                )
+                                .await;
+                            continue 'next_tick;
+                        }
+                    };
+                    if let Some(dispatch_budget) = dispatch_budget {
+                        if dispatches.len() > dispatch_budget {
+                            debug!(due = dispatches.len(), fired = dispatch_budget; "Over the dispatch budget, firing the other due runs on the next ticks");
+                            dispatches.truncate(dispatch_budget);
+                        }
+                    }
+                    // Fired one by one so they are activated, and queued, in the order they were
+                    // dispatched
+                    let firing = firing.clone();
+                    dispatching = Some(tokio::spawn(async move {
+                        for (i, dispatch) in dispatches.into_iter().enumerate() {
+                            // A catch-up of thousands of runs leaves the runtime to the others
+                            if i > 0 && i % DISPATCH_CHUNK == 0 {
+                                tokio::task::yield_now().await;
+                            }
+                            let uuid = dispatch.job_id;
+                            // Nobody may be listening
+                            let _ = firing.dispatch_tx.send(dispatch);
+                            firing.clone().fire(uuid, now, false).await;
+                        }
+                    }));
                 }
+
+                // Warm jobs due before the next pass are prepared now and fired by a task waiting
+                // for their exact instant
+                let now_ts = now.timestamp() as u64;
+                warming.retain(|(_, tick)| *tick >= now_ts);
+                let soon = now + chrono::Duration::milliseconds(TICK_INTERVAL.as_millis() as i64);
+                let upcoming = next_ticks
+                    .iter()
+                    .filter(|n| {
+                        n.next_tick_utc().is_some_and(|due| {
+                            due > now
+                                && due <= soon
+                                && !maintenance_windows.iter().any(|w| w.contains(&due))
+                        })
+                    })
+                    .filter_map(|n| n.id.as_ref().map(|id| (id.into(), n.next_tick)))
+                    .filter(|warm| !warming.contains(warm))
+                    .map(|(uuid, _)| uuid)
+                    .collect::<Vec<Uuid>>();
+                if upcoming.is_empty() {
+                    continue 'next_tick;
+                }
+                // Only the jobs set to warm start, the others are fired on the pass they are due
+                // on, in the order of the dispatch
+                let jobs = {
+                    let mut w = metadata_storage.write().await;
+                    w.get_many(&upcoming).await
+                };
+                let jobs = match jobs {
+                    Ok(jobs) => jobs,
+                    Err(e) => {
+                        internal_errors
+                            .report(
+                                InternalErrorKind::Storage,
+                                None,
+                                "Could not get the metadata of the warm jobs",
+                                Some(e),
+                            )
+                            .await;
+                        continue 'next_tick;
+                    }
+                };
+                for job in jobs.iter().filter(|job| firing.fires_warm(job)) {
+                    let (uuid, due) = match (job.id.as_ref(), job.next_tick_utc()) {
+                        (Some(id), Some(due)) => (id.into(), due),
+                        _ => continue,
+                    };
+                    if warming.insert((uuid, job.next_tick)) {
+                        tokio::spawn(firing.clone().fire(uuid, due, true));
+                    }
+                }
+            }
+        };
+        #[cfg(feature = "tracing")]
+        let tick_loop = tracing::Instrument::instrument(tick_loop, span);
+        tokio::spawn(tick_loop);
+    }
+
+    ///
+    /// The tick following a run of the job at `now`, at least the min interval of the job after
+    /// the tick that ran, a cron tick looked for no further than `horizon`
+    pub(crate) fn next_tick(
+        job: &JobStoredData,
+        now: &DateTime<Utc>,
+        horizon: chrono::Duration,
+    ) -> Option<DateTime<Utc>> {
+        let after = job
+            .next_tick_utc()
+            .map(|due| job.spaced_after(&due))
+            .filter(|after| after > now)
+            .unwrap_or(*now);
+        match JobType::from_i32(job.job_type)? {
+            JobType::Cron => job.next_cron_tick_within(&after, horizon),
+            JobType::OneShot => job.instant_after(&after),
+            JobType::AfterJob => None,
+            JobType::Repeated => job.repeated_every().and_then(|r| {
+                let due = job.next_tick_utc()?;
+                // The intervals that passed since, so a job behind by several keeps its phase
+                let behind = ((after - due).num_seconds().max(0) as u64)
+                    .checked_div(r)
+                    .unwrap_or(0);
+                due.checked_add_signed(chrono::Duration::seconds(((behind + 1) * r) as i64))
+            }),
+        }
+    }
+
+    ///
+    /// The tick of a job following `tick`, even when that is before now, a cron tick looked for
+    /// no further than `horizon`
+    pub(crate) fn tick_after(
+        job: &JobStoredData,
+        tick: &DateTime<Utc>,
+        horizon: chrono::Duration,
+    ) -> Option<DateTime<Utc>> {
+        let after = job.spaced_after(tick);
+        match JobType::from_i32(job.job_type)? {
+            JobType::Cron => job.next_cron_tick_within(&after, horizon),
+            JobType::OneShot => job.instant_after(&after),
+            JobType::AfterJob => None,
+            JobType::Repeated => job.repeated_every().and_then(|r| {
+                let next = tick.checked_add_signed(chrono::Duration::seconds(r as i64))?;
+                if next > after || r == 0 {
+                    return Some(next);
+                }
+                // The intervals ending sooner than the min interval are skipped
+                let skipped = (after - next).num_seconds() as u64 / r + 1;
+                next.checked_add_signed(chrono::Duration::seconds((skipped * r) as i64))
+            }),
+        }
+    }
+
+    ///
+    /// Keep the first of the next ticks listed for each job, so a job listed twice, e.g. by a
+    /// store merging the lists of several others, is fired once
+    pub(crate) fn listed_once(next_ticks: &mut Vec<JobAndNextTick>) {
+        let mut listed = HashSet::new();
+        next_ticks.retain(|n| n.id.as_ref().is_none_or(|id| listed.insert(Uuid::from(id))));
+    }
+
+    ///
+    /// The next tick of a job, or `None` when it is further than `horizon` from `now`, so a job
+    /// that would only run again decades from now is taken as running no more
+    pub(crate) fn within_horizon(
+        job_id: Uuid,
+        next_tick: Option<DateTime<Utc>>,
+        now: &DateTime<Utc>,
+        horizon: chrono::Duration,
+    ) -> Option<DateTime<Utc>> {
+        match next_tick {
+            Some(next_tick) if next_tick.signed_duration_since(*now) > horizon => {
+                warn!(
+                    job_id = job_id;
+                    "No upcoming tick within the search horizon, the next would be at {}",
+                    next_tick
+                );
+                None
             }
-        });
+            next_tick => next_tick,
+        }
+    }
+
+    ///
+    /// Whether the due run of a job is late by more than the misfire threshold at `now`
+    pub(crate) fn misfired(job: &JobStoredData, now: &DateTime<Utc>) -> bool {
+        job.next_tick_utc()
+            .is_some_and(|due| (*now - due).num_seconds() > MISFIRE_THRESHOLD_SECONDS)
+    }
+
+    ///
+    /// The next and last tick of a job fired at `now`, and whether its due run is skipped,
+    /// following its misfire policy. Running all the missed runs steps through them one tick at
+    /// a time, keeping the last tick at the one that ran so the tick loop fires the next.
+    pub(crate) fn after_misfire(
+        job: &JobStoredData,
+        now: &DateTime<Utc>,
+        horizon: chrono::Duration,
+    ) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>, bool) {
+        let policy = MisfirePolicy::from_i32(job.misfire_policy).unwrap_or(MisfirePolicy::RunOnce);
+        match (policy, job.next_tick_utc()) {
+            (MisfirePolicy::RunAll, Some(due)) => {
+                let next_tick = Scheduler::tick_after(job, &due, horizon);
+                let last_tick = match next_tick {
+                    Some(next_tick) if next_tick <= *now => due,
+                    _ => *now,
+                };
+                (next_tick, Some(last_tick), false)
+            }
+            (policy, _) => (
+                Scheduler::next_tick(job, now, horizon),
+                Some(*now),
+                policy == MisfirePolicy::Skip && Scheduler::misfired(job, now),
+            ),
+        }
+    }
+
+    ///
+    /// Whether the due run of a job can no longer start within its starting deadline
+    fn missed_deadline(job: &JobStoredData, now: &DateTime<Utc>) -> bool {
+        match (job.starting_deadline_seconds, job.next_tick_utc()) {
+            (Some(deadline), Some(due)) => (*now - due).num_seconds() > deadline as i64,
+            _ => false,
+        }
+    }
+
+    ///
+    /// The next tick of a job resumed at `now` after a pause, skipping the runs that fell due
+    /// while it was paused. `None` when the job keeps its next tick, like a one-shot job or a job
+    /// that wasn't due yet.
+    pub(crate) fn resumed_tick(
+        job: &JobStoredData,
+        now: &DateTime<Utc>,
+    ) -> Option<Option<DateTime<Utc>>> {
+        if job.stopped || job.next_tick_utc().is_none_or(|next_tick| next_tick > *now) {
+            return None;
+        }
+        match JobType::from_i32(job.job_type)? {
+            JobType::Cron => Some(job.next_cron_tick(now)),
+            JobType::OneShot | JobType::AfterJob => None,
+            JobType::Repeated => job
+                .repeated_every()
+                .map(|r| now.checked_add_signed(chrono::Duration::seconds(r as i64))),
+        }
+    }
+
+    ///
+    /// Release the leadership, when this scheduler is the leader
+    async fn step_down(
+        leader_election: &Option<Arc<tokio::sync::Mutex<Box<dyn LeaderElection + Send + Sync>>>>,
+        leading: &mut bool,
+        internal_errors: &InternalErrors,
+    ) {
+        let leader_election = match leader_election.as_ref() {
+            Some(leader_election) if *leading => leader_election,
+            _ => return,
+        };
+        *leading = false;
+        let released = {
+            let mut w = leader_election.lock().await;
+            w.release().await
+        };
+        match released {
+            Ok(()) => info!("Released the leadership"),
+            Err(e) => {
+                internal_errors
+                    .report(
+                        InternalErrorKind::LeaderElection,
+                        None,
+                        "Could not release the leadership",
+                        Some(e),
+                    )
+                    .await
+            }
+        }
     }
 
     pub async fn shutdown(&mut self) {
@@ -252,7 +769,7 @@ impl Scheduler {
 
             if let Some(tx) = tx {
                 if let Err(e) = tx.send(true) {
-                    error!(?e, "Start ticker send error");
+                    error!(error = e; "Start ticker send error");
                 }
             }
 
@@ -260,3 +777,821 @@ impl Scheduler {
         }
     }
 }
+
+/// How a firing moves a job on from its due run
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Moved {
+    /// The run missed its starting deadline and is counted as missed
+    Missed,
+    /// The run is skipped or waits on approval
+    Held,
+    /// The run goes on, its run condition checked before it starts
+    Run,
+}
+
+/// What the tick loop needs to fire a due run of a job
+#[derive(Clone)]
+struct Firing {
+    storage: Arc<RwLock<Box<dyn MetaDataStorage + Send + Sync>>>,
+    notify_tx: broadcast::Sender<(Uuid, JobState)>,
+    job_activation_tx: broadcast::Sender<Uuid>,
+    dispatch_tx: broadcast::Sender<Dispatch>,
+    pending_approvals: Arc<RwLock<HashMap<Uuid, DateTime<Utc>>>>,
+    reschedule_hooks: RescheduleHooks,
+    run_conditions: RunConditions,
+    payload_generators: Arc<RwLock<HashMap<Uuid, Arc<PayloadGenerator>>>>,
+    scheduled_times: Arc<RwLock<HashMap<Uuid, DateTime<Utc>>>>,
+    dead_letters: DeadLetters,
+    drift: Arc<RwLock<HashMap<Uuid, DriftStats>>>,
+    /// Whether runs are charged to a budget, which is done for runs fired on a pass only
+    budgeted: bool,
+    /// The tags whose jobs are queued, which are fired on a pass only
+    sequential_tags: Arc<Vec<String>>,
+    internal_errors: InternalErrors,
+    run_lock: Option<SharedRunLock>,
+    running_executions: RunningExecutions,
+    search_horizon: chrono::Duration,
+}
+
+impl Firing {
+    fn is_queued(sequential_tags: &[String], job: &JobStoredData) -> bool {
+        job.tags.iter().any(|t| sequential_tags.contains(t))
+    }
+
+    ///
+    /// Whether a run of the job is fired at its exact instant instead of on a pass. Costly runs
+    /// when there is a budget and queued runs are fired on a pass only.
+    fn fires_warm(&self, job: &JobStoredData) -> bool {
+        job.warm_start
+            && !(self.budgeted && job.cost > 0)
+            && !Firing::is_queued(&self.sequential_tags, job)
+    }
+
+    ///
+    /// Whether a job waits for its running run to end before it comes due again, as a job with a
+    /// fixed delay does
+    async fn waits_for_run(&self, job: &JobStoredData) -> bool {
+        let job_id: Uuid = match job.id.as_ref() {
+            Some(id) if job.fixed_delay() => id.into(),
+            _ => return false,
+        };
+        let r = self.running_executions.read().await;
+        r.values().any(|(execution, _)| execution.job_id == job_id)
+    }
+
+    async fn notify(&self, uuid: Uuid, state: JobState) {
+        if let Err(e) = self.notify_tx.send((uuid, state)) {
+            self.internal_errors
+                .report(
+                    InternalErrorKind::Channel,
+                    Some(uuid),
+                    format!("Error sending notification {:?}", e),
+                    None,
+                )
+                .await;
+        }
+    }
+
+    ///
+    /// Whether this scheduler may fire the run of a job due at `due`, which is when there is no
+    /// run lock or it took the lock on the run. A job not due at `now` anymore was moved past
+    /// the run by another scheduler.
+    async fn lock_run(&self, uuid: Uuid, due: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+        let (run_lock, ttl) = match self.run_lock.as_ref() {
+            Some(run_lock) => run_lock,
+            None => return true,
+        };
+        let due = match due {
+            Some(due) if due <= now => due,
+            _ => return false,
+        };
+        let acquired = {
+            let mut w = run_lock.lock().await;
+            w.try_acquire_run_lock(uuid, run_id(uuid, due), *ttl).await
+        };
+        match acquired {
+            Ok(acquired) => acquired,
+            Err(e) => {
+                self.internal_errors
+                    .report(
+                        InternalErrorKind::RunLock,
+                        Some(uuid),
+                        "Could not take the run lock",
+                        Some(e),
+                    )
+                    .await;
+                false
+            }
+        }
+    }
+
+    ///
+    /// Whether a due job should be skipped because its own previous run, or the run of the
+    /// upstream job it depends on, failed
+    async fn must_skip(&self, job: &JobStoredData) -> bool {
+        if job.skip_if_last_failed && job.last_run_failed {
+            return true;
+        }
+        let upstream: Uuid = match job.skip_if_upstream_failed.as_ref() {
+            Some(upstream) => upstream.into(),
+            None => return false,
+        };
+        let got = {
+            let mut w = self.storage.write().await;
+            w.get(upstream).await
+        };
+        match got {
+            Ok(Some(upstream)) => upstream.last_run_failed,
+            Ok(None) => false,
+            Err(e) => {
+                self.internal_errors
+                    .report(
+                        InternalErrorKind::Storage,
+                        Some(upstream),
+                        "Could not get the upstream job metadata",
+                        Some(e),
+                    )
+                    .await;
+                false
+            }
+        }
+    }
+
+    ///
+    /// Move a job on from its run due at `due`, unless another firing of the same run moved it on
+    /// meanwhile. Whether this firing moved it, and so goes on with the run.
+    async fn move_on(
+        &self,
+        uuid: Uuid,
+        due: Option<DateTime<Utc>>,
+        next_tick: Option<DateTime<Utc>>,
+        last_tick: Option<DateTime<Utc>>,
+        moved: Moved,
+    ) -> bool {
+        let mut w = self.storage.write().await;
+        let mut job = match w.get(uuid).await {
+            Ok(Some(job)) if job.next_tick_utc() == due => job,
+            Ok(_) => return false,
+            other => {
+                self.internal_errors
+                    .report(
+                        InternalErrorKind::Storage,
+                        Some(uuid),
+                        "Could not get job metadata",
+                        other.err(),
+                    )
+                    .await;
+                return false;
+            }
+        };
+        let (stored, message) = match moved {
+            Moved::Missed => {
+                job.missed_count += 1;
+                job.set_next_tick(next_tick);
+                job.set_last_tick(last_tick);
+                (w.add_or_update(job).await, "Could not count missed run")
+            }
+            Moved::Held | Moved::Run => {
+                if moved == Moved::Run {
+                    self.run_conditions.hold(uuid).await;
+                }
+                (
+                    w.set_next_and_last_tick(uuid, next_tick, last_tick).await,
+                    "Could not set next and last tick",
+                )
+            }
+        };
+        if let Err(e) = stored {
+            self.internal_errors
+                .report(InternalErrorKind::Storage, Some(uuid), message, Some(e))
+                .await;
+        }
+        true
+    }
+
+    ///
+    /// Store the payload of the run at `now` with the job, for jobs with a payload generator
+    async fn generate_payload(&self, uuid: Uuid, now: DateTime<Utc>) {
+        let generator = {
+            let r = self.payload_generators.read().await;
+            r.get(&uuid).cloned()
+        };
+        let generator = match generator {
+            Some(generator) => generator,
+            None => return,
+        };
+        let payload = generator(uuid, now).await;
+        let mut w = self.storage.write().await;
+        match w.get(uuid).await {
+            Ok(Some(mut job)) => {
+                job.last_payload = payload;
+                if let Err(e) = w.add_or_update(job).await {
+                    self.internal_errors
+                        .report(
+                            InternalErrorKind::Storage,
+                            Some(uuid),
+                            "Could not store the payload of the run",
+                            Some(e),
+                        )
+                        .await;
+                }
+            }
+            other => {
+                self.internal_errors
+                    .report(
+                        InternalErrorKind::Storage,
+                        Some(uuid),
+                        "Could not get job metadata to store its payload",
+                        other.err(),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    ///
+    /// Move a job past its due run at `now` and run it. A warm run is only fired for jobs set to
+    /// warm start, and waits for `now` after the job was moved on.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "job.fire", level = "debug", skip_all, fields(job_id = %uuid, due = %now, warm))
+    )]
+    async fn fire(self, uuid: Uuid, now: DateTime<Utc>, warm: bool) {
+        // Only read under the lock, the checks below await the run lock, the hooks and the
+        // upstream job, which other parts of the scheduler would be stuck behind otherwise
+        let job = {
+            let mut w = self.storage.write().await;
+            w.get(uuid).await
+        };
+
+        let (skip, requires_approval, due) = match job {
+            Ok(Some(job)) => {
+                if warm && !self.fires_warm(&job) {
+                    return;
+                }
+                if self.waits_for_run(&job).await {
+                    return;
+                }
+                if !self.lock_run(uuid, job.next_tick_utc(), now).await {
+                    return;
+                }
+                let due = job.next_tick_utc();
+                let (next_tick, last_tick, misfire_skip) =
+                    Scheduler::after_misfire(&job, &now, self.search_horizon);
+                let next_tick =
+                    Scheduler::within_horizon(uuid, next_tick, &now, self.search_horizon);
+                let next_tick = self.reschedule_hooks.apply(&job, next_tick).await;
+                if Scheduler::missed_deadline(&job, &clock::now()) {
+                    if !self
+                        .move_on(uuid, due, next_tick, last_tick, Moved::Missed)
+                        .await
+                    {
+                        return;
+                    }
+                    self.dead_letters
+                        .record(uuid, due.unwrap_or(now), DeadLetterReason::Missed)
+                        .await;
+                    (true, false, due)
+                } else {
+                    let skip = misfire_skip || self.must_skip(&job).await;
+                    let moved = if skip || job.requires_approval {
+                        Moved::Held
+                    } else {
+                        Moved::Run
+                    };
+                    if !self.move_on(uuid, due, next_tick, last_tick, moved).await {
+                        return;
+                    }
+                    (skip, job.requires_approval, due)
+                }
+            }
+            other => {
+                self.internal_errors
+                    .report(
+                        InternalErrorKind::Storage,
+                        Some(uuid),
+                        "Could not get job metadata",
+                        other.err(),
+                    )
+                    .await;
+                if warm {
+                    return;
+                }
+                (false, false, None)
+            }
+        };
+
+        if skip {
+            self.notify(uuid, JobState::Skipped).await;
+            return;
+        }
+        if requires_approval {
+            {
+                let mut w = self.pending_approvals.write().await;
+                w.entry(uuid).or_insert(now);
+            }
+            self.notify(uuid, JobState::Pending).await;
+            return;
+        }
+        if warm {
+            if let Ok(wait) = (now - clock::now()).to_std() {
+                tokio::time::sleep(wait).await;
+            }
+        }
+        if !self.run_conditions.allows(uuid).await {
+            self.notify(uuid, JobState::Skipped).await;
+            return;
+        }
+        self.generate_payload(uuid, now).await;
+        {
+            let mut w = self.scheduled_times.write().await;
+            w.insert(uuid, due.unwrap_or(now));
+        }
+        self.notify(uuid, JobState::Scheduled).await;
+        if let Err(e) = self.job_activation_tx.send(uuid) {
+            self.internal_errors
+                .report(
+                    InternalErrorKind::Channel,
+                    Some(uuid),
+                    format!("Error sending job activation tx {:?}", e),
+                    None,
+                )
+                .await;
+        }
+        if let Some(due) = due {
+            let drift = (clock::now() - due).to_std().unwrap_or_default();
+            #[cfg(feature = "metrics")]
+            crate::metrics::tick_lag(uuid, drift);
+            let mut w = self.drift.write().await;
+            w.entry(uuid).or_default().record(drift);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::budget::Budget;
+    use crate::clock;
+    #[cfg(not(feature = "has_bytes"))]
+    use crate::job::job_data::{
+        job_stored_data, CronJob, JobStoredData, JobType, MisfirePolicy, NonCronJob,
+    };
+    #[cfg(feature = "has_bytes")]
+    use crate::job::job_data_prost::{
+        job_stored_data, CronJob, JobStoredData, JobType, MisfirePolicy, NonCronJob,
+    };
+    use crate::run_lock::RunLock;
+    use crate::{Job, JobNotification, JobScheduler, JobSchedulerError};
+    use crate::{MaintenanceWindow, MaintenanceWindowPolicy};
+    use chrono::{DateTime, Datelike, Duration as ChronoDuration, Utc};
+    use std::collections::HashSet;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc::UnboundedSender;
+    use uuid::Uuid;
+
+    /// A run lock taking its time, telling when it is asked for a lock
+    struct SlowRunLock(UnboundedSender<()>);
+
+    impl RunLock for SlowRunLock {
+        fn try_acquire_run_lock(
+            &mut self,
+            _job_id: Uuid,
+            _run_id: Uuid,
+            _ttl: Duration,
+        ) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+            let asked = self.0.clone();
+            Box::pin(async move {
+                let _ = asked.send(());
+                tokio::time::sleep(Duration::from_secs(2)).await;
+                Ok(true)
+            })
+        }
+    }
+
+    /// The metadata store stays free while a due run waits on its run lock
+    #[tokio::test(start_paused = true)]
+    async fn fire_leaves_storage_unlocked() {
+        let (tx, mut asked) = tokio::sync::mpsc::unbounded_channel();
+        let mut sched = JobScheduler::new_builder()
+            .run_lock(Box::new(SlowRunLock(tx)), Duration::from_secs(10))
+            .build()
+            .await
+            .expect("scheduler");
+        let job_id = sched
+            .add(Job::new("* * * * * *", |_, _| {}).expect("job"))
+            .await
+            .expect("add");
+        sched.start().await.expect("start");
+
+        tokio::time::timeout(Duration::from_secs(3), asked.recv())
+            .await
+            .expect("the run lock was never asked for");
+        let next_tick =
+            tokio::time::timeout(Duration::from_millis(500), sched.next_tick_for_job(job_id))
+                .await
+                .expect("the metadata store was locked while taking the run lock");
+        assert!(next_tick.expect("next tick").is_some());
+    }
+
+    /// Warm jobs are fired at their instant, the other jobs stay on the dispatch of the passes
+    #[tokio::test(start_paused = true)]
+    async fn warm_start_only_for_warm_jobs() {
+        let sched = JobScheduler::new().await.expect("scheduler");
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counted = runs.clone();
+        let mut warm = Job::new("* * * * * *", move |_, _| {
+            counted.fetch_add(1, Ordering::SeqCst);
+        })
+        .expect("job");
+        warm.set_warm_start(true).expect("warm start");
+        sched.add(warm).await.expect("add");
+        let plain_id = sched
+            .add(Job::new("* * * * * *", |_, _| {}).expect("job"))
+            .await
+            .expect("add");
+        let mut dispatches = sched.subscribe_dispatches();
+        sched.start().await.expect("start");
+
+        let mut plain_dispatches = 0;
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(3);
+        while let Ok(Ok(dispatch)) = tokio::time::timeout_at(deadline, dispatches.recv()).await {
+            if dispatch.job_id == plain_id {
+                plain_dispatches += 1;
+            }
+        }
+        assert!(plain_dispatches >= 2, "the plain job was not dispatched");
+        assert!(runs.load(Ordering::SeqCst) >= 2, "the warm job did not run");
+    }
+
+    /// The runs of a job are skipped while its own last run failed, or the last run of the job
+    /// upstream of it
+    #[tokio::test(start_paused = true)]
+    async fn skip_after_failures() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let failing = Arc::new(AtomicBool::new(true));
+        let fails = failing.clone();
+        let upstream = Job::new_fallible("0 0 0 1 1 *", move |_, _| {
+            if fails.load(Ordering::SeqCst) {
+                Err("upstream failed")
+            } else {
+                Ok(())
+            }
+        })
+        .expect("job");
+        let upstream_id = sched.add(upstream).await.expect("add");
+
+        let own_runs = Arc::new(AtomicUsize::new(0));
+        let counted = own_runs.clone();
+        let mut own = Job::new_fallible("* * * * * *", move |_, _| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            Err("failed")
+        })
+        .expect("job");
+        own.set_skip_if_last_failed(true).expect("skip");
+        let own_id = sched.add(own).await.expect("add");
+
+        let downstream_runs = Arc::new(AtomicUsize::new(0));
+        let counted = downstream_runs.clone();
+        let mut downstream = Job::new("* * * * * *", move |_, _| {
+            counted.fetch_add(1, Ordering::SeqCst);
+        })
+        .expect("job");
+        downstream
+            .set_skip_if_upstream_failed(Some(upstream_id))
+            .expect("skip");
+        let downstream_id = sched.add(downstream).await.expect("add");
+
+        let mut events = sched.subscribe_events();
+        sched.trigger(&upstream_id).await.expect("trigger");
+        sched.start().await.expect("start");
+        let mut skipped = HashSet::new();
+        tokio::time::timeout(Duration::from_secs(4), async {
+            while skipped.len() < 2 {
+                if let Ok((job_id, JobNotification::Skipped)) = events.recv().await {
+                    skipped.insert(job_id);
+                }
+            }
+        })
+        .await
+        .expect("never skipped");
+        assert!(skipped.contains(&own_id) && skipped.contains(&downstream_id));
+        assert_eq!(own_runs.load(Ordering::SeqCst), 1, "ran after failing");
+        assert_eq!(downstream_runs.load(Ordering::SeqCst), 0);
+
+        // Once the upstream job succeeds the job downstream runs again
+        failing.store(false, Ordering::SeqCst);
+        sched.trigger(&upstream_id).await.expect("trigger");
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert!(downstream_runs.load(Ordering::SeqCst) > 0);
+        assert_eq!(own_runs.load(Ordering::SeqCst), 1, "ran after failing");
+        sched.shutdown().await.expect("shutdown");
+    }
+
+    /// The runs due in a maintenance window are deferred until it is over, or cancelled
+    #[tokio::test(start_paused = true)]
+    async fn maintenance_window_policies() {
+        // The windows are kept to the second
+        let now = DateTime::from_timestamp(clock::now().timestamp(), 0).unwrap();
+        let (start, end) = (
+            now - ChronoDuration::minutes(1),
+            now + ChronoDuration::seconds(3),
+        );
+        let window =
+            MaintenanceWindow::new(start.weekday(), start.time(), end.weekday(), end.time());
+        let mut sched = JobScheduler::new_builder()
+            .maintenance_windows(vec![window])
+            .build()
+            .await
+            .expect("scheduler");
+        let counter = |runs: &Arc<AtomicUsize>| {
+            let runs = runs.clone();
+            move |_, _| {
+                runs.fetch_add(1, Ordering::SeqCst);
+            }
+        };
+        let deferred_runs = Arc::new(AtomicUsize::new(0));
+        let deferred_id = sched
+            .add(Job::new("* * * * * *", counter(&deferred_runs)).expect("job"))
+            .await
+            .expect("add");
+        let cancelled_runs = Arc::new(AtomicUsize::new(0));
+        let mut cancelled = Job::new("* * * * * *", counter(&cancelled_runs)).expect("job");
+        cancelled
+            .set_maintenance_window_policy(MaintenanceWindowPolicy::Cancel)
+            .expect("policy");
+        let cancelled_id = sched.add(cancelled).await.expect("add");
+        let mut events = sched.subscribe_events();
+        sched.start().await.expect("start");
+
+        let mut deferrals = 0;
+        let mut cancellations = 0;
+        while let Ok(Ok((job_id, state))) =
+            tokio::time::timeout_at(to_instant(end), events.recv()).await
+        {
+            match state {
+                JobNotification::Deferred if job_id == deferred_id => deferrals += 1,
+                JobNotification::Skipped if job_id == cancelled_id => cancellations += 1,
+                _ => {}
+            }
+        }
+        assert_eq!(deferrals, 1, "a deferred run is deferred once");
+        assert!(
+            cancellations >= 2,
+            "every run due in the window is cancelled"
+        );
+        assert_eq!(deferred_runs.load(Ordering::SeqCst), 0);
+        assert_eq!(cancelled_runs.load(Ordering::SeqCst), 0);
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(deferred_runs.load(Ordering::SeqCst) > 0, "never ran after");
+        assert!(cancelled_runs.load(Ordering::SeqCst) > 0, "never ran after");
+        sched.shutdown().await.expect("shutdown");
+    }
+
+    /// The instant of the monotonic clock at `at`
+    fn to_instant(at: DateTime<Utc>) -> tokio::time::Instant {
+        let wait = (at - clock::now()).to_std().unwrap_or_default();
+        tokio::time::Instant::now() + wait
+    }
+
+    /// A run over the budget is deferred once, to the renewal of the budget
+    #[tokio::test(start_paused = true)]
+    async fn over_budget_deferred_once() {
+        let budget = Budget::new(1, Duration::from_secs(24 * 3600));
+        let mut sched = JobScheduler::new_builder()
+            .budget(budget)
+            .build()
+            .await
+            .expect("scheduler");
+        let mut job = Job::new("* * * * * *", |_, _| {}).expect("job");
+        job.set_cost(1).expect("cost");
+        let (tx, mut deferrals) = tokio::sync::mpsc::unbounded_channel();
+        job.on_notifications_add(
+            &sched,
+            Box::new(move |_, _, _| {
+                let _ = tx.send(());
+                Box::pin(async {})
+            }),
+            vec![JobNotification::Deferred],
+        )
+        .await
+        .expect("notification");
+        let job_id = sched.add(job).await.expect("add");
+        sched.start().await.expect("start");
+
+        tokio::time::timeout(Duration::from_secs(4), deferrals.recv())
+            .await
+            .expect("the second run was never deferred");
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert!(deferrals.try_recv().is_err(), "deferred more than once");
+        let next_tick = sched.next_tick_for_job(job_id).await.expect("next tick");
+        assert_eq!(next_tick, Some(budget.window_end(&clock::now())));
+    }
+
+    /// The data of a cron job with `id`, due at `next_tick`
+    fn job_data(id: Uuid, next_tick: u64) -> JobStoredData {
+        let mut job = Job::new("0 0 * * * *", |_, _| {}).expect("job");
+        let mut data = job.job_data().expect("data");
+        data.id = Some(id.into());
+        data.next_tick = next_tick;
+        data
+    }
+
+    #[test]
+    fn min_interval() {
+        use crate::scheduler::Scheduler;
+        use chrono::TimeZone;
+
+        // On the hour and five past, but at least half an hour apart
+        let ten = Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap();
+        let mut data = job_data(Uuid::new_v4(), 0);
+        data.job = Some(job_stored_data::Job::CronJob(CronJob {
+            schedule: "0 0,5 * * * *".to_string(),
+            timezone: String::new(),
+            exclusions: vec![],
+            jitter_seconds: 0,
+        }));
+        data.set_next_tick(Some(ten));
+        assert_eq!(
+            Scheduler::tick_after(&data, &ten, crate::leap::default_search_horizon()),
+            Some(ten + chrono::Duration::minutes(5))
+        );
+        data.min_interval_seconds = Some(1800);
+        assert_eq!(data.min_interval(), Some(chrono::Duration::minutes(30)));
+        assert_eq!(
+            Scheduler::tick_after(&data, &ten, crate::leap::default_search_horizon()),
+            Some(ten + chrono::Duration::hours(1))
+        );
+        assert_eq!(
+            Scheduler::next_tick(
+                &data,
+                &(ten + chrono::Duration::milliseconds(300)),
+                crate::leap::default_search_horizon()
+            ),
+            Some(ten + chrono::Duration::hours(1))
+        );
+        assert_eq!(
+            data.upcoming_ticks(3),
+            vec![
+                ten,
+                ten + chrono::Duration::hours(1),
+                ten + chrono::Duration::hours(2)
+            ]
+        );
+
+        // A min interval as long as the schedule's keeps every tick
+        data.min_interval_seconds = Some(300);
+        assert_eq!(
+            Scheduler::tick_after(&data, &ten, crate::leap::default_search_horizon()),
+            Some(ten + chrono::Duration::minutes(5))
+        );
+
+        data.job_type = JobType::Repeated.into();
+        data.job = Some(job_stored_data::Job::NonCronJob(NonCronJob {
+            repeating: true,
+            repeated_every: 600,
+            monotonic: false,
+            fixed_delay: false,
+            instants: vec![],
+        }));
+        data.min_interval_seconds = Some(1500);
+        assert_eq!(
+            Scheduler::tick_after(&data, &ten, crate::leap::default_search_horizon()),
+            Some(ten + chrono::Duration::minutes(30))
+        );
+        assert_eq!(
+            Scheduler::next_tick(&data, &ten, crate::leap::default_search_horizon()),
+            Some(ten + chrono::Duration::minutes(30))
+        );
+        data.min_interval_seconds = None;
+        assert_eq!(
+            Scheduler::tick_after(&data, &ten, crate::leap::default_search_horizon()),
+            Some(ten + chrono::Duration::minutes(10))
+        );
+
+        let job = crate::JobBuilder::new()
+            .with_schedule("0 0,5 * * * *")
+            .with_min_interval(std::time::Duration::from_secs(1800))
+            .with_run_sync(|_, _| {})
+            .build()
+            .expect("job");
+        assert_eq!(
+            job.clone().job_data().expect("data").min_interval_seconds,
+            Some(1800)
+        );
+    }
+
+    #[test]
+    fn misfire_policies() {
+        use crate::scheduler::Scheduler;
+
+        // Ticks are stored in whole seconds
+        let now = chrono::DateTime::from_timestamp(Utc::now().timestamp(), 0).unwrap();
+        let repeated = |policy: MisfirePolicy, late: i64| {
+            let mut data = job_data(
+                Uuid::new_v4(),
+                (now - chrono::Duration::seconds(late)).timestamp() as u64,
+            );
+            data.job_type = JobType::Repeated as i32;
+            data.misfire_policy = policy.into();
+            data.job = Some(job_stored_data::Job::NonCronJob(NonCronJob {
+                repeating: true,
+                repeated_every: 60,
+                monotonic: false,
+                fixed_delay: false,
+                instants: vec![],
+            }));
+            let (next, last, skip) =
+                Scheduler::after_misfire(&data, &now, crate::leap::default_search_horizon());
+            let seconds = |t: Option<chrono::DateTime<Utc>>| t.map(|t| (t - now).num_seconds());
+            (seconds(next), seconds(last), skip)
+        };
+        // A run late by less than the threshold runs, whatever the policy
+        assert_eq!(
+            repeated(MisfirePolicy::Skip, 10),
+            (Some(50), Some(0), false)
+        );
+        // (policy, late by, next tick, last tick, skipped), in seconds from now
+        let matrix = [
+            (MisfirePolicy::RunOnce, 300, Some(60), Some(0), false),
+            (MisfirePolicy::Skip, 300, Some(60), Some(0), true),
+            // One missed run after the other, until caught up
+            (MisfirePolicy::RunAll, 300, Some(-240), Some(-300), false),
+            (MisfirePolicy::RunAll, 60, Some(0), Some(-60), false),
+            (MisfirePolicy::RunAll, 30, Some(30), Some(0), false),
+            // Behind by part of an interval, the job keeps its phase
+            (MisfirePolicy::RunOnce, 330, Some(30), Some(0), false),
+        ];
+        for (policy, late, next, last, skip) in matrix {
+            assert_eq!(
+                repeated(policy, late),
+                (next, last, skip),
+                "{:?} late by {}",
+                policy,
+                late
+            );
+        }
+    }
+
+    /// A job listed twice keeps the first of its next ticks, and is fired once
+    #[test]
+    fn listed_once() {
+        use crate::scheduler::Scheduler;
+        use crate::JobAndNextTick;
+
+        let listed = |id: Uuid, next_tick: u64| JobAndNextTick {
+            id: Some(id.into()),
+            job_type: JobType::Cron as i32,
+            next_tick,
+            last_tick: None,
+            group: String::new(),
+        };
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut next_ticks = vec![
+            listed(first, 10),
+            listed(second, 20),
+            listed(first, 30),
+            listed(second, 20),
+        ];
+        Scheduler::listed_once(&mut next_ticks);
+        assert_eq!(next_ticks, vec![listed(first, 10), listed(second, 20)]);
+    }
+
+    /// The due runs over the dispatch budget of a tick fired in order over the next ticks
+    #[tokio::test(start_paused = true)]
+    async fn dispatch_budget() {
+        let mut sched = JobScheduler::new_builder()
+            .dispatch_budget(2)
+            .build()
+            .await
+            .expect("scheduler");
+        let instant = clock::instant() + Duration::from_secs(2);
+        let mut job_ids = vec![];
+        for name in ["a", "b", "c", "d", "e"] {
+            let mut job = Job::new_one_shot_at_instant(instant, |_, _| {}).expect("job");
+            job.set_name(name).expect("name");
+            let mut data = job.job_data().expect("data");
+            data.next_tick = clock::now().timestamp() as u64 + 2;
+            job.set_job_data(data).expect("job data");
+            job_ids.push(sched.add(job).await.expect("add"));
+        }
+        let mut dispatches = sched.subscribe_dispatches();
+        sched.start().await.expect("start");
+
+        // Two runs a tick, in order, each tick firing among the runs left due
+        let expected = [(0, 5), (1, 5), (0, 3), (1, 3), (0, 1)];
+        for (job_id, (position, of)) in job_ids.iter().zip(expected) {
+            let dispatch = tokio::time::timeout(Duration::from_secs(5), dispatches.recv())
+                .await
+                .expect("dispatched")
+                .expect("dispatch");
+            assert_eq!(dispatch.job_id, *job_id);
+            assert_eq!((dispatch.position, dispatch.of), (position, of));
+        }
+        sched.shutdown().await.expect("shutdown");
+    }
+}
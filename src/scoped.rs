@@ -0,0 +1,97 @@
+use crate::job::JobLocked;
+use crate::job_scheduler::{JobInfo, JobsSchedulerLocked};
+use crate::store::{JobQuery, Page};
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+///
+/// A handle on a scheduler restricted to the jobs carrying a tag, so a subsystem can be handed a
+/// scheduler that only sees and changes its own jobs. Created with `JobScheduler::scoped`.
+#[derive(Clone)]
+pub struct ScopedScheduler {
+    scheduler: JobsSchedulerLocked,
+    tag: String,
+}
+
+impl ScopedScheduler {
+    pub(crate) fn new(scheduler: JobsSchedulerLocked, tag: String) -> Self {
+        Self { scheduler, tag }
+    }
+
+    ///
+    /// The tag of the jobs in scope
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    ///
+    /// Add a job, tagging it with the tag of the scope
+    pub async fn add(&self, mut job: JobLocked) -> Result<Uuid, JobSchedulerError> {
+        let mut data = job.job_data()?;
+        if !data.tags.contains(&self.tag) {
+            data.tags.push(self.tag.clone());
+            job.set_job_data(data)?;
+        }
+        self.scheduler.add(job).await
+    }
+
+    ///
+    /// Remove a job in scope
+    pub async fn remove(&self, job_id: &Uuid) -> Result<(), JobSchedulerError> {
+        self.check_scope(job_id).await?;
+        self.scheduler.remove(job_id).await
+    }
+
+    ///
+    /// Run a job in scope right away, outside of its schedule
+    pub async fn trigger(&self, job_id: &Uuid) -> Result<(), JobSchedulerError> {
+        self.check_scope(job_id).await?;
+        self.scheduler.trigger(job_id).await
+    }
+
+    ///
+    /// Run a job in scope right away as of `scheduled_time`, see `JobScheduler::trigger_as_of`
+    pub async fn trigger_as_of(
+        &self,
+        job_id: &Uuid,
+        scheduled_time: DateTime<Utc>,
+    ) -> Result<(), JobSchedulerError> {
+        self.check_scope(job_id).await?;
+        self.scheduler.trigger_as_of(job_id, scheduled_time).await
+    }
+
+    ///
+    /// The next tick of a job in scope
+    pub async fn next_tick_for_job(
+        &self,
+        job_id: &Uuid,
+    ) -> Result<Option<DateTime<Utc>>, JobSchedulerError> {
+        self.check_scope(job_id).await?;
+        self.scheduler.clone().next_tick_for_job(*job_id).await
+    }
+
+    ///
+    /// List the jobs in scope
+    pub async fn list_jobs(&self) -> Result<Vec<JobInfo>, JobSchedulerError> {
+        let mut jobs = self.scheduler.list_jobs().await?;
+        jobs.retain(|j| j.tags.contains(&self.tag));
+        Ok(jobs)
+    }
+
+    ///
+    /// A page of the jobs in scope selected by `query`
+    pub async fn query_jobs(&self, query: JobQuery) -> Result<Page<JobInfo>, JobSchedulerError> {
+        self.scheduler.query_jobs(query.tag(&self.tag)).await
+    }
+
+    async fn check_scope(&self, job_id: &Uuid) -> Result<(), JobSchedulerError> {
+        let mut r = self.scheduler.context.metadata_storage.write().await;
+        let job = r.get(*job_id).await?.ok_or(JobSchedulerError::FetchJob)?;
+        if job.tags.contains(&self.tag) {
+            Ok(())
+        } else {
+            Err(JobSchedulerError::OutOfScope)
+        }
+    }
+}
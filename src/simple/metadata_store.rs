@@ -86,13 +86,13 @@ impl MetaDataStorage for SimpleMetadataStore {
         Box::pin(async move {
             let r = data.read().await;
             let ret = r
-                .iter()
-                .map(|(_, v)| (v.id.clone(), v.next_tick, v.last_tick, v.job_type))
-                .map(|(id, next_tick, last_tick, job_type)| JobAndNextTick {
-                    id,
-                    next_tick,
-                    last_tick,
-                    job_type,
+                .values()
+                .map(|v| JobAndNextTick {
+                    id: v.id.clone(),
+                    next_tick: v.next_tick,
+                    last_tick: v.last_tick,
+                    job_type: v.job_type,
+                    group: v.group.clone(),
                 })
                 .collect::<Vec<_>>();
             Ok(ret)
@@ -120,6 +120,16 @@ impl MetaDataStorage for SimpleMetadataStore {
         })
     }
 
+    fn list_job_ids(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Uuid>, JobSchedulerError>> + Send>> {
+        let data = self.data.clone();
+        Box::pin(async move {
+            let r = data.read().await;
+            Ok(r.keys().copied().collect())
+        })
+    }
+
     fn time_till_next_job(
         &mut self,
     ) -> Pin<Box<dyn Future<Output = Result<Option<std::time::Duration>, JobSchedulerError>> + Send>>
@@ -130,8 +140,8 @@ impl MetaDataStorage for SimpleMetadataStore {
             let now = Utc::now();
             let now = now.timestamp() as u64;
             let val = r
-                .iter()
-                .filter_map(|(_, jd)| match jd.next_tick {
+                .values()
+                .filter_map(|jd| match jd.next_tick {
                     0 => None,
                     i => {
                         if i > now {
@@ -1,8 +1,10 @@
 mod metadata_store;
 mod notification_store;
+mod run_history_store;
 mod to_code;
 
 pub use metadata_store::SimpleMetadataStore;
 pub use notification_store::SimpleNotificationStore;
+pub use run_history_store::SimpleRunHistoryStore;
 pub use to_code::SimpleJobCode;
 pub use to_code::SimpleNotificationCode;
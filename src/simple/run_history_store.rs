@@ -0,0 +1,105 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::JobRun;
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::JobRun;
+use crate::store::{InitStore, RunHistoryStore};
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+///
+/// A run history in memory, keeping the last `keep` runs of each job for as long as the process
+/// lives
+#[derive(Clone)]
+pub struct SimpleRunHistoryStore {
+    pub data: Arc<RwLock<HashMap<Uuid, VecDeque<JobRun>>>>,
+    pub keep: usize,
+    pub inited: bool,
+}
+
+impl Default for SimpleRunHistoryStore {
+    fn default() -> Self {
+        Self {
+            data: Arc::new(RwLock::new(HashMap::new())),
+            keep: 100,
+            inited: false,
+        }
+    }
+}
+
+impl SimpleRunHistoryStore {
+    pub fn new(keep: usize) -> Self {
+        Self {
+            keep: keep.max(1),
+            ..Default::default()
+        }
+    }
+}
+
+impl InitStore for SimpleRunHistoryStore {
+    fn init(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        self.inited = true;
+        Box::pin(std::future::ready(Ok(())))
+    }
+
+    fn inited(&mut self) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let val = self.inited;
+        Box::pin(std::future::ready(Ok(val)))
+    }
+}
+
+impl RunHistoryStore for SimpleRunHistoryStore {
+    fn record(
+        &mut self,
+        run: JobRun,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let data = self.data.clone();
+        let keep = self.keep;
+        Box::pin(async move {
+            let job_id = run.job_id().ok_or(JobSchedulerError::SaveJob)?;
+            let mut w = data.write().await;
+            let runs = w.entry(job_id).or_default();
+            runs.push_front(run);
+            runs.truncate(keep);
+            Ok(())
+        })
+    }
+
+    fn history(
+        &mut self,
+        job_id: Uuid,
+        limit: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobRun>, JobSchedulerError>> + Send>> {
+        let data = self.data.clone();
+        Box::pin(async move {
+            let r = data.read().await;
+            Ok(r.get(&job_id)
+                .map(|runs| runs.iter().take(limit).cloned().collect())
+                .unwrap_or_default())
+        })
+    }
+
+    fn runs_between(
+        &mut self,
+        range: Range<DateTime<Utc>>,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobRun>, JobSchedulerError>> + Send>> {
+        let data = self.data.clone();
+        Box::pin(async move {
+            let r = data.read().await;
+            let mut runs: Vec<JobRun> = r
+                .values()
+                .flatten()
+                .filter(|run| run.ended_in(&range))
+                .cloned()
+                .collect();
+            runs.sort_by_key(|run| run.ended_at);
+            Ok(runs)
+        })
+    }
+}
@@ -0,0 +1,132 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::JobType;
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::JobType;
+use crate::job::JobLocked;
+use crate::leap;
+use crate::scheduler::Scheduler;
+use crate::JobSchedulerError;
+use chrono::{DateTime, Duration, Offset, TimeZone, Utc};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+/// The number of hotspots kept in a `SimulationReport`
+const HOTSPOT_LIMIT: usize = 20;
+
+///
+/// A minute in which several jobs fire
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hotspot {
+    pub minute: DateTime<Utc>,
+    pub job_ids: Vec<Uuid>,
+}
+
+///
+/// Two consecutive runs of a cron job between which the UTC offset of the simulated time zone
+/// changed, so the job fires at a different local time after the change
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DstAnomaly {
+    pub job_id: Uuid,
+    pub before: DateTime<Utc>,
+    pub after: DateTime<Utc>,
+    pub offset_change_seconds: i32,
+}
+
+///
+/// The outcome of `simulate`
+#[derive(Clone, Debug, Default)]
+pub struct SimulationReport {
+    pub fire_counts: HashMap<Uuid, u64>,
+    pub total_fires: u64,
+    /// The busiest minutes, most jobs first
+    pub hotspots: Vec<Hotspot>,
+    pub dst_anomalies: Vec<DstAnomaly>,
+}
+
+///
+/// Run the scheduling logic of `jobs` from `start` until `end` in virtual time, without running
+/// the jobs themselves. Cron and repeated jobs are scheduled as if they were added at `start`,
+/// one shot jobs fire at their set instant when it falls in the range. Daylight saving anomalies
+/// are reported for the time zone of `start`, for the jobs whose schedule is evaluated in
+/// another time zone.
+pub fn simulate<Tz: TimeZone>(
+    jobs: &[JobLocked],
+    start: DateTime<Tz>,
+    end: DateTime<Tz>,
+) -> Result<SimulationReport, JobSchedulerError> {
+    let tz = start.timezone();
+    let offset = |t: &DateTime<Utc>| t.with_timezone(&tz).offset().fix().local_minus_utc();
+    let start = start.with_timezone(&Utc);
+    let end = end.with_timezone(&Utc);
+
+    let mut report = SimulationReport::default();
+    let mut minutes: BTreeMap<i64, Vec<Uuid>> = BTreeMap::new();
+
+    for job in jobs {
+        let mut data = job.clone().job_data()?;
+        let job_id = job.guid();
+        let job_type = JobType::from_i32(data.job_type);
+        let job_tz = data.timezone();
+        let first = match job_type {
+            Some(JobType::Cron) => data.next_cron_tick(&start),
+            Some(JobType::Repeated) => data
+                .repeated_every()
+                .and_then(|r| start.checked_add_signed(Duration::seconds(r as i64))),
+            Some(JobType::OneShot) => data
+                .next_tick_utc()
+                .filter(|t| *t >= start)
+                .or_else(|| data.instants().into_iter().find(|t| *t >= start)),
+            // Runs after another job depend on when it completes, which is not simulated
+            Some(JobType::AfterJob) | None => None,
+        };
+
+        let mut fires = 0;
+        let mut previous: Option<DateTime<Utc>> = None;
+        let mut next = first;
+        while let Some(tick) = next.filter(|t| *t < end) {
+            fires += 1;
+            let minute = tick.timestamp().div_euclid(60);
+            let in_minute = minutes.entry(minute).or_default();
+            if in_minute.last() != Some(&job_id) {
+                in_minute.push(job_id);
+            }
+            if let (Some(previous), Some(JobType::Cron)) = (previous, job_type) {
+                // A job keeps its local time in its own time zone, so only a change of offset
+                // between the two zones moves it
+                let job_offset =
+                    |t: &DateTime<Utc>| t.with_timezone(&job_tz).offset().fix().local_minus_utc();
+                let change =
+                    offset(&tick) - offset(&previous) - (job_offset(&tick) - job_offset(&previous));
+                if change != 0 {
+                    report.dst_anomalies.push(DstAnomaly {
+                        job_id,
+                        before: previous,
+                        after: tick,
+                        offset_change_seconds: change,
+                    });
+                }
+            }
+            previous = Some(tick);
+            data.set_next_tick(Some(tick));
+            next = Scheduler::next_tick(&data, &tick, leap::default_search_horizon());
+        }
+        report.fire_counts.insert(job_id, fires);
+        report.total_fires += fires;
+    }
+
+    let mut hotspots = minutes
+        .into_iter()
+        .filter(|(_, job_ids)| job_ids.len() > 1)
+        .collect::<Vec<_>>();
+    hotspots
+        .sort_by(|(a_minute, a), (b_minute, b)| b.len().cmp(&a.len()).then(a_minute.cmp(b_minute)));
+    report.hotspots = hotspots
+        .into_iter()
+        .take(HOTSPOT_LIMIT)
+        .filter_map(|(minute, job_ids)| {
+            DateTime::from_timestamp(minute * 60, 0).map(|minute| Hotspot { minute, job_ids })
+        })
+        .collect();
+
+    Ok(report)
+}
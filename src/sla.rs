@@ -0,0 +1,229 @@
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+///
+/// The completion time a percentile of the runs of a job should stay within, e.g. 95% of the
+/// runs done within 30 minutes of their scheduled time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SlaTarget {
+    /// The percentile checked, between 0 and 100
+    pub percentile: f64,
+    pub target: Duration,
+}
+
+impl SlaTarget {
+    pub fn new(percentile: f64, target: Duration) -> Self {
+        Self {
+            percentile: percentile.clamp(0.0, 100.0),
+            target,
+        }
+    }
+}
+
+///
+/// The SLA target of a job was breached, its percentile of the completion times in the window
+/// having gone over the target
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SlaBreach {
+    pub job_id: Uuid,
+    pub percentile: f64,
+    pub target: Duration,
+    pub observed: Duration,
+}
+
+///
+/// Called when the SLA target of a job is breached, once until the job meets it again
+pub type OnSlaBreach = dyn Fn(SlaBreach) + Send + Sync;
+
+///
+/// How long after their scheduled time the successful runs of a job completed, over the
+/// rolling window of the scheduler
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompletionStats {
+    samples: VecDeque<(DateTime<Utc>, Duration)>,
+}
+
+impl CompletionStats {
+    fn record(&mut self, completed_at: DateTime<Utc>, completion: Duration, window: Duration) {
+        self.samples.push_back((completed_at, completion));
+        let window = chrono::Duration::from_std(window).unwrap_or(chrono::Duration::MAX);
+        while let Some((at, _)) = self.samples.front() {
+            if completed_at - *at <= window {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+
+    ///
+    /// The number of runs in the window
+    pub fn runs(&self) -> usize {
+        self.samples.len()
+    }
+
+    ///
+    /// The completion time of the last run
+    pub fn last(&self) -> Option<Duration> {
+        self.samples.back().map(|(_, completion)| *completion)
+    }
+
+    ///
+    /// The completion time `percentile`% of the runs in the window completed within, by the
+    /// nearest rank. `None` without runs.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut completions: Vec<Duration> = self.samples.iter().map(|(_, c)| *c).collect();
+        completions.sort();
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * completions.len() as f64).ceil();
+        let index = (rank as usize).clamp(1, completions.len()) - 1;
+        Some(completions[index])
+    }
+}
+
+///
+/// Tracks the completion times of the runs of jobs and checks them against the SLA targets
+/// of the jobs
+#[derive(Clone)]
+pub struct CompletionTimes {
+    pub window: Duration,
+    pub stats: Arc<RwLock<HashMap<Uuid, CompletionStats>>>,
+    pub targets: Arc<RwLock<HashMap<Uuid, SlaTarget>>>,
+    pub breached: Arc<RwLock<HashMap<Uuid, bool>>>,
+    pub handler: Arc<RwLock<Option<Arc<OnSlaBreach>>>>,
+}
+
+impl Default for CompletionTimes {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(24 * 60 * 60),
+            stats: Arc::new(RwLock::new(HashMap::new())),
+            targets: Arc::new(RwLock::new(HashMap::new())),
+            breached: Arc::new(RwLock::new(HashMap::new())),
+            handler: Arc::new(RwLock::new(None)),
+        }
+    }
+}
+
+impl CompletionTimes {
+    ///
+    /// Record a run of a job scheduled at `scheduled` that completed now, and report a breach
+    /// of its SLA target if it went over it
+    pub async fn record(&self, job_id: Uuid, scheduled: DateTime<Utc>) {
+        let now = Utc::now();
+        let completion = (now - scheduled).to_std().unwrap_or_default();
+        let stats = {
+            let mut w = self.stats.write().await;
+            let stats = w.entry(job_id).or_default();
+            stats.record(now, completion, self.window);
+            stats.clone()
+        };
+        let target = {
+            let r = self.targets.read().await;
+            r.get(&job_id).copied()
+        };
+        let (target, observed) = match target {
+            Some(target) => match stats.percentile(target.percentile) {
+                Some(observed) => (target, observed),
+                None => return,
+            },
+            None => return,
+        };
+        let breached = observed > target.target;
+        let was_breached = {
+            let mut w = self.breached.write().await;
+            w.insert(job_id, breached).unwrap_or_default()
+        };
+        if !breached || was_breached {
+            return;
+        }
+        warn!(job_id = job_id; "SLA breached, p{} of completion times {:?} over {:?}", target.percentile, observed, target.target);
+        let handler = {
+            let r = self.handler.read().await;
+            r.clone()
+        };
+        if let Some(handler) = handler {
+            handler(SlaBreach {
+                job_id,
+                percentile: target.percentile,
+                target: target.target,
+                observed,
+            });
+        }
+    }
+
+    pub async fn remove_job(&self, job_id: &Uuid) {
+        self.stats.write().await.remove(job_id);
+        self.targets.write().await.remove(job_id);
+        self.breached.write().await.remove(job_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SlaTarget;
+    use crate::{Job, JobNotification, JobScheduler};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// The completion times of a job over its SLA target, reported once while it stays in breach
+    #[tokio::test]
+    async fn sla_breach() {
+        let breaches = Arc::new(Mutex::new(vec![]));
+        let reported = breaches.clone();
+        let mut sched = JobScheduler::new_builder()
+            .on_sla_breach(Box::new(move |breach| {
+                reported.lock().unwrap().push(breach);
+            }))
+            .build()
+            .await
+            .expect("scheduler");
+        let job = Job::new_repeated_async(Duration::from_secs(3600), |_, _| {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+            })
+        })
+        .expect("job");
+        let job_id = sched.add(job).await.expect("add");
+        let target = SlaTarget::new(50.0, Duration::from_millis(100));
+        sched.set_sla_target(&job_id, target).await;
+        let mut events = sched.subscribe_events();
+        sched.start().await.expect("start");
+
+        for _ in 0..2 {
+            sched.trigger(&job_id).await.expect("trigger");
+            tokio::time::timeout(Duration::from_secs(3), async {
+                loop {
+                    if let Ok((id, JobNotification::Done)) = events.recv().await {
+                        if id == job_id {
+                            return;
+                        }
+                    }
+                }
+            })
+            .await
+            .expect("run done");
+        }
+        let stats = sched
+            .completion_times(&job_id)
+            .await
+            .expect("completion times");
+        assert_eq!(stats.runs(), 2);
+        let p50 = stats.percentile(50.0).expect("p50");
+        assert!(p50 >= Duration::from_millis(300));
+        assert!(stats.percentile(100.0).expect("p100") >= p50);
+
+        // Reported once, the job staying in breach
+        let breaches = breaches.lock().unwrap().clone();
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].job_id, job_id);
+        assert_eq!(breaches[0].target, target.target);
+        assert!(breaches[0].observed > target.target);
+        sched.shutdown().await.expect("shutdown");
+    }
+}
@@ -0,0 +1,489 @@
+use crate::job::job_data_prost::JobType;
+use crate::sqlite::SqliteStore;
+use crate::store::{Codec, DataStore, InitStore, MetaDataStorage, ProstCodec};
+use crate::{JobAndNextTick, JobSchedulerError, JobStoredData, JobUuid};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+const TABLE: &str = "job";
+
+///
+/// A SQLite backed metadata store. The jobs are kept encoded by the codec, next to the columns
+/// the scheduler selects them by.
+#[derive(Clone)]
+pub struct SqliteMetadataStore {
+    pub store: Arc<RwLock<SqliteStore>>,
+    pub init_tables: bool,
+    pub table: String,
+    /// How the jobs are encoded in the table
+    pub codec: Arc<dyn Codec>,
+}
+
+impl Default for SqliteMetadataStore {
+    fn default() -> Self {
+        let init_tables = std::env::var("SQLITE_INIT_METADATA")
+            .map(|s| s.to_lowercase() != "false")
+            .unwrap_or(true);
+        let table = std::env::var("SQLITE_METADATA_TABLE").unwrap_or_else(|_| TABLE.to_lowercase());
+        let store = Arc::new(RwLock::new(SqliteStore::default()));
+        Self {
+            init_tables,
+            table,
+            store,
+            codec: Arc::new(ProstCodec),
+        }
+    }
+}
+
+impl SqliteMetadataStore {
+    /// A store keeping its jobs in `store`, which may be shared with a `SqliteNotificationStore`
+    pub fn new(store: SqliteStore) -> Self {
+        Self {
+            store: Arc::new(RwLock::new(store)),
+            ..Default::default()
+        }
+    }
+}
+
+fn decode(
+    codec: &dyn Codec,
+    data: Option<Vec<u8>>,
+) -> Result<Option<JobStoredData>, JobSchedulerError> {
+    data.map(|data| codec.decode_job(&data)).transpose()
+}
+
+fn update_error(e: rusqlite::Error) -> JobSchedulerError {
+    error!("Error updating next and last tick {:?}", e);
+    JobSchedulerError::UpdateJobData
+}
+
+///
+/// Create the table of the jobs, named `table`, unless it exists already
+fn create_table(connection: &Connection, table: &str) -> rusqlite::Result<()> {
+    let sql = "CREATE TABLE IF NOT EXISTS ".to_string()
+        + table
+        + " (\
+            id TEXT NOT NULL PRIMARY KEY,\
+            next_tick INTEGER NOT NULL,\
+            last_tick INTEGER,\
+            job_type INTEGER NOT NULL,\
+            job_group TEXT NOT NULL,\
+            data BLOB NOT NULL\
+        );\
+        CREATE INDEX IF NOT EXISTS "
+        + table
+        + "_next_tick ON "
+        + table
+        + " (next_tick);";
+    connection.execute_batch(&sql)
+}
+
+impl DataStore<JobStoredData> for SqliteMetadataStore {
+    fn get(
+        &mut self,
+        id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<JobStoredData>, JobSchedulerError>> + Send>>
+    {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::GetJobData),
+                SqliteStore::Inited(connection) => {
+                    let connection = connection.lock().await;
+                    let sql = "SELECT data FROM ".to_string() + &*table + " WHERE id = ?1";
+                    let data = connection
+                        .query_row(&sql, params![id.to_string()], |row| row.get(0))
+                        .optional();
+                    match data {
+                        Ok(data) => decode(&*codec, data),
+                        Err(e) => {
+                            error!("Error getting value {:?}", e);
+                            Err(JobSchedulerError::GetJobData)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn add_or_update(
+        &mut self,
+        data: JobStoredData,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::UpdateJobData),
+                SqliteStore::Inited(connection) => {
+                    let uuid: Uuid = match data.id.as_ref() {
+                        Some(id) => id.into(),
+                        None => return Err(JobSchedulerError::CantAdd),
+                    };
+                    let bytes = codec.encode_job(&data)?;
+                    let connection = connection.lock().await;
+                    let sql = "INSERT INTO ".to_string()
+                        + &*table
+                        + " (id, next_tick, last_tick, job_type, job_group, data) \
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6) \
+                        ON CONFLICT (id) \
+                        DO UPDATE SET \
+                            next_tick = excluded.next_tick, last_tick = excluded.last_tick, \
+                            job_type = excluded.job_type, job_group = excluded.job_group, \
+                            data = excluded.data";
+                    let result = connection.execute(
+                        &sql,
+                        params![
+                            uuid.to_string(),
+                            data.next_tick as i64,
+                            data.last_tick.map(|t| t as i64),
+                            data.job_type,
+                            data.group,
+                            bytes
+                        ],
+                    );
+                    match result {
+                        Ok(_) => Ok(()),
+                        Err(e) => {
+                            error!("Error {:?}", e);
+                            Err(JobSchedulerError::CantAdd)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn delete(
+        &mut self,
+        guid: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::CantRemove),
+                SqliteStore::Inited(connection) => {
+                    let connection = connection.lock().await;
+                    let sql = "DELETE FROM ".to_string() + &*table + " WHERE id = ?1";
+                    match connection.execute(&sql, params![guid.to_string()]) {
+                        Ok(_) => Ok(()),
+                        Err(e) => {
+                            error!("Error deleting job data {:?}", e);
+                            Err(JobSchedulerError::CantRemove)
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl InitStore for SqliteMetadataStore {
+    fn init(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let inited = self.inited();
+        let store = self.store.clone();
+        let init_tables = self.init_tables;
+        let table = self.table.clone();
+        Box::pin(async move {
+            let inited = inited.await;
+            let mut w = store.write().await;
+            let v = if matches!(inited, Ok(true)) {
+                w.clone()
+            } else {
+                match w.clone().init().await {
+                    Ok(v) => v,
+                    Err(e) => {
+                        error!("Error initialising {:?}", e);
+                        return Err(e);
+                    }
+                }
+            };
+            // The tables are created even when the store was inited by a notification store
+            // sharing the database
+            if init_tables {
+                if let SqliteStore::Inited(connection) = &v {
+                    let connection = connection.lock().await;
+                    if let Err(e) = create_table(&connection, &table) {
+                        error!("Error on init SQLite Metadata store {:?}", e);
+                        return Err(JobSchedulerError::CantInit);
+                    }
+                }
+            }
+            *w = v;
+            Ok(())
+        })
+    }
+
+    fn inited(&mut self) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            Ok(store.inited())
+        })
+    }
+}
+
+impl MetaDataStorage for SqliteMetadataStore {
+    fn list_next_ticks(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobAndNextTick>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::CantListNextTicks),
+                SqliteStore::Inited(connection) => {
+                    let connection = connection.lock().await;
+                    // Include the jobs due in the next second, so warm jobs can be prepared
+                    let now = Utc::now().timestamp() + 1;
+                    let sql = "SELECT id, job_type, next_tick, last_tick, job_group FROM "
+                        .to_string()
+                        + &*table
+                        + " WHERE next_tick > 0 AND next_tick < ?1";
+                    let rows = connection.prepare(&sql).and_then(|mut statement| {
+                        statement
+                            .query_map(params![now], |row| {
+                                let id: String = row.get(0)?;
+                                let id = Uuid::from_str(&id).ok().map(JobUuid::from);
+                                let next_tick: i64 = row.get(2)?;
+                                let last_tick: Option<i64> = row.get(3)?;
+                                Ok(JobAndNextTick {
+                                    id,
+                                    job_type: row.get(1)?,
+                                    next_tick: next_tick as u64,
+                                    last_tick: last_tick.map(|t| t as u64),
+                                    group: row.get(4)?,
+                                })
+                            })?
+                            .collect::<Result<Vec<_>, _>>()
+                    });
+                    match rows {
+                        Ok(rows) => Ok(rows),
+                        Err(e) => {
+                            error!("Error getting next ticks {:?}", e);
+                            Err(JobSchedulerError::CantListNextTicks)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn set_next_and_last_tick(
+        &mut self,
+        guid: Uuid,
+        next_tick: Option<DateTime<Utc>>,
+        last_tick: Option<DateTime<Utc>>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        let codec = self.codec.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::UpdateJobData),
+                SqliteStore::Inited(connection) => {
+                    let mut connection = connection.lock().await;
+                    let next_tick = next_tick.map(|b| b.timestamp()).unwrap_or(0);
+                    let last_tick = last_tick.map(|b| b.timestamp());
+                    let transaction = connection.transaction().map_err(update_error)?;
+                    let sql = "SELECT data FROM ".to_string() + &*table + " WHERE id = ?1";
+                    let data = transaction
+                        .query_row(&sql, params![guid.to_string()], |row| row.get(0))
+                        .optional()
+                        .map_err(update_error)?;
+                    let mut data = match decode(&*codec, data)? {
+                        Some(data) => data,
+                        None => {
+                            error!("Could not find job {:?} to update", guid);
+                            return Err(JobSchedulerError::UpdateJobData);
+                        }
+                    };
+                    data.next_tick = next_tick as u64;
+                    data.last_tick = last_tick.map(|t| t as u64);
+                    let sql = "UPDATE ".to_string()
+                        + &*table
+                        + " SET next_tick = ?1, last_tick = ?2, data = ?3 WHERE id = ?4";
+                    transaction
+                        .execute(
+                            &sql,
+                            params![
+                                next_tick,
+                                last_tick,
+                                codec.encode_job(&data)?,
+                                guid.to_string()
+                            ],
+                        )
+                        .map_err(update_error)?;
+                    transaction.commit().map_err(update_error)
+                }
+            }
+        })
+    }
+
+    fn time_till_next_job(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<Duration>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::CouldNotGetTimeUntilNextTick),
+                SqliteStore::Inited(connection) => {
+                    let connection = connection.lock().await;
+                    let now = Utc::now().timestamp();
+                    let sql = "SELECT next_tick FROM ".to_string()
+                        + &*table
+                        + " WHERE next_tick > 0 AND next_tick > ?1 \
+                        ORDER BY next_tick ASC \
+                        LIMIT 1";
+                    let next_tick = connection
+                        .query_row(&sql, params![now], |row| row.get::<_, i64>(0))
+                        .optional();
+                    match next_tick {
+                        Ok(next_tick) => Ok(next_tick
+                            .map(|ts| ts - now)
+                            .filter(|ts| *ts > 0)
+                            .map(|ts| Duration::from_secs(ts as u64))),
+                        Err(e) => {
+                            error!("Error getting time until next job {:?}", e);
+                            Err(JobSchedulerError::CouldNotGetTimeUntilNextTick)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn list_job_ids(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Uuid>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::CantListGuids),
+                SqliteStore::Inited(connection) => {
+                    let connection = connection.lock().await;
+                    let sql = "SELECT id FROM ".to_string() + &*table;
+                    let ids = connection.prepare(&sql).and_then(|mut statement| {
+                        statement
+                            .query_map([], |row| row.get::<_, String>(0))?
+                            .collect::<Result<Vec<_>, _>>()
+                    });
+                    match ids {
+                        Ok(ids) => Ok(ids
+                            .iter()
+                            .filter_map(|id| Uuid::from_str(id).ok())
+                            .collect()),
+                        Err(e) => {
+                            error!("Error listing job ids {:?}", e);
+                            Err(JobSchedulerError::CantListGuids)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn get_many(
+        &mut self,
+        ids: &[Uuid],
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobStoredData>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        let codec = self.codec.clone();
+        let ids = ids.iter().map(Uuid::to_string).collect::<Vec<_>>();
+        Box::pin(async move {
+            if ids.is_empty() {
+                return Ok(vec![]);
+            }
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::GetJobData),
+                SqliteStore::Inited(connection) => {
+                    let connection = connection.lock().await;
+                    let sql = format!(
+                        "SELECT data FROM {} WHERE id IN ({})",
+                        table,
+                        vec!["?"; ids.len()].join(", ")
+                    );
+                    let rows = connection.prepare(&sql).and_then(|mut statement| {
+                        statement
+                            .query_map(params_from_iter(ids.iter()), |row| {
+                                row.get::<_, Vec<u8>>(0)
+                            })?
+                            .collect::<Result<Vec<_>, _>>()
+                    });
+                    match rows {
+                        Ok(rows) => rows.iter().map(|data| codec.decode_job(data)).collect(),
+                        Err(e) => {
+                            error!("Error getting values {:?}", e);
+                            Err(JobSchedulerError::GetJobData)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn list_followers(
+        &mut self,
+        job_id: Uuid,
+    ) -> Option<Pin<Box<dyn Future<Output = Result<Vec<JobStoredData>, JobSchedulerError>> + Send>>>
+    {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        let codec = self.codec.clone();
+        Some(Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::GetJobData),
+                SqliteStore::Inited(connection) => {
+                    let connection = connection.lock().await;
+                    // Only the jobs run after another job are decoded
+                    let sql = format!("SELECT data FROM {} WHERE job_type = ?1", table);
+                    let rows = connection.prepare(&sql).and_then(|mut statement| {
+                        statement
+                            .query_map(params![JobType::AfterJob as i32], |row| {
+                                row.get::<_, Vec<u8>>(0)
+                            })?
+                            .collect::<Result<Vec<_>, _>>()
+                    });
+                    let rows = match rows {
+                        Ok(rows) => rows,
+                        Err(e) => {
+                            error!("Error getting the jobs run after a job {:?}", e);
+                            return Err(JobSchedulerError::GetJobData);
+                        }
+                    };
+                    let mut followers = vec![];
+                    for data in rows {
+                        let job = codec.decode_job(&data)?;
+                        if job.after_job.as_ref().map(Uuid::from) == Some(job_id) {
+                            followers.push(job);
+                        }
+                    }
+                    Ok(followers)
+                }
+            }
+        }))
+    }
+}
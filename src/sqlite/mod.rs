@@ -0,0 +1,65 @@
+mod metadata_store;
+mod notification_store;
+
+use crate::JobSchedulerError;
+use rusqlite::Connection;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub use metadata_store::SqliteMetadataStore;
+pub use notification_store::SqliteNotificationStore;
+
+///
+/// A SQLite database, opened on `init` from the path it was created with. `:memory:` opens a
+/// database in memory, which stores only share when they are given the same inited `SqliteStore`.
+#[derive(Clone)]
+pub enum SqliteStore {
+    Created(String),
+    Inited(Arc<Mutex<Connection>>),
+}
+
+impl SqliteStore {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self::Created(path.into())
+    }
+
+    pub fn inited(&self) -> bool {
+        matches!(self, SqliteStore::Inited(_))
+    }
+}
+
+impl Default for SqliteStore {
+    fn default() -> Self {
+        let path =
+            std::env::var("SQLITE_PATH").unwrap_or_else(|_| "tokio_cron_scheduler.db".to_string());
+        Self::Created(path)
+    }
+}
+
+impl SqliteStore {
+    pub fn init(
+        self,
+    ) -> Pin<Box<dyn Future<Output = Result<SqliteStore, JobSchedulerError>> + Send>> {
+        Box::pin(async move {
+            match self {
+                SqliteStore::Created(path) => {
+                    let connection = Connection::open(path).and_then(|connection| {
+                        // The states of a notification are deleted along with it
+                        connection.execute_batch("PRAGMA foreign_keys = ON")?;
+                        Ok(connection)
+                    });
+                    match connection {
+                        Ok(connection) => Ok(SqliteStore::Inited(Arc::new(Mutex::new(connection)))),
+                        Err(e) => {
+                            error!("Error opening SQLite database {:?}", e);
+                            Err(JobSchedulerError::CantInit)
+                        }
+                    }
+                }
+                SqliteStore::Inited(connection) => Ok(SqliteStore::Inited(connection)),
+            }
+        })
+    }
+}
@@ -0,0 +1,381 @@
+use crate::job::job_data_prost::{JobIdAndNotification, JobState, NotificationData};
+use crate::job::{JobId, NotificationId};
+use crate::sqlite::SqliteStore;
+use crate::store::{DataStore, InitStore, NotificationStore};
+use crate::JobSchedulerError;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+const MAIN_TABLE: &str = "notification";
+const STATES_TABLE: &str = "notification_state";
+
+///
+/// A SQLite backed notification store, with the states of the notifications in a table of
+/// their own
+#[derive(Clone)]
+pub struct SqliteNotificationStore {
+    pub store: Arc<RwLock<SqliteStore>>,
+    pub init_tables: bool,
+    pub table: String,
+    pub states_table: String,
+}
+
+impl Default for SqliteNotificationStore {
+    fn default() -> Self {
+        let init_tables = std::env::var("SQLITE_INIT_NOTIFICATIONS")
+            .map(|s| s.to_lowercase() != "false")
+            .unwrap_or(true);
+        let table = std::env::var("SQLITE_NOTIFICATION_TABLE")
+            .unwrap_or_else(|_| MAIN_TABLE.to_lowercase());
+        let states_table = std::env::var("SQLITE_NOTIFICATION_STATES_TABLE")
+            .unwrap_or_else(|_| STATES_TABLE.to_lowercase());
+        let store = Arc::new(RwLock::new(SqliteStore::default()));
+        Self {
+            init_tables,
+            table,
+            states_table,
+            store,
+        }
+    }
+}
+
+impl SqliteNotificationStore {
+    /// A store keeping its notifications in `store`, which may be shared with a
+    /// `SqliteMetadataStore`
+    pub fn new(store: SqliteStore) -> Self {
+        Self {
+            store: Arc::new(RwLock::new(store)),
+            ..Default::default()
+        }
+    }
+}
+
+fn list_ids(
+    connection: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> rusqlite::Result<Vec<Uuid>> {
+    let mut statement = connection.prepare(sql)?;
+    let ids = statement
+        .query_map(params, |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ids
+        .iter()
+        .filter_map(|id| Uuid::from_str(id).ok())
+        .collect())
+}
+
+impl DataStore<NotificationData> for SqliteNotificationStore {
+    fn get(
+        &mut self,
+        id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<NotificationData>, JobSchedulerError>> + Send>>
+    {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        let states_table = self.states_table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::GetJobData),
+                SqliteStore::Inited(connection) => {
+                    let connection = connection.lock().await;
+                    let sql = "SELECT job_id, extra FROM ".to_string() + &*table + " WHERE id = ?1";
+                    let row = connection
+                        .query_row(&sql, params![id.to_string()], |row| {
+                            Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+                        })
+                        .optional();
+                    let (job_id, extra) = match row {
+                        Ok(Some(row)) => row,
+                        Ok(None) => return Ok(None),
+                        Err(e) => {
+                            error!("Error fetching notification data {:?}", e);
+                            return Err(JobSchedulerError::GetJobData);
+                        }
+                    };
+                    let job_states = {
+                        let sql =
+                            "SELECT state FROM ".to_string() + &*states_table + " WHERE id = ?1";
+                        let states = connection.prepare(&sql).and_then(|mut statement| {
+                            statement
+                                .query_map(params![id.to_string()], |row| row.get::<_, i32>(0))?
+                                .collect::<Result<Vec<_>, _>>()
+                        });
+                        match states {
+                            Ok(states) => states,
+                            Err(e) => {
+                                error!("Error getting states {:?}", e);
+                                vec![]
+                            }
+                        }
+                    };
+                    let job_id = Uuid::from_str(&job_id)
+                        .ok()
+                        .map(|job_id| JobIdAndNotification {
+                            job_id: Some(job_id.into()),
+                            notification_id: Some(id.into()),
+                        });
+                    Ok(Some(NotificationData {
+                        job_id,
+                        job_states,
+                        extra,
+                    }))
+                }
+            }
+        })
+    }
+
+    fn add_or_update(
+        &mut self,
+        data: NotificationData,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        let states_table = self.states_table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::UpdateJobData),
+                SqliteStore::Inited(connection) => {
+                    let mut connection = connection.lock().await;
+                    let (job_id, notification_id) =
+                        match data.job_id_and_notification_id_from_data() {
+                            Some((job_id, notification_id)) => (job_id, notification_id),
+                            None => return Err(JobSchedulerError::UpdateJobData),
+                        };
+                    let notification_id = notification_id.to_string();
+                    let upsert = connection.transaction().and_then(|transaction| {
+                        let sql = "INSERT INTO ".to_string()
+                            + &*table
+                            + " (id, job_id, extra) \
+                            VALUES (?1, ?2, ?3) \
+                            ON CONFLICT (id) \
+                            DO UPDATE SET job_id = excluded.job_id, extra = excluded.extra";
+                        transaction.execute(
+                            &sql,
+                            params![notification_id, job_id.to_string(), data.extra],
+                        )?;
+                        let sql = "DELETE FROM ".to_string() + &*states_table + " WHERE id = ?1";
+                        transaction.execute(&sql, params![notification_id])?;
+                        let sql = "INSERT INTO ".to_string()
+                            + &*states_table
+                            + " (id, state) VALUES (?1, ?2) ON CONFLICT DO NOTHING";
+                        for state in data.job_states.iter() {
+                            transaction.execute(&sql, params![notification_id, state])?;
+                        }
+                        transaction.commit()
+                    });
+                    match upsert {
+                        Ok(()) => Ok(()),
+                        Err(e) => {
+                            error!("Error doing the upsert {:?}", e);
+                            Err(JobSchedulerError::UpdateJobData)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn delete(
+        &mut self,
+        guid: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::CantRemove),
+                SqliteStore::Inited(connection) => {
+                    let connection = connection.lock().await;
+                    let sql = "DELETE FROM ".to_string() + &*table + " WHERE id = ?1";
+                    connection
+                        .execute(&sql, params![guid.to_string()])
+                        .map(|_| ())
+                        .map_err(|e| {
+                            error!("Error deleting notification {:?}", e);
+                            JobSchedulerError::CantRemove
+                        })
+                }
+            }
+        })
+    }
+}
+
+impl InitStore for SqliteNotificationStore {
+    fn init(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let inited = self.inited();
+        let store = self.store.clone();
+        let init_tables = self.init_tables;
+        let table = self.table.clone();
+        let states_table = self.states_table.clone();
+        Box::pin(async move {
+            let inited = inited.await;
+            let mut w = store.write().await;
+            let v = if matches!(inited, Ok(true)) {
+                w.clone()
+            } else {
+                match w.clone().init().await {
+                    Ok(v) => v,
+                    Err(e) => {
+                        error!("Error initialising {:?}", e);
+                        return Err(e);
+                    }
+                }
+            };
+            if init_tables {
+                if let SqliteStore::Inited(connection) = &v {
+                    let connection = connection.lock().await;
+                    let sql = "CREATE TABLE IF NOT EXISTS ".to_string()
+                        + &*table
+                        + " (\
+                            id TEXT NOT NULL PRIMARY KEY,\
+                            job_id TEXT NOT NULL,\
+                            extra BLOB\
+                        );\
+                        CREATE TABLE IF NOT EXISTS "
+                        + &*states_table
+                        + " (\
+                            id TEXT NOT NULL REFERENCES "
+                        + &*table
+                        + " (id) ON DELETE CASCADE,\
+                            state INTEGER NOT NULL,\
+                            PRIMARY KEY (id, state)\
+                        );";
+                    if let Err(e) = connection.execute_batch(&sql) {
+                        error!("Error creating notification tables {:?}", e);
+                        return Err(JobSchedulerError::CantInit);
+                    }
+                }
+            }
+            *w = v;
+            Ok(())
+        })
+    }
+
+    fn inited(&mut self) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            Ok(store.inited())
+        })
+    }
+}
+
+impl NotificationStore for SqliteNotificationStore {
+    fn list_notification_guids_for_job_and_state(
+        &mut self,
+        job: JobId,
+        state: JobState,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<NotificationId>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        let states_table = self.states_table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::CantListGuids),
+                SqliteStore::Inited(connection) => {
+                    let connection = connection.lock().await;
+                    let sql = "SELECT DISTINCT n.id FROM ".to_string()
+                        + &*table
+                        + " AS n JOIN "
+                        + &*states_table
+                        + " AS st ON st.id = n.id \
+                        WHERE n.job_id = ?1 AND st.state = ?2";
+                    list_ids(&connection, &sql, params![job.to_string(), state as i32]).map_err(
+                        |e| {
+                            error!("Error listing notification guids for job and state {:?}", e);
+                            JobSchedulerError::CantListGuids
+                        },
+                    )
+                }
+            }
+        })
+    }
+
+    fn list_notification_guids_for_job_id(
+        &mut self,
+        job_id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Uuid>, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::CantListGuids),
+                SqliteStore::Inited(connection) => {
+                    let connection = connection.lock().await;
+                    let sql = "SELECT id FROM ".to_string() + &*table + " WHERE job_id = ?1";
+                    list_ids(&connection, &sql, params![job_id.to_string()]).map_err(|e| {
+                        error!(
+                            "Error getting list of notifications guids for job id {:?}",
+                            e
+                        );
+                        JobSchedulerError::CantListGuids
+                    })
+                }
+            }
+        })
+    }
+
+    fn delete_notification_for_state(
+        &mut self,
+        notification_id: Uuid,
+        state: JobState,
+    ) -> Pin<Box<dyn Future<Output = Result<bool, JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let states_table = self.states_table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::CantRemove),
+                SqliteStore::Inited(connection) => {
+                    let connection = connection.lock().await;
+                    let sql = "DELETE FROM ".to_string()
+                        + &*states_table
+                        + " WHERE id = ?1 AND state = ?2";
+                    connection
+                        .execute(&sql, params![notification_id.to_string(), state as i32])
+                        .map(|deleted| deleted > 0)
+                        .map_err(|e| {
+                            error!("Error deleting notification for state {:?}", e);
+                            JobSchedulerError::CantRemove
+                        })
+                }
+            }
+        })
+    }
+
+    fn delete_for_job(
+        &mut self,
+        job_id: Uuid,
+    ) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>> {
+        let store = self.store.clone();
+        let table = self.table.clone();
+        Box::pin(async move {
+            let store = store.read().await;
+            match &*store {
+                SqliteStore::Created(_) => Err(JobSchedulerError::CantRemove),
+                SqliteStore::Inited(connection) => {
+                    let connection = connection.lock().await;
+                    let sql = "DELETE FROM ".to_string() + &*table + " WHERE job_id = ?1";
+                    connection
+                        .execute(&sql, params![job_id.to_string()])
+                        .map(|_| ())
+                        .map_err(|e| {
+                            error!("Error deleting for job {:?}", e);
+                            JobSchedulerError::CantRemove
+                        })
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,166 @@
+//! Server-Sent Events of the state changes of the jobs, for browsers to follow the runs live.
+//! Only callers with at least the `Role::Viewer` role get the stream.
+//!
+//! This module serves no endpoint itself, as it depends on no web framework. The caller mounts a
+//! handler of its own that takes the credentials of the request, e.g. its bearer token, calls
+//! `events`, answers its errors with e.g. `403 Forbidden` or `429 Too Many Requests`, and
+//! otherwise answers with the `HEADERS` and the frames of the stream as the body.
+
+use crate::auth::{authorize, Authenticator, RateLimiter, Role};
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::JobState;
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::JobState;
+use crate::job_scheduler::JobsSchedulerLocked;
+use crate::JobSchedulerError;
+use futures_util::stream::{self, Stream};
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+///
+/// The headers of the response streaming the events: its content type, and no caching of it by
+/// the browser or the proxies
+pub const HEADERS: [(&str, &str); 2] = [
+    ("content-type", "text/event-stream"),
+    ("cache-control", "no-cache"),
+];
+
+///
+/// An SSE frame of a job changing state, numbered with `id` so a client can tell it missed some
+pub fn frame(id: u64, job_id: &Uuid, state: JobState) -> String {
+    format!(
+        "id: {}\nevent: {:?}\ndata: {{\"job_id\":\"{}\",\"state\":\"{:?}\"}}\n\n",
+        id, state, job_id, state
+    )
+}
+
+///
+/// The SSE frames of the state changes of the jobs run by the scheduler process, of one job
+/// when given, for a caller with `credentials` that `authenticator` tells is at least a viewer.
+/// With a `rate_limiter`, the streams opened by each caller, told apart by their credentials, are
+/// limited by it, the requests over the limit failing with `RateLimited`. A client too slow to
+/// keep up gets a comment telling how many changes it missed.
+pub async fn events(
+    scheduler: &JobsSchedulerLocked,
+    authenticator: &dyn Authenticator,
+    rate_limiter: Option<&RateLimiter>,
+    credentials: &str,
+    job_id: Option<Uuid>,
+) -> Result<impl Stream<Item = String> + Send + 'static, JobSchedulerError> {
+    if let Some(rate_limiter) = rate_limiter {
+        rate_limiter.check(credentials)?;
+    }
+    authorize(authenticator, credentials, Role::Viewer).await?;
+    let rx = scheduler.subscribe_events();
+    Ok(stream::unfold(
+        (rx, 0u64),
+        move |(mut rx, mut id)| async move {
+            loop {
+                match rx.recv().await {
+                    Ok((changed, state)) if job_id.is_none_or(|job_id| job_id == changed) => {
+                        id += 1;
+                        return Some((frame(id, &changed, state), (rx, id)));
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(missed)) => {
+                        return Some((format!(": missed {} events\n\n", missed), (rx, id)));
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::events;
+    use crate::auth::{RateLimiter, Role, StaticTokens};
+    use crate::{Job, JobScheduler, JobSchedulerError};
+    use futures_util::StreamExt;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn events_for_viewers() {
+        let sched = JobScheduler::new().await.expect("scheduler");
+        let tokens = StaticTokens::new().token("viewer-token", Role::Viewer);
+        assert!(events(&sched, &tokens, None, "viewer-token", None)
+            .await
+            .is_ok());
+        assert!(matches!(
+            events(&sched, &tokens, None, "unknown", None).await,
+            Err(JobSchedulerError::Forbidden(Role::Viewer))
+        ));
+    }
+
+    /// The streams opened by a caller are limited, whether its credentials are known or not
+    #[tokio::test]
+    async fn rate_limited_events() {
+        let sched = JobScheduler::new().await.expect("scheduler");
+        let tokens = StaticTokens::new().token("viewer-token", Role::Viewer);
+        let limiter = RateLimiter::new(1, 1);
+        assert!(
+            events(&sched, &tokens, Some(&limiter), "viewer-token", None)
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            events(&sched, &tokens, Some(&limiter), "viewer-token", None).await,
+            Err(JobSchedulerError::RateLimited)
+        ));
+        assert!(matches!(
+            events(&sched, &tokens, Some(&limiter), "unknown", None).await,
+            Err(JobSchedulerError::Forbidden(Role::Viewer))
+        ));
+        assert!(matches!(
+            events(&sched, &tokens, Some(&limiter), "unknown", None).await,
+            Err(JobSchedulerError::RateLimited)
+        ));
+    }
+
+    /// A viewer streams the frames of the job it follows, while a caller with unknown
+    /// credentials gets no stream at all
+    #[tokio::test]
+    async fn stream_of_a_job() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let job_id = sched
+            .add(Job::new("0 0 0 1 1 *", |_, _| {}).expect("job"))
+            .await
+            .expect("add");
+        let other_id = sched
+            .add(Job::new("0 0 0 1 1 *", |_, _| {}).expect("job"))
+            .await
+            .expect("add");
+        let tokens = StaticTokens::new().token("viewer-token", Role::Viewer);
+        assert!(matches!(
+            events(&sched, &tokens, None, "unknown", Some(job_id)).await,
+            Err(JobSchedulerError::Forbidden(Role::Viewer))
+        ));
+        let stream = events(&sched, &tokens, None, "viewer-token", Some(job_id))
+            .await
+            .expect("events");
+        sched.start().await.expect("start");
+        sched.trigger(&other_id).await.expect("trigger");
+        sched.trigger(&job_id).await.expect("trigger");
+
+        let frames =
+            tokio::time::timeout(Duration::from_secs(3), stream.take(2).collect::<Vec<_>>())
+                .await
+                .expect("frames");
+        assert!(
+            frames[0].starts_with("id: 1\nevent: Scheduled\n"),
+            "{}",
+            frames[0]
+        );
+        assert!(
+            frames[1].starts_with("id: 2\nevent: Started\n"),
+            "{}",
+            frames[1]
+        );
+        for frame in frames {
+            assert!(frame.contains(&job_id.to_string()));
+            assert!(frame.ends_with("\n\n"));
+        }
+        sched.shutdown().await.expect("shutdown");
+    }
+}
@@ -0,0 +1,243 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{JobAndNextTick, JobState, JobStoredData};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{JobAndNextTick, JobState, JobStoredData};
+use crate::job::{JobId, NotificationId};
+use crate::store::{DataStore, InitStore, MetaDataStorage, NotificationStore};
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, JobSchedulerError>> + Send>>;
+
+///
+/// How often a `ChaosStore` injects faults. Probabilities are between 0 and 1.
+#[derive(Clone, Debug)]
+pub struct FaultConfig {
+    /// Chance an operation is delayed by up to `max_delay`
+    pub delay_probability: f64,
+    pub max_delay: Duration,
+    /// Chance an operation fails without reaching the wrapped store
+    pub error_probability: f64,
+    /// Chance a write is sent to the wrapped store twice
+    pub duplicate_probability: f64,
+    /// Seed of the fault sequence, the same seed gives the same faults
+    pub seed: u64,
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self {
+            delay_probability: 0.0,
+            max_delay: Duration::from_millis(100),
+            error_probability: 0.0,
+            duplicate_probability: 0.0,
+            seed: 0x5eed,
+        }
+    }
+}
+
+struct Fault {
+    delay: Option<Duration>,
+    error: bool,
+}
+
+///
+/// Wraps a metadata or notification store and randomly delays, fails or duplicates its
+/// operations. Meant for testing how the scheduler and its configuration hold up against a
+/// flaky storage backend.
+#[derive(Clone)]
+pub struct ChaosStore<S> {
+    pub inner: S,
+    pub config: FaultConfig,
+    rng: Arc<Mutex<u64>>,
+}
+
+impl<S> ChaosStore<S> {
+    pub fn wrap(inner: S, config: FaultConfig) -> Self {
+        let rng = Arc::new(Mutex::new(config.seed.max(1)));
+        Self { inner, config, rng }
+    }
+
+    fn random(&self) -> f64 {
+        let mut state = self.rng.lock().unwrap();
+        // xorshift64*
+        let mut x = *state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        *state = x;
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn roll(&self, probability: f64) -> bool {
+        probability > 0.0 && self.random() < probability
+    }
+
+    fn fault(&self) -> Fault {
+        let delay = if self.roll(self.config.delay_probability) {
+            Some(self.config.max_delay.mul_f64(self.random()))
+        } else {
+            None
+        };
+        let error = self.roll(self.config.error_probability);
+        Fault { delay, error }
+    }
+
+    fn chaos<T>(&self, op: StoreFuture<T>, error: JobSchedulerError) -> StoreFuture<T>
+    where
+        T: Send + 'static,
+    {
+        let fault = self.fault();
+        Box::pin(async move {
+            if let Some(delay) = fault.delay {
+                tokio::time::sleep(delay).await;
+            }
+            if fault.error {
+                return Err(error);
+            }
+            op.await
+        })
+    }
+
+    fn chaos_write(
+        &self,
+        op: StoreFuture<()>,
+        duplicate: Option<StoreFuture<()>>,
+        error: JobSchedulerError,
+    ) -> StoreFuture<()> {
+        let op: StoreFuture<()> = match duplicate {
+            Some(duplicate) => Box::pin(async move {
+                op.await?;
+                duplicate.await
+            }),
+            None => op,
+        };
+        self.chaos(op, error)
+    }
+
+    fn duplicate(&self) -> bool {
+        self.roll(self.config.duplicate_probability)
+    }
+}
+
+impl<S, DATA> DataStore<DATA> for ChaosStore<S>
+where
+    S: DataStore<DATA>,
+    DATA: Clone + Send + 'static,
+{
+    fn get(&mut self, id: Uuid) -> StoreFuture<Option<DATA>> {
+        let op = self.inner.get(id);
+        self.chaos(op, JobSchedulerError::GetJobData)
+    }
+
+    fn add_or_update(&mut self, data: DATA) -> StoreFuture<()> {
+        let duplicate = self
+            .duplicate()
+            .then(|| self.inner.add_or_update(data.clone()));
+        let op = self.inner.add_or_update(data);
+        self.chaos_write(op, duplicate, JobSchedulerError::CantAdd)
+    }
+
+    fn delete(&mut self, guid: Uuid) -> StoreFuture<()> {
+        let duplicate = self.duplicate().then(|| self.inner.delete(guid));
+        let op = self.inner.delete(guid);
+        self.chaos_write(op, duplicate, JobSchedulerError::CantRemove)
+    }
+}
+
+impl<S> InitStore for ChaosStore<S>
+where
+    S: InitStore,
+{
+    fn init(&mut self) -> StoreFuture<()> {
+        self.inner.init()
+    }
+
+    fn inited(&mut self) -> StoreFuture<bool> {
+        self.inner.inited()
+    }
+}
+
+impl<S> MetaDataStorage for ChaosStore<S>
+where
+    S: MetaDataStorage,
+{
+    fn list_next_ticks(&mut self) -> StoreFuture<Vec<JobAndNextTick>> {
+        let op = self.inner.list_next_ticks();
+        self.chaos(op, JobSchedulerError::CantListNextTicks)
+    }
+
+    fn set_next_and_last_tick(
+        &mut self,
+        guid: Uuid,
+        next_tick: Option<DateTime<Utc>>,
+        last_tick: Option<DateTime<Utc>>,
+    ) -> StoreFuture<()> {
+        let duplicate = self.duplicate().then(|| {
+            self.inner
+                .set_next_and_last_tick(guid, next_tick, last_tick)
+        });
+        let op = self
+            .inner
+            .set_next_and_last_tick(guid, next_tick, last_tick);
+        self.chaos_write(op, duplicate, JobSchedulerError::UpdateJobData)
+    }
+
+    fn time_till_next_job(&mut self) -> StoreFuture<Option<Duration>> {
+        let op = self.inner.time_till_next_job();
+        self.chaos(op, JobSchedulerError::CouldNotGetTimeUntilNextTick)
+    }
+
+    fn list_job_ids(&mut self) -> StoreFuture<Vec<Uuid>> {
+        let op = self.inner.list_job_ids();
+        self.chaos(op, JobSchedulerError::CantListGuids)
+    }
+
+    fn get_many(&mut self, ids: &[Uuid]) -> StoreFuture<Vec<JobStoredData>> {
+        let op = self.inner.get_many(ids);
+        self.chaos(op, JobSchedulerError::GetJobData)
+    }
+}
+
+impl<S> NotificationStore for ChaosStore<S>
+where
+    S: NotificationStore,
+{
+    fn list_notification_guids_for_job_and_state(
+        &mut self,
+        job: JobId,
+        state: JobState,
+    ) -> StoreFuture<Vec<NotificationId>> {
+        let op = self
+            .inner
+            .list_notification_guids_for_job_and_state(job, state);
+        self.chaos(op, JobSchedulerError::GetJobData)
+    }
+
+    fn list_notification_guids_for_job_id(&mut self, job_id: Uuid) -> StoreFuture<Vec<Uuid>> {
+        let op = self.inner.list_notification_guids_for_job_id(job_id);
+        self.chaos(op, JobSchedulerError::GetJobData)
+    }
+
+    fn delete_notification_for_state(
+        &mut self,
+        notification_id: Uuid,
+        state: JobState,
+    ) -> StoreFuture<bool> {
+        let op = self
+            .inner
+            .delete_notification_for_state(notification_id, state);
+        self.chaos(op, JobSchedulerError::CantRemove)
+    }
+
+    fn delete_for_job(&mut self, job_id: Uuid) -> StoreFuture<()> {
+        let duplicate = self.duplicate().then(|| self.inner.delete_for_job(job_id));
+        let op = self.inner.delete_for_job(job_id);
+        self.chaos_write(op, duplicate, JobSchedulerError::CantRemove)
+    }
+}
@@ -0,0 +1,148 @@
+use crate::job::job_data_prost::NotificationData;
+use crate::{JobSchedulerError, JobStoredData};
+use prost::Message;
+
+///
+/// How a store encodes the jobs and notifications it keeps as bytes, so that the format can be
+/// picked per deployment: the compact protobuf by default, or a readable one like JSON when the
+/// stored data has to be inspected by hand
+pub trait Codec: Send + Sync {
+    fn encode_job(&self, data: &JobStoredData) -> Result<Vec<u8>, JobSchedulerError>;
+    fn decode_job(&self, bytes: &[u8]) -> Result<JobStoredData, JobSchedulerError>;
+    fn encode_notification(&self, data: &NotificationData) -> Result<Vec<u8>, JobSchedulerError>;
+    fn decode_notification(&self, bytes: &[u8]) -> Result<NotificationData, JobSchedulerError>;
+
+    ///
+    /// The extension of the files written in this format, e.g. by the file stores
+    fn extension(&self) -> &'static str {
+        "bin"
+    }
+}
+
+///
+/// Protobuf, the format the stores have always used
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProstCodec;
+
+impl Codec for ProstCodec {
+    fn encode_job(&self, data: &JobStoredData) -> Result<Vec<u8>, JobSchedulerError> {
+        Ok(data.encode_to_vec())
+    }
+
+    fn decode_job(&self, bytes: &[u8]) -> Result<JobStoredData, JobSchedulerError> {
+        JobStoredData::decode(bytes).map_err(|e| JobSchedulerError::Codec(e.to_string()))
+    }
+
+    fn encode_notification(&self, data: &NotificationData) -> Result<Vec<u8>, JobSchedulerError> {
+        Ok(data.encode_to_vec())
+    }
+
+    fn decode_notification(&self, bytes: &[u8]) -> Result<NotificationData, JobSchedulerError> {
+        NotificationData::decode(bytes).map_err(|e| JobSchedulerError::Codec(e.to_string()))
+    }
+
+    fn extension(&self) -> &'static str {
+        "pb"
+    }
+}
+
+///
+/// JSON, with the field names of the protobuf messages and the enums as their numbers
+#[cfg(feature = "json_codec")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonCodec;
+
+#[cfg(feature = "json_codec")]
+impl Codec for JsonCodec {
+    fn encode_job(&self, data: &JobStoredData) -> Result<Vec<u8>, JobSchedulerError> {
+        serde_json::to_vec(data).map_err(|e| JobSchedulerError::Codec(e.to_string()))
+    }
+
+    fn decode_job(&self, bytes: &[u8]) -> Result<JobStoredData, JobSchedulerError> {
+        serde_json::from_slice(bytes).map_err(|e| JobSchedulerError::Codec(e.to_string()))
+    }
+
+    fn encode_notification(&self, data: &NotificationData) -> Result<Vec<u8>, JobSchedulerError> {
+        serde_json::to_vec(data).map_err(|e| JobSchedulerError::Codec(e.to_string()))
+    }
+
+    fn decode_notification(&self, bytes: &[u8]) -> Result<NotificationData, JobSchedulerError> {
+        serde_json::from_slice(bytes).map_err(|e| JobSchedulerError::Codec(e.to_string()))
+    }
+
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+}
+
+///
+/// CBOR, a compact binary format that keeps the field names like JSON
+#[cfg(feature = "cbor_codec")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CborCodec;
+
+#[cfg(feature = "cbor_codec")]
+impl CborCodec {
+    fn encode<T: serde::Serialize>(data: &T) -> Result<Vec<u8>, JobSchedulerError> {
+        let mut bytes = vec![];
+        ciborium::ser::into_writer(data, &mut bytes)
+            .map_err(|e| JobSchedulerError::Codec(e.to_string()))?;
+        Ok(bytes)
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, JobSchedulerError> {
+        ciborium::de::from_reader(bytes).map_err(|e| JobSchedulerError::Codec(e.to_string()))
+    }
+}
+
+#[cfg(feature = "cbor_codec")]
+impl Codec for CborCodec {
+    fn encode_job(&self, data: &JobStoredData) -> Result<Vec<u8>, JobSchedulerError> {
+        CborCodec::encode(data)
+    }
+
+    fn decode_job(&self, bytes: &[u8]) -> Result<JobStoredData, JobSchedulerError> {
+        CborCodec::decode(bytes)
+    }
+
+    fn encode_notification(&self, data: &NotificationData) -> Result<Vec<u8>, JobSchedulerError> {
+        CborCodec::encode(data)
+    }
+
+    fn decode_notification(&self, bytes: &[u8]) -> Result<NotificationData, JobSchedulerError> {
+        CborCodec::decode(bytes)
+    }
+
+    fn extension(&self) -> &'static str {
+        "cbor"
+    }
+}
+
+///
+/// MessagePack, a compact binary format, with the field names kept so fields can be added
+#[cfg(feature = "msgpack_codec")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePackCodec;
+
+#[cfg(feature = "msgpack_codec")]
+impl Codec for MessagePackCodec {
+    fn encode_job(&self, data: &JobStoredData) -> Result<Vec<u8>, JobSchedulerError> {
+        rmp_serde::to_vec_named(data).map_err(|e| JobSchedulerError::Codec(e.to_string()))
+    }
+
+    fn decode_job(&self, bytes: &[u8]) -> Result<JobStoredData, JobSchedulerError> {
+        rmp_serde::from_slice(bytes).map_err(|e| JobSchedulerError::Codec(e.to_string()))
+    }
+
+    fn encode_notification(&self, data: &NotificationData) -> Result<Vec<u8>, JobSchedulerError> {
+        rmp_serde::to_vec_named(data).map_err(|e| JobSchedulerError::Codec(e.to_string()))
+    }
+
+    fn decode_notification(&self, bytes: &[u8]) -> Result<NotificationData, JobSchedulerError> {
+        rmp_serde::from_slice(bytes).map_err(|e| JobSchedulerError::Codec(e.to_string()))
+    }
+
+    fn extension(&self) -> &'static str {
+        "msgpack"
+    }
+}
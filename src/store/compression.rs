@@ -0,0 +1,240 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{JobAndNextTick, JobStoredData};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{JobAndNextTick, JobStoredData};
+use crate::store::{DataStore, InitStore, JobQuery, MetaDataStorage, Page};
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use uuid::Uuid;
+
+type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, JobSchedulerError>> + Send>>;
+
+/// Starts the blobs written by a `CompressedStore`, followed by the id of how the rest is encoded
+const MAGIC: &[u8] = b"TCSZ";
+const RAW: u8 = 0;
+const ZSTD: u8 = 1;
+const LZ4: u8 = 2;
+
+///
+/// The algorithms blobs can be compressed with, each behind its own feature
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionAlgorithm {
+    #[cfg(feature = "zstd_compression")]
+    Zstd { level: i32 },
+    #[cfg(feature = "lz4_compression")]
+    Lz4,
+}
+
+///
+/// Compresses the blobs at least `threshold` bytes long with `algorithm`. The algorithm is
+/// recorded in each blob, so blobs written uncompressed or with another algorithm stay readable.
+#[derive(Clone, Copy, Debug)]
+pub struct BlobCompression {
+    pub algorithm: CompressionAlgorithm,
+    pub threshold: usize,
+}
+
+impl BlobCompression {
+    ///
+    /// Compress the blobs of 1 KiB or more with `algorithm`
+    pub fn new(algorithm: CompressionAlgorithm) -> Self {
+        Self {
+            algorithm,
+            threshold: 1024,
+        }
+    }
+
+    pub fn threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn compress(&self, blob: Vec<u8>) -> Result<Vec<u8>, JobSchedulerError> {
+        if blob.len() < self.threshold {
+            // Marked as raw when it would pass for a compressed blob
+            if blob.starts_with(MAGIC) {
+                return Ok(header(RAW).into_iter().chain(blob).collect());
+            }
+            return Ok(blob);
+        }
+        let (id, compressed) = match self.algorithm {
+            #[cfg(feature = "zstd_compression")]
+            CompressionAlgorithm::Zstd { level } => (
+                ZSTD,
+                zstd::encode_all(blob.as_slice(), level)
+                    .map_err(|e| JobSchedulerError::Codec(e.to_string()))?,
+            ),
+            #[cfg(feature = "lz4_compression")]
+            CompressionAlgorithm::Lz4 => (LZ4, lz4_flex::compress_prepend_size(&blob)),
+        };
+        Ok(header(id).into_iter().chain(compressed).collect())
+    }
+
+    ///
+    /// The original of a blob, whether it was compressed or not
+    pub fn decompress(blob: Vec<u8>) -> Result<Vec<u8>, JobSchedulerError> {
+        if blob.len() <= MAGIC.len() || !blob.starts_with(MAGIC) {
+            return Ok(blob);
+        }
+        let id = blob[MAGIC.len()];
+        let body = &blob[MAGIC.len() + 1..];
+        match id {
+            RAW => Ok(body.to_vec()),
+            #[cfg(feature = "zstd_compression")]
+            ZSTD => zstd::decode_all(body).map_err(|e| JobSchedulerError::Codec(e.to_string())),
+            #[cfg(feature = "lz4_compression")]
+            LZ4 => lz4_flex::decompress_size_prepended(body)
+                .map_err(|e| JobSchedulerError::Codec(e.to_string())),
+            #[cfg(not(feature = "zstd_compression"))]
+            ZSTD => Err(JobSchedulerError::Codec(
+                "Blob compressed with zstd, enable the zstd_compression feature".to_string(),
+            )),
+            #[cfg(not(feature = "lz4_compression"))]
+            LZ4 => Err(JobSchedulerError::Codec(
+                "Blob compressed with lz4, enable the lz4_compression feature".to_string(),
+            )),
+            id => Err(JobSchedulerError::Codec(format!(
+                "Unknown blob compression {}",
+                id
+            ))),
+        }
+    }
+
+    fn compress_job(&self, mut data: JobStoredData) -> Result<JobStoredData, JobSchedulerError> {
+        data.extra = self.compress(data.extra)?;
+        data.last_payload = self.compress(data.last_payload)?;
+        Ok(data)
+    }
+
+    fn decompress_job(mut data: JobStoredData) -> Result<JobStoredData, JobSchedulerError> {
+        data.extra = BlobCompression::decompress(data.extra)?;
+        data.last_payload = BlobCompression::decompress(data.last_payload)?;
+        Ok(data)
+    }
+}
+
+fn header(id: u8) -> Vec<u8> {
+    MAGIC.iter().copied().chain([id]).collect()
+}
+
+///
+/// Wraps a metadata store and compresses the extra data and payloads of the jobs above a size
+/// before they are written to it, for backends storing many jobs with large blobs
+#[derive(Clone)]
+pub struct CompressedStore<S> {
+    pub inner: S,
+    pub compression: BlobCompression,
+}
+
+impl<S> CompressedStore<S> {
+    pub fn wrap(inner: S, compression: BlobCompression) -> Self {
+        Self { inner, compression }
+    }
+}
+
+impl<S> DataStore<JobStoredData> for CompressedStore<S>
+where
+    S: DataStore<JobStoredData>,
+{
+    fn get(&mut self, id: Uuid) -> StoreFuture<Option<JobStoredData>> {
+        let get = self.inner.get(id);
+        Box::pin(async move {
+            match get.await? {
+                Some(data) => BlobCompression::decompress_job(data).map(Some),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn add_or_update(&mut self, data: JobStoredData) -> StoreFuture<()> {
+        match self.compression.compress_job(data) {
+            Ok(data) => self.inner.add_or_update(data),
+            Err(e) => Box::pin(std::future::ready(Err(e))),
+        }
+    }
+
+    fn delete(&mut self, guid: Uuid) -> StoreFuture<()> {
+        self.inner.delete(guid)
+    }
+}
+
+impl<S> InitStore for CompressedStore<S>
+where
+    S: InitStore,
+{
+    fn init(&mut self) -> StoreFuture<()> {
+        self.inner.init()
+    }
+
+    fn inited(&mut self) -> StoreFuture<bool> {
+        self.inner.inited()
+    }
+}
+
+impl<S> MetaDataStorage for CompressedStore<S>
+where
+    S: MetaDataStorage,
+{
+    fn list_next_ticks(&mut self) -> StoreFuture<Vec<JobAndNextTick>> {
+        self.inner.list_next_ticks()
+    }
+
+    fn set_next_and_last_tick(
+        &mut self,
+        guid: Uuid,
+        next_tick: Option<DateTime<Utc>>,
+        last_tick: Option<DateTime<Utc>>,
+    ) -> StoreFuture<()> {
+        self.inner
+            .set_next_and_last_tick(guid, next_tick, last_tick)
+    }
+
+    fn time_till_next_job(&mut self) -> StoreFuture<Option<Duration>> {
+        self.inner.time_till_next_job()
+    }
+
+    fn list_job_ids(&mut self) -> StoreFuture<Vec<Uuid>> {
+        self.inner.list_job_ids()
+    }
+
+    fn get_many(&mut self, ids: &[Uuid]) -> StoreFuture<Vec<JobStoredData>> {
+        let get_many = self.inner.get_many(ids);
+        Box::pin(async move {
+            get_many
+                .await?
+                .into_iter()
+                .map(BlobCompression::decompress_job)
+                .collect()
+        })
+    }
+
+    fn list_followers(&mut self, job_id: Uuid) -> Option<StoreFuture<Vec<JobStoredData>>> {
+        let followers = self.inner.list_followers(job_id)?;
+        Some(Box::pin(async move {
+            followers
+                .await?
+                .into_iter()
+                .map(BlobCompression::decompress_job)
+                .collect()
+        }))
+    }
+
+    fn query_jobs(&mut self, query: &JobQuery) -> Option<StoreFuture<Page<JobStoredData>>> {
+        let query = self.inner.query_jobs(query)?;
+        Some(Box::pin(async move {
+            let page = query.await?;
+            let items = page
+                .items
+                .into_iter()
+                .map(BlobCompression::decompress_job)
+                .collect::<Result<_, _>>()?;
+            Ok(Page {
+                items,
+                next_cursor: page.next_cursor,
+            })
+        }))
+    }
+}
@@ -0,0 +1,1264 @@
+//! Conformance tests every `MetaDataStorage` implementation has to pass. The in-memory store is
+//! always tested, as are the SQLite one with a database in memory and the file one in a temporary
+//! directory. The Postgres, Nats and Redis stores need a running server, configured through the
+//! same environment variables as their `Default` implementations, and are run with `--ignored`.
+
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{
+    job_stored_data::Job, ConcurrencyPolicy, CronJob, JobStoredData, JobType, LeapDayPolicy,
+    MaintenanceWindowPolicy, MisfirePolicy, NonCronJob, RetryPolicy,
+};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{
+    job_stored_data::Job, ConcurrencyPolicy, CronJob, JobStoredData, JobType, LeapDayPolicy,
+    MaintenanceWindowPolicy, MisfirePolicy, NonCronJob, RetryPolicy,
+};
+use crate::store::{
+    ChaosStore, FaultConfig, JobListState, JobQuery, JobSort, MetaDataStorage, OfflineQueueStore,
+    RetryingStore, StoreRetryConfig,
+};
+use crate::SimpleMetadataStore;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+fn job_stored_data(id: Uuid, next_tick: u64) -> JobStoredData {
+    JobStoredData {
+        id: Some(id.into()),
+        last_updated: Some(1_600_000_000),
+        last_tick: Some(1_600_000_060),
+        next_tick,
+        job_type: JobType::Cron as i32,
+        count: 3,
+        extra: vec![1, 2, 3],
+        ran: true,
+        stopped: false,
+        last_run_failed: true,
+        skip_if_last_failed: true,
+        skip_if_upstream_failed: Some(Uuid::from_u128(0x1234).into()),
+        requires_approval: true,
+        maintenance_window_policy: MaintenanceWindowPolicy::Cancel.into(),
+        group: "conformance".to_string(),
+        starting_deadline_seconds: Some(30),
+        missed_count: 2,
+        tags: vec!["billing".to_string(), "nightly".to_string()],
+        warm_start: true,
+        cost: 5,
+        priority: -1,
+        exclusive_group: Some("db-migrations".to_string()),
+        consecutive_failures: 3,
+        last_payload: b"shard-1,shard-2".to_vec(),
+        name: "cleanup".to_string(),
+        retry_policy: Some(
+            RetryPolicy::exponential(
+                Duration::seconds(1).to_std().unwrap(),
+                Duration::minutes(1).to_std().unwrap(),
+                5,
+            )
+            .with_jitter(),
+        ),
+        last_error: Some("connection refused".to_string()),
+        concurrency_policy: ConcurrencyPolicy::Queue.into(),
+        leap_day_policy: LeapDayPolicy::LastDayOfFebruary.into(),
+        misfire_policy: MisfirePolicy::RunAll.into(),
+        after_job: Some(Uuid::from_u128(0x5678).into()),
+        min_interval_seconds: None,
+        job: Some(Job::CronJob(CronJob {
+            schedule: "0 0 * * * *".to_string(),
+            timezone: String::new(),
+            exclusions: vec![],
+            jitter_seconds: 0,
+        })),
+    }
+}
+
+async fn metadata_store_conformance(mut store: Box<dyn MetaDataStorage + Send + Sync>) {
+    store.init().await.expect("init");
+    assert!(store.inited().await.expect("inited"));
+
+    let id = Uuid::new_v4();
+    assert_eq!(store.get(id).await.expect("get missing"), None);
+
+    let due = (Utc::now() - Duration::seconds(60)).timestamp() as u64;
+    let data = job_stored_data(id, due);
+    store.add_or_update(data.clone()).await.expect("add");
+    assert_eq!(store.get(id).await.expect("get added"), Some(data.clone()));
+
+    let updated = JobStoredData {
+        count: 4,
+        ran: false,
+        stopped: true,
+        last_run_failed: false,
+        skip_if_upstream_failed: None,
+        min_interval_seconds: Some(300),
+        job: Some(Job::CronJob(CronJob {
+            schedule: "0 0 9 * * *".to_string(),
+            timezone: "Africa/Johannesburg".to_string(),
+            exclusions: vec!["0 0 9 25 12 *".to_string()],
+            jitter_seconds: 30,
+        })),
+        ..data
+    };
+    store.add_or_update(updated.clone()).await.expect("update");
+    assert_eq!(
+        store.get(id).await.expect("get updated"),
+        Some(updated.clone())
+    );
+
+    // Jobs that are not due yet may be left out, due jobs must be listed
+    let ticks = store.list_next_ticks().await.expect("list next ticks");
+    let listed = ticks
+        .iter()
+        .find(|t| t.id.as_ref().map(Uuid::from) == Some(id))
+        .expect("due job listed");
+    assert_eq!(listed.next_tick, updated.next_tick);
+    assert_eq!(listed.last_tick, updated.last_tick);
+    assert_eq!(listed.job_type, updated.job_type);
+    assert_eq!(listed.group, updated.group);
+
+    let next = Utc::now() + Duration::hours(1);
+    let last = Utc::now();
+    store
+        .set_next_and_last_tick(id, Some(next), Some(last))
+        .await
+        .expect("set next and last tick");
+    let stored = store.get(id).await.expect("get ticked").expect("ticked");
+    assert_eq!(stored.next_tick, next.timestamp() as u64);
+    assert_eq!(stored.last_tick, Some(last.timestamp() as u64));
+    assert_eq!(stored.job, updated.job);
+
+    let till = store
+        .time_till_next_job()
+        .await
+        .expect("time till next job")
+        .expect("a next job");
+    assert!(till <= std::time::Duration::from_secs(3600));
+
+    store
+        .set_next_and_last_tick(Uuid::new_v4(), Some(next), None)
+        .await
+        .expect_err("set ticks of missing job");
+
+    store.delete(id).await.expect("delete");
+    assert_eq!(store.get(id).await.expect("get deleted"), None);
+    store.delete(id).await.expect("delete missing");
+
+    query_conformance(&mut store).await;
+    followers_conformance(&mut store).await;
+}
+
+async fn followers_conformance(store: &mut Box<dyn MetaDataStorage + Send + Sync>) {
+    let upstream = Uuid::new_v4();
+    let follower = {
+        let mut data = job_stored_data(Uuid::new_v4(), 0);
+        data.job_type = JobType::AfterJob as i32;
+        data.after_job = Some(upstream.into());
+        data.job = Some(Job::NonCronJob(NonCronJob {
+            repeating: false,
+            repeated_every: 60,
+            monotonic: false,
+            fixed_delay: false,
+            instants: vec![],
+        }));
+        data
+    };
+    let other = JobStoredData {
+        id: Some(Uuid::new_v4().into()),
+        after_job: Some(Uuid::new_v4().into()),
+        ..follower.clone()
+    };
+    store.add_or_update(follower.clone()).await.expect("add");
+    store.add_or_update(other.clone()).await.expect("add");
+
+    // A store may leave the lookup to the scheduler
+    if let Some(followers) = store.list_followers(upstream) {
+        assert_eq!(
+            followers.await.expect("list followers"),
+            vec![follower.clone()]
+        );
+    }
+
+    for data in [follower, other] {
+        let id = data.id.as_ref().map(Uuid::from).unwrap();
+        store.delete(id).await.expect("delete");
+    }
+}
+
+async fn query_conformance(store: &mut Box<dyn MetaDataStorage + Send + Sync>) {
+    // A group of its own, the store may hold the jobs of earlier runs
+    let group = Uuid::new_v4().to_string();
+    let now = Utc::now().timestamp() as u64;
+    let mut ids = vec![];
+    for i in 0..5u64 {
+        let id = Uuid::new_v4();
+        let data = JobStoredData {
+            group: group.clone(),
+            stopped: i == 4,
+            tags: if i % 2 == 0 {
+                vec!["even".to_string()]
+            } else {
+                vec![]
+            },
+            ..job_stored_data(id, now + 3600 * (5 - i))
+        };
+        store.add_or_update(data).await.expect("add queried");
+        ids.push(id);
+    }
+
+    // Paged by next tick, two at a time
+    let mut query = JobQuery::new()
+        .group(&group)
+        .sort_by(JobSort::NextTick, false)
+        .limit(2);
+    let mut listed = vec![];
+    loop {
+        let page = query.run(store).await.expect("query jobs");
+        listed.extend(
+            page.items
+                .iter()
+                .filter_map(|j| j.id.as_ref().map(Uuid::from)),
+        );
+        match page.next_cursor {
+            Some(cursor) => query = query.after(cursor),
+            None => break,
+        }
+    }
+    let by_next_tick: Vec<_> = ids.iter().rev().copied().collect();
+    assert_eq!(listed, by_next_tick);
+
+    let tagged = JobQuery::new()
+        .group(&group)
+        .tag("even")
+        .state(JobListState::Active);
+    let page = tagged.run(store).await.expect("query tagged");
+    let mut tagged_ids: Vec<_> = page
+        .items
+        .iter()
+        .filter_map(|j| j.id.as_ref().map(Uuid::from))
+        .collect();
+    tagged_ids.sort();
+    let mut expected = vec![ids[0], ids[2]];
+    expected.sort();
+    assert_eq!(tagged_ids, expected);
+    assert_eq!(page.next_cursor, None);
+
+    // Read at once, leaving out the missing job
+    let jobs = store
+        .get_many(&[ids[1], Uuid::new_v4(), ids[3]])
+        .await
+        .expect("get many");
+    let mut read: Vec<_> = jobs
+        .iter()
+        .filter_map(|j| j.id.as_ref().map(Uuid::from))
+        .collect();
+    read.sort();
+    let mut expected = vec![ids[1], ids[3]];
+    expected.sort();
+    assert_eq!(read, expected);
+
+    for id in ids {
+        store.delete(id).await.expect("delete queried");
+    }
+}
+
+#[tokio::test]
+async fn simple_metadata_store() {
+    metadata_store_conformance(Box::<SimpleMetadataStore>::default()).await;
+}
+
+#[tokio::test]
+async fn chaos_store_duplicating_writes() {
+    let config = FaultConfig {
+        duplicate_probability: 1.0,
+        ..Default::default()
+    };
+    metadata_store_conformance(Box::new(ChaosStore::wrap(
+        SimpleMetadataStore::default(),
+        config,
+    )))
+    .await;
+}
+
+#[tokio::test]
+async fn retrying_store_over_faults() {
+    let faults = FaultConfig {
+        error_probability: 0.3,
+        ..Default::default()
+    };
+    let config = StoreRetryConfig {
+        policy: RetryPolicy::fixed(std::time::Duration::ZERO, 10),
+        failure_threshold: u32::MAX,
+        ..Default::default()
+    };
+    metadata_store_conformance(Box::new(RetryingStore::wrap(
+        ChaosStore::wrap(SimpleMetadataStore::default(), faults),
+        config,
+    )))
+    .await;
+}
+
+#[tokio::test]
+async fn retrying_store_circuit() {
+    use crate::store::{DataStore, StoreRetryEvent};
+
+    let faults = FaultConfig {
+        error_probability: 1.0,
+        ..Default::default()
+    };
+    let config = StoreRetryConfig {
+        policy: RetryPolicy::fixed(std::time::Duration::ZERO, 3),
+        failure_threshold: 2,
+        open_for: std::time::Duration::from_secs(3600),
+    };
+    let events = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let seen = events.clone();
+    let mut store = RetryingStore::wrap(
+        ChaosStore::wrap(SimpleMetadataStore::default(), faults),
+        config,
+    )
+    .on_event(Box::new(move |event| seen.lock().unwrap().push(event)));
+
+    let id = Uuid::new_v4();
+    for _ in 0..3 {
+        store.get(id).await.expect_err("store is down");
+    }
+    let events = events.lock().unwrap();
+    let names: Vec<_> = events
+        .iter()
+        .map(|event| match event {
+            StoreRetryEvent::Retry { attempt, .. } => format!("retry {}", attempt),
+            StoreRetryEvent::GaveUp { attempts, .. } => format!("gave up after {}", attempts),
+            StoreRetryEvent::CircuitOpened { operation } => format!("opened on {}", operation),
+            StoreRetryEvent::CircuitClosed { operation } => format!("closed on {}", operation),
+        })
+        .collect();
+    // The third get fails at once on the open circuit
+    assert_eq!(
+        names,
+        vec![
+            "retry 1",
+            "retry 2",
+            "gave up after 3",
+            "retry 1",
+            "retry 2",
+            "gave up after 3",
+            "opened on get",
+        ]
+    );
+}
+
+/// A simple store that fails every operation while `down` is set
+#[derive(Default)]
+struct Outage {
+    store: SimpleMetadataStore,
+    down: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+type TestFuture<T> = std::pin::Pin<
+    Box<dyn std::future::Future<Output = Result<T, crate::JobSchedulerError>> + Send>,
+>;
+
+impl Outage {
+    fn run<T: Send + 'static>(&self, op: TestFuture<T>) -> TestFuture<T> {
+        if self.down.load(std::sync::atomic::Ordering::SeqCst) {
+            Box::pin(std::future::ready(Err(crate::JobSchedulerError::CantInit)))
+        } else {
+            op
+        }
+    }
+}
+
+impl crate::store::DataStore<JobStoredData> for Outage {
+    fn get(&mut self, id: Uuid) -> TestFuture<Option<JobStoredData>> {
+        let op = self.store.get(id);
+        self.run(op)
+    }
+
+    fn add_or_update(&mut self, data: JobStoredData) -> TestFuture<()> {
+        let op = self.store.add_or_update(data);
+        self.run(op)
+    }
+
+    fn delete(&mut self, guid: Uuid) -> TestFuture<()> {
+        let op = self.store.delete(guid);
+        self.run(op)
+    }
+}
+
+impl crate::store::InitStore for Outage {
+    fn init(&mut self) -> TestFuture<()> {
+        let op = self.store.init();
+        self.run(op)
+    }
+
+    fn inited(&mut self) -> TestFuture<bool> {
+        let op = self.store.inited();
+        self.run(op)
+    }
+}
+
+impl MetaDataStorage for Outage {
+    fn list_next_ticks(&mut self) -> TestFuture<Vec<crate::JobAndNextTick>> {
+        let op = self.store.list_next_ticks();
+        self.run(op)
+    }
+
+    fn set_next_and_last_tick(
+        &mut self,
+        guid: Uuid,
+        next_tick: Option<chrono::DateTime<Utc>>,
+        last_tick: Option<chrono::DateTime<Utc>>,
+    ) -> TestFuture<()> {
+        let op = self
+            .store
+            .set_next_and_last_tick(guid, next_tick, last_tick);
+        self.run(op)
+    }
+
+    fn time_till_next_job(&mut self) -> TestFuture<Option<std::time::Duration>> {
+        let op = self.store.time_till_next_job();
+        self.run(op)
+    }
+}
+
+#[tokio::test]
+async fn offline_queue_store() {
+    metadata_store_conformance(Box::new(OfflineQueueStore::wrap(
+        Outage::default(),
+        Default::default(),
+    )))
+    .await;
+}
+
+#[tokio::test]
+async fn offline_queue_store_outage() {
+    use crate::store::{DataLoss, DataStore, InitStore, OfflineQueueConfig, OfflineQueueEvent};
+    use std::sync::atomic::Ordering;
+
+    let outage = Outage::default();
+    let down = outage.down.clone();
+    let events = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let seen = events.clone();
+    let mut store = OfflineQueueStore::wrap(outage, OfflineQueueConfig { capacity: 2 })
+        .on_event(Box::new(move |event| seen.lock().unwrap().push(event)));
+    store.init().await.expect("init");
+    let id = Uuid::new_v4();
+    store
+        .add_or_update(job_stored_data(id, 100))
+        .await
+        .expect("add");
+
+    down.store(true, Ordering::SeqCst);
+    for tick in [200, 300, 400] {
+        let tick = chrono::DateTime::from_timestamp(tick, 0);
+        store
+            .set_next_and_last_tick(id, tick, tick)
+            .await
+            .expect("queued while down");
+    }
+    store.get(id).await.expect_err("store is down");
+    assert_eq!(store.queued().await, 2);
+
+    down.store(false, Ordering::SeqCst);
+    let job = store.get(id).await.expect("get").expect("stored");
+    assert_eq!(job.next_tick, 400);
+    assert_eq!(job.last_tick, Some(400));
+    assert_eq!(store.queued().await, 0);
+
+    let events = events.lock().unwrap();
+    let names: Vec<_> = events
+        .iter()
+        .map(|event| match event {
+            OfflineQueueEvent::Queued { queued, .. } => format!("queued {}", queued),
+            OfflineQueueEvent::Flushed { updates, remaining } => {
+                format!("flushed {}, {} left", updates, remaining)
+            }
+            OfflineQueueEvent::DataLost { reason, .. } => {
+                assert_eq!(*reason, DataLoss::Overflow);
+                "lost".to_string()
+            }
+        })
+        .collect();
+    // The oldest update makes room for the newest
+    assert_eq!(
+        names,
+        vec![
+            "queued 1",
+            "queued 2",
+            "lost",
+            "queued 2",
+            "flushed 2, 0 left"
+        ]
+    );
+}
+
+/// Partitions in memory, one simple store each
+#[derive(Clone, Default)]
+struct MemoryPartitions {
+    partitions: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+impl crate::store::PartitionProvider for MemoryPartitions {
+    fn open(
+        &self,
+        name: &str,
+    ) -> std::pin::Pin<
+        Box<
+            dyn std::future::Future<
+                    Output = Result<
+                        Box<dyn MetaDataStorage + Send + Sync>,
+                        crate::JobSchedulerError,
+                    >,
+                > + Send,
+        >,
+    > {
+        self.partitions.lock().unwrap().push(name.to_string());
+        Box::pin(async move {
+            let mut store = SimpleMetadataStore::default();
+            crate::store::InitStore::init(&mut store).await?;
+            let store: Box<dyn MetaDataStorage + Send + Sync> = Box::new(store);
+            Ok(store)
+        })
+    }
+
+    fn list(
+        &self,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<String>, crate::JobSchedulerError>> + Send>,
+    > {
+        Box::pin(std::future::ready(Ok(vec![])))
+    }
+
+    fn drop_partition(
+        &self,
+        name: &str,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<(), crate::JobSchedulerError>> + Send>,
+    > {
+        self.partitions.lock().unwrap().retain(|p| p != name);
+        Box::pin(std::future::ready(Ok(())))
+    }
+}
+
+#[tokio::test]
+async fn partitioned_store() {
+    let partitions = MemoryPartitions::default();
+    let mut store =
+        crate::store::PartitionedStore::new(partitions.clone(), std::time::Duration::from_secs(60));
+    metadata_store_conformance(Box::new(store.clone())).await;
+    // The first job moved from the minute it was due in to the partition of its next tick, an
+    // hour away. The partition it left has ended, so it is dropped when the due jobs are listed,
+    // unlike the emptied partitions of the jobs queried, which are still to come.
+    let opened = partitions.partitions.lock().unwrap().len();
+    store.list_next_ticks().await.expect("list next ticks");
+    assert_eq!(partitions.partitions.lock().unwrap().len(), opened - 1);
+}
+
+#[cfg(any(feature = "zstd_compression", feature = "lz4_compression"))]
+#[tokio::test]
+async fn compressed_store() {
+    use crate::store::{
+        BlobCompression, CompressedStore, CompressionAlgorithm, DataStore, InitStore,
+    };
+
+    let algorithms = [
+        #[cfg(feature = "zstd_compression")]
+        CompressionAlgorithm::Zstd { level: 3 },
+        #[cfg(feature = "lz4_compression")]
+        CompressionAlgorithm::Lz4,
+    ];
+    for algorithm in algorithms {
+        let compression = BlobCompression::new(algorithm).threshold(0);
+        metadata_store_conformance(Box::new(CompressedStore::wrap(
+            SimpleMetadataStore::default(),
+            compression,
+        )))
+        .await;
+
+        // Blobs written without compression are read back as they are
+        let mut store = CompressedStore::wrap(SimpleMetadataStore::default(), compression);
+        store.init().await.expect("init");
+        let id = Uuid::new_v4();
+        let data = job_stored_data(id, 0);
+        store
+            .inner
+            .add_or_update(data.clone())
+            .await
+            .expect("add raw");
+        assert_eq!(store.get(id).await.expect("get raw"), Some(data.clone()));
+
+        let blob = b"TCSZ is not a header here".to_vec();
+        let kept = BlobCompression::new(algorithm).threshold(usize::MAX);
+        let stored = kept.compress(blob.clone()).expect("compress");
+        assert_eq!(
+            BlobCompression::decompress(stored).expect("decompress"),
+            blob
+        );
+    }
+}
+
+#[cfg(feature = "postgres_storage")]
+#[tokio::test]
+#[ignore = "needs a Postgres server"]
+async fn postgres_metadata_store() {
+    std::env::set_var("POSTGRES_INIT_METADATA", "true");
+    metadata_store_conformance(Box::<crate::PostgresMetadataStore>::default()).await;
+}
+
+/// A job table as made by the first release, upgraded by `init` and holding a job written by
+/// that release
+#[cfg(feature = "postgres_storage")]
+#[tokio::test]
+#[ignore = "needs a Postgres server"]
+async fn postgres_metadata_store_migration() {
+    use crate::store::{DataStore, InitStore};
+    let table = "job_migration";
+    let client = match crate::postgres::PostgresStore::default().init().await {
+        Ok(crate::postgres::PostgresStore::Inited(client)) => client,
+        _ => panic!("connect"),
+    };
+    let id = Uuid::new_v4();
+    {
+        let client = client.read().await;
+        client
+            .execute(&*("DROP TABLE IF EXISTS ".to_string() + table), &[])
+            .await
+            .expect("drop");
+        client
+            .execute(
+                &*("CREATE TABLE ".to_string()
+                    + table
+                    + " (id UUID, last_updated BIGINT, next_tick BIGINT, last_tick BIGINT, \
+                    job_type INTEGER NOT NULL, count INTEGER, ran BOOL, stopped BOOL, \
+                    schedule TEXT, repeating BOOL, repeated_every BIGINT, extra BYTEA, \
+                    CONSTRAINT pk_migration PRIMARY KEY (id))"),
+                &[],
+            )
+            .await
+            .expect("create the baseline table");
+        client
+            .execute(
+                &*("INSERT INTO ".to_string()
+                    + table
+                    + " (id, next_tick, job_type, count, ran, stopped, schedule) \
+                    VALUES ($1, 100, 0, 3, true, false, '0 0 9 * * *')"),
+                &[&id],
+            )
+            .await
+            .expect("insert a baseline job");
+    }
+
+    let mut store = crate::PostgresMetadataStore {
+        init_tables: true,
+        table: table.to_string(),
+        ..Default::default()
+    };
+    store.init().await.expect("init");
+    let old = store.get(id).await.expect("get").expect("the baseline job");
+    assert_eq!(old.count, 3);
+    assert_eq!(
+        old.schedule().map(|s| s.to_string()),
+        Some("0 0 9 * * *".to_string())
+    );
+    assert_eq!(old.tags, Vec::<String>::new());
+    assert_eq!(old.min_interval_seconds, None);
+    store.delete(id).await.expect("delete");
+    metadata_store_conformance(Box::new(store)).await;
+}
+
+#[cfg(feature = "nats_storage")]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a Nats server"]
+async fn nats_metadata_store() {
+    metadata_store_conformance(Box::<crate::NatsMetadataStore>::default()).await;
+}
+
+#[cfg(feature = "redis_storage")]
+#[tokio::test]
+#[ignore = "needs a Redis server"]
+async fn redis_metadata_store() {
+    metadata_store_conformance(Box::<crate::RedisMetadataStore>::default()).await;
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn sqlite_metadata_store() {
+    let store = crate::SqliteStore::new(":memory:");
+    metadata_store_conformance(Box::new(crate::SqliteMetadataStore::new(store))).await;
+}
+
+#[cfg(any(feature = "sqlite", feature = "file_storage"))]
+async fn notification_store_conformance(
+    store: &mut (dyn crate::store::NotificationStore + Send + Sync),
+    job_id: Uuid,
+) {
+    use crate::job::job_data_prost::{JobIdAndNotification, JobState, NotificationData};
+    let id = Uuid::new_v4();
+    let data = NotificationData {
+        job_id: Some(JobIdAndNotification {
+            job_id: Some(job_id.into()),
+            notification_id: Some(id.into()),
+        }),
+        job_states: vec![JobState::Started as i32, JobState::Done as i32],
+        extra: vec![1, 2, 3],
+    };
+    store.add_or_update(data.clone()).await.expect("add");
+    assert_eq!(store.get(id).await.expect("get"), Some(data.clone()));
+
+    assert_eq!(
+        store
+            .list_notification_guids_for_job_and_state(job_id, JobState::Done)
+            .await
+            .expect("list for state"),
+        vec![id]
+    );
+    assert!(store
+        .list_notification_guids_for_job_and_state(job_id, JobState::Stop)
+        .await
+        .expect("list for other state")
+        .is_empty());
+    assert_eq!(
+        store
+            .list_notification_guids_for_job_id(job_id)
+            .await
+            .expect("list for job"),
+        vec![id]
+    );
+
+    assert!(store
+        .delete_notification_for_state(id, JobState::Done)
+        .await
+        .expect("delete state"));
+    assert!(!store
+        .delete_notification_for_state(id, JobState::Done)
+        .await
+        .expect("delete state again"));
+    let stored = store.get(id).await.expect("get").expect("still stored");
+    assert_eq!(stored.job_states, vec![JobState::Started as i32]);
+
+    store.delete_for_job(job_id).await.expect("delete for job");
+    assert_eq!(store.get(id).await.expect("get deleted"), None);
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn sqlite_notification_store() {
+    use crate::store::{DataStore, InitStore};
+
+    // Both stores in the one database in memory, the notifications needing their job
+    let sqlite = crate::SqliteStore::new(":memory:")
+        .init()
+        .await
+        .expect("open");
+    let mut jobs = crate::SqliteMetadataStore::new(sqlite.clone());
+    jobs.init().await.expect("init metadata");
+    let mut store = crate::SqliteNotificationStore::new(sqlite);
+    store.init().await.expect("init notifications");
+
+    let job_id = Uuid::new_v4();
+    jobs.add_or_update(job_stored_data(job_id, 0))
+        .await
+        .expect("add job");
+    notification_store_conformance(&mut store, job_id).await;
+}
+
+#[cfg(feature = "file_storage")]
+fn file_storage_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join(Uuid::new_v4().to_string())
+}
+
+#[cfg(feature = "file_storage")]
+#[tokio::test]
+async fn file_metadata_store() {
+    let dir = file_storage_dir();
+    metadata_store_conformance(Box::new(crate::FileMetadataStore::new(&dir))).await;
+    #[cfg(feature = "cbor_codec")]
+    metadata_store_conformance(Box::new(
+        crate::FileMetadataStore::new(&dir).codec(crate::store::CborCodec),
+    ))
+    .await;
+    std::fs::remove_dir_all(dir).expect("remove dir");
+}
+
+#[cfg(feature = "file_storage")]
+#[tokio::test]
+async fn file_notification_store() {
+    use crate::store::InitStore;
+
+    let dir = file_storage_dir();
+    let mut store = crate::FileNotificationStore::new(&dir);
+    store.init().await.expect("init");
+    notification_store_conformance(&mut store, Uuid::new_v4()).await;
+    #[cfg(feature = "cbor_codec")]
+    {
+        let mut store = crate::FileNotificationStore::new(&dir).codec(crate::store::CborCodec);
+        notification_store_conformance(&mut store, Uuid::new_v4()).await;
+    }
+    std::fs::remove_dir_all(dir).expect("remove dir");
+}
+
+/// Two candidates of the same election, taking turns at being the leader
+async fn leader_election_conformance(
+    mut first: Box<dyn crate::LeaderElection + Send + Sync>,
+    mut second: Box<dyn crate::LeaderElection + Send + Sync>,
+) {
+    assert!(first.acquire().await.expect("first acquires"));
+    assert!(!second.acquire().await.expect("second waits"));
+    assert!(first.acquire().await.expect("first stays"));
+    first.release().await.expect("first releases");
+    assert!(second.acquire().await.expect("second takes over"));
+    assert!(!first.acquire().await.expect("first waits"));
+    second.release().await.expect("second releases");
+}
+
+#[tokio::test]
+async fn memory_leader_election() {
+    let election = crate::MemoryLeaderElection::default();
+    leader_election_conformance(Box::new(election.clone()), Box::new(election)).await;
+}
+
+#[cfg(feature = "postgres_storage")]
+#[tokio::test]
+#[ignore = "needs a Postgres server"]
+async fn postgres_leader_election() {
+    leader_election_conformance(
+        Box::<crate::PostgresLeaderElection>::default(),
+        Box::<crate::PostgresLeaderElection>::default(),
+    )
+    .await;
+}
+
+#[cfg(feature = "nats_storage")]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a Nats server"]
+async fn nats_leader_election() {
+    leader_election_conformance(
+        Box::<crate::NatsLeaderElection>::default(),
+        Box::<crate::NatsLeaderElection>::default(),
+    )
+    .await;
+}
+
+#[cfg(feature = "file_storage")]
+#[tokio::test]
+async fn file_store_lifecycle() {
+    let dir = file_storage_dir();
+    crate::test_harness::scheduler_lifecycle(|| {
+        (
+            Box::new(crate::FileMetadataStore::new(&dir)),
+            Box::new(crate::FileNotificationStore::new(&dir)),
+        )
+    })
+    .await;
+    std::fs::remove_dir_all(dir).expect("remove dir");
+}
+
+#[cfg(feature = "postgres_storage")]
+#[tokio::test]
+#[ignore = "needs a Postgres server"]
+async fn postgres_store_lifecycle() {
+    postgres_lifecycle().await;
+}
+
+#[cfg(feature = "postgres_storage")]
+async fn postgres_lifecycle() {
+    std::env::set_var("POSTGRES_INIT_METADATA", "true");
+    std::env::set_var("POSTGRES_INIT_NOTIFICATIONS", "true");
+    crate::test_harness::scheduler_lifecycle(|| {
+        (
+            Box::<crate::PostgresMetadataStore>::default(),
+            Box::<crate::PostgresNotificationStore>::default(),
+        )
+    })
+    .await;
+}
+
+#[cfg(feature = "nats_storage")]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a Nats server"]
+async fn nats_store_lifecycle() {
+    nats_lifecycle().await;
+}
+
+#[cfg(feature = "nats_storage")]
+async fn nats_lifecycle() {
+    crate::test_harness::scheduler_lifecycle(|| {
+        (
+            Box::<crate::NatsMetadataStore>::default(),
+            Box::<crate::NatsNotificationStore>::default(),
+        )
+    })
+    .await;
+}
+
+#[cfg(all(feature = "postgres_storage", feature = "test_harness"))]
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn postgres_container_lifecycle() {
+    let (_server, url) = crate::test_harness::postgres_server().await;
+    std::env::set_var("POSTGRES_URL", url);
+    postgres_lifecycle().await;
+}
+
+#[cfg(all(feature = "nats_storage", feature = "test_harness"))]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs Docker"]
+async fn nats_container_lifecycle() {
+    let (_server, url) = crate::test_harness::nats_server().await;
+    std::env::set_var("NATS_HOST", url);
+    nats_lifecycle().await;
+}
+
+/// Two schedulers racing for the runs of a job, each run going to the first of them
+async fn run_lock_conformance(
+    mut first: Box<dyn crate::RunLock + Send + Sync>,
+    mut second: Box<dyn crate::RunLock + Send + Sync>,
+) {
+    let job_id = Uuid::new_v4();
+    let due = Utc::now();
+    let run = crate::run_id(job_id, due);
+    assert_eq!(run, crate::run_id(job_id, due));
+    let ttl = std::time::Duration::from_secs(1);
+    assert!(first
+        .try_acquire_run_lock(job_id, run, ttl)
+        .await
+        .expect("first locks"));
+    assert!(!second
+        .try_acquire_run_lock(job_id, run, ttl)
+        .await
+        .expect("second is locked out"));
+    let next_run = crate::run_id(job_id, due + Duration::seconds(1));
+    assert!(second
+        .try_acquire_run_lock(job_id, next_run, ttl)
+        .await
+        .expect("second locks the next run"));
+    assert!(!first
+        .try_acquire_run_lock(job_id, next_run, ttl)
+        .await
+        .expect("first is locked out"));
+    let other_job = Uuid::new_v4();
+    assert!(first
+        .try_acquire_run_lock(other_job, crate::run_id(other_job, due), ttl)
+        .await
+        .expect("first locks another job"));
+}
+
+#[tokio::test]
+async fn memory_run_lock() {
+    let run_lock = crate::MemoryRunLock::default();
+    run_lock_conformance(Box::new(run_lock.clone()), Box::new(run_lock)).await;
+}
+
+#[cfg(feature = "postgres_storage")]
+#[tokio::test]
+#[ignore = "needs a Postgres server"]
+async fn postgres_run_lock() {
+    let run_lock = || crate::PostgresRunLock {
+        init_tables: true,
+        ..Default::default()
+    };
+    run_lock_conformance(Box::new(run_lock()), Box::new(run_lock())).await;
+}
+
+#[cfg(feature = "nats_storage")]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a Nats server"]
+async fn nats_run_lock() {
+    run_lock_conformance(
+        Box::<crate::NatsRunLock>::default(),
+        Box::<crate::NatsRunLock>::default(),
+    )
+    .await;
+}
+
+#[cfg(feature = "redis_storage")]
+#[tokio::test]
+#[ignore = "needs a Redis server"]
+async fn redis_run_lock() {
+    run_lock_conformance(
+        Box::<crate::RedisRunLock>::default(),
+        Box::<crate::RedisRunLock>::default(),
+    )
+    .await;
+}
+
+/// The runs of two jobs kept and read back, the latest first
+async fn run_history_conformance(mut store: Box<dyn crate::RunHistoryStore + Send + Sync>) {
+    store.init().await.expect("init");
+    let job_id = Uuid::new_v4();
+    let other_job = Uuid::new_v4();
+    let scheduled = Utc::now() - Duration::seconds(10);
+    let runs: Vec<_> = (0..3)
+        .map(|i| {
+            let started = scheduled + Duration::seconds(i);
+            let (outcome, error) = match i {
+                1 => (crate::JobNotification::Failed, Some("boom".to_string())),
+                _ => (crate::JobNotification::Succeeded, None),
+            };
+            crate::JobRun::new(
+                job_id,
+                scheduled,
+                started,
+                started + Duration::milliseconds(250),
+                outcome,
+                error,
+            )
+        })
+        .collect();
+    for run in runs.iter().cloned() {
+        store.record(run).await.expect("record");
+    }
+    let other_run = crate::JobRun::new(
+        other_job,
+        scheduled,
+        scheduled,
+        scheduled,
+        crate::JobNotification::Succeeded,
+        None,
+    );
+    store.record(other_run.clone()).await.expect("record other");
+
+    let history = store.history(job_id, 2).await.expect("history");
+    assert_eq!(history, vec![runs[2].clone(), runs[1].clone()]);
+    assert_eq!(history[1].error.as_deref(), Some("boom"));
+    assert_eq!(history[1].duration(), std::time::Duration::from_millis(250));
+    let history = store.history(other_job, 10).await.expect("other history");
+    assert_eq!(history, vec![other_run]);
+    let history = store.history(Uuid::new_v4(), 10).await.expect("no history");
+    assert!(history.is_empty());
+}
+
+#[tokio::test]
+async fn simple_run_history() {
+    run_history_conformance(Box::<crate::SimpleRunHistoryStore>::default()).await;
+
+    let mut store = crate::SimpleRunHistoryStore::new(2);
+    let job_id = Uuid::new_v4();
+    for _ in 0..3 {
+        let now = Utc::now();
+        let run = crate::JobRun::new(
+            job_id,
+            now,
+            now,
+            now,
+            crate::JobNotification::Succeeded,
+            None,
+        );
+        crate::RunHistoryStore::record(&mut store, run)
+            .await
+            .expect("record");
+    }
+    let history = crate::RunHistoryStore::history(&mut store, job_id, 10)
+        .await
+        .expect("history");
+    assert_eq!(history.len(), 2);
+}
+
+#[cfg(feature = "postgres_storage")]
+#[tokio::test]
+#[ignore = "needs a Postgres server"]
+async fn postgres_run_history() {
+    run_history_conformance(Box::new(crate::PostgresRunHistoryStore {
+        init_tables: true,
+        ..Default::default()
+    }))
+    .await;
+}
+
+#[cfg(feature = "nats_storage")]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a Nats server"]
+async fn nats_run_history() {
+    run_history_conformance(Box::<crate::NatsRunHistoryStore>::default()).await;
+}
+
+/// Two runs of a job in a run history, and one ended before them that is left out of exports
+async fn run_history_to_export() -> (
+    crate::SimpleRunHistoryStore,
+    std::ops::Range<chrono::DateTime<Utc>>,
+) {
+    use crate::RunHistoryStore;
+    use chrono::TimeZone;
+
+    let mut store = crate::SimpleRunHistoryStore::default();
+    let job_id = Uuid::from_u128(0x42);
+    let at = Utc.with_ymd_and_hms(2024, 1, 1, 2, 0, 0).unwrap();
+    let runs = [
+        (
+            at - Duration::days(1),
+            crate::JobNotification::Succeeded,
+            None,
+        ),
+        (at, crate::JobNotification::Succeeded, None),
+        (
+            at + Duration::days(1),
+            crate::JobNotification::Failed,
+            Some("disk full, \"/data\"".to_string()),
+        ),
+    ];
+    for (scheduled, outcome, error) in runs {
+        let ended = scheduled + Duration::milliseconds(1500);
+        let run = crate::JobRun::new(job_id, scheduled, scheduled, ended, outcome, error);
+        store.record(run).await.expect("record");
+    }
+    (store, at..at + Duration::days(2))
+}
+
+#[tokio::test]
+async fn run_history_csv_export() {
+    use crate::RunHistoryStore;
+    use std::io::Read;
+
+    let (mut store, range) = run_history_to_export().await;
+    let path = std::env::temp_dir().join(Uuid::new_v4().to_string() + ".csv");
+    let file = std::fs::File::create(&path).expect("create");
+    let exported = store
+        .export(range, crate::ExportFormat::Csv, Box::new(file))
+        .await
+        .expect("export");
+    assert_eq!(exported, 2);
+    let mut csv = String::new();
+    std::fs::File::open(&path)
+        .expect("open")
+        .read_to_string(&mut csv)
+        .expect("read");
+    std::fs::remove_file(path).expect("remove");
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[0],
+        "id,job_id,scheduled_at,started_at,ended_at,duration_ms,outcome,error"
+    );
+    let job_id = Uuid::from_u128(0x42).to_string();
+    assert!(lines[1].ends_with(&format!(
+        "{},2024-01-01T02:00:00.000Z,2024-01-01T02:00:00.000Z,2024-01-01T02:00:01.500Z,1500,Succeeded,",
+        job_id
+    )));
+    assert!(lines[2].ends_with(&format!(
+        "{},2024-01-02T02:00:00.000Z,2024-01-02T02:00:00.000Z,2024-01-02T02:00:01.500Z,1500,Failed,\"disk full, \"\"/data\"\"\"",
+        job_id
+    )));
+}
+
+#[cfg(feature = "parquet_export")]
+#[tokio::test]
+async fn run_history_parquet_export() {
+    use crate::RunHistoryStore;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
+
+    let (mut store, range) = run_history_to_export().await;
+    let path = std::env::temp_dir().join(Uuid::new_v4().to_string() + ".parquet");
+    let file = std::fs::File::create(&path).expect("create");
+    let exported = store
+        .export(range, crate::ExportFormat::Parquet, Box::new(file))
+        .await
+        .expect("export");
+    assert_eq!(exported, 2);
+    let reader =
+        SerializedFileReader::new(std::fs::File::open(&path).expect("open")).expect("parquet file");
+    assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+    let rows: Vec<_> = reader
+        .get_row_iter(None)
+        .expect("rows")
+        .map(|row| row.expect("row"))
+        .collect();
+    std::fs::remove_file(path).expect("remove");
+    let column = |row: usize, name: &str| {
+        rows[row]
+            .get_column_iter()
+            .find(|(column, _)| column.as_str() == name)
+            .map(|(_, field)| field.clone())
+            .expect("column")
+    };
+    assert_eq!(column(0, "duration_ms"), Field::Long(1500));
+    assert_eq!(column(0, "outcome"), Field::Str("Succeeded".to_string()));
+    assert_eq!(column(0, "error"), Field::Null);
+    assert_eq!(
+        column(1, "error"),
+        Field::Str("disk full, \"/data\"".to_string())
+    );
+    assert_eq!(
+        column(1, "ended_at"),
+        Field::TimestampMillis(1_704_160_801_500)
+    );
+}
+
+/// The bytes written by the version that added the fixture, which every later version has to
+/// decode. It is never regenerated: a field added since decodes to its default here, and is
+/// listed so, and a request that needs older bytes of its own adds a fixture next to this one.
+#[cfg(feature = "has_bytes")]
+#[test]
+fn golden_job_stored_data() {
+    use prost::Message;
+
+    const FIXTURE: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/job_stored_data.bin"
+    ));
+    let data = JobStoredData {
+        id: Some(Uuid::from_u128(0xabcd).into()),
+        last_updated: Some(1_600_000_000),
+        last_tick: Some(1_600_000_060),
+        next_tick: 1_600_000_120,
+        job_type: JobType::Cron as i32,
+        count: 3,
+        extra: vec![1, 2, 3],
+        ran: true,
+        stopped: false,
+        last_run_failed: true,
+        skip_if_last_failed: true,
+        skip_if_upstream_failed: Some(Uuid::from_u128(0x1234).into()),
+        requires_approval: true,
+        // Added since the fixture, all decoding to their defaults
+        maintenance_window_policy: MaintenanceWindowPolicy::Defer.into(),
+        group: String::new(),
+        starting_deadline_seconds: None,
+        missed_count: 0,
+        tags: vec![],
+        warm_start: false,
+        cost: 0,
+        priority: 0,
+        exclusive_group: None,
+        consecutive_failures: 0,
+        last_payload: vec![],
+        name: String::new(),
+        retry_policy: None,
+        last_error: None,
+        concurrency_policy: ConcurrencyPolicy::Allow.into(),
+        leap_day_policy: LeapDayPolicy::LeapYearsOnly.into(),
+        misfire_policy: MisfirePolicy::RunOnce.into(),
+        after_job: None,
+        min_interval_seconds: None,
+        job: Some(Job::CronJob(CronJob {
+            schedule: "0 0 * * * *".to_string(),
+            timezone: String::new(),
+            exclusions: vec![],
+            jitter_seconds: 0,
+        })),
+    };
+    assert_eq!(
+        JobStoredData::decode(FIXTURE).expect("decode fixture"),
+        data
+    );
+    // The defaults are left out, so the older version reads what this one writes back
+    assert_eq!(data.encode_to_vec(), FIXTURE);
+}
+
+#[cfg(feature = "has_bytes")]
+#[test]
+fn codecs_round_trip() {
+    use crate::store::{Codec, ProstCodec};
+
+    let data = job_stored_data(Uuid::from_u128(0xabcd), 1_600_000_120);
+    let mut codecs: Vec<Box<dyn Codec>> = vec![Box::new(ProstCodec)];
+    #[cfg(feature = "json_codec")]
+    codecs.push(Box::new(crate::store::JsonCodec));
+    #[cfg(feature = "cbor_codec")]
+    codecs.push(Box::new(crate::store::CborCodec));
+    #[cfg(feature = "msgpack_codec")]
+    codecs.push(Box::new(crate::store::MessagePackCodec));
+    for codec in codecs {
+        let bytes = codec.encode_job(&data).expect("encode");
+        assert_eq!(codec.decode_job(&bytes).expect("decode"), data);
+    }
+}
@@ -0,0 +1,540 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{
+    store_event, JobAndNextTick, JobStoredData, LoggedSnapshot, Snapshot, StoreEvent, TicksSet,
+};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{
+    store_event, JobAndNextTick, JobStoredData, LoggedSnapshot, Snapshot, StoreEvent, TicksSet,
+};
+use crate::store::{DataStore, EventQuery, InitStore, MetaDataStorage, Page};
+use crate::JobSchedulerError;
+use chrono::{DateTime, TimeZone, Utc};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, JobSchedulerError>> + Send>>;
+type StoreOp<S> = Box<dyn FnOnce(&mut S) -> StoreFuture<()> + Send>;
+
+///
+/// An append-only log of the changes made to the stored jobs, along with snapshots of the jobs
+/// taken now and then so a replay does not have to start from the first event
+pub trait EventLog: InitStore {
+    ///
+    /// Append an event, numbered one after the last appended
+    fn append(&mut self, event: StoreEvent) -> StoreFuture<()>;
+
+    ///
+    /// The events numbered after `sequence`, in order
+    fn events_after(&mut self, sequence: u64) -> StoreFuture<Vec<StoreEvent>>;
+
+    fn save_snapshot(&mut self, snapshot: LoggedSnapshot) -> StoreFuture<()>;
+
+    ///
+    /// The snapshot with the highest sequence number
+    fn latest_snapshot(&mut self) -> StoreFuture<Option<LoggedSnapshot>>;
+
+    ///
+    /// Run `query` in the log itself, e.g. as SQL. Defaults to `None`, for the caller to read
+    /// the events after the cursor and select the page with `EventQuery::page`.
+    fn query_events(&mut self, _query: &EventQuery) -> Option<StoreFuture<Page<StoreEvent>>> {
+        None
+    }
+}
+
+impl StoreEvent {
+    pub fn at_utc(&self) -> Option<DateTime<Utc>> {
+        Utc.timestamp_opt(self.at as i64, 0).single()
+    }
+}
+
+struct Journal {
+    log: Box<dyn EventLog + Send + Sync>,
+    sequence: u64,
+    since_snapshot: u64,
+    replayed: bool,
+}
+
+///
+/// A metadata store keeping the jobs in a wrapped store, usually a `SimpleMetadataStore`, and
+/// every change to them in an event log. On init the wrapped store is rebuilt by replaying the
+/// log from its latest snapshot, and the log can be read back for an audit of what changed.
+pub struct EventSourcedStore<S> {
+    inner: Arc<RwLock<S>>,
+    journal: Arc<Mutex<Journal>>,
+    snapshot_every: u64,
+}
+
+impl<S> Clone for EventSourcedStore<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            journal: self.journal.clone(),
+            snapshot_every: self.snapshot_every,
+        }
+    }
+}
+
+impl<S> EventSourcedStore<S>
+where
+    S: MetaDataStorage + Send + Sync + 'static,
+{
+    pub fn new(inner: S, log: Box<dyn EventLog + Send + Sync>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(inner)),
+            journal: Arc::new(Mutex::new(Journal {
+                log,
+                sequence: 0,
+                since_snapshot: 0,
+                replayed: false,
+            })),
+            snapshot_every: 1000,
+        }
+    }
+
+    ///
+    /// Take a snapshot after every `events` events, or never when 0. Defaults to 1000.
+    pub fn snapshot_every(mut self, events: u64) -> Self {
+        self.snapshot_every = events;
+        self
+    }
+
+    ///
+    /// All the events in the log, for an audit of the changes to the jobs
+    pub async fn events(&self) -> Result<Vec<StoreEvent>, JobSchedulerError> {
+        let mut journal = self.journal.lock().await;
+        journal.log.events_after(0).await
+    }
+
+    ///
+    /// The page of the events in the log selected by `query`, for an audit of the changes to
+    /// some jobs or over some time
+    pub async fn query_events(
+        &self,
+        query: EventQuery,
+    ) -> Result<Page<StoreEvent>, JobSchedulerError> {
+        let mut journal = self.journal.lock().await;
+        if let Some(page) = journal.log.query_events(&query) {
+            return page.await;
+        }
+        let events = journal.log.events_after(query.after_sequence()).await?;
+        Ok(query.page(events))
+    }
+
+    ///
+    /// Change the wrapped store with `op`, then log the change as `event`
+    fn record(&self, op: StoreOp<S>, event: store_event::Event) -> StoreFuture<()> {
+        let inner = self.inner.clone();
+        let journal = self.journal.clone();
+        let snapshot_every = self.snapshot_every;
+        Box::pin(async move {
+            // Held across the change so the log is in the order the changes were made
+            let mut journal = journal.lock().await;
+            let op = {
+                let mut w = inner.write().await;
+                op(&mut w)
+            };
+            op.await?;
+            journal.sequence += 1;
+            let sequence = journal.sequence;
+            journal
+                .log
+                .append(StoreEvent {
+                    sequence,
+                    at: Utc::now().timestamp() as u64,
+                    event: Some(event),
+                })
+                .await?;
+            journal.since_snapshot += 1;
+            if snapshot_every > 0 && journal.since_snapshot >= snapshot_every {
+                let jobs = Self::jobs(&inner).await?;
+                journal
+                    .log
+                    .save_snapshot(LoggedSnapshot {
+                        sequence,
+                        snapshot: Some(Snapshot {
+                            taken_at: Utc::now().timestamp() as u64,
+                            jobs,
+                            notifications: vec![],
+                        }),
+                    })
+                    .await?;
+                journal.since_snapshot = 0;
+            }
+            Ok(())
+        })
+    }
+
+    async fn jobs(inner: &Arc<RwLock<S>>) -> Result<Vec<JobStoredData>, JobSchedulerError> {
+        let mut w = inner.write().await;
+        let mut jobs = vec![];
+        for job_id in w.list_job_ids().await? {
+            if let Some(job) = w.get(job_id).await? {
+                jobs.push(job);
+            }
+        }
+        Ok(jobs)
+    }
+
+    async fn apply(inner: &mut S, event: store_event::Event) -> Result<(), JobSchedulerError> {
+        match event {
+            store_event::Event::JobSaved(job) => inner.add_or_update(job).await,
+            store_event::Event::JobDeleted(job_id) => inner.delete((&job_id).into()).await,
+            store_event::Event::TicksSet(ticks) => {
+                let job_id: Uuid = match ticks.job_id.as_ref() {
+                    Some(job_id) => job_id.into(),
+                    None => return Ok(()),
+                };
+                let next_tick = ticks.next_tick.and_then(to_utc);
+                let last_tick = ticks.last_tick.and_then(to_utc);
+                inner
+                    .set_next_and_last_tick(job_id, next_tick, last_tick)
+                    .await
+            }
+        }
+    }
+}
+
+fn to_utc(tick: u64) -> Option<DateTime<Utc>> {
+    Utc.timestamp_opt(tick as i64, 0).single()
+}
+
+impl<S> DataStore<JobStoredData> for EventSourcedStore<S>
+where
+    S: MetaDataStorage + Send + Sync + 'static,
+{
+    fn get(&mut self, id: Uuid) -> StoreFuture<Option<JobStoredData>> {
+        let inner = self.inner.clone();
+        Box::pin(async move {
+            let get = {
+                let mut w = inner.write().await;
+                w.get(id)
+            };
+            get.await
+        })
+    }
+
+    fn add_or_update(&mut self, data: JobStoredData) -> StoreFuture<()> {
+        let event = store_event::Event::JobSaved(data.clone());
+        self.record(Box::new(move |inner| inner.add_or_update(data)), event)
+    }
+
+    fn delete(&mut self, guid: Uuid) -> StoreFuture<()> {
+        let event = store_event::Event::JobDeleted(guid.into());
+        self.record(Box::new(move |inner| inner.delete(guid)), event)
+    }
+}
+
+impl<S> InitStore for EventSourcedStore<S>
+where
+    S: MetaDataStorage + Send + Sync + 'static,
+{
+    fn init(&mut self) -> StoreFuture<()> {
+        let inner = self.inner.clone();
+        let journal = self.journal.clone();
+        Box::pin(async move {
+            let mut journal = journal.lock().await;
+            if journal.replayed {
+                return Ok(());
+            }
+            if !journal.log.inited().await? {
+                journal.log.init().await?;
+            }
+            let mut inner = inner.write().await;
+            if !inner.inited().await? {
+                inner.init().await?;
+            }
+            let mut sequence = 0;
+            if let Some(logged) = journal.log.latest_snapshot().await? {
+                sequence = logged.sequence;
+                for job in logged.snapshot.map(|s| s.jobs).unwrap_or_default() {
+                    inner.add_or_update(job).await?;
+                }
+            }
+            for event in journal.log.events_after(sequence).await? {
+                sequence = event.sequence;
+                if let Some(event) = event.event {
+                    Self::apply(&mut inner, event).await?;
+                }
+            }
+            journal.sequence = sequence;
+            journal.since_snapshot = 0;
+            journal.replayed = true;
+            Ok(())
+        })
+    }
+
+    fn inited(&mut self) -> StoreFuture<bool> {
+        let journal = self.journal.clone();
+        Box::pin(async move {
+            let journal = journal.lock().await;
+            Ok(journal.replayed)
+        })
+    }
+}
+
+impl<S> MetaDataStorage for EventSourcedStore<S>
+where
+    S: MetaDataStorage + Send + Sync + 'static,
+{
+    fn list_next_ticks(&mut self) -> StoreFuture<Vec<JobAndNextTick>> {
+        let inner = self.inner.clone();
+        Box::pin(async move {
+            let list = {
+                let mut w = inner.write().await;
+                w.list_next_ticks()
+            };
+            list.await
+        })
+    }
+
+    fn set_next_and_last_tick(
+        &mut self,
+        guid: Uuid,
+        next_tick: Option<DateTime<Utc>>,
+        last_tick: Option<DateTime<Utc>>,
+    ) -> StoreFuture<()> {
+        let event = store_event::Event::TicksSet(TicksSet {
+            job_id: Some(guid.into()),
+            next_tick: next_tick.map(|t| t.timestamp() as u64),
+            last_tick: last_tick.map(|t| t.timestamp() as u64),
+        });
+        self.record(
+            Box::new(move |inner| inner.set_next_and_last_tick(guid, next_tick, last_tick)),
+            event,
+        )
+    }
+
+    fn time_till_next_job(&mut self) -> StoreFuture<Option<Duration>> {
+        let inner = self.inner.clone();
+        Box::pin(async move {
+            let time = {
+                let mut w = inner.write().await;
+                w.time_till_next_job()
+            };
+            time.await
+        })
+    }
+
+    fn list_job_ids(&mut self) -> StoreFuture<Vec<Uuid>> {
+        let inner = self.inner.clone();
+        Box::pin(async move {
+            let list = {
+                let mut w = inner.write().await;
+                w.list_job_ids()
+            };
+            list.await
+        })
+    }
+
+    fn get_many(&mut self, ids: &[Uuid]) -> StoreFuture<Vec<JobStoredData>> {
+        let inner = self.inner.clone();
+        let ids = ids.to_vec();
+        Box::pin(async move {
+            let get_many = {
+                let mut w = inner.write().await;
+                w.get_many(&ids)
+            };
+            get_many.await
+        })
+    }
+}
+
+///
+/// A volatile event log, kept in memory. Clones share the same log.
+#[derive(Clone, Default)]
+pub struct MemoryEventLog {
+    events: Arc<RwLock<Vec<StoreEvent>>>,
+    snapshot: Arc<RwLock<Option<LoggedSnapshot>>>,
+}
+
+impl InitStore for MemoryEventLog {
+    fn init(&mut self) -> StoreFuture<()> {
+        Box::pin(std::future::ready(Ok(())))
+    }
+
+    fn inited(&mut self) -> StoreFuture<bool> {
+        Box::pin(std::future::ready(Ok(true)))
+    }
+}
+
+impl EventLog for MemoryEventLog {
+    fn append(&mut self, event: StoreEvent) -> StoreFuture<()> {
+        let events = self.events.clone();
+        Box::pin(async move {
+            let mut w = events.write().await;
+            w.push(event);
+            Ok(())
+        })
+    }
+
+    fn events_after(&mut self, sequence: u64) -> StoreFuture<Vec<StoreEvent>> {
+        let events = self.events.clone();
+        Box::pin(async move {
+            let r = events.read().await;
+            Ok(r.iter()
+                .filter(|e| e.sequence > sequence)
+                .cloned()
+                .collect())
+        })
+    }
+
+    fn save_snapshot(&mut self, snapshot: LoggedSnapshot) -> StoreFuture<()> {
+        let latest = self.snapshot.clone();
+        Box::pin(async move {
+            let mut w = latest.write().await;
+            *w = Some(snapshot);
+            Ok(())
+        })
+    }
+
+    fn latest_snapshot(&mut self) -> StoreFuture<Option<LoggedSnapshot>> {
+        let latest = self.snapshot.clone();
+        Box::pin(async move {
+            let r = latest.read().await;
+            Ok(r.clone())
+        })
+    }
+}
+
+///
+/// An event log in a file, the events being appended to it encoded with Protobuf. The latest
+/// snapshot is kept next to it, in a file with the `snapshot` extension.
+#[cfg(feature = "has_bytes")]
+#[derive(Clone)]
+pub struct FileEventLog {
+    pub path: std::path::PathBuf,
+}
+
+#[cfg(feature = "has_bytes")]
+impl FileEventLog {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn snapshot_path(&self) -> std::path::PathBuf {
+        self.path.with_extension("snapshot")
+    }
+
+    async fn blocking<T, F>(f: F, error: JobSchedulerError) -> Result<T, JobSchedulerError>
+    where
+        F: FnOnce() -> std::io::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        match tokio::task::spawn_blocking(f).await {
+            Ok(Ok(val)) => Ok(val),
+            Ok(Err(e)) => {
+                error!("Error accessing event log file {:?}", e);
+                Err(error)
+            }
+            Err(e) => {
+                error!("Error waiting on event log file {:?}", e);
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "has_bytes")]
+impl InitStore for FileEventLog {
+    fn init(&mut self) -> StoreFuture<()> {
+        Box::pin(std::future::ready(Ok(())))
+    }
+
+    fn inited(&mut self) -> StoreFuture<bool> {
+        Box::pin(std::future::ready(Ok(true)))
+    }
+}
+
+#[cfg(feature = "has_bytes")]
+impl EventLog for FileEventLog {
+    fn append(&mut self, event: StoreEvent) -> StoreFuture<()> {
+        use prost::Message;
+        use std::io::Write;
+
+        let path = self.path.clone();
+        let bytes = event.encode_length_delimited_to_vec();
+        Box::pin(FileEventLog::blocking(
+            move || {
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?;
+                file.write_all(&bytes)?;
+                file.sync_data()
+            },
+            JobSchedulerError::SaveJob,
+        ))
+    }
+
+    fn events_after(&mut self, sequence: u64) -> StoreFuture<Vec<StoreEvent>> {
+        use prost::Message;
+
+        let path = self.path.clone();
+        Box::pin(async move {
+            let bytes = FileEventLog::blocking(
+                move || match std::fs::read(path) {
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+                    read => read,
+                },
+                JobSchedulerError::ErrorLoadingJob,
+            )
+            .await?;
+            let mut buf = bytes.as_slice();
+            let mut events = vec![];
+            while !buf.is_empty() {
+                match StoreEvent::decode_length_delimited(&mut buf) {
+                    Ok(event) if event.sequence > sequence => events.push(event),
+                    Ok(_) => {}
+                    Err(e) => {
+                        // A write cut short at the end of the log
+                        error!("Error decoding event log, ignoring its tail {:?}", e);
+                        break;
+                    }
+                }
+            }
+            Ok(events)
+        })
+    }
+
+    fn save_snapshot(&mut self, snapshot: LoggedSnapshot) -> StoreFuture<()> {
+        use prost::Message;
+
+        let path = self.snapshot_path();
+        let bytes = snapshot.encode_to_vec();
+        Box::pin(FileEventLog::blocking(
+            move || {
+                let tmp = path.with_extension("snapshot.tmp");
+                std::fs::write(&tmp, bytes)?;
+                std::fs::rename(tmp, path)
+            },
+            JobSchedulerError::SaveJob,
+        ))
+    }
+
+    fn latest_snapshot(&mut self) -> StoreFuture<Option<LoggedSnapshot>> {
+        use prost::Message;
+
+        let path = self.snapshot_path();
+        Box::pin(async move {
+            let bytes = FileEventLog::blocking(
+                move || match std::fs::read(path) {
+                    Ok(bytes) => Ok(Some(bytes)),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                    Err(e) => Err(e),
+                },
+                JobSchedulerError::ErrorLoadingJob,
+            )
+            .await?;
+            match bytes {
+                Some(bytes) => LoggedSnapshot::decode(bytes.as_slice())
+                    .map(Some)
+                    .map_err(|_| JobSchedulerError::CantDecodeSnapshot),
+                None => Ok(None),
+            }
+        })
+    }
+}
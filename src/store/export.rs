@@ -0,0 +1,179 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{JobRun, JobState};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{JobRun, JobState};
+use crate::JobSchedulerError;
+use chrono::SecondsFormat;
+use std::io::Write;
+use uuid::Uuid;
+
+///
+/// The format the run history is exported in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma separated values with a header row, the times in RFC 3339
+    Csv,
+    /// A Parquet file of one row group, the times as timestamps in milliseconds
+    #[cfg(feature = "parquet_export")]
+    Parquet,
+}
+
+const COLUMNS: [&str; 8] = [
+    "id",
+    "job_id",
+    "scheduled_at",
+    "started_at",
+    "ended_at",
+    "duration_ms",
+    "outcome",
+    "error",
+];
+
+fn export_error(e: impl std::fmt::Display) -> JobSchedulerError {
+    JobSchedulerError::Export(e.to_string())
+}
+
+fn uuid(id: &Option<crate::JobUuid>) -> String {
+    id.as_ref()
+        .map(|id| Uuid::from(id).to_string())
+        .unwrap_or_default()
+}
+
+fn outcome(run: &JobRun) -> String {
+    JobState::from_i32(run.outcome)
+        .map(|state| format!("{:?}", state))
+        .unwrap_or_else(|| run.outcome.to_string())
+}
+
+pub(crate) fn write_runs(
+    runs: &[JobRun],
+    format: ExportFormat,
+    writer: Box<dyn Write + Send>,
+) -> Result<(), JobSchedulerError> {
+    match format {
+        ExportFormat::Csv => write_csv(runs, writer),
+        #[cfg(feature = "parquet_export")]
+        ExportFormat::Parquet => write_parquet(runs, writer),
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn csv_time(millis: u64) -> String {
+    chrono::DateTime::from_timestamp_millis(millis as i64)
+        .map(|t| t.to_rfc3339_opts(SecondsFormat::Millis, true))
+        .unwrap_or_default()
+}
+
+fn write_csv(runs: &[JobRun], mut writer: Box<dyn Write + Send>) -> Result<(), JobSchedulerError> {
+    writeln!(writer, "{}", COLUMNS.join(",")).map_err(export_error)?;
+    for run in runs {
+        let row = [
+            uuid(&run.id),
+            uuid(&run.job_id),
+            csv_time(run.scheduled_at),
+            csv_time(run.started_at),
+            csv_time(run.ended_at),
+            run.duration().as_millis().to_string(),
+            outcome(run),
+            csv_field(run.error.as_deref().unwrap_or_default()),
+        ];
+        writeln!(writer, "{}", row.join(",")).map_err(export_error)?;
+    }
+    writer.flush().map_err(export_error)
+}
+
+#[cfg(feature = "parquet_export")]
+fn write_parquet(runs: &[JobRun], writer: Box<dyn Write + Send>) -> Result<(), JobSchedulerError> {
+    use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use std::sync::Arc;
+
+    let schema = "message job_run {
+        REQUIRED BYTE_ARRAY id (UTF8);
+        REQUIRED BYTE_ARRAY job_id (UTF8);
+        REQUIRED INT64 scheduled_at (TIMESTAMP(MILLIS,true));
+        REQUIRED INT64 started_at (TIMESTAMP(MILLIS,true));
+        REQUIRED INT64 ended_at (TIMESTAMP(MILLIS,true));
+        REQUIRED INT64 duration_ms;
+        REQUIRED BYTE_ARRAY outcome (UTF8);
+        OPTIONAL BYTE_ARRAY error (UTF8);
+    }";
+    let schema = Arc::new(parse_message_type(schema).map_err(export_error)?);
+    let properties = Arc::new(WriterProperties::builder().build());
+    let mut file = SerializedFileWriter::new(writer, schema, properties).map_err(export_error)?;
+    let mut row_group = file.next_row_group().map_err(export_error)?;
+    let strings = |f: fn(&JobRun) -> String| -> Vec<ByteArray> {
+        runs.iter()
+            .map(|run| ByteArray::from(f(run).as_str()))
+            .collect()
+    };
+    let times =
+        |f: fn(&JobRun) -> u64| -> Vec<i64> { runs.iter().map(|run| f(run) as i64).collect() };
+    for name in COLUMNS {
+        let mut column = row_group
+            .next_column()
+            .map_err(export_error)?
+            .ok_or_else(|| export_error(format!("No column {} in the schema", name)))?;
+        let written = match name {
+            "id" => column.typed::<ByteArrayType>().write_batch(
+                &strings(|run| uuid(&run.id)),
+                None,
+                None,
+            ),
+            "job_id" => column.typed::<ByteArrayType>().write_batch(
+                &strings(|run| uuid(&run.job_id)),
+                None,
+                None,
+            ),
+            "scheduled_at" => {
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&times(|run| run.scheduled_at), None, None)
+            }
+            "started_at" => {
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&times(|run| run.started_at), None, None)
+            }
+            "ended_at" => {
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&times(|run| run.ended_at), None, None)
+            }
+            "duration_ms" => column.typed::<Int64Type>().write_batch(
+                &times(|run| run.duration().as_millis() as u64),
+                None,
+                None,
+            ),
+            "outcome" => column
+                .typed::<ByteArrayType>()
+                .write_batch(&strings(outcome), None, None),
+            _ => {
+                // Only the errors of the failed runs are written, the others being null
+                let errors: Vec<ByteArray> = runs
+                    .iter()
+                    .flat_map(|run| run.error.as_deref())
+                    .map(ByteArray::from)
+                    .collect();
+                let defined: Vec<i16> = runs.iter().map(|run| run.error.is_some() as i16).collect();
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&errors, Some(&defined), None)
+            }
+        };
+        written.map_err(export_error)?;
+        column.close().map_err(export_error)?;
+    }
+    row_group.close().map_err(export_error)?;
+    file.close().map_err(export_error)?;
+    Ok(())
+}
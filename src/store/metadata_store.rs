@@ -3,7 +3,7 @@ use crate::job::job_data::{JobAndNextTick, JobStoredData};
 #[cfg(feature = "has_bytes")]
 use crate::job::job_data_prost::{JobAndNextTick, JobStoredData};
 use crate::job::JobToRunAsync;
-use crate::store::{CodeGet, DataStore, InitStore};
+use crate::store::{CodeGet, DataStore, InitStore, JobQuery, Page};
 use crate::JobSchedulerError;
 use chrono::{DateTime, Utc};
 use std::future::Future;
@@ -23,6 +23,63 @@ pub trait MetaDataStorage: DataStore<JobStoredData> + InitStore {
     fn time_till_next_job(
         &mut self,
     ) -> Pin<Box<dyn Future<Output = Result<Option<std::time::Duration>, JobSchedulerError>> + Send>>;
+
+    ///
+    /// The ids of all the stored jobs. Defaults to the jobs returned by `list_next_ticks`, which
+    /// a store should override when that does not list every job.
+    fn list_job_ids(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Uuid>, JobSchedulerError>> + Send>> {
+        let next_ticks = self.list_next_ticks();
+        Box::pin(async move {
+            let next_ticks = next_ticks.await?;
+            Ok(next_ticks
+                .iter()
+                .filter_map(|n| n.id.as_ref().map(Uuid::from))
+                .collect())
+        })
+    }
+
+    ///
+    /// The stored jobs with the given ids, leaving out those that aren't stored. Defaults to a
+    /// `get` per id, which a store should override when it can read them all at once.
+    fn get_many(
+        &mut self,
+        ids: &[Uuid],
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<JobStoredData>, JobSchedulerError>> + Send>> {
+        let gets = ids.iter().map(|id| self.get(*id)).collect::<Vec<_>>();
+        Box::pin(async move {
+            let mut jobs = vec![];
+            for get in gets {
+                jobs.extend(get.await?);
+            }
+            Ok(jobs)
+        })
+    }
+
+    ///
+    /// Run `query` in the store itself, e.g. as SQL. Defaults to `None`, for the caller to get
+    /// every job and select the page with `JobQuery::page`.
+    #[allow(clippy::type_complexity)]
+    fn query_jobs(
+        &mut self,
+        _query: &JobQuery,
+    ) -> Option<Pin<Box<dyn Future<Output = Result<Page<JobStoredData>, JobSchedulerError>> + Send>>>
+    {
+        None
+    }
+
+    ///
+    /// The jobs run after the job `job_id`, looked up in the store itself. Defaults to `None`,
+    /// for the caller to go through the jobs run after another job.
+    #[allow(clippy::type_complexity)]
+    fn list_followers(
+        &mut self,
+        _job_id: Uuid,
+    ) -> Option<Pin<Box<dyn Future<Output = Result<Vec<JobStoredData>, JobSchedulerError>> + Send>>>
+    {
+        None
+    }
 }
 
 pub trait JobCodeGet: CodeGet<Box<JobToRunAsync>> {}
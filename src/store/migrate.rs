@@ -0,0 +1,82 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::NotificationData;
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::NotificationData;
+use crate::store::{InitStore, MetaDataStorage, NotificationStore};
+use crate::JobSchedulerError;
+
+///
+/// What was copied by `migrate_store`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub jobs: usize,
+    pub notifications: usize,
+}
+
+///
+/// Copy all the jobs and their notifications from one pair of stores to another, e.g. from the
+/// simple stores to Postgres, then read every copy back from the destination to check it matches
+/// its source. Stores not inited yet are inited first. Only the stored data moves; the code of
+/// the jobs and notifications is not stored and has to be registered again with the scheduler
+/// using the destination.
+pub async fn migrate_store(
+    source_metadata: &mut (dyn MetaDataStorage + Send + Sync),
+    source_notifications: &mut (dyn NotificationStore + Send + Sync),
+    dest_metadata: &mut (dyn MetaDataStorage + Send + Sync),
+    dest_notifications: &mut (dyn NotificationStore + Send + Sync),
+) -> Result<MigrationReport, JobSchedulerError> {
+    ensure_inited(source_metadata).await?;
+    ensure_inited(source_notifications).await?;
+    ensure_inited(dest_metadata).await?;
+    ensure_inited(dest_notifications).await?;
+    let mut report = MigrationReport::default();
+    for job_id in source_metadata.list_job_ids().await? {
+        let job = match source_metadata.get(job_id).await? {
+            Some(job) => job,
+            None => continue,
+        };
+        dest_metadata.add_or_update(job.clone()).await?;
+        if dest_metadata.get(job_id).await?.as_ref() != Some(&job) {
+            error!("Job {:?} differs after being migrated", job_id);
+            return Err(JobSchedulerError::MigrationVerification(job_id));
+        }
+        report.jobs += 1;
+
+        let notification_ids = source_notifications
+            .list_notification_guids_for_job_id(job_id)
+            .await?;
+        for notification_id in notification_ids {
+            let notification = match source_notifications.get(notification_id).await? {
+                Some(notification) => notification,
+                None => continue,
+            };
+            dest_notifications
+                .add_or_update(notification.clone())
+                .await?;
+            let copy = dest_notifications.get(notification_id).await?;
+            if copy.map(sorted_states) != Some(sorted_states(notification)) {
+                error!(
+                    "Notification {:?} differs after being migrated",
+                    notification_id
+                );
+                return Err(JobSchedulerError::MigrationVerification(notification_id));
+            }
+            report.notifications += 1;
+        }
+    }
+    Ok(report)
+}
+
+async fn ensure_inited<S: InitStore + ?Sized>(store: &mut S) -> Result<(), JobSchedulerError> {
+    if !store.inited().await? {
+        store.init().await?;
+    }
+    Ok(())
+}
+
+///
+/// The states of a notification are a set, which stores do not list in the same order
+fn sorted_states(mut notification: NotificationData) -> NotificationData {
+    notification.job_states.sort();
+    notification
+}
@@ -2,13 +2,53 @@ use crate::JobSchedulerError;
 use std::future::Future;
 use std::pin::Pin;
 use uuid::Uuid;
+mod chaos;
+#[cfg(feature = "has_bytes")]
+mod codec;
+#[cfg(any(feature = "zstd_compression", feature = "lz4_compression"))]
+mod compression;
+#[cfg(test)]
+mod conformance;
+mod event_log;
+mod export;
 mod metadata_store;
+mod migrate;
 mod notification_store;
+mod offline;
+mod outbox;
+mod partition;
+mod query;
+mod retry;
+mod run_history;
 
+pub use chaos::{ChaosStore, FaultConfig};
+#[cfg(feature = "cbor_codec")]
+pub use codec::CborCodec;
+#[cfg(feature = "json_codec")]
+pub use codec::JsonCodec;
+#[cfg(feature = "msgpack_codec")]
+pub use codec::MessagePackCodec;
+#[cfg(feature = "has_bytes")]
+pub use codec::{Codec, ProstCodec};
+#[cfg(any(feature = "zstd_compression", feature = "lz4_compression"))]
+pub use compression::{BlobCompression, CompressedStore, CompressionAlgorithm};
+#[cfg(feature = "has_bytes")]
+pub use event_log::FileEventLog;
+pub use event_log::{EventLog, EventSourcedStore, MemoryEventLog};
+pub use export::ExportFormat;
 pub use metadata_store::JobCodeGet;
 pub use metadata_store::MetaDataStorage;
+pub use migrate::{migrate_store, MigrationReport};
 pub use notification_store::NotificationRunnableCodeGet;
 pub use notification_store::NotificationStore;
+pub use offline::{
+    DataLoss, OfflineQueueConfig, OfflineQueueEvent, OfflineQueueStore, OnOfflineQueue,
+};
+pub use outbox::{MemoryOutbox, Outbox, OutboxEntry};
+pub use partition::{PartitionProvider, PartitionedStore};
+pub use query::{EventQuery, JobListState, JobQuery, JobSort, Page};
+pub use retry::{OnStoreRetry, RetryingStore, StoreRetryConfig, StoreRetryEvent};
+pub use run_history::RunHistoryStore;
 
 pub trait InitStore {
     fn init(&mut self) -> Pin<Box<dyn Future<Output = Result<(), JobSchedulerError>> + Send>>;
@@ -0,0 +1,341 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{JobAndNextTick, JobStoredData};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{JobAndNextTick, JobStoredData};
+use crate::store::{DataStore, InitStore, MetaDataStorage};
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, JobSchedulerError>> + Send>>;
+
+///
+/// How many updates an `OfflineQueueStore` holds on to while its store is unreachable
+#[derive(Clone, Debug)]
+pub struct OfflineQueueConfig {
+    /// The most updates queued at once, the oldest one is dropped to make room for a new one
+    pub capacity: usize,
+}
+
+impl Default for OfflineQueueConfig {
+    fn default() -> Self {
+        Self { capacity: 1000 }
+    }
+}
+
+///
+/// Why an `OfflineQueueStore` dropped an update
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataLoss {
+    /// The queue was full
+    Overflow,
+    /// The store was back but refused the update, e.g. because the job was deleted meanwhile
+    Refused,
+}
+
+///
+/// What an `OfflineQueueStore` did with the updates of its store, named like the method of the
+/// store
+#[derive(Clone, Debug)]
+pub enum OfflineQueueEvent {
+    /// The update of the job could not be written and was queued, `queued` updates are waiting
+    Queued {
+        operation: &'static str,
+        job_id: Uuid,
+        queued: usize,
+    },
+    /// `updates` queued updates were written to the store, `remaining` are still waiting
+    Flushed { updates: usize, remaining: usize },
+    /// The update of the job was dropped without ever being written
+    DataLost {
+        operation: &'static str,
+        job_id: Uuid,
+        reason: DataLoss,
+    },
+}
+
+///
+/// Called for every update an `OfflineQueueStore` queues, flushes or drops, e.g. to alert on
+/// lost updates
+pub type OnOfflineQueue = dyn Fn(OfflineQueueEvent) + Send + Sync;
+
+#[derive(Clone, Debug)]
+enum Update {
+    Ticks {
+        guid: Uuid,
+        next_tick: Option<DateTime<Utc>>,
+        last_tick: Option<DateTime<Utc>>,
+    },
+    Job(Box<JobStoredData>),
+    Delete(Uuid),
+}
+
+impl Update {
+    fn operation(&self) -> &'static str {
+        match self {
+            Update::Ticks { .. } => "set_next_and_last_tick",
+            Update::Job(_) => "add_or_update",
+            Update::Delete(_) => "delete",
+        }
+    }
+
+    fn job_id(&self) -> Uuid {
+        match self {
+            Update::Ticks { guid, .. } => *guid,
+            Update::Job(data) => data.id.as_ref().map(|id| id.into()).unwrap_or_default(),
+            Update::Delete(guid) => *guid,
+        }
+    }
+
+    fn apply<S: MetaDataStorage>(self, store: &mut S) -> StoreFuture<()> {
+        match self {
+            Update::Ticks {
+                guid,
+                next_tick,
+                last_tick,
+            } => store.set_next_and_last_tick(guid, next_tick, last_tick),
+            Update::Job(data) => store.add_or_update(*data),
+            Update::Delete(guid) => store.delete(guid),
+        }
+    }
+}
+
+struct Queued<S> {
+    store: S,
+    updates: VecDeque<Update>,
+}
+
+impl<S: MetaDataStorage> Queued<S> {
+    ///
+    /// Whether the store answers at all, by reading the job the failed update was for
+    async fn reachable(&mut self, job_id: Uuid) -> bool {
+        self.store.get(job_id).await.is_ok()
+    }
+}
+
+///
+/// Wraps a metadata store and queues the updates of the jobs, their ticks, the outcomes of their
+/// runs and their deletion, that fail to be written while the store is unreachable. The queue is
+/// written to the store, in the order the updates were made, before any later operation, so the
+/// scheduler keeps running through a short outage without losing its state. While updates are
+/// queued, new ones are queued behind them, and reads see the store without them.
+///
+/// A failed update is only queued when reading its job fails as well, an update the store
+/// refuses while it answers fails like it would without the queue.
+pub struct OfflineQueueStore<S> {
+    queued: Arc<Mutex<Queued<S>>>,
+    pub config: OfflineQueueConfig,
+    on_event: Option<Arc<OnOfflineQueue>>,
+}
+
+impl<S> Clone for OfflineQueueStore<S> {
+    fn clone(&self) -> Self {
+        Self {
+            queued: self.queued.clone(),
+            config: self.config.clone(),
+            on_event: self.on_event.clone(),
+        }
+    }
+}
+
+impl<S> OfflineQueueStore<S>
+where
+    S: MetaDataStorage + Send + 'static,
+{
+    pub fn wrap(store: S, config: OfflineQueueConfig) -> Self {
+        Self {
+            queued: Arc::new(Mutex::new(Queued {
+                store,
+                updates: VecDeque::new(),
+            })),
+            config,
+            on_event: None,
+        }
+    }
+
+    ///
+    /// Call `handler` for every update queued, flushed or dropped
+    pub fn on_event(mut self, handler: Box<OnOfflineQueue>) -> Self {
+        self.on_event = Some(Arc::from(handler));
+        self
+    }
+
+    ///
+    /// The number of updates waiting for the store
+    pub async fn queued(&self) -> usize {
+        self.queued.lock().await.updates.len()
+    }
+
+    ///
+    /// Write the queued updates to the store, stopping at the first one that still fails.
+    /// Returns the number of updates still waiting.
+    pub async fn flush(&self) -> usize {
+        let mut queued = self.queued.lock().await;
+        flush(&mut queued, &self.on_event).await;
+        queued.updates.len()
+    }
+
+    ///
+    /// Flush the queue and run the operation made by `op` on the store
+    fn read<T, F>(&self, op: F) -> StoreFuture<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut S) -> StoreFuture<T> + Send + 'static,
+    {
+        let queued = self.queued.clone();
+        let on_event = self.on_event.clone();
+        Box::pin(async move {
+            let mut queued = queued.lock().await;
+            flush(&mut queued, &on_event).await;
+            op(&mut queued.store).await
+        })
+    }
+
+    ///
+    /// Flush the queue and write the update to the store, queueing it when that fails or when
+    /// updates made before it are still waiting
+    fn write(&self, update: Update) -> StoreFuture<()> {
+        let queued = self.queued.clone();
+        let config = self.config.clone();
+        let on_event = self.on_event.clone();
+        Box::pin(async move {
+            let mut queued = queued.lock().await;
+            flush(&mut queued, &on_event).await;
+            if queued.updates.is_empty() {
+                match update.clone().apply(&mut queued.store).await {
+                    Ok(()) => return Ok(()),
+                    Err(e) if queued.reachable(update.job_id()).await => return Err(e),
+                    Err(e) => {
+                        warn!(operation = update.operation(); "Store unreachable, queueing the update of {:?}: {:?}", update.job_id(), e);
+                    }
+                }
+            }
+            if queued.updates.len() >= config.capacity.max(1) {
+                if let Some(dropped) = queued.updates.pop_front() {
+                    lost(&on_event, dropped, DataLoss::Overflow);
+                }
+            }
+            let operation = update.operation();
+            let job_id = update.job_id();
+            queued.updates.push_back(update);
+            emit(
+                &on_event,
+                OfflineQueueEvent::Queued {
+                    operation,
+                    job_id,
+                    queued: queued.updates.len(),
+                },
+            );
+            Ok(())
+        })
+    }
+}
+
+fn emit(on_event: &Option<Arc<OnOfflineQueue>>, event: OfflineQueueEvent) {
+    if let Some(on_event) = on_event {
+        on_event(event);
+    }
+}
+
+fn lost(on_event: &Option<Arc<OnOfflineQueue>>, update: Update, reason: DataLoss) {
+    error!(operation = update.operation(); "Dropped the update of {:?} to the store: {:?}", update.job_id(), reason);
+    emit(
+        on_event,
+        OfflineQueueEvent::DataLost {
+            operation: update.operation(),
+            job_id: update.job_id(),
+            reason,
+        },
+    );
+}
+
+async fn flush<S: MetaDataStorage>(queued: &mut Queued<S>, on_event: &Option<Arc<OnOfflineQueue>>) {
+    let mut updates = 0;
+    while let Some(update) = queued.updates.front().cloned() {
+        match update.clone().apply(&mut queued.store).await {
+            Ok(()) => updates += 1,
+            // Not holding back the updates behind the one refused
+            Err(_) if queued.reachable(update.job_id()).await => {
+                lost(on_event, update, DataLoss::Refused)
+            }
+            Err(_) => break,
+        }
+        queued.updates.pop_front();
+    }
+    if updates > 0 {
+        let remaining = queued.updates.len();
+        info!(updates = updates, remaining = remaining; "Flushed queued updates to the store");
+        emit(on_event, OfflineQueueEvent::Flushed { updates, remaining });
+    }
+}
+
+impl<S> DataStore<JobStoredData> for OfflineQueueStore<S>
+where
+    S: MetaDataStorage + Send + 'static,
+{
+    fn get(&mut self, id: Uuid) -> StoreFuture<Option<JobStoredData>> {
+        self.read(move |s| s.get(id))
+    }
+
+    fn add_or_update(&mut self, data: JobStoredData) -> StoreFuture<()> {
+        self.write(Update::Job(Box::new(data)))
+    }
+
+    fn delete(&mut self, guid: Uuid) -> StoreFuture<()> {
+        self.write(Update::Delete(guid))
+    }
+}
+
+impl<S> InitStore for OfflineQueueStore<S>
+where
+    S: MetaDataStorage + Send + 'static,
+{
+    fn init(&mut self) -> StoreFuture<()> {
+        self.read(|s| s.init())
+    }
+
+    fn inited(&mut self) -> StoreFuture<bool> {
+        self.read(|s| s.inited())
+    }
+}
+
+impl<S> MetaDataStorage for OfflineQueueStore<S>
+where
+    S: MetaDataStorage + Send + 'static,
+{
+    fn list_next_ticks(&mut self) -> StoreFuture<Vec<JobAndNextTick>> {
+        self.read(|s| s.list_next_ticks())
+    }
+
+    fn set_next_and_last_tick(
+        &mut self,
+        guid: Uuid,
+        next_tick: Option<DateTime<Utc>>,
+        last_tick: Option<DateTime<Utc>>,
+    ) -> StoreFuture<()> {
+        self.write(Update::Ticks {
+            guid,
+            next_tick,
+            last_tick,
+        })
+    }
+
+    fn time_till_next_job(&mut self) -> StoreFuture<Option<Duration>> {
+        self.read(|s| s.time_till_next_job())
+    }
+
+    fn list_job_ids(&mut self) -> StoreFuture<Vec<Uuid>> {
+        self.read(|s| s.list_job_ids())
+    }
+
+    fn get_many(&mut self, ids: &[Uuid]) -> StoreFuture<Vec<JobStoredData>> {
+        let ids = ids.to_vec();
+        self.read(move |s| s.get_many(&ids))
+    }
+}
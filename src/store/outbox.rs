@@ -0,0 +1,115 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::JobState;
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::JobState;
+use crate::store::InitStore;
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, JobSchedulerError>> + Send>>;
+
+///
+/// A notification of the outcome of a run waiting in the outbox to be delivered to the sinks of
+/// the job
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutboxEntry {
+    pub id: Uuid,
+    pub job_id: Uuid,
+    pub state: JobState,
+    pub created_at: DateTime<Utc>,
+    /// The attempts at delivering it that failed so far
+    pub attempts: u32,
+}
+
+impl OutboxEntry {
+    pub fn new(job_id: Uuid, state: JobState) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            job_id,
+            state,
+            created_at: Utc::now(),
+            attempts: 0,
+        }
+    }
+}
+
+///
+/// Keeps the notifications of the outcomes of runs until their sinks ran, so that a notification
+/// is not lost when the process dies right after a run. An entry is only removed once it was
+/// delivered, so it may be delivered more than once.
+pub trait Outbox: InitStore {
+    fn enqueue(&mut self, entry: OutboxEntry) -> StoreFuture<()>;
+
+    ///
+    /// The entries still to be delivered, oldest first
+    fn pending(&mut self) -> StoreFuture<Vec<OutboxEntry>>;
+
+    ///
+    /// Count a failed attempt at delivering an entry
+    fn failed_attempt(&mut self, id: Uuid) -> StoreFuture<()>;
+
+    ///
+    /// Remove an entry that was delivered, or given up on
+    fn remove(&mut self, id: Uuid) -> StoreFuture<()>;
+}
+
+///
+/// An outbox in memory, which only holds the entries for as long as the process lives
+#[derive(Clone, Default)]
+pub struct MemoryOutbox {
+    entries: Arc<RwLock<Vec<OutboxEntry>>>,
+}
+
+impl InitStore for MemoryOutbox {
+    fn init(&mut self) -> StoreFuture<()> {
+        Box::pin(std::future::ready(Ok(())))
+    }
+
+    fn inited(&mut self) -> StoreFuture<bool> {
+        Box::pin(std::future::ready(Ok(true)))
+    }
+}
+
+impl Outbox for MemoryOutbox {
+    fn enqueue(&mut self, entry: OutboxEntry) -> StoreFuture<()> {
+        let entries = self.entries.clone();
+        Box::pin(async move {
+            let mut w = entries.write().await;
+            w.push(entry);
+            Ok(())
+        })
+    }
+
+    fn pending(&mut self) -> StoreFuture<Vec<OutboxEntry>> {
+        let entries = self.entries.clone();
+        Box::pin(async move {
+            let r = entries.read().await;
+            Ok(r.clone())
+        })
+    }
+
+    fn failed_attempt(&mut self, id: Uuid) -> StoreFuture<()> {
+        let entries = self.entries.clone();
+        Box::pin(async move {
+            let mut w = entries.write().await;
+            if let Some(entry) = w.iter_mut().find(|e| e.id == id) {
+                entry.attempts += 1;
+            }
+            Ok(())
+        })
+    }
+
+    fn remove(&mut self, id: Uuid) -> StoreFuture<()> {
+        let entries = self.entries.clone();
+        Box::pin(async move {
+            let mut w = entries.write().await;
+            w.retain(|e| e.id != id);
+            Ok(())
+        })
+    }
+}
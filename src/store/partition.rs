@@ -0,0 +1,322 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{JobAndNextTick, JobStoredData};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{JobAndNextTick, JobStoredData};
+use crate::store::{DataStore, InitStore, MetaDataStorage};
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, JobSchedulerError>> + Send>>;
+type Partition = Arc<RwLock<Box<dyn MetaDataStorage + Send + Sync>>>;
+
+/// The partition of the jobs without a next tick
+const UNSCHEDULED: u64 = 0;
+
+///
+/// Opens, lists and drops the partitions of a `PartitionedStore`, each being a metadata store of
+/// its own, like a table or a bucket
+pub trait PartitionProvider: Send + Sync {
+    ///
+    /// The store of a partition, created and initialised if it doesn't exist yet
+    fn open(&self, name: &str) -> StoreFuture<Box<dyn MetaDataStorage + Send + Sync>>;
+
+    ///
+    /// The names of the partitions that exist
+    fn list(&self) -> StoreFuture<Vec<String>>;
+
+    ///
+    /// Drop a partition that has no jobs left
+    fn drop_partition(&self, name: &str) -> StoreFuture<()>;
+}
+
+fn partition_name(start: u64) -> String {
+    format!("p{}", start)
+}
+
+fn partition_start(name: &str) -> Option<u64> {
+    name.strip_prefix('p')?.parse().ok()
+}
+
+///
+/// A metadata store split in partitions by the next tick of the jobs, one per `period`, for
+/// deployments with so many jobs, like millions of one-shot reminders, that a single table or
+/// bucket gets slow. The due jobs are only looked up in the partitions that started already, a
+/// partition is created when the first job lands in it, and the partitions that have run all
+/// their jobs are dropped.
+///
+/// Which partition holds a job is indexed in memory, and looked up in all the partitions for the
+/// jobs added by other schedulers sharing the partitions.
+#[derive(Clone)]
+pub struct PartitionedStore {
+    provider: Arc<dyn PartitionProvider>,
+    period: u64,
+    partitions: Arc<RwLock<BTreeMap<u64, Partition>>>,
+    index: Arc<RwLock<HashMap<Uuid, u64>>>,
+    inited: Arc<AtomicBool>,
+}
+
+impl PartitionedStore {
+    pub fn new(provider: impl PartitionProvider + 'static, period: Duration) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            period: period.as_secs().max(1),
+            partitions: Arc::new(RwLock::new(BTreeMap::new())),
+            index: Arc::new(RwLock::new(HashMap::new())),
+            inited: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    ///
+    /// The start of the partition of a next tick, in seconds since the epoch
+    fn start_of(&self, next_tick: u64) -> u64 {
+        match next_tick {
+            0 => UNSCHEDULED,
+            // Partition 0 is taken by the unscheduled jobs
+            t => (t - t % self.period).max(1),
+        }
+    }
+
+    async fn partition(&self, start: u64) -> Result<Partition, JobSchedulerError> {
+        {
+            let r = self.partitions.read().await;
+            if let Some(partition) = r.get(&start) {
+                return Ok(partition.clone());
+            }
+        }
+        let mut w = self.partitions.write().await;
+        if let Some(partition) = w.get(&start) {
+            return Ok(partition.clone());
+        }
+        let partition = self.provider.open(&partition_name(start)).await?;
+        let partition = Arc::new(RwLock::new(partition));
+        w.insert(start, partition.clone());
+        Ok(partition)
+    }
+
+    async fn all_partitions(&self) -> Vec<(u64, Partition)> {
+        let r = self.partitions.read().await;
+        r.iter().map(|(start, p)| (*start, p.clone())).collect()
+    }
+
+    ///
+    /// The partition holding a job, looked up in all the partitions when it isn't indexed
+    async fn locate(&self, id: Uuid) -> Result<Option<(u64, JobStoredData)>, JobSchedulerError> {
+        let indexed = {
+            let r = self.index.read().await;
+            r.get(&id).copied()
+        };
+        if let Some(start) = indexed {
+            let partition = self.partition(start).await?;
+            let get = partition.write().await.get(id);
+            if let Some(data) = get.await? {
+                return Ok(Some((start, data)));
+            }
+        }
+        for (start, partition) in self.all_partitions().await {
+            if Some(start) == indexed {
+                continue;
+            }
+            let get = partition.write().await.get(id);
+            if let Some(data) = get.await? {
+                let mut w = self.index.write().await;
+                w.insert(id, start);
+                return Ok(Some((start, data)));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn load(&self) -> Result<(), JobSchedulerError> {
+        for name in self.provider.list().await? {
+            let start = match partition_start(&name) {
+                Some(start) => start,
+                None => continue,
+            };
+            let partition = self.partition(start).await?;
+            let ids = partition.write().await.list_job_ids();
+            let mut w = self.index.write().await;
+            for id in ids.await? {
+                w.insert(id, start);
+            }
+        }
+        self.inited.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn save(&self, data: JobStoredData) -> Result<(), JobSchedulerError> {
+        let id: Uuid = match data.id.as_ref() {
+            Some(id) => id.into(),
+            None => return Err(JobSchedulerError::CantAdd),
+        };
+        let start = self.start_of(data.next_tick);
+        let previous = self.locate(id).await?.map(|(start, _)| start);
+        let partition = self.partition(start).await?;
+        let add = partition.write().await.add_or_update(data);
+        add.await?;
+        if let Some(previous) = previous.filter(|p| *p != start) {
+            let partition = self.partition(previous).await?;
+            let delete = partition.write().await.delete(id);
+            delete.await?;
+        }
+        let mut w = self.index.write().await;
+        w.insert(id, start);
+        Ok(())
+    }
+
+    async fn remove(&self, id: Uuid) -> Result<(), JobSchedulerError> {
+        if let Some((start, _)) = self.locate(id).await? {
+            let partition = self.partition(start).await?;
+            let delete = partition.write().await.delete(id);
+            delete.await?;
+        }
+        let mut w = self.index.write().await;
+        w.remove(&id);
+        Ok(())
+    }
+
+    async fn due(&self) -> Result<Vec<JobAndNextTick>, JobSchedulerError> {
+        // Include the jobs due in the next second, so warm jobs can be prepared
+        let now = Utc::now().timestamp() as u64 + 1;
+        let mut due = vec![];
+        for (start, partition) in self.all_partitions().await {
+            if start == UNSCHEDULED {
+                continue;
+            }
+            // Partition pruning, the partitions are ordered by their start
+            if start > now {
+                break;
+            }
+            let list = partition.write().await.list_next_ticks();
+            let listed = list.await?;
+            // A partition that ended lists all its jobs as due, so when it lists none it is
+            // empty and can be rolled over
+            if listed.is_empty() && start + self.period <= now {
+                self.roll_over(start).await;
+            }
+            due.extend(listed);
+        }
+        Ok(due)
+    }
+
+    async fn roll_over(&self, start: u64) {
+        {
+            let mut w = self.partitions.write().await;
+            w.remove(&start);
+        }
+        if let Err(e) = self.provider.drop_partition(&partition_name(start)).await {
+            error!("Could not drop partition {} {:?}", start, e);
+        }
+    }
+
+    async fn set_ticks(
+        &self,
+        id: Uuid,
+        next_tick: Option<DateTime<Utc>>,
+        last_tick: Option<DateTime<Utc>>,
+    ) -> Result<(), JobSchedulerError> {
+        let (start, mut data) = self
+            .locate(id)
+            .await?
+            .ok_or(JobSchedulerError::UpdateJobData)?;
+        let next = next_tick.map(|t| t.timestamp() as u64).unwrap_or_default();
+        if self.start_of(next) == start {
+            let partition = self.partition(start).await?;
+            let set = partition
+                .write()
+                .await
+                .set_next_and_last_tick(id, next_tick, last_tick);
+            return set.await;
+        }
+        data.next_tick = next;
+        data.last_tick = last_tick.map(|t| t.timestamp() as u64);
+        self.save(data).await
+    }
+
+    async fn time_till_next(&self) -> Result<Option<Duration>, JobSchedulerError> {
+        let current = self.start_of(Utc::now().timestamp() as u64);
+        for (start, partition) in self.all_partitions().await {
+            if start == UNSCHEDULED || start < current {
+                continue;
+            }
+            // The first partition with a job to come has the next one
+            let till = partition.write().await.time_till_next_job();
+            if let Some(till) = till.await? {
+                return Ok(Some(till));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn job_ids(&self) -> Result<Vec<Uuid>, JobSchedulerError> {
+        let mut ids = vec![];
+        for (_, partition) in self.all_partitions().await {
+            let list = partition.write().await.list_job_ids();
+            ids.extend(list.await?);
+        }
+        Ok(ids)
+    }
+}
+
+impl DataStore<JobStoredData> for PartitionedStore {
+    fn get(&mut self, id: Uuid) -> StoreFuture<Option<JobStoredData>> {
+        let store = self.clone();
+        Box::pin(async move { Ok(store.locate(id).await?.map(|(_, data)| data)) })
+    }
+
+    fn add_or_update(&mut self, data: JobStoredData) -> StoreFuture<()> {
+        let store = self.clone();
+        Box::pin(async move { store.save(data).await })
+    }
+
+    fn delete(&mut self, guid: Uuid) -> StoreFuture<()> {
+        let store = self.clone();
+        Box::pin(async move { store.remove(guid).await })
+    }
+}
+
+impl InitStore for PartitionedStore {
+    fn init(&mut self) -> StoreFuture<()> {
+        let store = self.clone();
+        Box::pin(async move { store.load().await })
+    }
+
+    fn inited(&mut self) -> StoreFuture<bool> {
+        let inited = self.inited.load(Ordering::SeqCst);
+        Box::pin(std::future::ready(Ok(inited)))
+    }
+}
+
+impl MetaDataStorage for PartitionedStore {
+    fn list_next_ticks(&mut self) -> StoreFuture<Vec<JobAndNextTick>> {
+        let store = self.clone();
+        Box::pin(async move { store.due().await })
+    }
+
+    fn set_next_and_last_tick(
+        &mut self,
+        guid: Uuid,
+        next_tick: Option<DateTime<Utc>>,
+        last_tick: Option<DateTime<Utc>>,
+    ) -> StoreFuture<()> {
+        let store = self.clone();
+        Box::pin(async move { store.set_ticks(guid, next_tick, last_tick).await })
+    }
+
+    fn time_till_next_job(&mut self) -> StoreFuture<Option<Duration>> {
+        let store = self.clone();
+        Box::pin(async move { store.time_till_next().await })
+    }
+
+    fn list_job_ids(&mut self) -> StoreFuture<Vec<Uuid>> {
+        let store = self.clone();
+        Box::pin(async move { store.job_ids().await })
+    }
+}
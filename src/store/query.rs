@@ -0,0 +1,316 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{store_event, JobStoredData, StoreEvent};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{store_event, JobStoredData, StoreEvent};
+use crate::store::MetaDataStorage;
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// The page size of a query that doesn't set one
+const DEFAULT_LIMIT: usize = 100;
+
+///
+/// A page of a listing, with the cursor to pass to the query for the next page, if any
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+///
+/// What the jobs of a listing are sorted by, the id breaking ties
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum JobSort {
+    #[default]
+    Id,
+    NextTick,
+    LastTick,
+}
+
+///
+/// The state of the jobs to list
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobListState {
+    /// Not stopped
+    Active,
+    Stopped,
+    /// Whose last run failed
+    Failed,
+}
+
+///
+/// Selects, sorts and pages the stored jobs. An empty query lists all of them, by id, a hundred
+/// at a time. The fields are public for the stores that run queries themselves, like Postgres.
+#[derive(Clone, Debug)]
+pub struct JobQuery {
+    /// The tags a job must all have
+    pub tags: Vec<String>,
+    pub group: Option<String>,
+    pub state: Option<JobListState>,
+    /// Jobs whose next tick is from, included, ...
+    pub next_tick_from: Option<DateTime<Utc>>,
+    /// ... to, excluded
+    pub next_tick_to: Option<DateTime<Utc>>,
+    pub sort: JobSort,
+    pub descending: bool,
+    /// The `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    pub limit: usize,
+}
+
+impl Default for JobQuery {
+    fn default() -> Self {
+        Self {
+            tags: vec![],
+            group: None,
+            state: None,
+            next_tick_from: None,
+            next_tick_to: None,
+            sort: JobSort::default(),
+            descending: false,
+            cursor: None,
+            limit: DEFAULT_LIMIT,
+        }
+    }
+}
+
+impl JobQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Only list the jobs with this tag. Can be given more than once.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    pub fn group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+
+    pub fn state(mut self, state: JobListState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    ///
+    /// Only list the jobs next ticking from `from`, included, to `to`, excluded
+    pub fn next_tick_between(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        self.next_tick_from = Some(from);
+        self.next_tick_to = Some(to);
+        self
+    }
+
+    pub fn sort_by(mut self, sort: JobSort, descending: bool) -> Self {
+        self.sort = sort;
+        self.descending = descending;
+        self
+    }
+
+    ///
+    /// Continue from the page that returned `cursor`
+    pub fn after(mut self, cursor: String) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit.max(1);
+        self
+    }
+
+    pub fn matches(&self, job: &JobStoredData) -> bool {
+        let state = match self.state {
+            None => true,
+            Some(JobListState::Active) => !job.stopped,
+            Some(JobListState::Stopped) => job.stopped,
+            Some(JobListState::Failed) => job.last_run_failed,
+        };
+        state
+            && self.tags.iter().all(|tag| job.tags.contains(tag))
+            && self.group.as_ref().is_none_or(|group| job.group == *group)
+            && self
+                .next_tick_from
+                .is_none_or(|from| job.next_tick >= from.timestamp() as u64)
+            && self
+                .next_tick_to
+                .is_none_or(|to| job.next_tick < to.timestamp() as u64)
+    }
+
+    ///
+    /// The value of the sort field of a job, with its id
+    pub fn sort_key(&self, job: &JobStoredData) -> (u64, Uuid) {
+        let id = job.id.as_ref().map(Uuid::from).unwrap_or_default();
+        let key = match self.sort {
+            JobSort::Id => 0,
+            JobSort::NextTick => job.next_tick,
+            JobSort::LastTick => job.last_tick.unwrap_or_default(),
+        };
+        (key, id)
+    }
+
+    ///
+    /// The sort key the cursor points after, `None` when there is no valid cursor
+    pub fn cursor_key(&self) -> Option<(u64, Uuid)> {
+        let (key, id) = self.cursor.as_ref()?.split_once(':')?;
+        Some((key.parse().ok()?, Uuid::parse_str(id).ok()?))
+    }
+
+    ///
+    /// The page of `jobs` the query selects, for the stores that can't run it themselves. The
+    /// jobs may be listed already filtered and sorted, or not.
+    pub fn page(&self, jobs: Vec<JobStoredData>) -> Page<JobStoredData> {
+        let cursor = self.cursor_key();
+        let mut jobs: Vec<_> = jobs
+            .into_iter()
+            .filter(|job| self.matches(job))
+            .map(|job| (self.sort_key(&job), job))
+            .filter(|(key, _)| match (cursor, self.descending) {
+                (None, _) => true,
+                (Some(cursor), false) => *key > cursor,
+                (Some(cursor), true) => *key < cursor,
+            })
+            .collect();
+        jobs.sort_by(|(a, _), (b, _)| if self.descending { b.cmp(a) } else { a.cmp(b) });
+        let more = jobs.len() > self.limit;
+        jobs.truncate(self.limit);
+        let next_cursor = jobs
+            .last()
+            .filter(|_| more)
+            .map(|((key, id), _)| format!("{}:{}", key, id));
+        Page {
+            items: jobs.into_iter().map(|(_, job)| job).collect(),
+            next_cursor,
+        }
+    }
+
+    ///
+    /// Run the query in `storage` when it can, else get all its jobs and select the page here
+    pub(crate) async fn run(
+        &self,
+        storage: &mut Box<dyn MetaDataStorage + Send + Sync>,
+    ) -> Result<Page<JobStoredData>, JobSchedulerError> {
+        if let Some(query) = storage.query_jobs(self) {
+            return query.await;
+        }
+        let job_ids = storage.list_job_ids().await?;
+        let mut jobs = Vec::with_capacity(job_ids.len());
+        for job_id in job_ids {
+            // The job may have been removed since it was listed
+            if let Some(job) = storage.get(job_id).await? {
+                jobs.push(job);
+            }
+        }
+        Ok(self.page(jobs))
+    }
+}
+
+///
+/// Selects and pages the events of an event log, in the order they were logged. The cursor of
+/// a page is the sequence number of its last event.
+#[derive(Clone, Debug)]
+pub struct EventQuery {
+    pub job_id: Option<Uuid>,
+    /// Events logged from, included, ...
+    pub from: Option<DateTime<Utc>>,
+    /// ... to, excluded
+    pub to: Option<DateTime<Utc>>,
+    pub cursor: Option<String>,
+    pub limit: usize,
+}
+
+impl Default for EventQuery {
+    fn default() -> Self {
+        Self {
+            job_id: None,
+            from: None,
+            to: None,
+            cursor: None,
+            limit: DEFAULT_LIMIT,
+        }
+    }
+}
+
+impl EventQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn job(mut self, job_id: Uuid) -> Self {
+        self.job_id = Some(job_id);
+        self
+    }
+
+    pub fn between(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self.to = Some(to);
+        self
+    }
+
+    pub fn after(mut self, cursor: String) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit.max(1);
+        self
+    }
+
+    ///
+    /// The sequence number the events are listed after
+    pub fn after_sequence(&self) -> u64 {
+        self.cursor
+            .as_ref()
+            .and_then(|cursor| cursor.parse().ok())
+            .unwrap_or_default()
+    }
+
+    pub fn matches(&self, event: &StoreEvent) -> bool {
+        let job_id = match &event.event {
+            Some(store_event::Event::JobSaved(job)) => job.id.as_ref(),
+            Some(store_event::Event::JobDeleted(id)) => Some(id),
+            Some(store_event::Event::TicksSet(ticks)) => ticks.job_id.as_ref(),
+            None => None,
+        };
+        event.sequence > self.after_sequence()
+            && self
+                .job_id
+                .is_none_or(|id| job_id.map(Uuid::from) == Some(id))
+            && self
+                .from
+                .is_none_or(|from| event.at >= from.timestamp() as u64)
+            && self.to.is_none_or(|to| event.at < to.timestamp() as u64)
+    }
+
+    ///
+    /// The page of `events`, in the order they were logged, the query selects
+    pub fn page(&self, events: Vec<StoreEvent>) -> Page<StoreEvent> {
+        let mut events: Vec<_> = events.into_iter().filter(|e| self.matches(e)).collect();
+        let more = events.len() > self.limit;
+        events.truncate(self.limit);
+        let next_cursor = events
+            .last()
+            .filter(|_| more)
+            .map(|event| event.sequence.to_string());
+        Page {
+            items: events,
+            next_cursor,
+        }
+    }
+}
@@ -0,0 +1,347 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{JobAndNextTick, JobState, JobStoredData, RetryPolicy};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{JobAndNextTick, JobState, JobStoredData, RetryPolicy};
+use crate::job::{JobId, NotificationId};
+use crate::store::{DataStore, InitStore, MetaDataStorage, NotificationStore};
+use crate::JobSchedulerError;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, JobSchedulerError>> + Send>>;
+
+///
+/// How a `RetryingStore` retries the operations of its store and when it stops trying
+#[derive(Clone, Debug)]
+pub struct StoreRetryConfig {
+    /// The waits between the attempts of an operation, and how many attempts it gets in all
+    pub policy: RetryPolicy,
+    /// The number of operations failing one after the other, all their attempts included, that
+    /// opens the circuit
+    pub failure_threshold: u32,
+    /// How long an open circuit fails the operations at once, before letting one through to
+    /// find out whether the store is back
+    pub open_for: Duration,
+}
+
+impl Default for StoreRetryConfig {
+    fn default() -> Self {
+        Self {
+            policy: RetryPolicy::exponential(Duration::from_millis(100), Duration::from_secs(5), 5)
+                .with_jitter(),
+            failure_threshold: 5,
+            open_for: Duration::from_secs(30),
+        }
+    }
+}
+
+///
+/// What a `RetryingStore` did about a failing operation, named like the method of the store
+#[derive(Clone, Debug)]
+pub enum StoreRetryEvent {
+    /// The `attempt`th attempt of the operation failed with `error`, it is tried again after
+    /// `delay`
+    Retry {
+        operation: &'static str,
+        attempt: u32,
+        delay: Duration,
+        error: JobSchedulerError,
+    },
+    /// The operation failed on all its attempts, the last one with `error`
+    GaveUp {
+        operation: &'static str,
+        attempts: u32,
+        error: JobSchedulerError,
+    },
+    /// The operation failed once too many, the next ones fail at once until the circuit closes
+    CircuitOpened { operation: &'static str },
+    /// The operation let through by the open circuit succeeded, so the store is used again
+    CircuitClosed { operation: &'static str },
+}
+
+///
+/// Called for every retry of a `RetryingStore` and every change of its circuit, e.g. to count
+/// them in metrics
+pub type OnStoreRetry = dyn Fn(StoreRetryEvent) + Send + Sync;
+
+#[derive(Default)]
+struct Circuit {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+///
+/// Wraps a metadata or notification store and retries its failing operations with the backoff
+/// of its `RetryPolicy`, so a transient error of e.g. Postgres or Nats doesn't lose a write like
+/// the update of a job's ticks. Once operations keep failing, the circuit opens and they fail at
+/// once for a while instead of piling up retries against a store that is down.
+pub struct RetryingStore<S> {
+    inner: Arc<tokio::sync::Mutex<S>>,
+    pub config: StoreRetryConfig,
+    circuit: Arc<Mutex<Circuit>>,
+    on_event: Option<Arc<OnStoreRetry>>,
+}
+
+impl<S> Clone for RetryingStore<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            config: self.config.clone(),
+            circuit: self.circuit.clone(),
+            on_event: self.on_event.clone(),
+        }
+    }
+}
+
+impl<S> RetryingStore<S>
+where
+    S: Send + 'static,
+{
+    pub fn wrap(inner: S, config: StoreRetryConfig) -> Self {
+        Self {
+            inner: Arc::new(tokio::sync::Mutex::new(inner)),
+            config,
+            circuit: Arc::new(Mutex::new(Circuit::default())),
+            on_event: None,
+        }
+    }
+
+    ///
+    /// Call `handler` for every retry and change of the circuit
+    pub fn on_event(mut self, handler: Box<OnStoreRetry>) -> Self {
+        self.on_event = Some(Arc::from(handler));
+        self
+    }
+
+    ///
+    /// Run the operation made by `op` until it succeeds or is out of attempts, failing with
+    /// `error` at once when the circuit is open
+    fn retry<T, F>(
+        &self,
+        operation: &'static str,
+        error: JobSchedulerError,
+        op: F,
+    ) -> StoreFuture<T>
+    where
+        T: Send + 'static,
+        F: Fn(&mut S) -> StoreFuture<T> + Send + 'static,
+    {
+        let inner = self.inner.clone();
+        let policy = self.config.policy.clone();
+        let failure_threshold = self.config.failure_threshold;
+        let open_for = self.config.open_for;
+        let circuit = self.circuit.clone();
+        let on_event = self.on_event.clone();
+        let emit = move |event: StoreRetryEvent| {
+            if let Some(on_event) = &on_event {
+                on_event(event);
+            }
+        };
+        Box::pin(async move {
+            let half_open = {
+                let circuit = circuit.lock().unwrap();
+                match circuit.open_until {
+                    Some(open_until) if Instant::now() < open_until => return Err(error),
+                    open_until => open_until.is_some(),
+                }
+            };
+            let mut attempt = 0;
+            let result = loop {
+                attempt += 1;
+                let future = {
+                    let mut inner = inner.lock().await;
+                    op(&mut *inner)
+                };
+                let e = match future.await {
+                    Ok(val) => break Ok(val),
+                    Err(e) => e,
+                };
+                // A store that was down gets a single attempt to show it is back
+                let delay = if half_open {
+                    None
+                } else {
+                    policy.delay(attempt)
+                };
+                match delay {
+                    Some(delay) => {
+                        let delay = policy.jittered(delay);
+                        warn!(operation = operation, attempt = attempt; "Retrying store operation after {:?}: {:?}", delay, e);
+                        emit(StoreRetryEvent::Retry {
+                            operation,
+                            attempt,
+                            delay,
+                            error: e,
+                        });
+                        tokio::time::sleep(delay).await;
+                    }
+                    None => break Err(e),
+                }
+            };
+            match result {
+                Ok(val) => {
+                    let closed = {
+                        let mut circuit = circuit.lock().unwrap();
+                        circuit.failures = 0;
+                        circuit.open_until.take().is_some()
+                    };
+                    if closed {
+                        info!(operation = operation; "Store is back, closing the circuit");
+                        emit(StoreRetryEvent::CircuitClosed { operation });
+                    }
+                    Ok(val)
+                }
+                Err(e) => {
+                    error!(operation = operation, attempts = attempt; "Store operation failed: {:?}", e);
+                    emit(StoreRetryEvent::GaveUp {
+                        operation,
+                        attempts: attempt,
+                        error: e.clone(),
+                    });
+                    let opened = {
+                        let mut circuit = circuit.lock().unwrap();
+                        circuit.failures += 1;
+                        let opened = circuit.failures >= failure_threshold.max(1);
+                        if opened {
+                            circuit.open_until = Some(Instant::now() + open_for);
+                        }
+                        opened
+                    };
+                    if opened {
+                        warn!(operation = operation; "Store keeps failing, opening the circuit for {:?}", open_for);
+                        emit(StoreRetryEvent::CircuitOpened { operation });
+                    }
+                    Err(e)
+                }
+            }
+        })
+    }
+}
+
+impl<S, DATA> DataStore<DATA> for RetryingStore<S>
+where
+    S: DataStore<DATA> + Send + 'static,
+    DATA: Clone + Send + Sync + 'static,
+{
+    fn get(&mut self, id: Uuid) -> StoreFuture<Option<DATA>> {
+        self.retry("get", JobSchedulerError::GetJobData, move |s| s.get(id))
+    }
+
+    fn add_or_update(&mut self, data: DATA) -> StoreFuture<()> {
+        self.retry("add_or_update", JobSchedulerError::CantAdd, move |s| {
+            s.add_or_update(data.clone())
+        })
+    }
+
+    fn delete(&mut self, guid: Uuid) -> StoreFuture<()> {
+        self.retry("delete", JobSchedulerError::CantRemove, move |s| {
+            s.delete(guid)
+        })
+    }
+}
+
+impl<S> InitStore for RetryingStore<S>
+where
+    S: InitStore + Send + 'static,
+{
+    fn init(&mut self) -> StoreFuture<()> {
+        self.retry("init", JobSchedulerError::CantInit, |s| s.init())
+    }
+
+    fn inited(&mut self) -> StoreFuture<bool> {
+        self.retry("inited", JobSchedulerError::CantInit, |s| s.inited())
+    }
+}
+
+impl<S> MetaDataStorage for RetryingStore<S>
+where
+    S: MetaDataStorage + Send + 'static,
+{
+    fn list_next_ticks(&mut self) -> StoreFuture<Vec<JobAndNextTick>> {
+        self.retry(
+            "list_next_ticks",
+            JobSchedulerError::CantListNextTicks,
+            |s| s.list_next_ticks(),
+        )
+    }
+
+    fn set_next_and_last_tick(
+        &mut self,
+        guid: Uuid,
+        next_tick: Option<DateTime<Utc>>,
+        last_tick: Option<DateTime<Utc>>,
+    ) -> StoreFuture<()> {
+        self.retry(
+            "set_next_and_last_tick",
+            JobSchedulerError::UpdateJobData,
+            move |s| s.set_next_and_last_tick(guid, next_tick, last_tick),
+        )
+    }
+
+    fn time_till_next_job(&mut self) -> StoreFuture<Option<Duration>> {
+        self.retry(
+            "time_till_next_job",
+            JobSchedulerError::CouldNotGetTimeUntilNextTick,
+            |s| s.time_till_next_job(),
+        )
+    }
+
+    fn list_job_ids(&mut self) -> StoreFuture<Vec<Uuid>> {
+        self.retry("list_job_ids", JobSchedulerError::CantListGuids, |s| {
+            s.list_job_ids()
+        })
+    }
+
+    fn get_many(&mut self, ids: &[Uuid]) -> StoreFuture<Vec<JobStoredData>> {
+        let ids = ids.to_vec();
+        self.retry("get_many", JobSchedulerError::GetJobData, move |s| {
+            s.get_many(&ids)
+        })
+    }
+}
+
+impl<S> NotificationStore for RetryingStore<S>
+where
+    S: NotificationStore + Send + 'static,
+{
+    fn list_notification_guids_for_job_and_state(
+        &mut self,
+        job: JobId,
+        state: JobState,
+    ) -> StoreFuture<Vec<NotificationId>> {
+        self.retry(
+            "list_notification_guids_for_job_and_state",
+            JobSchedulerError::GetJobData,
+            move |s| s.list_notification_guids_for_job_and_state(job, state),
+        )
+    }
+
+    fn list_notification_guids_for_job_id(&mut self, job_id: Uuid) -> StoreFuture<Vec<Uuid>> {
+        self.retry(
+            "list_notification_guids_for_job_id",
+            JobSchedulerError::GetJobData,
+            move |s| s.list_notification_guids_for_job_id(job_id),
+        )
+    }
+
+    fn delete_notification_for_state(
+        &mut self,
+        notification_id: Uuid,
+        state: JobState,
+    ) -> StoreFuture<bool> {
+        self.retry(
+            "delete_notification_for_state",
+            JobSchedulerError::CantRemove,
+            move |s| s.delete_notification_for_state(notification_id, state),
+        )
+    }
+
+    fn delete_for_job(&mut self, job_id: Uuid) -> StoreFuture<()> {
+        self.retry("delete_for_job", JobSchedulerError::CantRemove, move |s| {
+            s.delete_for_job(job_id)
+        })
+    }
+}
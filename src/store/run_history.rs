@@ -0,0 +1,98 @@
+#[cfg(not(feature = "has_bytes"))]
+use crate::job::job_data::{JobRun, JobState};
+#[cfg(feature = "has_bytes")]
+use crate::job::job_data_prost::{JobRun, JobState};
+use crate::store::export::{self, ExportFormat};
+use crate::store::InitStore;
+use crate::JobSchedulerError;
+use chrono::{DateTime, TimeZone, Utc};
+use std::future::Future;
+use std::io::Write;
+use std::ops::Range;
+use std::pin::Pin;
+use uuid::Uuid;
+
+type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, JobSchedulerError>> + Send>>;
+
+///
+/// Keeps a record of every run of the jobs, when it was scheduled, started and ended, and how
+/// it ended, as an audit trail of the runs
+pub trait RunHistoryStore: InitStore {
+    fn record(&mut self, run: JobRun) -> StoreFuture<()>;
+
+    ///
+    /// The last `limit` runs of a job, the latest first
+    fn history(&mut self, job_id: Uuid, limit: usize) -> StoreFuture<Vec<JobRun>>;
+
+    ///
+    /// The runs of all the jobs that ended in `range`, the earliest first
+    fn runs_between(&mut self, range: Range<DateTime<Utc>>) -> StoreFuture<Vec<JobRun>>;
+
+    ///
+    /// Write the runs of all the jobs that ended in `range` to `writer` in `format`, the earliest
+    /// first, e.g. to load months of runs into an analytics tool. Returns the number of runs
+    /// written.
+    fn export(
+        &mut self,
+        range: Range<DateTime<Utc>>,
+        format: ExportFormat,
+        writer: Box<dyn Write + Send>,
+    ) -> StoreFuture<usize> {
+        let runs = self.runs_between(range);
+        Box::pin(async move {
+            let runs = runs.await?;
+            export::write_runs(&runs, format, writer)?;
+            Ok(runs.len())
+        })
+    }
+}
+
+impl JobRun {
+    pub fn new(
+        job_id: Uuid,
+        scheduled_at: DateTime<Utc>,
+        started_at: DateTime<Utc>,
+        ended_at: DateTime<Utc>,
+        outcome: JobState,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            id: Some(Uuid::new_v4().into()),
+            job_id: Some(job_id.into()),
+            scheduled_at: scheduled_at.timestamp_millis().max(0) as u64,
+            started_at: started_at.timestamp_millis().max(0) as u64,
+            ended_at: ended_at.timestamp_millis().max(0) as u64,
+            outcome: outcome as i32,
+            error,
+        }
+    }
+
+    pub fn job_id(&self) -> Option<Uuid> {
+        self.job_id.as_ref().map(|id| id.into())
+    }
+
+    pub fn scheduled_at_utc(&self) -> Option<DateTime<Utc>> {
+        Utc.timestamp_millis_opt(self.scheduled_at as i64).single()
+    }
+
+    pub fn started_at_utc(&self) -> Option<DateTime<Utc>> {
+        Utc.timestamp_millis_opt(self.started_at as i64).single()
+    }
+
+    pub fn ended_at_utc(&self) -> Option<DateTime<Utc>> {
+        Utc.timestamp_millis_opt(self.ended_at as i64).single()
+    }
+
+    ///
+    /// Whether the run ended in `range`
+    pub fn ended_in(&self, range: &Range<DateTime<Utc>>) -> bool {
+        let ended_at = self.ended_at as i64;
+        ended_at >= range.start.timestamp_millis() && ended_at < range.end.timestamp_millis()
+    }
+
+    ///
+    /// How long the run took, from its start to its end
+    pub fn duration(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.ended_at.saturating_sub(self.started_at))
+    }
+}
@@ -0,0 +1,238 @@
+use crate::job::{fallible_async, JobLocked};
+use crate::job_scheduler::JobsSchedulerLocked;
+use crate::JobSchedulerError;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+///
+/// A schedule given to synthetic jobs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyntheticSchedule {
+    Cron(String),
+    Every(Duration),
+}
+
+///
+/// A configurable load of synthetic jobs, to check how a new deployment of the scheduler and its
+/// store hold up before the real jobs are moved onto it. Each job sleeps for a random time
+/// between `min_duration` and `max_duration` and then fails with a chance of `failure_rate`. The
+/// jobs are tagged with `tag`, so they are told apart from the real ones and removed together.
+///
+/// ```rust,ignore
+/// let workload = SyntheticWorkload::new(1_000)
+///     .schedule(SyntheticSchedule::Every(Duration::from_secs(60)), 9)
+///     .schedule(SyntheticSchedule::Cron("0 */5 * * * *".into()), 1)
+///     .durations(Duration::from_millis(10), Duration::from_secs(2))
+///     .failure_rate(0.01);
+/// workload.register(&sched).await?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct SyntheticWorkload {
+    pub jobs: usize,
+    /// The schedules the jobs are given, each with its weight in the mix
+    pub schedules: Vec<(SyntheticSchedule, u32)>,
+    pub min_duration: Duration,
+    pub max_duration: Duration,
+    /// Chance a run fails, between 0 and 1
+    pub failure_rate: f64,
+    pub tag: String,
+    /// Seed of the schedules, durations and failures, the same seed gives the same workload
+    pub seed: u64,
+}
+
+impl Default for SyntheticWorkload {
+    fn default() -> Self {
+        Self {
+            jobs: 0,
+            schedules: vec![],
+            min_duration: Duration::ZERO,
+            max_duration: Duration::ZERO,
+            failure_rate: 0.0,
+            tag: "synthetic".to_string(),
+            seed: 0x5eed,
+        }
+    }
+}
+
+impl SyntheticWorkload {
+    pub fn new(jobs: usize) -> Self {
+        Self {
+            jobs,
+            ..Default::default()
+        }
+    }
+
+    ///
+    /// Add a schedule to the mix, given to about `weight` in every total weight jobs. Without
+    /// any schedule the jobs are run every minute.
+    pub fn schedule(mut self, schedule: SyntheticSchedule, weight: u32) -> Self {
+        self.schedules.push((schedule, weight));
+        self
+    }
+
+    pub fn durations(mut self, min_duration: Duration, max_duration: Duration) -> Self {
+        self.min_duration = min_duration;
+        self.max_duration = max_duration.max(min_duration);
+        self
+    }
+
+    pub fn failure_rate(mut self, failure_rate: f64) -> Self {
+        self.failure_rate = failure_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = tag.into();
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    ///
+    /// Add the jobs of the workload to `scheduler`, returning their ids
+    pub async fn register(
+        &self,
+        scheduler: &JobsSchedulerLocked,
+    ) -> Result<Vec<Uuid>, JobSchedulerError> {
+        let random = SeededRandom::new(self.seed);
+        let mut job_ids = Vec::with_capacity(self.jobs);
+        for i in 0..self.jobs {
+            let mut job = self.job(random.clone())?;
+            job.set_name(format!("{}-{}", self.tag, i))?;
+            job.set_tags(vec![self.tag.clone()])?;
+            job_ids.push(scheduler.add(job).await?);
+        }
+        Ok(job_ids)
+    }
+
+    ///
+    /// Remove the jobs tagged with the tag of the workload from `scheduler`, returning how many
+    /// were removed
+    pub async fn unregister(
+        &self,
+        scheduler: &JobsSchedulerLocked,
+    ) -> Result<usize, JobSchedulerError> {
+        let jobs = scheduler.scoped(self.tag.clone()).list_jobs().await?;
+        for job in jobs.iter() {
+            scheduler.remove(&job.job_id).await?;
+        }
+        Ok(jobs.len())
+    }
+
+    fn pick_schedule(&self, random: &SeededRandom) -> SyntheticSchedule {
+        let total: u64 = self.schedules.iter().map(|(_, w)| *w as u64).sum();
+        if total == 0 {
+            return SyntheticSchedule::Every(Duration::from_secs(60));
+        }
+        let mut pick = (random.next() * total as f64) as u64;
+        for (schedule, weight) in self.schedules.iter() {
+            if pick < *weight as u64 {
+                return schedule.clone();
+            }
+            pick -= *weight as u64;
+        }
+        self.schedules[self.schedules.len() - 1].0.clone()
+    }
+
+    fn job(&self, random: SeededRandom) -> Result<JobLocked, JobSchedulerError> {
+        let schedule = self.pick_schedule(&random);
+        let min_duration = self.min_duration;
+        let spread = self.max_duration.saturating_sub(self.min_duration);
+        let failure_rate = self.failure_rate;
+        let run = move |_job_id, _scheduler| {
+            let duration = min_duration + spread.mul_f64(random.next());
+            let failed = failure_rate > 0.0 && random.next() < failure_rate;
+            Box::pin(async move {
+                tokio::time::sleep(duration).await;
+                if failed {
+                    Err("Synthetic failure")
+                } else {
+                    Ok(())
+                }
+            }) as _
+        };
+        match schedule {
+            SyntheticSchedule::Cron(schedule) => {
+                JobLocked::new_fallible_async(schedule.as_str(), run)
+            }
+            SyntheticSchedule::Every(every) => {
+                JobLocked::new_repeated_async(every, fallible_async(run))
+            }
+        }
+    }
+}
+
+///
+/// A xorshift64* sequence shared by the jobs of a workload
+#[derive(Clone)]
+struct SeededRandom(Arc<Mutex<u64>>);
+
+impl SeededRandom {
+    fn new(seed: u64) -> Self {
+        Self(Arc::new(Mutex::new(seed.max(1))))
+    }
+
+    fn next(&self) -> f64 {
+        let mut state = self.0.lock().unwrap();
+        let mut x = *state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        *state = x;
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SyntheticSchedule, SyntheticWorkload};
+    use crate::{Job, JobNotification, JobScheduler};
+    use std::time::Duration;
+
+    /// A workload registered next to a real job, run, and removed without the real job
+    #[tokio::test]
+    async fn synthetic_workload() {
+        let mut sched = JobScheduler::new().await.expect("scheduler");
+        let real = Job::new("0 0 0 1 1 *", |_, _| {}).expect("job");
+        sched.add(real).await.expect("add");
+        let workload = SyntheticWorkload::new(6)
+            .schedule(SyntheticSchedule::Every(Duration::from_secs(1)), 1)
+            .schedule(SyntheticSchedule::Cron("0 0 0 1 1 *".into()), 1)
+            .durations(Duration::from_millis(10), Duration::from_millis(50))
+            .failure_rate(1.0);
+        let job_ids = workload.register(&sched).await.expect("register");
+        assert_eq!(job_ids.len(), 6);
+        let jobs = sched.scoped("synthetic").list_jobs().await.expect("list");
+        assert_eq!(jobs.len(), 6);
+        assert!(jobs.iter().any(|job| job.repeated_every == Some(1)));
+        assert!(jobs.iter().any(|job| job.schedule.is_some()));
+
+        let mut events = sched.subscribe_events();
+        sched.start().await.expect("start");
+        let done = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Ok((job_id, JobNotification::Done)) = events.recv().await {
+                    if job_ids.contains(&job_id) {
+                        return job_id;
+                    }
+                }
+            }
+        })
+        .await
+        .expect("a synthetic run");
+        let runs = sched.run_history(&done, 10).await.expect("history");
+        assert!(!runs.is_empty());
+        assert!(runs
+            .iter()
+            .all(|run| run.outcome == JobNotification::Failed as i32
+                && run.duration() >= Duration::from_millis(10)));
+
+        assert_eq!(workload.unregister(&sched).await.expect("unregister"), 6);
+        assert_eq!(sched.list_jobs().await.expect("list").len(), 1);
+        sched.shutdown().await.expect("shutdown");
+    }
+}
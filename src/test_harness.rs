@@ -0,0 +1,169 @@
+//! The life of a scheduler against a metadata and notification store: adding a job, persisting
+//! it, restarting the scheduler on new connections to the same backend, loading the job back,
+//! firing it and shutting down. The tests of the crate run it against its own stores, and it is
+//! exported with the `test_harness` feature so the tests of a custom store can run it too:
+//!
+//! ```rust,ignore
+//! #[tokio::test]
+//! async fn my_store_lifecycle() {
+//!     tokio_cron_scheduler::test_harness::scheduler_lifecycle(|| {
+//!         (Box::new(MyMetadataStore::connect(URL)), Box::new(MyNotificationStore::connect(URL)))
+//!     })
+//!     .await;
+//! }
+//! ```
+//!
+//! The stores are made twice, once for each scheduler, and have to reach the same backend. The
+//! harness panics on the first step that fails, like an assertion.
+//!
+//! With the `test_harness` feature, `postgres_server` and `nats_server` start the backends of
+//! the crate in containers, through a Docker daemon, for stores built on them:
+//!
+//! ```rust,ignore
+//! let (_server, url) = tokio_cron_scheduler::test_harness::postgres_server().await;
+//! std::env::set_var("POSTGRES_URL", url);
+//! ```
+
+use crate::store::{MetaDataStorage, NotificationStore};
+use crate::{Job, JobSchedulerBuilder, SimpleJobCode};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "test_harness")]
+use testcontainers::core::{IntoContainerPort, WaitFor};
+#[cfg(feature = "test_harness")]
+use testcontainers::runners::AsyncRunner;
+#[cfg(feature = "test_harness")]
+use testcontainers::{ContainerAsync, GenericImage, ImageExt};
+use uuid::Uuid;
+
+/// How long the job, repeating every second, gets to fire after the restart
+const FIRE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A server started in a container, stopped and removed once dropped
+#[cfg(feature = "test_harness")]
+pub type Server = ContainerAsync<GenericImage>;
+
+/// A metadata store and a notification store on the same backend
+pub type Stores = (
+    Box<dyn MetaDataStorage + Send + Sync>,
+    Box<dyn NotificationStore + Send + Sync>,
+);
+
+///
+/// Run a scheduler through its lifecycle on the stores made by `stores`, returning the id of
+/// the job it ran. The job is removed at the end, leaving the backend as it was.
+pub async fn scheduler_lifecycle<F>(stores: F) -> Uuid
+where
+    F: Fn() -> Stores,
+{
+    // The code of the jobs lives in the process, so the restarted scheduler shares it
+    let code = SimpleJobCode::default().job_code;
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let (metadata_storage, notification_storage) = stores();
+    let mut sched = JobSchedulerBuilder::default()
+        .metadata_storage(metadata_storage)
+        .notification_storage(notification_storage)
+        .job_code(Box::new(SimpleJobCode {
+            job_code: code.clone(),
+        }))
+        .build()
+        .await
+        .expect("build the scheduler");
+    sched.init().await.expect("init the scheduler");
+    let counter = runs.clone();
+    let job = Job::new_repeated(Duration::from_secs(1), move |_, _| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    })
+    .expect("make the job");
+    let job_id = sched.add(job).await.expect("add the job");
+    let persisted = sched.list_jobs().await.expect("list the jobs");
+    assert!(
+        persisted.iter().any(|j| j.job_id == job_id),
+        "the job was not persisted"
+    );
+    sched.shutdown().await.expect("shut the scheduler down");
+    assert_eq!(runs.load(Ordering::SeqCst), 0, "the job ran before a start");
+
+    let (metadata_storage, notification_storage) = stores();
+    let mut sched = JobSchedulerBuilder::default()
+        .metadata_storage(metadata_storage)
+        .notification_storage(notification_storage)
+        .job_code(Box::new(SimpleJobCode { job_code: code }))
+        .build()
+        .await
+        .expect("build the restarted scheduler");
+    let report = sched.start().await.expect("start the restarted scheduler");
+    assert!(report.jobs_loaded >= 1, "the job was not loaded back");
+    assert!(
+        !report.missing_code.contains(&job_id),
+        "the job was loaded back without its code"
+    );
+
+    let deadline = tokio::time::Instant::now() + FIRE_TIMEOUT;
+    while runs.load(Ordering::SeqCst) == 0 {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "the job loaded back did not fire"
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    sched.remove(&job_id).await.expect("remove the job");
+    sched
+        .shutdown()
+        .await
+        .expect("shut the restarted scheduler down");
+    job_id
+}
+
+///
+/// Start a Postgres server in a container, returning it with its URL in the form `POSTGRES_URL`
+/// takes
+#[cfg(feature = "test_harness")]
+pub async fn postgres_server() -> (Server, String) {
+    let server = GenericImage::new("postgres", "16-alpine")
+        .with_exposed_port(5432.tcp())
+        // Logged once by the server started for the initialization, then by the one serving
+        .with_wait_for(WaitFor::message_on_stdout(
+            "PostgreSQL init process complete; ready for start up.",
+        ))
+        .with_wait_for(WaitFor::message_on_stderr(
+            "database system is ready to accept connections",
+        ))
+        .with_env_var("POSTGRES_PASSWORD", "postgres")
+        .start()
+        .await
+        .expect("start the Postgres container");
+    let host = server.get_host().await.expect("get the Postgres host");
+    let port = server
+        .get_host_port_ipv4(5432)
+        .await
+        .expect("get the Postgres port");
+    let url = format!(
+        "host={} port={} user=postgres password=postgres dbname=postgres",
+        host, port
+    );
+    (server, url)
+}
+
+///
+/// Start a Nats server with JetStream in a container, returning it with its URL in the form
+/// `NATS_HOST` takes
+#[cfg(feature = "test_harness")]
+pub async fn nats_server() -> (Server, String) {
+    let server = GenericImage::new("nats", "2-alpine")
+        .with_exposed_port(4222.tcp())
+        .with_wait_for(WaitFor::message_on_stderr("Server is ready"))
+        .with_cmd(["-js"])
+        .start()
+        .await
+        .expect("start the Nats container");
+    let host = server.get_host().await.expect("get the Nats host");
+    let port = server
+        .get_host_port_ipv4(4222)
+        .await
+        .expect("get the Nats port");
+    (server, format!("nats://{}:{}", host, port))
+}